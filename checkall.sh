@@ -5,6 +5,7 @@ set -euxo pipefail
 # Check all the crates
 cargo check --features=tokio-std --manifest-path=./crates/ergot/Cargo.toml
 cargo check --features=std --manifest-path=./crates/cobs-acc/Cargo.toml
+cargo check --manifest-path=./crates/ergot-cli/Cargo.toml
 
 # Check all the demo workspaces
 cargo check --manifest-path=./demos/shared-icd/Cargo.toml
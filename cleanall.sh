@@ -5,6 +5,7 @@ set -euxo pipefail
 # clean all the crates
 cargo clean --manifest-path=./crates/ergot/Cargo.toml
 cargo clean --manifest-path=./crates/cobs-acc/Cargo.toml
+cargo clean --manifest-path=./crates/ergot-cli/Cargo.toml
 
 # clean all the demo workspaces
 cargo clean --manifest-path=./demos/shared-icd/Cargo.toml
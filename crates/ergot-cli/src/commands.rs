@@ -0,0 +1,277 @@
+//! The subcommands, for any kind of [`Stack`]
+
+use std::{
+    fs::File,
+    io::BufReader,
+    path::Path,
+    pin::pin,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use ergot::{
+    Address, DEFAULT_TTL, FrameKind, Header, Priority,
+    interface_manager::{
+        Profile,
+        capture::pcapng::{Dissect, PcapngReader},
+        utils::std::{StdQueue, new_std_queue},
+    },
+    net_stack::discovery::TracerouteHop,
+    socket::{Attributes, borrow::Socket},
+    well_known::{ErgotPingEndpoint, NameRequirement, SocketQuery},
+};
+use log::warn;
+use postcard_schema::schema::owned::OwnedNamedType;
+use serde_json::Value;
+use tokio::time::{sleep, timeout};
+
+use crate::{
+    connect::{MTU, Stack},
+    schema,
+};
+
+/// Bytes buffered by the sockets receiving dynamically typed messages
+const RX_BUFFER_SIZE: usize = 8 * MTU as usize;
+
+pub async fn ping<P: Profile>(
+    stack: &Stack<P>,
+    dst: Address,
+    count: u32,
+    wait: Duration,
+) -> Result<()> {
+    let dst = Address { port_id: 0, ..dst };
+    for seq in 0..count {
+        if seq != 0 {
+            sleep(Duration::from_secs(1)).await;
+        }
+        let start = Instant::now();
+        let req = stack
+            .endpoints()
+            .request::<ErgotPingEndpoint>(dst, &seq, None);
+        match timeout(wait, req).await {
+            Ok(Ok(val)) if val == seq => {
+                println!("{dst}: seq={seq} time={:?}", start.elapsed());
+            }
+            Ok(Ok(val)) => println!("{dst}: seq={seq} unexpected reply {val}"),
+            Ok(Err(e)) => println!("{dst}: seq={seq} error {e:?}"),
+            Err(_) => println!("{dst}: seq={seq} timeout"),
+        }
+    }
+    Ok(())
+}
+
+/// Print the routers on the path to `dst`, one per hop
+pub async fn traceroute<P: Profile>(stack: &Stack<P>, dst: Address, wait: Duration) -> Result<()> {
+    let hops = stack
+        .discovery()
+        .traceroute(dst, wait)
+        .await
+        .map_err(|e| anyhow!("Traceroute failed: {e:?}"))?;
+    for (ttl, hop) in (1..).zip(hops) {
+        match hop {
+            TracerouteHop::Router(addr) => println!("{ttl:2} {addr}"),
+            TracerouteHop::Destination(addr) => println!("{ttl:2} {addr} (destination)"),
+            TracerouteHop::Error { addr, err } => println!("{ttl:2} {addr} error {err:?}"),
+            TracerouteHop::Timeout => println!("{ttl:2} *"),
+        }
+    }
+    Ok(())
+}
+
+/// List the devices answering discovery, with their sockets if `sockets`
+pub async fn discover<P: Profile>(stack: &Stack<P>, sockets: bool, wait: Duration) -> Result<()> {
+    let devices = stack.discovery().discover(64, wait).await;
+    for dev in devices {
+        let addr = Address {
+            port_id: 0,
+            ..dev.addr
+        };
+        println!(
+            "{addr}: {} - {} (id {:016X})",
+            dev.info.name.as_deref().unwrap_or("?"),
+            dev.info.description.as_deref().unwrap_or("?"),
+            dev.info.unique_id,
+        );
+        if !sockets {
+            continue;
+        }
+        for ds in schema::collect(stack, Some(addr), wait).await {
+            let schema = ds.schema;
+            let ty = schema
+                .ty
+                .as_ref()
+                .map(OwnedNamedType::to_pseudocode)
+                .unwrap_or_else(|| "?".into());
+            println!(
+                "  port {:3} {} {}: {ty}",
+                schema.port,
+                kind_name(schema.kind),
+                schema.path,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// List the sockets for `path` of all devices answering socket queries,
+/// using the key reported in the schemas of the devices
+pub async fn discover_path<P: Profile>(stack: &Stack<P>, path: &str, wait: Duration) -> Result<()> {
+    let mut keys = vec![];
+    for ds in schema::collect(stack, None, wait).await {
+        let s = ds.schema;
+        if s.path == path && s.kind != FrameKind::ENDPOINT_RESP && !keys.contains(&(s.key, s.kind))
+        {
+            keys.push((s.key, s.kind));
+        }
+    }
+    if keys.is_empty() {
+        bail!("No device reported a schema for {path}");
+    }
+
+    for (key, kind) in keys {
+        let query = SocketQuery {
+            key: key.0,
+            nash_req: NameRequirement::Any,
+            frame_kind: kind,
+            // Topic receivers are usually attached for broadcast messages
+            broadcast: kind == FrameKind::TOPIC_MSG,
+        };
+        for sock in stack.discovery().discover_sockets(64, wait, &query).await {
+            println!("{} {} {path}", sock.address, kind_name(kind));
+        }
+    }
+    Ok(())
+}
+
+/// Print the messages of the topic `path`, decoded with its schema
+pub async fn subscribe<P: Profile>(
+    stack: &Stack<P>,
+    device: Option<Address>,
+    path: &str,
+    wait: Duration,
+) -> Result<()> {
+    let ds = schema::find(stack, device, path, FrameKind::TOPIC_MSG, wait).await?;
+    let ty = ds
+        .schema
+        .ty
+        .with_context(|| format!("{}: the schema of {path} is too large", ds.device))?;
+
+    let socket = Socket::<StdQueue, (), Stack<P>>::new(
+        stack.clone(),
+        ds.schema.key,
+        Attributes {
+            kind: FrameKind::TOPIC_MSG,
+            discoverable: true,
+        },
+        new_std_queue(RX_BUFFER_SIZE),
+        MTU,
+        None,
+    );
+    let socket = pin!(socket);
+    let mut hdl = socket.attach_broadcast();
+    loop {
+        let msg = hdl.recv().await;
+        let Some(body) = msg.raw_body() else {
+            continue;
+        };
+        match postcard_dyn::from_slice_dyn(&ty, body) {
+            Ok(val) => println!("{}: {val}", msg.hdr.src),
+            Err(e) => warn!("{}: failed to decode message: {e:?}", msg.hdr.src),
+        }
+    }
+}
+
+/// Send the `request` to the endpoint `path`, and print the response, using
+/// the schemas of the endpoint
+pub async fn call<P: Profile>(
+    stack: &Stack<P>,
+    device: Option<Address>,
+    path: &str,
+    request: &str,
+    wait: Duration,
+) -> Result<()> {
+    let (req, resp) = schema::find_endpoint(stack, device, path, wait).await?;
+    let (Some(req_ty), Some(resp_ty)) = (&req.schema.ty, &resp.ty) else {
+        bail!("{}: the schemas of {path} are too large", req.device);
+    };
+    let request: Value = serde_json::from_str(request).context("The request is not valid JSON")?;
+    let body = postcard_dyn::to_stdvec_dyn(req_ty, &request)
+        .map_err(|e| anyhow!("The request does not match {req_ty}: {e:?}"))?;
+
+    // Like `Endpoints::request()`, with a socket for the response key
+    let socket = Socket::<StdQueue, (), Stack<P>>::new(
+        stack.clone(),
+        resp.key,
+        Attributes {
+            kind: FrameKind::ENDPOINT_RESP,
+            discoverable: false,
+        },
+        new_std_queue(RX_BUFFER_SIZE),
+        MTU,
+        None,
+    );
+    let socket = pin!(socket);
+    let mut hdl = socket.attach();
+    let seq_no = stack.next_seq_no();
+    let hdr = Header {
+        src: Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: hdl.port(),
+        },
+        dst: Address {
+            port_id: req.schema.port,
+            ..req.device
+        },
+        any_all: None,
+        seq_no: Some(seq_no),
+        kind: FrameKind::ENDPOINT_REQ,
+        ttl: DEFAULT_TTL,
+        prio: Priority::NORMAL,
+    };
+    stack
+        .send_serialized(&hdr, &body)
+        .map_err(|e| anyhow!("Failed to send the request: {e:?}"))?;
+
+    let recv = async {
+        loop {
+            let msg = hdl.recv().await;
+            if msg.hdr.seq_no == seq_no {
+                return msg;
+            }
+        }
+    };
+    let msg = timeout(wait, recv).await.context("No response")?;
+    if let Some(Err(e)) = msg.try_access() {
+        bail!("{}: error response {:?}", e.hdr.src, e.t);
+    }
+    let body = msg.raw_body().context("Empty response")?;
+    let val = postcard_dyn::from_slice_dyn(resp_ty, body)
+        .map_err(|e| anyhow!("The response does not match {resp_ty}: {e:?}"))?;
+    println!("{}", serde_json::to_string_pretty(&val)?);
+    Ok(())
+}
+
+/// Print the log messages sent on the network
+pub async fn logs<P: Profile>(stack: &Stack<P>) -> Result<()> {
+    stack.services().default_stdout_log_handler(64).await
+}
+
+/// Print the frames of the pcapng capture `file`
+pub fn dump(file: &Path) -> Result<()> {
+    let input = File::open(file).with_context(|| format!("Failed to open {}", file.display()))?;
+    for packet in PcapngReader::new(BufReader::new(input)) {
+        let packet = packet.with_context(|| format!("Failed to read {}", file.display()))?;
+        println!("{}", Dissect(&packet));
+    }
+    Ok(())
+}
+
+fn kind_name(kind: FrameKind) -> &'static str {
+    match kind {
+        FrameKind::TOPIC_MSG => "topic",
+        FrameKind::ENDPOINT_REQ => "request",
+        FrameKind::ENDPOINT_RESP => "response",
+        _ => "other",
+    }
+}
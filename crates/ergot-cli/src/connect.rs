@@ -0,0 +1,110 @@
+//! Connecting to an ergot network, with the toolkits of each transport
+//!
+//! Over TCP and UDP, the tool is an edge device of a router. Over serial and
+//! USB, it is the router of a single device, as with `defmt-serial-host`.
+
+use std::{collections::HashSet, time::Duration};
+
+use anyhow::{Context, Result, anyhow, bail};
+use ergot::{
+    Address,
+    interface_manager::{Profile, profiles::direct_edge::EDGE_NODE_ID},
+    net_stack::ArcNetStack,
+    toolkits::{nusb_v0_1, tokio_serial_v5, tokio_tcp, tokio_udp},
+    well_known::ErgotPingEndpoint,
+};
+use log::info;
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use tokio::{
+    net::{TcpStream, UdpSocket},
+    time::timeout,
+};
+
+/// The largest ergot packet sent or received
+pub const MTU: u16 = 1024;
+const OUT_BUFFER_SIZE: usize = 4096;
+
+/// The stacks of all toolkits, with their own profile `P`
+pub type Stack<P> = ArcNetStack<CriticalSectionRawMutex, P>;
+
+pub async fn tcp(addr: &str) -> Result<tokio_tcp::EdgeStack> {
+    let queue = tokio_tcp::new_std_queue(OUT_BUFFER_SIZE);
+    let stack = tokio_tcp::new_target_stack(&queue, MTU);
+    let socket = TcpStream::connect(addr)
+        .await
+        .with_context(|| format!("Failed to connect to {addr}"))?;
+    tokio_tcp::register_edge_interface(&stack, socket, &queue)
+        .await
+        .map_err(|e| anyhow!("Failed to register the TCP interface: {e:?}"))?;
+    Ok(stack)
+}
+
+pub async fn udp(addr: &str) -> Result<tokio_udp::EdgeStack> {
+    let queue = tokio_udp::new_std_queue(OUT_BUFFER_SIZE);
+    let stack = tokio_udp::new_target_stack(&queue, MTU);
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .await
+        .context("Failed to bind a UDP socket")?;
+    socket
+        .connect(addr)
+        .await
+        .with_context(|| format!("Failed to connect to {addr}"))?;
+    tokio_udp::register_edge_target_interface(&stack, socket, &queue, None, None)
+        .await
+        .map_err(|e| anyhow!("Failed to register the UDP interface: {e:?}"))?;
+    Ok(stack)
+}
+
+/// Route to the device on the serial `port`, returning its address
+pub async fn serial(port: &str, baud: u32) -> Result<(tokio_serial_v5::RouterStack, Address)> {
+    let stack = tokio_serial_v5::RouterStack::new();
+    tokio_serial_v5::register_router_interface(&stack, port, baud, MTU, OUT_BUFFER_SIZE)
+        .await
+        .map_err(|e| anyhow!("Failed to open {port}: {e:?}"))?;
+    let device = device_address(&stack.manage_profile(|im| im.get_nets()))?;
+    ping_until_reachable(&stack, device).await?;
+    Ok((stack, device))
+}
+
+/// Route to the first ergot USB device found, returning its address
+pub async fn nusb() -> Result<(nusb_v0_1::RouterStack, Address)> {
+    let stack = nusb_v0_1::RouterStack::new();
+    let Some(dev) = nusb_v0_1::find_new_devices(&HashSet::new())
+        .await
+        .into_iter()
+        .next()
+    else {
+        bail!("No ergot USB device found");
+    };
+    info!("Found {:?}", dev.info);
+    nusb_v0_1::register_router_interface(&stack, dev, MTU, OUT_BUFFER_SIZE)
+        .await
+        .map_err(|e| anyhow!("Failed to register the USB device: {e:?}"))?;
+    let device = device_address(&stack.manage_profile(|im| im.get_nets()))?;
+    ping_until_reachable(&stack, device).await?;
+    Ok((stack, device))
+}
+
+fn device_address(nets: &[u16]) -> Result<Address> {
+    let &[network_id] = nets else {
+        bail!("Expected a single network, found {nets:?}");
+    };
+    Ok(Address {
+        network_id,
+        node_id: EDGE_NODE_ID,
+        port_id: 0,
+    })
+}
+
+/// Ping the device, so that it learns the network it was assigned
+async fn ping_until_reachable<P: Profile>(stack: &Stack<P>, device: Address) -> Result<()> {
+    for i in 0..10u32 {
+        let req = stack
+            .endpoints()
+            .request::<ErgotPingEndpoint>(device, &i, None);
+        if let Ok(Ok(_)) = timeout(Duration::from_millis(200), req).await {
+            return Ok(());
+        }
+    }
+    bail!("The device at {device} does not answer pings")
+}
@@ -0,0 +1,194 @@
+//! A command-line tool for debugging ergot networks
+//!
+//! Topic and endpoint messages are encoded and decoded as JSON, using the
+//! schemas reported by devices running `Services::schema_handler()`, so no
+//! ICD crate is needed.
+//!
+//! Usage:
+//!   ergot --tcp 127.0.0.1:2025 discover --sockets
+//!   ergot --tcp 127.0.0.1:2025 traceroute 3.2
+//!   ergot --serial /dev/ttyACM0 subscribe topic/yeet
+//!   ergot --nusb call example/led '{"on": true}'
+//!   ergot dump ergot.pcapng
+
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::{Context, Result, bail};
+use clap::{ArgGroup, Parser, Subcommand};
+use ergot::{Address, interface_manager::Profile};
+
+use crate::connect::Stack;
+
+mod commands;
+mod connect;
+mod schema;
+
+#[derive(Parser, Debug)]
+#[command(name = "ergot", version)]
+#[command(about = "Talk to the devices of an ergot network")]
+#[command(group(ArgGroup::new("link").args(["tcp", "udp", "serial", "nusb"])))]
+struct Args {
+    /// Connect to an ergot router over TCP, such as `127.0.0.1:2025`
+    #[arg(long)]
+    tcp: Option<String>,
+
+    /// Connect to an ergot router over UDP
+    #[arg(long)]
+    udp: Option<String>,
+
+    /// Connect to a device on a serial port, such as `/dev/ttyACM0`
+    #[arg(long)]
+    serial: Option<String>,
+
+    /// Baud rate of the serial port
+    #[arg(long, default_value = "115200")]
+    baud: u32,
+
+    /// Connect to the first ergot USB device found
+    #[arg(long)]
+    nusb: bool,
+
+    /// How long to wait for responses, in milliseconds
+    #[arg(long, default_value = "1000")]
+    timeout: u64,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Ping a device
+    Ping {
+        /// The device, as `net.node`. Defaults to the serial or USB device
+        #[arg(value_parser = parse_address)]
+        address: Option<Address>,
+
+        /// Number of pings
+        #[arg(short, long, default_value = "4")]
+        count: u32,
+    },
+    /// List the routers on the path to a device
+    Traceroute {
+        /// The device, as `net.node`. Defaults to the serial or USB device
+        #[arg(value_parser = parse_address)]
+        address: Option<Address>,
+    },
+    /// List the devices of the network
+    Discover {
+        /// Also list the topics and endpoints of each device
+        #[arg(long)]
+        sockets: bool,
+
+        /// Only list the sockets for this path, on all devices
+        #[arg(long, conflicts_with = "sockets")]
+        path: Option<String>,
+    },
+    /// Print the messages of a topic as JSON
+    Subscribe {
+        /// The path of the topic
+        path: String,
+
+        /// Only look for the schema on this device, as `net.node`
+        #[arg(long, value_parser = parse_address)]
+        device: Option<Address>,
+    },
+    /// Send a request to an endpoint, and print the response as JSON
+    Call {
+        /// The path of the endpoint
+        path: String,
+
+        /// The request, as JSON
+        request: String,
+
+        /// Only call the endpoint on this device, as `net.node`
+        #[arg(long, value_parser = parse_address)]
+        device: Option<Address>,
+    },
+    /// Print the log messages sent on the network
+    Logs,
+    /// Print the frames of a pcapng capture, no link is needed
+    Dump {
+        /// The capture, as written by `PcapngWriter`
+        file: PathBuf,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+    let args = Args::parse();
+
+    if let Command::Dump { file } = &args.command {
+        return commands::dump(file);
+    }
+
+    if let Some(addr) = &args.tcp {
+        let stack = connect::tcp(addr).await?;
+        run(&stack, None, args).await
+    } else if let Some(addr) = &args.udp {
+        let stack = connect::udp(addr).await?;
+        run(&stack, None, args).await
+    } else if let Some(port) = &args.serial {
+        let (stack, device) = connect::serial(port, args.baud).await?;
+        run(&stack, Some(device), args).await
+    } else if args.nusb {
+        let (stack, device) = connect::nusb().await?;
+        run(&stack, Some(device), args).await
+    } else {
+        bail!("One of --tcp, --udp, --serial or --nusb is needed")
+    }
+}
+
+/// Run the command, `device` being the device at the other end of the link,
+/// if it is known
+async fn run<P: Profile>(stack: &Stack<P>, device: Option<Address>, args: Args) -> Result<()> {
+    let wait = Duration::from_millis(args.timeout);
+    match args.command {
+        Command::Ping { address, count } => {
+            let dst = address
+                .or(device)
+                .context("An address is needed to ping over TCP or UDP")?;
+            commands::ping(stack, dst, count, wait).await
+        }
+        Command::Traceroute { address } => {
+            let dst = address
+                .or(device)
+                .context("An address is needed to traceroute over TCP or UDP")?;
+            commands::traceroute(stack, dst, wait).await
+        }
+        Command::Discover {
+            sockets: _,
+            path: Some(path),
+        } => commands::discover_path(stack, &path, wait).await,
+        Command::Discover {
+            sockets,
+            path: None,
+        } => commands::discover(stack, sockets, wait).await,
+        Command::Subscribe { path, device: dev } => {
+            commands::subscribe(stack, dev.or(device), &path, wait).await
+        }
+        Command::Call {
+            path,
+            request,
+            device: dev,
+        } => commands::call(stack, dev.or(device), &path, &request, wait).await,
+        Command::Logs => commands::logs(stack).await,
+        Command::Dump { .. } => unreachable!("dumping needs no link"),
+    }
+}
+
+/// Parse `net.node` or `net.node:port`
+fn parse_address(s: &str) -> Result<Address, String> {
+    let err = || format!("expected `net.node` or `net.node:port`, got `{s}`");
+    let (net_node, port) = match s.split_once(':') {
+        Some((net_node, port)) => (net_node, port.parse().map_err(|_| err())?),
+        None => (s, 0),
+    };
+    let (net, node) = net_node.split_once('.').ok_or_else(err)?;
+    Ok(Address {
+        network_id: net.parse().map_err(|_| err())?,
+        node_id: node.parse().map_err(|_| err())?,
+        port_id: port,
+    })
+}
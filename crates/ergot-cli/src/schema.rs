@@ -0,0 +1,95 @@
+//! Finding topics and endpoints by path, from the schemas reported by devices
+//! running `Services::schema_handler()`
+
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use ergot::{
+    Address, FrameKind, interface_manager::Profile, net_stack::ReqRespError,
+    well_known::SocketSchemaRxOwned,
+};
+use log::warn;
+
+use crate::connect::Stack;
+
+/// A socket of a device, and the schema of one of its message types
+pub struct DeviceSchema {
+    pub device: Address,
+    pub schema: SocketSchemaRxOwned,
+}
+
+/// The schemas of `device`, or of all devices answering discovery
+pub async fn collect<P: Profile>(
+    stack: &Stack<P>,
+    device: Option<Address>,
+    wait: Duration,
+) -> Vec<DeviceSchema> {
+    let devices = match device {
+        Some(device) => vec![device],
+        None => stack
+            .discovery()
+            .discover(64, wait)
+            .await
+            .into_iter()
+            .map(|rec| rec.addr)
+            .collect(),
+    };
+
+    let mut out = vec![];
+    for device in devices {
+        match stack.discovery().discover_schemas(device, wait).await {
+            Ok(schemas) => out.extend(
+                schemas
+                    .into_iter()
+                    .map(|schema| DeviceSchema { device, schema }),
+            ),
+            Err(ReqRespError::Timeout) => warn!("{device}: schema request timed out"),
+            Err(e) => warn!("{device}: schema request failed: {e:?}"),
+        }
+    }
+    out
+}
+
+/// The first socket for `path` with frames of `kind`
+pub async fn find<P: Profile>(
+    stack: &Stack<P>,
+    device: Option<Address>,
+    path: &str,
+    kind: FrameKind,
+    wait: Duration,
+) -> Result<DeviceSchema> {
+    let found = collect(stack, device, wait)
+        .await
+        .into_iter()
+        .find(|ds| ds.schema.path == path && ds.schema.kind == kind);
+    match found {
+        Some(ds) => Ok(ds),
+        None => bail!("No device reported a schema for {path}"),
+    }
+}
+
+/// The request and response schemas of the first endpoint server for `path`
+pub async fn find_endpoint<P: Profile>(
+    stack: &Stack<P>,
+    device: Option<Address>,
+    path: &str,
+    wait: Duration,
+) -> Result<(DeviceSchema, SocketSchemaRxOwned)> {
+    let mut schemas = collect(stack, device, wait).await;
+    let Some(pos) = schemas
+        .iter()
+        .position(|ds| ds.schema.path == path && ds.schema.kind == FrameKind::ENDPOINT_REQ)
+    else {
+        bail!("No device reported a schema for {path}");
+    };
+    let req = schemas.swap_remove(pos);
+    // The response is listed by the same socket
+    let Some(resp) = schemas.into_iter().find(|ds| {
+        ds.device == req.device
+            && ds.schema.port == req.schema.port
+            && ds.schema.kind == FrameKind::ENDPOINT_RESP
+    }) else {
+        bail!("{}: no response schema for {path}", req.device);
+    };
+    Ok((req, resp.schema))
+}
@@ -84,7 +84,13 @@
 //! when possible. Addresses are only encoded as larger than 4 bytes when
 //! addressing a network ID >= 4096.
 //!
+//! Links with a tiny MTU may instead use the
+//! [`HeaderEncoding::Compact`] header encoding, which encodes each part of
+//! the source and destination addresses separately, and omits the destination
+//! Network ID when it is the same as the source Network ID.
+//!
 //! [`NetStack`]: crate::NetStack
+//! [`HeaderEncoding::Compact`]: crate::wire_frames::HeaderEncoding::Compact
 
 use serde::{Deserialize, Serialize};
 
@@ -33,11 +33,21 @@
 //!
 //! Most **users** will never have to think about these details, only people writing or debugging a particular kind of socket.
 //!
+//! With the `signing` feature, a socket may also be given a trust store of signers, with `with_trust_store`. Such a socket only accepts messages from other devices that were sent signed by one of those signers, such as with `Topics::broadcast_signed`, even if they crossed untrusted routers on the way, and rejects copies of messages it already received. See the `signing` module.
+//!
 //! If sockets are receive only, you may wonder, "how do I send messages", or "what about sockets kinds that are inherently bidirectional?". Sending a message is done through the netstack, so if your socket is providing a request/response or RPC (remote procedure call) type interface, the "server side" of the socket may provide helper methods that accept an incoming message, and then send any generated replies back to the source of the message.
 //!
-//! Similarly, the Netstack may have helper methods for sending certain types of messages to a socket, either in a "fire and forget" manner (for broadcast type communication), or a method that sends a request, and awaits a response, by opening a one-shot response socket that can receive the expected response.
+//! Similarly, the Netstack may have helper methods for sending certain types of messages to a socket, either in a "fire and forget" manner (for broadcast type communication), or a method that sends a request, and awaits a response, by opening a one-shot response socket that can receive the expected response. A request may also be broadcast to every server of an endpoint with `Endpoints::request_all`, which collects responses until a timeout.
+//!
+//! To receive a topic from a device without it being broadcast to the whole network, a host may ask the device's `Publisher` for that topic to send it to one of its unicast receivers, with `Topics::remote_subscribe`. Each publish is then unicast to the live subscribers only. Subscriptions are leased with a refresh token, in the same way as seed-assigned net_ids, so subscribers that go away are eventually dropped. See the `net_stack::publisher` module.
+//!
+//! A topic message only reaches the sockets that exist when it is sent. To let late joiners catch up, a device may keep the last message of each topic in a `RetainedStore`, publishing with `Topics::broadcast_retained`. Other devices can then ask for it with `Topics::request_retained`, served by `Services::retained_handler`, and a `Publisher` served with `serve_with_retained` sends it to each new subscriber. Storage is bounded with `StaticRetained` for no_std, or heap-backed with `HeapRetained` on std. See the `net_stack::retained` module.
+//!
+//! For post-mortem debugging, a device or router may also keep the last messages of selected topics, with the time they were received, in a `TopicHistory`. Each topic is recorded by `Services::topic_history_recorder` as serialized bytes, and other devices query the history by sequence number or time range with `Topics::history`, served by `Services::topic_history_handler`. See the `net_stack::history` module.
 //!
-//! As of today (2025-08-11), there are no socket kinds that automatically handle things like retries or timeouts automatically. This means that you will typically need to handle this as appropriate in your application. In the future, there may be socket kinds that do handle this, storing messages for resending, or automatically detecting timeouts. If you are interested in this functionality, please [start a discussion] on how we can make this happen!
+//! Topic receivers and endpoint servers also record the path and schema of their message types when they are attached. A device running `Services::schema_handler` lists them to anyone asking with `Discovery::discover_schemas`, so generic tools can decode its traffic without depending on the crate defining its topics and endpoints.
+//!
+//! Endpoint requests can be bounded in time: the `Endpoints` helpers offer deadlines (`request_with_deadline`) and a configurable `RetryPolicy` (`request_with_retry`, or `EndpointClient::with_deadline` and `with_retry`), which return `ReqRespError::Timeout` when no response arrives in time. The other socket kinds, such as topics, still don't retry or time out on their own, so you will need to handle this as appropriate in your application. In the future, there may be socket kinds that do, storing messages for resending, or automatically detecting timeouts. If you are interested in this functionality, please [start a discussion] on how we can make this happen!
 //!
 //! ## Addresses
 //!
@@ -59,6 +69,8 @@
 //!
 //! On a point-to-point link the two Node IDs are fixed (the controller side is `1`, the target side is `2`). On a **bus-style segment**, where many devices share one Network ID, each device instead **claims** a unique Node ID at runtime from the segment's router: it starts "link-local" (Network ID `0`), proposes a candidate Node ID, and the router grants and leases it (or reports a conflict, so the device tries another). See the "Shared bus segment" section of the [Feature Overview](super::_03_feature_overview) for more.
 //!
+//! By default, routers grant Node IDs, and seed routers grant Network IDs, to any device that asks. A router may instead be given an **admission policy** with `Router::with_admission_policy`, such as an allow-list of device unique ids, or (with the `signing` feature) a challenge that can only be answered with a shared secret. Devices present their credentials with `with_identity`. See the `interface_manager::admission` module.
+//!
 //! A single device may have multiple Network ID + Node ID pairs, one for each external interface. This may occur if a device is connected to both a USB port and an RS-485 bus, and is responsible for bridging the two network segments together.
 //!
 //! Finally, each socket is assigned a Port ID. The Netstack is responsible for assigning port IDs when a socket is opened. Generally, these port IDs will be unique, with the exception of broadcast ports, which may all share the port ID of 255. A single device may have up to 254 unique port IDs, and an unlimited number of broadcast ports.
@@ -83,6 +95,13 @@
 //! * A sequence number - a rotating 16-bit number that can be used for correlating requests and responses
 //! * A Frame Kind - an 8-bit number that describes the kind of frame or the kind of socket this frame is for
 //! * A TTL - an 8-bit number that is decremented at each hop as a message is routed
+//! * A Priority - an 8-bit number, interfaces with several outgoing queues send frames with a higher priority first
+//!
+//! The Common Frame Header is serialized with postcard by default. Interfaces
+//! with a tiny MTU may use a bit-packed "compact" encoding of the same fields
+//! instead, see [`HeaderEncoding`](crate::wire_frames::HeaderEncoding). Both
+//! ends of a link must use the same encoding, routers translate between the
+//! encodings of their interfaces.
 //!
 //! The optional Any/All Frame Appendix contains the following information:
 //!
@@ -102,7 +121,7 @@
 //!
 //! Currently, Ergot does NOT mandate a minimum or maximum "transmission unit", e.g. the min/max size of a frame.
 //!
-//! Ergot also currently does not handle fragmentation, or splitting a frame to fit within a maximum transmission unit of an interface.
+//! Frames are not split to fit within the maximum transmission unit of an interface automatically. Instead, a sender may use [`NetStack::send_ty_fragmented()`](crate::NetStack::send_ty_fragmented) to send a large message as a series of `FRAGMENT` frames, each carrying a fragment header (message id, offset, and a "more fragments" flag). Routers forward fragments like any other frame, and the destination Netstack reassembles them in a bounded number of reassembly buffers, evicting messages that are not completed in time.
 //!
 //! Ergot also currently does not mandate any kind of message integrity check, e.g. a CRC or other checksum.
 //!
@@ -135,6 +154,20 @@
 //!   unroutable traffic to a parent router). Works on both `std` and `no_std`.
 //!   The type aliases `DirectRouter` and `NoStdRouter` provide convenient defaults.
 //!
+//! A Router or DirectEdge may also be given a **packet filter** with `with_packet_filter`, which decides which frames may be received on, or sent out of, each interface, for example to keep an untrusted upstream from calling anything but a telemetry endpoint. The `RuleTable` filter matches frames by direction, interface, source and destination network, frame kind, and endpoint or topic key, and works on `no_std`. Denied unicast frames are answered with `ProtocolError::IseFiltered`. See the `interface_manager::filter` module.
+//!
+//! To keep a single misbehaving device from flooding the network, a Router may be given a **rate limiter** with `with_rate_limiter`. The `TokenBuckets` limiter limits the frames received on each interface, and sent by each source device, with separate rates per frame kind. Frames over the limit are dropped and counted, without an error response. See the `interface_manager::rate_limit` module.
+//!
+//! To see what actually crossed each interface, like with `tcpdump`, a Router or DirectEdge may be given a **capture tap** with `with_capture`, which records every frame sent or received, with the interface and direction. On `std`, the `PcapngWriter` tap writes them to a pcapng file, which `PcapngReader` and `Dissect` (or `ergot dump`) print again, one decoded header per line. See the `interface_manager::capture` module.
+//!
+//! To find out where frames are dropped, each interface counts the frames and bytes it sends and receives, and the frames it drops because it was full, because they were too big for its MTU, or, on a router, because their TTL expired or they would have looped back out of the interface they came from. Each socket counts the messages delivered to it, and those it dropped because it was full, or because they couldn't be deserialized or had an unexpected type. The counters are atomics, so they are cheap enough to always be on. They are read locally with `NetStack::interface_stats()` and `NetStack::socket_stats()`, or remotely with `Discovery::discover_stats()`, from a device running `Services::stats_handler()`. See the `stats` module.
+//!
+//! A remote router may be inspected without attaching a probe: `Services::router_handler()` answers requests for its interfaces (with their ident, net_id and state), the net_ids it assigned or was delegated with their remaining lease time, the node_ids claimed on its buses, the tombstones of expired leases, and the state of its upstream interface. `Discovery::discover_router()` collects all of them.
+//!
+//! When a router drops a frame because its TTL expired, it answers the sender with an `IseTtlExpired` error sent from its own address on the interface the frame came from. `Discovery::traceroute()` uses this to map the path to a device: it pings the device with a TTL of 1, 2, 3, and so on, and collects the address of the router answering each probe, until the device itself answers.
+//!
+//! Topic broadcasts are normally flooded to every interface of every router. If every device runs `Services::subscription_advertiser`, each device periodically tells its direct neighbors which topics it (and, for a router, the devices behind it) subscribes to, and routers only forward a topic broadcast to the interfaces where that topic was advertised. Interfaces that have not advertised yet, or whose advertisement expired, still receive every broadcast. See the `interface_manager::subscriptions` module.
+//!
 //! ## Interfaces
 //!
 //! **Interfaces** describe how a device communicates with a given network segment. Profiles are responsible for managing interfaces, and these concepts are separate to allow different kinds of profiles to reuse the same code for sending and receiving messages, separately from how they handle routing.
@@ -154,3 +187,13 @@
 //! and adding a new profile automatically works with all existing transports.
 //!
 //! One or both of these worker tasks will also typically be responsible for tracking the "link state" of the interface, for example reporting to the Profile if a USB cable is disconnected, so frames will no longer be routable to that interface.
+//!
+//! When a link comes up, both ends may exchange a "link hello", to agree on a
+//! protocol version before any frames are processed. A peer that speaks an
+//! incompatible version is refused, rather than having its frames misparsed.
+//! See the [`hello`](crate::interface_manager::hello) module.
+//!
+//! Links that cross untrusted networks may also be encrypted and
+//! authenticated, by wrapping the Sink and the `FrameProcessor` of both ends
+//! of the link. See the `interface_manager::aead` module, which requires the
+//! `link-aead` feature.
@@ -280,3 +280,8 @@
 //!     * Exclusive access to items
 //!     * Keepalives, notice when device "drops"
 //!     * Reliable deliver, retries, higher QoS
+//!
+//! A first version of sessionful sockets is available in
+//! [`socket::session`](crate::socket::session): a handshake, sequence numbers,
+//! acknowledgements with a sliding window, retransmission, keepalives, and a
+//! close handshake, carrying typed messages described by an `Endpoint`.
@@ -66,6 +66,12 @@
 //!   in a timeout and a bounded number of retries." Because a message may now be
 //!   delivered more than once, the receiver must tolerate duplicates — that is,
 //!   the operation must be **idempotent**.
+//!   For endpoints, `Endpoints::request_with_retry` (or an `EndpointClient`
+//!   configured with `with_deadline` and `with_retry`) implements exactly this:
+//!   each attempt waits up to a deadline, and timed out attempts are re-sent
+//!   according to a `RetryPolicy` with optional exponential backoff. On firmware
+//!   this uses `embassy-time`; other executors can provide their own sleep via
+//!   `request_full_with_retry`.
 //! * **Effectively-once** — at-least-once delivery plus de-duplication on the
 //!   receiver (an idempotency key / request id the server remembers). True
 //!   exactly-once is not achievable over an unreliable transport; this is the
@@ -116,7 +116,7 @@
 use mocks::{ExpectedSend, test_stack};
 
 use crate::{
-    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, Key, NetStackSendError,
+    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, Key, NetStackSendError, Priority,
     interface_manager::InterfaceSendError,
 };
 
@@ -274,6 +274,7 @@ fn unicast_specific_port() -> Header {
         seq_no: None,
         kind: FrameKind::RESERVED,
         ttl: DEFAULT_TTL,
+        prio: Priority::NORMAL,
     }
 }
 
@@ -294,6 +295,7 @@ fn broadcast_hdr() -> Header {
         seq_no: None,
         kind: FrameKind::TOPIC_MSG,
         ttl: DEFAULT_TTL,
+        prio: Priority::NORMAL,
     }
 }
 
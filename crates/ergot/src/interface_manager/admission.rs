@@ -0,0 +1,418 @@
+//! Admission Control
+//!
+//! By default, a seed router grants a net_id, and a router on a bus grants a
+//! node_id, to any device that asks. On a shared RS-485 bus, or a public UDP
+//! segment, a rogue device could claim every address, and lock out the
+//! devices that should be there.
+//!
+//! A [`Router`](super::profiles::router::Router) can instead be given an
+//! [`AdmissionPolicy`], using `Router::with_admission_policy()`. The policy
+//! is consulted by [`Profile::request_seed_net_assign`] and
+//! [`Profile::request_node_claim`], with the [`Credentials`] sent by the
+//! requesting device, before anything is allocated. Requests that are not
+//! admitted fail with `NotAdmitted`.
+//!
+//! This module provides the following policies:
+//!
+//! * [`OpenAdmission`], the default, which admits every device
+//! * [`AllowList`], which only admits devices with a known
+//!   [`DeviceInfo::unique_id`](crate::well_known::DeviceInfo::unique_id)
+//! * `SharedSecret`, which requires the `signing` feature, and only admits
+//!   devices that answer a challenge with a secret shared by all provisioned
+//!   devices
+//!
+//! ## Requesting devices
+//!
+//! The credentials a device sends are provided by its profile, see
+//! [`Profile::admission_credentials`]. A device is given a [`DeviceIdentity`]
+//! with `with_identity()`, for example on a
+//! [`DirectEdge`](super::profiles::direct_edge::DirectEdge) that claims a
+//! node_id on a bus, or on a bridging `Router` that requests a net_id from
+//! its upstream seed router.
+//!
+//! Devices without an identity send no credentials, and are only admitted by
+//! [`OpenAdmission`].
+//!
+//! ## Challenge and response
+//!
+//! A policy may answer a request with
+//! [`AdmissionError::ChallengeRequired`]. The client helpers, such as
+//! [`bus_claim`](crate::net_stack::services::bus_claim), then repeat the
+//! request once, with a [`Proof`] that answers the challenge. With the
+//! `signing` feature, the proof is an HMAC-SHA256 tag of the challenge and
+//! the unique id, see `prove()`. Each challenge can only be answered once.
+//!
+//! [`Profile::request_seed_net_assign`]: super::Profile::request_seed_net_assign
+//! [`Profile::request_node_claim`]: super::Profile::request_node_claim
+//! [`Profile::admission_credentials`]: super::Profile::admission_credentials
+
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+/// The credentials sent by a device requesting a net_id or node_id
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct Credentials {
+    /// The unique id of the device, as in its
+    /// [`DeviceInfo`](crate::well_known::DeviceInfo)
+    pub unique_id: u64,
+    /// The answer to a challenge, if the router sent one
+    pub proof: Option<Proof>,
+}
+
+/// The answer to a challenge sent with [`AdmissionError::ChallengeRequired`]
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct Proof {
+    /// The challenge being answered
+    pub challenge: [u8; 8],
+    /// The response calculated from the challenge
+    pub response: [u8; 32],
+}
+
+/// The reason a request was not admitted
+#[derive(Serialize, Deserialize, Schema, Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub enum AdmissionError {
+    /// The device is not allowed to join
+    Denied,
+    /// The request must be repeated with a [`Proof`] answering this challenge
+    ChallengeRequired([u8; 8]),
+}
+
+/// Decides which devices may be granted a net_id or node_id
+pub trait AdmissionPolicy {
+    /// Decide whether the device sending `credentials`, from the interface
+    /// with the net_id `source_net`, is admitted
+    ///
+    /// `challenge` is a fresh random value, which the policy may send to the
+    /// device with [`AdmissionError::ChallengeRequired`].
+    fn admit(
+        &mut self,
+        source_net: u16,
+        credentials: Option<&Credentials>,
+        challenge: [u8; 8],
+    ) -> Result<(), AdmissionError>;
+}
+
+/// Admits every device
+///
+/// This is the default policy of a [`Router`](super::profiles::router::Router).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OpenAdmission;
+
+impl AdmissionPolicy for OpenAdmission {
+    fn admit(
+        &mut self,
+        _source_net: u16,
+        _credentials: Option<&Credentials>,
+        _challenge: [u8; 8],
+    ) -> Result<(), AdmissionError> {
+        Ok(())
+    }
+}
+
+/// Only admits devices with one of the given unique ids
+///
+/// The unique id is sent by the device itself, so this only keeps out
+/// devices that are misconfigured, not ones that lie. Use `SharedSecret`
+/// for devices that can't be trusted.
+#[derive(Debug, Clone, Copy)]
+pub struct AllowList<'a> {
+    ids: &'a [u64],
+}
+
+impl<'a> AllowList<'a> {
+    /// Create a policy that admits the devices with the given unique ids
+    pub const fn new(ids: &'a [u64]) -> Self {
+        Self { ids }
+    }
+
+    /// Is `credentials` for a device on this list?
+    pub fn contains(&self, credentials: Option<&Credentials>) -> bool {
+        credentials.is_some_and(|c| self.ids.contains(&c.unique_id))
+    }
+}
+
+impl AdmissionPolicy for AllowList<'_> {
+    fn admit(
+        &mut self,
+        _source_net: u16,
+        credentials: Option<&Credentials>,
+        _challenge: [u8; 8],
+    ) -> Result<(), AdmissionError> {
+        if self.contains(credentials) {
+            Ok(())
+        } else {
+            Err(AdmissionError::Denied)
+        }
+    }
+}
+
+/// The identity a device presents when requesting a net_id or node_id
+#[derive(Clone, Copy)]
+pub struct DeviceIdentity {
+    unique_id: u64,
+    #[cfg(feature = "signing")]
+    secret: Option<[u8; 32]>,
+}
+
+impl DeviceIdentity {
+    /// An identity with the given unique id, and no secret
+    pub const fn new(unique_id: u64) -> Self {
+        Self {
+            unique_id,
+            #[cfg(feature = "signing")]
+            secret: None,
+        }
+    }
+
+    /// Answer challenges with the given shared secret, see `SharedSecret`
+    #[cfg(feature = "signing")]
+    pub const fn with_shared_secret(self, secret: [u8; 32]) -> Self {
+        Self {
+            unique_id: self.unique_id,
+            secret: Some(secret),
+        }
+    }
+
+    /// The unique id of this device
+    pub const fn unique_id(&self) -> u64 {
+        self.unique_id
+    }
+
+    /// The credentials to send, answering `challenge` if there is one
+    ///
+    /// Returns `None` if there is a challenge this identity can't answer.
+    pub fn credentials(&self, challenge: Option<[u8; 8]>) -> Option<Credentials> {
+        let proof = match challenge {
+            None => None,
+            #[cfg(feature = "signing")]
+            Some(challenge) => Some(Proof {
+                challenge,
+                response: prove(&self.secret?, self.unique_id, &challenge),
+            }),
+            #[cfg(not(feature = "signing"))]
+            Some(_) => return None,
+        };
+        Some(Credentials {
+            unique_id: self.unique_id,
+            proof,
+        })
+    }
+}
+
+impl core::fmt::Debug for DeviceIdentity {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DeviceIdentity")
+            .field("unique_id", &self.unique_id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "signing")]
+pub use shared_secret::{SharedSecret, prove};
+
+#[cfg(feature = "signing")]
+mod shared_secret {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    use super::{AdmissionError, AdmissionPolicy, AllowList, Credentials};
+
+    /// Calculate the response to `challenge` for the device `unique_id`
+    pub fn prove(secret: &[u8; 32], unique_id: u64, challenge: &[u8; 8]) -> [u8; 32] {
+        mac(secret, unique_id, challenge)
+            .finalize()
+            .into_bytes()
+            .into()
+    }
+
+    fn mac(secret: &[u8; 32], unique_id: u64, challenge: &[u8; 8]) -> Hmac<Sha256> {
+        // Infallible: HMAC accepts keys of any length
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret).unwrap();
+        mac.update(challenge);
+        mac.update(&unique_id.to_le_bytes());
+        mac
+    }
+
+    /// Only admits devices that answer a challenge with a shared secret
+    ///
+    /// Every request without a valid [`Proof`](super::Proof) is answered with
+    /// a new challenge. Up to `P` challenges are outstanding at a time, one
+    /// per unique id, and the oldest one is forgotten when a new one is
+    /// issued.
+    pub struct SharedSecret<const P: usize> {
+        secret: [u8; 32],
+        allow: Option<AllowList<'static>>,
+        pending: heapless::Deque<(u64, [u8; 8]), P>,
+    }
+
+    impl<const P: usize> SharedSecret<P> {
+        /// Create a policy that admits the devices knowing `secret`
+        pub const fn new(secret: [u8; 32]) -> Self {
+            Self {
+                secret,
+                allow: None,
+                pending: heapless::Deque::new(),
+            }
+        }
+
+        /// Additionally, only admit the devices on `allow`
+        pub fn with_allow_list(mut self, allow: AllowList<'static>) -> Self {
+            self.allow = Some(allow);
+            self
+        }
+
+        /// Take the outstanding challenge for `unique_id`, if there is one
+        fn take_pending(&mut self, unique_id: u64) -> Option<[u8; 8]> {
+            let mut found = None;
+            for _ in 0..self.pending.len() {
+                let Some(entry) = self.pending.pop_front() else {
+                    break;
+                };
+                if entry.0 == unique_id {
+                    found = Some(entry.1);
+                } else {
+                    // Keep the order, oldest first
+                    _ = self.pending.push_back(entry);
+                }
+            }
+            found
+        }
+    }
+
+    impl<const P: usize> core::fmt::Debug for SharedSecret<P> {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.debug_struct("SharedSecret")
+                .field("allow", &self.allow)
+                .field("pending", &self.pending.len())
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl<const P: usize> AdmissionPolicy for SharedSecret<P> {
+        fn admit(
+            &mut self,
+            _source_net: u16,
+            credentials: Option<&Credentials>,
+            challenge: [u8; 8],
+        ) -> Result<(), AdmissionError> {
+            let Some(creds) = credentials else {
+                return Err(AdmissionError::Denied);
+            };
+            if self.allow.is_some_and(|a| !a.contains(credentials)) {
+                return Err(AdmissionError::Denied);
+            }
+
+            // Challenges are single use, whether answered correctly or not
+            let pending = self.take_pending(creds.unique_id);
+            let answered = match (&creds.proof, pending) {
+                (Some(proof), Some(pending)) if proof.challenge == pending => Some(proof),
+                _ => None,
+            };
+            if let Some(proof) = answered {
+                return mac(&self.secret, creds.unique_id, &proof.challenge)
+                    .verify_slice(&proof.response)
+                    .map_err(|_| AdmissionError::Denied);
+            }
+
+            if P == 0 {
+                return Err(AdmissionError::Denied);
+            }
+            if self.pending.is_full() {
+                _ = self.pending.pop_front();
+            }
+            _ = self.pending.push_back((creds.unique_id, challenge));
+            Err(AdmissionError::ChallengeRequired(challenge))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AdmissionError, AdmissionPolicy, AllowList, DeviceIdentity, OpenAdmission};
+
+    #[test]
+    fn allow_list() {
+        let mut open = OpenAdmission;
+        assert_eq!(open.admit(1, None, [0; 8]), Ok(()));
+
+        let mut allow = AllowList::new(&[10, 20]);
+        let known = DeviceIdentity::new(20).credentials(None);
+        let unknown = DeviceIdentity::new(30).credentials(None);
+        assert_eq!(allow.admit(1, known.as_ref(), [0; 8]), Ok(()));
+        assert_eq!(
+            allow.admit(1, unknown.as_ref(), [0; 8]),
+            Err(AdmissionError::Denied)
+        );
+        assert_eq!(allow.admit(1, None, [0; 8]), Err(AdmissionError::Denied));
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn shared_secret() {
+        use super::SharedSecret;
+
+        let mut policy = SharedSecret::<2>::new([7; 32]);
+        let device = DeviceIdentity::new(10).with_shared_secret([7; 32]);
+        let rogue = DeviceIdentity::new(10).with_shared_secret([6; 32]);
+
+        // Without a proof, a challenge is issued
+        let creds = device.credentials(None);
+        assert_eq!(
+            policy.admit(1, creds.as_ref(), [1; 8]),
+            Err(AdmissionError::ChallengeRequired([1; 8]))
+        );
+        let creds = device.credentials(Some([1; 8]));
+        assert_eq!(policy.admit(1, creds.as_ref(), [2; 8]), Ok(()));
+
+        // A challenge can only be answered once
+        assert_eq!(
+            policy.admit(1, creds.as_ref(), [3; 8]),
+            Err(AdmissionError::ChallengeRequired([3; 8]))
+        );
+
+        // The wrong secret
+        let creds = rogue.credentials(Some([3; 8]));
+        assert_eq!(
+            policy.admit(1, creds.as_ref(), [4; 8]),
+            Err(AdmissionError::Denied)
+        );
+
+        // Devices without a secret can't answer
+        assert_eq!(DeviceIdentity::new(10).credentials(Some([4; 8])), None);
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn shared_secret_forgets_oldest_challenge() {
+        use super::SharedSecret;
+
+        let mut policy =
+            SharedSecret::<2>::new([7; 32]).with_allow_list(AllowList::new(&[1, 2, 3]));
+        let ids = [1, 2, 3].map(|id| DeviceIdentity::new(id).with_shared_secret([7; 32]));
+        for (i, id) in ids.iter().enumerate() {
+            let creds = id.credentials(None);
+            assert!(policy.admit(1, creds.as_ref(), [i as u8; 8]).is_err());
+        }
+
+        // The first challenge was forgotten, the last one is still valid
+        let creds = ids[2].credentials(Some([2; 8]));
+        assert_eq!(policy.admit(1, creds.as_ref(), [9; 8]), Ok(()));
+        let creds = ids[0].credentials(Some([0; 8]));
+        assert_eq!(
+            policy.admit(1, creds.as_ref(), [9; 8]),
+            Err(AdmissionError::ChallengeRequired([9; 8]))
+        );
+
+        // Not on the allow list
+        let creds = DeviceIdentity::new(4)
+            .with_shared_secret([7; 32])
+            .credentials(None);
+        assert_eq!(
+            policy.admit(1, creds.as_ref(), [9; 8]),
+            Err(AdmissionError::Denied)
+        );
+    }
+}
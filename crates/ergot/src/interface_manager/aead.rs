@@ -0,0 +1,865 @@
+//! Link Encryption
+//!
+//! Frames are normally sent in plaintext, with no integrity check beyond the
+//! framing of the transport. For links that cross untrusted networks, both
+//! ends of a link may instead encrypt and authenticate every frame with
+//! ChaCha20-Poly1305, using a [`LinkKey`] shared by both ends.
+//!
+//! Encryption is opt-in, and requires the `link-aead` feature:
+//!
+//! * On the sending side, the interface's sink is wrapped in a [`Sink`], for
+//!   example by using an [`AeadInterface`] as the profile's [`Interface`].
+//!   Every frame, including link frames such as a
+//!   [`LinkHello`](super::hello::LinkHello), is encoded into the [`Sink`]'s
+//!   buffer, sealed, and sent as a link frame by the wrapped sink.
+//! * On the receiving side, the [`FrameProcessor`] is wrapped in an
+//!   [`AeadProcessor`], which opens every frame before passing it to the
+//!   wrapped processor. Frames that fail to authenticate, or that were
+//!   already received, are dropped.
+//!
+//! Neither wrapper allocates, so both can be used on `no_std`. Wrapping the
+//! [`FrameProcessor`] in a [`HelloProcessor`](super::hello::HelloProcessor)
+//! inside the [`AeadProcessor`] is recommended: no frame can be sent until
+//! the session is set up, and the hello is retransmitted until it is.
+//!
+//! ## Wire format
+//!
+//! ```text
+//! session: [u8; 8],       random:         8 bytes
+//! counter: u64,           little endian:  8 bytes
+//! ciphertext,             frame length:   n bytes
+//! tag,                    Poly1305:      16 bytes
+//! ```
+//!
+//! Challenges are sent as link frames, without sealing them:
+//!
+//! ```text
+//! LINK_CHALLENGE_MAGIC,   [u8; 8]:        8 bytes
+//! reply: bool,            bool:           1 byte
+//! challenge: [u8; 16],    random:        16 bytes
+//! ```
+//!
+//! ## Sessions
+//!
+//! Frames are sealed with a session key, derived from the [`LinkKey`], a
+//! random session id chosen by the sender, and a random challenge chosen by
+//! the receiver. A key shared by both ends can be used for any number of
+//! sessions, across restarts of either end, without reusing a nonce.
+//!
+//! * When the link comes up, each [`AeadProcessor`] sends a new challenge to
+//!   the peer. A challenge that is not itself a reply is answered, so both
+//!   directions get one no matter which end comes up first.
+//! * A received challenge is passed to the [`Sink`] on the same end, which
+//!   starts a new session with it. Until then, the [`Sink`] can't send any
+//!   frame.
+//! * The receiver accepts a new session only if its frames authenticate with
+//!   its outstanding challenge, which is then used up. Frames of any other
+//!   session are dropped: recorded frames of earlier sessions, including
+//!   sessions from before the receiver restarted, can't be replayed, and
+//!   can't displace the current session.
+//! * Until a session is accepted, the challenge is sent again after
+//!   [`CHALLENGE_RETRY_MS`], doubling after each attempt, and when frames of
+//!   an unknown session are dropped: on the 1st, 2nd, 4th, 8th, ... frame.
+//!   The latter also recovers a link whose sender started a session the
+//!   receiver doesn't know about.
+//!
+//! The 96-bit nonce is made of the sender's [`LinkRole`] and the counter, so
+//! each direction of a link uses its own nonces. The counter starts at zero
+//! in every session, and increments with every frame. The receiver keeps a
+//! window of the last [`REPLAY_WINDOW`] counters of the current session, and
+//! rejects counters it has seen before, or that are older than the window.
+
+use core::marker::PhantomData;
+
+use chacha20poly1305::{
+    AeadInPlace, ChaCha20Poly1305, KeyInit, Nonce, Tag,
+    aead::generic_array::{GenericArray, typenum::U10},
+};
+use postcard::{
+    Serializer,
+    ser_flavors::{self, Flavor},
+};
+use rand_core::CryptoRng;
+use serde::Serialize;
+
+use crate::{
+    FrameKind, HeaderSeq, ProtocolError,
+    interface_manager::{FrameProcessor, Interface, InterfaceSink, Profile},
+    logging::{debug, info, warn},
+    net_stack::NetStackHandle,
+    replay::ReplayWindow,
+    wire_frames::HeaderEncoding,
+};
+
+pub use crate::replay::REPLAY_WINDOW;
+
+/// The size of the session id at the start of a sealed frame
+pub const SESSION_SIZE: usize = 8;
+
+/// The size of the counter after the session id of a sealed frame
+pub const COUNTER_SIZE: usize = 8;
+
+/// The size of the Poly1305 tag at the end of a sealed frame
+pub const TAG_SIZE: usize = 16;
+
+/// The number of bytes added to each frame by sealing it
+pub const AEAD_OVERHEAD: usize = SESSION_SIZE + COUNTER_SIZE + TAG_SIZE;
+
+/// The size of a receiver's challenge
+pub const CHALLENGE_SIZE: usize = 16;
+
+/// The start of every challenge link frame
+///
+/// Chosen like [`LINK_HELLO_MAGIC`](super::hello::LINK_HELLO_MAGIC), which
+/// it only differs from in the last byte.
+pub const LINK_CHALLENGE_MAGIC: [u8; 8] = [0xFF, b'e', b'r', b'g', b'o', b't', 0xFF, 0x02];
+
+/// The encoded size of a challenge link frame
+pub const LINK_CHALLENGE_SIZE: usize = LINK_CHALLENGE_MAGIC.len() + 1 + CHALLENGE_SIZE;
+
+/// How long to wait for a new session of the peer before sending the
+/// challenge again, doubled after each attempt, up to 32 times as long
+pub const CHALLENGE_RETRY_MS: u64 = 250;
+
+/// The start of the link frame an [`AeadProcessor`] passes a peer's challenge
+/// to its [`Sink`] with
+///
+/// Never sent.
+const START_SESSION_MAGIC: [u8; 8] = [0xFF, b'e', b'r', b'g', b'o', b't', 0xFF, 0x03];
+
+/// A 256-bit ChaCha20-Poly1305 key, shared by both ends of a link
+#[derive(Clone)]
+pub struct LinkKey([u8; 32]);
+
+impl LinkKey {
+    /// Use the given bytes as a key
+    pub const fn new(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+
+    /// Derive a per-link key from this key, using HChaCha20
+    ///
+    /// Both ends of the link must use the same `context`. Keys derived from
+    /// different contexts are unrelated, so a single pre-shared key can be
+    /// used to derive a different key for each link.
+    pub fn derive(&self, context: &[u8; 16]) -> Self {
+        let key = chacha20::hchacha::<U10>(
+            GenericArray::from_slice(&self.0),
+            GenericArray::from_slice(context),
+        );
+        Self(key.into())
+    }
+
+    /// The cipher sealing the frames of the given session, started with the
+    /// receiver's `challenge`
+    fn session_cipher(
+        &self,
+        session: &[u8; SESSION_SIZE],
+        challenge: &[u8; CHALLENGE_SIZE],
+    ) -> ChaCha20Poly1305 {
+        let key = self
+            .derive(challenge)
+            .derive(&Self::context(b"ergotses", session));
+        ChaCha20Poly1305::new(GenericArray::from_slice(&key.0))
+    }
+
+    /// The `n`-th of a sequence of unpredictable values, when this key is a
+    /// random seed
+    fn nth(&self, label: &[u8; 8], n: u64) -> [u8; 32] {
+        self.derive(&Self::context(label, &n.to_le_bytes())).0
+    }
+
+    fn context(label: &[u8; 8], data: &[u8; 8]) -> [u8; 16] {
+        let mut context = [0u8; 16];
+        context[..8].copy_from_slice(label);
+        context[8..].copy_from_slice(data);
+        context
+    }
+
+    /// A key from `rng`, to be used as a seed
+    fn random<R: CryptoRng>(rng: &mut R) -> Self {
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        Self(seed)
+    }
+}
+
+impl core::fmt::Debug for LinkKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("LinkKey(..)")
+    }
+}
+
+/// Which end of a link a [`Sink`] or [`AeadProcessor`] is on
+///
+/// The two ends of a link MUST use different roles. For links between a
+/// router and an edge device, the router is the controller.
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkRole {
+    Controller,
+    Target,
+}
+
+impl LinkRole {
+    /// The role of the other end of the link
+    pub const fn peer(self) -> Self {
+        match self {
+            LinkRole::Controller => LinkRole::Target,
+            LinkRole::Target => LinkRole::Controller,
+        }
+    }
+
+    fn nonce(self, counter: u64) -> Nonce {
+        let mut nonce = [0u8; 12];
+        nonce[0] = match self {
+            LinkRole::Controller => 0x01,
+            LinkRole::Target => 0x02,
+        };
+        nonce[4..].copy_from_slice(&counter.to_le_bytes());
+        nonce.into()
+    }
+}
+
+/// An [`Interface`] whose sink is `I`'s sink, wrapped in a [`Sink`] with a
+/// buffer of `N` bytes
+pub struct AeadInterface<I, const N: usize> {
+    _pd: PhantomData<I>,
+}
+
+impl<I, const N: usize> Interface for AeadInterface<I, N>
+where
+    I: Interface,
+{
+    type Sink = Sink<I::Sink, N>;
+}
+
+/// An interface sink that seals every frame before passing it to the wrapped
+/// sink
+///
+/// Frames are encoded into a buffer of `N` bytes, which limits the
+/// [`mtu`](InterfaceSink::mtu) to `N - AEAD_OVERHEAD`. The wrapped sink must
+/// support [`send_link_frame`](InterfaceSink::send_link_frame).
+pub struct Sink<S, const N: usize> {
+    inner: S,
+    key: LinkKey,
+    /// Session ids are taken from this seed
+    seed: LinkKey,
+    sessions: u64,
+    session: Option<SinkSession>,
+    role: LinkRole,
+    encoding: HeaderEncoding,
+    buf: [u8; N],
+}
+
+/// The session a [`Sink`] seals frames with
+struct SinkSession {
+    id: [u8; SESSION_SIZE],
+    challenge: [u8; CHALLENGE_SIZE],
+    cipher: ChaCha20Poly1305,
+    counter: u64,
+}
+
+impl<S, const N: usize> Sink<S, N>
+where
+    S: InterfaceSink,
+{
+    /// Wrap `inner`, sealing frames with `key` as the given end of the link
+    ///
+    /// Session ids are taken from `rng`, which must not repeat its output
+    /// after a restart, e.g. a CSPRNG seeded by a hardware RNG. No frame can
+    /// be sent until the peer's challenge is received, see the
+    /// [module docs](self).
+    pub fn new<R: CryptoRng>(inner: S, key: &LinkKey, role: LinkRole, rng: &mut R) -> Self {
+        const { assert!(N > AEAD_OVERHEAD) };
+        Self {
+            inner,
+            key: key.clone(),
+            seed: LinkKey::random(rng),
+            sessions: 0,
+            session: None,
+            role,
+            encoding: HeaderEncoding::Standard,
+            buf: [0u8; N],
+        }
+    }
+
+    /// Encode frame headers with the given [`HeaderEncoding`], instead of
+    /// [`HeaderEncoding::Standard`]
+    ///
+    /// The other end of the link must decode frames with the same encoding.
+    pub fn with_header_encoding(self, encoding: HeaderEncoding) -> Self {
+        Self { encoding, ..self }
+    }
+
+    /// The wrapped sink
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// The part of the buffer the plaintext frame is encoded into
+    fn plaintext(&mut self) -> &mut [u8] {
+        &mut self.buf[SESSION_SIZE + COUNTER_SIZE..N - TAG_SIZE]
+    }
+
+    /// Start a new session with the peer's `challenge`, unless the current
+    /// session already uses it
+    fn start_session(&mut self, challenge: &[u8; CHALLENGE_SIZE]) {
+        if self
+            .session
+            .as_ref()
+            .is_some_and(|session| session.challenge == *challenge)
+        {
+            return;
+        }
+        let mut id = [0u8; SESSION_SIZE];
+        id.copy_from_slice(&self.seed.nth(b"ergotsid", self.sessions)[..SESSION_SIZE]);
+        self.sessions += 1;
+        debug!("Starting a new link session");
+        self.session = Some(SinkSession {
+            id,
+            challenge: *challenge,
+            cipher: self.key.session_cipher(&id, challenge),
+            counter: 0,
+        });
+    }
+
+    /// Seal the first `len` bytes of the plaintext, and send the sealed frame
+    fn seal_and_send(&mut self, len: usize) -> Result<(), ()> {
+        let Some(session) = &mut self.session else {
+            debug!("Dropping frame, no link session yet");
+            return Err(());
+        };
+        // Never wrap around, as that would reuse nonces
+        let counter = session.counter;
+        if counter == u64::MAX {
+            warn!("Link counter exhausted, a new session is required");
+            return Err(());
+        }
+        session.counter += 1;
+
+        let start = SESSION_SIZE + COUNTER_SIZE;
+        let end = start + len;
+        let tag = session
+            .cipher
+            .encrypt_in_place_detached(&self.role.nonce(counter), &[], &mut self.buf[start..end])
+            .map_err(drop)?;
+        self.buf[..SESSION_SIZE].copy_from_slice(&session.id);
+        self.buf[SESSION_SIZE..start].copy_from_slice(&counter.to_le_bytes());
+        self.buf[end..][..TAG_SIZE].copy_from_slice(&tag);
+        self.inner.send_link_frame(&self.buf[..end + TAG_SIZE])
+    }
+}
+
+#[allow(clippy::result_unit_err)]
+impl<S, const N: usize> InterfaceSink for Sink<S, N>
+where
+    S: InterfaceSink,
+{
+    fn mtu(&self) -> u16 {
+        let max = (N - AEAD_OVERHEAD).min(u16::MAX as usize) as u16;
+        self.inner
+            .mtu()
+            .saturating_sub(AEAD_OVERHEAD as u16)
+            .min(max)
+    }
+
+    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, body: &T) -> Result<usize, ()> {
+        if hdr.kind == FrameKind::PROTOCOL_ERROR {
+            return Err(());
+        }
+
+        let encoding = self.encoding;
+        let used = encoding
+            .encode_frame_ty(ser_flavors::Slice::new(self.plaintext()), hdr, body)
+            .map_err(drop)?;
+        let len = used.len();
+        self.seal_and_send(len)?;
+        Ok(len)
+    }
+
+    fn send_raw(&mut self, hdr: &HeaderSeq, body: &[u8]) -> Result<usize, ()> {
+        if hdr.kind == FrameKind::PROTOCOL_ERROR {
+            return Err(());
+        }
+
+        let encoding = self.encoding;
+        let mut ser = Serializer {
+            output: ser_flavors::Slice::new(self.plaintext()),
+        };
+        encoding.encode_frame_hdr(&mut ser, hdr).map_err(drop)?;
+        ser.output.try_extend(body).map_err(drop)?;
+        let len = ser.output.finalize().map_err(drop)?.len();
+        self.seal_and_send(len)?;
+        Ok(len)
+    }
+
+    fn send_err(&mut self, hdr: &HeaderSeq, err: ProtocolError) -> Result<usize, ()> {
+        if hdr.kind != FrameKind::PROTOCOL_ERROR {
+            return Err(());
+        }
+
+        let encoding = self.encoding;
+        let used = encoding
+            .encode_frame_err(ser_flavors::Slice::new(self.plaintext()), hdr, err)
+            .map_err(drop)?;
+        let len = used.len();
+        self.seal_and_send(len)?;
+        Ok(len)
+    }
+
+    fn send_link_frame(&mut self, data: &[u8]) -> Result<(), ()> {
+        // From the AeadProcessor on this end: challenges are sent as they
+        // are, and the peer's challenge starts a new session
+        if data.len() == LINK_CHALLENGE_SIZE && data.starts_with(&LINK_CHALLENGE_MAGIC) {
+            return self.inner.send_link_frame(data);
+        }
+        if let Some(challenge) = data.strip_prefix(&START_SESSION_MAGIC) {
+            let challenge = challenge.try_into().map_err(drop)?;
+            self.start_session(challenge);
+            return Ok(());
+        }
+
+        let plaintext = self.plaintext();
+        if data.len() > plaintext.len() {
+            return Err(());
+        }
+        plaintext[..data.len()].copy_from_slice(data);
+        self.seal_and_send(data.len())
+    }
+}
+
+/// A session of the peer
+struct Session {
+    id: [u8; SESSION_SIZE],
+    cipher: ChaCha20Poly1305,
+    window: ReplayWindow,
+}
+
+/// A [`FrameProcessor`] that opens sealed frames, before passing them to the
+/// wrapped processor
+///
+/// Frames are decrypted into a buffer of `N` bytes, larger frames are
+/// dropped. See the [module docs](self) for details.
+pub struct AeadProcessor<P, const N: usize> {
+    inner: P,
+    key: LinkKey,
+    /// Challenges are taken from this seed
+    seed: LinkKey,
+    challenges: u64,
+    peer: LinkRole,
+    session: Option<Session>,
+    /// The challenge the peer's next session must use, if one was sent
+    challenge: Option<[u8; CHALLENGE_SIZE]>,
+    /// Challenges sent since the last session was accepted
+    attempts: u8,
+    /// Frames of unknown sessions dropped since the last session was accepted
+    unknown: u32,
+    buf: [u8; N],
+}
+
+impl<P, const N: usize> AeadProcessor<P, N> {
+    /// Wrap `inner`, opening frames sealed with `key` by the other end of the
+    /// link
+    ///
+    /// `role` is the role of this end of the link, the same as for its
+    /// [`Sink`]. Challenges are taken from `rng`, with the same requirements
+    /// as for [`Sink::new`].
+    pub fn new<R: CryptoRng>(inner: P, key: &LinkKey, role: LinkRole, rng: &mut R) -> Self {
+        Self {
+            inner,
+            key: key.clone(),
+            seed: LinkKey::random(rng),
+            challenges: 0,
+            peer: role.peer(),
+            session: None,
+            challenge: None,
+            attempts: 0,
+            unknown: 0,
+            buf: [0u8; N],
+        }
+    }
+
+    /// The wrapped processor
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Authenticate and decrypt a sealed frame, returns the plaintext
+    fn open(&mut self, data: &[u8]) -> Option<&[u8]> {
+        let (id, rest) = data.split_first_chunk::<SESSION_SIZE>()?;
+        let (counter, rest) = rest.split_first_chunk::<COUNTER_SIZE>()?;
+        let (ciphertext, tag) = rest.split_last_chunk::<TAG_SIZE>()?;
+        let counter = u64::from_le_bytes(*counter);
+
+        if ciphertext.len() > N {
+            debug!("Dropping sealed frame, too large");
+            return None;
+        }
+
+        // A frame of the current session, or the first of a new one, which
+        // must use our challenge
+        let mut new_session = None;
+        let session = match (&mut self.session, self.challenge) {
+            (Some(session), _) if session.id == *id => session,
+            (_, Some(challenge)) => new_session.insert(Session {
+                id: *id,
+                cipher: self.key.session_cipher(id, &challenge),
+                window: ReplayWindow::default(),
+            }),
+            (_, None) => {
+                debug!("Dropping frame of an unknown session");
+                self.unknown = self.unknown.saturating_add(1);
+                return None;
+            }
+        };
+
+        if !session.window.check(counter) {
+            debug!("Dropping replayed frame, counter {}", counter);
+            return None;
+        }
+
+        let plaintext = &mut self.buf[..ciphertext.len()];
+        plaintext.copy_from_slice(ciphertext);
+        let res = session.cipher.decrypt_in_place_detached(
+            &self.peer.nonce(counter),
+            &[],
+            plaintext,
+            Tag::from_slice(tag),
+        );
+        if res.is_err() {
+            warn!("Dropping frame that failed to authenticate");
+            if new_session.is_some() {
+                self.unknown = self.unknown.saturating_add(1);
+            }
+            return None;
+        }
+
+        // Only authenticated frames may move the window, or start a session
+        session.window.accept(counter);
+        if let Some(session) = new_session {
+            info!("Link peer started a new session");
+            self.session = Some(session);
+            // Used up, a replay of this session's first frames must not
+            // start it again
+            self.challenge = None;
+            self.attempts = 0;
+            self.unknown = 0;
+        }
+        Some(plaintext)
+    }
+
+    /// The next challenge for the peer, a new one once the last was used up
+    fn challenge(&mut self) -> [u8; CHALLENGE_SIZE] {
+        if let Some(challenge) = self.challenge {
+            return challenge;
+        }
+        let mut challenge = [0u8; CHALLENGE_SIZE];
+        challenge.copy_from_slice(&self.seed.nth(b"ergotchl", self.challenges)[..CHALLENGE_SIZE]);
+        self.challenges += 1;
+        self.challenge = Some(challenge);
+        challenge
+    }
+
+    fn send_challenge<NS: NetStackHandle>(
+        &mut self,
+        nsh: &NS,
+        ident: <<NS as NetStackHandle>::Profile as Profile>::InterfaceIdent,
+        reply: bool,
+    ) {
+        let mut frame = [0u8; LINK_CHALLENGE_SIZE];
+        frame[..LINK_CHALLENGE_MAGIC.len()].copy_from_slice(&LINK_CHALLENGE_MAGIC);
+        frame[LINK_CHALLENGE_MAGIC.len()] = reply as u8;
+        frame[LINK_CHALLENGE_MAGIC.len() + 1..].copy_from_slice(&self.challenge());
+        self.attempts = self.attempts.saturating_add(1);
+        let res = nsh
+            .stack()
+            .manage_profile(|im| im.send_link_frame(ident, &frame));
+        if res.is_err() {
+            warn!("Failed to send link challenge");
+        }
+    }
+
+    /// Pass the peer's challenge to our [`Sink`], and answer it
+    fn handle_challenge<NS: NetStackHandle>(
+        &mut self,
+        frame: &[u8; LINK_CHALLENGE_SIZE],
+        nsh: &NS,
+        ident: <<NS as NetStackHandle>::Profile as Profile>::InterfaceIdent,
+    ) {
+        let reply = frame[LINK_CHALLENGE_MAGIC.len()] != 0;
+        let challenge = &frame[LINK_CHALLENGE_MAGIC.len() + 1..];
+        let mut start = [0u8; START_SESSION_MAGIC.len() + CHALLENGE_SIZE];
+        start[..START_SESSION_MAGIC.len()].copy_from_slice(&START_SESSION_MAGIC);
+        start[START_SESSION_MAGIC.len()..].copy_from_slice(challenge);
+        let res = nsh
+            .stack()
+            .manage_profile(|im| im.send_link_frame(ident.clone(), &start));
+        if res.is_err() {
+            warn!("Failed to start a link session");
+        }
+        if !reply {
+            self.send_challenge(nsh, ident, true);
+        }
+    }
+
+    /// How long to wait before sending the challenge again, if a session with
+    /// it is still expected
+    fn retry_ms(&self) -> Option<u64> {
+        self.challenge
+            .map(|_| CHALLENGE_RETRY_MS << self.attempts.saturating_sub(1).min(5))
+    }
+}
+
+impl<N, P, const M: usize> FrameProcessor<N> for AeadProcessor<P, M>
+where
+    N: NetStackHandle,
+    P: FrameProcessor<N>,
+{
+    fn process_frame(
+        &mut self,
+        data: &[u8],
+        nsh: &N,
+        ident: <<N as NetStackHandle>::Profile as Profile>::InterfaceIdent,
+    ) -> bool {
+        if let Ok(frame) = <&[u8; LINK_CHALLENGE_SIZE]>::try_from(data)
+            && frame.starts_with(&LINK_CHALLENGE_MAGIC)
+        {
+            self.handle_challenge(frame, nsh, ident);
+            return false;
+        }
+
+        let unknown = self.unknown;
+        if self.open(data).is_none() {
+            // The peer may not have our challenge, or started a session we
+            // don't know about
+            if self.unknown != unknown && self.unknown.is_power_of_two() {
+                self.send_challenge(nsh, ident, false);
+            }
+            return false;
+        }
+        let len = data.len() - AEAD_OVERHEAD;
+        self.inner.process_frame(&self.buf[..len], nsh, ident)
+    }
+
+    fn reset(&mut self) {
+        // The session is kept: the peer's session doesn't restart when the
+        // link does, and old frames must still be rejected. A new challenge
+        // is sent when the link comes up again.
+        self.inner.reset();
+    }
+
+    fn link_up(
+        &mut self,
+        nsh: &N,
+        ident: <<N as NetStackHandle>::Profile as Profile>::InterfaceIdent,
+    ) -> bool {
+        self.attempts = 0;
+        self.unknown = 0;
+        self.send_challenge(nsh, ident.clone(), false);
+        self.inner.link_up(nsh, ident)
+    }
+
+    fn timer_ms(&self) -> Option<u64> {
+        match (self.retry_ms(), self.inner.timer_ms()) {
+            (Some(ours), Some(inner)) => Some(ours.min(inner)),
+            (ours, inner) => ours.or(inner),
+        }
+    }
+
+    fn on_timer(
+        &mut self,
+        nsh: &N,
+        ident: <<N as NetStackHandle>::Profile as Profile>::InterfaceIdent,
+    ) -> bool {
+        // Whichever timer was the shortest
+        match (self.retry_ms(), self.inner.timer_ms()) {
+            (Some(ours), inner) if inner.is_none_or(|inner| ours <= inner) => {
+                debug!("No new link session, sending the challenge again");
+                self.send_challenge(nsh, ident, false);
+                false
+            }
+            _ => self.inner.on_timer(nsh, ident),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        AEAD_OVERHEAD, CHALLENGE_SIZE, COUNTER_SIZE, LinkKey, LinkRole, SESSION_SIZE,
+        START_SESSION_MAGIC,
+    };
+    use crate::{
+        Address, DEFAULT_TTL, FrameKind, HeaderSeq, Priority,
+        interface_manager::{InterfaceSink, utils::framed_stream},
+    };
+    use bbqueue::{
+        BBQueue,
+        traits::{coordination::cas::AtomicCoord, notifier::maitake::MaiNotSpsc, storage::Inline},
+    };
+
+    #[test]
+    fn derived_keys_differ() {
+        let psk = LinkKey::new([7; 32]);
+        let a = psk.derive(&[0; 16]);
+        let b = psk.derive(&[1; 16]);
+        assert_ne!(a.0, b.0);
+        assert_ne!(a.0, psk.0);
+        assert_eq!(a.0, psk.derive(&[0; 16]).0);
+    }
+
+    type Queue = BBQueue<Inline<1024>, AtomicCoord, MaiNotSpsc>;
+
+    /// The link frame passing the peer's challenge to a [`super::Sink`]
+    fn start(challenge: [u8; CHALLENGE_SIZE]) -> Vec<u8> {
+        [&START_SESSION_MAGIC[..], &challenge].concat()
+    }
+
+    #[test]
+    fn seal_and_open() {
+        let queue = Queue::new();
+        let key = LinkKey::new([3; 32]);
+        let mut proc =
+            super::AeadProcessor::<(), 256>::new((), &key, LinkRole::Target, &mut rand::rng());
+        let mut sink = super::Sink::<_, 256>::new(
+            framed_stream::Sink::new_from_handle(&queue, 512),
+            &key,
+            LinkRole::Controller,
+            &mut rand::rng(),
+        );
+        assert_eq!(sink.mtu(), 256 - AEAD_OVERHEAD as u16);
+
+        let hdr = HeaderSeq {
+            src: Address {
+                network_id: 1,
+                node_id: 1,
+                port_id: 2,
+            },
+            dst: Address {
+                network_id: 1,
+                node_id: 2,
+                port_id: 3,
+            },
+            any_all: None,
+            seq_no: 4,
+            kind: FrameKind::ENDPOINT_REQ,
+            ttl: DEFAULT_TTL,
+            prio: Priority::NORMAL,
+        };
+        // Nothing is sent before the receiver's challenge
+        assert!(sink.send_ty(&hdr, &1234u32).is_err());
+        let challenge = proc.challenge();
+        sink.send_link_frame(&start(challenge)).unwrap();
+        sink.send_ty(&hdr, &1234u32).unwrap();
+        sink.send_ty(&hdr, &1234u32).unwrap();
+
+        let cons = queue.framed_consumer();
+        let read = || {
+            let grant = cons.read().unwrap();
+            let frame = grant.to_vec();
+            grant.release();
+            frame
+        };
+        let first = read();
+        let second = read();
+        // Same frame, different counter and ciphertext
+        assert_eq!(first.len(), second.len());
+        assert_ne!(first, second);
+
+        let plain = proc.open(&first).unwrap().to_vec();
+        let frame = crate::wire_frames::de_frame(&plain).unwrap();
+        assert_eq!(frame.hdr.seq_no, 4);
+        assert_eq!(frame.body, Ok(&[0xD2, 0x09][..]));
+
+        // Replayed
+        assert!(proc.open(&first).is_none());
+        // Tampered
+        let mut tampered = second.clone();
+        tampered[20] ^= 1;
+        assert!(proc.open(&tampered).is_none());
+        assert!(proc.open(&second).is_some());
+
+        // Frames sealed by the same role are not accepted, even with the
+        // same key and challenge
+        let mut own =
+            super::AeadProcessor::<(), 256>::new((), &key, LinkRole::Controller, &mut rand::rng());
+        own.challenge = Some(challenge);
+        assert!(own.open(&first).is_none());
+    }
+
+    #[test]
+    fn new_sessions() {
+        let queue = Queue::new();
+        let key = LinkKey::new([3; 32]);
+        let hdr = HeaderSeq {
+            src: Address::unknown(),
+            dst: Address::unknown(),
+            any_all: None,
+            seq_no: 4,
+            kind: FrameKind::ENDPOINT_REQ,
+            ttl: DEFAULT_TTL,
+            prio: Priority::NORMAL,
+        };
+        let cons = queue.framed_consumer();
+        // Seal two frames with a new session, as if the sender restarted and
+        // received `challenge`
+        let restart = |challenge| {
+            let mut sink = super::Sink::<_, 256>::new(
+                framed_stream::Sink::new_from_handle(&queue, 512),
+                &key,
+                LinkRole::Target,
+                &mut rand::rng(),
+            );
+            sink.send_link_frame(&start(challenge)).unwrap();
+            [(); 2].map(|_| {
+                sink.send_ty(&hdr, &1234u32).unwrap();
+                let grant = cons.read().unwrap();
+                let frame = grant.to_vec();
+                grant.release();
+                frame
+            })
+        };
+
+        let mut proc =
+            super::AeadProcessor::<(), 256>::new((), &key, LinkRole::Controller, &mut rand::rng());
+        let challenge = proc.challenge();
+        let [first, _] = restart(challenge);
+        // Both sessions start at counter zero, but with different keys
+        let [unsolicited, _] = restart(challenge);
+        assert_eq!(first[SESSION_SIZE..][..COUNTER_SIZE], [0; COUNTER_SIZE]);
+        assert_eq!(
+            unsolicited[SESSION_SIZE..][..COUNTER_SIZE],
+            [0; COUNTER_SIZE]
+        );
+        assert_ne!(first, unsolicited);
+
+        assert!(proc.open(&first).is_some());
+        assert!(proc.open(&first).is_none());
+        // The challenge was used up, another session can't take over
+        assert!(proc.open(&unsolicited).is_none());
+
+        // The restarted sender is accepted with a new challenge
+        let [second, second_next] = restart(proc.challenge());
+        assert!(proc.open(&second).is_some());
+
+        // The older session, replayed, doesn't displace the live one, even
+        // while a challenge is outstanding
+        assert!(proc.open(&first).is_none());
+        proc.challenge();
+        assert!(proc.open(&first).is_none());
+        assert!(proc.open(&second_next).is_some());
+
+        // A forged session id does not replace the current session
+        let mut forged = second.clone();
+        forged[0] ^= 1;
+        assert!(proc.open(&forged).is_none());
+
+        // After the receiver restarts, recorded sessions are still rejected
+        let mut restarted =
+            super::AeadProcessor::<(), 256>::new((), &key, LinkRole::Controller, &mut rand::rng());
+        restarted.challenge();
+        assert!(restarted.open(&first).is_none());
+        assert!(restarted.open(&second).is_none());
+    }
+}
@@ -0,0 +1,190 @@
+//! Packet Capture
+//!
+//! When routing misbehaves, it helps to see the frames that actually crossed
+//! each interface, like with `tcpdump`. A [`CaptureTap`] is given every frame
+//! sent or received on the interfaces of a profile, along with the interface
+//! ident and the direction of the frame.
+//!
+//! A [`Router`](super::profiles::router::Router) or
+//! [`DirectEdge`](super::profiles::direct_edge::DirectEdge) feeds a tap given
+//! with `with_capture()`, or later with `set_capture()`, for example through
+//! `NetStack::manage_profile()`:
+//!
+//! * on **ingress**, frame processors pass every frame they decode to
+//!   [`Profile::record_ingress`](super::Profile::record_ingress), as
+//!   received, before any address is rewritten
+//! * on **egress**, every frame accepted by the sink of an interface is
+//!   recorded as sent
+//!
+//! Interfaces are identified like in [`filter`](super::filter): by the ident
+//! returned when registering an interface on a `Router` (or
+//! [`UPSTREAM_IDENT`](super::profiles::router::UPSTREAM_IDENT) for its
+//! upstream), and always `0` on a `DirectEdge`.
+//!
+//! Frames are recorded with the [`HeaderEncoding::Standard`] header encoding,
+//! whatever the encoding of the link, so that they can be decoded with
+//! [`de_frame`](crate::wire_frames::de_frame). Link frames, such as
+//! [`LinkHello`](super::hello::LinkHello)s, are not recorded. Other profiles
+//! and transport workers may feed a tap too, with [`record_ty`],
+//! [`record_raw`], [`record_err`] or [`record_frame`].
+//!
+//! Frames are encoded again for the tap, and only their first [`SNAPLEN`]
+//! bytes are recorded.
+//!
+//! With the `std` feature, the [`pcapng`] module writes captures to pcapng
+//! files, which can be read with it again, or opened with Wireshark.
+//!
+//! [`HeaderEncoding::Standard`]: crate::wire_frames::HeaderEncoding::Standard
+
+use postcard::{Serializer, ser_flavors::Flavor};
+use serde::Serialize;
+
+use crate::{
+    HeaderSeq, ProtocolError,
+    interface_manager::filter::Direction,
+    wire_frames::{BorrowedFrame, encode_frame_err, encode_frame_hdr, encode_frame_ty},
+};
+
+#[cfg(feature = "std")]
+pub mod pcapng;
+
+/// The most bytes of a frame that are recorded
+///
+/// Longer frames are truncated, [`CapturedFrame::len`] is still the length
+/// of the whole frame.
+#[cfg(feature = "std")]
+pub const SNAPLEN: usize = 4096;
+/// The most bytes of a frame that are recorded
+///
+/// Longer frames are truncated, [`CapturedFrame::len`] is still the length
+/// of the whole frame.
+#[cfg(not(feature = "std"))]
+pub const SNAPLEN: usize = 256;
+
+/// A frame crossing an interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapturedFrame<'a> {
+    /// The interface the frame crossed
+    pub interface: u8,
+    /// Whether the frame was received or sent
+    pub direction: Direction,
+    /// The frame, at most [`SNAPLEN`] bytes of it
+    pub data: &'a [u8],
+    /// The length of the whole frame
+    pub len: usize,
+}
+
+/// Records the frames crossing the interfaces of a profile
+///
+/// Taps are called while the profile is locked, so they should be quick.
+pub trait CaptureTap: Sync {
+    /// Record one frame
+    fn record(&self, frame: &CapturedFrame<'_>);
+}
+
+/// Record a frame with the body `body`
+pub fn record_ty<T: Serialize>(
+    tap: &dyn CaptureTap,
+    interface: u8,
+    direction: Direction,
+    hdr: &HeaderSeq,
+    body: &T,
+) {
+    let mut buf = [0u8; SNAPLEN];
+    if let Ok(len) = encode_frame_ty(Snap::new(&mut buf), hdr, body) {
+        record(tap, interface, direction, &buf, len);
+    }
+}
+
+/// Record a frame with the serialized body `body`
+pub fn record_raw(
+    tap: &dyn CaptureTap,
+    interface: u8,
+    direction: Direction,
+    hdr: &HeaderSeq,
+    body: &[u8],
+) {
+    let mut buf = [0u8; SNAPLEN];
+    let mut ser = Serializer {
+        output: Snap::new(&mut buf),
+    };
+    if encode_frame_hdr(&mut ser, hdr).is_err() {
+        return;
+    }
+    if let Ok(len) = ser
+        .output
+        .try_extend(body)
+        .and_then(|()| ser.output.finalize())
+    {
+        record(tap, interface, direction, &buf, len);
+    }
+}
+
+/// Record a protocol error frame
+pub fn record_err(
+    tap: &dyn CaptureTap,
+    interface: u8,
+    direction: Direction,
+    hdr: &HeaderSeq,
+    err: ProtocolError,
+) {
+    let mut buf = [0u8; SNAPLEN];
+    if let Ok(len) = encode_frame_err(Snap::new(&mut buf), hdr, err) {
+        record(tap, interface, direction, &buf, len);
+    }
+}
+
+/// Record a decoded frame
+pub fn record_frame(
+    tap: &dyn CaptureTap,
+    interface: u8,
+    direction: Direction,
+    frame: &BorrowedFrame<'_>,
+) {
+    match frame.body {
+        Ok(body) => record_raw(tap, interface, direction, &frame.hdr, body),
+        Err(err) => record_err(tap, interface, direction, &frame.hdr, err),
+    }
+}
+
+fn record(tap: &dyn CaptureTap, interface: u8, direction: Direction, buf: &[u8], len: usize) {
+    tap.record(&CapturedFrame {
+        interface,
+        direction,
+        data: &buf[..len.min(buf.len())],
+        len,
+    });
+}
+
+/// A flavor keeping the start of the serialized data, and counting all of it
+struct Snap<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Snap<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+}
+
+impl Flavor for Snap<'_> {
+    /// The length of the serialized data
+    type Output = usize;
+
+    fn try_push(&mut self, data: u8) -> postcard::Result<()> {
+        self.try_extend(&[data])
+    }
+
+    fn try_extend(&mut self, data: &[u8]) -> postcard::Result<()> {
+        let start = self.len.min(self.buf.len());
+        let kept = data.len().min(self.buf.len() - start);
+        self.buf[start..][..kept].copy_from_slice(&data[..kept]);
+        self.len += data.len();
+        Ok(())
+    }
+
+    fn finalize(self) -> postcard::Result<usize> {
+        Ok(self.len)
+    }
+}
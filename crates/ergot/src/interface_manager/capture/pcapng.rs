@@ -0,0 +1,690 @@
+//! Reading and writing captures as pcapng files
+//!
+//! [`PcapngWriter`] is a [`CaptureTap`] writing every frame to a pcapng file,
+//! with the [`LINKTYPE_ERGOT`] link type. Each interface of the profile gets
+//! its own pcapng interface, named `ergot<ident>`, and each frame is written
+//! as an Enhanced Packet Block, with a timestamp in microseconds and its
+//! direction (inbound or outbound).
+//!
+//! [`PcapngReader`] reads the frames of such a file back, and [`Dissect`]
+//! prints them, using [`de_frame`] and the `Display` impl of [`HeaderSeq`]:
+//!
+//! ```text
+//! 1718000000.123456 if 0 in  (1.2:3 -> 1.1:0; FK:001, SQ:0004): key 0102030405060708, 4 bytes: 01020304
+//! ```
+//!
+//! ```rust,no_run
+//! use std::fs::File;
+//! use ergot::interface_manager::capture::pcapng::{Dissect, PcapngReader, PcapngWriter};
+//!
+//! // Recording, e.g. with `Router::with_capture()`
+//! let writer = PcapngWriter::new(File::create("ergot.pcapng").unwrap()).unwrap();
+//! let tap: &'static PcapngWriter<File> = Box::leak(Box::new(writer));
+//!
+//! // Printing
+//! for packet in PcapngReader::new(File::open("ergot.pcapng").unwrap()) {
+//!     println!("{}", Dissect(&packet.unwrap()));
+//! }
+//! ```
+//!
+//! The `ergot` command-line tool prints captures too, with `ergot dump`.
+
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{Receiver, SyncSender, sync_channel},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::{CaptureTap, CapturedFrame, SNAPLEN};
+use crate::{
+    HeaderSeq, interface_manager::filter::Direction, logging::warn, wire_frames::de_frame,
+};
+
+/// The pcapng link type of ergot frames: `LINKTYPE_USER0`
+///
+/// The user link types are reserved for private use. In Wireshark, frames can
+/// be decoded further by assigning a dissector to it, in the `DLT_USER`
+/// protocol preferences.
+pub const LINKTYPE_ERGOT: u16 = 147;
+
+const SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const ENHANCED_PACKET: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+const OPT_END: u16 = 0;
+const OPT_IF_NAME: u16 = 2;
+const OPT_EPB_FLAGS: u16 = 2;
+
+const EPB_INBOUND: u32 = 0b01;
+const EPB_OUTBOUND: u32 = 0b10;
+
+/// Blocks larger than this are assumed to be corrupted
+const MAX_BLOCK_SIZE: u32 = 16 * 1024 * 1024;
+
+/// The number of frames [`PcapngWriter::new`] queues for its writer thread
+pub const DEFAULT_QUEUE_DEPTH: usize = 256;
+
+/// A [`CaptureTap`] writing frames to a pcapng file
+///
+/// Taps are called while the profile is locked, so frames are not written
+/// there: they are copied to a bounded queue, and written by a thread owning
+/// the file. Frames recorded while the queue is full are dropped, and counted
+/// by [`dropped()`](Self::dropped). A failure to write is logged, and the
+/// frame is lost.
+///
+/// Each frame is written with a single call to `write_all()`, so a file
+/// holds every frame written, even if the program is stopped without
+/// dropping the writer. Frames still queued are lost then: call
+/// [`flush()`](Self::flush) first.
+pub struct PcapngWriter<W: Write + Send + 'static> {
+    queue: SyncSender<Command>,
+    thread: JoinHandle<W>,
+    dropped: AtomicU64,
+}
+
+enum Command {
+    Record(QueuedFrame),
+    Flush(SyncSender<io::Result<()>>),
+}
+
+/// A [`CapturedFrame`] waiting to be written
+struct QueuedFrame {
+    interface: u8,
+    direction: Direction,
+    /// When the frame was recorded, in microseconds since the UNIX epoch
+    micros: u64,
+    data: Vec<u8>,
+    len: usize,
+}
+
+struct WriterState<W> {
+    out: W,
+    /// The ergot interface ident of each pcapng interface
+    interfaces: Vec<u8>,
+    block: Vec<u8>,
+}
+
+impl<W: Write + Send + 'static> PcapngWriter<W> {
+    /// Start a pcapng file, writing its section header to `out`, with a
+    /// queue of [`DEFAULT_QUEUE_DEPTH`] frames
+    pub fn new(out: W) -> io::Result<Self> {
+        Self::with_queue_depth(out, DEFAULT_QUEUE_DEPTH)
+    }
+
+    /// Start a pcapng file, writing its section header to `out`, with a
+    /// queue of `depth` frames
+    pub fn with_queue_depth(mut out: W, depth: usize) -> io::Result<Self> {
+        let mut block = Vec::new();
+        let body = block_start(&mut block, SECTION_HEADER);
+        put_u32(&mut block, BYTE_ORDER_MAGIC);
+        put_u16(&mut block, 1);
+        put_u16(&mut block, 0);
+        // Unknown section length
+        block.extend_from_slice(&(-1i64).to_le_bytes());
+        block_end(&mut block, body);
+        out.write_all(&block)?;
+
+        let state = WriterState {
+            out,
+            interfaces: Vec::new(),
+            block,
+        };
+        let (queue, rx) = sync_channel(depth);
+        let thread = thread::Builder::new()
+            .name("ergot-pcapng".into())
+            .spawn(move || state.run(rx))?;
+        Ok(Self {
+            queue,
+            thread,
+            dropped: AtomicU64::new(0),
+        })
+    }
+
+    /// The number of frames dropped because the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Write the frames queued so far, and flush the underlying writer
+    pub fn flush(&self) -> io::Result<()> {
+        let (tx, rx) = sync_channel(1);
+        self.queue
+            .send(Command::Flush(tx))
+            .map_err(|_| writer_gone())?;
+        rx.recv().map_err(|_| writer_gone())?
+    }
+
+    /// Write the frames queued so far, and return the underlying writer
+    pub fn into_inner(self) -> W {
+        // The thread returns once the queue is closed and empty
+        drop(self.queue);
+        match self.thread.join() {
+            Ok(out) => out,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+impl<W: Write> WriterState<W> {
+    /// Write the frames of `queue` until it is closed
+    fn run(mut self, queue: Receiver<Command>) -> W {
+        for cmd in queue {
+            match cmd {
+                Command::Record(frame) => {
+                    if let Err(_e) = self.record(&frame) {
+                        warn!("Failed to write a captured frame: {:?}", _e);
+                    }
+                }
+                Command::Flush(done) => _ = done.send(self.out.flush()),
+            }
+        }
+        self.out
+    }
+
+    /// The pcapng interface id of the ergot interface `ident`, writing its
+    /// description to the block buffer if it is new
+    fn interface_id(&mut self, ident: u8) -> u32 {
+        if let Some(id) = self.interfaces.iter().position(|i| *i == ident) {
+            return id as u32;
+        }
+        let body = block_start(&mut self.block, INTERFACE_DESCRIPTION);
+        put_u16(&mut self.block, LINKTYPE_ERGOT);
+        put_u16(&mut self.block, 0);
+        put_u32(&mut self.block, SNAPLEN as u32);
+        put_option(
+            &mut self.block,
+            OPT_IF_NAME,
+            format!("ergot{ident}").as_bytes(),
+        );
+        put_u16(&mut self.block, OPT_END);
+        put_u16(&mut self.block, 0);
+        block_end(&mut self.block, body);
+
+        self.interfaces.push(ident);
+        (self.interfaces.len() - 1) as u32
+    }
+
+    fn record(&mut self, frame: &QueuedFrame) -> io::Result<()> {
+        let flags = match frame.direction {
+            Direction::Ingress => EPB_INBOUND,
+            Direction::Egress => EPB_OUTBOUND,
+        };
+
+        self.block.clear();
+        let id = self.interface_id(frame.interface);
+        let body = block_start(&mut self.block, ENHANCED_PACKET);
+        put_u32(&mut self.block, id);
+        put_u32(&mut self.block, (frame.micros >> 32) as u32);
+        put_u32(&mut self.block, frame.micros as u32);
+        put_u32(&mut self.block, frame.data.len() as u32);
+        put_u32(&mut self.block, frame.len as u32);
+        self.block.extend_from_slice(&frame.data);
+        pad(&mut self.block);
+        put_option(&mut self.block, OPT_EPB_FLAGS, &flags.to_le_bytes());
+        put_u16(&mut self.block, OPT_END);
+        put_u16(&mut self.block, 0);
+        block_end(&mut self.block, body);
+
+        self.out.write_all(&self.block)
+    }
+}
+
+impl<W: Write + Send + 'static> CaptureTap for PcapngWriter<W> {
+    fn record(&self, frame: &CapturedFrame<'_>) {
+        let micros = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let frame = QueuedFrame {
+            interface: frame.interface,
+            direction: frame.direction,
+            micros,
+            data: frame.data.to_vec(),
+            len: frame.len,
+        };
+        if self.queue.try_send(Command::Record(frame)).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn writer_gone() -> io::Error {
+    io::Error::other("the pcapng writer thread stopped")
+}
+
+/// Start a block of type `ty`, returning the position of its length
+fn block_start(block: &mut Vec<u8>, ty: u32) -> usize {
+    put_u32(block, ty);
+    let len_pos = block.len();
+    put_u32(block, 0);
+    len_pos
+}
+
+/// End the block whose length is at `len_pos`
+fn block_end(block: &mut Vec<u8>, len_pos: usize) {
+    // The block starts with its type, before the length
+    let len = (block.len() - len_pos + 8) as u32;
+    block[len_pos..][..4].copy_from_slice(&len.to_le_bytes());
+    put_u32(block, len);
+}
+
+fn put_option(block: &mut Vec<u8>, code: u16, value: &[u8]) {
+    put_u16(block, code);
+    put_u16(block, value.len() as u16);
+    block.extend_from_slice(value);
+    pad(block);
+}
+
+fn pad(block: &mut Vec<u8>) {
+    block.resize(block.len().next_multiple_of(4), 0);
+}
+
+fn put_u16(block: &mut Vec<u8>, val: u16) {
+    block.extend_from_slice(&val.to_le_bytes());
+}
+
+fn put_u32(block: &mut Vec<u8>, val: u32) {
+    block.extend_from_slice(&val.to_le_bytes());
+}
+
+/// A frame read from a pcapng file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedPacket {
+    /// The ergot interface the frame crossed
+    pub interface: u8,
+    /// Whether the frame was received or sent, if recorded
+    pub direction: Option<Direction>,
+    /// When the frame was recorded, since the UNIX epoch
+    pub timestamp: Duration,
+    /// The frame, possibly truncated
+    pub data: Vec<u8>,
+    /// The length of the whole frame
+    pub len: usize,
+}
+
+/// Reads the frames of a pcapng file written by [`PcapngWriter`]
+///
+/// Packets of interfaces with a link type other than [`LINKTYPE_ERGOT`] are
+/// skipped, as are blocks other than interface descriptions and enhanced
+/// packets. Files written on big-endian machines are supported.
+pub struct PcapngReader<R: Read> {
+    input: R,
+    big_endian: bool,
+    /// The ergot interface ident of each pcapng interface, or `None` if it
+    /// doesn't carry ergot frames
+    interfaces: Vec<Option<u8>>,
+    /// Microseconds per timestamp unit of each pcapng interface
+    resolutions: Vec<f64>,
+    done: bool,
+}
+
+impl<R: Read> PcapngReader<R> {
+    pub fn new(input: R) -> Self {
+        Self {
+            input,
+            big_endian: false,
+            interfaces: Vec::new(),
+            resolutions: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Read the next packet, or `None` at the end of the file
+    pub fn next_packet(&mut self) -> io::Result<Option<CapturedPacket>> {
+        while let Some((ty, body)) = self.next_block()? {
+            match ty {
+                SECTION_HEADER => {
+                    // A new section has its own interfaces
+                    self.interfaces.clear();
+                    self.resolutions.clear();
+                }
+                INTERFACE_DESCRIPTION => self.interface_description(&body)?,
+                ENHANCED_PACKET => {
+                    if let Some(packet) = self.enhanced_packet(&body)? {
+                        return Ok(Some(packet));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read the next block, returning its type and body
+    fn next_block(&mut self) -> io::Result<Option<(u32, Vec<u8>)>> {
+        let mut head = [0u8; 8];
+        match self.input.read_exact(&mut head[..4]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        self.input.read_exact(&mut head[4..])?;
+
+        // The byte order of a section is given by its header
+        if u32::from_le_bytes(head[..4].try_into().unwrap()) == SECTION_HEADER {
+            let mut magic = [0u8; 4];
+            self.input.read_exact(&mut magic)?;
+            self.big_endian = match magic {
+                m if u32::from_le_bytes(m) == BYTE_ORDER_MAGIC => false,
+                m if u32::from_be_bytes(m) == BYTE_ORDER_MAGIC => true,
+                _ => return Err(invalid("bad byte order magic")),
+            };
+            let len = self.u32(&head[4..]);
+            let body = self.block_body(len, 4)?;
+            return Ok(Some((SECTION_HEADER, body)));
+        }
+
+        let ty = self.u32(&head[..4]);
+        let len = self.u32(&head[4..]);
+        let body = self.block_body(len, 0)?;
+        Ok(Some((ty, body)))
+    }
+
+    /// Read the rest of a block of length `len`, after `read` bytes of its
+    /// body were already read
+    fn block_body(&mut self, len: u32, read: usize) -> io::Result<Vec<u8>> {
+        if !(12..=MAX_BLOCK_SIZE).contains(&len) || !len.is_multiple_of(4) {
+            return Err(invalid("bad block length"));
+        }
+        let mut body = vec![0u8; len as usize - 8 - read];
+        self.input.read_exact(&mut body)?;
+        // Drop the trailing length
+        body.truncate(body.len() - 4);
+        Ok(body)
+    }
+
+    fn interface_description(&mut self, body: &[u8]) -> io::Result<()> {
+        if body.len() < 8 {
+            return Err(invalid("short interface description"));
+        }
+        let link_type = self.u16(&body[..2]);
+        let mut name = None;
+        let mut resolution = 1.0;
+        for (code, value) in self.options(&body[8..]) {
+            match code {
+                OPT_IF_NAME => name = Some(value),
+                // if_tsresol
+                9 if value.len() == 1 => {
+                    let exp = i32::from(value[0] & 0x7F);
+                    let units_per_sec = if value[0] & 0x80 != 0 {
+                        2f64.powi(exp)
+                    } else {
+                        10f64.powi(exp)
+                    };
+                    resolution = 1_000_000.0 / units_per_sec;
+                }
+                _ => {}
+            }
+        }
+
+        // Interfaces not written by `PcapngWriter` are numbered in order
+        let ident = name
+            .and_then(|n| core::str::from_utf8(n).ok())
+            .and_then(|n| n.strip_prefix("ergot"))
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(self.interfaces.len() as u8);
+        self.interfaces
+            .push((link_type == LINKTYPE_ERGOT).then_some(ident));
+        self.resolutions.push(resolution);
+        Ok(())
+    }
+
+    fn enhanced_packet(&mut self, body: &[u8]) -> io::Result<Option<CapturedPacket>> {
+        if body.len() < 20 {
+            return Err(invalid("short enhanced packet"));
+        }
+        let id = self.u32(&body[..4]) as usize;
+        let Some(&interface) = self.interfaces.get(id) else {
+            return Err(invalid("packet of an unknown interface"));
+        };
+        let Some(interface) = interface else {
+            return Ok(None);
+        };
+        let ts = (u64::from(self.u32(&body[4..8])) << 32) | u64::from(self.u32(&body[8..12]));
+        let captured = self.u32(&body[12..16]) as usize;
+        let len = self.u32(&body[16..20]) as usize;
+        let data = body
+            .get(20..20 + captured)
+            .ok_or_else(|| invalid("truncated enhanced packet"))?;
+
+        let mut direction = None;
+        let options = body.get(20 + captured.next_multiple_of(4)..).unwrap_or(&[]);
+        for (code, value) in self.options(options) {
+            if code == OPT_EPB_FLAGS && value.len() == 4 {
+                direction = match self.u32(value) & 0b11 {
+                    EPB_INBOUND => Some(Direction::Ingress),
+                    EPB_OUTBOUND => Some(Direction::Egress),
+                    _ => None,
+                };
+            }
+        }
+
+        let micros = ts as f64 * self.resolutions[id];
+        Ok(Some(CapturedPacket {
+            interface,
+            direction,
+            timestamp: Duration::from_micros(micros as u64),
+            data: data.to_vec(),
+            len,
+        }))
+    }
+
+    /// The options at the start of `data`, as `(code, value)`
+    fn options<'a>(&self, mut data: &'a [u8]) -> Vec<(u16, &'a [u8])> {
+        let mut out = Vec::new();
+        while data.len() >= 4 {
+            let code = self.u16(&data[..2]);
+            let len = self.u16(&data[2..4]) as usize;
+            if code == OPT_END {
+                break;
+            }
+            let Some(value) = data.get(4..4 + len) else {
+                break;
+            };
+            out.push((code, value));
+            data = data.get(4 + len.next_multiple_of(4)..).unwrap_or(&[]);
+        }
+        out
+    }
+
+    fn u16(&self, b: &[u8]) -> u16 {
+        let b = b[..2].try_into().unwrap();
+        if self.big_endian {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        }
+    }
+
+    fn u32(&self, b: &[u8]) -> u32 {
+        let b = b[..4].try_into().unwrap();
+        if self.big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        }
+    }
+}
+
+impl<R: Read> Iterator for PcapngReader<R> {
+    type Item = io::Result<CapturedPacket>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.next_packet().transpose();
+        // Stop after the end of the file, or the first error
+        self.done = !matches!(res, Some(Ok(_)));
+        res
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Prints a [`CapturedPacket`] on one line: its timestamp, interface,
+/// direction, header, key (for frames to the "any" or "all" port) and body
+///
+/// Frames that can't be decoded are printed as hex.
+pub struct Dissect<'a>(pub &'a CapturedPacket);
+
+impl fmt::Display for Dissect<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let packet = self.0;
+        let dir = match packet.direction {
+            Some(Direction::Ingress) => "in ",
+            Some(Direction::Egress) => "out",
+            None => "?  ",
+        };
+        write!(
+            f,
+            "{}.{:06} if {} {dir} ",
+            packet.timestamp.as_secs(),
+            packet.timestamp.subsec_micros(),
+            packet.interface,
+        )?;
+
+        let Some(frame) = de_frame(&packet.data) else {
+            write!(f, "undecodable, {} bytes: ", packet.len)?;
+            return write_hex(f, &packet.data);
+        };
+        write_hdr(f, &frame.hdr)?;
+        match frame.body {
+            Ok(body) => {
+                // The header is never truncated, only the body
+                let len = body.len() + packet.len - packet.data.len();
+                write!(f, ", {len} bytes: ")?;
+                write_hex(f, body)?;
+                if len > body.len() {
+                    f.write_str("...")?;
+                }
+                Ok(())
+            }
+            Err(e) => write!(f, ", error {e:?}"),
+        }
+    }
+}
+
+fn write_hdr(f: &mut fmt::Formatter<'_>, hdr: &HeaderSeq) -> fmt::Result {
+    write!(f, "{hdr}")?;
+    if let Some(apdx) = hdr.any_all.as_ref() {
+        f.write_str(": key ")?;
+        write_hex(f, &apdx.key.0)?;
+    }
+    Ok(())
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, data: &[u8]) -> fmt::Result {
+    data.iter().try_for_each(|b| write!(f, "{b:02x}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Key, Priority, ProtocolError,
+        interface_manager::capture::{record_err, record_raw, record_ty},
+    };
+
+    fn hdr(kind: FrameKind, any_all: Option<AnyAllAppendix>) -> HeaderSeq {
+        HeaderSeq {
+            src: Address {
+                network_id: 1,
+                node_id: 2,
+                port_id: 3,
+            },
+            dst: Address {
+                network_id: 1,
+                node_id: 1,
+                port_id: if any_all.is_some() { 0 } else { 4 },
+            },
+            any_all,
+            seq_no: 5,
+            kind,
+            ttl: DEFAULT_TTL,
+            prio: Priority::NORMAL,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let writer = PcapngWriter::new(Vec::new()).unwrap();
+        let any = AnyAllAppendix {
+            key: Key([1, 2, 3, 4, 5, 6, 7, 8]),
+            nash: None,
+        };
+        record_ty(
+            &writer,
+            0,
+            Direction::Ingress,
+            &hdr(FrameKind::ENDPOINT_REQ, Some(any.clone())),
+            &0x1234u16,
+        );
+        record_raw(
+            &writer,
+            7,
+            Direction::Egress,
+            &hdr(FrameKind::TOPIC_MSG, None),
+            &[0xAA; SNAPLEN + 10],
+        );
+        record_err(
+            &writer,
+            0,
+            Direction::Egress,
+            &hdr(FrameKind::PROTOCOL_ERROR, None),
+            ProtocolError::IseFiltered,
+        );
+        let file = writer.into_inner();
+
+        let packets = PcapngReader::new(file.as_slice())
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(packets.len(), 3);
+
+        assert_eq!(packets[0].interface, 0);
+        assert_eq!(packets[0].direction, Some(Direction::Ingress));
+        let frame = de_frame(&packets[0].data).unwrap();
+        assert_eq!(frame.hdr.any_all, Some(any));
+        assert_eq!(frame.body, Ok(&[0xB4, 0x24][..]));
+        let line = Dissect(&packets[0]).to_string();
+        assert!(
+            line.ends_with(
+                "in  (1.2:3 -> 1.1:0; FK:001, SQ:0005): key 0102030405060708, 2 bytes: b424"
+            ),
+            "{line}"
+        );
+
+        assert_eq!(packets[1].interface, 7);
+        assert_eq!(packets[1].direction, Some(Direction::Egress));
+        assert_eq!(packets[1].data.len(), SNAPLEN);
+        assert!(packets[1].len > SNAPLEN + 10);
+        assert!(Dissect(&packets[1]).to_string().ends_with("aa..."));
+
+        assert_eq!(packets[2].interface, 0);
+        let frame = de_frame(&packets[2].data).unwrap();
+        assert_eq!(frame.body, Err(ProtocolError::IseFiltered));
+    }
+
+    #[test]
+    fn undecodable_frames_are_printed_as_hex() {
+        let packet = CapturedPacket {
+            interface: 3,
+            direction: None,
+            timestamp: Duration::from_micros(1_500_000),
+            data: vec![0xFF, 0xFF],
+            len: 2,
+        };
+        assert_eq!(
+            Dissect(&packet).to_string(),
+            "1.500000 if 3 ?   undecodable, 2 bytes: ffff"
+        );
+    }
+}
@@ -9,6 +9,14 @@
 //! routing devices). Previously this logic existed as separate copies in
 //! each profile; this module unifies them.
 //!
+//! [`EdgePort`] also lowers the `path_mtu` of [`ErgotPathMtuEndpoint`] queries
+//! to the MTU of its sink, so that a query records the smallest MTU along
+//! the path it takes.
+//!
+//! [`EdgePort`] also records every frame accepted by its sink with its
+//! [`CaptureTap`], if it has one, and counts the frames it sends or drops
+//! in its [`InterfaceCounters`].
+//!
 //! [`EdgePort`] does NOT call [`Header::decrement_ttl`] — that is the
 //! responsibility of the calling [`Profile`], which may need to decrement
 //! TTL once for the entire routing decision rather than per-port.
@@ -20,11 +28,16 @@
 use serde::Serialize;
 
 use crate::{
-    Header, HeaderSeq, ProtocolError,
+    FrameKind, Header, HeaderSeq, Key, ProtocolError,
     interface_manager::{
         Interface, InterfaceSendError, InterfaceSink, InterfaceState, SetStateError,
+        capture::{self, CaptureTap},
+        filter::Direction,
     },
     logging::trace,
+    stats::InterfaceCounters,
+    traits::Endpoint,
+    well_known::{ErgotPathMtuEndpoint, PathMtuQuery},
 };
 
 /// Node ID for the central (controller/router) side of a point-to-point link.
@@ -43,6 +56,10 @@ pub struct EdgePort<I: Interface> {
     state: InterfaceState,
     own_node_id: u8,
     other_node_id: u8,
+    /// The tap recording the frames sent, with the ident of this port
+    capture: Option<(&'static dyn CaptureTap, u8)>,
+    /// The frames sent, received or dropped by this port
+    counters: InterfaceCounters,
 }
 
 impl<I: Interface> EdgePort<I> {
@@ -57,6 +74,8 @@ impl<I: Interface> EdgePort<I> {
             state: InterfaceState::Down,
             own_node_id: EDGE_NODE_ID,
             other_node_id: CENTRAL_NODE_ID,
+            capture: None,
+            counters: InterfaceCounters::new(),
         }
     }
 
@@ -71,9 +90,30 @@ impl<I: Interface> EdgePort<I> {
             state,
             own_node_id: CENTRAL_NODE_ID,
             other_node_id: EDGE_NODE_ID,
+            capture: None,
+            counters: InterfaceCounters::new(),
         }
     }
 
+    /// Record the frames sent through this port with `tap`, as sent out of
+    /// the interface `ident`, or stop recording them if `tap` is `None`.
+    pub fn set_capture(&mut self, tap: Option<&'static dyn CaptureTap>, ident: u8) {
+        self.capture = tap.map(|tap| (tap, ident));
+    }
+
+    /// The tap recording the frames sent through this port, if any
+    pub fn capture(&self) -> Option<&'static dyn CaptureTap> {
+        self.capture.map(|(tap, _)| tap)
+    }
+
+    /// The counters of this port, see [`crate::stats`]
+    ///
+    /// The port counts the frames it sends and the frames it drops. Profiles
+    /// count the frames received, and the frames received then dropped.
+    pub fn counters(&self) -> &InterfaceCounters {
+        &self.counters
+    }
+
     /// Returns the current [`InterfaceState`] of this port.
     pub fn state(&self) -> InterfaceState {
         self.state
@@ -99,7 +139,9 @@ impl<I: Interface> EdgePort<I> {
     /// the correct value.
     pub fn set_state(&mut self, state: InterfaceState) -> Result<(), SetStateError> {
         match state {
-            InterfaceState::Down | InterfaceState::Inactive => {
+            InterfaceState::Down
+            | InterfaceState::Inactive
+            | InterfaceState::Incompatible { .. } => {
                 self.state = state;
             }
             InterfaceState::ActiveLocal { node_id } => {
@@ -177,13 +219,38 @@ impl<I: Interface> EdgePort<I> {
         Ok((&mut self.sink, header))
     }
 
+    /// Send a link frame through this port, in any state other than
+    /// [`InterfaceState::Down`].
+    pub fn send_link_frame(&mut self, data: &[u8]) -> Result<(), InterfaceSendError> {
+        if self.state == InterfaceState::Down {
+            return Err(InterfaceSendError::NoRouteToDest);
+        }
+        self.sink
+            .send_link_frame(data)
+            .map_err(|()| InterfaceSendError::InterfaceFull)
+    }
+
     /// Send a serializable message through this port.
     ///
     /// The caller must decrement TTL before calling.
     pub fn send<T: Serialize>(&mut self, hdr: &Header, data: &T) -> Result<(), InterfaceSendError> {
         let (sink, header) = self.common_send(hdr)?;
-        sink.send_ty(&header, data)
-            .map_err(|()| InterfaceSendError::InterfaceFull)
+        if is_path_mtu_query(&header) {
+            let mut buf = [0u8; MAX_PATH_MTU_QUERY_SIZE];
+            let query = postcard::to_slice(data, &mut buf)
+                .ok()
+                .and_then(|body| lower_path_mtu(body, sink.mtu()));
+            if let Some(query) = query {
+                let res = sink.send_ty(&header, &query);
+                self.count_sent(res)?;
+                self.record_ty(&header, &query);
+                return Ok(());
+            }
+        }
+        let res = sink.send_ty(&header, data);
+        self.count_sent(res)?;
+        self.record_ty(&header, data);
+        Ok(())
     }
 
     /// Send a protocol error through this port.
@@ -191,8 +258,12 @@ impl<I: Interface> EdgePort<I> {
     /// The caller must decrement TTL before calling.
     pub fn send_err(&mut self, hdr: &Header, err: ProtocolError) -> Result<(), InterfaceSendError> {
         let (sink, header) = self.common_send(hdr)?;
-        sink.send_err(&header, err)
-            .map_err(|()| InterfaceSendError::InterfaceFull)
+        let res = sink.send_err(&header, err);
+        self.count_sent(res)?;
+        if let Some((tap, ident)) = self.capture {
+            capture::record_err(tap, ident, Direction::Egress, &header, err);
+        }
+        Ok(())
     }
 
     /// Send a pre-serialized (raw) message through this port.
@@ -207,14 +278,77 @@ impl<I: Interface> EdgePort<I> {
         let frame_size = crate::wire_frames::MAX_HDR_ENCODED_SIZE + data.len();
         let iface_mtu = self.sink.mtu() as usize;
         if frame_size > iface_mtu {
-            return Err(InterfaceSendError::PacketTooBig {
+            let err = InterfaceSendError::PacketTooBig {
                 mtu: iface_mtu as u16,
-            });
+            };
+            self.counters.count_drop(&err);
+            return Err(err);
         }
 
         let nshdr: Header = hdr.clone().into();
         let (sink, header) = self.common_send(&nshdr)?;
-        sink.send_raw(&header, data)
-            .map_err(|()| InterfaceSendError::InterfaceFull)
+        if is_path_mtu_query(&header)
+            && let Some(query) = lower_path_mtu(data, sink.mtu())
+        {
+            let mut buf = [0u8; MAX_PATH_MTU_QUERY_SIZE];
+            if let Ok(body) = postcard::to_slice(&query, &mut buf) {
+                let res = sink.send_raw(&header, body);
+                self.count_sent(res)?;
+                self.record_raw(&header, body);
+                return Ok(());
+            }
+        }
+        let res = sink.send_raw(&header, data);
+        self.count_sent(res)?;
+        self.record_raw(&header, data);
+        Ok(())
+    }
+
+    /// Count a frame as sent, with the length returned by the sink, or as
+    /// dropped if the sink was full
+    fn count_sent(&self, res: Result<usize, ()>) -> Result<(), InterfaceSendError> {
+        match res {
+            Ok(len) => {
+                self.counters.count_sent(len);
+                Ok(())
+            }
+            Err(()) => {
+                let err = InterfaceSendError::InterfaceFull;
+                self.counters.count_drop(&err);
+                Err(err)
+            }
+        }
+    }
+
+    /// Record a frame sent through this port, if it has a capture tap
+    fn record_ty<T: Serialize>(&self, hdr: &HeaderSeq, body: &T) {
+        if let Some((tap, ident)) = self.capture {
+            capture::record_ty(tap, ident, Direction::Egress, hdr, body);
+        }
+    }
+
+    /// Record a pre-serialized frame sent through this port, if it has a
+    /// capture tap
+    fn record_raw(&self, hdr: &HeaderSeq, body: &[u8]) {
+        if let Some((tap, ident)) = self.capture {
+            capture::record_raw(tap, ident, Direction::Egress, hdr, body);
+        }
     }
 }
+
+/// The largest encoded size of a [`PathMtuQuery`]
+const MAX_PATH_MTU_QUERY_SIZE: usize = 3;
+
+/// Is this a request for the [`ErgotPathMtuEndpoint`]?
+fn is_path_mtu_query(hdr: &HeaderSeq) -> bool {
+    let key = Key(ErgotPathMtuEndpoint::REQ_KEY.to_bytes());
+    hdr.kind == FrameKind::ENDPOINT_REQ && hdr.any_all.as_ref().is_some_and(|apdx| apdx.key == key)
+}
+
+/// Decode a [`PathMtuQuery`], lowering its `path_mtu` to `mtu`
+fn lower_path_mtu(body: &[u8], mtu: u16) -> Option<PathMtuQuery> {
+    let query: PathMtuQuery = postcard::from_bytes(body).ok()?;
+    Some(PathMtuQuery {
+        path_mtu: query.path_mtu.min(mtu),
+    })
+}
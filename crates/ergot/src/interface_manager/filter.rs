@@ -0,0 +1,442 @@
+//! Packet Filters
+//!
+//! By default, a profile forwards every frame with a routable destination. A
+//! [`Router`](super::profiles::router::Router) or
+//! [`DirectEdge`](super::profiles::direct_edge::DirectEdge) can instead be
+//! given a [`PacketFilter`], using `with_packet_filter()`, which is consulted
+//! for every frame:
+//!
+//! * on **ingress**, when a frame is received on an interface, before it is
+//!   delivered locally or routed
+//! * on **egress**, when a frame is about to be sent out of an interface,
+//!   whether it was sent by this device or is being forwarded
+//!
+//! A denied unicast frame is answered with
+//! [`ProtocolError::IseFiltered`](crate::ProtocolError::IseFiltered), so the
+//! sender doesn't wait for a response that will never come. Denied
+//! broadcasts are dropped silently.
+//!
+//! Interfaces are identified by their interface ident: the ident returned
+//! when registering an interface on a `Router` (or
+//! [`UPSTREAM_IDENT`](super::profiles::router::UPSTREAM_IDENT) for its
+//! upstream), and always `0` on a `DirectEdge`, which only has one interface.
+//!
+//! ## Rule tables
+//!
+//! [`RuleTable`] is a ready-made filter, with a fixed capacity of rules. Each
+//! [`Rule`] matches frames by direction, interface, source and destination
+//! network, [`FrameKind`], and [`Key`]. The verdict of the first matching rule
+//! is used, or the default verdict of the table if no rule matches.
+//!
+//! For example, on a router where the upstream is cloud-facing, and only the
+//! telemetry endpoint may be called from there:
+//!
+//! ```rust
+//! use ergot::{FrameKind, Key, traits::Endpoint};
+//! use ergot::interface_manager::filter::{Rule, RuleTable, Verdict};
+//! use ergot::interface_manager::profiles::router::UPSTREAM_IDENT;
+//!
+//! ergot::endpoint!(TelemetryEndpoint, (), u32, "telemetry");
+//!
+//! let mut table = RuleTable::<4>::new(Verdict::Allow);
+//! table
+//!     .push(
+//!         Rule::allow()
+//!             .ingress()
+//!             .on_interface(UPSTREAM_IDENT)
+//!             .kind(FrameKind::ENDPOINT_REQ)
+//!             .key(Key(TelemetryEndpoint::REQ_KEY.to_bytes())),
+//!     )
+//!     .unwrap();
+//! table
+//!     .push(Rule::deny().ingress().on_interface(UPSTREAM_IDENT).kind(FrameKind::ENDPOINT_REQ))
+//!     .unwrap();
+//! ```
+//!
+//! Or, where devices may reach the upstream, and be reached from it, but may
+//! not reach each other:
+//!
+//! ```rust
+//! use ergot::interface_manager::filter::{Rule, RuleTable, Verdict};
+//! use ergot::interface_manager::profiles::router::UPSTREAM_IDENT;
+//!
+//! let mut table = RuleTable::<4>::new(Verdict::Allow);
+//! table.push(Rule::allow().from_interface(UPSTREAM_IDENT)).unwrap();
+//! table.push(Rule::allow().egress().on_interface(UPSTREAM_IDENT)).unwrap();
+//! table.push(Rule::deny().egress().forwarded()).unwrap();
+//! ```
+//!
+//! Note that the [`Key`] of a frame is only known for frames sent to the
+//! "any" or "all" port: a rule with a key never matches a frame sent to a
+//! specific port.
+
+use crate::{FrameKind, Header, Key};
+
+/// Whether a frame may pass
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    Deny,
+}
+
+/// The direction of a frame, relative to the interface it crosses
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The frame was received on the interface
+    Ingress,
+    /// The frame is about to be sent out of the interface
+    Egress,
+}
+
+/// Where a frame crosses an interface
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hop {
+    /// The frame was received on `interface`
+    Ingress { interface: u8 },
+    /// The frame is about to be sent out of `interface`
+    ///
+    /// `from` is the interface the frame was received on, or `None` if it was
+    /// sent by this device.
+    Egress { interface: u8, from: Option<u8> },
+}
+
+impl Hop {
+    /// The direction of this hop
+    pub fn direction(&self) -> Direction {
+        match self {
+            Hop::Ingress { .. } => Direction::Ingress,
+            Hop::Egress { .. } => Direction::Egress,
+        }
+    }
+
+    /// The interface the frame crosses
+    pub fn interface(&self) -> u8 {
+        match self {
+            Hop::Ingress { interface } | Hop::Egress { interface, .. } => *interface,
+        }
+    }
+}
+
+/// Decides which frames may cross an interface
+pub trait PacketFilter {
+    /// Decide whether the frame with the header `hdr` may cross an interface
+    /// at `hop`
+    fn check(&mut self, hop: Hop, hdr: &Header) -> Verdict;
+}
+
+/// Allows every frame
+///
+/// This is the default filter of every profile.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AllowAll;
+
+impl PacketFilter for AllowAll {
+    fn check(&mut self, _hop: Hop, _hdr: &Header) -> Verdict {
+        Verdict::Allow
+    }
+}
+
+/// One rule of a [`RuleTable`]
+///
+/// A rule matches the frames that match all of its criteria. A rule without
+/// any criteria matches every frame.
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub verdict: Verdict,
+    pub direction: Option<Direction>,
+    /// The interface the frame crosses
+    pub interface: Option<u8>,
+    /// The interface an egress frame was received on. Never matches ingress
+    /// frames, or frames sent by this device.
+    pub from_interface: Option<u8>,
+    /// Whether an egress frame is forwarded, instead of sent by this device.
+    /// Never matches ingress frames.
+    pub forwarded: Option<bool>,
+    pub src_net: Option<u16>,
+    pub dst_net: Option<u16>,
+    pub kind: Option<FrameKind>,
+    pub key: Option<Key>,
+}
+
+impl Rule {
+    /// A rule allowing every frame
+    pub const fn allow() -> Self {
+        Self::new(Verdict::Allow)
+    }
+
+    /// A rule denying every frame
+    pub const fn deny() -> Self {
+        Self::new(Verdict::Deny)
+    }
+
+    const fn new(verdict: Verdict) -> Self {
+        Self {
+            verdict,
+            direction: None,
+            interface: None,
+            from_interface: None,
+            forwarded: None,
+            src_net: None,
+            dst_net: None,
+            kind: None,
+            key: None,
+        }
+    }
+
+    /// Only match frames received on an interface
+    pub const fn ingress(mut self) -> Self {
+        self.direction = Some(Direction::Ingress);
+        self
+    }
+
+    /// Only match frames sent out of an interface
+    pub const fn egress(mut self) -> Self {
+        self.direction = Some(Direction::Egress);
+        self
+    }
+
+    /// Only match frames crossing the interface `ident`
+    pub const fn on_interface(mut self, ident: u8) -> Self {
+        self.interface = Some(ident);
+        self
+    }
+
+    /// Only match egress frames that were received on the interface `ident`
+    pub const fn from_interface(mut self, ident: u8) -> Self {
+        self.from_interface = Some(ident);
+        self
+    }
+
+    /// Only match egress frames that are forwarded from another interface
+    pub const fn forwarded(mut self) -> Self {
+        self.forwarded = Some(true);
+        self
+    }
+
+    /// Only match egress frames that were sent by this device
+    pub const fn local(mut self) -> Self {
+        self.forwarded = Some(false);
+        self
+    }
+
+    /// Only match frames from the network `net_id`
+    pub const fn from_net(mut self, net_id: u16) -> Self {
+        self.src_net = Some(net_id);
+        self
+    }
+
+    /// Only match frames to the network `net_id`
+    pub const fn to_net(mut self, net_id: u16) -> Self {
+        self.dst_net = Some(net_id);
+        self
+    }
+
+    /// Only match frames of the given kind
+    pub const fn kind(mut self, kind: FrameKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Only match frames for the endpoint or topic with the given key
+    pub const fn key(mut self, key: Key) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Does this rule match the frame with the header `hdr`, crossing an
+    /// interface at `hop`?
+    pub fn matches(&self, hop: Hop, hdr: &Header) -> bool {
+        let from = match hop {
+            Hop::Ingress { .. } => None,
+            Hop::Egress { from, .. } => Some(from),
+        };
+        let direction_ok = self.direction.is_none_or(|d| d == hop.direction());
+        let interface_ok = self.interface.is_none_or(|i| i == hop.interface());
+        let from_ok = self
+            .from_interface
+            .is_none_or(|i| from.flatten() == Some(i));
+        let forwarded_ok = self
+            .forwarded
+            .is_none_or(|f| from.is_some_and(|from| from.is_some() == f));
+        let src_ok = self.src_net.is_none_or(|n| n == hdr.src.network_id);
+        let dst_ok = self.dst_net.is_none_or(|n| n == hdr.dst.network_id);
+        let kind_ok = self.kind.is_none_or(|k| k == hdr.kind);
+        let key_ok = self
+            .key
+            .is_none_or(|k| hdr.any_all.as_ref().is_some_and(|aa| aa.key == k));
+
+        direction_ok
+            && interface_ok
+            && from_ok
+            && forwarded_ok
+            && src_ok
+            && dst_ok
+            && kind_ok
+            && key_ok
+    }
+}
+
+/// A filter with up to `N` rules
+///
+/// The verdict of the first matching rule is used, or the default verdict if
+/// no rule matches.
+#[derive(Debug, Clone)]
+pub struct RuleTable<const N: usize> {
+    rules: heapless::Vec<Rule, N>,
+    default: Verdict,
+}
+
+impl<const N: usize> RuleTable<N> {
+    /// Create an empty table, using `default` for frames that match no rule
+    pub const fn new(default: Verdict) -> Self {
+        Self {
+            rules: heapless::Vec::new(),
+            default,
+        }
+    }
+
+    /// Append a rule, after all existing rules
+    ///
+    /// Returns the rule back if the table is full.
+    pub fn push(&mut self, rule: Rule) -> Result<(), Rule> {
+        self.rules.push(rule)
+    }
+
+    /// Remove all rules
+    pub fn clear(&mut self) {
+        self.rules.clear();
+    }
+
+    /// The rules of this table, in the order they are checked
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+}
+
+impl<const N: usize> PacketFilter for RuleTable<N> {
+    fn check(&mut self, hop: Hop, hdr: &Header) -> Verdict {
+        self.rules
+            .iter()
+            .find(|r| r.matches(hop, hdr))
+            .map_or(self.default, |r| r.verdict)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Address, AnyAllAppendix, Priority};
+
+    fn hdr(src_net: u16, dst_net: u16, kind: FrameKind, key: Option<Key>) -> Header {
+        Header {
+            src: Address {
+                network_id: src_net,
+                node_id: 2,
+                port_id: 10,
+            },
+            dst: Address {
+                network_id: dst_net,
+                node_id: 2,
+                port_id: if key.is_some() { 0 } else { 10 },
+            },
+            any_all: key.map(|key| AnyAllAppendix { key, nash: None }),
+            seq_no: None,
+            kind,
+            ttl: crate::DEFAULT_TTL,
+            prio: Priority::NORMAL,
+        }
+    }
+
+    #[test]
+    fn first_match_wins() {
+        const KEY: Key = Key([1; 8]);
+        let mut table = RuleTable::<4>::new(Verdict::Allow);
+        table
+            .push(
+                Rule::allow()
+                    .ingress()
+                    .on_interface(7)
+                    .kind(FrameKind::ENDPOINT_REQ)
+                    .key(KEY),
+            )
+            .unwrap();
+        table
+            .push(
+                Rule::deny()
+                    .ingress()
+                    .on_interface(7)
+                    .kind(FrameKind::ENDPOINT_REQ),
+            )
+            .unwrap();
+
+        let on_7 = Hop::Ingress { interface: 7 };
+        let on_1 = Hop::Ingress { interface: 1 };
+        let req = FrameKind::ENDPOINT_REQ;
+
+        assert_eq!(
+            table.check(on_7, &hdr(1, 2, req, Some(KEY))),
+            Verdict::Allow
+        );
+        assert_eq!(
+            table.check(on_7, &hdr(1, 2, req, Some(Key([2; 8])))),
+            Verdict::Deny
+        );
+        // No key is known for frames to a specific port
+        assert_eq!(table.check(on_7, &hdr(1, 2, req, None)), Verdict::Deny);
+        assert_eq!(table.check(on_1, &hdr(1, 2, req, None)), Verdict::Allow);
+        let resp = FrameKind::ENDPOINT_RESP;
+        assert_eq!(table.check(on_7, &hdr(1, 2, resp, None)), Verdict::Allow);
+    }
+
+    #[test]
+    fn forwarded() {
+        let mut table = RuleTable::<4>::new(Verdict::Allow);
+        table.push(Rule::allow().from_interface(255)).unwrap();
+        table
+            .push(Rule::allow().egress().on_interface(255))
+            .unwrap();
+        table.push(Rule::deny().egress().forwarded()).unwrap();
+
+        let h = hdr(1, 2, FrameKind::TOPIC_MSG, None);
+        let check = |table: &mut RuleTable<4>, interface, from| {
+            table.check(Hop::Egress { interface, from }, &h)
+        };
+        // Device to device
+        assert_eq!(check(&mut table, 1, Some(0)), Verdict::Deny);
+        // Device to upstream, and back
+        assert_eq!(check(&mut table, 255, Some(0)), Verdict::Allow);
+        assert_eq!(check(&mut table, 0, Some(255)), Verdict::Allow);
+        // Sent by the router itself
+        assert_eq!(check(&mut table, 1, None), Verdict::Allow);
+        // Ingress is never "forwarded"
+        assert_eq!(
+            table.check(Hop::Ingress { interface: 1 }, &h),
+            Verdict::Allow
+        );
+    }
+
+    #[test]
+    fn nets_and_default() {
+        let mut table = RuleTable::<2>::new(Verdict::Deny);
+        table.push(Rule::allow().from_net(1).to_net(2)).unwrap();
+        table.push(Rule::allow().local()).unwrap();
+        assert!(table.push(Rule::allow()).is_err());
+
+        let hop = Hop::Egress {
+            interface: 0,
+            from: Some(1),
+        };
+        let kind = FrameKind::TOPIC_MSG;
+        assert_eq!(table.check(hop, &hdr(1, 2, kind, None)), Verdict::Allow);
+        assert_eq!(table.check(hop, &hdr(2, 1, kind, None)), Verdict::Deny);
+        let hop = Hop::Egress {
+            interface: 0,
+            from: None,
+        };
+        assert_eq!(table.check(hop, &hdr(2, 1, kind, None)), Verdict::Allow);
+    }
+}
@@ -0,0 +1,512 @@
+//! Link Hello
+//!
+//! Frames carry no protocol version, so a peer running an incompatible
+//! version of ergot, for example with a different header layout, would have
+//! its frames silently misparsed. To prevent this, both ends of a link may
+//! exchange a [`LinkHello`] when the link comes up, agreeing on a protocol
+//! version and a set of [`Capabilities`] before any frames are processed.
+//!
+//! The exchange is opt-in, by wrapping the [`FrameProcessor`] on both ends of
+//! a link in a [`HelloProcessor`]:
+//!
+//! * When the link comes up (see [`FrameProcessor::link_up`]), each end
+//!   sends its hello. A hello that is not itself a reply is answered, so the
+//!   exchange completes no matter which end comes up first.
+//! * Until a hello has been received, all other frames are dropped. The
+//!   hello is sent again after [`HELLO_RETRY_MS`], doubling after each
+//!   attempt, and when frames are dropped: on the 1st, 2nd, 4th, 8th, ...
+//!   frame. Retransmitting on a timer needs an RxWorker with a timer, see
+//!   [`FrameProcessor::timer_ms`].
+//! * A peer that doesn't answer [`HELLO_ATTEMPTS`] hellos, such as one running
+//!   a version of ergot without link hellos, is refused like an incompatible
+//!   one, with a `peer_version` of 0.
+//! * A peer with a compatible version is accepted, and the link uses the
+//!   lower of the two versions and the capabilities both ends support.
+//! * An incompatible peer is refused: its frames are dropped, and the
+//!   interface is set to [`InterfaceState::Incompatible`].
+//!
+//! ## Wire format
+//!
+//! A hello is sent as a "link frame", without an ergot header, so that any
+//! version can parse it. It consists of [`LINK_HELLO_MAGIC`], followed by the
+//! postcard-serialized [`LinkHello`]. This format MUST NOT change in a way
+//! that older versions can't parse: new fields may only be appended, and are
+//! ignored by older versions.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    interface_manager::{FrameProcessor, InterfaceState, Profile},
+    logging::{debug, info, warn},
+    net_stack::NetStackHandle,
+};
+
+/// The protocol version of this build of ergot
+///
+/// Incremented on every change to the wire format, such as the frame header
+/// layout or the [`ProtocolError`](crate::ProtocolError) variants.
+///
+/// * 1: Initial version
+/// * 2: Added [`ProtocolError::SseUnverified`](crate::ProtocolError::SseUnverified)
+/// * 3: Added admission credentials to seed router and address claim requests,
+///   see [`admission`](super::admission)
+/// * 4: Added [`ProtocolError::IseFiltered`](crate::ProtocolError::IseFiltered)
+pub const PROTOCOL_VERSION: u16 = 4;
+
+/// The oldest protocol version this build of ergot can talk to
+///
+/// This build only speaks the wire format of [`PROTOCOL_VERSION`], and every
+/// version so far changed it in a way that older versions can't parse, so no
+/// older version is accepted. Should a future version keep the old wire
+/// format available, this can be lowered, with the negotiated
+/// [`NegotiatedLink::version`] selecting the format.
+pub const MIN_PROTOCOL_VERSION: u16 = PROTOCOL_VERSION;
+
+/// The start of every [`LinkHello`] frame
+///
+/// Chosen to be very unlikely as the start of a frame in any
+/// [`HeaderEncoding`](crate::wire_frames::HeaderEncoding).
+pub const LINK_HELLO_MAGIC: [u8; 8] = [0xFF, b'e', b'r', b'g', b'o', b't', 0xFF, 0x01];
+
+/// The largest encoded size of a [`LinkHello`] frame
+///
+/// ```text
+/// LINK_HELLO_MAGIC,          [u8; 8]:     8 bytes
+/// LinkHello====================================
+/// version: u16,              u16, varint: 3 bytes
+/// min_version: u16,          u16, varint: 3 bytes
+/// capabilities: u32,         u32, varint: 5 bytes
+/// reply: bool,               bool:        1 byte
+/// ==================================== 20 bytes
+/// ```
+pub const MAX_LINK_HELLO_SIZE: usize = 20;
+
+/// How long to wait for the peer's hello before sending ours again, doubled
+/// after each attempt
+pub const HELLO_RETRY_MS: u64 = 250;
+
+/// How many hellos are sent before giving up on a peer that doesn't answer
+pub const HELLO_ATTEMPTS: u8 = 5;
+
+/// Optional features a peer supports
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Capabilities(pub u32);
+
+impl Capabilities {
+    /// No optional features
+    pub const NONE: Self = Self(0);
+    /// Can decode the [`HeaderEncoding::Compact`] header encoding
+    ///
+    /// [`HeaderEncoding::Compact`]: crate::wire_frames::HeaderEncoding::Compact
+    pub const COMPACT_HEADER: Self = Self(1 << 0);
+    /// Can reassemble [`FrameKind::FRAGMENT`](crate::FrameKind::FRAGMENT)
+    /// frames
+    pub const REASSEMBLY: Self = Self(1 << 1);
+
+    /// All optional features supported by this build of ergot
+    #[cfg(any(feature = "std", feature = "nostd-reassembly"))]
+    pub const SUPPORTED: Self = Self(Self::COMPACT_HEADER.0 | Self::REASSEMBLY.0);
+    /// All optional features supported by this build of ergot
+    #[cfg(not(any(feature = "std", feature = "nostd-reassembly")))]
+    pub const SUPPORTED: Self = Self::COMPACT_HEADER;
+
+    /// Are all features of `other` also in `self`?
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The features in both `self` and `other`
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+/// The hello exchanged by both ends of a link when it comes up
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkHello {
+    /// The newest protocol version the sender supports
+    pub version: u16,
+    /// The oldest protocol version the sender supports
+    pub min_version: u16,
+    /// The optional features the sender supports
+    pub capabilities: Capabilities,
+    /// Is this hello a reply to the receiver's hello?
+    ///
+    /// Replies are not answered.
+    pub reply: bool,
+}
+
+/// The result of a successful hello exchange
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NegotiatedLink {
+    /// The protocol version used on the link
+    pub version: u16,
+    /// The optional features supported by both ends
+    pub capabilities: Capabilities,
+}
+
+/// The peer's supported protocol versions don't overlap with ours
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncompatiblePeer {
+    /// The newest protocol version the peer supports
+    pub peer_version: u16,
+    /// The oldest protocol version the peer supports
+    pub peer_min_version: u16,
+}
+
+impl LinkHello {
+    /// A hello for this build of ergot, offering the given capabilities
+    pub const fn new(capabilities: Capabilities) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            min_version: MIN_PROTOCOL_VERSION,
+            capabilities,
+            reply: false,
+        }
+    }
+
+    /// Agree on the newest protocol version, and the capabilities, supported
+    /// by both `self` and the `peer`
+    pub fn negotiate(&self, peer: &LinkHello) -> Result<NegotiatedLink, IncompatiblePeer> {
+        let version = self.version.min(peer.version);
+        if version < self.min_version.max(peer.min_version) {
+            return Err(IncompatiblePeer {
+                peer_version: peer.version,
+                peer_min_version: peer.min_version,
+            });
+        }
+        Ok(NegotiatedLink {
+            version,
+            capabilities: self.capabilities.intersection(peer.capabilities),
+        })
+    }
+
+    /// Encode the hello as a link frame into `buf`
+    ///
+    /// Returns `None` if `buf` is too small, [`MAX_LINK_HELLO_SIZE`] is always
+    /// large enough.
+    pub fn encode<'a>(&self, buf: &'a mut [u8]) -> Option<&'a [u8]> {
+        let (magic, rest) = buf.split_first_chunk_mut::<8>()?;
+        *magic = LINK_HELLO_MAGIC;
+        let used = postcard::to_slice(self, rest).ok()?.len();
+        Some(&buf[..LINK_HELLO_MAGIC.len() + used])
+    }
+
+    /// Decode a link frame, returns `None` if it is not a hello
+    ///
+    /// Bytes after the hello are ignored, newer versions may append fields.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let rest = data.strip_prefix(&LINK_HELLO_MAGIC)?;
+        let (hello, _) = postcard::take_from_bytes(rest).ok()?;
+        Some(hello)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HelloState {
+    /// Waiting for the peer's hello
+    Pending,
+    Ready(NegotiatedLink),
+    Refused(IncompatiblePeer),
+    /// The peer didn't answer any of our hellos
+    Silent,
+}
+
+/// A [`FrameProcessor`] that exchanges a [`LinkHello`] with the peer, before
+/// passing frames to the wrapped processor
+///
+/// See the [module docs](self) for details.
+pub struct HelloProcessor<P> {
+    inner: P,
+    hello: LinkHello,
+    state: HelloState,
+    /// Hellos sent since the link came up
+    attempts: u8,
+    /// Frames dropped while waiting for the peer's hello
+    dropped: u32,
+}
+
+impl<P> HelloProcessor<P> {
+    /// Wrap `inner`, offering [`Capabilities::SUPPORTED`]
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            hello: LinkHello::new(Capabilities::SUPPORTED),
+            state: HelloState::Pending,
+            attempts: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Offer the given capabilities, instead of [`Capabilities::SUPPORTED`]
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.hello.capabilities = capabilities;
+        self
+    }
+
+    /// The result of the hello exchange, if it completed successfully
+    pub fn link(&self) -> Option<NegotiatedLink> {
+        match self.state {
+            HelloState::Ready(link) => Some(link),
+            _ => None,
+        }
+    }
+
+    /// The wrapped processor
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    fn send_hello<N: NetStackHandle>(
+        &self,
+        nsh: &N,
+        ident: <<N as NetStackHandle>::Profile as Profile>::InterfaceIdent,
+        reply: bool,
+    ) {
+        let mut buf = [0u8; MAX_LINK_HELLO_SIZE];
+        let hello = LinkHello {
+            reply,
+            ..self.hello
+        };
+        let Some(frame) = hello.encode(&mut buf) else {
+            return;
+        };
+        let res = nsh
+            .stack()
+            .manage_profile(|im| im.send_link_frame(ident, frame));
+        if res.is_err() {
+            warn!("Failed to send link hello");
+        }
+    }
+
+    fn handle_hello<N: NetStackHandle>(
+        &mut self,
+        peer: LinkHello,
+        nsh: &N,
+        ident: <<N as NetStackHandle>::Profile as Profile>::InterfaceIdent,
+    ) -> bool {
+        if !peer.reply {
+            self.send_hello(nsh, ident.clone(), true);
+        }
+
+        match self.hello.negotiate(&peer) {
+            Ok(link) => {
+                info!(
+                    "Link hello: using version {}, capabilities {}",
+                    link.version, link.capabilities.0
+                );
+                let was_refused = matches!(self.state, HelloState::Refused(_) | HelloState::Silent);
+                self.state = HelloState::Ready(link);
+                // A peer that was refused before has been updated, let the
+                // inner processor bring the interface up again
+                was_refused
+                    && nsh.stack().manage_profile(|im| {
+                        im.set_interface_state(ident, InterfaceState::Inactive)
+                            .is_ok()
+                    })
+            }
+            Err(incompatible) => {
+                warn!(
+                    "Link hello: refusing peer with versions {}..={}",
+                    incompatible.peer_min_version, incompatible.peer_version
+                );
+                self.state = HelloState::Refused(incompatible);
+                Self::set_incompatible(nsh, ident, incompatible.peer_version)
+            }
+        }
+    }
+
+    /// Send the hello again, or give up on the peer after [`HELLO_ATTEMPTS`]
+    fn retry<N: NetStackHandle>(
+        &mut self,
+        nsh: &N,
+        ident: <<N as NetStackHandle>::Profile as Profile>::InterfaceIdent,
+    ) -> bool {
+        if self.attempts < HELLO_ATTEMPTS {
+            debug!("Link hello: no answer, sending again");
+            self.send_hello(nsh, ident, false);
+            self.attempts += 1;
+            false
+        } else {
+            warn!(
+                "Link hello: refusing peer that didn't answer {} hellos",
+                HELLO_ATTEMPTS
+            );
+            self.state = HelloState::Silent;
+            Self::set_incompatible(nsh, ident, 0)
+        }
+    }
+
+    fn set_incompatible<N: NetStackHandle>(
+        nsh: &N,
+        ident: <<N as NetStackHandle>::Profile as Profile>::InterfaceIdent,
+        peer_version: u16,
+    ) -> bool {
+        nsh.stack().manage_profile(|im| {
+            let state = InterfaceState::Incompatible { peer_version };
+            let changed = im.interface_state(ident.clone()) != Some(state);
+            changed && im.set_interface_state(ident, state).is_ok()
+        })
+    }
+}
+
+impl<N, P> FrameProcessor<N> for HelloProcessor<P>
+where
+    N: NetStackHandle,
+    P: FrameProcessor<N>,
+{
+    fn process_frame(
+        &mut self,
+        data: &[u8],
+        nsh: &N,
+        ident: <<N as NetStackHandle>::Profile as Profile>::InterfaceIdent,
+    ) -> bool {
+        if let Some(peer) = LinkHello::decode(data) {
+            return self.handle_hello(peer, nsh, ident);
+        }
+
+        match self.state {
+            HelloState::Ready(_) => self.inner.process_frame(data, nsh, ident),
+            HelloState::Pending => {
+                debug!("Dropping frame received before link hello");
+                self.dropped = self.dropped.saturating_add(1);
+                // Our hello, or the peer's, may have been lost
+                self.dropped.is_power_of_two() && self.retry(nsh, ident)
+            }
+            HelloState::Refused(_) | HelloState::Silent => false,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state = HelloState::Pending;
+        self.attempts = 0;
+        self.dropped = 0;
+        self.inner.reset();
+    }
+
+    fn link_up(
+        &mut self,
+        nsh: &N,
+        ident: <<N as NetStackHandle>::Profile as Profile>::InterfaceIdent,
+    ) -> bool {
+        self.send_hello(nsh, ident.clone(), false);
+        self.attempts = 1;
+        self.dropped = 0;
+        self.inner.link_up(nsh, ident)
+    }
+
+    fn timer_ms(&self) -> Option<u64> {
+        match self.state {
+            HelloState::Pending if self.attempts > 0 => Some(HELLO_RETRY_MS << (self.attempts - 1)),
+            _ => self.inner.timer_ms(),
+        }
+    }
+
+    fn on_timer(
+        &mut self,
+        nsh: &N,
+        ident: <<N as NetStackHandle>::Profile as Profile>::InterfaceIdent,
+    ) -> bool {
+        match self.state {
+            HelloState::Pending if self.attempts > 0 => self.retry(nsh, ident),
+            _ => self.inner.on_timer(nsh, ident),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Capabilities, IncompatiblePeer, LINK_HELLO_MAGIC, LinkHello, MAX_LINK_HELLO_SIZE,
+        NegotiatedLink, PROTOCOL_VERSION,
+    };
+
+    #[test]
+    fn max_hello_ser_size() {
+        let hello = LinkHello {
+            version: u16::MAX,
+            min_version: u16::MAX,
+            capabilities: Capabilities(u32::MAX),
+            reply: true,
+        };
+        let mut buf = [0u8; MAX_LINK_HELLO_SIZE];
+        let frame = hello.encode(&mut buf).unwrap();
+        assert_eq!(frame.len(), MAX_LINK_HELLO_SIZE);
+        assert_eq!(LinkHello::decode(frame), Some(hello));
+    }
+
+    #[test]
+    fn decode_ignores_appended_fields() {
+        let hello = LinkHello::new(Capabilities::COMPACT_HEADER);
+        let mut buf = [0u8; MAX_LINK_HELLO_SIZE + 4];
+        let len = hello.encode(&mut buf).unwrap().len();
+        assert_eq!(LinkHello::decode(&buf[..len + 4]), Some(hello));
+
+        // Not a hello
+        assert_eq!(LinkHello::decode(&buf[1..len]), None);
+        assert_eq!(LinkHello::decode(&LINK_HELLO_MAGIC), None);
+    }
+
+    #[test]
+    fn negotiate() {
+        let hello = |min_version, version, caps| LinkHello {
+            version,
+            min_version,
+            capabilities: Capabilities(caps),
+            reply: false,
+        };
+
+        // Downgrade to the older peer's version and capabilities
+        assert_eq!(
+            hello(1, 3, 0b11).negotiate(&hello(1, 2, 0b10)),
+            Ok(NegotiatedLink {
+                version: 2,
+                capabilities: Capabilities(0b10),
+            })
+        );
+        assert_eq!(
+            hello(1, 2, 0b10).negotiate(&hello(1, 3, 0b11)),
+            Ok(NegotiatedLink {
+                version: 2,
+                capabilities: Capabilities(0b10),
+            })
+        );
+
+        // No common version
+        assert_eq!(
+            hello(3, 4, 0).negotiate(&hello(1, 2, 0)),
+            Err(IncompatiblePeer {
+                peer_version: 2,
+                peer_min_version: 1,
+            })
+        );
+        assert_eq!(
+            hello(1, 2, 0).negotiate(&hello(3, 4, 0)),
+            Err(IncompatiblePeer {
+                peer_version: 4,
+                peer_min_version: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn older_wire_formats_are_refused() {
+        let older = LinkHello {
+            version: PROTOCOL_VERSION - 1,
+            min_version: 1,
+            capabilities: Capabilities::NONE,
+            reply: false,
+        };
+        assert_eq!(
+            LinkHello::new(Capabilities::NONE).negotiate(&older),
+            Err(IncompatiblePeer {
+                peer_version: PROTOCOL_VERSION - 1,
+                peer_min_version: 1,
+            })
+        );
+    }
+}
@@ -4,7 +4,7 @@
 
 use crate::interface_manager::{
     Interface,
-    utils::{cobs_stream, std::StdQueue},
+    utils::{cobs_stream, priority, std::StdQueue},
 };
 
 /// An interface implementation for generic tokio async streams
@@ -13,3 +13,11 @@ pub struct TokioStreamInterface {}
 impl Interface for TokioStreamInterface {
     type Sink = cobs_stream::Sink<StdQueue>;
 }
+
+/// An interface implementation for generic tokio async streams, with `P`
+/// priority queues
+pub struct TokioStreamPrioInterface<const P: usize> {}
+
+impl<const P: usize> Interface for TokioStreamPrioInterface<P> {
+    type Sink = priority::Sink<cobs_stream::Sink<StdQueue>, P>;
+}
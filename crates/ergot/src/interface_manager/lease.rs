@@ -0,0 +1,193 @@
+//! Refresh-token leases
+//!
+//! The lease model shared by everything that hands out a resource for a
+//! limited time: seed-assigned net_ids and bus node_id claims in the
+//! [`Router`], and remote topic subscriptions in the
+//! [`Publisher`](crate::net_stack::publisher::Publisher).
+//!
+//! A lease is granted for [`INITIAL_LEASE_SECS`] along with a random refresh
+//! token. The holder may refresh it once fewer than [`MIN_REFRESH_SECS`] are
+//! left, which extends it to [`MAX_LEASE_SECS`] and rotates the token.
+//!
+//! [`Router`]: crate::interface_manager::profiles::router::Router
+
+// See `profiles::router` for why `web-time` is used
+#[cfg(feature = "std")]
+use web_time::{Duration, Instant};
+
+#[cfg(all(not(feature = "std"), feature = "nostd-seed-router"))]
+use embassy_time::{Duration, Instant};
+
+use crate::well_known::LeaseEntryState;
+
+/// Initial duration of a newly granted lease (seconds).
+pub(crate) const INITIAL_LEASE_SECS: u16 = 30;
+/// Maximum lease duration after refresh (seconds).
+pub(crate) const MAX_LEASE_SECS: u16 = 120;
+/// Refresh is allowed only when remaining time is less than this (seconds).
+pub(crate) const MIN_REFRESH_SECS: u16 = 62;
+
+pub(crate) fn remaining_lease_seconds(expiration: Instant, now: Instant) -> u16 {
+    let remaining = expiration - now;
+    let whole_seconds = remaining.as_secs();
+    let rounded_up =
+        whole_seconds.saturating_add(u64::from(remaining > Duration::from_secs(whole_seconds)));
+    rounded_up.min(u16::MAX as u64) as u16
+}
+
+/// Tombstone duration — how long a revoked net_id/node_id stays reserved,
+/// measured from its lease expiration, before it can be reused (seconds).
+pub(crate) const TOMBSTONE_DURATION_SECS: u64 = 30;
+
+/// A granted lease: when it expires, and the token required to refresh it.
+#[derive(Clone, Copy)]
+pub(crate) struct Lease {
+    pub(crate) expiration: Instant,
+    pub(crate) refresh_token: u64,
+    /// The immediately previous token remains valid only for replaying a lost
+    /// refresh response. A successful refresh replaces this replay slot.
+    pub(crate) previous_refresh_token: Option<u64>,
+}
+
+/// The state of a leased resource.
+#[derive(Clone, Copy)]
+pub(crate) enum LeaseKind {
+    /// An active lease.
+    Active(Lease),
+    /// Expired, but the key stays reserved until `clear_time` (the grace
+    /// period) so it isn't reused while a stale peer might still use it.
+    Tombstone { clear_time: Instant },
+}
+
+/// Why a [`LeaseKind::refresh`] was rejected.
+pub(crate) enum RefreshDenied {
+    /// Already expired (and now tombstoned).
+    Expired,
+    /// The presented refresh token doesn't match.
+    BadToken,
+    /// Too early to refresh.
+    TooSoon,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenMatch {
+    Current,
+    Replay,
+}
+
+impl LeaseKind {
+    /// A fresh active lease expiring `secs` from `now`.
+    pub(crate) fn active(now: Instant, secs: u16, token: u64) -> Self {
+        LeaseKind::Active(Lease {
+            expiration: now + Duration::from_secs(secs as u64),
+            refresh_token: token,
+            previous_refresh_token: None,
+        })
+    }
+
+    /// `true` if this is an active lease that hasn't expired yet.
+    pub(crate) fn is_active(&self, now: Instant) -> bool {
+        matches!(self, LeaseKind::Active(l) if l.expiration > now)
+    }
+
+    /// The state of this lease, as reported by a [`RouterEntry`], or `None`
+    /// if it would be dropped
+    ///
+    /// Expired leases are reported like [`gc_retain`](Self::gc_retain) would
+    /// leave them, without changing them.
+    ///
+    /// [`RouterEntry`]: crate::well_known::RouterEntry
+    pub(crate) fn report(&self, now: Instant) -> Option<LeaseEntryState> {
+        let mut current = *self;
+        if !current.gc_retain(now) {
+            return None;
+        }
+        Some(match current {
+            LeaseKind::Active(Lease { expiration, .. }) => LeaseEntryState::Active {
+                remaining_secs: remaining_lease_seconds(expiration, now),
+            },
+            LeaseKind::Tombstone { clear_time } => LeaseEntryState::Tombstone {
+                clear_secs: remaining_lease_seconds(clear_time, now),
+            },
+        })
+    }
+
+    /// [`heapless::Vec::retain_mut`] predicate: an expired active lease becomes
+    /// a tombstone (grace anchored to the expiration), and a tombstone whose
+    /// grace period has elapsed is dropped.
+    pub(crate) fn gc_retain(&mut self, now: Instant) -> bool {
+        match *self {
+            LeaseKind::Active(Lease { expiration, .. }) => {
+                if now >= expiration {
+                    let clear_time = expiration + Duration::from_secs(TOMBSTONE_DURATION_SECS);
+                    if now >= clear_time {
+                        false
+                    } else {
+                        *self = LeaseKind::Tombstone { clear_time };
+                        true
+                    }
+                } else {
+                    true
+                }
+            }
+            LeaseKind::Tombstone { clear_time } => clear_time > now,
+        }
+    }
+
+    /// Validate a current refresh token or, when enabled, the immediately
+    /// previous token used to replay a lost response. Expiry handling and
+    /// token ordering live here for every seed state-machine path.
+    pub(crate) fn validate_token(
+        &mut self,
+        req_token: u64,
+        now: Instant,
+        allow_replay: bool,
+    ) -> Result<TokenMatch, RefreshDenied> {
+        match self {
+            LeaseKind::Tombstone { .. } => Err(RefreshDenied::Expired),
+            LeaseKind::Active(lease) => {
+                let token_match = if lease.refresh_token == req_token {
+                    TokenMatch::Current
+                } else if allow_replay && lease.previous_refresh_token == Some(req_token) {
+                    TokenMatch::Replay
+                } else {
+                    return Err(RefreshDenied::BadToken);
+                };
+                if now >= lease.expiration {
+                    *self = LeaseKind::Tombstone {
+                        clear_time: lease.expiration + Duration::from_secs(TOMBSTONE_DURATION_SECS),
+                    };
+                    return Err(RefreshDenied::Expired);
+                }
+                Ok(token_match)
+            }
+        }
+    }
+
+    /// Refresh an active lease: verify the token, reject if expired (tombstoning
+    /// it) or too soon, otherwise extend to `MAX_LEASE_SECS` and rotate
+    /// the token to `new_token`. Returns the renewed lease and whether this
+    /// was an idempotent replay rather than a new extension.
+    pub(crate) fn refresh(
+        &mut self,
+        req_token: u64,
+        now: Instant,
+        new_token: u64,
+        allow_replay: bool,
+    ) -> Result<(Lease, bool), RefreshDenied> {
+        let token_match = self.validate_token(req_token, now, allow_replay)?;
+        let LeaseKind::Active(lease) = self else {
+            unreachable!("successful validation guarantees an active lease")
+        };
+        if token_match == TokenMatch::Replay {
+            return Ok((*lease, true));
+        }
+        if lease.expiration - now > Duration::from_secs(MIN_REFRESH_SECS as u64) {
+            return Err(RefreshDenied::TooSoon);
+        }
+        lease.expiration = now + Duration::from_secs(MAX_LEASE_SECS as u64);
+        lease.previous_refresh_token = allow_replay.then_some(lease.refresh_token);
+        lease.refresh_token = new_token;
+        Ok((*lease, false))
+    }
+}
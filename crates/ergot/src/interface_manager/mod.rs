@@ -34,14 +34,30 @@
 //!
 //! [`NetStack`]: crate::NetStack
 
-use crate::{Header, HeaderSeq, ProtocolError};
+use crate::{
+    Header, HeaderSeq, ProtocolError,
+    stats::InterfaceStats,
+    well_known::{RouterEntry, RouterQuery, SubscriptionAdvertisement},
+    wire_frames::BorrowedFrame,
+};
 use postcard_schema::Schema;
 use serde::{Deserialize, Serialize};
 
+pub mod admission;
+#[cfg(feature = "link-aead")]
+pub mod aead;
+pub mod capture;
 pub(crate) mod edge_port;
+pub mod filter;
+pub mod hello;
 pub mod interface_impls;
+#[cfg(any(feature = "std", feature = "nostd-seed-router"))]
+pub(crate) mod lease;
 pub mod multi;
 pub mod profiles;
+#[cfg(any(feature = "std", feature = "nostd-seed-router"))]
+pub mod rate_limit;
+pub mod subscriptions;
 pub mod transports;
 pub mod utils;
 
@@ -75,6 +91,48 @@ pub trait FrameProcessor<N: crate::net_stack::NetStackHandle> {
     /// discovered state (e.g., net_id) so that the next frame triggers
     /// re-discovery.
     fn reset(&mut self);
+
+    /// Called by the transport RxWorker when the link comes up: before the
+    /// first frame is received, and again after each [`reset()`](Self::reset).
+    ///
+    /// Processors that exchange link frames with the peer, such as
+    /// [`HelloProcessor`](hello::HelloProcessor), send them here. Returns
+    /// `true` if the interface state changed. Does nothing by default.
+    fn link_up(
+        &mut self,
+        nsh: &N,
+        ident: <<N as crate::net_stack::NetStackHandle>::Profile as Profile>::InterfaceIdent,
+    ) -> bool {
+        _ = nsh;
+        _ = ident;
+        false
+    }
+
+    /// How many milliseconds the processor wants to wait for a frame before
+    /// [`on_timer()`](Self::on_timer) is called, if it is waiting for one
+    ///
+    /// Asked again before each wait, so receiving a frame restarts the timer.
+    /// Used by processors that retransmit link frames, such as
+    /// [`HelloProcessor`](hello::HelloProcessor). RxWorkers without a timer
+    /// never call `on_timer()`. Returns `None` by default.
+    fn timer_ms(&self) -> Option<u64> {
+        None
+    }
+
+    /// Called by the transport RxWorker once [`timer_ms()`](Self::timer_ms)
+    /// have passed without a frame being received
+    ///
+    /// Returns `true` if the interface state changed. Does nothing by
+    /// default.
+    fn on_timer(
+        &mut self,
+        nsh: &N,
+        ident: <<N as crate::net_stack::NetStackHandle>::Profile as Profile>::InterfaceIdent,
+    ) -> bool {
+        _ = nsh;
+        _ = ident;
+        false
+    }
 }
 
 pub trait ConstInit {
@@ -142,6 +200,9 @@ pub enum SeedAssignmentError {
     DelegationDepthExceeded,
     /// The parent assigned a net_id already used by this bridge
     NetIdCollision,
+    /// The requesting device was not admitted by the seed router's
+    /// [`AdmissionPolicy`](admission::AdmissionPolicy)
+    NotAdmitted(admission::AdmissionError),
 }
 
 /// A successful node_id claim assignment from a router on a bus-style interface
@@ -177,6 +238,9 @@ pub enum AddressClaimError {
     /// The candidate node_id is reserved and cannot be claimed
     /// (0 = "any", CENTRAL_NODE_ID, EDGE_NODE_ID, 255 = broadcast)
     InvalidNodeId,
+    /// The requesting device was not admitted by the router's
+    /// [`AdmissionPolicy`](admission::AdmissionPolicy)
+    NotAdmitted(admission::AdmissionError),
 }
 
 /// An error occurred when refreshing a node_id claim
@@ -257,6 +321,90 @@ pub trait Profile {
         source: Self::InterfaceIdent,
     ) -> Result<(), InterfaceSendError>;
 
+    /// Ask the packet filter whether a frame received on `source` may be
+    /// processed, see [`filter`]
+    ///
+    /// Called by frame processors before a received frame is delivered
+    /// locally or routed. Returns [`InterfaceSendError::Filtered`] if the
+    /// frame is denied, or [`InterfaceSendError::RateLimited`] if it should
+    /// be dropped. The default implementation allows every frame.
+    fn filter_ingress(
+        &mut self,
+        hdr: &Header,
+        source: Self::InterfaceIdent,
+    ) -> Result<(), InterfaceSendError> {
+        _ = (hdr, source);
+        Ok(())
+    }
+
+    /// Record a frame received on `source`, in the counters of the
+    /// interface (see [`stats`](crate::stats)) and with the capture tap (see
+    /// [`capture`])
+    ///
+    /// Called by frame processors with every frame they decode, as received,
+    /// before any address is rewritten, and the length `len` of the frame it
+    /// was decoded from. Frames that aren't dropped early are recorded in the
+    /// same lock as [`filter_ingress`](Self::filter_ingress). The default
+    /// implementation does nothing.
+    fn record_ingress(
+        &mut self,
+        source: Self::InterfaceIdent,
+        frame: &BorrowedFrame<'_>,
+        len: usize,
+    ) {
+        _ = (source, frame, len);
+    }
+
+    /// Send a [`SubscriptionAdvertisement`] to each direct neighbor, see
+    /// [`subscriptions`]
+    ///
+    /// `local` contains the topics subscribed to on this device. The default
+    /// implementation does nothing.
+    fn advertise_subscriptions(&mut self, local: &SubscriptionAdvertisement) {
+        _ = local;
+    }
+
+    /// Learn the topics subscribed to behind the interface `source`, from an
+    /// advertisement received on it
+    ///
+    /// Called by frame processors, instead of delivering the advertisement.
+    /// The default implementation ignores it.
+    fn learn_subscriptions(
+        &mut self,
+        source: Self::InterfaceIdent,
+        advertisement: &SubscriptionAdvertisement,
+    ) {
+        _ = (source, advertisement);
+    }
+
+    /// The counters of the `index`-th interface, see [`stats`](crate::stats)
+    ///
+    /// Returns None once `index` is past the last interface. The default
+    /// implementation has no interfaces.
+    fn interface_stats(&mut self, index: usize) -> Option<InterfaceStats> {
+        _ = index;
+        None
+    }
+
+    /// One entry of the state of a router, see [`RouterQuery`]
+    ///
+    /// Returns None once the index of the query is past the last entry. The
+    /// default implementation is not a router, and has no entries.
+    fn router_state(&mut self, query: &RouterQuery) -> Option<RouterEntry> {
+        _ = query;
+        None
+    }
+
+    /// The node_id that interfaces fill in as the source of messages sent by
+    /// this device, see [`crate::signing`]
+    ///
+    /// Returns None if it is not known, or differs between interfaces. The
+    /// default implementation returns None.
+    #[cfg(feature = "signing")]
+    fn own_node_id(&mut self) -> Option<u8> {
+        None
+    }
+
     /// Obtain the interface state of the given interface ident
     ///
     /// Returns None if the given ident is unknown by the Profile
@@ -269,15 +417,34 @@ pub trait Profile {
         state: InterfaceState,
     ) -> Result<(), SetStateError>;
 
+    /// Send a link frame, such as a [`LinkHello`](hello::LinkHello), out of
+    /// the given interface
+    ///
+    /// Link frames have no ergot header, and are never routed. They may be
+    /// sent in any interface state other than [`InterfaceState::Down`].
+    fn send_link_frame(
+        &mut self,
+        ident: Self::InterfaceIdent,
+        data: &[u8],
+    ) -> Result<(), InterfaceSendError> {
+        _ = ident;
+        _ = data;
+        Err(InterfaceSendError::NoRouteToDest)
+    }
+
     /// Request a Net ID assignment from this profile
     ///
     /// For Profiles that are not (currently acting as) a Seed Router, this method will always return
     /// an error.
+    ///
+    /// `credentials` are the [`Credentials`](admission::Credentials) sent by
+    /// the requesting device, if any, see [`admission`].
     fn request_seed_net_assign(
         &mut self,
         source_net: u16,
+        credentials: Option<&admission::Credentials>,
     ) -> Result<SeedNetAssignment, SeedAssignmentError> {
-        _ = source_net;
+        _ = (source_net, credentials);
         Err(SeedAssignmentError::ProfileCantSeed)
     }
 
@@ -298,16 +465,33 @@ pub trait Profile {
     ///
     /// `source_net` is the net_id of the interface the request came from.
     /// `candidate` is the requested node_id, `nonce` is a random tiebreaker.
+    /// `credentials` are the [`Credentials`](admission::Credentials) sent by
+    /// the requesting device, if any, see [`admission`].
     fn request_node_claim(
         &mut self,
         source_net: u16,
         candidate: u8,
         nonce: u64,
+        credentials: Option<&admission::Credentials>,
     ) -> Result<NodeClaimAssignment, AddressClaimError> {
-        _ = (source_net, candidate, nonce);
+        _ = (source_net, candidate, nonce, credentials);
         Err(AddressClaimError::NotSupported)
     }
 
+    /// The credentials this device sends when requesting a node_id claim, or
+    /// a Net ID assignment from an upstream seed router
+    ///
+    /// `challenge` is set when repeating a request that was answered with
+    /// [`AdmissionError::ChallengeRequired`](admission::AdmissionError::ChallengeRequired).
+    /// The default implementation has no identity, and sends no credentials.
+    fn admission_credentials(
+        &mut self,
+        challenge: Option<[u8; 8]>,
+    ) -> Option<admission::Credentials> {
+        _ = challenge;
+        None
+    }
+
     /// Refresh an existing node_id claim.
     fn refresh_node_claim(
         &mut self,
@@ -367,9 +551,17 @@ pub trait Profile {
     /// [`register_delegated_seed_net`] would, so a doomed request is rejected
     /// without stranding an upstream lease (which only frees on expiry).
     ///
+    /// The requester's `credentials` are checked here too, as in
+    /// [`request_seed_net_assign`].
+    ///
     /// [`register_delegated_seed_net`]: Profile::register_delegated_seed_net
-    fn can_delegate_seed(&mut self, source_net: u16) -> Result<(), SeedAssignmentError> {
-        _ = source_net;
+    /// [`request_seed_net_assign`]: Profile::request_seed_net_assign
+    fn can_delegate_seed(
+        &mut self,
+        source_net: u16,
+        credentials: Option<&admission::Credentials>,
+    ) -> Result<(), SeedAssignmentError> {
+        _ = (source_net, credentials);
         Err(SeedAssignmentError::ProfileCantSeed)
     }
 
@@ -492,9 +684,26 @@ pub trait InterfaceSink {
     /// the max reassembled size, not the raw link frame size.
     fn mtu(&self) -> u16;
 
-    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, body: &T) -> Result<(), ()>;
-    fn send_raw(&mut self, hdr: &HeaderSeq, body: &[u8]) -> Result<(), ()>;
-    fn send_err(&mut self, hdr: &HeaderSeq, err: ProtocolError) -> Result<(), ()>;
+    /// Send a frame with the body `body`
+    ///
+    /// Returns the length of the frame as encoded, header included, before
+    /// any link framing such as COBS or encryption. Interfaces count it in
+    /// their [`stats`](crate::stats).
+    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, body: &T) -> Result<usize, ()>;
+    /// Send a frame with the serialized body `body`, like
+    /// [`send_ty`](Self::send_ty)
+    fn send_raw(&mut self, hdr: &HeaderSeq, body: &[u8]) -> Result<usize, ()>;
+    /// Send a protocol error frame, like [`send_ty`](Self::send_ty)
+    fn send_err(&mut self, hdr: &HeaderSeq, err: ProtocolError) -> Result<usize, ()>;
+
+    /// Send a link frame, which has no ergot header, as one frame
+    ///
+    /// Returns an error by default, for interfaces that don't support link
+    /// frames.
+    fn send_link_frame(&mut self, data: &[u8]) -> Result<(), ()> {
+        _ = data;
+        Err(())
+    }
 }
 
 #[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
@@ -518,6 +727,10 @@ pub enum InterfaceSendError {
     RoutingLoop,
     /// The serialized frame exceeds the outgoing interface's MTU
     PacketTooBig { mtu: u16 },
+    /// A packet filter denied the frame, see [`filter`]
+    Filtered,
+    /// A rate limiter dropped the received frame
+    RateLimited,
 }
 
 /// An error when deregistering an interface
@@ -529,7 +742,7 @@ pub enum DeregisterError {
 }
 
 #[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Schema)]
 pub enum InterfaceState {
     // Missing sink, no net id
     Down,
@@ -539,6 +752,9 @@ pub enum InterfaceState {
     ActiveLocal { node_id: u8 },
     // Has sink, has net id
     Active { net_id: u16, node_id: u8 },
+    // Has sink, but the peer speaks an incompatible protocol version. The
+    // version is 0 if the peer never sent a link hello.
+    Incompatible { peer_version: u16 },
 }
 
 /// Configuration for opt-in liveness tracking.
@@ -586,6 +802,9 @@ impl InterfaceSendError {
             InterfaceSendError::PacketTooBig { mtu } => {
                 ProtocolError::IsePacketTooBig { mtu: *mtu }
             }
+            InterfaceSendError::Filtered => ProtocolError::IseFiltered,
+            // Not sent, see `rate_limit`, so it doesn't need a variant of its own
+            InterfaceSendError::RateLimited => ProtocolError::IseFiltered,
         }
     }
 }
@@ -64,7 +64,7 @@ macro_rules! multi_interface {
                 &mut self,
                 hdr: &$crate::HeaderSeq,
                 body: &T,
-            ) -> Result<(), ()> {
+            ) -> Result<usize, ()> {
                 match self {
                     $( Self::$variant(s) => s.send_ty(hdr, body), )+
                 }
@@ -74,7 +74,7 @@ macro_rules! multi_interface {
                 &mut self,
                 hdr: &$crate::HeaderSeq,
                 body: &[u8],
-            ) -> Result<(), ()> {
+            ) -> Result<usize, ()> {
                 match self {
                     $( Self::$variant(s) => s.send_raw(hdr, body), )+
                 }
@@ -84,11 +84,17 @@ macro_rules! multi_interface {
                 &mut self,
                 hdr: &$crate::HeaderSeq,
                 err: $crate::ProtocolError,
-            ) -> Result<(), ()> {
+            ) -> Result<usize, ()> {
                 match self {
                     $( Self::$variant(s) => s.send_err(hdr, err), )+
                 }
             }
+
+            fn send_link_frame(&mut self, data: &[u8]) -> Result<(), ()> {
+                match self {
+                    $( Self::$variant(s) => s.send_link_frame(data), )+
+                }
+            }
         }
     };
 }
@@ -10,7 +10,7 @@
 //! any outgoing packets, rather than trying to determine whether that packet is
 //! actually routable to a node on the network.
 
-use crate::logging::{debug, warn};
+use crate::logging::{debug, trace, warn};
 
 use serde::Serialize;
 
@@ -25,12 +25,21 @@ pub type EmbassyUsbManager<Q> =
     DirectEdge<crate::interface_manager::interface_impls::embassy_usb::EmbassyInterface<Q>>;
 
 use crate::{
-    Header, HeaderSeq, ProtocolError,
+    Address, FrameKind, Header, HeaderSeq, ProtocolError,
     interface_manager::{
-        Interface, InterfaceSendError, InterfaceState, Profile, SetStateError, edge_port::EdgePort,
+        Interface, InterfaceSendError, InterfaceState, Profile, SetStateError,
+        admission::{Credentials, DeviceIdentity},
+        capture::{self, CaptureTap},
+        edge_port::EdgePort,
+        filter::{AllowAll, Direction, Hop, PacketFilter, Verdict},
+        profiles::reply_err,
+        subscriptions,
     },
-    net_stack::NetStackHandle,
-    wire_frames::de_frame,
+    net_stack::{NetStackHandle, NetStackSendError},
+    socket::SocketSendError,
+    stats::InterfaceStats,
+    well_known::SubscriptionAdvertisement,
+    wire_frames::{BorrowedFrame, HeaderEncoding},
 };
 
 pub use crate::interface_manager::edge_port::{CENTRAL_NODE_ID, EDGE_NODE_ID};
@@ -41,12 +50,20 @@ pub enum SetNetIdError {
 }
 
 /// Edge device profile backed by a single `EdgePort`.
-pub struct DirectEdge<I: Interface> {
+///
+/// Every frame sent or received is checked by the [`PacketFilter`] `F`, see
+/// [`DirectEdge::with_packet_filter`], and may be recorded by a
+/// [`CaptureTap`], see [`DirectEdge::with_capture`]. The interface of a
+/// `DirectEdge` is always identified as `0` by the filter and the tap.
+pub struct DirectEdge<I: Interface, F: PacketFilter = AllowAll> {
     port: EdgePort<I>,
     /// Closer for signaling workers to stop. Set by `register_*_stream`,
     /// closed when the interface transitions to `Down`.
     #[cfg(feature = "std")]
     closer: Option<std::sync::Arc<maitake_sync::WaitQueue>>,
+    /// Sent to the router when claiming a node_id on a bus
+    identity: Option<DeviceIdentity>,
+    filter: F,
 }
 
 impl<I: Interface> DirectEdge<I> {
@@ -55,6 +72,8 @@ impl<I: Interface> DirectEdge<I> {
             port: EdgePort::new_target(sink),
             #[cfg(feature = "std")]
             closer: None,
+            identity: None,
+            filter: AllowAll,
         }
     }
 
@@ -63,9 +82,46 @@ impl<I: Interface> DirectEdge<I> {
             port: EdgePort::new_controller(sink, state),
             #[cfg(feature = "std")]
             closer: None,
+            identity: None,
+            filter: AllowAll,
         }
     }
 
+    /// Check every frame sent or received with `filter`, see
+    /// [`filter`](crate::interface_manager::filter)
+    ///
+    /// This replaces any previous filter.
+    pub fn with_packet_filter<F2: PacketFilter>(self, filter: F2) -> DirectEdge<I, F2> {
+        DirectEdge {
+            port: self.port,
+            #[cfg(feature = "std")]
+            closer: self.closer,
+            identity: self.identity,
+            filter,
+        }
+    }
+}
+
+impl<I: Interface, F: PacketFilter> DirectEdge<I, F> {
+    /// Record every frame sent or received with `tap`, see [`capture`]
+    pub fn with_capture(mut self, tap: &'static dyn CaptureTap) -> Self {
+        self.set_capture(Some(tap));
+        self
+    }
+
+    /// Start recording every frame sent or received with `tap`, or stop
+    /// recording them if `tap` is `None`
+    pub fn set_capture(&mut self, tap: Option<&'static dyn CaptureTap>) {
+        self.port.set_capture(tap, 0);
+    }
+
+    /// Present `identity` when claiming a node_id on a bus, see
+    /// [`admission`](crate::interface_manager::admission)
+    pub fn with_identity(mut self, identity: DeviceIdentity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
     /// Tear down the interface: stop any running workers and transition to `Down`.
     ///
     /// Call this before re-opening a transport to ensure the old workers
@@ -90,12 +146,37 @@ impl<I: Interface> DirectEdge<I> {
     }
 }
 
-impl<I: Interface> Profile for DirectEdge<I> {
+impl<I: Interface, F: PacketFilter> DirectEdge<I, F> {
+    /// The packet filter of this edge, e.g. to change its rules
+    pub fn packet_filter_mut(&mut self) -> &mut F {
+        &mut self.filter
+    }
+
+    /// Ask the packet filter whether `hdr` may be sent out
+    fn filter_egress(&mut self, hdr: &Header) -> Result<(), InterfaceSendError> {
+        let hop = Hop::Egress {
+            interface: 0,
+            from: None,
+        };
+        match self.filter.check(hop, hdr) {
+            Verdict::Allow => Ok(()),
+            Verdict::Deny => {
+                debug!("{}: denied by packet filter on egress", hdr);
+                let err = InterfaceSendError::Filtered;
+                self.port.counters().count_drop(&err);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl<I: Interface, F: PacketFilter> Profile for DirectEdge<I, F> {
     type InterfaceIdent = ();
 
     fn send<T: Serialize>(&mut self, hdr: &Header, data: &T) -> Result<(), InterfaceSendError> {
         let mut hdr = hdr.clone();
         hdr.decrement_ttl()?;
+        self.filter_egress(&hdr)?;
         self.port.send(&hdr, data)
     }
 
@@ -106,10 +187,12 @@ impl<I: Interface> Profile for DirectEdge<I> {
         source: Option<Self::InterfaceIdent>,
     ) -> Result<(), InterfaceSendError> {
         if source.is_some() {
-            return Err(InterfaceSendError::RoutingLoop);
+            // As a DirectEdge, we don't forward, so a received error is for us
+            return Err(InterfaceSendError::DestinationLocal);
         }
         let mut hdr = hdr.clone();
         hdr.decrement_ttl()?;
+        self.filter_egress(&hdr)?;
         self.port.send_err(&hdr, err)
     }
 
@@ -124,6 +207,46 @@ impl<I: Interface> Profile for DirectEdge<I> {
         Err(InterfaceSendError::RoutingLoop)
     }
 
+    fn filter_ingress(&mut self, hdr: &Header, _source: ()) -> Result<(), InterfaceSendError> {
+        match self.filter.check(Hop::Ingress { interface: 0 }, hdr) {
+            Verdict::Allow => Ok(()),
+            Verdict::Deny => {
+                debug!("{}: denied by packet filter on ingress", hdr);
+                let err = InterfaceSendError::Filtered;
+                self.port.counters().count_drop(&err);
+                Err(err)
+            }
+        }
+    }
+
+    fn record_ingress(&mut self, _source: (), frame: &BorrowedFrame<'_>, len: usize) {
+        self.port.counters().count_received(len);
+        if let Some(tap) = self.port.capture() {
+            capture::record_frame(tap, 0, Direction::Ingress, frame);
+        }
+    }
+
+    fn interface_stats(&mut self, index: usize) -> Option<InterfaceStats> {
+        (index == 0).then(|| self.port.counters().snapshot(0))
+    }
+
+    fn advertise_subscriptions(&mut self, local: &SubscriptionAdvertisement) {
+        if let Err(_e) = self
+            .port
+            .send(&subscriptions::advertisement_header(), local)
+        {
+            trace!("Couldn't advertise subscriptions: {:?}", _e);
+        }
+    }
+
+    #[cfg(feature = "signing")]
+    fn own_node_id(&mut self) -> Option<u8> {
+        match self.port.state() {
+            InterfaceState::Active { node_id, .. } => Some(node_id),
+            _ => None,
+        }
+    }
+
     fn interface_state(&mut self, _ident: ()) -> Option<InterfaceState> {
         Some(self.port.state())
     }
@@ -135,6 +258,14 @@ impl<I: Interface> Profile for DirectEdge<I> {
     ) -> Result<(), SetStateError> {
         self.port.set_state(state)
     }
+
+    fn send_link_frame(&mut self, _ident: (), data: &[u8]) -> Result<(), InterfaceSendError> {
+        self.port.send_link_frame(data)
+    }
+
+    fn admission_credentials(&mut self, challenge: Option<[u8; 8]>) -> Option<Credentials> {
+        self.identity.as_ref()?.credentials(challenge)
+    }
 }
 
 /// Frame processor for `DirectEdge` profile.
@@ -171,6 +302,7 @@ pub struct EdgeFrameProcessor {
     /// reactivate that ID, but the window stays open until an addressed frame
     /// confirms or replaces it.
     rediscovering: bool,
+    encoding: HeaderEncoding,
 }
 
 impl EdgeFrameProcessor {
@@ -181,6 +313,7 @@ impl EdgeFrameProcessor {
             own_node: EDGE_NODE_ID,
             activated: false,
             rediscovering: false,
+            encoding: HeaderEncoding::Standard,
         }
     }
 
@@ -191,8 +324,15 @@ impl EdgeFrameProcessor {
             own_node: CENTRAL_NODE_ID,
             activated: false,
             rediscovering: false,
+            encoding: HeaderEncoding::Standard,
         }
     }
+
+    /// Decode frames with the given [`HeaderEncoding`], instead of
+    /// [`HeaderEncoding::Standard`]
+    pub fn with_header_encoding(self, encoding: HeaderEncoding) -> Self {
+        Self { encoding, ..self }
+    }
 }
 
 impl Default for EdgeFrameProcessor {
@@ -237,7 +377,7 @@ pub fn process_frame<N>(
 where
     N: NetStackHandle,
 {
-    let Some(mut frame) = de_frame(data) else {
+    let Some(mut frame) = state.encoding.de_frame(data) else {
         warn!(
             "Decode error! Ignoring frame on net_id {}",
             state.net_id.unwrap_or(0)
@@ -247,6 +387,13 @@ where
 
     debug!("{}: Got Frame!", frame.hdr);
 
+    // The frame as received, recorded along with the packet filter check
+    // below, or when dropping it before
+    let received = BorrowedFrame {
+        hdr: frame.hdr.clone(),
+        body: frame.body,
+    };
+
     let mut state_changed = false;
 
     // Discovery / reactivation window: open after construction or reset()
@@ -310,11 +457,16 @@ where
                 }
                 // Bus-style local-only state is managed by the claim protocol.
                 InterfaceState::ActiveLocal { .. } => state.activated = true,
+                // Refused by a link hello, the peer's frames are not trusted
+                // to carry a net_id.
+                InterfaceState::Incompatible { .. } => {}
             }
             Ok::<(), &'static str>(())
         });
         if let Err(_message) = discovery {
             warn!("{}, dropping", _message);
+            nsh.stack()
+                .manage_profile(|im| im.record_ingress(ident.clone(), &received, data.len()));
             return state_changed;
         }
     }
@@ -329,6 +481,8 @@ where
     {
         if frame.hdr.src.node_id == 0 {
             warn!("Dropping frame with src node_id 0 (stale or local packet received remotely)");
+            nsh.stack()
+                .manage_profile(|im| im.record_ingress(ident.clone(), &received, data.len()));
             return state_changed;
         }
         if frame.hdr.src.node_id == state.own_node {
@@ -336,6 +490,8 @@ where
                 "Dropping frame with src node_id {} (spoofed as us)",
                 state.own_node
             );
+            nsh.stack()
+                .manage_profile(|im| im.record_ingress(ident.clone(), &received, data.len()));
             return state_changed;
         }
 
@@ -347,18 +503,59 @@ where
     //
     // If the dest is 0, should we rewrite the dest as self.net_id? This
     // is the opposite as above, but I dunno how that will work with responses
-    let res = match frame.body {
-        Ok(body) => nsh.stack().send_raw(&frame.hdr, body, ident),
-        Err(e) => {
-            // send_err requires a Header instead of a HeaderSeq, so we convert it
-            let nshdr: Header = frame.hdr.clone().into();
-            nsh.stack().send_err(&nshdr, e, Some(ident))
-        }
+    // send_err and the packet filter require a Header instead of a HeaderSeq,
+    // so we convert it
+    let nshdr: Header = frame.hdr.clone().into();
+    let res = match nsh.stack().manage_profile(|im| {
+        im.record_ingress(ident.clone(), &received, data.len());
+        im.filter_ingress(&nshdr, ident.clone())
+    }) {
+        Err(e) => Err(NetStackSendError::InterfaceSend(e)),
+        Ok(()) => match frame.body {
+            // Advertisements are only meant for this device
+            Ok(body) if subscriptions::is_advertisement(&frame.hdr) => {
+                if let Some(adv) = subscriptions::decode(body) {
+                    nsh.stack()
+                        .manage_profile(|im| im.learn_subscriptions(ident, &adv));
+                }
+                Ok(())
+            }
+            Ok(body) => nsh.stack().send_raw(&frame.hdr, body, ident),
+            Err(e) => nsh.stack().send_err(&nshdr, e, Some(ident)),
+        },
     };
 
     #[allow(unused_variables)]
     match res {
         Ok(()) => {}
+        Err(NetStackSendError::InterfaceSend(InterfaceSendError::Filtered))
+            if frame.hdr.dst.port_id != 255 && frame.hdr.kind != FrameKind::PROTOCOL_ERROR =>
+        {
+            // Tell the sender, instead of letting it wait for a response
+            warn!("{}: denied by packet filter", frame.hdr);
+            reply_err(nsh, &frame.hdr, frame.hdr.dst, ProtocolError::IseFiltered);
+        }
+        Err(NetStackSendError::InterfaceSend(InterfaceSendError::TtlExpired))
+            if frame.hdr.dst.port_id != 255 && frame.hdr.kind != FrameKind::PROTOCOL_ERROR =>
+        {
+            // Only a router forwards frames: this is the upstream of a
+            // bridge. Tell the sender where the frame was dropped, from our
+            // address on this interface
+            warn!("{}: TTL expired", frame.hdr);
+            let src = Address {
+                network_id: state.net_id.unwrap_or(0),
+                node_id: state.own_node,
+                port_id: 0,
+            };
+            reply_err(nsh, &frame.hdr, src, ProtocolError::IseTtlExpired);
+        }
+        Err(NetStackSendError::SocketSend(SocketSendError::Unverified))
+            if frame.hdr.kind == FrameKind::ENDPOINT_REQ =>
+        {
+            // Tell the client, instead of letting it wait for a response
+            warn!("{}: rejected unverified request", frame.hdr);
+            reply_err(nsh, &frame.hdr, frame.hdr.dst, ProtocolError::SseUnverified);
+        }
         Err(e) => {
             // TODO: match on error, potentially try to send NAK?
             warn!("send error: {:?}", e);
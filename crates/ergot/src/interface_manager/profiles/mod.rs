@@ -8,3 +8,31 @@ pub mod null;
 
 #[cfg(any(feature = "std", feature = "nostd-seed-router"))]
 pub mod router;
+
+use crate::{
+    Address, DEFAULT_TTL, FrameKind, Header, HeaderSeq, ProtocolError, net_stack::NetStackHandle,
+};
+
+/// Tell the sender of the received frame `hdr` why it was dropped, with
+/// `err` sent from `src`
+///
+/// Used by frame processors instead of letting the sender wait for a
+/// response. The error originates here, so it may go back out the interface
+/// the frame was received on.
+pub(crate) fn reply_err<N: NetStackHandle>(
+    nsh: &N,
+    hdr: &HeaderSeq,
+    src: Address,
+    err: ProtocolError,
+) {
+    let err_hdr = Header {
+        src,
+        dst: hdr.src,
+        any_all: None,
+        seq_no: Some(hdr.seq_no),
+        kind: FrameKind::PROTOCOL_ERROR,
+        ttl: DEFAULT_TTL,
+        prio: hdr.prio,
+    };
+    let _ = nsh.stack().send_err(&err_hdr, err, None);
+}
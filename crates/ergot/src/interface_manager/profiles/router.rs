@@ -22,27 +22,33 @@ use rand_core::RngCore;
 use serde::Serialize;
 
 use crate::{
-    Header, HeaderSeq, ProtocolError,
+    Address, Header, HeaderSeq, Key, ProtocolError,
     interface_manager::{
         AddressClaimError, AddressRefreshError, DelegatedRefreshPreparation, Interface,
         InterfaceSendError, InterfaceState, NodeClaimAssignment, Profile, SeedAssignmentError,
         SeedLease, SeedNetAssignment, SeedRefreshError, SetStateError,
+        admission::{AdmissionError, AdmissionPolicy, Credentials, DeviceIdentity, OpenAdmission},
+        capture::{self, CaptureTap},
         edge_port::{CENTRAL_NODE_ID, EDGE_NODE_ID, EdgePort},
+        filter::{AllowAll, Direction, Hop, PacketFilter, Verdict},
+        lease::{
+            INITIAL_LEASE_SECS, LeaseKind, MAX_LEASE_SECS, MIN_REFRESH_SECS, RefreshDenied,
+            TOMBSTONE_DURATION_SECS, TokenMatch, remaining_lease_seconds,
+        },
+        profiles::reply_err,
+        rate_limit::{RateLimiter, Unlimited},
+        subscriptions::{self, MAX_ADVERTISED_KEYS},
     },
     logging::{debug, trace, warn},
     net_stack::NetStackHandle,
-    wire_frames::de_frame,
+    stats::{InterfaceCounters, InterfaceStats},
+    well_known::{
+        NodeClaimEntry, RouterEntry, RouterQuery, SeedRouteEntry, SlotEntry,
+        SubscriptionAdvertisement, UpstreamEntry,
+    },
+    wire_frames::{BorrowedFrame, HeaderEncoding},
 };
 
-// These lease parameters are shared by seed-route and node-claim leases (both
-// stored in `LeaseTable`), hence the representation-neutral names.
-/// Initial lease duration for a newly granted seed net_id or node_id (seconds).
-const INITIAL_LEASE_SECS: u16 = 30;
-/// Maximum lease duration after refresh (seconds).
-const MAX_LEASE_SECS: u16 = 120;
-/// Refresh is allowed only when remaining time is less than this (seconds).
-const MIN_REFRESH_SECS: u16 = 62;
-
 /// Each delegation hop hands its downstream a `min_refresh_seconds` smaller
 /// by this margin, so a child's refresh always lands inside the window where
 /// the parent's own upstream refresh is accepted.
@@ -62,160 +68,35 @@ fn delegated_assignment(
     }
 }
 
-fn remaining_lease_seconds(expiration: Instant, now: Instant) -> u16 {
-    let remaining = expiration - now;
-    let whole_seconds = remaining.as_secs();
-    let rounded_up = whole_seconds.saturating_add(u64::from(
-        remaining > Duration::from_secs(whole_seconds),
-    ));
-    rounded_up.min(u16::MAX as u64) as u16
-}
-
-/// Tombstone duration — how long a revoked net_id/node_id stays reserved,
-/// measured from its lease expiration, before it can be reused (seconds).
-const TOMBSTONE_DURATION_SECS: u64 = 30;
-
 /// A directly connected downstream interface slot.
 struct Slot<I: Interface> {
     ident: u8,
     port: EdgePort<I>,
     net_id: u16,
+    interest: Option<Interest>,
     #[cfg(feature = "std")]
     closer: Option<std::sync::Arc<maitake_sync::WaitQueue>>,
 }
 
-// ---------------------------------------------------------------------------
-// Lease lifecycle (representation-agnostic, shared by seed routes and claims)
-// ---------------------------------------------------------------------------
-
-/// A granted lease: when it expires, and the token required to refresh it.
-#[derive(Clone, Copy)]
-struct Lease {
+/// The topics subscribed to behind an interface, learned from a complete
+/// [`SubscriptionAdvertisement`]
+struct Interest {
+    keys: heapless::Vec<Key, MAX_ADVERTISED_KEYS>,
     expiration: Instant,
-    refresh_token: u64,
-    /// The immediately previous token remains valid only for replaying a lost
-    /// refresh response. A successful refresh replaces this replay slot.
-    previous_refresh_token: Option<u64>,
-}
-
-/// The state of a leased resource.
-#[derive(Clone, Copy)]
-enum LeaseKind {
-    /// An active lease.
-    Active(Lease),
-    /// Expired, but the key stays reserved until `clear_time` (the grace
-    /// period) so it isn't reused while a stale peer might still use it.
-    Tombstone { clear_time: Instant },
-}
-
-/// Why a [`LeaseKind::refresh`] was rejected.
-enum RefreshDenied {
-    /// Already expired (and now tombstoned).
-    Expired,
-    /// The presented refresh token doesn't match.
-    BadToken,
-    /// Too early to refresh.
-    TooSoon,
-}
-
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum TokenMatch {
-    Current,
-    Replay,
 }
 
-impl LeaseKind {
-    /// A fresh active lease expiring `secs` from `now`.
-    fn active(now: Instant, secs: u16, token: u64) -> Self {
-        LeaseKind::Active(Lease {
-            expiration: now + Duration::from_secs(secs as u64),
-            refresh_token: token,
-            previous_refresh_token: None,
-        })
-    }
-
-    /// `true` if this is an active lease that hasn't expired yet.
-    fn is_active(&self, now: Instant) -> bool {
-        matches!(self, LeaseKind::Active(l) if l.expiration > now)
-    }
-
-    /// [`heapless::Vec::retain_mut`] predicate: an expired active lease becomes
-    /// a tombstone (grace anchored to the expiration), and a tombstone whose
-    /// grace period has elapsed is dropped.
-    fn gc_retain(&mut self, now: Instant) -> bool {
-        match *self {
-            LeaseKind::Active(Lease { expiration, .. }) => {
-                if now >= expiration {
-                    let clear_time = expiration + Duration::from_secs(TOMBSTONE_DURATION_SECS);
-                    if now >= clear_time {
-                        false
-                    } else {
-                        *self = LeaseKind::Tombstone { clear_time };
-                        true
-                    }
-                } else {
-                    true
-                }
-            }
-            LeaseKind::Tombstone { clear_time } => clear_time > now,
-        }
-    }
-
-    /// Validate a current refresh token or, when enabled, the immediately
-    /// previous token used to replay a lost response. Expiry handling and
-    /// token ordering live here for every seed state-machine path.
-    fn validate_token(
-        &mut self,
-        req_token: u64,
-        now: Instant,
-        allow_replay: bool,
-    ) -> Result<TokenMatch, RefreshDenied> {
-        match self {
-            LeaseKind::Tombstone { .. } => Err(RefreshDenied::Expired),
-            LeaseKind::Active(lease) => {
-                let token_match = if lease.refresh_token == req_token {
-                    TokenMatch::Current
-                } else if allow_replay && lease.previous_refresh_token == Some(req_token) {
-                    TokenMatch::Replay
-                } else {
-                    return Err(RefreshDenied::BadToken);
-                };
-                if now >= lease.expiration {
-                    *self = LeaseKind::Tombstone {
-                        clear_time: lease.expiration + Duration::from_secs(TOMBSTONE_DURATION_SECS),
-                    };
-                    return Err(RefreshDenied::Expired);
-                }
-                Ok(token_match)
-            }
-        }
-    }
-
-    /// Refresh an active lease: verify the token, reject if expired (tombstoning
-    /// it) or too soon, otherwise extend to `MAX_LEASE_SECS` and rotate
-    /// the token to `new_token`. Returns the renewed lease and whether this
-    /// was an idempotent replay rather than a new extension.
-    fn refresh(
-        &mut self,
-        req_token: u64,
-        now: Instant,
-        new_token: u64,
-        allow_replay: bool,
-    ) -> Result<(Lease, bool), RefreshDenied> {
-        let token_match = self.validate_token(req_token, now, allow_replay)?;
-        let LeaseKind::Active(lease) = self else {
-            unreachable!("successful validation guarantees an active lease")
-        };
-        if token_match == TokenMatch::Replay {
-            return Ok((*lease, true));
-        }
-        if lease.expiration - now > Duration::from_secs(MIN_REFRESH_SECS as u64) {
-            return Err(RefreshDenied::TooSoon);
+/// Should the broadcast `hdr` be sent to an interface with `interest`?
+///
+/// Only topic broadcasts are pruned. They are still flooded to interfaces
+/// where the subscriptions are unknown, or the advertisement expired.
+fn interested(interest: &Option<Interest>, hdr: &Header, now: Instant) -> bool {
+    match (interest, &hdr.any_all) {
+        (Some(interest), Some(aa))
+            if interest.expiration > now && hdr.kind == crate::FrameKind::TOPIC_MSG =>
+        {
+            interest.keys.contains(&aa.key)
         }
-        lease.expiration = now + Duration::from_secs(MAX_LEASE_SECS as u64);
-        lease.previous_refresh_token = allow_replay.then_some(lease.refresh_token);
-        lease.refresh_token = new_token;
-        Ok((*lease, false))
+        _ => true,
     }
 }
 
@@ -335,6 +216,7 @@ impl<K: Copy + Eq, X, const N: usize> LeaseTable<K, X, N> {
 /// The upstream interface port (bridge mode only).
 struct UpstreamPort<I: Interface> {
     port: EdgePort<I>,
+    interest: Option<Interest>,
     #[cfg(feature = "std")]
     closer: Option<std::sync::Arc<maitake_sync::WaitQueue>>,
 }
@@ -362,16 +244,38 @@ pub const UPSTREAM_IDENT: u8 = u8::MAX;
 /// - `N`: Maximum number of directly connected downstream interfaces
 /// - `S`: Maximum number of seed-assigned routes (for bridge downstream networks)
 /// - `C`: Maximum number of bus-style node_id claims (address claim protocol)
+/// - `A`: [`AdmissionPolicy`] deciding which devices are granted a seed
+///   net_id or node_id claim, see [`Router::with_admission_policy`]
+/// - `F`: [`PacketFilter`] deciding which frames may cross each interface,
+///   see [`Router::with_packet_filter`]
+/// - `L`: [`RateLimiter`] deciding which received frames are dropped, see
+///   [`Router::with_rate_limiter`]
 ///
 /// **Root mode** (`new`/`new_std`): no upstream, acts as a seed router.
 /// **Bridge mode** (`new_bridge`): has an upstream interface, forwards
 /// unroutable traffic upstream. The upstream discovers its net_id from
 /// incoming frames (like a DirectEdge).
 ///
+/// The frames crossing every interface may be recorded by a [`CaptureTap`],
+/// see [`Router::with_capture`].
+///
+/// Its interfaces and lease tables are reported by
+/// [`Profile::router_state`], for example to remote devices through
+/// `Services::router_handler()`.
+///
 /// Works on both `std` and `no_std` (with `nostd-seed-router` feature).
 ///
 /// [`multi_interface!`]: crate::multi_interface
-pub struct Router<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize = 0> {
+pub struct Router<
+    I: Interface,
+    R: RngCore,
+    const N: usize,
+    const S: usize,
+    const C: usize = 0,
+    A: AdmissionPolicy = OpenAdmission,
+    F: PacketFilter = AllowAll,
+    L: RateLimiter = Unlimited,
+> {
     slots: heapless::Vec<Slot<I>, N>,
     /// Seed-assigned routes. Key = assigned net_id, scope = requesting
     /// source net_id, extra = routing metadata and optional parent lease.
@@ -380,6 +284,13 @@ pub struct Router<I: Interface, R: RngCore, const N: usize, const S: usize, cons
     node_claims: LeaseTable<u8, u64, C>,
     rng: R,
     upstream: Option<UpstreamPort<I>>,
+    admission: A,
+    /// Sent to the upstream seed router when requesting a net_id
+    identity: Option<DeviceIdentity>,
+    filter: F,
+    limiter: L,
+    /// Records the frames crossing every interface
+    capture: Option<&'static dyn CaptureTap>,
 }
 
 /// Errors from [`Router::register_interface`].
@@ -413,6 +324,11 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize>
             node_claims: LeaseTable::new(),
             rng,
             upstream: None,
+            admission: OpenAdmission,
+            identity: None,
+            filter: AllowAll,
+            limiter: Unlimited,
+            capture: None,
         }
     }
 
@@ -429,12 +345,193 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize>
             rng,
             upstream: Some(UpstreamPort {
                 port: EdgePort::new_target(upstream_sink),
+                interest: None,
                 #[cfg(feature = "std")]
                 closer: None,
             }),
+            admission: OpenAdmission,
+            identity: None,
+            filter: AllowAll,
+            limiter: Unlimited,
+            capture: None,
+        }
+    }
+}
+
+impl<
+    I: Interface,
+    R: RngCore,
+    const N: usize,
+    const S: usize,
+    const C: usize,
+    A: AdmissionPolicy,
+    F: PacketFilter,
+    L: RateLimiter,
+> Router<I, R, N, S, C, A, F, L>
+{
+    /// Only grant seed net_ids and node_id claims to the devices admitted by
+    /// `policy`, see [`admission`](crate::interface_manager::admission)
+    ///
+    /// This replaces any previous policy. Requests from devices that are not
+    /// admitted fail with `NotAdmitted`.
+    pub fn with_admission_policy<A2: AdmissionPolicy>(
+        self,
+        policy: A2,
+    ) -> Router<I, R, N, S, C, A2, F, L> {
+        Router {
+            slots: self.slots,
+            seed_routes: self.seed_routes,
+            node_claims: self.node_claims,
+            rng: self.rng,
+            upstream: self.upstream,
+            admission: policy,
+            identity: self.identity,
+            filter: self.filter,
+            limiter: self.limiter,
+            capture: self.capture,
+        }
+    }
+
+    /// Check every frame crossing an interface with `filter`, see
+    /// [`filter`](crate::interface_manager::filter)
+    ///
+    /// This replaces any previous filter. Denied unicast frames are answered
+    /// with [`ProtocolError::IseFiltered`].
+    pub fn with_packet_filter<F2: PacketFilter>(
+        self,
+        filter: F2,
+    ) -> Router<I, R, N, S, C, A, F2, L> {
+        Router {
+            slots: self.slots,
+            seed_routes: self.seed_routes,
+            node_claims: self.node_claims,
+            rng: self.rng,
+            upstream: self.upstream,
+            admission: self.admission,
+            identity: self.identity,
+            filter,
+            limiter: self.limiter,
+            capture: self.capture,
         }
     }
 
+    /// The packet filter of this router, e.g. to change its rules
+    pub fn packet_filter_mut(&mut self) -> &mut F {
+        &mut self.filter
+    }
+
+    /// Drop the received frames that exceed the limits of `limiter`, see
+    /// [`rate_limit`](crate::interface_manager::rate_limit)
+    ///
+    /// This replaces any previous rate limiter. Dropped frames are not
+    /// answered.
+    pub fn with_rate_limiter<L2: RateLimiter>(
+        self,
+        limiter: L2,
+    ) -> Router<I, R, N, S, C, A, F, L2> {
+        Router {
+            slots: self.slots,
+            seed_routes: self.seed_routes,
+            node_claims: self.node_claims,
+            rng: self.rng,
+            upstream: self.upstream,
+            admission: self.admission,
+            identity: self.identity,
+            filter: self.filter,
+            limiter,
+            capture: self.capture,
+        }
+    }
+
+    /// The rate limiter of this router, e.g. to read its counters
+    pub fn rate_limiter_mut(&mut self) -> &mut L {
+        &mut self.limiter
+    }
+
+    /// Record every frame sent or received on any interface with `tap`,
+    /// see [`capture`]
+    pub fn with_capture(mut self, tap: &'static dyn CaptureTap) -> Self {
+        self.set_capture(Some(tap));
+        self
+    }
+
+    /// Start recording every frame sent or received on any interface with
+    /// `tap`, or stop recording them if `tap` is `None`
+    ///
+    /// Interfaces registered later are recorded too.
+    pub fn set_capture(&mut self, tap: Option<&'static dyn CaptureTap>) {
+        self.capture = tap;
+        for slot in self.slots.iter_mut() {
+            slot.port.set_capture(tap, slot.ident);
+        }
+        if let Some(up) = self.upstream.as_mut() {
+            up.port.set_capture(tap, UPSTREAM_IDENT);
+        }
+    }
+
+    /// Present `identity` when requesting a net_id from the upstream seed
+    /// router, in bridge mode
+    pub fn with_identity(mut self, identity: DeviceIdentity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// The advertisement for the neighbor on `ident`: the topics of this
+    /// device, and the topics learned from every *other* interface
+    ///
+    /// Leaving out the topics learned from the neighbor itself keeps two
+    /// routers from keeping each other's stale subscriptions alive.
+    fn advertisement_for(
+        &self,
+        ident: u8,
+        local: &SubscriptionAdvertisement,
+        now: Instant,
+    ) -> SubscriptionAdvertisement {
+        let mut adv = local.clone();
+        let slots = self
+            .slots
+            .iter()
+            .filter(|s| s.ident != ident)
+            .map(|s| (&s.port, &s.interest));
+        let upstream = self
+            .upstream
+            .iter()
+            .filter(|_| ident != UPSTREAM_IDENT)
+            .map(|up| (&up.port, &up.interest));
+        for (port, interest) in slots.chain(upstream) {
+            // Broadcasts are not sent to inactive interfaces anyway
+            if !matches!(port.state(), InterfaceState::Active { .. }) {
+                continue;
+            }
+            match interest {
+                Some(interest) if interest.expiration > now => {
+                    adv.valid_secs = adv
+                        .valid_secs
+                        .min(remaining_lease_seconds(interest.expiration, now));
+                    for key in interest.keys.iter() {
+                        adv.complete &= subscriptions::insert_key(&mut adv.keys, *key);
+                    }
+                }
+                _ => adv.complete = false,
+            }
+        }
+        adv
+    }
+
+    /// Ask the admission policy whether the requester is admitted
+    fn admit(
+        &mut self,
+        source_net: u16,
+        credentials: Option<&Credentials>,
+    ) -> Result<(), AdmissionError> {
+        let challenge = self.rng.next_u64().to_le_bytes();
+        self.admission
+            .admit(source_net, credentials, challenge)
+            .inspect_err(|_e| {
+                warn!("net {}: request not admitted: {:?}", source_net, _e);
+            })
+    }
+
     /// Returns `true` if this router has an upstream interface (bridge mode).
     pub fn has_upstream(&self) -> bool {
         self.upstream.is_some()
@@ -493,12 +590,15 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize>
             net_id,
             node_id: CENTRAL_NODE_ID,
         };
+        let mut port = EdgePort::new_controller(sink, state);
+        port.set_capture(self.capture, ident);
 
         self.slots
             .push(Slot {
                 ident,
-                port: EdgePort::new_controller(sink, state),
+                port,
                 net_id,
+                interest: None,
                 #[cfg(feature = "std")]
                 closer: None,
             })
@@ -524,12 +624,15 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize>
         let ident = (0..N as u8)
             .find(|id| !self.slots.iter().any(|s| s.ident == *id))
             .expect("pigeonhole: fewer than N slots occupied, so a free ident in 0..N must exist");
+        let mut port = EdgePort::new_controller(sink, InterfaceState::Down);
+        port.set_capture(self.capture, ident);
 
         self.slots
             .push(Slot {
                 ident,
-                port: EdgePort::new_controller(sink, InterfaceState::Down),
+                port,
                 net_id: 0,
+                interest: None,
                 #[cfg(feature = "std")]
                 closer: None,
             })
@@ -615,6 +718,26 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize>
             .collect()
     }
 
+    /// The EdgePort of the interface `ident`, if it exists
+    fn port(&self, ident: u8) -> Option<&EdgePort<I>> {
+        if ident == UPSTREAM_IDENT {
+            return self.upstream.as_ref().map(|up| &up.port);
+        }
+        self.slots
+            .iter()
+            .find(|s| s.ident == ident)
+            .map(|s| &s.port)
+    }
+
+    /// Count a frame received on `source` and dropped because of `err`, see
+    /// [`crate::stats`]
+    fn count_drop(&self, source: u8, err: InterfaceSendError) -> InterfaceSendError {
+        if let Some(port) = self.port(source) {
+            port.counters().count_drop(&err);
+        }
+        err
+    }
+
     /// Find the EdgePort to send through for a given destination net_id.
     ///
     /// Searches direct slots first, then seed routes.
@@ -638,15 +761,17 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize>
             .iter()
             .position(|s| s.net_id != 0 && s.net_id == hdr.dst.network_id)
         {
-            let slot = &self.slots[pos];
+            let ident = self.slots[pos].ident;
             if hdr.dst.node_id == CENTRAL_NODE_ID {
                 return Err(InterfaceSendError::DestinationLocal);
             }
             if let Some(src_ident) = source
-                && slot.ident == src_ident
+                && ident == src_ident
             {
                 return Err(InterfaceSendError::RoutingLoop);
             }
+            let counters = self.slots[pos].port.counters();
+            filter_egress(&mut self.filter, hdr, ident, source, counters)?;
             return Ok(&mut self.slots[pos].port);
         }
 
@@ -654,7 +779,7 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize>
         //    net_id is the unique key, so look up by key alone.
         let via_ident = match self.seed_routes.by_key(hdr.dst.network_id) {
             // 3. Upstream fallback (bridge mode)
-            None => return self.find_upstream(source),
+            None => return self.find_upstream(hdr, source),
             Some(entry) if entry.kind.is_active(Instant::now()) => entry.extra.via_ident,
             Some(_) => return Err(InterfaceSendError::NoRouteToDest),
         };
@@ -676,6 +801,8 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize>
                 );
                 InterfaceSendError::NoRouteToDest
             })?;
+        let counters = self.slots[pos].port.counters();
+        filter_egress(&mut self.filter, hdr, via_ident, source, counters)?;
 
         Ok(&mut self.slots[pos].port)
     }
@@ -683,6 +810,7 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize>
     /// Try to route through the upstream interface (bridge mode only).
     fn find_upstream(
         &mut self,
+        hdr: &Header,
         source: Option<u8>,
     ) -> Result<&mut EdgePort<I>, InterfaceSendError> {
         let Some(up) = self.upstream.as_mut() else {
@@ -692,6 +820,13 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize>
         if source == Some(UPSTREAM_IDENT) {
             return Err(InterfaceSendError::RoutingLoop);
         }
+        filter_egress(
+            &mut self.filter,
+            hdr,
+            UPSTREAM_IDENT,
+            source,
+            up.port.counters(),
+        )?;
         Ok(&mut up.port)
     }
 }
@@ -700,11 +835,38 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize>
 // Profile implementation
 // ---------------------------------------------------------------------------
 
+/// Ask the packet filter whether `hdr` may be sent out of the interface
+/// `ident`, after being received on `source` (`None` if sent by this router).
+///
+/// Denied frames are counted in the `counters` of the interface `ident`.
+fn filter_egress<F: PacketFilter>(
+    filter: &mut F,
+    hdr: &Header,
+    ident: u8,
+    source: Option<u8>,
+    counters: &InterfaceCounters,
+) -> Result<(), InterfaceSendError> {
+    let hop = Hop::Egress {
+        interface: ident,
+        from: source,
+    };
+    match filter.check(hop, hdr) {
+        Verdict::Allow => Ok(()),
+        Verdict::Deny => {
+            debug!("{}: denied by packet filter on egress of {}", hdr, ident);
+            let err = InterfaceSendError::Filtered;
+            counters.count_drop(&err);
+            Err(err)
+        }
+    }
+}
+
 /// Fold one broadcast-leg send result into the loop accumulators.
 ///
 /// The benign class — down/inactive interface (`NoRouteToDest`), the frame's
-/// own source (`RoutingLoop`), a self-addressed leg (`DestinationLocal`) —
-/// just means "no recipient here" and is not remembered. Anything else (e.g.
+/// own source (`RoutingLoop`), a self-addressed leg (`DestinationLocal`), a
+/// leg denied by the packet filter (`Filtered`) — just means "no recipient
+/// here" and is not remembered. Anything else (e.g.
 /// `InterfaceFull`, `PacketTooBig`) is a *genuine* failure on an interface
 /// that exists and was attempted; the caller reports it if no leg succeeded,
 /// so a broadcast that failed everywhere is distinguishable from a broadcast
@@ -719,42 +881,58 @@ fn fold_broadcast_leg(
         Err(
             InterfaceSendError::NoRouteToDest
             | InterfaceSendError::RoutingLoop
-            | InterfaceSendError::DestinationLocal,
+            | InterfaceSendError::DestinationLocal
+            | InterfaceSendError::Filtered,
         ) => {}
         Err(e) => *genuine = Some(e),
     }
 }
 
-impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize> Profile
-    for Router<I, R, N, S, C>
+impl<
+    I: Interface,
+    R: RngCore,
+    const N: usize,
+    const S: usize,
+    const C: usize,
+    A: AdmissionPolicy,
+    F: PacketFilter,
+    L: RateLimiter,
+> Profile for Router<I, R, N, S, C, A, F, L>
 {
     type InterfaceIdent = u8;
 
     fn send<T: Serialize>(&mut self, hdr: &Header, data: &T) -> Result<(), InterfaceSendError> {
         let mut hdr = hdr.clone();
-        if hdr.decrement_ttl().is_err() {
-            return Err(InterfaceSendError::NoRouteToDest);
-        }
+        hdr.decrement_ttl()?;
 
         if hdr.dst.port_id == 255 {
             if hdr.any_all.is_none() {
                 return Err(InterfaceSendError::AnyPortMissingKey);
             }
 
+            let now = Instant::now();
             let mut any_good = false;
             let mut genuine = None;
             for slot in self.slots.iter_mut() {
-                if hdr.dst.network_id == slot.net_id {
+                if hdr.dst.network_id == slot.net_id || !interested(&slot.interest, &hdr, now) {
                     continue;
                 }
                 let mut bhdr = hdr.clone();
                 bhdr.dst.network_id = slot.net_id;
                 bhdr.dst.node_id = EDGE_NODE_ID;
-                fold_broadcast_leg(slot.port.send(&bhdr, data), &mut any_good, &mut genuine);
+                let counters = slot.port.counters();
+                let res = filter_egress(&mut self.filter, &bhdr, slot.ident, None, counters)
+                    .and_then(|()| slot.port.send(&bhdr, data));
+                fold_broadcast_leg(res, &mut any_good, &mut genuine);
             }
             // Also broadcast to upstream (bridge mode)
-            if let Some(up) = self.upstream.as_mut() {
-                fold_broadcast_leg(up.port.send(&hdr, data), &mut any_good, &mut genuine);
+            if let Some(up) = self.upstream.as_mut()
+                && interested(&up.interest, &hdr, now)
+            {
+                let counters = up.port.counters();
+                let res = filter_egress(&mut self.filter, &hdr, UPSTREAM_IDENT, None, counters)
+                    .and_then(|()| up.port.send(&hdr, data));
+                fold_broadcast_leg(res, &mut any_good, &mut genuine);
             }
             if any_good {
                 Ok(())
@@ -776,10 +954,19 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize> P
         source: Option<Self::InterfaceIdent>,
     ) -> Result<(), InterfaceSendError> {
         let mut hdr = hdr.clone();
-        if hdr.decrement_ttl().is_err() {
-            return Err(InterfaceSendError::NoRouteToDest);
+        if let Err(e) = hdr.decrement_ttl() {
+            return Err(match source {
+                Some(source) => self.count_drop(source, e),
+                None => e,
+            });
         }
-        let port = self.find(&hdr, source)?;
+        let port = match (self.find(&hdr, source), source) {
+            (Ok(port), _) => port,
+            (Err(e @ InterfaceSendError::RoutingLoop), Some(source)) => {
+                return Err(self.count_drop(source, e));
+            }
+            (Err(e), _) => return Err(e),
+        };
         port.send_err(&hdr, err)
     }
 
@@ -790,8 +977,8 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize> P
         source: Self::InterfaceIdent,
     ) -> Result<(), InterfaceSendError> {
         let mut hdr = hdr.clone();
-        if hdr.decrement_ttl().is_err() {
-            return Err(InterfaceSendError::NoRouteToDest);
+        if let Err(e) = hdr.decrement_ttl() {
+            return Err(self.count_drop(source, e));
         }
 
         if hdr.dst.port_id == 255 {
@@ -806,23 +993,47 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize> P
             let mut default_error = InterfaceSendError::RoutingLoop;
             let mut any_good = false;
             let mut genuine = None;
+            // The header the packet filter sees, only the destination
+            // changes between legs
+            let mut fhdr: Header = hdr.clone().into();
+            let now = Instant::now();
 
             for slot in self.slots.iter_mut() {
                 if source == slot.ident {
                     continue;
                 }
                 default_error = InterfaceSendError::NoRouteToDest;
+                if !interested(&slot.interest, &fhdr, now) {
+                    continue;
+                }
 
                 hdr.dst.network_id = slot.net_id;
                 hdr.dst.node_id = EDGE_NODE_ID;
-                fold_broadcast_leg(slot.port.send_raw(&hdr, data), &mut any_good, &mut genuine);
+                fhdr.dst = hdr.dst;
+                let counters = slot.port.counters();
+                let res =
+                    filter_egress(&mut self.filter, &fhdr, slot.ident, Some(source), counters)
+                        .and_then(|()| slot.port.send_raw(&hdr, data));
+                fold_broadcast_leg(res, &mut any_good, &mut genuine);
             }
             // Also broadcast to upstream (bridge mode), unless source is upstream
             if let Some(up) = self.upstream.as_mut()
                 && source != UPSTREAM_IDENT
             {
                 default_error = InterfaceSendError::NoRouteToDest;
-                fold_broadcast_leg(up.port.send_raw(&hdr, data), &mut any_good, &mut genuine);
+                fhdr.dst = hdr.dst;
+                if interested(&up.interest, &fhdr, now) {
+                    let counters = up.port.counters();
+                    let res = filter_egress(
+                        &mut self.filter,
+                        &fhdr,
+                        UPSTREAM_IDENT,
+                        Some(source),
+                        counters,
+                    )
+                    .and_then(|()| up.port.send_raw(&hdr, data));
+                    fold_broadcast_leg(res, &mut any_good, &mut genuine);
+                }
             }
             if any_good {
                 Ok(())
@@ -833,11 +1044,152 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize> P
             }
         } else {
             let nshdr: Header = hdr.clone().into();
-            let port = self.find(&nshdr, Some(source))?;
+            let port = match self.find(&nshdr, Some(source)) {
+                Ok(port) => port,
+                Err(e @ InterfaceSendError::RoutingLoop) => return Err(self.count_drop(source, e)),
+                Err(e) => return Err(e),
+            };
             port.send_raw(&hdr, data)
         }
     }
 
+    fn filter_ingress(
+        &mut self,
+        hdr: &Header,
+        source: Self::InterfaceIdent,
+    ) -> Result<(), InterfaceSendError> {
+        if self.filter.check(Hop::Ingress { interface: source }, hdr) == Verdict::Deny {
+            debug!("{}: denied by packet filter on ingress of {}", hdr, source);
+            return Err(self.count_drop(source, InterfaceSendError::Filtered));
+        }
+        // Frames denied by the filter don't use up any tokens
+        if self.limiter.check(source, hdr) == Verdict::Deny {
+            trace!("{}: rate limited on ingress of {}", hdr, source);
+            return Err(self.count_drop(source, InterfaceSendError::RateLimited));
+        }
+        Ok(())
+    }
+
+    fn record_ingress(&mut self, source: u8, frame: &BorrowedFrame<'_>, len: usize) {
+        if let Some(port) = self.port(source) {
+            port.counters().count_received(len);
+        }
+        if let Some(tap) = self.capture {
+            capture::record_frame(tap, source, Direction::Ingress, frame);
+        }
+    }
+
+    fn interface_stats(&mut self, index: usize) -> Option<InterfaceStats> {
+        let slots = self.slots.iter().map(|s| (s.ident, &s.port));
+        let upstream = self.upstream.as_ref().map(|up| (UPSTREAM_IDENT, &up.port));
+        let (ident, port) = slots.chain(upstream).nth(index)?;
+        Some(port.counters().snapshot(ident))
+    }
+
+    fn advertise_subscriptions(&mut self, local: &SubscriptionAdvertisement) {
+        let now = Instant::now();
+        let hdr = subscriptions::advertisement_header();
+        for i in 0..self.slots.len() {
+            let adv = self.advertisement_for(self.slots[i].ident, local, now);
+            if let Err(_e) = self.slots[i].port.send(&hdr, &adv) {
+                trace!(
+                    "Couldn't advertise subscriptions to {}: {:?}",
+                    self.slots[i].ident, _e
+                );
+            }
+        }
+        if self.upstream.is_some() {
+            let adv = self.advertisement_for(UPSTREAM_IDENT, local, now);
+            if let Some(up) = self.upstream.as_mut()
+                && let Err(_e) = up.port.send(&hdr, &adv)
+            {
+                trace!("Couldn't advertise subscriptions upstream: {:?}", _e);
+            }
+        }
+    }
+
+    fn learn_subscriptions(&mut self, source: u8, advertisement: &SubscriptionAdvertisement) {
+        // An incomplete advertisement is as good as none: flood
+        let interest = advertisement.complete.then(|| Interest {
+            keys: advertisement.keys.clone(),
+            expiration: Instant::now() + Duration::from_secs(advertisement.valid_secs.into()),
+        });
+        if source == UPSTREAM_IDENT {
+            if let Some(up) = self.upstream.as_mut() {
+                up.interest = interest;
+            }
+        } else if let Some(pos) = self.slots.iter().position(|s| s.ident == source) {
+            // On a bus, each device advertises only its own subscriptions:
+            // flood while more than one device has claimed a node_id
+            let now = Instant::now();
+            let net_id = self.slots[pos].net_id;
+            let claimed = self
+                .node_claims
+                .entries
+                .iter()
+                .filter(|e| e.scope == net_id && e.kind.is_active(now))
+                .count();
+            self.slots[pos].interest = interest.filter(|_| claimed <= 1);
+        }
+    }
+
+    fn router_state(&mut self, query: &RouterQuery) -> Option<RouterEntry> {
+        let now = Instant::now();
+        match *query {
+            RouterQuery::Slot { index } => {
+                let slot = self.slots.get(usize::from(index))?;
+                Some(RouterEntry::Slot(SlotEntry {
+                    ident: slot.ident,
+                    net_id: slot.net_id,
+                    state: slot.port.state(),
+                }))
+            }
+            // Entries that expired are skipped, but not collected: a query
+            // doesn't change the tables
+            RouterQuery::SeedRoute { index } => {
+                let (entry, lease) = self
+                    .seed_routes
+                    .entries
+                    .iter()
+                    .filter_map(|e| Some((e, e.kind.report(now)?)))
+                    .nth(usize::from(index))?;
+                Some(RouterEntry::SeedRoute(SeedRouteEntry {
+                    net_id: entry.key,
+                    source_net_id: entry.scope,
+                    via_ident: entry.extra.via_ident,
+                    delegated: entry.extra.parent.is_some(),
+                    lease,
+                }))
+            }
+            RouterQuery::NodeClaim { index } => {
+                let (entry, lease) = self
+                    .node_claims
+                    .entries
+                    .iter()
+                    .filter_map(|e| Some((e, e.kind.report(now)?)))
+                    .nth(usize::from(index))?;
+                Some(RouterEntry::NodeClaim(NodeClaimEntry {
+                    node_id: entry.key,
+                    net_id: entry.scope,
+                    lease,
+                }))
+            }
+            RouterQuery::Upstream => {
+                let up = self.upstream.as_ref()?;
+                Some(RouterEntry::Upstream(UpstreamEntry {
+                    state: up.port.state(),
+                }))
+            }
+        }
+    }
+
+    #[cfg(feature = "signing")]
+    fn own_node_id(&mut self) -> Option<u8> {
+        // The node_id of all downstream interfaces. A bridge has another one
+        // on its upstream, where its signed messages can't be verified.
+        Some(CENTRAL_NODE_ID)
+    }
+
     fn interface_state(&mut self, ident: Self::InterfaceIdent) -> Option<InterfaceState> {
         if ident == UPSTREAM_IDENT {
             return self.upstream.as_ref().map(|up| up.port.state());
@@ -853,22 +1205,51 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize> P
         ident: Self::InterfaceIdent,
         state: InterfaceState,
     ) -> Result<(), SetStateError> {
+        // Whoever is behind the interface when it comes back may subscribe
+        // to different topics
+        let forget = !matches!(state, InterfaceState::Active { .. });
         if ident == UPSTREAM_IDENT {
-            return self
+            let up = self
                 .upstream
                 .as_mut()
-                .ok_or(SetStateError::InterfaceNotFound)?
-                .port
-                .set_state(state);
+                .ok_or(SetStateError::InterfaceNotFound)?;
+            if forget {
+                up.interest = None;
+            }
+            return up.port.set_state(state);
         }
         let slot = self
             .slots
             .iter_mut()
             .find(|s| s.ident == ident)
             .ok_or(SetStateError::InterfaceNotFound)?;
+        if forget {
+            slot.interest = None;
+        }
         slot.port.set_state(state)
     }
 
+    fn send_link_frame(
+        &mut self,
+        ident: Self::InterfaceIdent,
+        data: &[u8],
+    ) -> Result<(), InterfaceSendError> {
+        if ident == UPSTREAM_IDENT {
+            return self
+                .upstream
+                .as_mut()
+                .ok_or(InterfaceSendError::NoRouteToDest)?
+                .port
+                .send_link_frame(data);
+        }
+        let slot = self
+            .slots
+            .iter_mut()
+            .find(|s| s.ident == ident)
+            .ok_or(InterfaceSendError::NoRouteToDest)?;
+        slot.port.send_link_frame(data)
+    }
+
     fn reassign_interface_net_id(
         &mut self,
         ident: Self::InterfaceIdent,
@@ -902,6 +1283,7 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize> P
     fn request_seed_net_assign(
         &mut self,
         source_net: u16,
+        credentials: Option<&Credentials>,
     ) -> Result<SeedNetAssignment, SeedAssignmentError> {
         if self.has_upstream() {
             return Err(SeedAssignmentError::ProfileCantSeed);
@@ -916,6 +1298,9 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize> P
             .map(|s| s.ident)
             .ok_or(SeedAssignmentError::UnknownSource)?;
 
+        self.admit(source_net, credentials)
+            .map_err(SeedAssignmentError::NotAdmitted)?;
+
         if self.seed_routes.is_full() {
             return Err(SeedAssignmentError::NetIdsExhausted);
         }
@@ -952,11 +1337,17 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize> P
         }
     }
 
-    fn can_delegate_seed(&mut self, source_net: u16) -> Result<(), SeedAssignmentError> {
+    fn can_delegate_seed(
+        &mut self,
+        source_net: u16,
+        credentials: Option<&Credentials>,
+    ) -> Result<(), SeedAssignmentError> {
         self.seed_routes.gc(Instant::now());
         if source_net == 0 || !self.slots.iter().any(|s| s.net_id == source_net) {
             return Err(SeedAssignmentError::UnknownSource);
         }
+        self.admit(source_net, credentials)
+            .map_err(SeedAssignmentError::NotAdmitted)?;
         if self.seed_routes.is_full() {
             return Err(SeedAssignmentError::NetIdsExhausted);
         }
@@ -1239,6 +1630,7 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize> P
         source_net: u16,
         candidate: u8,
         nonce: u64,
+        credentials: Option<&Credentials>,
     ) -> Result<NodeClaimAssignment, AddressClaimError> {
         // Reject reserved node_ids: 0 ("any"), CENTRAL/EDGE (point-to-point
         // roles that are always valid), and 255 (broadcast).
@@ -1255,6 +1647,9 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize> P
             return Err(AddressClaimError::UnknownSource);
         }
 
+        self.admit(source_net, credentials)
+            .map_err(AddressClaimError::NotAdmitted)?;
+
         // Check if the candidate is already claimed on this bus.
         if let Some(entry) = self.node_claims.get(candidate, source_net) {
             return match entry.kind {
@@ -1286,6 +1681,12 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize> P
             LeaseKind::active(now, INITIAL_LEASE_SECS, refresh_token),
         );
 
+        // The subscriptions advertised on the bus so far don't cover the new
+        // device, see `learn_subscriptions()`
+        if let Some(slot) = self.slots.iter_mut().find(|s| s.net_id == source_net) {
+            slot.interest = None;
+        }
+
         Ok(NodeClaimAssignment {
             node_id: candidate,
             net_id: source_net,
@@ -1326,6 +1727,10 @@ impl<I: Interface, R: RngCore, const N: usize, const S: usize, const C: usize> P
         }
     }
 
+    fn admission_credentials(&mut self, challenge: Option<[u8; 8]>) -> Option<Credentials> {
+        self.identity.as_ref()?.credentials(challenge)
+    }
+
     fn is_node_claimed(&mut self, net_id: u16, node_id: u8) -> bool {
         // CENTRAL/EDGE are always valid for point-to-point compatibility.
         if node_id == CENTRAL_NODE_ID || node_id == EDGE_NODE_ID {
@@ -1393,6 +1798,7 @@ impl<I: Interface, const N: usize, const S: usize, const C: usize> Default
 pub struct RouterFrameProcessor {
     net_id: u16,
     activated: bool,
+    encoding: HeaderEncoding,
 }
 
 impl RouterFrameProcessor {
@@ -1401,14 +1807,22 @@ impl RouterFrameProcessor {
         Self {
             net_id,
             activated: false,
+            encoding: HeaderEncoding::Standard,
         }
     }
+
+    /// Decode frames with the given [`HeaderEncoding`], instead of
+    /// [`HeaderEncoding::Standard`]
+    pub fn with_header_encoding(self, encoding: HeaderEncoding) -> Self {
+        Self { encoding, ..self }
+    }
 }
 
 #[cfg(all(test, feature = "tokio-std"))]
 mod tests {
     use super::*;
     use crate::interface_manager::interface_impls::tokio_stream::TokioStreamInterface;
+    use crate::well_known::LeaseEntryState;
     use rand::{SeedableRng, rngs::StdRng};
 
     #[test]
@@ -1442,6 +1856,142 @@ mod tests {
         );
         assert!(router.seed_routes.entries.is_empty());
     }
+
+    #[test]
+    fn router_state_reports_leases_and_tombstones() {
+        let mut router: Router<TokioStreamInterface, StdRng, 1, 3, 1> =
+            Router::new(StdRng::seed_from_u64(0));
+        let now = Instant::now();
+        let route = |via_ident| SeedRoute {
+            via_ident,
+            parent: None,
+        };
+        assert!(
+            router
+                .seed_routes
+                .push(42, 1, route(3), LeaseKind::active(now, 30, 7))
+        );
+        assert!(router.seed_routes.push(
+            43,
+            1,
+            route(4),
+            LeaseKind::Tombstone {
+                clear_time: now + Duration::from_secs(10),
+            },
+        ));
+        // Expires right away
+        assert!(
+            router
+                .seed_routes
+                .push(44, 1, route(5), LeaseKind::active(now, 0, 9))
+        );
+        assert!(
+            router
+                .node_claims
+                .push(5, 1, 0, LeaseKind::active(now, 30, 8))
+        );
+
+        assert_eq!(
+            router.router_state(&RouterQuery::SeedRoute { index: 0 }),
+            Some(RouterEntry::SeedRoute(SeedRouteEntry {
+                net_id: 42,
+                source_net_id: 1,
+                via_ident: 3,
+                delegated: false,
+                lease: LeaseEntryState::Active { remaining_secs: 30 },
+            }))
+        );
+        assert_eq!(
+            router.router_state(&RouterQuery::SeedRoute { index: 1 }),
+            Some(RouterEntry::SeedRoute(SeedRouteEntry {
+                net_id: 43,
+                source_net_id: 1,
+                via_ident: 4,
+                delegated: false,
+                lease: LeaseEntryState::Tombstone { clear_secs: 10 },
+            }))
+        );
+        // Reported as a tombstone, but left unchanged
+        assert_eq!(
+            router.router_state(&RouterQuery::SeedRoute { index: 2 }),
+            Some(RouterEntry::SeedRoute(SeedRouteEntry {
+                net_id: 44,
+                source_net_id: 1,
+                via_ident: 5,
+                delegated: false,
+                lease: LeaseEntryState::Tombstone {
+                    clear_secs: TOMBSTONE_DURATION_SECS as u16,
+                },
+            }))
+        );
+        assert!(matches!(
+            router.seed_routes.entries[2].kind,
+            LeaseKind::Active(_)
+        ));
+        assert_eq!(
+            router.router_state(&RouterQuery::SeedRoute { index: 3 }),
+            None
+        );
+        assert_eq!(
+            router.router_state(&RouterQuery::NodeClaim { index: 0 }),
+            Some(RouterEntry::NodeClaim(NodeClaimEntry {
+                node_id: 5,
+                net_id: 1,
+                lease: LeaseEntryState::Active { remaining_secs: 30 },
+            }))
+        );
+        assert_eq!(router.router_state(&RouterQuery::Slot { index: 0 }), None);
+        assert_eq!(router.router_state(&RouterQuery::Upstream), None);
+    }
+
+    #[test]
+    fn bus_with_two_devices_floods_broadcasts() {
+        use crate::interface_manager::utils::{cobs_stream, std::new_std_queue};
+
+        let mut router: Router<TokioStreamInterface, StdRng, 1, 1, 4> =
+            Router::new(StdRng::seed_from_u64(0));
+        let ident = router
+            .register_interface(cobs_stream::Sink::new_from_handle(new_std_queue(1024), 512))
+            .unwrap();
+        let net_id = router.slots[0].net_id;
+        let mut adv = SubscriptionAdvertisement {
+            valid_secs: 60,
+            complete: true,
+            keys: heapless::Vec::new(),
+        };
+        adv.keys.push(Key(*b"TOPIC_A_")).unwrap();
+
+        // The only device on the bus: its advertisement is trusted
+        router.request_node_claim(net_id, 10, 1, None).unwrap();
+        router.learn_subscriptions(ident, &adv);
+        assert!(router.slots[0].interest.is_some());
+
+        // Another device joins, and the first advertisement doesn't cover it
+        router.request_node_claim(net_id, 11, 2, None).unwrap();
+        assert!(router.slots[0].interest.is_none());
+
+        // Neither device's advertisement covers the other
+        adv.keys[0] = Key(*b"TOPIC_B_");
+        router.learn_subscriptions(ident, &adv);
+        assert!(router.slots[0].interest.is_none());
+        let hdr = Header {
+            src: Address::unknown(),
+            dst: Address {
+                network_id: 0,
+                node_id: 0,
+                port_id: 255,
+            },
+            any_all: Some(crate::AnyAllAppendix {
+                key: Key(*b"TOPIC_A_"),
+                nash: None,
+            }),
+            seq_no: None,
+            kind: crate::FrameKind::TOPIC_MSG,
+            ttl: crate::DEFAULT_TTL,
+            prio: crate::Priority::default(),
+        };
+        assert!(interested(&router.slots[0].interest, &hdr, Instant::now()));
+    }
 }
 
 impl<N> crate::interface_manager::FrameProcessor<N> for RouterFrameProcessor
@@ -1465,7 +2015,7 @@ where
             self.net_id = net_id;
         }
 
-        process_frame(self.net_id, data, nsh, ident.clone());
+        process_frame(self.net_id, self.encoding, data, nsh, ident.clone());
 
         if !self.activated {
             let changed = nsh.stack().manage_profile(|im| {
@@ -1506,19 +2056,27 @@ where
 /// Process one received frame for a Router RX worker.
 pub fn process_frame<N>(
     net_id: u16,
+    encoding: HeaderEncoding,
     data: &[u8],
     nsh: &N,
     ident: <<N as NetStackHandle>::Profile as Profile>::InterfaceIdent,
 ) where
     N: NetStackHandle,
 {
-    let Some(mut frame) = de_frame(data) else {
+    let Some(mut frame) = encoding.de_frame(data) else {
         warn!("Decode error! Ignoring frame on net_id {}", net_id);
         return;
     };
 
     trace!("{} got frame from {:?}", frame.hdr, ident);
 
+    // The frame as received, recorded along with the packet filter check
+    // below, or when dropping it before
+    let received = BorrowedFrame {
+        hdr: frame.hdr.clone(),
+        body: frame.body,
+    };
+
     // Rewrite zero src net_id so it isn't mistaken for a local packet
     if frame.hdr.src.network_id == 0 {
         match frame.hdr.src.node_id {
@@ -1527,10 +2085,14 @@ pub fn process_frame<N>(
                     "{}: device is sending us frames without a node id, ignoring",
                     frame.hdr
                 );
+                nsh.stack()
+                    .manage_profile(|im| im.record_ingress(ident.clone(), &received, data.len()));
                 return;
             }
             CENTRAL_NODE_ID => {
                 warn!("{}: device is sending us frames as us, ignoring", frame.hdr);
+                nsh.stack()
+                    .manage_profile(|im| im.record_ingress(ident.clone(), &received, data.len()));
                 return;
             }
             // Accept any non-zero, non-central node_id (bus-style support)
@@ -1550,9 +2112,13 @@ pub fn process_frame<N>(
     // also exempt: they may be claim requests from a node without an address
     // yet.
     if frame.hdr.dst.port_id != 0 && frame.hdr.src.network_id == net_id {
-        let is_claimed = nsh
-            .stack()
-            .manage_profile(|im| im.is_node_claimed(net_id, frame.hdr.src.node_id));
+        let is_claimed = nsh.stack().manage_profile(|im| {
+            let claimed = im.is_node_claimed(net_id, frame.hdr.src.node_id);
+            if !claimed {
+                im.record_ingress(ident.clone(), &received, data.len());
+            }
+            claimed
+        });
         if !is_claimed {
             warn!(
                 "{}: frame from unclaimed node_id {}, dropping",
@@ -1572,9 +2138,23 @@ pub fn process_frame<N>(
     let hdr = frame.hdr.clone();
     let nshdr: Header = hdr.clone().into();
 
-    let res = match frame.body {
-        Ok(body) => nsh.stack().send_raw(&hdr, body, ident.clone()),
-        Err(e) => nsh.stack().send_err(&nshdr, e, Some(ident.clone())),
+    let res = match nsh.stack().manage_profile(|im| {
+        im.record_ingress(ident.clone(), &received, data.len());
+        im.filter_ingress(&nshdr, ident.clone())
+    }) {
+        Err(e) => Err(crate::net_stack::NetStackSendError::InterfaceSend(e)),
+        Ok(()) => match frame.body {
+            // Advertisements are only meant for this router
+            Ok(body) if subscriptions::is_advertisement(&hdr) => {
+                if let Some(adv) = subscriptions::decode(body) {
+                    nsh.stack()
+                        .manage_profile(|im| im.learn_subscriptions(ident.clone(), &adv));
+                }
+                Ok(())
+            }
+            Ok(body) => nsh.stack().send_raw(&hdr, body, ident.clone()),
+            Err(e) => nsh.stack().send_err(&nshdr, e, Some(ident.clone())),
+        },
     };
 
     match res {
@@ -1588,19 +2168,41 @@ pub fn process_frame<N>(
                 "{} packet too big for outgoing interface (mtu={})",
                 hdr, mtu
             );
-            let err_hdr = Header {
-                src: hdr.dst,
-                dst: hdr.src,
-                any_all: None,
-                seq_no: Some(hdr.seq_no),
-                kind: crate::FrameKind::PROTOCOL_ERROR,
-                ttl: crate::DEFAULT_TTL,
+            reply_err(nsh, &hdr, hdr.dst, ProtocolError::IsePacketTooBig { mtu });
+        }
+        Err(crate::net_stack::NetStackSendError::InterfaceSend(InterfaceSendError::Filtered))
+            if hdr.dst.port_id != 255 && hdr.kind != crate::FrameKind::PROTOCOL_ERROR =>
+        {
+            // Tell the sender, instead of letting it wait for a response
+            warn!("{}: denied by packet filter", hdr);
+            reply_err(nsh, &hdr, hdr.dst, ProtocolError::IseFiltered);
+        }
+        Err(crate::net_stack::NetStackSendError::InterfaceSend(InterfaceSendError::TtlExpired))
+            if hdr.dst.port_id != 255 && hdr.kind != crate::FrameKind::PROTOCOL_ERROR =>
+        {
+            // Tell the sender where the frame was dropped: the error comes
+            // from this router's address on the interface the frame came
+            // from, which is what `Discovery::traceroute()` collects
+            warn!("{}: TTL expired", hdr);
+            let src = Address {
+                network_id: net_id,
+                node_id: CENTRAL_NODE_ID,
+                port_id: 0,
             };
-            let _ = nsh.stack().send_err(
-                &err_hdr,
-                ProtocolError::IsePacketTooBig { mtu },
-                Some(ident),
-            );
+            reply_err(nsh, &hdr, src, ProtocolError::IseTtlExpired);
+        }
+        Err(crate::net_stack::NetStackSendError::InterfaceSend(
+            InterfaceSendError::RateLimited,
+        )) => {
+            // Dropped and counted by the rate limiter. Answering, or logging
+            // every frame of a storm, would only make it worse
+        }
+        Err(crate::net_stack::NetStackSendError::SocketSend(
+            crate::socket::SocketSendError::Unverified,
+        )) if hdr.kind == crate::FrameKind::ENDPOINT_REQ => {
+            // Tell the client, instead of letting it wait for a response
+            warn!("{}: rejected unverified request", hdr);
+            reply_err(nsh, &hdr, hdr.dst, ProtocolError::SseUnverified);
         }
         Err(e) => {
             warn!("{} recv->send error: {:?}", hdr, e);
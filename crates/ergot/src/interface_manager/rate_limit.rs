@@ -0,0 +1,387 @@
+//! Rate Limiting
+//!
+//! By default, a [`Router`](super::profiles::router::Router) forwards every
+//! frame it receives, so a single misbehaving device sending topic broadcasts
+//! in a loop floods every interface of the network. A router can instead be
+//! given a [`RateLimiter`], using `with_rate_limiter()`, which is consulted for
+//! every frame received on one of its interfaces, before it is delivered
+//! locally or routed.
+//!
+//! Frames over the limit are dropped, and counted. They are not answered with
+//! an error, as that would only add to the traffic.
+//!
+//! ## Token buckets
+//!
+//! [`TokenBuckets`] is a ready-made limiter, with a fixed capacity of buckets.
+//! It limits both the frames received on each interface, and the frames sent
+//! by each source device (by net_id and node_id, from any port), with separate
+//! [`Limits`] per [`FrameKind`]. A frame must pass both limits.
+//!
+//! For example, allowing each device to send 20 topic messages per second
+//! (with bursts of up to 50), and each interface to receive 200, while leaving
+//! other kinds of frames unlimited:
+//!
+//! ```rust
+//! use ergot::FrameKind;
+//! use ergot::interface_manager::rate_limit::{Limits, Rate, TokenBuckets};
+//!
+//! let limiter = TokenBuckets::<8, 32>::new(
+//!     Limits::unlimited().with_kind(FrameKind::TOPIC_MSG, Some(Rate::new(200, 400))),
+//!     Limits::unlimited().with_kind(FrameKind::TOPIC_MSG, Some(Rate::new(20, 50))),
+//! );
+//! ```
+
+#[cfg(feature = "std")]
+use web_time::{Duration, Instant};
+
+#[cfg(all(not(feature = "std"), feature = "nostd-seed-router"))]
+use embassy_time::{Duration, Instant};
+
+use crate::{FrameKind, Header, interface_manager::filter::Verdict};
+
+/// The maximum number of kinds a [`Limits`] can have a rate for
+pub const MAX_KIND_RATES: usize = 8;
+
+/// Decides which received frames may be processed
+pub trait RateLimiter {
+    /// Decide whether the frame with the header `hdr`, received on
+    /// `interface`, may be processed
+    fn check(&mut self, interface: u8, hdr: &Header) -> Verdict;
+}
+
+/// Allows every frame
+///
+/// This is the default rate limiter of a router.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Unlimited;
+
+impl RateLimiter for Unlimited {
+    fn check(&mut self, _interface: u8, _hdr: &Header) -> Verdict {
+        Verdict::Allow
+    }
+}
+
+/// The rate of a token bucket
+///
+/// Up to `burst` frames may be received at once, after which frames may be
+/// received at `per_second`. A `per_second` of zero is treated as one, use a
+/// [`PacketFilter`](super::filter::PacketFilter) to drop every frame of a
+/// kind instead.
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    pub per_second: u16,
+    pub burst: u16,
+}
+
+impl Rate {
+    pub const fn new(per_second: u16, burst: u16) -> Self {
+        Self { per_second, burst }
+    }
+
+    /// The time it takes to refill one token
+    fn interval(&self) -> Duration {
+        Duration::from_micros(1_000_000 / self.per_second.max(1) as u64)
+    }
+}
+
+/// The rates of each [`FrameKind`]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+    /// The rate of the kinds without a rate of their own, `None` for unlimited
+    pub default: Option<Rate>,
+    kinds: heapless::Vec<(FrameKind, Option<Rate>), MAX_KIND_RATES>,
+}
+
+impl Limits {
+    /// Limits with the same rate for every kind
+    pub const fn new(default: Option<Rate>) -> Self {
+        Self {
+            default,
+            kinds: heapless::Vec::new(),
+        }
+    }
+
+    /// No limits, unless a kind is given a rate with [`Limits::with_kind`]
+    pub const fn unlimited() -> Self {
+        Self::new(None)
+    }
+
+    /// Use `rate` for frames of `kind`, `None` for unlimited
+    ///
+    /// # Panics
+    ///
+    /// If rates were already given to [`MAX_KIND_RATES`] other kinds.
+    pub fn with_kind(mut self, kind: FrameKind, rate: Option<Rate>) -> Self {
+        if let Some(entry) = self.kinds.iter_mut().find(|(k, _)| *k == kind) {
+            entry.1 = rate;
+        } else if self.kinds.push((kind, rate)).is_err() {
+            panic!("too many kind rates");
+        }
+        self
+    }
+
+    /// The rate of frames of `kind`
+    pub fn rate(&self, kind: FrameKind) -> Option<Rate> {
+        self.kinds
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map_or(self.default, |(_, rate)| *rate)
+    }
+}
+
+/// The number of frames dropped by a [`TokenBuckets`]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DroppedFrames {
+    /// Dropped because the interface exceeded its limit
+    pub interface: u32,
+    /// Dropped because the source device exceeded its limit
+    pub source: u32,
+}
+
+/// A token bucket, tracked as the time at which it will be full again
+/// (the "theoretical arrival time" of the generic cell rate algorithm)
+struct Bucket<K> {
+    key: K,
+    kind: FrameKind,
+    full_at: Instant,
+}
+
+/// A table of up to `N` buckets, evicting the fullest bucket when needed
+struct Buckets<K, const N: usize> {
+    buckets: heapless::Vec<Bucket<K>, N>,
+}
+
+impl<K: PartialEq, const N: usize> Buckets<K, N> {
+    const fn new() -> Self {
+        Self {
+            buckets: heapless::Vec::new(),
+        }
+    }
+
+    /// Take one token from the bucket of `(key, kind)`, if it has one left
+    fn take(&mut self, key: K, kind: FrameKind, rate: Rate, now: Instant) -> Verdict {
+        let idx = match self
+            .buckets
+            .iter()
+            .position(|b| b.key == key && b.kind == kind)
+        {
+            Some(idx) => idx,
+            None => {
+                let bucket = Bucket {
+                    key,
+                    kind,
+                    full_at: now,
+                };
+                match self.buckets.push(bucket) {
+                    Ok(()) => self.buckets.len() - 1,
+                    Err(bucket) => {
+                        // Evicting a bucket that is already full loses
+                        // nothing. Otherwise, the fullest bucket is the one
+                        // that forgets the least.
+                        let Some(idx) = self
+                            .buckets
+                            .iter()
+                            .enumerate()
+                            .min_by_key(|(_, b)| b.full_at)
+                            .map(|(idx, _)| idx)
+                        else {
+                            // A table without any capacity can't limit anything
+                            return Verdict::Allow;
+                        };
+                        self.buckets[idx] = bucket;
+                        idx
+                    }
+                }
+            }
+        };
+
+        let bucket = &mut self.buckets[idx];
+        let interval = rate.interval();
+        let full_at = bucket.full_at.max(now);
+        // The bucket is empty when it is more than `burst` intervals from
+        // being full
+        if full_at + interval > now + interval * u32::from(rate.burst) {
+            return Verdict::Deny;
+        }
+        bucket.full_at = full_at + interval;
+        Verdict::Allow
+    }
+}
+
+/// Token bucket rate limits for each interface, and for each source device
+///
+/// Tracks up to `I` interface buckets, and up to `S` source buckets: one per
+/// interface or source, and each [`FrameKind`] with a rate. When a table is
+/// full, the fullest bucket is forgotten, so `S` may be smaller than the number
+/// of devices in the network.
+pub struct TokenBuckets<const I: usize, const S: usize> {
+    interface_limits: Limits,
+    source_limits: Limits,
+    interfaces: Buckets<u8, I>,
+    sources: Buckets<(u16, u8), S>,
+    dropped: DroppedFrames,
+}
+
+impl<const I: usize, const S: usize> TokenBuckets<I, S> {
+    /// Limit the frames received on each interface to `interface_limits`, and
+    /// the frames sent by each source device to `source_limits`
+    pub const fn new(interface_limits: Limits, source_limits: Limits) -> Self {
+        Self {
+            interface_limits,
+            source_limits,
+            interfaces: Buckets::new(),
+            sources: Buckets::new(),
+            dropped: DroppedFrames {
+                interface: 0,
+                source: 0,
+            },
+        }
+    }
+
+    /// The number of frames dropped so far
+    pub fn dropped(&self) -> DroppedFrames {
+        self.dropped
+    }
+
+    /// Replace the limits, starting every bucket full
+    pub fn set_limits(&mut self, interface_limits: Limits, source_limits: Limits) {
+        self.interface_limits = interface_limits;
+        self.source_limits = source_limits;
+        self.interfaces = Buckets::new();
+        self.sources = Buckets::new();
+    }
+}
+
+impl<const I: usize, const S: usize> RateLimiter for TokenBuckets<I, S> {
+    fn check(&mut self, interface: u8, hdr: &Header) -> Verdict {
+        let now = Instant::now();
+        // Check the source first, so a single noisy device uses up its own
+        // tokens, and not the tokens of everyone on its interface
+        if let Some(rate) = self.source_limits.rate(hdr.kind) {
+            let src = (hdr.src.network_id, hdr.src.node_id);
+            if self.sources.take(src, hdr.kind, rate, now) == Verdict::Deny {
+                self.dropped.source = self.dropped.source.wrapping_add(1);
+                return Verdict::Deny;
+            }
+        }
+        if let Some(rate) = self.interface_limits.rate(hdr.kind)
+            && self.interfaces.take(interface, hdr.kind, rate, now) == Verdict::Deny
+        {
+            self.dropped.interface = self.dropped.interface.wrapping_add(1);
+            return Verdict::Deny;
+        }
+        Verdict::Allow
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Address, Priority};
+
+    fn hdr(net: u16, node: u8, kind: FrameKind) -> Header {
+        Header {
+            src: Address {
+                network_id: net,
+                node_id: node,
+                port_id: 1,
+            },
+            dst: Address {
+                network_id: 1,
+                node_id: 1,
+                port_id: 255,
+            },
+            any_all: None,
+            seq_no: None,
+            kind,
+            ttl: 16,
+            prio: Priority::NORMAL,
+        }
+    }
+
+    #[test]
+    fn burst_then_drop() {
+        // A rate slow enough that no token is refilled during the test
+        let mut tb = TokenBuckets::<4, 4>::new(
+            Limits::unlimited(),
+            Limits::unlimited().with_kind(FrameKind::TOPIC_MSG, Some(Rate::new(1, 3))),
+        );
+        let topic = hdr(2, 2, FrameKind::TOPIC_MSG);
+        for _ in 0..3 {
+            assert_eq!(tb.check(0, &topic), Verdict::Allow);
+        }
+        assert_eq!(tb.check(0, &topic), Verdict::Deny);
+        assert_eq!(tb.check(1, &topic), Verdict::Deny);
+
+        // Other kinds, and other sources, are not affected
+        assert_eq!(
+            tb.check(0, &hdr(2, 2, FrameKind::ENDPOINT_REQ)),
+            Verdict::Allow
+        );
+        assert_eq!(
+            tb.check(0, &hdr(2, 3, FrameKind::TOPIC_MSG)),
+            Verdict::Allow
+        );
+        assert_eq!(
+            tb.dropped(),
+            DroppedFrames {
+                interface: 0,
+                source: 2
+            }
+        );
+    }
+
+    #[test]
+    fn interface_limit() {
+        let mut tb =
+            TokenBuckets::<4, 4>::new(Limits::new(Some(Rate::new(1, 2))), Limits::unlimited());
+        assert_eq!(
+            tb.check(0, &hdr(2, 2, FrameKind::TOPIC_MSG)),
+            Verdict::Allow
+        );
+        assert_eq!(
+            tb.check(0, &hdr(2, 3, FrameKind::TOPIC_MSG)),
+            Verdict::Allow
+        );
+        assert_eq!(tb.check(0, &hdr(2, 4, FrameKind::TOPIC_MSG)), Verdict::Deny);
+        assert_eq!(
+            tb.check(1, &hdr(3, 2, FrameKind::TOPIC_MSG)),
+            Verdict::Allow
+        );
+        assert_eq!(tb.dropped().interface, 1);
+    }
+
+    #[test]
+    fn refill() {
+        let mut tb =
+            TokenBuckets::<4, 4>::new(Limits::new(Some(Rate::new(100, 1))), Limits::unlimited());
+        let topic = hdr(2, 2, FrameKind::TOPIC_MSG);
+        assert_eq!(tb.check(0, &topic), Verdict::Allow);
+        assert_eq!(tb.check(0, &topic), Verdict::Deny);
+        std::thread::sleep(core::time::Duration::from_millis(20));
+        assert_eq!(tb.check(0, &topic), Verdict::Allow);
+    }
+
+    #[test]
+    fn eviction() {
+        let mut tb =
+            TokenBuckets::<4, 2>::new(Limits::unlimited(), Limits::new(Some(Rate::new(1, 1))));
+        assert_eq!(
+            tb.check(0, &hdr(2, 2, FrameKind::TOPIC_MSG)),
+            Verdict::Allow
+        );
+        assert_eq!(
+            tb.check(0, &hdr(2, 3, FrameKind::TOPIC_MSG)),
+            Verdict::Allow
+        );
+        // Forgets one of the others, which then start over
+        assert_eq!(
+            tb.check(0, &hdr(2, 4, FrameKind::TOPIC_MSG)),
+            Verdict::Allow
+        );
+        assert_eq!(tb.check(0, &hdr(2, 4, FrameKind::TOPIC_MSG)), Verdict::Deny);
+    }
+}
@@ -0,0 +1,81 @@
+//! Subscription Advertisements
+//!
+//! By default, a topic broadcast is flooded to every interface of every
+//! router, whether or not any device behind an interface subscribes to the
+//! topic. On slow links, this can waste most of the bandwidth.
+//!
+//! To avoid this, each device can periodically advertise the keys of the
+//! topics it subscribes to, as a [`SubscriptionAdvertisement`], using
+//! [`Services::subscription_advertiser()`] (or by calling
+//! [`NetStack::advertise_subscriptions()`] on a timer). Advertisements are
+//! only sent to the direct neighbors of a device, and are never forwarded:
+//!
+//! * A [`DirectEdge`] advertises the topics of its own sockets.
+//! * A [`Router`] learns the advertisements received on each interface, and
+//!   advertises to each neighbor the topics of its own sockets, plus the
+//!   topics learned from all *other* interfaces.
+//!
+//! A router then only forwards a topic broadcast to the interfaces where the
+//! topic was advertised. It keeps flooding broadcasts to interfaces where
+//! the subscriptions are unknown: before the first advertisement is received,
+//! after an advertisement expires, or when an advertisement is not
+//! `complete`, e.g. because a device subscribes to more than
+//! [`MAX_ADVERTISED_KEYS`] topics, or because one of the devices behind it
+//! does not advertise at all. On a bus, where each device only advertises its
+//! own topics, broadcasts are flooded while more than one device has claimed
+//! a node_id. Only [`FrameKind::TOPIC_MSG`] broadcasts are pruned.
+//!
+//! [`Services::subscription_advertiser()`]: crate::net_stack::services::Services::subscription_advertiser
+//! [`NetStack::advertise_subscriptions()`]: crate::NetStack::advertise_subscriptions
+//! [`DirectEdge`]: super::profiles::direct_edge::DirectEdge
+//! [`Router`]: super::profiles::router::Router
+
+use crate::{
+    Address, AnyAllAppendix, FrameKind, Header, HeaderSeq, Key, Priority,
+    traits::Topic,
+    well_known::{ErgotSubscriptionsTopic, SubscriptionAdvertisement},
+};
+
+pub use crate::well_known::MAX_ADVERTISED_KEYS;
+
+/// The key of [`ErgotSubscriptionsTopic`]
+const ADVERTISEMENT_KEY: Key = Key(ErgotSubscriptionsTopic::TOPIC_KEY.to_bytes());
+
+/// The header of an advertisement, to be sent to a direct neighbor only
+pub(crate) fn advertisement_header() -> Header {
+    Header {
+        src: Address::unknown(),
+        dst: Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: 255,
+        },
+        any_all: Some(AnyAllAppendix {
+            key: ADVERTISEMENT_KEY,
+            nash: None,
+        }),
+        seq_no: None,
+        kind: FrameKind::TOPIC_MSG,
+        ttl: 1,
+        prio: Priority::LOW,
+    }
+}
+
+/// Is this frame an advertisement from a direct neighbor?
+pub(crate) fn is_advertisement(hdr: &HeaderSeq) -> bool {
+    hdr.kind == FrameKind::TOPIC_MSG
+        && hdr.dst.port_id == 255
+        && hdr.any_all.as_ref().map(|a| a.key) == Some(ADVERTISEMENT_KEY)
+}
+
+/// Decode the body of an advertisement
+pub(crate) fn decode(body: &[u8]) -> Option<SubscriptionAdvertisement> {
+    postcard::from_bytes(body).ok()
+}
+
+/// Add `key` to `keys`, unless it is already there
+///
+/// Returns `false` if `keys` is full.
+pub(crate) fn insert_key(keys: &mut heapless::Vec<Key, MAX_ADVERTISED_KEYS>, key: Key) -> bool {
+    keys.contains(&key) || keys.push(key).is_ok()
+}
@@ -139,6 +139,7 @@ where
 
     async fn run_inner(&mut self, frame: &mut [u8], scratch: &mut [u8]) -> Result<(), R::Error> {
         let mut acc = CobsAccumulator::new(frame);
+        self.link_up();
 
         'outer: loop {
             let used = self.read_or_timeout(scratch).await?;
@@ -182,48 +183,72 @@ where
         }
     }
 
+    /// Let the processor know that the link (re)started.
+    fn link_up(&mut self) {
+        #[allow(unused_variables)]
+        let changed = self.processor.link_up(&self.nsh, self.ident.clone());
+        #[cfg(feature = "embassy-time")]
+        if changed {
+            self.notify();
+        }
+    }
+
     /// Read from the transport with optional liveness timeout.
     ///
     /// Without `embassy-time` feature, this is a plain read.
     /// With `embassy-time` and liveness configured, transitions to `Inactive`
     /// on timeout (only after first frame received). Loops back waiting for
     /// frames or another timeout — the transport read error is the only exit.
+    /// With `embassy-time`, the processor's timer is also driven, see
+    /// [`FrameProcessor::timer_ms`].
     async fn read_or_timeout(&mut self, scratch: &mut [u8]) -> Result<usize, R::Error> {
         #[cfg(feature = "embassy-time")]
         {
             loop {
-                let liveness_active = self.liveness.is_some() && self.have_received;
-                if liveness_active {
-                    let timeout_ms = self.liveness.as_ref().unwrap().timeout_ms;
-                    let duration = embassy_time::Duration::from_millis(timeout_ms);
-                    match embassy_time::with_timeout(duration, self.rx.read(scratch)).await {
-                        Ok(result) => return result,
-                        Err(_timeout) => {
-                            let changed = self.nsh.stack().manage_profile(|im| {
-                                if matches!(
-                                    im.interface_state(self.ident.clone()),
-                                    Some(InterfaceState::Active { .. })
-                                ) {
-                                    _ = im.set_interface_state(
-                                        self.ident.clone(),
-                                        InterfaceState::Inactive,
-                                    );
-                                    true
-                                } else {
-                                    false
-                                }
-                            });
-                            if changed {
-                                self.notify();
+                let liveness_ms = self
+                    .liveness
+                    .as_ref()
+                    .filter(|_| self.have_received)
+                    .map(|cfg| cfg.timeout_ms);
+                let timer_ms = self.processor.timer_ms();
+                // Whichever of the liveness timeout and the processor's timer
+                // comes first
+                let wait_ms = match (liveness_ms, timer_ms) {
+                    (Some(l), Some(t)) => l.min(t),
+                    (Some(ms), None) | (None, Some(ms)) => ms,
+                    (None, None) => return self.rx.read(scratch).await,
+                };
+                let duration = embassy_time::Duration::from_millis(wait_ms);
+                match embassy_time::with_timeout(duration, self.rx.read(scratch)).await {
+                    Ok(result) => return result,
+                    Err(_timeout) if liveness_ms == Some(wait_ms) => {
+                        let changed = self.nsh.stack().manage_profile(|im| {
+                            if matches!(
+                                im.interface_state(self.ident.clone()),
+                                Some(InterfaceState::Active { .. })
+                            ) {
+                                _ = im.set_interface_state(
+                                    self.ident.clone(),
+                                    InterfaceState::Inactive,
+                                );
+                                true
+                            } else {
+                                false
                             }
-                            self.processor.reset();
-                            self.have_received = false;
-                            self.needs_cobs_reset = true;
-                            continue;
+                        });
+                        if changed {
+                            self.notify();
+                        }
+                        self.processor.reset();
+                        self.link_up();
+                        self.have_received = false;
+                        self.needs_cobs_reset = true;
+                    }
+                    Err(_timeout) => {
+                        if self.processor.on_timer(&self.nsh, self.ident.clone()) {
+                            self.notify();
                         }
                     }
-                } else {
-                    return self.rx.read(scratch).await;
                 }
             }
         }
@@ -94,6 +94,7 @@ where
             .inspect_err(|err| {
                 error!("Error setting interface state: {:?}", err);
             });
+        self.processor.link_up(&self.nsh, self.ident.clone());
 
         let res = self.run_inner(scratch).await;
         _ = self
@@ -168,6 +168,9 @@ where
     ) {
         let mut have_received = false;
         let mut last_data_at: Option<Instant> = None;
+        if self.processor.link_up(&self.nsh, self.ident.clone()) {
+            self.notify();
+        }
 
         loop {
             // Compute liveness remaining time (only active after first frame)
@@ -223,6 +226,7 @@ where
                             }
                         });
                         self.processor.reset();
+                        self.processor.link_up(&self.nsh, self.ident.clone());
                         have_received = false;
                         last_data_at = None;
                         self.notify();
@@ -244,6 +248,7 @@ where
                         }
                     });
                     self.processor.reset();
+                    self.processor.link_up(&self.nsh, self.ident.clone());
                     have_received = false;
                     last_data_at = None;
                     self.notify();
@@ -15,6 +15,9 @@
 //! - **Liveness timeout**: [`RxWorker::run_with_liveness`] — takes a
 //!   `sleeper` closure so any runtime's timer can drive it (e.g.
 //!   `tokio::time::sleep`, `gloo_timers::future::sleep`).
+//! - **Processor timer**: [`RxWorker::run_with_timer`] — the same `sleeper`,
+//!   only driving [`FrameProcessor::on_timer`], e.g. to retransmit a link
+//!   hello. `run_with_liveness` drives it as well.
 //!
 //! The caller is responsible for setting the initial interface state before
 //! running the worker. On exit (or drop), the interface is set to
@@ -131,12 +134,40 @@ where
         scratch: &mut [u8],
     ) -> Result<RxEnd, std::io::Error> {
         let res = self
-            .run_inner(frame, scratch, None::<(_, fn(u64) -> core::future::Pending<()>)>)
+            .run_inner(
+                frame,
+                scratch,
+                None,
+                None::<fn(u64) -> core::future::Pending<()>>,
+            )
             .await;
         self.set_down();
         res
     }
 
+    /// Run the receive loop, with a timer for the processor
+    ///
+    /// Like [`run`](Self::run), but calls [`FrameProcessor::on_timer`] when
+    /// the processor asks for it, see [`FrameProcessor::timer_ms`].
+    ///
+    /// `sleeper` provides the timer: a closure from milliseconds to a future
+    /// that resolves after that long (e.g.
+    /// `|ms| tokio::time::sleep(Duration::from_millis(ms))`).
+    pub async fn run_with_timer<S, F>(
+        &mut self,
+        frame: &mut [u8],
+        scratch: &mut [u8],
+        sleeper: S,
+    ) -> Result<RxEnd, std::io::Error>
+    where
+        S: Fn(u64) -> F,
+        F: Future<Output = ()>,
+    {
+        let res = self.run_inner(frame, scratch, None, Some(sleeper)).await;
+        self.set_down();
+        res
+    }
+
     /// Run the receive loop with a liveness timeout.
     ///
     /// Once at least one frame has been received, going `liveness.timeout_ms`
@@ -159,7 +190,7 @@ where
         F: Future<Output = ()>,
     {
         let res = self
-            .run_inner(frame, scratch, Some((liveness, sleeper)))
+            .run_inner(frame, scratch, Some(liveness), Some(sleeper))
             .await;
         self.set_down();
         res
@@ -169,7 +200,8 @@ where
         &mut self,
         frame: &mut [u8],
         scratch: &mut [u8],
-        liveness: Option<(LivenessConfig, S)>,
+        liveness: Option<LivenessConfig>,
+        sleeper: Option<S>,
     ) -> Result<RxEnd, std::io::Error>
     where
         S: Fn(u64) -> F,
@@ -178,6 +210,7 @@ where
         let mut acc = CobsAccumulator::new(frame);
         let closer = self.closer.clone();
         let mut have_received = false;
+        self.link_up();
 
         loop {
             let close_fut = async {
@@ -189,9 +222,20 @@ where
                     None => core::future::pending().await,
                 }
             };
+            let liveness_ms = liveness
+                .as_ref()
+                .filter(|_| have_received)
+                .map(|cfg| cfg.timeout_ms);
+            let timer_ms = self.processor.timer_ms();
+            // Whichever of the liveness timeout and the processor's timer
+            // comes first
+            let wait_ms = match (liveness_ms, timer_ms) {
+                (Some(l), Some(t)) => Some(l.min(t)),
+                (l, t) => l.or(t),
+            };
             let timeout_fut = async {
-                match &liveness {
-                    Some((cfg, sleeper)) if have_received => sleeper(cfg.timeout_ms).await,
+                match (&sleeper, wait_ms) {
+                    (Some(sleeper), Some(ms)) => sleeper(ms).await,
                     _ => core::future::pending().await,
                 }
             };
@@ -200,10 +244,17 @@ where
                 match select3(async_read(&mut self.rx, scratch), close_fut, timeout_fut).await {
                     Either3::First(res) => res?,
                     Either3::Second(()) => return Ok(RxEnd::Closed),
-                    Either3::Third(()) => {
+                    Either3::Third(()) if wait_ms == liveness_ms => {
                         self.liveness_timeout();
                         have_received = false;
                         acc.reset();
+                        self.link_up();
+                        continue;
+                    }
+                    Either3::Third(()) => {
+                        if self.processor.on_timer(&self.nsh, self.ident.clone()) {
+                            self.notify();
+                        }
                         continue;
                     }
                 };
@@ -235,6 +286,13 @@ where
         }
     }
 
+    /// Let the processor know that the link (re)started.
+    fn link_up(&mut self) {
+        if self.processor.link_up(&self.nsh, self.ident.clone()) {
+            self.notify();
+        }
+    }
+
     /// Handle a liveness timeout: deactivate the interface (if active)
     /// and reset the processor so the next frame triggers re-discovery.
     fn liveness_timeout(&mut self) {
@@ -305,6 +363,32 @@ where
     }
 }
 
+/// Transmitter worker task for a priority sink.
+///
+/// Like [`tx_worker`], but reads one frame at a time from several bbqueue
+/// consumers, always taking the next frame from the highest priority queue
+/// that has one. `rx` are the consumers of a
+/// [`priority::Sink`](crate::interface_manager::utils::priority::Sink),
+/// lowest priority first.
+pub async fn prio_tx_worker<W, Q, const N: usize>(
+    tx: &mut W,
+    rx: [bbqueue::prod_cons::stream::StreamConsumer<Q>; N],
+) -> Result<(), std::io::Error>
+where
+    W: futures_io::AsyncWrite + Unpin,
+    Q: bbqueue::traits::bbqhdl::BbqHandle,
+    Q::Notifier: bbqueue::traits::notifier::AsyncNotifier,
+{
+    loop {
+        let (data, len) = crate::interface_manager::utils::priority::read_cobs_frame(&rx).await;
+        if len == 0 {
+            return Ok(());
+        }
+        async_write_all(tx, &data[..len]).await?;
+        data.release(len);
+    }
+}
+
 /// Async write helper: writes all bytes, handling partial writes.
 async fn async_write_all<W: futures_io::AsyncWrite + Unpin>(
     writer: &mut W,
@@ -64,6 +64,9 @@ where
     /// the closer fires.
     pub async fn run(&mut self) -> ReceiverError {
         let mut consecutive_errs: usize = 0;
+        if self.processor.link_up(&self.nsh, self.ident.clone()) {
+            self.notify();
+        }
 
         loop {
             // Rehydrate the queue
@@ -305,7 +308,10 @@ where
 // Registration: Router
 // ---------------------------------------------------------------------------
 
+use crate::interface_manager::admission::AdmissionPolicy;
+use crate::interface_manager::filter::PacketFilter;
 use crate::interface_manager::profiles::router::{Router, RouterFrameProcessor};
+use crate::interface_manager::rate_limit::RateLimiter;
 use crate::interface_manager::utils::framed_stream::Sink;
 use crate::interface_manager::utils::std::new_std_queue;
 use rand_core::RngCore;
@@ -315,7 +321,7 @@ use rand_core::RngCore;
 pub struct RouterRegistrationError;
 
 /// Register a nusb USB bulk transport on a [`Router`] profile.
-pub async fn register_router<N, I, Rng, const M: usize, const SS: usize, const CC: usize>(
+pub async fn register_router<N, I, Rng, A, F, L, const M: usize, const SS: usize, const CC: usize>(
     stack: N,
     device: NewDevice,
     max_ergot_packet_size: u16,
@@ -325,7 +331,10 @@ pub async fn register_router<N, I, Rng, const M: usize, const SS: usize, const C
 where
     I: Interface<Sink = Sink<StdQueue>>,
     Rng: RngCore + Send + 'static,
-    N: NetStackHandle<Profile = Router<I, Rng, M, SS, CC>> + Send + 'static,
+    A: AdmissionPolicy + Send + 'static,
+    F: PacketFilter + Send + 'static,
+    L: RateLimiter + Send + 'static,
+    N: NetStackHandle<Profile = Router<I, Rng, M, SS, CC, A, F, L>> + Send + 'static,
 {
     let q: StdQueue = new_std_queue(outgoing_buffer_size);
     let res = stack.stack().manage_profile(|im| {
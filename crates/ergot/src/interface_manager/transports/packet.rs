@@ -193,6 +193,13 @@ where
         #[cfg(feature = "embassy-time")]
         self.notify();
 
+        #[allow(unused_variables)]
+        let changed = self.processor.link_up(&self.nsh, self.ident.clone());
+        #[cfg(feature = "embassy-time")]
+        if changed {
+            self.notify();
+        }
+
         let res = self.run_inner(scratch).await;
 
         _ = self
@@ -292,6 +299,7 @@ where
                             self.notify();
                         }
                         self.processor.reset();
+                        self.processor.link_up(&self.nsh, self.ident.clone());
                         self.have_received = false;
                     }
                 }
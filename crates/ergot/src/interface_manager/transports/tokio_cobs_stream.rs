@@ -28,12 +28,15 @@ use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
 use super::futures_io::RxWorker;
 
-/// The liveness sleeper for tokio-based transports.
+/// The sleeper for tokio-based transports, driving the liveness timeout and
+/// the processor's timer.
 fn tokio_sleeper(ms: u64) -> tokio::time::Sleep {
     tokio::time::sleep(tokio::time::Duration::from_millis(ms))
 }
 
 /// Run an [`RxWorker`] to completion, with or without a liveness timeout.
+///
+/// The processor's timer is always driven.
 async fn run_rx_worker<N, R, P>(
     rx_worker: &mut RxWorker<N, R, P>,
     liveness: Option<LivenessConfig>,
@@ -51,7 +54,11 @@ async fn run_rx_worker<N, R, P>(
                 .run_with_liveness(&mut frame, &mut scratch, cfg, tokio_sleeper)
                 .await
         }
-        None => rx_worker.run(&mut frame, &mut scratch).await,
+        None => {
+            rx_worker
+                .run_with_timer(&mut frame, &mut scratch, tokio_sleeper)
+                .await
+        }
     };
     match res {
         Ok(end) => info!("rx_worker ended: {:?}", end),
@@ -93,6 +100,35 @@ impl<W: AsyncWriteExt + Unpin> CobsStreamTxWorker<W> {
     }
 }
 
+/// A COBS stream TxWorker for a priority sink, see [`register_router_prio`].
+///
+/// Wraps the [`futures_io::prio_tx_worker`](super::futures_io::prio_tx_worker)
+/// with closer support, like [`CobsStreamTxWorker`].
+pub struct PrioCobsStreamTxWorker<W: AsyncWriteExt + Unpin, const P: usize> {
+    pub writer: W,
+    pub consumers: [bbqueue::prod_cons::stream::StreamConsumer<StdQueue>; P],
+    pub closer: Arc<WaitQueue>,
+}
+
+impl<W: AsyncWriteExt + Unpin, const P: usize> PrioCobsStreamTxWorker<W, P> {
+    pub async fn run(self) {
+        info!("Started COBS stream priority tx_worker");
+        let mut compat_writer = self.writer.compat_write();
+
+        select! {
+            res = super::futures_io::prio_tx_worker(&mut compat_writer, self.consumers) => {
+                if let Err(e) = res {
+                    error!("Tx Error: {:?}", e);
+                }
+            }
+            _c = self.closer.wait() => {}
+        }
+
+        warn!("Closing COBS stream priority tx_worker");
+        self.closer.close();
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Registration: DirectEdge
 // ---------------------------------------------------------------------------
@@ -109,13 +145,16 @@ pub struct EdgeRegistrationError;
 /// - Target: `InterfaceState::Active { net_id: 0, node_id: EDGE_NODE_ID }` with `EdgeFrameProcessor::new()`
 /// - Controller: `InterfaceState::Active { net_id: 1, node_id: 1 }` with
 ///   `EdgeFrameProcessor::new_controller(1)`
+///
+/// The processor may also be wrapped, e.g. in a
+/// [`HelloProcessor`](crate::interface_manager::hello::HelloProcessor).
 #[allow(clippy::too_many_arguments)]
 pub async fn register_edge<N, I, R, W>(
     stack: N,
     reader: R,
     writer: W,
     queue: StdQueue,
-    processor: EdgeFrameProcessor,
+    processor: impl crate::interface_manager::FrameProcessor<N> + Send + 'static,
     initial_state: InterfaceState,
     liveness: Option<LivenessConfig>,
     state_notify: Option<Arc<WaitQueue>>,
@@ -169,9 +208,14 @@ where
 // Registration: Router
 // ---------------------------------------------------------------------------
 
+use crate::interface_manager::admission::AdmissionPolicy;
+use crate::interface_manager::filter::PacketFilter;
 use crate::interface_manager::profiles::router::{Router, RouterFrameProcessor};
+use crate::interface_manager::rate_limit::RateLimiter;
 use crate::interface_manager::utils::cobs_stream::Sink;
+use crate::interface_manager::utils::priority;
 use crate::interface_manager::utils::std::new_std_queue;
+use crate::wire_frames::HeaderEncoding;
 use rand_core::RngCore;
 
 /// Registration error for Router.
@@ -179,7 +223,69 @@ use rand_core::RngCore;
 pub struct RouterRegistrationError;
 
 /// Register a COBS-framed stream transport on a [`Router`] profile.
-pub async fn register_router<N, I, Rng, R, W, const M: usize, const SS: usize, const CC: usize>(
+pub async fn register_router<
+    N,
+    I,
+    Rng,
+    A,
+    F,
+    L,
+    R,
+    W,
+    const M: usize,
+    const SS: usize,
+    const CC: usize,
+>(
+    stack: N,
+    reader: R,
+    writer: W,
+    max_ergot_packet_size: u16,
+    outgoing_buffer_size: usize,
+    liveness: Option<LivenessConfig>,
+    state_notify: Option<Arc<WaitQueue>>,
+) -> Result<u8, RouterRegistrationError>
+where
+    I: Interface<Sink = Sink<StdQueue>>,
+    Rng: RngCore + Send + 'static,
+    A: AdmissionPolicy + Send + 'static,
+    F: PacketFilter + Send + 'static,
+    L: RateLimiter + Send + 'static,
+    N: NetStackHandle<Profile = Router<I, Rng, M, SS, CC, A, F, L>> + Send + 'static,
+    R: AsyncReadExt + Unpin + Send + 'static,
+    W: AsyncWriteExt + Unpin + Send + 'static,
+{
+    register_router_with_encoding(
+        stack,
+        reader,
+        writer,
+        max_ergot_packet_size,
+        outgoing_buffer_size,
+        liveness,
+        state_notify,
+        HeaderEncoding::Standard,
+    )
+    .await
+}
+
+/// Register a COBS-framed stream transport on a [`Router`] profile, using
+/// the given [`HeaderEncoding`] for all frames on this link.
+///
+/// Like [`register_router`], the other end of the link must use the same
+/// encoding.
+#[allow(clippy::too_many_arguments)]
+pub async fn register_router_with_encoding<
+    N,
+    I,
+    Rng,
+    A,
+    F,
+    L,
+    R,
+    W,
+    const M: usize,
+    const SS: usize,
+    const CC: usize,
+>(
     stack: N,
     reader: R,
     writer: W,
@@ -187,19 +293,129 @@ pub async fn register_router<N, I, Rng, R, W, const M: usize, const SS: usize, c
     outgoing_buffer_size: usize,
     liveness: Option<LivenessConfig>,
     state_notify: Option<Arc<WaitQueue>>,
+    encoding: HeaderEncoding,
 ) -> Result<u8, RouterRegistrationError>
 where
     I: Interface<Sink = Sink<StdQueue>>,
     Rng: RngCore + Send + 'static,
-    N: NetStackHandle<Profile = Router<I, Rng, M, SS, CC>> + Send + 'static,
+    A: AdmissionPolicy + Send + 'static,
+    F: PacketFilter + Send + 'static,
+    L: RateLimiter + Send + 'static,
+    N: NetStackHandle<Profile = Router<I, Rng, M, SS, CC, A, F, L>> + Send + 'static,
     R: AsyncReadExt + Unpin + Send + 'static,
     W: AsyncWriteExt + Unpin + Send + 'static,
 {
     let q: StdQueue = new_std_queue(outgoing_buffer_size);
+    let sink =
+        Sink::new_from_handle(q.clone(), max_ergot_packet_size).with_header_encoding(encoding);
+    let (ident, closer) = register_router_sink(
+        stack,
+        reader,
+        sink,
+        max_ergot_packet_size,
+        liveness,
+        state_notify,
+        encoding,
+    )?;
+    tokio::task::spawn(
+        CobsStreamTxWorker {
+            writer,
+            consumer: <StdQueue as BbqHandle>::stream_consumer(&q),
+            closer,
+        }
+        .run(),
+    );
+
+    Ok(ident)
+}
+
+/// Register a COBS-framed stream transport with `P` priority queues on a
+/// [`Router`] profile.
+///
+/// Like [`register_router`], but each priority class gets its own outgoing
+/// queue of `outgoing_buffer_size` bytes, and frames are always sent from the
+/// highest priority queue first. See
+/// [`priority`].
+pub async fn register_router_prio<
+    N,
+    I,
+    Rng,
+    A,
+    F,
+    L,
+    R,
+    W,
+    const M: usize,
+    const SS: usize,
+    const CC: usize,
+    const P: usize,
+>(
+    stack: N,
+    reader: R,
+    writer: W,
+    max_ergot_packet_size: u16,
+    outgoing_buffer_size: usize,
+    liveness: Option<LivenessConfig>,
+    state_notify: Option<Arc<WaitQueue>>,
+) -> Result<u8, RouterRegistrationError>
+where
+    I: Interface<Sink = priority::Sink<Sink<StdQueue>, P>>,
+    Rng: RngCore + Send + 'static,
+    A: AdmissionPolicy + Send + 'static,
+    F: PacketFilter + Send + 'static,
+    L: RateLimiter + Send + 'static,
+    N: NetStackHandle<Profile = Router<I, Rng, M, SS, CC, A, F, L>> + Send + 'static,
+    R: AsyncReadExt + Unpin + Send + 'static,
+    W: AsyncWriteExt + Unpin + Send + 'static,
+{
+    let qs: [StdQueue; P] = core::array::from_fn(|_| new_std_queue(outgoing_buffer_size));
+    let sink = priority::Sink::new(core::array::from_fn(|i| {
+        Sink::new_from_handle(qs[i].clone(), max_ergot_packet_size)
+    }));
+    let (ident, closer) = register_router_sink(
+        stack,
+        reader,
+        sink,
+        max_ergot_packet_size,
+        liveness,
+        state_notify,
+        HeaderEncoding::Standard,
+    )?;
+    tokio::task::spawn(
+        PrioCobsStreamTxWorker {
+            writer,
+            consumers: qs.each_ref().map(<StdQueue as BbqHandle>::stream_consumer),
+            closer,
+        }
+        .run(),
+    );
+
+    Ok(ident)
+}
+
+/// Register the interface with the given sink, and spawn its RxWorker.
+///
+/// Returns the interface identifier, and the closer for the TxWorker.
+fn register_router_sink<N, I, Rng, A, F, L, R, const M: usize, const SS: usize, const CC: usize>(
+    stack: N,
+    reader: R,
+    sink: I::Sink,
+    max_ergot_packet_size: u16,
+    liveness: Option<LivenessConfig>,
+    state_notify: Option<Arc<WaitQueue>>,
+    encoding: HeaderEncoding,
+) -> Result<(u8, Arc<WaitQueue>), RouterRegistrationError>
+where
+    I: Interface,
+    Rng: RngCore + Send + 'static,
+    A: AdmissionPolicy + Send + 'static,
+    F: PacketFilter + Send + 'static,
+    L: RateLimiter + Send + 'static,
+    N: NetStackHandle<Profile = Router<I, Rng, M, SS, CC, A, F, L>> + Send + 'static,
+    R: AsyncReadExt + Unpin + Send + 'static,
+{
     let res = stack.stack().manage_profile(|im| {
-        let ident = im
-            .register_interface(Sink::new_from_handle(q.clone(), max_ergot_packet_size))
-            .ok()?;
+        let ident = im.register_interface(sink).ok()?;
         let state = im.interface_state(ident)?;
         match state {
             InterfaceState::Active { net_id, node_id: _ } => Some((ident, net_id)),
@@ -222,7 +438,7 @@ where
     let mut rx_worker = RxWorker::new(
         stack.clone(),
         reader.compat(),
-        RouterFrameProcessor::new(net_id),
+        RouterFrameProcessor::new(net_id).with_header_encoding(encoding),
         ident,
     )
     .with_closer(closer.clone());
@@ -245,16 +461,8 @@ where
             notify.wake_all();
         }
     });
-    tokio::task::spawn(
-        CobsStreamTxWorker {
-            writer,
-            consumer: <StdQueue as BbqHandle>::stream_consumer(&q),
-            closer,
-        }
-        .run(),
-    );
 
-    Ok(ident)
+    Ok((ident, closer))
 }
 
 // ---------------------------------------------------------------------------
@@ -366,6 +574,9 @@ pub async fn register_bridge_downstream<
     N,
     I,
     Rng,
+    A,
+    F,
+    L,
     R,
     W,
     const M: usize,
@@ -383,7 +594,10 @@ pub async fn register_bridge_downstream<
 where
     I: Interface<Sink = Sink<StdQueue>>,
     Rng: RngCore + Send + 'static,
-    N: NetStackHandle<Profile = Router<I, Rng, M, SS, CC>> + Send + 'static,
+    A: AdmissionPolicy + Send + 'static,
+    F: PacketFilter + Send + 'static,
+    L: RateLimiter + Send + 'static,
+    N: NetStackHandle<Profile = Router<I, Rng, M, SS, CC, A, F, L>> + Send + 'static,
     R: AsyncReadExt + Unpin + Send + 'static,
     W: AsyncWriteExt + Unpin + Send + 'static,
 {
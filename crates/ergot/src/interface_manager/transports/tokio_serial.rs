@@ -10,8 +10,11 @@ use tokio_serial_v5::{ClearBuffer, SerialPort, SerialPortBuilderExt};
 
 use crate::interface_manager::{
     Interface, InterfaceState, LivenessConfig,
+    admission::AdmissionPolicy,
+    filter::PacketFilter,
     profiles::direct_edge::{DirectEdge, EdgeFrameProcessor},
     profiles::router::Router,
+    rate_limit::RateLimiter,
     utils::{cobs_stream::Sink, std::StdQueue},
 };
 use crate::logging::warn;
@@ -80,7 +83,7 @@ where
 ///
 /// Opens the serial port, clears buffers, and delegates to
 /// [`tokio_cobs_stream::register_router`].
-pub async fn register_router<N, I, Rng, const M: usize, const SS: usize, const CC: usize>(
+pub async fn register_router<N, I, Rng, A, F, L, const M: usize, const SS: usize, const CC: usize>(
     stack: N,
     path: &str,
     baud: u32,
@@ -92,7 +95,10 @@ pub async fn register_router<N, I, Rng, const M: usize, const SS: usize, const C
 where
     I: Interface<Sink = Sink<StdQueue>>,
     Rng: RngCore + Send + 'static,
-    N: NetStackHandle<Profile = Router<I, Rng, M, SS, CC>> + Send + 'static,
+    A: AdmissionPolicy + Send + 'static,
+    F: PacketFilter + Send + 'static,
+    L: RateLimiter + Send + 'static,
+    N: NetStackHandle<Profile = Router<I, Rng, M, SS, CC, A, F, L>> + Send + 'static,
 {
     let mut port = tokio_serial_v5::new(path, baud)
         .open_native_async()
@@ -103,7 +109,7 @@ where
     let _ = std::io::Write::write_all(&mut port, &[0]);
     let (rx, tx) = tokio::io::split(port);
 
-    tokio_cobs_stream::register_router::<N, I, Rng, _, _, M, SS, CC>(
+    tokio_cobs_stream::register_router::<N, I, Rng, A, F, L, _, _, M, SS, CC>(
         stack,
         rx,
         tx,
@@ -25,6 +25,8 @@ use tokio::{net::UdpSocket, select, sync::watch};
 /// Each received UDP datagram is treated as a complete frame and
 /// passed to the [`FrameProcessor`].
 ///
+/// Drives the processor's timer, see [`FrameProcessor::timer_ms`].
+///
 /// On liveness timeout, transitions to [`InterfaceState::Down`]
 /// (not Inactive) because UDP is connectionless — there is no
 /// persistent connection to recover.
@@ -64,71 +66,69 @@ where
     pub async fn run(&mut self, mut on_recv: impl FnMut(SocketAddr)) -> ReceiverError {
         let mut raw_buf = vec![0u8; 4096].into_boxed_slice();
         let mut have_received = false;
+        if self.processor.link_up(&self.nsh, self.ident.clone()) {
+            self.notify();
+        }
 
         loop {
             let rd = self.skt.recv_from(&mut raw_buf);
             let close = self.closer.wait();
 
-            let liveness_active = self.liveness.is_some() && have_received;
-
-            let (ct, remote_addr) = if liveness_active {
-                let liveness = self.liveness.as_ref().unwrap();
-                let timeout =
-                    tokio::time::sleep(tokio::time::Duration::from_millis(liveness.timeout_ms));
+            let liveness_ms = self
+                .liveness
+                .as_ref()
+                .filter(|_| have_received)
+                .map(|cfg| cfg.timeout_ms);
+            let timer_ms = self.processor.timer_ms();
+            // Whichever of the liveness timeout and the processor's timer
+            // comes first
+            let wait_ms = match (liveness_ms, timer_ms) {
+                (Some(l), Some(t)) => Some(l.min(t)),
+                (l, t) => l.or(t),
+            };
+            let timeout = async {
+                match wait_ms {
+                    Some(ms) => tokio::time::sleep(tokio::time::Duration::from_millis(ms)).await,
+                    None => core::future::pending().await,
+                }
+            };
 
-                select! {
-                    r = rd => {
-                        match r {
-                            Ok((0, _)) => {
-                                warn!("received nothing, retrying");
-                                continue;
-                            }
-                            Err(e) => {
-                                warn!("receiver error, retrying. error: {}, kind: {}", e, e.kind());
-                                continue;
-                            }
-                            Ok((ct, addr)) => {
-                                trace!("received {} bytes from {}", ct, addr);
-                                (ct, addr)
-                            }
+            let (ct, remote_addr) = select! {
+                r = rd => {
+                    match r {
+                        Ok((0, _)) => {
+                            warn!("received nothing, retrying");
+                            continue;
+                        }
+                        Err(e) => {
+                            warn!("receiver error, retrying. error: {}, kind: {}", e, e.kind());
+                            continue;
+                        }
+                        Ok((ct, addr)) => {
+                            trace!("received {} bytes from {}", ct, addr);
+                            (ct, addr)
                         }
-                    }
-                    _c = close => {
-                        return ReceiverError::SocketClosed;
-                    }
-                    _ = timeout => {
-                        warn!("Liveness timeout — interface down");
-                        self.nsh.stack().manage_profile(|im| {
-                            _ = im.set_interface_state(
-                                self.ident.clone(),
-                                InterfaceState::Down,
-                            );
-                        });
-                        self.notify();
-                        return ReceiverError::SocketClosed;
                     }
                 }
-            } else {
-                select! {
-                    r = rd => {
-                        match r {
-                            Ok((0, _)) => {
-                                warn!("received nothing, retrying");
-                                continue;
-                            }
-                            Err(e) => {
-                                warn!("receiver error, retrying. error: {}, kind: {}", e, e.kind());
-                                continue;
-                            }
-                            Ok((ct, addr)) => {
-                                trace!("received {} bytes from {}", ct, addr);
-                                (ct, addr)
-                            }
+                _c = close => {
+                    return ReceiverError::SocketClosed;
+                }
+                _ = timeout => {
+                    if wait_ms != liveness_ms {
+                        if self.processor.on_timer(&self.nsh, self.ident.clone()) {
+                            self.notify();
                         }
+                        continue;
                     }
-                    _c = close => {
-                        return ReceiverError::SocketClosed;
-                    }
+                    warn!("Liveness timeout — interface down");
+                    self.nsh.stack().manage_profile(|im| {
+                        _ = im.set_interface_state(
+                            self.ident.clone(),
+                            InterfaceState::Down,
+                        );
+                    });
+                    self.notify();
+                    return ReceiverError::SocketClosed;
                 }
             };
 
@@ -355,7 +355,10 @@ where
 // Registration: Router
 // ---------------------------------------------------------------------------
 
+use crate::interface_manager::admission::AdmissionPolicy;
+use crate::interface_manager::filter::PacketFilter;
 use crate::interface_manager::profiles::router::{Router, RouterFrameProcessor};
+use crate::interface_manager::rate_limit::RateLimiter;
 use crate::interface_manager::utils::framed_stream::Sink;
 use crate::interface_manager::utils::std::new_std_queue;
 use rand_core::RngCore;
@@ -373,7 +376,7 @@ pub struct RouterRegistrationError;
 /// router dialing a fixed upstream) uses `send()`. The unconnected path
 /// latches the first peer it learns and replies there for the rest of the
 /// session (one peer per bound port).
-pub async fn register_router<N, I, Rng, const M: usize, const SS: usize, const CC: usize>(
+pub async fn register_router<N, I, Rng, A, F, L, const M: usize, const SS: usize, const CC: usize>(
     stack: N,
     socket: UdpSocket,
     max_ergot_packet_size: u16,
@@ -384,7 +387,10 @@ pub async fn register_router<N, I, Rng, const M: usize, const SS: usize, const C
 where
     I: Interface<Sink = Sink<StdQueue>>,
     Rng: RngCore + Send + 'static,
-    N: NetStackHandle<Profile = Router<I, Rng, M, SS, CC>> + Send + 'static,
+    A: AdmissionPolicy + Send + 'static,
+    F: PacketFilter + Send + 'static,
+    L: RateLimiter + Send + 'static,
+    N: NetStackHandle<Profile = Router<I, Rng, M, SS, CC, A, F, L>> + Send + 'static,
 {
     let arc_socket = Arc::new(socket);
     let q: StdQueue = new_std_queue(outgoing_buffer_size);
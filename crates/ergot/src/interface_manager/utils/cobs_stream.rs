@@ -13,7 +13,7 @@ use serde::Serialize;
 use crate::{
     FrameKind, HeaderSeq, ProtocolError,
     interface_manager::InterfaceSink,
-    wire_frames::{self, MAX_HDR_ENCODED_SIZE, encode_frame_hdr},
+    wire_frames::{HeaderEncoding, MAX_HDR_ENCODED_SIZE},
 };
 
 pub struct Sink<Q>
@@ -22,6 +22,7 @@ where
 {
     pub(crate) mtu: u16,
     pub(crate) prod: StreamProducer<Q>,
+    pub(crate) encoding: HeaderEncoding,
 }
 
 #[allow(clippy::result_unit_err)] // todo
@@ -33,11 +34,24 @@ where
         Self {
             mtu,
             prod: q.stream_producer(),
+            encoding: HeaderEncoding::Standard,
         }
     }
 
     pub const fn new(prod: StreamProducer<Q>, mtu: u16) -> Self {
-        Self { mtu, prod }
+        Self {
+            mtu,
+            prod,
+            encoding: HeaderEncoding::Standard,
+        }
+    }
+
+    /// Encode frame headers with the given [`HeaderEncoding`], instead of
+    /// [`HeaderEncoding::Standard`]
+    ///
+    /// The other end of the link must decode frames with the same encoding.
+    pub fn with_header_encoding(self, encoding: HeaderEncoding) -> Self {
+        Self { encoding, ..self }
     }
 }
 
@@ -50,7 +64,7 @@ where
         self.mtu
     }
 
-    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, body: &T) -> Result<(), ()> {
+    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, body: &T) -> Result<usize, ()> {
         let is_err = hdr.kind == FrameKind::PROTOCOL_ERROR;
 
         if is_err {
@@ -62,14 +76,17 @@ where
         let mut wgr = self.prod.grant_exact(max_len).map_err(drop)?;
 
         let ser = ser_flavors::Cobs::try_new(ser_flavors::Slice::new(&mut wgr)).map_err(drop)?;
-        let used = wire_frames::encode_frame_ty(ser, hdr, body).map_err(drop)?;
+        let (used, frame_len) = self
+            .encoding
+            .encode_frame_ty(Counted::new(ser), hdr, body)
+            .map_err(drop)?;
         let len = used.len();
         wgr.commit(len);
 
-        Ok(())
+        Ok(frame_len)
     }
 
-    fn send_raw(&mut self, hdr: &HeaderSeq, body: &[u8]) -> Result<(), ()> {
+    fn send_raw(&mut self, hdr: &HeaderSeq, body: &[u8]) -> Result<usize, ()> {
         let is_err = hdr.kind == FrameKind::PROTOCOL_ERROR;
 
         if is_err {
@@ -80,18 +97,22 @@ where
         let mut wgr = self.prod.grant_exact(max_len).map_err(drop)?;
 
         let mut ser = Serializer {
-            output: ser_flavors::Cobs::try_new(ser_flavors::Slice::new(&mut wgr)).map_err(drop)?,
+            output: Counted::new(
+                ser_flavors::Cobs::try_new(ser_flavors::Slice::new(&mut wgr)).map_err(drop)?,
+            ),
         };
-        encode_frame_hdr(&mut ser, hdr).map_err(drop)?;
+        self.encoding
+            .encode_frame_hdr(&mut ser, hdr)
+            .map_err(drop)?;
         ser.output.try_extend(body).map_err(drop)?;
-        let fin = ser.output.finalize().map_err(drop)?;
+        let (fin, frame_len) = ser.output.finalize().map_err(drop)?;
         let len = fin.len();
         wgr.commit(len);
 
-        Ok(())
+        Ok(frame_len)
     }
 
-    fn send_err(&mut self, hdr: &HeaderSeq, err: ProtocolError) -> Result<(), ()> {
+    fn send_err(&mut self, hdr: &HeaderSeq, err: ProtocolError) -> Result<usize, ()> {
         let is_err = hdr.kind == FrameKind::PROTOCOL_ERROR;
 
         // note: here it SHOULD be an err!
@@ -104,10 +125,59 @@ where
         let mut wgr = self.prod.grant_exact(max_len).map_err(drop)?;
 
         let ser = ser_flavors::Cobs::try_new(ser_flavors::Slice::new(&mut wgr)).map_err(drop)?;
-        let used = wire_frames::encode_frame_err(ser, hdr, err).map_err(drop)?;
+        let (used, frame_len) = self
+            .encoding
+            .encode_frame_err(Counted::new(ser), hdr, err)
+            .map_err(drop)?;
         let len = used.len();
         wgr.commit(len);
 
+        Ok(frame_len)
+    }
+
+    fn send_link_frame(&mut self, data: &[u8]) -> Result<(), ()> {
+        let max_len = cobs::max_encoding_length(data.len() + 1);
+        let mut wgr = self.prod.grant_exact(max_len).map_err(drop)?;
+
+        let mut flav =
+            ser_flavors::Cobs::try_new(ser_flavors::Slice::new(&mut wgr)).map_err(drop)?;
+        flav.try_extend(data).map_err(drop)?;
+        let len = flav.finalize().map_err(drop)?.len();
+        wgr.commit(len);
+
+        Ok(())
+    }
+}
+
+/// A flavor counting the bytes serialized, before they are COBS encoded
+struct Counted<F> {
+    inner: F,
+    len: usize,
+}
+
+impl<F> Counted<F> {
+    fn new(inner: F) -> Self {
+        Self { inner, len: 0 }
+    }
+}
+
+impl<F: Flavor> Flavor for Counted<F> {
+    /// The output of the inner flavor, and the number of bytes serialized
+    type Output = (F::Output, usize);
+
+    fn try_push(&mut self, data: u8) -> postcard::Result<()> {
+        self.inner.try_push(data)?;
+        self.len += 1;
         Ok(())
     }
+
+    fn try_extend(&mut self, data: &[u8]) -> postcard::Result<()> {
+        self.inner.try_extend(data)?;
+        self.len += data.len();
+        Ok(())
+    }
+
+    fn finalize(self) -> postcard::Result<Self::Output> {
+        Ok((self.inner.finalize()?, self.len))
+    }
 }
@@ -13,7 +13,7 @@ use serde::Serialize;
 use crate::{
     FrameKind, HeaderSeq, ProtocolError,
     interface_manager::InterfaceSink,
-    wire_frames::{self, MAX_HDR_ENCODED_SIZE, encode_frame_hdr},
+    wire_frames::{HeaderEncoding, MAX_HDR_ENCODED_SIZE},
 };
 
 pub struct Sink<Q>
@@ -22,6 +22,7 @@ where
 {
     pub(crate) mtu: u16,
     pub(crate) prod: FramedProducer<Q, u16>,
+    pub(crate) encoding: HeaderEncoding,
 }
 
 #[allow(clippy::result_unit_err)] // todo
@@ -33,11 +34,24 @@ where
         Self {
             mtu,
             prod: q.framed_producer(),
+            encoding: HeaderEncoding::Standard,
         }
     }
 
     pub const fn new(prod: FramedProducer<Q, u16>, mtu: u16) -> Self {
-        Self { mtu, prod }
+        Self {
+            mtu,
+            prod,
+            encoding: HeaderEncoding::Standard,
+        }
+    }
+
+    /// Encode frame headers with the given [`HeaderEncoding`], instead of
+    /// [`HeaderEncoding::Standard`]
+    ///
+    /// The other end of the link must decode frames with the same encoding.
+    pub fn with_header_encoding(self, encoding: HeaderEncoding) -> Self {
+        Self { encoding, ..self }
     }
 }
 
@@ -49,7 +63,7 @@ where
         self.mtu
     }
 
-    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, body: &T) -> Result<(), ()> {
+    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, body: &T) -> Result<usize, ()> {
         let is_err = hdr.kind == FrameKind::PROTOCOL_ERROR;
 
         if is_err {
@@ -59,14 +73,17 @@ where
         let mut wgr = self.prod.grant(self.mtu).map_err(drop)?;
 
         let ser = ser_flavors::Slice::new(&mut wgr);
-        let used = wire_frames::encode_frame_ty(ser, hdr, body).map_err(drop)?;
-        let len = used.len() as u16;
-        wgr.commit(len);
-
-        Ok(())
+        let used = self
+            .encoding
+            .encode_frame_ty(ser, hdr, body)
+            .map_err(drop)?;
+        let len = used.len();
+        wgr.commit(len as u16);
+
+        Ok(len)
     }
 
-    fn send_raw(&mut self, hdr: &HeaderSeq, body: &[u8]) -> Result<(), ()> {
+    fn send_raw(&mut self, hdr: &HeaderSeq, body: &[u8]) -> Result<usize, ()> {
         let is_err = hdr.kind == FrameKind::PROTOCOL_ERROR;
 
         if is_err {
@@ -81,15 +98,17 @@ where
         let mut ser = Serializer {
             output: Slice::new(&mut wgr),
         };
-        encode_frame_hdr(&mut ser, hdr).map_err(drop)?;
+        self.encoding
+            .encode_frame_hdr(&mut ser, hdr)
+            .map_err(drop)?;
         ser.output.try_extend(body).map_err(drop)?;
         let used = ser.output.finalize().map_err(drop)?.len();
         wgr.commit(u16::try_from(used).map_err(drop)?);
 
-        Ok(())
+        Ok(used)
     }
 
-    fn send_err(&mut self, hdr: &HeaderSeq, err: ProtocolError) -> Result<(), ()> {
+    fn send_err(&mut self, hdr: &HeaderSeq, err: ProtocolError) -> Result<usize, ()> {
         let is_err = hdr.kind == FrameKind::PROTOCOL_ERROR;
 
         // note: here it SHOULD be an err!
@@ -100,8 +119,20 @@ where
         let mut wgr = self.prod.grant(self.mtu).map_err(drop)?;
 
         let ser = ser_flavors::Slice::new(&mut wgr);
-        let used = wire_frames::encode_frame_err(ser, hdr, err).map_err(drop)?;
-        let len = used.len() as u16;
+        let used = self
+            .encoding
+            .encode_frame_err(ser, hdr, err)
+            .map_err(drop)?;
+        let len = used.len();
+        wgr.commit(len as u16);
+
+        Ok(len)
+    }
+
+    fn send_link_frame(&mut self, data: &[u8]) -> Result<(), ()> {
+        let len = u16::try_from(data.len()).map_err(drop)?;
+        let mut wgr = self.prod.grant(len).map_err(drop)?;
+        wgr.copy_from_slice(data);
         wgr.commit(len);
 
         Ok(())
@@ -1,5 +1,6 @@
 pub mod cobs_stream;
 pub mod framed_stream;
+pub mod priority;
 
 #[cfg(feature = "std")]
 pub mod std;
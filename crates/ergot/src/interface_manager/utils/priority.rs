@@ -0,0 +1,95 @@
+//! Priority Sink
+//!
+//! The "Priority Sink" wraps one interface sink per [`Priority`] class, so
+//! that frames of a higher priority never wait behind lower priority frames,
+//! such as a burst of log messages, queued for the same interface.
+//!
+//! Queue `i` carries frames with priority `i`. Priorities above `N - 1` share
+//! the last queue. The transmit side must drain the queues strictly by
+//! priority, for COBS streams this is done by [`read_cobs_frame`].
+//!
+//! [`Priority`]: crate::Priority
+
+use bbqueue::{
+    prod_cons::stream::{StreamConsumer, StreamGrantR},
+    traits::{bbqhdl::BbqHandle, notifier::AsyncNotifier},
+};
+use embassy_futures::select::select_array;
+use serde::Serialize;
+
+use crate::{HeaderSeq, ProtocolError, interface_manager::InterfaceSink};
+
+/// An interface sink with one queue per priority class
+pub struct Sink<S, const N: usize> {
+    sinks: [S; N],
+}
+
+impl<S, const N: usize> Sink<S, N>
+where
+    S: InterfaceSink,
+{
+    /// Create a sink from one sink per priority class, lowest priority first
+    pub const fn new(sinks: [S; N]) -> Self {
+        const { assert!(N > 0) };
+        Self { sinks }
+    }
+
+    fn queue(&mut self, hdr: &HeaderSeq) -> &mut S {
+        let idx = (hdr.prio.0 as usize).min(N - 1);
+        &mut self.sinks[idx]
+    }
+}
+
+impl<S, const N: usize> InterfaceSink for Sink<S, N>
+where
+    S: InterfaceSink,
+{
+    fn mtu(&self) -> u16 {
+        self.sinks.iter().map(S::mtu).min().unwrap_or(0)
+    }
+
+    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, body: &T) -> Result<usize, ()> {
+        self.queue(hdr).send_ty(hdr, body)
+    }
+
+    fn send_raw(&mut self, hdr: &HeaderSeq, body: &[u8]) -> Result<usize, ()> {
+        self.queue(hdr).send_raw(hdr, body)
+    }
+
+    fn send_err(&mut self, hdr: &HeaderSeq, err: ProtocolError) -> Result<usize, ()> {
+        self.queue(hdr).send_err(hdr, err)
+    }
+
+    fn send_link_frame(&mut self, data: &[u8]) -> Result<(), ()> {
+        // Link frames are sent before anything else
+        self.sinks[N - 1].send_link_frame(data)
+    }
+}
+
+/// Wait for the next COBS frame, taken from the highest priority queue that
+/// has one
+///
+/// `rxs` are the consumers of the queues of a priority [`Sink`] made of
+/// [`cobs_stream::Sink`](super::cobs_stream::Sink)s, lowest priority first.
+/// Returns the read grant and the length of the frame at its start,
+/// including the terminating zero. Only the frame should be released, so
+/// that higher priority frames are checked again before the next one.
+pub async fn read_cobs_frame<Q, const N: usize>(
+    rxs: &[StreamConsumer<Q>; N],
+) -> (StreamGrantR<Q>, usize)
+where
+    Q: BbqHandle,
+    Q::Notifier: AsyncNotifier,
+{
+    // `select_array` polls in order, so put the highest priority first
+    let (grant, _) = select_array(core::array::from_fn::<_, N, _>(|i| {
+        rxs[N - 1 - i].wait_read()
+    }))
+    .await;
+    let len = grant
+        .iter()
+        .position(|b| *b == 0)
+        .map(|pos| pos + 1)
+        .unwrap_or(grant.len());
+    (grant, len)
+}
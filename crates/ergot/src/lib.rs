@@ -9,7 +9,12 @@ pub mod logging;
 pub mod nash;
 pub mod net_stack;
 pub mod prelude;
+#[cfg(any(feature = "link-aead", feature = "signing"))]
+mod replay;
+#[cfg(feature = "signing")]
+pub mod signing;
 pub mod socket;
+pub mod stats;
 pub mod toolkits;
 pub mod traits;
 pub mod well_known;
@@ -38,6 +43,16 @@ pub struct FrameKind(pub u8);
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Key(pub [u8; 8]);
 
+/// The priority class of a frame
+///
+/// Higher values are more urgent. Interfaces with several outgoing queues,
+/// see [`priority::Sink`](crate::interface_manager::utils::priority::Sink),
+/// always send frames of a higher priority first. Other interfaces ignore
+/// the priority.
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Priority(pub u8);
+
 #[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
@@ -89,6 +104,14 @@ pub enum ProtocolError {
     NsseAllPortMissingKey,
     /// Would deadlock
     NsseWouldDeadlock,
+    /// Message too large
+    NsseMessageTooLarge,
+
+    // Appended variants, to keep the encoding of the ones above
+    /// The socket could not verify the signature of the message
+    SseUnverified,
+    /// A packet filter denied the frame
+    IseFiltered,
 }
 
 #[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
@@ -107,6 +130,11 @@ pub struct Header {
     pub seq_no: Option<u16>,
     pub kind: FrameKind,
     pub ttl: u8,
+    /// The priority class of the frame
+    ///
+    /// Sent in every frame header since ergot 0.13, so frames can't be
+    /// exchanged with devices running an older version.
+    pub prio: Priority,
 }
 
 #[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
@@ -118,6 +146,11 @@ pub struct HeaderSeq {
     pub seq_no: u16,
     pub kind: FrameKind,
     pub ttl: u8,
+    /// The priority class of the frame
+    ///
+    /// Sent in every frame header since ergot 0.13, so frames can't be
+    /// exchanged with devices running an older version.
+    pub prio: Priority,
 }
 
 impl core::fmt::Display for Header {
@@ -153,9 +186,28 @@ impl FrameKind {
     pub const ENDPOINT_REQ: Self = Self(1);
     pub const ENDPOINT_RESP: Self = Self(2);
     pub const TOPIC_MSG: Self = Self(3);
+    pub const SESSION: Self = Self(4);
+    pub const FRAGMENT: Self = Self(5);
     pub const PROTOCOL_ERROR: Self = Self(u8::MAX);
 }
 
+impl Priority {
+    /// Bulk traffic, such as logs, that may be delayed
+    pub const LOW: Self = Self(0);
+    /// The default priority
+    pub const NORMAL: Self = Self(1);
+    /// Traffic that should overtake normal traffic
+    pub const HIGH: Self = Self(2);
+    /// Traffic that must not be delayed, such as an emergency stop
+    pub const CRITICAL: Self = Self(3);
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Self::NORMAL
+    }
+}
+
 impl postcard_schema::Schema for FrameKind {
     const SCHEMA: &'static postcard_schema::schema::NamedType =
         &postcard_schema::schema::NamedType {
@@ -182,6 +234,7 @@ impl Header {
             seq_no: _,
             kind,
             ttl,
+            prio,
         } = self;
         HeaderSeq {
             src,
@@ -190,6 +243,7 @@ impl Header {
             seq_no,
             kind,
             ttl,
+            prio,
         }
     }
 
@@ -202,6 +256,7 @@ impl Header {
             seq_no: self.seq_no.unwrap_or_else(f),
             kind: self.kind,
             ttl: self.ttl,
+            prio: self.prio,
         }
     }
 
@@ -235,6 +290,7 @@ impl From<HeaderSeq> for Header {
             seq_no: Some(val.seq_no),
             kind: val.kind,
             ttl: val.ttl,
+            prio: val.prio,
         }
     }
 }
@@ -1,4 +1,5 @@
 use crate::{
+    Priority,
     interface_manager::Profile,
     net_stack::{NetStack, NetStackHandle, Services},
 };
@@ -102,12 +103,14 @@ impl<R: ScopedRawMutex, M: Profile> ArcNetStack<R, M> {
     pub fn endpoints(&self) -> Endpoints<Self> {
         Endpoints {
             inner: self.clone(),
+            prio: Priority::NORMAL,
         }
     }
 
     pub fn topics(&self) -> Topics<Self> {
         Topics {
             inner: self.clone(),
+            prio: Priority::NORMAL,
         }
     }
 
@@ -116,4 +119,15 @@ impl<R: ScopedRawMutex, M: Profile> ArcNetStack<R, M> {
             inner: self.clone(),
         }
     }
+
+    #[cfg(any(
+        feature = "tokio-std",
+        feature = "embassy-time",
+        feature = "nostd-seed-router"
+    ))]
+    pub fn sessions(&self) -> super::sessions::Sessions<Self> {
+        super::sessions::Sessions {
+            inner: self.clone(),
+        }
+    }
 }
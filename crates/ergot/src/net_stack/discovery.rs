@@ -1,10 +1,24 @@
 #[cfg(feature = "tokio-std")]
-use crate::well_known::{SocketQuery, SocketQueryResponseAddress};
-use crate::{net_stack::NetStackHandle, well_known::DeviceInfo};
+use crate::well_known::{
+    ErgotSchemaRxOwnedEndpoint, SchemaQuery, SocketQuery, SocketQueryResponseAddress,
+    SocketSchemaRxOwned,
+};
+use crate::{
+    Address, Priority,
+    net_stack::{NetStackHandle, ReqRespError, endpoints::Endpoints},
+    well_known::{DeviceInfo, ErgotPathMtuEndpoint, PathMtuQuery},
+};
+#[cfg(feature = "std")]
+use crate::{
+    stats::{InterfaceStats, SocketStats},
+    well_known::{
+        ErgotRouterEndpoint, ErgotStatsEndpoint, NodeClaimEntry, RouterEntry, RouterQuery,
+        SeedRouteEntry, SlotEntry, Stats, StatsQuery, UpstreamEntry,
+    },
+};
 
 /// A proxy type usable for performing Discovery services
 pub struct Discovery<NS: NetStackHandle> {
-    #[allow(dead_code)]
     pub(super) inner: NS,
 }
 
@@ -14,7 +28,213 @@ pub struct DeviceRecord {
     pub info: DeviceInfo,
 }
 
+/// The counters of the interfaces and sockets of a device, see
+/// [`stats`](crate::stats)
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceStats {
+    pub interfaces: Vec<InterfaceStats>,
+    pub sockets: Vec<SocketStats>,
+}
+
+/// The interfaces and lease tables of a router, see [`RouterEntry`]
+///
+/// Seed routes and node claims include those that expired, and are still
+/// reserved as tombstones.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouterState {
+    pub slots: Vec<SlotEntry>,
+    pub seed_routes: Vec<SeedRouteEntry>,
+    pub node_claims: Vec<NodeClaimEntry>,
+    /// The upstream interface, for a bridge router
+    pub upstream: Option<UpstreamEntry>,
+}
+
+/// One hop of a path, see [`Discovery::traceroute()`]
+#[cfg(feature = "tokio-std")]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TracerouteHop {
+    /// The TTL of the probe expired at the router with this address, on the
+    /// interface the probe came from
+    Router(Address),
+    /// The destination answered the probe
+    Destination(Address),
+    /// The device at this address dropped the probe for another reason,
+    /// ending the path
+    Error {
+        addr: Address,
+        err: crate::ProtocolError,
+    },
+    /// Nothing answered the probe in time
+    Timeout,
+}
+
 impl<NS: NetStackHandle> Discovery<NS> {
+    /// Discover the path MTU towards the device at `addr`
+    ///
+    /// Sends a query to the [`ErgotPathMtuEndpoint`] of the device, usually
+    /// handled by `Services::path_mtu_handler()`. Each interface on the way
+    /// lowers the MTU reported by the query. The result is stored in the
+    /// [`path_mtu`](crate::net_stack::path_mtu) cache of the local
+    /// [`NetStack`](crate::NetStack).
+    ///
+    /// The port of `addr` is ignored. A query to a local address reports
+    /// `u16::MAX`, as no interface is involved.
+    pub async fn discover_path_mtu(&self, addr: Address) -> Result<u16, ReqRespError> {
+        let dst = Address { port_id: 0, ..addr };
+        let res = Endpoints {
+            inner: self.inner.clone(),
+            prio: Priority::NORMAL,
+        }
+        .request::<ErgotPathMtuEndpoint>(dst, &PathMtuQuery { path_mtu: u16::MAX }, None)
+        .await?;
+        self.inner.stack().cache_path_mtu(addr, res.path_mtu);
+        Ok(res.path_mtu)
+    }
+
+    /// Discover the message types of the sockets of the device at `addr`
+    ///
+    /// Sends queries to the schema endpoint of the device, usually handled by
+    /// `Services::schema_handler()`, until every topic receiver and endpoint
+    /// server has been listed. An endpoint server is listed twice: once for
+    /// its requests, and once for its responses.
+    ///
+    /// Sockets are listed by index, one request at a time: if sockets are
+    /// attached or detached meanwhile, some may be skipped or listed twice.
+    /// Sockets whose information is too large for the handler to send are
+    /// skipped. Each request gives up with [`ReqRespError::Timeout`] if no
+    /// response is received within `timeout`. The port of `addr` is ignored.
+    #[cfg(feature = "tokio-std")]
+    pub async fn discover_schemas(
+        &self,
+        addr: Address,
+        timeout: std::time::Duration,
+    ) -> Result<Vec<SocketSchemaRxOwned>, ReqRespError> {
+        let dst = Address { port_id: 0, ..addr };
+        let endpoints = Endpoints {
+            inner: self.inner.clone(),
+            prio: Priority::NORMAL,
+        };
+        let mut schemas = vec![];
+        for index in 0..=u16::MAX {
+            let res = endpoints
+                .clone()
+                .request_with_deadline::<ErgotSchemaRxOwnedEndpoint>(
+                    dst,
+                    &SchemaQuery { index },
+                    None,
+                    timeout,
+                )
+                .await;
+            match res {
+                Ok(Some(schema)) => schemas.push(schema),
+                Ok(None) => break,
+                Err(ReqRespError::Remote(crate::ProtocolError::NsseMessageTooLarge)) => {
+                    crate::logging::debug!("schema {} of {:?} is too large", index, addr);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(schemas)
+    }
+
+    /// Discover the counters of the interfaces and sockets of the device at
+    /// `addr`
+    ///
+    /// Sends queries to the [`ErgotStatsEndpoint`] of the device, usually
+    /// handled by `Services::stats_handler()`, until every interface and
+    /// socket has been listed, one request at a time, like
+    /// [`discover_schemas()`](Self::discover_schemas). The port of `addr` is
+    /// ignored.
+    #[cfg(feature = "std")]
+    pub async fn discover_stats(&self, addr: Address) -> Result<DeviceStats, ReqRespError> {
+        let dst = Address { port_id: 0, ..addr };
+        let endpoints = Endpoints {
+            inner: self.inner.clone(),
+            prio: Priority::NORMAL,
+        };
+        let mut stats = DeviceStats {
+            interfaces: vec![],
+            sockets: vec![],
+        };
+        for index in 0..=u16::MAX {
+            let query = StatsQuery::Interface { index };
+            match endpoints
+                .clone()
+                .request::<ErgotStatsEndpoint>(dst, &query, None)
+                .await?
+            {
+                Some(Stats::Interface(s)) => stats.interfaces.push(s),
+                _ => break,
+            }
+        }
+        for index in 0..=u16::MAX {
+            let query = StatsQuery::Socket { index };
+            match endpoints
+                .clone()
+                .request::<ErgotStatsEndpoint>(dst, &query, None)
+                .await?
+            {
+                Some(Stats::Socket(s)) => stats.sockets.push(s),
+                _ => break,
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Discover the interfaces and lease tables of the router at `addr`
+    ///
+    /// Sends queries to the [`ErgotRouterEndpoint`] of the router, usually
+    /// handled by `Services::router_handler()`, until every entry of each
+    /// table has been listed, one request at a time, like
+    /// [`discover_schemas()`](Self::discover_schemas). A device that isn't a
+    /// router reports no entries. The port of `addr` is ignored.
+    #[cfg(feature = "std")]
+    pub async fn discover_router(&self, addr: Address) -> Result<RouterState, ReqRespError> {
+        let dst = Address { port_id: 0, ..addr };
+        let endpoints = Endpoints {
+            inner: self.inner.clone(),
+            prio: Priority::NORMAL,
+        };
+        let request = |query: RouterQuery| {
+            let endpoints = endpoints.clone();
+            async move {
+                endpoints
+                    .request::<ErgotRouterEndpoint>(dst, &query, None)
+                    .await
+            }
+        };
+        let mut state = RouterState {
+            slots: vec![],
+            seed_routes: vec![],
+            node_claims: vec![],
+            upstream: None,
+        };
+        for index in 0..=u16::MAX {
+            match request(RouterQuery::Slot { index }).await? {
+                Some(RouterEntry::Slot(slot)) => state.slots.push(slot),
+                _ => break,
+            }
+        }
+        for index in 0..=u16::MAX {
+            match request(RouterQuery::SeedRoute { index }).await? {
+                Some(RouterEntry::SeedRoute(route)) => state.seed_routes.push(route),
+                _ => break,
+            }
+        }
+        for index in 0..=u16::MAX {
+            match request(RouterQuery::NodeClaim { index }).await? {
+                Some(RouterEntry::NodeClaim(claim)) => state.node_claims.push(claim),
+                _ => break,
+            }
+        }
+        if let Some(RouterEntry::Upstream(up)) = request(RouterQuery::Upstream).await? {
+            state.upstream = Some(up);
+        }
+        Ok(state)
+    }
+
     /// Discover devices on the network
     ///
     /// Terminates when the timeout is reached
@@ -27,6 +247,7 @@ impl<NS: NetStackHandle> Discovery<NS> {
 
         let topics = Topics {
             inner: self.inner.clone(),
+            prio: Priority::NORMAL,
         };
         let subber = topics
             .clone()
@@ -84,6 +305,7 @@ impl<NS: NetStackHandle> Discovery<NS> {
         // Set up listener for responses
         let topics = Topics {
             inner: self.inner.clone(),
+            prio: Priority::NORMAL,
         };
         let subber = topics
             .clone()
@@ -118,4 +340,65 @@ impl<NS: NetStackHandle> Discovery<NS> {
 
         rxd
     }
+
+    /// Discover the routers on the path towards the device at `dst`
+    ///
+    /// Sends pings to the [`ErgotPingEndpoint`] of the device, usually
+    /// handled by `Services::ping_handler()`, with a TTL of 1, 2, 3, ...
+    /// Each router on the path drops the probe whose TTL expires there, and
+    /// answers it with a [`ProtocolError::IseTtlExpired`] sent from its own
+    /// address. The path ends when the destination answers, when a probe is
+    /// dropped for another reason, or after [`DEFAULT_TTL`] hops.
+    ///
+    /// A hop that doesn't answer within `timeout` is reported as
+    /// [`TracerouteHop::Timeout`], and the following hops are still probed.
+    /// The port of `dst` is ignored.
+    ///
+    /// [`ErgotPingEndpoint`]: crate::well_known::ErgotPingEndpoint
+    /// [`ProtocolError::IseTtlExpired`]: crate::ProtocolError::IseTtlExpired
+    /// [`DEFAULT_TTL`]: crate::DEFAULT_TTL
+    #[cfg(feature = "tokio-std")]
+    pub async fn traceroute(
+        &self,
+        dst: Address,
+        timeout: std::time::Duration,
+    ) -> Result<Vec<TracerouteHop>, ReqRespError> {
+        use crate::{DEFAULT_TTL, ProtocolError, well_known::ErgotPingEndpoint};
+
+        let dst = Address { port_id: 0, ..dst };
+        let endpoints = Endpoints {
+            inner: self.inner.clone(),
+            prio: Priority::NORMAL,
+        };
+        let mut hops = vec![];
+        for ttl in 1..=DEFAULT_TTL {
+            let res = endpoints
+                .clone()
+                .probe_with_timeout::<ErgotPingEndpoint, _>(
+                    dst,
+                    &u32::from(ttl),
+                    ttl,
+                    tokio::time::sleep(timeout),
+                )
+                .await;
+            let hop = match res {
+                Ok(Ok(resp)) => TracerouteHop::Destination(resp.hdr.src),
+                Ok(Err(err)) if err.t == ProtocolError::IseTtlExpired => {
+                    TracerouteHop::Router(err.hdr.src)
+                }
+                Ok(Err(err)) => TracerouteHop::Error {
+                    addr: err.hdr.src,
+                    err: err.t,
+                },
+                Err(ReqRespError::Timeout) => TracerouteHop::Timeout,
+                Err(e) => return Err(e),
+            };
+            let done = !matches!(hop, TracerouteHop::Router(_) | TracerouteHop::Timeout);
+            hops.push(hop);
+            if done {
+                break;
+            }
+        }
+        Ok(hops)
+    }
 }
@@ -1,46 +1,204 @@
-use core::{marker::PhantomData, pin::pin};
+use core::{marker::PhantomData, pin::pin, time::Duration};
 
+use embassy_futures::select::{Either, select};
 use serde::{Serialize, de::DeserializeOwned};
 
 use crate::{
-    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, HeaderSeq, Key, nash::NameHash,
-    socket::HeaderMessage, traits::Endpoint,
+    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, HeaderSeq, Key, Priority,
+    ProtocolError,
+    logging::debug,
+    nash::NameHash,
+    socket::{
+        HeaderMessage, Response, endpoint::raw, raw_owned::Storage, stream_endpoint::StreamSink,
+    },
+    traits::{Endpoint, StreamEndpoint},
 };
 
+#[cfg(any(
+    feature = "tokio-std",
+    feature = "embassy-time",
+    feature = "nostd-seed-router"
+))]
+use super::timer_sleep;
 use super::{NetStackHandle, NetStackSendError, ReqRespError};
 
 /// A proxy type usable for creating helper services
 #[derive(Clone)]
 pub struct Endpoints<NS: NetStackHandle> {
     pub(super) inner: NS,
+    pub(super) prio: Priority,
+}
+
+/// Retry and backoff policy for requests that did not receive a response in time
+///
+/// Only timeouts and local interface send failures are retried. Errors
+/// reported by the remote, or routing errors, are returned immediately.
+///
+/// Note that a retry is a fresh request: the server may see the same request
+/// more than once, so retried endpoints should be idempotent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one. `0` is treated as `1`.
+    pub attempts: u8,
+    /// Delay before the first retry
+    pub backoff: Duration,
+    /// Upper bound for the delay between retries. The delay doubles after
+    /// each retry until this limit is reached.
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Send the request once, never retry
+    pub const NONE: Self = Self {
+        attempts: 1,
+        backoff: Duration::ZERO,
+        max_backoff: Duration::ZERO,
+    };
+
+    /// Retry up to `attempts` total attempts, with no delay between them
+    pub const fn attempts(attempts: u8) -> Self {
+        Self {
+            attempts,
+            backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    /// Set an exponential backoff, starting at `backoff` and capped at `max_backoff`
+    pub const fn with_backoff(self, backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            attempts: self.attempts,
+            backoff,
+            max_backoff,
+        }
+    }
+
+    fn should_retry(err: &ReqRespError) -> bool {
+        matches!(
+            err,
+            ReqRespError::Timeout | ReqRespError::Local(NetStackSendError::InterfaceSend(_))
+        )
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::NONE
+    }
 }
 
 pub struct EndpointClient<'a, E: Endpoint, NS: NetStackHandle> {
     inner: NS,
     name: Option<&'a str>,
     address: Address,
+    deadline: Option<Duration>,
+    retry: RetryPolicy,
+    prio: Priority,
     _pd: PhantomData<fn() -> E>,
 }
 
-impl<E, NS> EndpointClient<'_, E, NS>
+impl<'a, E, NS> EndpointClient<'a, E, NS>
 where
     E: Endpoint,
     NS: NetStackHandle,
 {
+    /// Set the default deadline used for each attempt of [`Self::request`]
+    pub fn with_deadline(self, deadline: Duration) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..self
+        }
+    }
+
+    /// Set the retry policy used by [`Self::request`]
+    ///
+    /// Retries are only performed if a deadline has been set with
+    /// [`Self::with_deadline`].
+    pub fn with_retry(self, retry: RetryPolicy) -> Self {
+        Self { retry, ..self }
+    }
+
+    /// Set the [`Priority`] of requests sent by this client
+    ///
+    /// Responses are sent with the priority of the request.
+    pub fn with_priority(self, prio: Priority) -> Self {
+        Self { prio, ..self }
+    }
+
+    /// Perform a request, using the client's default deadline and retry policy
+    ///
+    /// If no deadline has been set, this waits for a response forever. With
+    /// the `tokio-std`, `embassy-time`, or `nostd-seed-router` features
+    /// disabled, the deadline is ignored; use [`Self::request_with_sleep`]
+    /// instead.
     pub async fn request(&self, req: &E::Request) -> Result<E::Response, ReqRespError>
     where
         E: Endpoint,
         E::Request: Serialize + Clone + DeserializeOwned + 'static,
         E::Response: Serialize + Clone + DeserializeOwned + 'static,
     {
+        #[cfg(any(
+            feature = "tokio-std",
+            feature = "embassy-time",
+            feature = "nostd-seed-router"
+        ))]
+        if self.deadline.is_some() {
+            return self.request_with_sleep(req, timer_sleep).await;
+        }
+
         let ep = Endpoints {
             inner: self.inner.clone(),
+            prio: self.prio,
         };
         ep.request::<E>(self.address, req, self.name).await
     }
+
+    /// Same as [`Self::request`], but using a caller-provided sleep function for
+    /// deadlines and backoff, for executors without a built-in timer.
+    pub async fn request_with_sleep<T, F>(
+        &self,
+        req: &E::Request,
+        sleep: T,
+    ) -> Result<E::Response, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+        T: Fn(Duration) -> F,
+        F: Future<Output = ()>,
+    {
+        let ep = Endpoints {
+            inner: self.inner.clone(),
+            prio: self.prio,
+        };
+        match self.deadline {
+            Some(deadline) => {
+                let resp = ep
+                    .request_full_with_retry::<E, T, F>(
+                        self.address,
+                        req,
+                        self.name,
+                        deadline,
+                        &self.retry,
+                        sleep,
+                    )
+                    .await?;
+                Ok(resp.t)
+            }
+            None => ep.request::<E>(self.address, req, self.name).await,
+        }
+    }
 }
 
 impl<NS: NetStackHandle> Endpoints<NS> {
+    /// Send requests with the given [`Priority`], instead of [`Priority::NORMAL`]
+    ///
+    /// Servers respond with the priority of the request, so this also applies
+    /// to the responses.
+    pub fn with_priority(self, prio: Priority) -> Self {
+        Self { prio, ..self }
+    }
+
     pub fn client<E: Endpoint>(
         self,
         address: Address,
@@ -51,6 +209,9 @@ impl<NS: NetStackHandle> Endpoints<NS> {
             _pd: PhantomData,
             name,
             address,
+            deadline: None,
+            retry: RetryPolicy::NONE,
+            prio: self.prio,
         }
     }
 
@@ -118,6 +279,160 @@ impl<NS: NetStackHandle> Endpoints<NS> {
         E: Endpoint,
         E::Request: Serialize + Clone + DeserializeOwned + 'static,
         E::Response: Serialize + Clone + DeserializeOwned + 'static,
+    {
+        self.request_full_with_timeout::<E, _>(dst, req, name, core::future::pending())
+            .await
+    }
+
+    /// Same as [`Self::request_full`], but gives up with [`ReqRespError::Timeout`]
+    /// once the `timeout` future completes.
+    ///
+    /// This is useful for executors where the crate cannot choose a timer
+    /// implementation. See also [`Self::request_with_deadline`].
+    pub async fn request_full_with_timeout<E, F>(
+        self,
+        dst: Address,
+        req: &E::Request,
+        name: Option<&str>,
+        timeout: F,
+    ) -> Result<HeaderMessage<E::Response>, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+        F: Future<Output = ()>,
+    {
+        let stack = self.inner.stack();
+        self.request_full_with_send::<E, F, _>(dst, name, timeout, |hdr| stack.send_ty(hdr, req))
+            .await
+    }
+
+    /// Same as [`Self::request_full_with_timeout`], but signs the request with
+    /// `signer`, see [`crate::signing`]
+    ///
+    /// `scratch` is used to serialize and sign the request, see
+    /// [`NetStack::send_ty_signed()`](crate::NetStack::send_ty_signed). A
+    /// response from another device is only accepted if the server signed it
+    /// with the same key, which requires the server to trust `signer`, see
+    /// [Responses](crate::signing#responses).
+    #[cfg(feature = "signing")]
+    pub async fn request_signed_with_timeout<E, F>(
+        self,
+        dst: Address,
+        req: &E::Request,
+        name: Option<&str>,
+        signer: &crate::signing::Signer,
+        scratch: &mut [u8],
+        timeout: F,
+    ) -> Result<HeaderMessage<E::Response>, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+        F: Future<Output = ()>,
+    {
+        let stack = self.inner.stack();
+        let key = Key(E::REQ_KEY.to_bytes());
+        self.request_full_with_send::<E, F, _>(dst, name, timeout, |hdr| {
+            stack.send_ty_signed(hdr, &key, req, signer, scratch)
+        })
+        .await
+    }
+
+    /// Perform an [`Endpoint`] Request signed with `signer`, see
+    /// [`Self::request_signed_with_timeout`]
+    #[cfg(feature = "signing")]
+    pub async fn request_signed<E>(
+        self,
+        dst: Address,
+        req: &E::Request,
+        name: Option<&str>,
+        signer: &crate::signing::Signer,
+        scratch: &mut [u8],
+    ) -> Result<E::Response, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+    {
+        let resp = self
+            .request_signed_with_timeout::<E, _>(
+                dst,
+                req,
+                name,
+                signer,
+                scratch,
+                core::future::pending(),
+            )
+            .await?;
+        Ok(resp.t)
+    }
+
+    /// Send a request with `send`, and wait for its response
+    async fn request_full_with_send<E, F, S>(
+        self,
+        dst: Address,
+        name: Option<&str>,
+        timeout: F,
+        send: S,
+    ) -> Result<HeaderMessage<E::Response>, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+        F: Future<Output = ()>,
+        S: FnOnce(&Header) -> Result<(), NetStackSendError>,
+    {
+        match self
+            .exchange_with_send::<E, F, S>(dst, name, timeout, send)
+            .await?
+        {
+            Ok(msg) => Ok(msg),
+            Err(e) => Err(ReqRespError::Remote(e.t)),
+        }
+    }
+
+    /// Send a request with a TTL of `ttl`, and wait for its response or for
+    /// the error it caused
+    ///
+    /// Unlike the `request` methods, the header of an error is kept, so that
+    /// the device that reported it is known. Used by `Discovery::traceroute()`.
+    #[cfg(feature = "tokio-std")]
+    pub(crate) async fn probe_with_timeout<E, F>(
+        self,
+        dst: Address,
+        req: &E::Request,
+        ttl: u8,
+        timeout: F,
+    ) -> Result<Result<HeaderMessage<E::Response>, HeaderMessage<ProtocolError>>, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+        F: Future<Output = ()>,
+    {
+        let stack = self.inner.stack();
+        self.exchange_with_send::<E, F, _>(dst, None, timeout, |hdr| {
+            stack.send_ty(&Header { ttl, ..hdr.clone() }, req)
+        })
+        .await
+    }
+
+    /// Send a request with `send`, and wait for its response or for an error
+    /// reported by the network
+    async fn exchange_with_send<E, F, S>(
+        self,
+        dst: Address,
+        name: Option<&str>,
+        timeout: F,
+        send: S,
+    ) -> Result<Result<HeaderMessage<E::Response>, HeaderMessage<ProtocolError>>, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+        F: Future<Output = ()>,
+        S: FnOnce(&Header) -> Result<(), NetStackSendError>,
     {
         // Response doesn't need a name because we will reply back.
         //
@@ -141,6 +456,10 @@ impl<NS: NetStackHandle> Endpoints<NS> {
             _ => None,
         };
 
+        // Responses carry the seq_no of the request. The port may have been
+        // used by an earlier request, so anything else is a late response to
+        // that request.
+        let seq_no = stack.next_seq_no();
         let hdr = Header {
             src: Address {
                 network_id: 0,
@@ -149,18 +468,342 @@ impl<NS: NetStackHandle> Endpoints<NS> {
             },
             dst,
             any_all,
+            seq_no: Some(seq_no),
+            kind: FrameKind::ENDPOINT_REQ,
+            ttl: DEFAULT_TTL,
+            prio: self.prio,
+        };
+        send(&hdr).map_err(ReqRespError::Local)?;
+        let recv = async {
+            loop {
+                match resp_hdl.recv().await {
+                    Ok(msg) if msg.hdr.seq_no == seq_no => return Ok(msg),
+                    Err(e) if e.hdr.seq_no == seq_no => return Err(e),
+                    _ => {
+                        debug!("Dropping stray response on port {}", resp_hdl.port());
+                    }
+                }
+            }
+        };
+        match select(recv, timeout).await {
+            Either::First(res) => Ok(res),
+            Either::Second(()) => Err(ReqRespError::Timeout),
+        }
+    }
+
+    /// Perform a request with a per-attempt `deadline`, retrying according to `policy`.
+    ///
+    /// `sleep` is used both for the deadline of each attempt and for the backoff
+    /// delay between attempts. Each attempt uses a fresh response socket, so a
+    /// late response to an earlier attempt is never mistaken for a later one.
+    pub async fn request_full_with_retry<E, T, F>(
+        self,
+        dst: Address,
+        req: &E::Request,
+        name: Option<&str>,
+        deadline: Duration,
+        policy: &RetryPolicy,
+        sleep: T,
+    ) -> Result<HeaderMessage<E::Response>, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+        T: Fn(Duration) -> F,
+        F: Future<Output = ()>,
+    {
+        let attempts = policy.attempts.max(1);
+        let mut backoff = policy.backoff;
+        let mut attempt = 1;
+        loop {
+            let res = self
+                .clone()
+                .request_full_with_timeout::<E, F>(dst, req, name, sleep(deadline))
+                .await;
+            match res {
+                Err(e) if attempt < attempts && RetryPolicy::should_retry(&e) => {}
+                res => return res,
+            }
+            attempt += 1;
+            if !backoff.is_zero() {
+                sleep(backoff).await;
+                backoff = backoff
+                    .saturating_mul(2)
+                    .min(policy.max_backoff.max(policy.backoff));
+            }
+        }
+    }
+
+    /// Perform an [`Endpoint`] Request, giving up with [`ReqRespError::Timeout`] if
+    /// no response is received within `deadline`.
+    #[cfg(any(
+        feature = "tokio-std",
+        feature = "embassy-time",
+        feature = "nostd-seed-router"
+    ))]
+    pub async fn request_with_deadline<E>(
+        self,
+        dst: Address,
+        req: &E::Request,
+        name: Option<&str>,
+        deadline: Duration,
+    ) -> Result<E::Response, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+    {
+        let resp = self
+            .request_full_with_timeout::<E, _>(dst, req, name, timer_sleep(deadline))
+            .await?;
+        Ok(resp.t)
+    }
+
+    /// Perform an [`Endpoint`] Request with a per-attempt `deadline`, retrying
+    /// according to `policy`.
+    #[cfg(any(
+        feature = "tokio-std",
+        feature = "embassy-time",
+        feature = "nostd-seed-router"
+    ))]
+    pub async fn request_with_retry<E>(
+        self,
+        dst: Address,
+        req: &E::Request,
+        name: Option<&str>,
+        deadline: Duration,
+        policy: &RetryPolicy,
+    ) -> Result<E::Response, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+    {
+        let resp = self
+            .request_full_with_retry::<E, _, _>(dst, req, name, deadline, policy, timer_sleep)
+            .await?;
+        Ok(resp.t)
+    }
+
+    /// Send an [`Endpoint`] Request to every server of the endpoint within
+    /// `scope`, and collect up to `N` Responses until `timeout` elapses.
+    ///
+    /// The request is broadcast, so it is delivered to all local servers, and
+    /// flooded to all other devices the same way as a topic broadcast. The
+    /// port of `scope` is ignored. If `name` is given, only servers with that
+    /// name respond.
+    ///
+    /// Responses are returned in the order they arrived. Error responses from
+    /// individual devices are skipped, and if no device could be reached at
+    /// all, no Responses are returned.
+    ///
+    /// See [`Self::heap_request_all`] for a version that collects a runtime
+    /// number of Responses on the heap.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use core::{pin::pin, time::Duration};
+    /// # use mutex::raw_impls::cs::CriticalSectionRawMutex as CSRMutex;
+    /// # use ergot::{Address, NetStack, interface_manager::profiles::null::Null};
+    /// ergot::endpoint!(Temperature, (), f32, "example/temperature");
+    ///
+    /// static STACK: NetStack<CSRMutex, Null> = NetStack::new();
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let srv = STACK.endpoints().bounded_server::<Temperature, 4>(None);
+    ///     let srv = pin!(srv);
+    ///     let mut srv = srv.attach();
+    ///
+    ///     let (resps, _) = tokio::join!(
+    ///         STACK.endpoints().request_all::<Temperature, 8>(
+    ///             Address::unknown(),
+    ///             &(),
+    ///             None,
+    ///             Duration::from_millis(50),
+    ///         ),
+    ///         srv.serve_blocking(|_| 21.5),
+    ///     );
+    ///     let resps = resps.unwrap();
+    ///     assert_eq!(resps.len(), 1);
+    ///     assert_eq!(resps[0].t, 21.5);
+    /// }
+    /// ```
+    #[cfg(any(
+        feature = "tokio-std",
+        feature = "embassy-time",
+        feature = "nostd-seed-router"
+    ))]
+    pub async fn request_all<E, const N: usize>(
+        self,
+        scope: Address,
+        req: &E::Request,
+        name: Option<&str>,
+        timeout: Duration,
+    ) -> Result<heapless::Vec<HeaderMessage<E::Response>, N>, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+    {
+        self.request_all_with_timeout::<E, _, N>(scope, req, name, timer_sleep(timeout))
+            .await
+    }
+
+    /// Same as [`Self::request_all`], but collects Responses until the
+    /// `timeout` future completes.
+    pub async fn request_all_with_timeout<E, F, const N: usize>(
+        self,
+        scope: Address,
+        req: &E::Request,
+        name: Option<&str>,
+        timeout: F,
+    ) -> Result<heapless::Vec<HeaderMessage<E::Response>, N>, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+        F: Future<Output = ()>,
+    {
+        let mut resps = heapless::Vec::new();
+        self.collect_all::<E, _, _>(
+            crate::socket::owned::stack_vec::Bounded::<_, N>::new(),
+            scope,
+            req,
+            name,
+            timeout,
+            N,
+            |msg| {
+                // At most N responses are collected
+                let _ = resps.push(msg);
+            },
+        )
+        .await?;
+        Ok(resps)
+    }
+
+    /// Same as [`Self::request_all`], but collects up to `max` Responses on
+    /// the heap.
+    #[cfg(all(
+        feature = "std",
+        any(
+            feature = "tokio-std",
+            feature = "embassy-time",
+            feature = "nostd-seed-router"
+        )
+    ))]
+    pub async fn heap_request_all<E>(
+        self,
+        scope: Address,
+        req: &E::Request,
+        name: Option<&str>,
+        timeout: Duration,
+        max: usize,
+    ) -> Result<std::vec::Vec<HeaderMessage<E::Response>>, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+    {
+        self.heap_request_all_with_timeout::<E, _>(scope, req, name, timer_sleep(timeout), max)
+            .await
+    }
+
+    /// Same as [`Self::heap_request_all`], but collects Responses until the
+    /// `timeout` future completes.
+    #[cfg(feature = "std")]
+    pub async fn heap_request_all_with_timeout<E, F>(
+        self,
+        scope: Address,
+        req: &E::Request,
+        name: Option<&str>,
+        timeout: F,
+        max: usize,
+    ) -> Result<std::vec::Vec<HeaderMessage<E::Response>>, ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+        F: Future<Output = ()>,
+    {
+        let mut resps = std::vec::Vec::new();
+        self.collect_all::<E, _, _>(
+            crate::socket::owned::std_bounded::Bounded::with_bound(max),
+            scope,
+            req,
+            name,
+            timeout,
+            max,
+            |msg| resps.push(msg),
+        )
+        .await?;
+        Ok(resps)
+    }
+
+    /// Broadcast a request, and pass up to `max` responses to `push`
+    #[allow(clippy::too_many_arguments)]
+    async fn collect_all<E, S, F>(
+        self,
+        sto: S,
+        scope: Address,
+        req: &E::Request,
+        name: Option<&str>,
+        timeout: F,
+        max: usize,
+        mut push: impl FnMut(HeaderMessage<E::Response>),
+    ) -> Result<(), ReqRespError>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + DeserializeOwned + 'static,
+        S: Storage<Response<E::Response>>,
+        F: Future<Output = ()>,
+    {
+        // Unlike `request_full_with_timeout`, we expect any number of
+        // responses, so the response socket needs room for all of them.
+        let stack = self.inner.stack();
+        let resp_sock = raw::Client::<S, E, NS>::new(self.inner, sto, None);
+        let resp_sock = pin!(resp_sock);
+        let mut resp_hdl = resp_sock.attach();
+
+        let hdr = Header {
+            src: Address {
+                network_id: 0,
+                node_id: 0,
+                port_id: resp_hdl.port(),
+            },
+            dst: Address {
+                port_id: 255,
+                ..scope
+            },
+            any_all: Some(AnyAllAppendix {
+                key: Key(E::REQ_KEY.to_bytes()),
+                nash: name.map(NameHash::new),
+            }),
             seq_no: None,
             kind: FrameKind::ENDPOINT_REQ,
             ttl: DEFAULT_TTL,
+            prio: self.prio,
         };
-        stack.send_ty(&hdr, req).map_err(ReqRespError::Local)?;
-        // TODO: assert seq nos match somewhere? do we NEED seq nos if we have
-        // port ids now?
-        let resp = resp_hdl.recv().await;
-        match resp {
-            Ok(msg) => Ok(msg),
-            Err(e) => Err(ReqRespError::Remote(e.t)),
+        match stack.send_ty(&hdr, req) {
+            Ok(()) => {}
+            // Nobody could be reached, so nobody will respond
+            Err(NetStackSendError::NoRoute) => return Ok(()),
+            Err(e) => return Err(ReqRespError::Local(e)),
         }
+
+        let collect = async {
+            let mut count = 0;
+            while count < max {
+                if let Ok(msg) = resp_hdl.recv().await {
+                    push(msg);
+                    count += 1;
+                }
+            }
+        };
+        select(collect, timeout).await;
+        Ok(())
     }
 
     /// Send an endpoint response. Useful if you used `recv_manual()` and need to make a manual
@@ -182,10 +825,49 @@ impl<NS: NetStackHandle> Endpoints<NS> {
             seq_no: Some(req_hdr.seq_no),
             kind: FrameKind::ENDPOINT_RESP,
             ttl: DEFAULT_TTL,
+            prio: req_hdr.prio,
         };
         self.inner.stack().send_ty::<E::Response>(&hdr, resp)
     }
 
+    /// Create a sink for streaming responses to a [`StreamEndpoint`] request.
+    /// Useful if you used `recv_manual()` and need to make a manual response.
+    ///
+    /// `port` is the port of the server that received the request.
+    pub fn stream_sink<E>(self, req_hdr: &HeaderSeq, port: u8) -> StreamSink<E, NS>
+    where
+        E: StreamEndpoint,
+        E::Item: Serialize + Clone + DeserializeOwned + 'static,
+    {
+        StreamSink::new(self.inner.stack(), req_hdr, port)
+    }
+
+    /// Create a client socket for [`StreamEndpoint`] requests, queueing up to
+    /// `N` response items
+    pub fn stream_client<E, const N: usize>(
+        self,
+    ) -> crate::socket::stream_endpoint::Client<E, NS, N>
+    where
+        E: StreamEndpoint,
+        E::Item: Serialize + Clone + DeserializeOwned + 'static,
+    {
+        crate::socket::stream_endpoint::Client::new(self.inner, None).with_priority(self.prio)
+    }
+
+    /// Create a client socket that can have many requests in flight at once,
+    /// receiving all responses on a single port.
+    ///
+    /// See the [`multiplex`](crate::socket::multiplex) module for details.
+    pub fn multiplex_client<E: Endpoint, const N: usize>(
+        self,
+    ) -> crate::socket::multiplex::Client<E, NS, N>
+    where
+        E::Request: Serialize + DeserializeOwned + Clone,
+        E::Response: Serialize + DeserializeOwned + Clone,
+    {
+        crate::socket::multiplex::Client::new(self.inner).with_priority(self.prio)
+    }
+
     pub fn single_client<E: Endpoint>(self) -> crate::socket::endpoint::single::Client<E, NS>
     where
         E::Request: Serialize + DeserializeOwned + Clone,
@@ -0,0 +1,429 @@
+//! Topic History
+//!
+//! For post-mortem debugging, a device or router can keep the last messages
+//! of selected topics in a [`TopicHistory`], with the time each message was
+//! received. Each topic is recorded by a
+//! [`Services::topic_history_recorder()`], which subscribes to it with a
+//! borrowed receiver and stores each message as its serialized bytes, so that
+//! the history itself does not need the concrete Rust types.
+//!
+//! The history is queried by topic key, and by sequence number and time
+//! range, with [`Services::topic_history_handler()`] serving the
+//! [`ErgotTopicHistoryEndpoint`]. Each request returns a single message, so
+//! [`TopicHistoryQuery`], created with [`Topics::history()`], walks through
+//! the matching messages one request at a time.
+//!
+//! Messages longer than [`MAX_HISTORY_BODY_LEN`] are recorded, but only their
+//! sequence number, time and sender can be queried.
+//!
+//! [`TopicHistory`] requires the `std` feature, as the number of messages
+//! kept for each topic is chosen at runtime. Since each message takes one
+//! request, reading a long history over a slow link takes a while: narrow
+//! the query with [`TopicHistoryQuery::seq()`] or
+//! [`TopicHistoryQuery::time_ms()`] where possible.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use core::pin::pin;
+//! # use mutex::raw_impls::cs::CriticalSectionRawMutex as CSRMutex;
+//! # use ergot::{Address, NetStack, interface_manager::profiles::null::Null};
+//! use ergot::net_stack::history::TopicHistory;
+//!
+//! ergot::topic!(Status, u32, "example/status");
+//!
+//! static STACK: NetStack<CSRMutex, Null> = NetStack::new();
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let history = TopicHistory::<CSRMutex>::new();
+//!     let recorder = STACK.services().topic_history_recorder::<Status>(&history, 8, 256);
+//!     let handler = STACK.services().topic_history_handler::<4>(&history);
+//!
+//!     let client = async {
+//!         for status in 0..10 {
+//!             STACK.topics().broadcast::<Status>(&status, None).unwrap();
+//!         }
+//!
+//!         // Only the last 8 messages are kept
+//!         let mut query = STACK.topics().history::<Status>(Address::unknown());
+//!         let first = query.next_record().await.unwrap().unwrap();
+//!         assert_eq!((first.seq, first.msg), (2, Some(2)));
+//!
+//!         let mut query = STACK
+//!             .topics()
+//!             .history::<Status>(Address::unknown())
+//!             .seq(5..=6);
+//!         let mut seen = vec![];
+//!         while let Some(record) = query.next_record().await.unwrap() {
+//!             seen.push(record.msg.unwrap());
+//!         }
+//!         assert_eq!(seen, [5, 6]);
+//!     };
+//!
+//!     tokio::select! {
+//!         biased;
+//!         _ = recorder => unreachable!(),
+//!         _ = handler => unreachable!(),
+//!         _ = client => {}
+//!     }
+//! }
+//! ```
+//!
+//! [`Services::topic_history_recorder()`]: crate::net_stack::services::Services::topic_history_recorder
+//! [`Services::topic_history_handler()`]: crate::net_stack::services::Services::topic_history_handler
+//! [`Topics::history()`]: crate::net_stack::topics::Topics::history
+
+use core::{marker::PhantomData, ops::RangeInclusive};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    Address, Key, Priority,
+    net_stack::{NetStackHandle, ReqRespError, endpoints::Endpoints},
+    traits::Topic,
+    well_known::{ErgotTopicHistoryEndpoint, TopicHistoryError, TopicHistoryRequest},
+};
+
+#[cfg(feature = "std")]
+pub use self::store::TopicHistory;
+
+pub use crate::well_known::MAX_HISTORY_BODY_LEN;
+
+#[cfg(feature = "std")]
+mod store {
+    use std::collections::VecDeque;
+
+    use mutex::{BlockingMutex, ConstInit, ScopedRawMutex};
+    // See `interface_manager::profiles::router` for why `web-time` is used
+    use web_time::Instant;
+
+    use super::MAX_HISTORY_BODY_LEN;
+    use crate::{
+        Address, Key,
+        well_known::{
+            TopicHistoryEntry, TopicHistoryError, TopicHistoryRequest, TopicHistoryResponse,
+        },
+    };
+
+    struct Entry {
+        seq: u32,
+        rx_ms: u64,
+        src: Address,
+        body: Vec<u8>,
+    }
+
+    struct Log {
+        key: Key,
+        depth: usize,
+        next_seq: u32,
+        entries: VecDeque<Entry>,
+    }
+
+    /// The last messages of selected topics, see the [module docs](super)
+    pub struct TopicHistory<M: ScopedRawMutex> {
+        start: Instant,
+        logs: BlockingMutex<M, Vec<Log>>,
+    }
+
+    impl<M: ScopedRawMutex + ConstInit> TopicHistory<M> {
+        /// Create a history that records no topics yet
+        pub fn new() -> Self {
+            Self {
+                start: Instant::now(),
+                logs: BlockingMutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl<M: ScopedRawMutex + ConstInit> Default for TopicHistory<M> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<M: ScopedRawMutex> TopicHistory<M> {
+        /// The number of messages currently held for `key`
+        pub fn len(&self, key: &Key) -> usize {
+            self.logs.with_lock(|logs| {
+                logs.iter()
+                    .find(|l| l.key == *key)
+                    .map_or(0, |l| l.entries.len())
+            })
+        }
+
+        /// Does this history hold no messages at all?
+        pub fn is_empty(&self) -> bool {
+            self.logs
+                .with_lock(|logs| logs.iter().all(|l| l.entries.is_empty()))
+        }
+
+        /// Start recording `key`, keeping its last `depth` messages
+        pub(crate) fn track(&self, key: Key, depth: usize) {
+            self.logs.with_lock(|logs| {
+                if let Some(log) = logs.iter_mut().find(|l| l.key == key) {
+                    log.depth = depth;
+                    while log.entries.len() > depth {
+                        log.entries.pop_front();
+                    }
+                } else {
+                    logs.push(Log {
+                        key,
+                        depth,
+                        next_seq: 0,
+                        entries: VecDeque::with_capacity(depth),
+                    });
+                }
+            })
+        }
+
+        /// Record a message of `key`, if it is tracked
+        pub(crate) fn record(&self, key: Key, src: Address, body: &[u8]) {
+            let rx_ms = self.now_ms();
+            self.logs.with_lock(|logs| {
+                let Some(log) = logs.iter_mut().find(|l| l.key == key) else {
+                    return;
+                };
+                if log.entries.len() >= log.depth {
+                    log.entries.pop_front();
+                }
+                if log.depth > 0 {
+                    log.entries.push_back(Entry {
+                        seq: log.next_seq,
+                        rx_ms,
+                        src,
+                        body: body.to_vec(),
+                    });
+                }
+                log.next_seq = log.next_seq.wrapping_add(1);
+            })
+        }
+
+        /// Find the oldest message matching `req`
+        pub(crate) fn lookup(&self, req: &TopicHistoryRequest) -> TopicHistoryResponse {
+            let now_ms = self.now_ms();
+            self.logs.with_lock(|logs| {
+                let log = logs
+                    .iter()
+                    .find(|l| l.key == req.key)
+                    .ok_or(TopicHistoryError::UnknownTopic)?;
+                let entry = log
+                    .entries
+                    .iter()
+                    .find(|e| {
+                        (req.first_seq..=req.last_seq).contains(&e.seq)
+                            && (req.from_ms..=req.to_ms).contains(&e.rx_ms)
+                    })
+                    .ok_or(TopicHistoryError::NoMatch)?;
+                Ok(TopicHistoryEntry {
+                    seq: entry.seq,
+                    rx_ms: entry.rx_ms,
+                    now_ms,
+                    src: entry.src,
+                    body: heapless::Vec::<u8, MAX_HISTORY_BODY_LEN>::from_slice(&entry.body).ok(),
+                })
+            })
+        }
+
+        fn now_ms(&self) -> u64 {
+            self.start.elapsed().as_millis() as u64
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use mutex::raw_impls::cs::CriticalSectionRawMutex;
+
+        use super::TopicHistory;
+        use crate::{
+            Address, Key,
+            well_known::{TopicHistoryError, TopicHistoryRequest},
+        };
+
+        fn request(key: Key, first_seq: u32) -> TopicHistoryRequest {
+            TopicHistoryRequest {
+                key,
+                first_seq,
+                last_seq: u32::MAX,
+                from_ms: 0,
+                to_ms: u64::MAX,
+            }
+        }
+
+        #[test]
+        fn history_keeps_the_last_messages() {
+            let history = TopicHistory::<CriticalSectionRawMutex>::new();
+            let key = Key([1; 8]);
+            let src = Address::unknown();
+
+            // Untracked topics are not recorded
+            history.record(key, src, &[0]);
+            assert!(history.is_empty());
+            assert_eq!(
+                history.lookup(&request(key, 0)),
+                Err(TopicHistoryError::UnknownTopic)
+            );
+
+            history.track(key, 2);
+            assert_eq!(
+                history.lookup(&request(key, 0)),
+                Err(TopicHistoryError::NoMatch)
+            );
+            for i in 0..3 {
+                history.record(key, src, &[i]);
+            }
+            assert_eq!(history.len(&key), 2);
+
+            let first = history.lookup(&request(key, 0)).unwrap();
+            assert_eq!(first.seq, 1);
+            assert_eq!(first.body.as_deref(), Some(&[1][..]));
+            let next = history.lookup(&request(key, first.seq + 1)).unwrap();
+            assert_eq!(next.seq, 2);
+            assert_eq!(
+                history.lookup(&request(key, next.seq + 1)),
+                Err(TopicHistoryError::NoMatch)
+            );
+
+            // Bodies that don't fit in a response are left out
+            history.record(key, src, &[0; super::MAX_HISTORY_BODY_LEN + 1]);
+            let large = history.lookup(&request(key, 3)).unwrap();
+            assert_eq!((large.seq, large.body), (3, None));
+        }
+    }
+}
+
+/// Errors from [`TopicHistoryQuery`]
+#[derive(Debug, PartialEq)]
+pub enum TopicHistoryClientError {
+    /// The request failed at the req-resp layer.
+    RequestFailed(ReqRespError),
+    /// The device does not record the topic.
+    UnknownTopic,
+}
+
+/// A message recorded in a remote [`TopicHistory`]
+///
+/// [`TopicHistory`]: crate::net_stack::history::TopicHistory
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryRecord<M> {
+    /// The sequence number of the message in the history
+    pub seq: u32,
+    /// When the message was received, in milliseconds since the history was
+    /// created
+    pub rx_ms: u64,
+    /// How long before the response the message was received, in milliseconds
+    pub age_ms: u64,
+    /// The sender of the message
+    pub src: Address,
+    /// The message, or `None` if it was too long to be sent, or could not be
+    /// deserialized
+    pub msg: Option<M>,
+}
+
+/// Walks through the messages of `T` recorded in a remote [`TopicHistory`],
+/// oldest first
+///
+/// Created with [`Topics::history()`]. By default, all recorded messages are
+/// returned.
+///
+/// [`TopicHistory`]: crate::net_stack::history::TopicHistory
+/// [`Topics::history()`]: crate::net_stack::topics::Topics::history
+pub struct TopicHistoryQuery<T, NS>
+where
+    T: Topic,
+    NS: NetStackHandle,
+{
+    inner: NS,
+    device: Address,
+    req: TopicHistoryRequest,
+    done: bool,
+    _pd: PhantomData<fn() -> T>,
+}
+
+impl<T, NS> TopicHistoryQuery<T, NS>
+where
+    T: Topic,
+    T::Message: DeserializeOwned,
+    NS: NetStackHandle,
+{
+    pub(crate) fn new(inner: NS, device: Address) -> Self {
+        Self {
+            inner,
+            device: Address {
+                port_id: 0,
+                ..device
+            },
+            req: TopicHistoryRequest {
+                key: Key(T::TOPIC_KEY.to_bytes()),
+                first_seq: 0,
+                last_seq: u32::MAX,
+                from_ms: 0,
+                to_ms: u64::MAX,
+            },
+            done: false,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Only return the messages with a sequence number within `range`
+    pub fn seq(self, range: RangeInclusive<u32>) -> Self {
+        Self {
+            req: TopicHistoryRequest {
+                first_seq: *range.start(),
+                last_seq: *range.end(),
+                ..self.req
+            },
+            ..self
+        }
+    }
+
+    /// Only return the messages received within `range`, in milliseconds since
+    /// the history was created
+    pub fn time_ms(self, range: RangeInclusive<u64>) -> Self {
+        Self {
+            req: TopicHistoryRequest {
+                from_ms: *range.start(),
+                to_ms: *range.end(),
+                ..self.req
+            },
+            ..self
+        }
+    }
+
+    /// Fetch the next matching message, or `None` once there are no more
+    pub async fn next_record(
+        &mut self,
+    ) -> Result<Option<HistoryRecord<T::Message>>, TopicHistoryClientError> {
+        if self.done {
+            return Ok(None);
+        }
+        let resp = Endpoints {
+            inner: self.inner.clone(),
+            prio: Priority::NORMAL,
+        }
+        .request::<ErgotTopicHistoryEndpoint>(self.device, &self.req, None)
+        .await
+        .map_err(TopicHistoryClientError::RequestFailed)?;
+        let entry = match resp {
+            Ok(entry) => entry,
+            Err(TopicHistoryError::NoMatch) => {
+                self.done = true;
+                return Ok(None);
+            }
+            Err(TopicHistoryError::UnknownTopic) => {
+                return Err(TopicHistoryClientError::UnknownTopic);
+            }
+        };
+        match entry.seq.checked_add(1) {
+            Some(next) => self.req.first_seq = next,
+            None => self.done = true,
+        }
+        Ok(Some(HistoryRecord {
+            seq: entry.seq,
+            rx_ms: entry.rx_ms,
+            age_ms: entry.now_ms.saturating_sub(entry.rx_ms),
+            src: entry.src,
+            msg: entry.body.and_then(|body| postcard::from_bytes(&body).ok()),
+        }))
+    }
+}
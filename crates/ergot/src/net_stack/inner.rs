@@ -3,16 +3,28 @@ use core::{any::TypeId, ptr::NonNull};
 use cordyceps::List;
 use serde::Serialize;
 
+#[cfg(any(feature = "std", feature = "nostd-reassembly", feature = "signing"))]
+use crate::logging::warn;
 use crate::logging::{debug, error, trace};
+#[cfg(feature = "signing")]
+use crate::{
+    Key,
+    signing::{SIGNATURE_SIZE, SignedFields, Signer, sign_to_slice},
+};
 
 use crate::{
     FrameKind, Header, HeaderSeq, ProtocolError,
     interface_manager::{self, InterfaceSendError, Profile},
     net_stack::NetStackSendError,
     socket::{SocketHeader, SocketSendError, SocketVTable, borser},
+    wire_frames::{
+        Fragment, FragmentHeader, MAX_HDR_ENCODED_SIZE, PreSerialized, fragment_data_len,
+    },
 };
 
-use super::SocketHeaderIter;
+#[cfg(any(feature = "std", feature = "nostd-reassembly"))]
+use super::reassembly::Reassembler;
+use super::{SocketHeaderIter, path_mtu::PathMtuCache};
 
 pub(crate) struct NetStackInner<P: Profile> {
     pub(super) sockets: List<SocketHeader>,
@@ -20,6 +32,10 @@ pub(crate) struct NetStackInner<P: Profile> {
     pub(super) pcache_bits: u32,
     pub(super) pcache_start: u8,
     pub(super) seq_no: u16,
+    pub(super) frag_id: u16,
+    pub(super) path_mtu: PathMtuCache,
+    #[cfg(any(feature = "std", feature = "nostd-reassembly"))]
+    pub(super) reassembly: Reassembler,
 }
 
 // ---- impl NetStackInner ----
@@ -36,6 +52,10 @@ where
             seq_no: 0,
             pcache_bits: 0,
             pcache_start: 0,
+            frag_id: 0,
+            path_mtu: PathMtuCache::new(),
+            #[cfg(any(feature = "std", feature = "nostd-reassembly"))]
+            reassembly: Reassembler::new(),
         }
     }
 }
@@ -52,6 +72,10 @@ where
             seq_no: 0,
             pcache_bits: 0,
             pcache_start: 0,
+            frag_id: 0,
+            path_mtu: PathMtuCache::new(),
+            #[cfg(any(feature = "std", feature = "nostd-reassembly"))]
+            reassembly: Reassembler::new(),
         }
     }
 
@@ -321,6 +345,8 @@ where
             sockets,
             seq_no,
             profile: manager,
+            #[cfg(any(feature = "std", feature = "nostd-reassembly"))]
+            reassembly,
             ..
         } = self;
         trace!("{}: Sending msg raw from {:?}", hdr, source);
@@ -329,6 +355,11 @@ where
             todo!("{}: Don't do that", hdr);
         }
 
+        #[cfg(any(feature = "std", feature = "nostd-reassembly"))]
+        if hdr.kind == FrameKind::FRAGMENT {
+            return Self::recv_fragment(sockets, seq_no, manager, reassembly, hdr, body, source);
+        }
+
         let nshdr: Header = hdr.clone().into();
 
         // Is this a broadcast message?
@@ -371,6 +402,23 @@ where
             todo!("{}: Don't do that", hdr);
         }
 
+        // Responses to signed requests are signed for interfaces, see
+        // `signing`
+        #[cfg(feature = "signing")]
+        if hdr.kind == FrameKind::ENDPOINT_RESP
+            && let Some(sig) = Self::sign_response(sockets, manager, hdr, t)
+        {
+            return Self::unicast(
+                sockets,
+                hdr,
+                |skt| Self::send_ty_to_socket(skt, t, hdr, seq_no),
+                || manager.send(hdr, &(t, PreSerialized(&sig))),
+            )
+            .inspect_err(|e| {
+                error!("{}: Error sending signed response: {:?}", hdr, e);
+            });
+        }
+
         // Is this a broadcast message?
         if hdr.dst.port_id == 255 {
             Self::broadcast(
@@ -392,6 +440,248 @@ where
         })
     }
 
+    /// Handle sending of a typed message, signed with `signer`
+    ///
+    /// The message is delivered to local sockets as-is, and serialized and
+    /// signed into `scratch` for interfaces.
+    #[cfg(feature = "signing")]
+    #[allow(unused_variables)] // `e` in inspect_err is only used in logging macros (no-op when disabled)
+    pub(super) fn send_ty_signed<T: 'static + Serialize + Clone>(
+        &mut self,
+        hdr: &Header,
+        key: &Key,
+        t: &T,
+        signer: &Signer,
+        scratch: &mut [u8],
+    ) -> Result<(), NetStackSendError> {
+        let Self {
+            sockets,
+            seq_no,
+            profile: manager,
+            ..
+        } = self;
+        trace!("{}: Sending msg ty signed", hdr);
+
+        if hdr.kind == FrameKind::PROTOCOL_ERROR {
+            return Err(NetStackSendError::ProtocolErrorKind);
+        }
+
+        // Sign the source node_id the message will be sent with, interfaces
+        // only fill it in if it is unset
+        let src_node = match hdr.src.node_id {
+            0 => manager.own_node_id().unwrap_or(0),
+            node_id => node_id,
+        };
+        let fields = SignedFields {
+            kind: hdr.kind,
+            key: *key,
+            src_node,
+            src_port: hdr.src.port_id,
+            dst_port: hdr.dst.port_id,
+        };
+        let (body, stamp) =
+            sign_to_slice(signer, &fields, t, scratch).ok_or(NetStackSendError::MessageTooLarge)?;
+        let body = PreSerialized(body);
+
+        // The client of a signed request only accepts a signed response.
+        // Remembered before sending, the response may arrive at any time.
+        if hdr.kind == FrameKind::ENDPOINT_REQ
+            && let Some(skt) = Self::find_port_local(sockets, hdr.src.port_id)
+        {
+            let skt_ref = unsafe { skt.as_ref() };
+            if skt_ref.attrs.kind == FrameKind::ENDPOINT_RESP {
+                // SAFETY: only accessed with the lock of the net stack held
+                let exchanges = unsafe { &mut *skt_ref.exchanges.get() };
+                exchanges.sent(signer, *key, stamp);
+            }
+        }
+
+        // Is this a broadcast message?
+        if hdr.dst.port_id == 255 {
+            Self::broadcast(
+                sockets,
+                hdr,
+                |skt| Self::send_ty_to_socket(skt, t, hdr, seq_no),
+                || manager.send(hdr, &body),
+            )
+        } else {
+            Self::unicast(
+                sockets,
+                hdr,
+                |skt| Self::send_ty_to_socket(skt, t, hdr, seq_no),
+                || manager.send(hdr, &body),
+            )
+        }
+        .inspect_err(|e| {
+            error!("{}: Error sending ty signed: {:?}", hdr, e);
+        })
+    }
+
+    /// Handle a received fragment
+    ///
+    /// Fragments are forwarded as-is if they are not for us, and are only
+    /// reassembled at their destination.
+    #[cfg(any(feature = "std", feature = "nostd-reassembly"))]
+    fn recv_fragment(
+        sockets: &mut List<SocketHeader>,
+        seq_no: &mut u16,
+        manager: &mut P,
+        reassembly: &mut Reassembler,
+        hdr: &HeaderSeq,
+        body: &[u8],
+        source: P::InterfaceIdent,
+    ) -> Result<(), NetStackSendError> {
+        let is_bcast = hdr.dst.port_id == 255;
+        let local_bypass = hdr.src.net_node_any() && hdr.dst.net_node_any();
+
+        if !local_bypass {
+            match manager.send_raw(hdr, body, source) {
+                Ok(()) if !is_bcast => return Ok(()),
+                Ok(()) => {}
+                Err(InterfaceSendError::DestinationLocal | InterfaceSendError::RoutingLoop) => {}
+                Err(InterfaceSendError::NoRouteToDest) if is_bcast => {}
+                Err(e) if !is_bcast => return Err(NetStackSendError::InterfaceSend(e)),
+                // `e` is only used in the logging macro (no-op when internal logging is disabled)
+                #[allow(unused_variables)]
+                Err(e) => {
+                    error!(
+                        "{}: failed to forward broadcast fragment, error: {:?}",
+                        hdr, e
+                    );
+                }
+            }
+        }
+
+        let Some(frag) = crate::wire_frames::decode_fragment(body) else {
+            warn!("{}: Dropping malformed fragment", hdr);
+            return Ok(());
+        };
+        let Some(msg) = reassembly.push(hdr.src, &frag) else {
+            return Ok(());
+        };
+        let Some((kind, body)) = msg.split_first() else {
+            return Ok(());
+        };
+
+        let nshdr = Header {
+            kind: FrameKind(*kind),
+            ..hdr.clone().into()
+        };
+        debug!("{}: Reassembled {} byte message", nshdr, body.len());
+
+        if is_bcast {
+            Self::broadcast_local(sockets, &nshdr, |skt| {
+                Self::send_raw_to_socket(skt, body, &nshdr, seq_no)
+            })
+        } else {
+            let socket = if nshdr.dst.port_id == 0 {
+                Self::find_any_local(sockets, &nshdr)
+            } else {
+                Self::find_one_local(sockets, &nshdr)
+            }?;
+            Self::send_raw_to_socket(socket, body, &nshdr, seq_no)
+        }
+    }
+
+    /// Handle sending of a typed message, which is split into fragments of at
+    /// most `mtu` bytes if it is sent to an interface
+    #[allow(unused_variables)] // `e` in inspect_err is only used in logging macros (no-op when disabled)
+    pub(super) fn send_ty_fragmented<T: 'static + Serialize + Clone>(
+        &mut self,
+        hdr: &Header,
+        t: &T,
+        mtu: u16,
+        scratch: &mut [u8],
+    ) -> Result<(), NetStackSendError> {
+        let Self {
+            sockets,
+            seq_no,
+            profile: manager,
+            frag_id,
+            ..
+        } = self;
+        trace!("{}: Sending msg ty fragmented", hdr);
+
+        if hdr.kind == FrameKind::PROTOCOL_ERROR {
+            return Err(NetStackSendError::ProtocolErrorKind);
+        }
+
+        let data_len = fragment_data_len(mtu).ok_or(NetStackSendError::InterfaceSend(
+            InterfaceSendError::PacketTooBig { mtu },
+        ))?;
+
+        // The reassembled payload is the frame kind, followed by the body
+        let (kind, rest) = scratch
+            .split_first_mut()
+            .ok_or(NetStackSendError::MessageTooLarge)?;
+        *kind = hdr.kind.0;
+        let body_len = postcard::to_slice(t, rest)
+            .map_err(|_| NetStackSendError::MessageTooLarge)?
+            .len();
+        let payload = &scratch[..body_len + 1];
+        if payload.len() > u16::MAX as usize {
+            return Err(NetStackSendError::MessageTooLarge);
+        }
+
+        // All fragments share the same seq_no, which is also used for the
+        // reassembled message
+        let hdr = Header {
+            seq_no: Some(hdr.seq_no.unwrap_or_else(|| {
+                let seq = *seq_no;
+                *seq_no = seq_no.wrapping_add(1);
+                seq
+            })),
+            ..hdr.clone()
+        };
+        let msg_id = *frag_id;
+        *frag_id = frag_id.wrapping_add(1);
+
+        let smgr = || {
+            // Small enough to go as-is
+            if MAX_HDR_ENCODED_SIZE + body_len <= mtu as usize {
+                return manager.send(&hdr, t);
+            }
+            let frag_hdr = Header {
+                kind: FrameKind::FRAGMENT,
+                ..hdr.clone()
+            };
+            let mut offset = 0;
+            for data in payload.chunks(data_len) {
+                let frag = Fragment {
+                    hdr: FragmentHeader {
+                        msg_id,
+                        offset: offset as u16,
+                        more: offset + data.len() < payload.len(),
+                    },
+                    data,
+                };
+                manager.send(&frag_hdr, &frag)?;
+                offset += data.len();
+            }
+            Ok(())
+        };
+
+        // Is this a broadcast message?
+        if hdr.dst.port_id == 255 {
+            Self::broadcast(
+                sockets,
+                &hdr,
+                |skt| Self::send_ty_to_socket(skt, t, &hdr, seq_no),
+                smgr,
+            )
+        } else {
+            Self::unicast(
+                sockets,
+                &hdr,
+                |skt| Self::send_ty_to_socket(skt, t, &hdr, seq_no),
+                smgr,
+            )
+        }
+        .inspect_err(|e| {
+            error!("{}: Error sending ty fragmented: {:?}", hdr, e);
+        })
+    }
+
     /// Handle sending of a typed message
     #[allow(unused_variables)] // `e` in inspect_err is only used in logging macros (no-op when disabled)
     pub(super) fn send_ty_local<T: 'static + Clone>(
@@ -466,6 +756,49 @@ where
         })
     }
 
+    /// Handle sending of an already serialized message
+    ///
+    /// Local sockets receive `body` as if it came from an interface.
+    #[allow(unused_variables)] // `e` in inspect_err is only used in logging macros (no-op when disabled)
+    pub(super) fn send_serialized(
+        &mut self,
+        hdr: &Header,
+        body: &[u8],
+    ) -> Result<(), NetStackSendError> {
+        let Self {
+            sockets,
+            seq_no,
+            profile: manager,
+            ..
+        } = self;
+        trace!("{}: Sending msg serialized", hdr);
+
+        if hdr.kind == FrameKind::PROTOCOL_ERROR {
+            return Err(NetStackSendError::ProtocolErrorKind);
+        }
+
+        let pre = PreSerialized(body);
+        // Is this a broadcast message?
+        if hdr.dst.port_id == 255 {
+            Self::broadcast(
+                sockets,
+                hdr,
+                |skt| Self::send_raw_to_socket(skt, body, hdr, seq_no),
+                || manager.send(hdr, &pre),
+            )
+        } else {
+            Self::unicast(
+                sockets,
+                hdr,
+                |skt| Self::send_raw_to_socket(skt, body, hdr, seq_no),
+                || manager.send(hdr, &pre),
+            )
+        }
+        .inspect_err(|e| {
+            error!("{}: Error sending serialized: {:?}", hdr, e);
+        })
+    }
+
     /// Handle sending of a typed message
     pub(super) fn send_err(
         &mut self,
@@ -503,6 +836,51 @@ where
         f(iter)
     }
 
+    /// Sign the response `t` of a local endpoint server, if it answers a
+    /// signed request, see [`crate::signing`]
+    #[cfg(feature = "signing")]
+    fn sign_response<T: Serialize>(
+        sockets: &mut List<SocketHeader>,
+        manager: &mut P,
+        hdr: &Header,
+        t: &T,
+    ) -> Option<[u8; SIGNATURE_SIZE]> {
+        let seq_no = hdr.seq_no?;
+        let skt = Self::find_port_local(sockets, hdr.src.port_id)?;
+        let skt_ref = unsafe { skt.as_ref() };
+        let store = skt_ref.trust?;
+        if skt_ref.attrs.kind != FrameKind::ENDPOINT_REQ {
+            return None;
+        }
+        // Sign the source node_id the response will be sent with, interfaces
+        // only fill it in if it is unset
+        let src_node = match hdr.src.node_id {
+            0 => manager.own_node_id().unwrap_or(0),
+            node_id => node_id,
+        };
+        let fields = SignedFields {
+            kind: hdr.kind,
+            key: skt_ref.key,
+            src_node,
+            src_port: hdr.src.port_id,
+            dst_port: hdr.dst.port_id,
+        };
+        // SAFETY: only accessed with the lock of the net stack held
+        let exchanges = unsafe { &mut *skt_ref.exchanges.get() };
+        exchanges.sign_response(store, &hdr.dst, seq_no, &fields, t)
+    }
+
+    /// Find the local socket with `port`
+    #[cfg(feature = "signing")]
+    fn find_port_local(
+        sockets: &mut List<SocketHeader>,
+        port: u8,
+    ) -> Option<NonNull<SocketHeader>> {
+        sockets
+            .iter_raw()
+            .find(|skt| unsafe { skt.as_ref() }.port == port)
+    }
+
     /// Find a specific (e.g. port_id not 0 or 255) destination port matching
     /// the given header.
     fn find_one_local(
@@ -598,6 +976,10 @@ where
 
     /// Find ALL broadcast (e.g. port_id == 255) sockets matching the given header.
     ///
+    /// Broadcast endpoint requests are also delivered to every discoverable
+    /// endpoint server, so that all servers of an endpoint can be queried at
+    /// once.
+    ///
     /// Returns an error if the header does not contain a Key. May return zero
     /// matches.
     fn find_all_local(
@@ -609,7 +991,8 @@ where
         };
         Ok(sockets.iter_raw().filter(move |socket| {
             let skt_ref = unsafe { socket.as_ref() };
-            let bport = skt_ref.port == 255;
+            let bport = skt_ref.port == 255
+                || (hdr.kind == FrameKind::ENDPOINT_REQ && skt_ref.attrs.discoverable);
             let dkind = skt_ref.attrs.kind == hdr.kind;
             let dkey = skt_ref.key == any_all.key;
 
@@ -645,7 +1028,8 @@ where
                 *seq_no = seq_no.wrapping_add(1);
                 seq
             });
-            (f)(this, that, hdr, &TypeId::of::<T>()).map_err(NetStackSendError::SocketSend)
+            let res = (f)(this, that, hdr, &TypeId::of::<T>());
+            Self::count_delivery(this, res)
         } else if let Some(_f) = vtable.recv_bor {
             // TODO: support send borrowed
             todo!()
@@ -680,7 +1064,8 @@ where
                 seq
             });
             let func = borser::<T>;
-            (f)(this, that, hdr, func).map_err(NetStackSendError::SocketSend)
+            let res = (f)(this, that, hdr, func);
+            Self::count_delivery(this, res)
         } else {
             // todo: keep going? If we found the "right" destination and
             // sending fails, then there's not much we can do. Probably: there
@@ -727,9 +1112,43 @@ where
         hdr: &Header,
         seq_no: &mut u16,
     ) -> Result<(), NetStackSendError> {
-        let vtable: &'static SocketVTable = {
-            let skt_ref = unsafe { this.as_ref() };
-            skt_ref.vtable
+        let skt_ref = unsafe { this.as_ref() };
+        let vtable: &'static SocketVTable = skt_ref.vtable;
+
+        // Messages from other devices must be signed by a trusted signer,
+        // and responses to signed requests by the signer of the request
+        #[cfg(feature = "signing")]
+        let body = {
+            let fields = SignedFields {
+                kind: hdr.kind,
+                key: skt_ref.key,
+                src_node: hdr.src.node_id,
+                src_port: hdr.src.port_id,
+                dst_port: hdr.dst.port_id,
+            };
+            // SAFETY: the replay guard and exchanges are only accessed with
+            // the lock of the net stack held
+            let replay = unsafe { &mut *skt_ref.replay.get() };
+            let exchanges = unsafe { &mut *skt_ref.exchanges.get() };
+            let res = match (exchanges.verify_response(&fields, body), skt_ref.trust) {
+                (Some(res), _) => res,
+                (None, Some(store)) => {
+                    crate::signing::verify(store, &fields, body).and_then(|(body, stamp)| {
+                        replay.accept(&stamp)?;
+                        if hdr.kind == FrameKind::ENDPOINT_REQ
+                            && let Some(seq_no) = hdr.seq_no
+                        {
+                            exchanges.received(hdr.src, seq_no, stamp);
+                        }
+                        Ok(body)
+                    })
+                }
+                (None, None) => Ok(body),
+            };
+            res.map_err(|_e| {
+                warn!("{}: rejecting unverified message: {:?}", hdr, _e);
+                NetStackSendError::SocketSend(SocketSendError::Unverified)
+            })?
         };
         let f = vtable.recv_raw;
 
@@ -740,7 +1159,19 @@ where
             seq
         });
 
-        (f)(this, body, hdr).map_err(NetStackSendError::SocketSend)
+        let res = (f)(this, body, hdr);
+        Self::count_delivery(this, res)
+    }
+
+    /// Count the outcome of delivering a message to a socket, see
+    /// [`crate::stats`]
+    fn count_delivery(
+        this: NonNull<()>,
+        res: Result<(), SocketSendError>,
+    ) -> Result<(), NetStackSendError> {
+        let skt_ref: &SocketHeader = unsafe { this.cast().as_ref() };
+        skt_ref.counters.count(&res);
+        res.map_err(NetStackSendError::SocketSend)
     }
 }
 
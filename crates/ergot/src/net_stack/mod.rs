@@ -21,17 +21,20 @@
 use core::{fmt::Arguments, ops::Deref, ptr::NonNull};
 
 use cordyceps::{List, list::Iter};
+use discovery::Discovery;
 use endpoints::Endpoints;
+use maitake_sync::WaitQueue;
 use mutex::{BlockingMutex, ConstInit, ScopedRawMutex};
 use serde::Serialize;
 use topics::Topics;
 
 use crate::{
-    FrameKind, Header, HeaderSeq, ProtocolError,
+    Address, FrameKind, Header, HeaderSeq, Priority, ProtocolError,
     fmtlog::{ErgotFmtTx, Level},
-    interface_manager::{self, InterfaceSendError, Profile},
+    interface_manager::{self, InterfaceSendError, Profile, subscriptions},
     socket::{SocketHeader, SocketSendError},
-    well_known::ErgotFmtTxTopic,
+    stats::{InterfaceStats, SocketStats},
+    well_known::{ErgotFmtTxTopic, SubscriptionAdvertisement},
 };
 
 #[cfg(feature = "std")]
@@ -45,11 +48,25 @@ use inner::NetStackInner;
 pub use services::Services;
 pub mod discovery;
 pub mod endpoints;
+pub mod history;
+pub mod path_mtu;
+pub mod publisher;
+#[cfg(any(feature = "std", feature = "nostd-reassembly"))]
+pub mod reassembly;
+pub mod retained;
+#[cfg(any(
+    feature = "tokio-std",
+    feature = "embassy-time",
+    feature = "nostd-seed-router"
+))]
+pub mod sessions;
 pub mod topics;
 
 /// The Ergot Netstack
 pub struct NetStack<R: ScopedRawMutex, P: Profile> {
     inner: BlockingMutex<R, NetStackInner<P>>,
+    /// Woken when a full local socket makes room again
+    space: WaitQueue,
 }
 
 pub trait NetStackHandle
@@ -89,6 +106,12 @@ pub enum NetStackSendError {
     AnyPortNotUnique,
     AllPortMissingKey,
     WouldDeadlock,
+    /// The message did not fit in the provided buffer, or was too large to be
+    /// sent as fragments
+    MessageTooLarge,
+    /// A message was sent with [`FrameKind::PROTOCOL_ERROR`], which is only
+    /// used for errors sent by the stack itself
+    ProtocolErrorKind,
 }
 
 // ---- impl NetStack ----
@@ -130,6 +153,7 @@ where
     pub const fn new() -> Self {
         Self {
             inner: BlockingMutex::new(NetStackInner::new()),
+            space: WaitQueue::new(),
         }
     }
 }
@@ -142,6 +166,7 @@ where
     pub const fn new_with_profile(p: P) -> Self {
         Self {
             inner: BlockingMutex::new(NetStackInner::new_with_profile(p)),
+            space: WaitQueue::new(),
         }
     }
 }
@@ -155,6 +180,7 @@ where
     pub(crate) fn new_arc(p: P) -> std::sync::Arc<Self> {
         std::sync::Arc::new(Self {
             inner: BlockingMutex::new(NetStackInner::new_with_profile(p)),
+            space: WaitQueue::new(),
         })
     }
 }
@@ -180,8 +206,13 @@ where
                     seq_no: 0,
                     pcache_start: 0,
                     pcache_bits: 0,
+                    frag_id: 0,
+                    path_mtu: path_mtu::PathMtuCache::new(),
+                    #[cfg(any(feature = "std", feature = "nostd-reassembly"))]
+                    reassembly: reassembly::Reassembler::new(),
                 },
             ),
+            space: WaitQueue::new(),
         }
     }
 
@@ -243,6 +274,54 @@ where
             .ok_or(NetStackSendError::WouldDeadlock)?
     }
 
+    /// Send a typed message, which may be larger than the MTU of the outgoing
+    /// interface
+    ///
+    /// If the message is sent to an interface, and a frame containing it would
+    /// be larger than `mtu` bytes, it is instead sent as a series of
+    /// [`FrameKind::FRAGMENT`] frames, which are reassembled by the receiving
+    /// [`NetStack`]. See the `reassembly` module for details.
+    ///
+    /// A suitable `mtu` for a destination can be found with
+    /// [`Discovery::discover_path_mtu()`], and later checked with
+    /// [`NetStack::path_mtu()`].
+    ///
+    /// `scratch` is used to serialize the message, and must be at least one byte
+    /// larger than the serialized message.
+    pub fn send_ty_fragmented<T: 'static + Serialize + Clone>(
+        &self,
+        hdr: &Header,
+        t: &T,
+        mtu: u16,
+        scratch: &mut [u8],
+    ) -> Result<(), NetStackSendError> {
+        self.inner
+            .try_with_lock(|inner| inner.send_ty_fragmented(hdr, t, mtu, scratch))
+            .ok_or(NetStackSendError::WouldDeadlock)?
+    }
+
+    /// Send a typed message, signed with `signer`
+    ///
+    /// `key` is the [`Key`](crate::Key) of the destination topic or endpoint. Messages
+    /// delivered to local sockets are not signed. For interfaces, the message
+    /// is serialized and signed into `scratch`, which must be at least
+    /// [`SIGNATURE_SIZE`](crate::signing::SIGNATURE_SIZE) bytes larger than
+    /// the serialized message. See the [`signing`](crate::signing) module for
+    /// details.
+    #[cfg(feature = "signing")]
+    pub fn send_ty_signed<T: 'static + Serialize + Clone>(
+        &self,
+        hdr: &Header,
+        key: &crate::Key,
+        t: &T,
+        signer: &crate::signing::Signer,
+        scratch: &mut [u8],
+    ) -> Result<(), NetStackSendError> {
+        self.inner
+            .try_with_lock(|inner| inner.send_ty_signed(hdr, key, t, signer, scratch))
+            .ok_or(NetStackSendError::WouldDeadlock)?
+    }
+
     /// Send a typed message locally
     pub fn send_ty_local<T: 'static + Clone>(
         &self,
@@ -260,6 +339,17 @@ where
             .ok_or(NetStackSendError::WouldDeadlock)?
     }
 
+    /// Send an already serialized message
+    ///
+    /// Useful for tools that handle messages of types only known at runtime,
+    /// from their schema. `body` must be the postcard encoding of a message
+    /// matching the key of the destination socket.
+    pub fn send_serialized(&self, hdr: &Header, body: &[u8]) -> Result<(), NetStackSendError> {
+        self.inner
+            .try_with_lock(|inner| inner.send_serialized(hdr, body))
+            .ok_or(NetStackSendError::WouldDeadlock)?
+    }
+
     pub fn send_err(
         &self,
         hdr: &Header,
@@ -282,6 +372,31 @@ where
             .try_with_lock(|inner| inner.with_sockets::<F, U>(f))
     }
 
+    /// Advertise the topics subscribed to on this device to each direct
+    /// neighbor, see [`subscriptions`]
+    ///
+    /// Neighbors rely on the advertisement for `valid_secs`, so this should be
+    /// called again well before that.
+    ///
+    /// Returns false if the mutex is already locked.
+    pub fn advertise_subscriptions(&self, valid_secs: u16) -> bool {
+        self.inner
+            .try_with_lock(|inner| {
+                let mut adv = SubscriptionAdvertisement {
+                    valid_secs,
+                    complete: true,
+                    keys: heapless::Vec::new(),
+                };
+                inner.with_sockets(|iter| {
+                    for skt in iter.filter(|s| s.attrs.kind == FrameKind::TOPIC_MSG) {
+                        adv.complete &= subscriptions::insert_key(&mut adv.keys, skt.key);
+                    }
+                });
+                inner.profile.advertise_subscriptions(&adv);
+            })
+            .is_some()
+    }
+
     pub(crate) unsafe fn try_attach_socket(&self, mut node: NonNull<SocketHeader>) -> Option<u8> {
         self.inner.try_with_lock(|inner| {
             let new_port = inner.alloc_port()?;
@@ -324,6 +439,81 @@ where
     pub(crate) unsafe fn with_lock<U, F: FnOnce() -> U>(&self, f: F) -> U {
         self.inner.with_lock(|_inner| f())
     }
+
+    /// Wait for a full local socket to make room
+    ///
+    /// Subscribe to the returned future before retrying a send that failed
+    /// with [`SocketSendError::NoSpace`], so that no wakeup is missed.
+    pub(crate) fn wait_for_space(&self) -> maitake_sync::wait_queue::Wait<'_> {
+        self.space.wait()
+    }
+
+    /// Wake all tasks waiting for a full local socket to make room
+    pub(crate) fn wake_space_waiters(&self) {
+        self.space.wake_all();
+    }
+
+    /// The cached path MTU towards the device at `addr`, if it has been
+    /// discovered
+    ///
+    /// See the [`path_mtu`] module for details.
+    pub fn path_mtu(&self, addr: Address) -> Option<u16> {
+        self.inner.with_lock(|inner| inner.path_mtu.get(addr))
+    }
+
+    /// Forget the cached path MTU towards the device at `addr`, e.g. after
+    /// the route to it has changed
+    pub fn forget_path_mtu(&self, addr: Address) {
+        self.inner.with_lock(|inner| inner.path_mtu.remove(addr));
+    }
+
+    /// Reassemble fragmented messages in `buffers`, see [`reassembly`]
+    ///
+    /// Without `std`, fragments are dropped until this is called. With `std`,
+    /// `buffers` are used instead of the heap.
+    #[cfg(feature = "nostd-reassembly")]
+    pub fn set_reassembly_buffers<const SLOTS: usize, const LEN: usize>(
+        &self,
+        buffers: &'static mut reassembly::ReassemblyBuffers<SLOTS, LEN>,
+    ) {
+        self.inner
+            .with_lock(|inner| inner.reassembly.set_buffers(buffers));
+    }
+
+    pub(crate) fn cache_path_mtu(&self, addr: Address, path_mtu: u16) {
+        self.inner
+            .with_lock(|inner| inner.path_mtu.insert(addr, path_mtu));
+    }
+
+    /// The counters of the `index`-th interface of the profile, see
+    /// [`stats`](crate::stats)
+    ///
+    /// Returns None once `index` is past the last interface.
+    pub fn interface_stats(&self, index: usize) -> Option<InterfaceStats> {
+        self.manage_profile(|im| im.interface_stats(index))
+    }
+
+    /// The counters of the `index`-th socket attached to the stack, see
+    /// [`stats`](crate::stats)
+    ///
+    /// Unlike [`with_sockets()`](Self::with_sockets), this includes the
+    /// sockets that are not discoverable. Returns None once `index` is past
+    /// the last socket.
+    pub fn socket_stats(&self, index: usize) -> Option<SocketStats> {
+        self.inner
+            .with_lock(|inner| inner.sockets.iter().nth(index).map(SocketHeader::stats))
+    }
+
+    /// Take the next sequence number from the stack's local counter
+    ///
+    /// Used to match responses to a request sent with an explicit `seq_no`.
+    pub fn next_seq_no(&self) -> u16 {
+        self.inner.with_lock(|inner| {
+            let seq = inner.seq_no;
+            inner.seq_no = inner.seq_no.wrapping_add(1);
+            seq
+        })
+    }
 }
 
 /// An iterator over all discoverable [`SocketHeader`]s
@@ -396,8 +586,10 @@ where
     }
 
     fn level_fmt(&self, level: Level, args: &Arguments<'_>) {
+        // Logs must not delay other traffic on slow interfaces
         _ = self
             .topics()
+            .with_priority(Priority::LOW)
             .broadcast_borrowed::<ErgotFmtTxTopic>(&ErgotFmtTx { level, inner: args }, None);
     }
 
@@ -406,11 +598,30 @@ where
     }
 
     pub fn endpoints(&self) -> Endpoints<&Self> {
-        Endpoints { inner: self }
+        Endpoints {
+            inner: self,
+            prio: Priority::NORMAL,
+        }
     }
 
     pub fn topics(&self) -> Topics<&Self> {
-        Topics { inner: self }
+        Topics {
+            inner: self,
+            prio: Priority::NORMAL,
+        }
+    }
+
+    pub fn discovery(&self) -> Discovery<&Self> {
+        Discovery { inner: self }
+    }
+
+    #[cfg(any(
+        feature = "tokio-std",
+        feature = "embassy-time",
+        feature = "nostd-seed-router"
+    ))]
+    pub fn sessions(&self) -> sessions::Sessions<&Self> {
+        sessions::Sessions { inner: self }
     }
 }
 
@@ -422,6 +633,27 @@ pub enum ReqRespError {
     Remote(ProtocolError),
     // Requests cannot be sent to broadcast ports
     NoBroadcast,
+    // No response was received before the deadline (and all retries) elapsed
+    Timeout,
+    // A streamed response item was lost or arrived out of order
+    Missed,
+}
+
+/// Sleep using the timer of the enabled executor feature
+#[cfg(feature = "tokio-std")]
+pub(crate) fn timer_sleep(d: core::time::Duration) -> impl Future<Output = ()> {
+    tokio::time::sleep(d)
+}
+
+/// Sleep using the timer of the enabled executor feature
+#[cfg(all(
+    not(feature = "tokio-std"),
+    any(feature = "embassy-time", feature = "nostd-seed-router")
+))]
+pub(crate) fn timer_sleep(d: core::time::Duration) -> impl Future<Output = ()> {
+    embassy_time::Timer::after(embassy_time::Duration::from_micros(
+        d.as_micros().try_into().unwrap_or(u64::MAX),
+    ))
 }
 
 impl<R, P> Default for NetStack<R, P>
@@ -447,6 +679,9 @@ impl NetStackSendError {
             NetStackSendError::AnyPortNotUnique => ProtocolError::NsseAnyPortNotUnique,
             NetStackSendError::AllPortMissingKey => ProtocolError::NsseAllPortMissingKey,
             NetStackSendError::WouldDeadlock => ProtocolError::NsseWouldDeadlock,
+            NetStackSendError::MessageTooLarge => ProtocolError::NsseMessageTooLarge,
+            // Not a new wire variant: only local senders can cause this
+            NetStackSendError::ProtocolErrorKind => ProtocolError::NsseWrongPortKind,
         }
     }
 }
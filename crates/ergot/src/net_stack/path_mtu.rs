@@ -0,0 +1,103 @@
+//! Path MTU cache
+//!
+//! The results of [`Discovery::discover_path_mtu()`] are remembered by the
+//! [`NetStack`], so that senders can check [`NetStack::path_mtu()`] before
+//! serializing a large message, e.g. to decide whether to use
+//! [`NetStack::send_ty_fragmented()`].
+//!
+//! The cache holds up to [`PATH_MTU_CACHE_SLOTS`] destinations. When full, the
+//! least recently discovered destination is replaced.
+//!
+//! [`Discovery::discover_path_mtu()`]: crate::net_stack::discovery::Discovery::discover_path_mtu
+//! [`NetStack`]: crate::NetStack
+//! [`NetStack::path_mtu()`]: crate::NetStack::path_mtu
+//! [`NetStack::send_ty_fragmented()`]: crate::NetStack::send_ty_fragmented
+
+use crate::Address;
+
+/// The maximum number of destinations kept in the path MTU cache
+pub const PATH_MTU_CACHE_SLOTS: usize = 8;
+
+struct Entry {
+    network_id: u16,
+    node_id: u8,
+    path_mtu: u16,
+}
+
+pub(crate) struct PathMtuCache {
+    entries: heapless::Deque<Entry, PATH_MTU_CACHE_SLOTS>,
+}
+
+impl PathMtuCache {
+    pub(crate) const fn new() -> Self {
+        Self {
+            entries: heapless::Deque::new(),
+        }
+    }
+
+    /// The cached path MTU towards the device at `addr`, if any
+    pub(crate) fn get(&self, addr: Address) -> Option<u16> {
+        self.entries
+            .iter()
+            .find(|e| e.network_id == addr.network_id && e.node_id == addr.node_id)
+            .map(|e| e.path_mtu)
+    }
+
+    /// Remember the path MTU towards the device at `addr`
+    pub(crate) fn insert(&mut self, addr: Address, path_mtu: u16) {
+        self.remove(addr);
+        if self.entries.is_full() {
+            self.entries.pop_front();
+        }
+        let _ = self.entries.push_back(Entry {
+            network_id: addr.network_id,
+            node_id: addr.node_id,
+            path_mtu,
+        });
+    }
+
+    /// Forget the path MTU towards the device at `addr`
+    pub(crate) fn remove(&mut self, addr: Address) {
+        let len = self.entries.len();
+        for _ in 0..len {
+            if let Some(e) = self.entries.pop_front()
+                && (e.network_id != addr.network_id || e.node_id != addr.node_id)
+            {
+                let _ = self.entries.push_back(e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PATH_MTU_CACHE_SLOTS, PathMtuCache};
+    use crate::Address;
+
+    fn addr(node_id: u8) -> Address {
+        Address {
+            network_id: 1,
+            node_id,
+            port_id: 0,
+        }
+    }
+
+    #[test]
+    fn oldest_entry_is_replaced() {
+        let mut cache = PathMtuCache::new();
+        for i in 0..PATH_MTU_CACHE_SLOTS as u8 {
+            cache.insert(addr(i), 100 + i as u16);
+        }
+        // Updating an entry makes it the most recent one
+        cache.insert(addr(0), 64);
+        cache.insert(addr(200), 128);
+
+        assert_eq!(cache.get(addr(0)), Some(64));
+        assert_eq!(cache.get(addr(1)), None);
+        assert_eq!(cache.get(addr(2)), Some(102));
+        assert_eq!(cache.get(addr(200)), Some(128));
+
+        cache.remove(addr(200));
+        assert_eq!(cache.get(addr(200)), None);
+    }
+}
@@ -0,0 +1,492 @@
+//! Remote Subscriptions
+//!
+//! A topic broadcast is sent to every device on the network. When only one
+//! host is interested in a topic, e.g. telemetry from a device several hops
+//! away, a [`Publisher`] can send it to that host alone instead.
+//!
+//! The subscriber creates a unicast receiver for the topic (see
+//! `Receiver::subscribe_unicast()`), and asks the publisher to send the topic
+//! to the port of that receiver with [`Topics::remote_subscribe()`]. Requests
+//! are sent to the [`ErgotRemoteSubscriptionEndpoint`] of the publishing
+//! device, using the path of the topic as the name, so that one device can
+//! host publishers for several topics.
+//!
+//! Subscriptions are leased, like seed-assigned net_ids: each subscription
+//! expires unless the subscriber refreshes it in time with the refresh token
+//! it was given, see [`RemoteSubscription::keep_alive()`]. A subscriber that
+//! goes away without unsubscribing is dropped once its lease expires.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use core::pin::pin;
+//! # use mutex::raw_impls::cs::CriticalSectionRawMutex as CSRMutex;
+//! # use ergot::{Address, NetStack, interface_manager::profiles::null::Null};
+//! # use rand::SeedableRng;
+//! ergot::topic!(Temperature, f32, "example/temperature");
+//!
+//! static STACK: NetStack<CSRMutex, Null> = NetStack::new();
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let publisher = STACK
+//!         .topics()
+//!         .publisher::<Temperature, _, 4>(rand::rngs::StdRng::seed_from_u64(0));
+//!     let server = publisher.serve::<4>();
+//!
+//!     let client = async {
+//!         let rx = STACK.topics().bounded_receiver::<Temperature, 4>(None);
+//!         let rx = pin!(rx);
+//!         let mut rx = rx.subscribe_unicast();
+//!         let sub = STACK
+//!             .topics()
+//!             .remote_subscribe::<Temperature>(Address::unknown(), rx.port())
+//!             .await
+//!             .unwrap();
+//!
+//!         assert_eq!(publisher.publish(&21.5), 1);
+//!         assert_eq!(rx.recv().await.t, 21.5);
+//!         sub.unsubscribe().await.unwrap();
+//!         assert_eq!(publisher.publish(&22.0), 0);
+//!     };
+//!
+//!     // Poll the server first, so that it is listening before any request
+//!     tokio::select! {
+//!         biased;
+//!         _ = server => unreachable!(),
+//!         _ = client => {}
+//!     }
+//! }
+//! ```
+//!
+//! [`Topics::remote_subscribe()`]: crate::net_stack::topics::Topics::remote_subscribe
+
+use core::marker::PhantomData;
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    Address, Key, Priority,
+    net_stack::{NetStackHandle, ReqRespError, endpoints::Endpoints},
+    traits::Topic,
+    well_known::{
+        ErgotRemoteSubscriptionEndpoint, RemoteSubscriptionError, RemoteSubscriptionLease,
+        RemoteSubscriptionRequest,
+    },
+};
+
+#[cfg(any(feature = "std", feature = "nostd-seed-router"))]
+pub use self::server::Publisher;
+
+#[cfg(any(feature = "std", feature = "nostd-seed-router"))]
+mod server {
+    use core::{marker::PhantomData, pin::pin};
+
+    use mutex::{BlockingMutex, ConstInit, ScopedRawMutex};
+    use rand_core::RngCore;
+    use serde::{Serialize, de::DeserializeOwned};
+
+    // See `interface_manager::profiles::router` for why `web-time` is used
+    #[cfg(feature = "std")]
+    use web_time::Instant;
+
+    #[cfg(all(not(feature = "std"), feature = "nostd-seed-router"))]
+    use embassy_time::Instant;
+
+    use crate::{
+        Address, Key, Priority,
+        interface_manager::lease::{
+            INITIAL_LEASE_SECS, LeaseKind, MAX_LEASE_SECS, MIN_REFRESH_SECS, RefreshDenied,
+            remaining_lease_seconds,
+        },
+        logging::debug,
+        net_stack::{
+            NetStackHandle,
+            endpoints::Endpoints,
+            retained::{RetainedStorage, RetainedStore},
+            topics::Topics,
+        },
+        traits::Topic,
+        well_known::{
+            ErgotRemoteSubscriptionEndpoint, RemoteSubscriptionError, RemoteSubscriptionLease,
+            RemoteSubscriptionRequest, RemoteSubscriptionResponse,
+        },
+    };
+
+    struct Subscriber {
+        addr: Address,
+        kind: LeaseKind,
+    }
+
+    struct State<R, const N: usize> {
+        rng: R,
+        subscribers: heapless::Vec<Subscriber, N>,
+    }
+
+    /// Sends the topic `T` to up to `N` remote subscribers, see the
+    /// [module docs](super)
+    ///
+    /// Subscriptions are only accepted while [`Publisher::serve()`] is running.
+    pub struct Publisher<T, NS, R, const N: usize>
+    where
+        T: Topic,
+        NS: NetStackHandle,
+        R: RngCore,
+    {
+        inner: NS,
+        prio: Priority,
+        state: BlockingMutex<NS::Mutex, State<R, N>>,
+        _pd: PhantomData<fn() -> T>,
+    }
+
+    impl<T, NS, R, const N: usize> Publisher<T, NS, R, N>
+    where
+        T: Topic,
+        T::Message: Serialize + Clone + DeserializeOwned + 'static,
+        NS: NetStackHandle,
+        NS::Mutex: ConstInit,
+        R: RngCore,
+    {
+        /// Create a publisher with no subscribers, using `rng` to generate
+        /// refresh tokens
+        pub fn new(inner: NS, rng: R) -> Self {
+            Self {
+                inner,
+                prio: Priority::NORMAL,
+                state: BlockingMutex::new(State {
+                    rng,
+                    subscribers: heapless::Vec::new(),
+                }),
+                _pd: PhantomData,
+            }
+        }
+
+        /// Send messages with the given [`Priority`], instead of [`Priority::NORMAL`]
+        pub fn with_priority(self, prio: Priority) -> Self {
+            Self { prio, ..self }
+        }
+    }
+
+    impl<T, NS, R, const N: usize> Publisher<T, NS, R, N>
+    where
+        T: Topic,
+        T::Message: Serialize + Clone + DeserializeOwned + 'static,
+        NS: NetStackHandle,
+        R: RngCore,
+    {
+        /// Send `msg` to each subscriber with a live subscription
+        ///
+        /// Like a broadcast, this is best-effort. Returns the number of
+        /// subscribers the message was sent to.
+        pub fn publish(&self, msg: &T::Message) -> usize {
+            let now = Instant::now();
+            let live = self.state.with_lock(|state| {
+                state.subscribers.retain(|s| s.kind.is_active(now));
+                state
+                    .subscribers
+                    .iter()
+                    .map(|s| s.addr)
+                    .collect::<heapless::Vec<Address, N>>()
+            });
+            let topics = Topics {
+                inner: self.inner.clone(),
+                prio: self.prio,
+            };
+            live.into_iter()
+                .filter(|addr| match topics.clone().unicast::<T>(*addr, msg) {
+                    Ok(()) => true,
+                    Err(_e) => {
+                        debug!("publish to {:?} failed: {:?}", addr, _e);
+                        false
+                    }
+                })
+                .count()
+        }
+
+        /// The number of live subscriptions
+        pub fn subscribers(&self) -> usize {
+            let now = Instant::now();
+            self.state.with_lock(|state| {
+                state
+                    .subscribers
+                    .iter()
+                    .filter(|s| s.kind.is_active(now))
+                    .count()
+            })
+        }
+
+        /// Accept subscribe, refresh and unsubscribe requests for `T`
+        ///
+        /// The const parameter `D` controls the depth of the socket to buffer
+        /// requests.
+        pub async fn serve<const D: usize>(&self) -> ! {
+            self.serve_with::<D>(|_| {}).await
+        }
+
+        /// Like [`Publisher::serve()`], but also send the message of `T`
+        /// retained in `store`, if any, to each new subscriber
+        ///
+        /// See the [`retained`](crate::net_stack::retained) module docs.
+        pub async fn serve_with_retained<const D: usize>(
+            &self,
+            store: &RetainedStore<impl ScopedRawMutex, impl RetainedStorage>,
+        ) -> ! {
+            self.serve_with::<D>(|addr| {
+                // Deserialized, so that the store is not locked while sending
+                let Some(msg) = store.get::<T>() else {
+                    return;
+                };
+                let topics = Topics {
+                    inner: self.inner.clone(),
+                    prio: self.prio,
+                };
+                if let Err(_e) = topics.unicast::<T>(addr, &msg) {
+                    debug!("sending retained message to {:?} failed: {:?}", addr, _e);
+                }
+            })
+            .await
+        }
+
+        async fn serve_with<const D: usize>(&self, on_subscribe: impl Fn(Address)) -> ! {
+            let server = Endpoints {
+                inner: self.inner.clone(),
+                prio: Priority::NORMAL,
+            }
+            .bounded_server::<ErgotRemoteSubscriptionEndpoint, D>(Some(T::PATH));
+            let server = pin!(server);
+            let mut server_hdl = server.attach();
+            loop {
+                _ = server_hdl
+                    .serve_full(async |msg| {
+                        let resp = self.handle(msg.hdr.src, &msg.t);
+                        if let (RemoteSubscriptionRequest::Subscribe { port, .. }, Ok(_)) =
+                            (&msg.t, &resp)
+                        {
+                            on_subscribe(Address {
+                                port_id: *port,
+                                ..msg.hdr.src
+                            });
+                        }
+                        resp
+                    })
+                    .await;
+            }
+        }
+
+        fn handle(
+            &self,
+            src: Address,
+            req: &RemoteSubscriptionRequest,
+        ) -> RemoteSubscriptionResponse {
+            let (key, port) = match req {
+                RemoteSubscriptionRequest::Subscribe { key, port }
+                | RemoteSubscriptionRequest::Refresh { key, port, .. }
+                | RemoteSubscriptionRequest::Unsubscribe { key, port, .. } => (*key, *port),
+            };
+            if key != Key(T::TOPIC_KEY.to_bytes()) {
+                return Err(RemoteSubscriptionError::UnknownTopic);
+            }
+            let addr = Address {
+                port_id: port,
+                ..src
+            };
+            let now = Instant::now();
+
+            self.state.with_lock(|state| {
+                state.subscribers.retain(|s| s.kind.is_active(now));
+                let pos = state.subscribers.iter().position(|s| s.addr == addr);
+                match (req, pos) {
+                    (RemoteSubscriptionRequest::Subscribe { .. }, pos) => {
+                        if let Some(pos) = pos {
+                            state.subscribers.swap_remove(pos);
+                        }
+                        let refresh_token = state.rng.next_u64();
+                        state
+                            .subscribers
+                            .push(Subscriber {
+                                addr,
+                                kind: LeaseKind::active(now, INITIAL_LEASE_SECS, refresh_token),
+                            })
+                            .map_err(|_| RemoteSubscriptionError::TableFull)?;
+                        Ok(lease(INITIAL_LEASE_SECS, refresh_token))
+                    }
+                    (RemoteSubscriptionRequest::Refresh { refresh_token, .. }, Some(pos)) => {
+                        let new_token = state.rng.next_u64();
+                        let (lease, _replay) = state.subscribers[pos]
+                            .kind
+                            .refresh(u64::from_le_bytes(*refresh_token), now, new_token, true)
+                            .map_err(denied)?;
+                        Ok(self::lease(
+                            remaining_lease_seconds(lease.expiration, now),
+                            lease.refresh_token,
+                        ))
+                    }
+                    (RemoteSubscriptionRequest::Unsubscribe { refresh_token, .. }, Some(pos)) => {
+                        state.subscribers[pos]
+                            .kind
+                            .validate_token(u64::from_le_bytes(*refresh_token), now, false)
+                            .map_err(denied)?;
+                        state.subscribers.swap_remove(pos);
+                        Ok(lease(0, 0))
+                    }
+                    (_, None) => Err(RemoteSubscriptionError::NotSubscribed),
+                }
+            })
+        }
+    }
+
+    fn lease(expires_seconds: u16, refresh_token: u64) -> RemoteSubscriptionLease {
+        RemoteSubscriptionLease {
+            expires_seconds,
+            max_refresh_seconds: MAX_LEASE_SECS,
+            min_refresh_seconds: MIN_REFRESH_SECS,
+            refresh_token: refresh_token.to_le_bytes(),
+        }
+    }
+
+    fn denied(e: RefreshDenied) -> RemoteSubscriptionError {
+        match e {
+            RefreshDenied::Expired => RemoteSubscriptionError::AlreadyExpired,
+            RefreshDenied::BadToken => RemoteSubscriptionError::BadRequest,
+            RefreshDenied::TooSoon => RemoteSubscriptionError::TooSoon,
+        }
+    }
+}
+
+/// Errors from remote subscription client operations.
+#[derive(Debug, PartialEq)]
+pub enum RemoteSubscriptionClientError {
+    /// The request failed at the req-resp layer.
+    RequestFailed(ReqRespError),
+    /// The publisher denied the request.
+    Denied(RemoteSubscriptionError),
+}
+
+/// A subscription granted by a remote [`Publisher`]
+///
+/// Dropping this does not end the subscription, which lasts until it expires
+/// or [`RemoteSubscription::unsubscribe()`] is called.
+pub struct RemoteSubscription<T, NS>
+where
+    T: Topic,
+    NS: NetStackHandle,
+{
+    inner: NS,
+    publisher: Address,
+    port: u8,
+    lease: RemoteSubscriptionLease,
+    _pd: PhantomData<fn() -> T>,
+}
+
+impl<T, NS> RemoteSubscription<T, NS>
+where
+    T: Topic,
+    T::Message: Serialize + Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    pub(crate) async fn subscribe(
+        inner: NS,
+        publisher: Address,
+        port: u8,
+    ) -> Result<Self, RemoteSubscriptionClientError> {
+        let publisher = Address {
+            port_id: 0,
+            ..publisher
+        };
+        let mut this = Self {
+            inner,
+            publisher,
+            port,
+            lease: RemoteSubscriptionLease {
+                expires_seconds: 0,
+                max_refresh_seconds: 0,
+                min_refresh_seconds: 0,
+                refresh_token: [0; 8],
+            },
+            _pd: PhantomData,
+        };
+        this.lease = this
+            .request(&RemoteSubscriptionRequest::Subscribe {
+                key: Key(T::TOPIC_KEY.to_bytes()),
+                port,
+            })
+            .await?;
+        Ok(this)
+    }
+
+    /// The device publishing the topic
+    pub fn publisher(&self) -> Address {
+        self.publisher
+    }
+
+    /// The local port the topic is sent to
+    pub fn port(&self) -> u8 {
+        self.port
+    }
+
+    /// The lease granted by the last subscribe or refresh request
+    pub fn lease(&self) -> &RemoteSubscriptionLease {
+        &self.lease
+    }
+
+    /// Extend the subscription
+    ///
+    /// The publisher rejects refreshes while more than `min_refresh_seconds`
+    /// of the [`lease()`](Self::lease) are left.
+    pub async fn refresh(&mut self) -> Result<(), RemoteSubscriptionClientError> {
+        self.lease = self
+            .request(&RemoteSubscriptionRequest::Refresh {
+                key: Key(T::TOPIC_KEY.to_bytes()),
+                port: self.port,
+                refresh_token: self.lease.refresh_token,
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// End the subscription
+    pub async fn unsubscribe(self) -> Result<(), RemoteSubscriptionClientError> {
+        self.request(&RemoteSubscriptionRequest::Unsubscribe {
+            key: Key(T::TOPIC_KEY.to_bytes()),
+            port: self.port,
+            refresh_token: self.lease.refresh_token,
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Refresh the subscription whenever the publisher allows it, until a
+    /// refresh fails
+    #[cfg(any(
+        feature = "tokio-std",
+        feature = "embassy-time",
+        feature = "nostd-seed-router"
+    ))]
+    pub async fn keep_alive(&mut self) -> RemoteSubscriptionClientError {
+        loop {
+            let wait = self
+                .lease
+                .expires_seconds
+                .saturating_sub(self.lease.min_refresh_seconds)
+                .saturating_add(1);
+            super::timer_sleep(core::time::Duration::from_secs(wait.into())).await;
+            if let Err(e) = self.refresh().await {
+                return e;
+            }
+        }
+    }
+
+    async fn request(
+        &self,
+        req: &RemoteSubscriptionRequest,
+    ) -> Result<RemoteSubscriptionLease, RemoteSubscriptionClientError> {
+        Endpoints {
+            inner: self.inner.clone(),
+            prio: Priority::NORMAL,
+        }
+        .request::<ErgotRemoteSubscriptionEndpoint>(self.publisher, req, Some(T::PATH))
+        .await
+        .map_err(RemoteSubscriptionClientError::RequestFailed)?
+        .map_err(RemoteSubscriptionClientError::Denied)
+    }
+}
@@ -0,0 +1,272 @@
+//! Reassembly of fragmented messages
+//!
+//! Messages sent with [`NetStack::send_ty_fragmented()`] may arrive as a series
+//! of [`FrameKind::FRAGMENT`] frames. The receiving [`NetStack`] keeps a bounded
+//! number of partially received messages, keyed by source address and message
+//! id. Messages that are not completed within [`REASSEMBLY_TIMEOUT_MS`] are
+//! evicted, and when all slots are in use, the oldest partial message is evicted
+//! to make room for a new one.
+//!
+//! Fragments must arrive in order: a fragment that skips ahead discards the
+//! partial message, and repeated fragments are ignored.
+//!
+//! With `std`, up to [`REASSEMBLY_SLOTS`] messages of up to
+//! [`REASSEMBLY_MAX_LEN`] bytes are reassembled on the heap. The
+//! `nostd-reassembly` feature adds `ReassemblyBuffers`, storage for a chosen
+//! number of messages of a chosen size, which is given to the stack with
+//! `NetStack::set_reassembly_buffers()`. Without `std`, fragments are
+//! dropped until the stack is given buffers.
+//!
+//! ```rust,ignore
+//! use static_cell::ConstStaticCell;
+//! use ergot::net_stack::reassembly::ReassemblyBuffers;
+//!
+//! // Two messages of up to 2 KiB at a time
+//! static BUFFERS: ConstStaticCell<ReassemblyBuffers<2, 2048>> =
+//!     ConstStaticCell::new(ReassemblyBuffers::new());
+//!
+//! STACK.set_reassembly_buffers(BUFFERS.take());
+//! ```
+//!
+//! [`NetStack`]: crate::NetStack
+//! [`NetStack::send_ty_fragmented()`]: crate::NetStack::send_ty_fragmented
+//! [`FrameKind::FRAGMENT`]: crate::FrameKind::FRAGMENT
+
+#[cfg(feature = "std")]
+use web_time::{Duration, Instant};
+
+#[cfg(all(not(feature = "std"), feature = "nostd-reassembly"))]
+use embassy_time::{Duration, Instant};
+
+use crate::{Address, logging::debug, wire_frames::Fragment};
+
+/// The maximum number of messages that may be reassembled at the same time on
+/// the heap
+#[cfg(feature = "std")]
+pub const REASSEMBLY_SLOTS: usize = 16;
+
+/// The largest payload reassembled on the heap, in bytes
+#[cfg(feature = "std")]
+pub const REASSEMBLY_MAX_LEN: usize = 64 * 1024;
+
+/// How long a partial message is kept before it is evicted
+pub const REASSEMBLY_TIMEOUT_MS: u64 = 2_000;
+
+/// A partially received message
+#[derive(Clone, Copy)]
+pub(crate) struct Partial {
+    src: Address,
+    msg_id: u16,
+    started: Instant,
+    /// The number of bytes received so far
+    len: usize,
+}
+
+/// Slots that messages are reassembled in
+pub(crate) trait Storage {
+    /// The partial message in each slot, if any
+    fn slots(&mut self) -> &mut [Option<Partial>];
+    /// Write `data` at `offset` into the buffer of slot `idx`
+    ///
+    /// Returns false if it doesn't fit.
+    fn write(&mut self, idx: usize, offset: usize, data: &[u8]) -> bool;
+    /// The first `len` bytes of the buffer of slot `idx`
+    fn read(&self, idx: usize, len: usize) -> &[u8];
+}
+
+/// Statically sized storage to reassemble `SLOTS` messages of up to `LEN`
+/// bytes at the same time
+///
+/// See the [module docs](self) for how to use it.
+#[cfg(feature = "nostd-reassembly")]
+pub struct ReassemblyBuffers<const SLOTS: usize, const LEN: usize> {
+    slots: [Option<Partial>; SLOTS],
+    bufs: [[u8; LEN]; SLOTS],
+}
+
+#[cfg(feature = "nostd-reassembly")]
+impl<const SLOTS: usize, const LEN: usize> ReassemblyBuffers<SLOTS, LEN> {
+    pub const fn new() -> Self {
+        Self {
+            slots: [None; SLOTS],
+            bufs: [[0; LEN]; SLOTS],
+        }
+    }
+}
+
+#[cfg(feature = "nostd-reassembly")]
+impl<const SLOTS: usize, const LEN: usize> Default for ReassemblyBuffers<SLOTS, LEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "nostd-reassembly")]
+impl<const SLOTS: usize, const LEN: usize> Storage for ReassemblyBuffers<SLOTS, LEN> {
+    fn slots(&mut self) -> &mut [Option<Partial>] {
+        &mut self.slots
+    }
+
+    fn write(&mut self, idx: usize, offset: usize, data: &[u8]) -> bool {
+        match self.bufs[idx].get_mut(offset..offset + data.len()) {
+            Some(dst) => {
+                dst.copy_from_slice(data);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn read(&self, idx: usize, len: usize) -> &[u8] {
+        &self.bufs[idx][..len]
+    }
+}
+
+/// Heap storage, the buffers grow as fragments arrive
+#[cfg(feature = "std")]
+struct HeapBuffers {
+    slots: [Option<Partial>; REASSEMBLY_SLOTS],
+    bufs: [std::vec::Vec<u8>; REASSEMBLY_SLOTS],
+}
+
+#[cfg(feature = "std")]
+impl Storage for HeapBuffers {
+    fn slots(&mut self) -> &mut [Option<Partial>] {
+        &mut self.slots
+    }
+
+    fn write(&mut self, idx: usize, offset: usize, data: &[u8]) -> bool {
+        if offset + data.len() > REASSEMBLY_MAX_LEN {
+            return false;
+        }
+        let buf = &mut self.bufs[idx];
+        buf.truncate(offset);
+        buf.extend_from_slice(data);
+        true
+    }
+
+    fn read(&self, idx: usize, len: usize) -> &[u8] {
+        &self.bufs[idx][..len]
+    }
+}
+
+pub(crate) struct Reassembler {
+    #[cfg(feature = "std")]
+    heap: HeapBuffers,
+    #[cfg(feature = "nostd-reassembly")]
+    buffers: Option<&'static mut (dyn Storage + Send)>,
+}
+
+impl Reassembler {
+    pub(crate) const fn new() -> Self {
+        Self {
+            #[cfg(feature = "std")]
+            heap: HeapBuffers {
+                slots: [None; REASSEMBLY_SLOTS],
+                bufs: [const { std::vec::Vec::new() }; REASSEMBLY_SLOTS],
+            },
+            #[cfg(feature = "nostd-reassembly")]
+            buffers: None,
+        }
+    }
+
+    /// Reassemble in `buffers` from now on, instead of on the heap
+    #[cfg(feature = "nostd-reassembly")]
+    pub(crate) fn set_buffers(&mut self, buffers: &'static mut (dyn Storage + Send)) {
+        self.buffers = Some(buffers);
+    }
+
+    fn storage(&mut self) -> Option<&mut dyn Storage> {
+        #[cfg(feature = "nostd-reassembly")]
+        if self.buffers.is_some() {
+            return self.buffers.as_deref_mut().map(|b| b as &mut dyn Storage);
+        }
+        #[cfg(feature = "std")]
+        return Some(&mut self.heap);
+        #[cfg(not(feature = "std"))]
+        None
+    }
+
+    /// Add a fragment received from `src`
+    ///
+    /// Returns the reassembled payload once the last fragment of a message
+    /// has been received.
+    pub(crate) fn push(&mut self, src: Address, frag: &Fragment<'_>) -> Option<&[u8]> {
+        let Some(storage) = self.storage() else {
+            debug!("No reassembly buffers, dropping fragment from {}", src);
+            return None;
+        };
+        let now = Instant::now();
+        let timeout = Duration::from_millis(REASSEMBLY_TIMEOUT_MS);
+        let slots = storage.slots();
+        for slot in slots.iter_mut() {
+            if slot.is_some_and(|p| now.duration_since(p.started) >= timeout) {
+                *slot = None;
+            }
+        }
+
+        let msg_id = frag.hdr.msg_id;
+        let pos = slots
+            .iter()
+            .position(|p| p.is_some_and(|p| p.src == src && p.msg_id == msg_id));
+        let idx = match pos {
+            Some(idx) => idx,
+            // We missed the start of this message, there's nothing to do
+            None if frag.hdr.offset != 0 => return None,
+            None => {
+                let idx = match slots.iter().position(Option::is_none) {
+                    Some(idx) => idx,
+                    // `oldest` is only used in the logging macro (no-op when
+                    // internal logging is disabled)
+                    #[allow(unused_variables)]
+                    None => {
+                        let (idx, oldest) = slots
+                            .iter()
+                            .enumerate()
+                            .filter_map(|(i, p)| Some((i, (*p)?)))
+                            .min_by_key(|(_, p)| p.started)?;
+                        debug!(
+                            "Evicting partial message {} from {}",
+                            oldest.msg_id, oldest.src
+                        );
+                        idx
+                    }
+                };
+                slots[idx] = Some(Partial {
+                    src,
+                    msg_id,
+                    started: now,
+                    len: 0,
+                });
+                idx
+            }
+        };
+
+        let slot = &mut slots[idx];
+        let partial = slot.as_mut()?;
+        let offset = frag.hdr.offset as usize;
+        if offset < partial.len {
+            // A repeated fragment
+            return None;
+        }
+        if offset > partial.len {
+            debug!("Discarding partial message {} from {}", msg_id, src);
+            *slot = None;
+            return None;
+        }
+        partial.len = offset + frag.data.len();
+        let len = partial.len;
+        if !storage.write(idx, offset, frag.data) {
+            debug!("Discarding partial message {} from {}", msg_id, src);
+            storage.slots()[idx] = None;
+            return None;
+        }
+
+        if frag.hdr.more {
+            None
+        } else {
+            storage.slots()[idx] = None;
+            Some(storage.read(idx, len))
+        }
+    }
+}
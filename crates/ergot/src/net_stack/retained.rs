@@ -0,0 +1,341 @@
+//! Retained Messages
+//!
+//! A topic message is only received by the sockets that exist when it is
+//! sent. A host that connects after a device published its configuration, or
+//! its last status, would have to wait for the next message. Like MQTT's
+//! retained messages, a [`RetainedStore`] keeps the last message of each
+//! topic, so that it can be sent again on request.
+//!
+//! Messages are retained by publishing them with
+//! [`Topics::broadcast_retained()`], or with [`RetainedStore::retain()`]. They
+//! are stored serialized, in a [`RetainedStorage`]:
+//!
+//! * [`StaticRetained`] holds up to `N` topics, of up to `B` bytes each, and
+//!   works on `no_std`
+//! * [`HeapRetained`] holds any number of topics, and requires the `std`
+//!   feature
+//!
+//! Retained messages are sent to:
+//!
+//! * any device that asks for them, with [`Topics::request_retained()`], when
+//!   the device holding the store runs [`Services::retained_handler()`]
+//! * each new remote subscriber of a
+//!   [`Publisher`](crate::net_stack::publisher::Publisher) served with
+//!   `Publisher::serve_with_retained()`
+//!
+//! In both cases, the message is sent to a unicast receiver of the requesting
+//! device, as if it had just been published.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use core::pin::pin;
+//! # use mutex::raw_impls::cs::CriticalSectionRawMutex as CSRMutex;
+//! # use ergot::{Address, NetStack, interface_manager::profiles::null::Null};
+//! use ergot::net_stack::retained::{RetainedStore, StaticRetained};
+//!
+//! ergot::topic!(Config, u32, "example/config");
+//!
+//! static STACK: NetStack<CSRMutex, Null> = NetStack::new();
+//! static RETAINED: RetainedStore<CSRMutex, StaticRetained<4, 16>> =
+//!     RetainedStore::new(StaticRetained::new());
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     // Nobody is listening yet
+//!     RETAINED.retain::<Config>(&42).unwrap();
+//!
+//!     let server = STACK.services().retained_handler::<4, 16>(&RETAINED);
+//!     let client = async {
+//!         // A receiver created after the message was published
+//!         let rx = STACK.topics().bounded_receiver::<Config, 4>(None);
+//!         let rx = pin!(rx);
+//!         let mut rx = rx.subscribe_unicast();
+//!         let sent = STACK
+//!             .topics()
+//!             .request_retained::<Config>(Address::unknown(), rx.port())
+//!             .await
+//!             .unwrap();
+//!         assert!(sent);
+//!         assert_eq!(rx.recv().await.t, 42);
+//!     };
+//!
+//!     // Poll the server first, so that it is listening before any request
+//!     tokio::select! {
+//!         biased;
+//!         _ = server => unreachable!(),
+//!         _ = client => {}
+//!     }
+//! }
+//! ```
+//!
+//! [`Topics::broadcast_retained()`]: crate::net_stack::topics::Topics::broadcast_retained
+//! [`Topics::request_retained()`]: crate::net_stack::topics::Topics::request_retained
+//! [`Services::retained_handler()`]: crate::net_stack::services::Services::retained_handler
+
+use mutex::{BlockingMutex, ConstInit, ScopedRawMutex};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    Address, DEFAULT_TTL, FrameKind, Header, Key, Priority,
+    net_stack::{NetStackHandle, NetStackSendError},
+    traits::Topic,
+};
+
+/// An error retaining a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub enum RetainError {
+    /// The storage already holds as many topics as it can
+    StorageFull,
+    /// The serialized message does not fit in the storage. Any message
+    /// previously retained for the topic is forgotten.
+    TooLarge,
+}
+
+/// An error from [`Topics::broadcast_retained()`]
+///
+/// [`Topics::broadcast_retained()`]: crate::net_stack::topics::Topics::broadcast_retained
+#[derive(Debug, PartialEq)]
+pub enum BroadcastRetainedError {
+    /// The message could not be retained, and was not sent
+    Retain(RetainError),
+    /// The message was retained, but could not be sent
+    Send(NetStackSendError),
+}
+
+/// Storage for the serialized last message of each topic
+pub trait RetainedStorage {
+    /// The retained message for `key`, if any
+    fn get(&self, key: &Key) -> Option<&[u8]>;
+
+    /// Serialize `msg`, replacing the retained message for `key`
+    fn retain<T: Serialize + ?Sized>(&mut self, key: Key, msg: &T) -> Result<(), RetainError>;
+
+    /// Forget the retained message for `key`
+    fn remove(&mut self, key: &Key);
+}
+
+struct Slot<const B: usize> {
+    key: Key,
+    len: usize,
+    buf: [u8; B],
+}
+
+/// Bounded [`RetainedStorage`] for up to `N` topics, of up to `B` serialized
+/// bytes each
+pub struct StaticRetained<const N: usize, const B: usize> {
+    slots: heapless::Vec<Slot<B>, N>,
+}
+
+impl<const N: usize, const B: usize> StaticRetained<N, B> {
+    pub const fn new() -> Self {
+        Self {
+            slots: heapless::Vec::new(),
+        }
+    }
+}
+
+impl<const N: usize, const B: usize> Default for StaticRetained<N, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, const B: usize> RetainedStorage for StaticRetained<N, B> {
+    fn get(&self, key: &Key) -> Option<&[u8]> {
+        self.slots
+            .iter()
+            .find(|s| s.key == *key)
+            .map(|s| &s.buf[..s.len])
+    }
+
+    fn retain<T: Serialize + ?Sized>(&mut self, key: Key, msg: &T) -> Result<(), RetainError> {
+        let pos = match self.slots.iter().position(|s| s.key == key) {
+            Some(pos) => pos,
+            None => {
+                self.slots
+                    .push(Slot {
+                        key,
+                        len: 0,
+                        buf: [0; B],
+                    })
+                    .map_err(|_| RetainError::StorageFull)?;
+                self.slots.len() - 1
+            }
+        };
+        // Serialize in place: on failure, the slot no longer holds a valid
+        // message, so it is dropped
+        match postcard::to_slice(msg, &mut self.slots[pos].buf) {
+            Ok(used) => {
+                self.slots[pos].len = used.len();
+                Ok(())
+            }
+            Err(_) => {
+                self.slots.swap_remove(pos);
+                Err(RetainError::TooLarge)
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &Key) {
+        self.slots.retain(|s| s.key != *key);
+    }
+}
+
+/// Heap-backed [`RetainedStorage`], for any number of topics
+#[cfg(feature = "std")]
+#[derive(Default)]
+pub struct HeapRetained {
+    messages: std::collections::HashMap<[u8; 8], Vec<u8>>,
+}
+
+#[cfg(feature = "std")]
+impl HeapRetained {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "std")]
+impl RetainedStorage for HeapRetained {
+    fn get(&self, key: &Key) -> Option<&[u8]> {
+        self.messages.get(&key.0).map(Vec::as_slice)
+    }
+
+    fn retain<T: Serialize + ?Sized>(&mut self, key: Key, msg: &T) -> Result<(), RetainError> {
+        match postcard::to_stdvec(msg) {
+            Ok(body) => {
+                self.messages.insert(key.0, body);
+                Ok(())
+            }
+            Err(_) => {
+                self.messages.remove(&key.0);
+                Err(RetainError::TooLarge)
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &Key) {
+        self.messages.remove(&key.0);
+    }
+}
+
+/// The last message of each topic, in a [`RetainedStorage`] `S`, see the
+/// [module docs](self)
+pub struct RetainedStore<R: ScopedRawMutex, S: RetainedStorage> {
+    storage: BlockingMutex<R, S>,
+}
+
+impl<R: ScopedRawMutex + ConstInit, S: RetainedStorage> RetainedStore<R, S> {
+    pub const fn new(storage: S) -> Self {
+        Self {
+            storage: BlockingMutex::new(storage),
+        }
+    }
+}
+
+impl<R: ScopedRawMutex, S: RetainedStorage> RetainedStore<R, S> {
+    /// Retain `msg` as the last message of the topic `T`
+    pub fn retain<T>(&self, msg: &T::Message) -> Result<(), RetainError>
+    where
+        T: Topic,
+        T::Message: Serialize,
+    {
+        self.storage
+            .with_lock(|s| s.retain(Key(T::TOPIC_KEY.to_bytes()), msg))
+    }
+
+    /// The last message retained for the topic `T`
+    pub fn get<T>(&self) -> Option<T::Message>
+    where
+        T: Topic,
+        T::Message: DeserializeOwned,
+    {
+        self.storage.with_lock(|s| {
+            let body = s.get(&Key(T::TOPIC_KEY.to_bytes()))?;
+            postcard::from_bytes(body).ok()
+        })
+    }
+
+    /// Forget the last message retained for the topic `T`
+    pub fn forget<T: Topic>(&self) {
+        self.storage
+            .with_lock(|s| s.remove(&Key(T::TOPIC_KEY.to_bytes())));
+    }
+
+    /// Send the message retained for `key` to `dst`
+    ///
+    /// The message is copied into `scratch` first, so that the store is not
+    /// locked while it is sent. Returns `Ok(false)` if there is no retained
+    /// message.
+    pub(crate) fn send_to<NS: NetStackHandle>(
+        &self,
+        nsh: &NS,
+        key: Key,
+        dst: Address,
+        scratch: &mut [u8],
+    ) -> Result<bool, NetStackSendError> {
+        let hdr = Header {
+            src: Address {
+                network_id: 0,
+                node_id: 0,
+                port_id: 0,
+            },
+            dst,
+            any_all: None,
+            seq_no: None,
+            kind: FrameKind::TOPIC_MSG,
+            ttl: DEFAULT_TTL,
+            prio: Priority::NORMAL,
+        };
+        let len = self.storage.with_lock(|s| {
+            let Some(body) = s.get(&key) else {
+                return Ok(None);
+            };
+            scratch
+                .get_mut(..body.len())
+                .ok_or(NetStackSendError::MessageTooLarge)?
+                .copy_from_slice(body);
+            Ok(Some(body.len()))
+        })?;
+        let Some(len) = len else {
+            return Ok(false);
+        };
+        nsh.stack()
+            .send_serialized(&hdr, &scratch[..len])
+            .map(|()| true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RetainError, RetainedStorage, StaticRetained};
+    use crate::Key;
+
+    #[test]
+    fn static_storage_is_bounded() {
+        let mut storage = StaticRetained::<2, 4>::new();
+        storage.retain(Key([1; 8]), &1u32).unwrap();
+        storage.retain(Key([2; 8]), &2u32).unwrap();
+        assert_eq!(
+            storage.retain(Key([3; 8]), &3u32),
+            Err(RetainError::StorageFull)
+        );
+
+        // Replacing a message doesn't need a new slot
+        storage.retain(Key([1; 8]), &300u32).unwrap();
+        assert_eq!(storage.get(&Key([1; 8])), Some(&[0xAC, 0x02][..]));
+
+        // A message that is too large drops the stale one
+        assert_eq!(
+            storage.retain(Key([2; 8]), &[1u8; 8]),
+            Err(RetainError::TooLarge)
+        );
+        assert_eq!(storage.get(&Key([2; 8])), None);
+        storage.retain(Key([3; 8]), &3u32).unwrap();
+
+        storage.remove(&Key([1; 8]));
+        assert_eq!(storage.get(&Key([1; 8])), None);
+    }
+}
@@ -3,21 +3,39 @@ use embassy_futures::select::{Either, Either3, select3};
 #[cfg(feature = "std")]
 use crate::fmtlog::ErgotFmtRxOwned;
 use crate::{
-    interface_manager::Profile,
-    net_stack::{NetStackHandle, endpoints::Endpoints, topics::Topics},
+    Address, DEFAULT_TTL, FrameKind, Header, Priority, ProtocolError,
+    interface_manager::{Profile, profiles::reply_err},
+    net_stack::{
+        NetStackHandle,
+        endpoints::Endpoints,
+        retained::{RetainedStorage, RetainedStore},
+        topics::Topics,
+    },
     socket::HeaderMessage,
     well_known::{
         AddressClaimGranted, AddressClaimRequest, AddressRefreshRequest, DeviceInfo,
-        ErgotAddressClaimEndpoint, ErgotAddressRefreshEndpoint,
-        ErgotDeviceInfoInterrogationTopic, ErgotDeviceInfoTopic, ErgotPingEndpoint,
-        ErgotSeedRouterAssignmentEndpoint, ErgotSeedRouterRefreshEndpoint,
-        ErgotSeedRouterReleaseEndpoint,
-        ErgotSocketQueryResponseTopic, ErgotSocketQueryTopic, NameRequirement,
-        SeedRouterAssignment, SeedRouterRefreshRequest, SeedRouterReleaseRequest, SocketQuery,
-        SocketQueryResponse,
+        ErgotAddressClaimEndpoint, ErgotAddressRefreshEndpoint, ErgotDeviceInfoInterrogationTopic,
+        ErgotDeviceInfoTopic, ErgotPathMtuEndpoint, ErgotPingEndpoint, ErgotRetainedEndpoint,
+        ErgotRouterEndpoint, ErgotSchemaTxEndpoint, ErgotSeedRouterAssignmentEndpoint,
+        ErgotSeedRouterRefreshEndpoint, ErgotSeedRouterReleaseEndpoint,
+        ErgotSocketQueryResponseTopic, ErgotSocketQueryTopic, ErgotStatsEndpoint, NameRequirement,
+        PathMtuQuery, PathMtuResult, RetainedRequest, RouterQuery, SchemaResponseTx,
+        SeedRouterAssignment, SeedRouterAssignmentRequest, SeedRouterRefreshRequest,
+        SeedRouterReleaseRequest, SocketQuery, SocketQueryResponse, SocketSchemaTx, Stats,
+        StatsQuery,
     },
 };
+#[cfg(feature = "std")]
+use crate::{
+    Key,
+    net_stack::history::TopicHistory,
+    traits::Topic,
+    well_known::{ErgotTopicHistoryEndpoint, TopicHistoryRequest},
+};
 use core::{future::Future, pin::pin};
+use mutex::ScopedRawMutex;
+#[cfg(feature = "std")]
+use serde::Serialize;
 
 pub use crate::interface_manager::SeedLease;
 
@@ -35,6 +53,7 @@ impl<NS: NetStackHandle> Services<NS> {
     pub async fn ping_handler<const D: usize>(self) -> ! {
         let server = Endpoints {
             inner: self.inner.clone(),
+            prio: Priority::NORMAL,
         }
         .bounded_server::<ErgotPingEndpoint, D>(None);
         let server = pin!(server);
@@ -44,12 +63,37 @@ impl<NS: NetStackHandle> Services<NS> {
         }
     }
 
+    /// Automatically responds to path MTU queries via the [`ErgotPathMtuEndpoint`] endpoint
+    ///
+    /// Each interface a query is sent through lowers its `path_mtu` to the MTU
+    /// of that interface, so the response contains the smallest MTU on the
+    /// path from the requester to this device.
+    ///
+    /// The const parameter `D` controls the depth of the socket to buffer path MTU queries
+    pub async fn path_mtu_handler<const D: usize>(self) -> ! {
+        let server = Endpoints {
+            inner: self.inner.clone(),
+            prio: Priority::NORMAL,
+        }
+        .bounded_server::<ErgotPathMtuEndpoint, D>(None);
+        let server = pin!(server);
+        let mut server_hdl = server.attach();
+        loop {
+            _ = server_hdl
+                .serve_blocking(|query: &PathMtuQuery| PathMtuResult {
+                    path_mtu: query.path_mtu,
+                })
+                .await;
+        }
+    }
+
     /// Handler for device info requests
     ///
     /// The const parameter `D` controls the depth of the socket to buffer info requests
     pub async fn device_info_handler<const D: usize>(self, info: &DeviceInfo) -> ! {
         let topics = Topics {
             inner: self.inner.clone(),
+            prio: Priority::NORMAL,
         };
         let subber = topics
             .clone()
@@ -77,6 +121,7 @@ impl<NS: NetStackHandle> Services<NS> {
 
         let subber = Topics {
             inner: self.inner.clone(),
+            prio: Priority::NORMAL,
         }
         .heap_bounded_receiver::<ErgotFmtRxOwnedTopic>(depth, None);
 
@@ -104,6 +149,7 @@ impl<NS: NetStackHandle> Services<NS> {
 
         let subber = Topics {
             inner: self.inner.clone(),
+            prio: Priority::NORMAL,
         }
         .heap_bounded_receiver::<ErgotFmtRxOwnedTopic>(depth, None);
 
@@ -155,7 +201,10 @@ impl<NS: NetStackHandle> Services<NS> {
     /// Handler for accepting and responding to [`ErgotSocketQueryTopic`] messages
     pub async fn socket_query_handler<const D: usize>(self) {
         let nsh = self.inner.clone();
-        let topics = Topics { inner: self.inner };
+        let topics = Topics {
+            inner: self.inner,
+            prio: Priority::NORMAL,
+        };
         let subber = topics
             .clone()
             .bounded_receiver::<ErgotSocketQueryTopic, D>(None);
@@ -175,6 +224,230 @@ impl<NS: NetStackHandle> Services<NS> {
         }
     }
 
+    /// Handler for [`ErgotSchemaTxEndpoint`] requests, reporting the paths and
+    /// message types of the topic receivers and endpoint servers of this device
+    ///
+    /// Each response is serialized into `scratch`. A schema that doesn't fit
+    /// is reported as `None`, along with the rest of the socket information.
+    /// Requests for sockets whose information doesn't fit either, mostly
+    /// because of a long path, are answered with
+    /// [`ProtocolError::NsseMessageTooLarge`].
+    ///
+    /// The const parameter `D` controls the depth of the socket to buffer requests
+    pub async fn schema_handler<const D: usize>(self, scratch: &mut [u8]) -> ! {
+        let server = crate::socket::endpoint::stack_vec::Server::<
+            ErgotSchemaTxEndpoint<'static>,
+            NS,
+            D,
+        >::new(self.inner.clone(), None);
+        let server = pin!(server);
+        let mut server_hdl = server.attach();
+        loop {
+            let Ok(msg) = server_hdl.recv_manual().await else {
+                continue;
+            };
+            let src = Address {
+                port_id: server_hdl.port(),
+                ..msg.hdr.dst
+            };
+            let stack = self.inner.stack();
+            let Some(resp) = stack.with_sockets(|iter| schema_searcher(msg.t.index, iter)) else {
+                reply_err(&self.inner, &msg.hdr, src, ProtocolError::NsseWouldDeadlock);
+                continue;
+            };
+            let body = match postcard::to_slice(&resp, scratch) {
+                Ok(used) => &*used,
+                Err(_) => {
+                    let resp = resp.map(|entry| SocketSchemaTx { ty: None, ..entry });
+                    match postcard::to_slice(&resp, scratch) {
+                        Ok(used) => &*used,
+                        Err(_) => {
+                            reply_err(
+                                &self.inner,
+                                &msg.hdr,
+                                src,
+                                ProtocolError::NsseMessageTooLarge,
+                            );
+                            continue;
+                        }
+                    }
+                }
+            };
+            let hdr = Header {
+                src,
+                dst: msg.hdr.src,
+                any_all: None,
+                seq_no: Some(msg.hdr.seq_no),
+                kind: FrameKind::ENDPOINT_RESP,
+                ttl: DEFAULT_TTL,
+                prio: msg.hdr.prio,
+            };
+            _ = stack.send_serialized(&hdr, body);
+        }
+    }
+
+    /// Handler for [`ErgotStatsEndpoint`] requests, reporting the counters of
+    /// the interfaces and sockets of this device, see [`stats`](crate::stats)
+    ///
+    /// The const parameter `D` controls the depth of the socket to buffer requests
+    pub async fn stats_handler<const D: usize>(self) -> ! {
+        let server = Endpoints {
+            inner: self.inner.clone(),
+            prio: Priority::NORMAL,
+        }
+        .bounded_server::<ErgotStatsEndpoint, D>(None);
+        let server = pin!(server);
+        let mut server_hdl = server.attach();
+        loop {
+            _ = server_hdl
+                .serve_blocking(|query: &StatsQuery| {
+                    let stack = self.inner.stack();
+                    match *query {
+                        StatsQuery::Interface { index } => {
+                            stack.interface_stats(index.into()).map(Stats::Interface)
+                        }
+                        StatsQuery::Socket { index } => {
+                            stack.socket_stats(index.into()).map(Stats::Socket)
+                        }
+                    }
+                })
+                .await;
+        }
+    }
+
+    /// Handler for [`ErgotRouterEndpoint`] requests, reporting the interfaces
+    /// and lease tables of the router profile of this device
+    ///
+    /// Every request is answered with `None` if the profile isn't a router.
+    ///
+    /// The const parameter `D` controls the depth of the socket to buffer requests
+    pub async fn router_handler<const D: usize>(self) -> ! {
+        let server = Endpoints {
+            inner: self.inner.clone(),
+            prio: Priority::NORMAL,
+        }
+        .bounded_server::<ErgotRouterEndpoint, D>(None);
+        let server = pin!(server);
+        let mut server_hdl = server.attach();
+        loop {
+            _ = server_hdl
+                .serve_blocking(|query: &RouterQuery| {
+                    self.inner
+                        .stack()
+                        .manage_profile(|im| im.router_state(query))
+                })
+                .await;
+        }
+    }
+
+    /// Advertise the topics subscribed to on this device to its direct
+    /// neighbors every `interval`, see
+    /// [`subscriptions`](crate::interface_manager::subscriptions)
+    ///
+    /// Each advertisement is valid for three intervals, so a single lost
+    /// advertisement doesn't stop broadcasts from reaching this device.
+    #[cfg(any(
+        feature = "tokio-std",
+        feature = "embassy-time",
+        feature = "nostd-seed-router"
+    ))]
+    pub async fn subscription_advertiser(self, interval: core::time::Duration) -> ! {
+        let valid_secs = (interval.as_secs() * 3).clamp(1, u16::MAX.into()) as u16;
+        loop {
+            self.inner.stack().advertise_subscriptions(valid_secs);
+            super::timer_sleep(interval).await;
+        }
+    }
+
+    /// Handler for [`ErgotRetainedEndpoint`] requests, sending the messages
+    /// retained in `store` to the requesting devices, see
+    /// [`retained`](crate::net_stack::retained)
+    ///
+    /// The const parameter `D` controls the depth of the socket to buffer requests,
+    /// and `B` the size of the buffer retained messages are copied into before
+    /// they are sent. Larger messages are not sent.
+    pub async fn retained_handler<const D: usize, const B: usize>(
+        self,
+        store: &RetainedStore<impl ScopedRawMutex, impl RetainedStorage>,
+    ) -> ! {
+        let server = Endpoints {
+            inner: self.inner.clone(),
+            prio: Priority::NORMAL,
+        }
+        .bounded_server::<ErgotRetainedEndpoint, D>(None);
+        let server = pin!(server);
+        let mut server_hdl = server.attach();
+        let mut scratch = [0u8; B];
+        loop {
+            _ = server_hdl
+                .serve_full(async |msg: &HeaderMessage<RetainedRequest>| {
+                    let dst = Address {
+                        port_id: msg.t.port,
+                        ..msg.hdr.src
+                    };
+                    store
+                        .send_to(&self.inner, msg.t.key, dst, &mut scratch)
+                        .unwrap_or(false)
+                })
+                .await;
+        }
+    }
+
+    /// Record the last `depth` messages of `T` in `history`, see
+    /// [`history`](crate::net_stack::history)
+    ///
+    /// Messages are received with a borrowed receiver, buffering up to four
+    /// messages of `mtu` bytes.
+    #[cfg(feature = "std")]
+    pub async fn topic_history_recorder<T>(
+        self,
+        history: &TopicHistory<impl ScopedRawMutex>,
+        depth: usize,
+        mtu: u16,
+    ) -> !
+    where
+        T: Topic,
+        T::Message: Serialize + Sized,
+    {
+        let key = Key(T::TOPIC_KEY.to_bytes());
+        history.track(key, depth);
+        let topics = Topics {
+            inner: self.inner.clone(),
+            prio: Priority::NORMAL,
+        };
+        let subber = topics.heap_bounded_borrowed_receiver::<T>(4 * usize::from(mtu), None, mtu);
+        let subber = pin!(subber);
+        let mut sub = subber.subscribe();
+        loop {
+            let msg = sub.recv().await;
+            if let Some(body) = msg.raw_body() {
+                history.record(key, msg.hdr.src, body);
+            }
+        }
+    }
+
+    /// Handler for [`ErgotTopicHistoryEndpoint`] requests, querying `history`
+    ///
+    /// The const parameter `D` controls the depth of the socket to buffer requests
+    #[cfg(feature = "std")]
+    pub async fn topic_history_handler<const D: usize>(
+        self,
+        history: &TopicHistory<impl ScopedRawMutex>,
+    ) -> ! {
+        let server = Endpoints {
+            inner: self.inner.clone(),
+            prio: Priority::NORMAL,
+        }
+        .bounded_server::<ErgotTopicHistoryEndpoint, D>(None);
+        let server = pin!(server);
+        let mut server_hdl = server.attach();
+        loop {
+            _ = server_hdl
+                .serve_blocking(|req: &TopicHistoryRequest| history.lookup(req))
+                .await;
+        }
+    }
+
     /// Handler for accepting and responding to Seed Router assignment and refresh requests
     ///
     /// Should only be used by Profiles that are capable of acting as Seed Routers, otherwise
@@ -209,7 +482,10 @@ impl<NS: NetStackHandle> Services<NS> {
         F: Future<Output = ()>,
     {
         let nsh = self.inner.clone();
-        let endpoints = Endpoints { inner: self.inner };
+        let endpoints = Endpoints {
+            inner: self.inner,
+            prio: Priority::NORMAL,
+        };
 
         let refresh = endpoints
             .clone()
@@ -293,7 +569,10 @@ impl<NS: NetStackHandle> Services<NS> {
     /// Should only be used by Profiles that support bus-style address claims (Router with C > 0).
     pub async fn address_claim_handler<const D: usize>(self) {
         let nsh = self.inner.clone();
-        let endpoints = Endpoints { inner: self.inner };
+        let endpoints = Endpoints {
+            inner: self.inner,
+            prio: Priority::NORMAL,
+        };
 
         let refresh = endpoints
             .clone()
@@ -343,6 +622,7 @@ fn handle_address_claim<NS: NetStackHandle>(
             req.hdr.src.network_id,
             req.t.candidate_node_id,
             req.t.nonce,
+            req.t.credentials.as_ref(),
         )
     });
     let res = res.map(|assignment| AddressClaimGranted {
@@ -426,11 +706,14 @@ fn handle_assign<NS: NetStackHandle>(
     nsh: &NS,
     refresh_port: u8,
     release_port: u8,
-    assign_req: &HeaderMessage<()>,
+    assign_req: &HeaderMessage<SeedRouterAssignmentRequest>,
 ) {
-    let res = nsh
-        .stack()
-        .manage_profile(|p| p.request_seed_net_assign(assign_req.hdr.src.network_id));
+    let res = nsh.stack().manage_profile(|p| {
+        p.request_seed_net_assign(
+            assign_req.hdr.src.network_id,
+            assign_req.t.credentials.as_ref(),
+        )
+    });
     let res = res.map(|assignment| SeedRouterAssignment {
         assignment,
         refresh_port,
@@ -478,7 +761,8 @@ fn handle_refresh<NS: NetStackHandle>(
 }
 
 use crate::interface_manager::{
-    DelegatedRefreshPreparation, SeedAssignmentError, SeedRefreshError,
+    AddressClaimError, DelegatedRefreshPreparation, SeedAssignmentError, SeedRefreshError,
+    admission::AdmissionError,
 };
 
 #[cfg(any(feature = "tokio-std", feature = "nostd-seed-router"))]
@@ -515,7 +799,7 @@ async fn handle_assign_delegated<NS, T, F>(
     nsh: &NS,
     refresh_port: u8,
     release_port: u8,
-    assign_req: &HeaderMessage<()>,
+    assign_req: &HeaderMessage<SeedRouterAssignmentRequest>,
     upstream: <NS::Profile as crate::interface_manager::Profile>::InterfaceIdent,
     timeout: &T,
 ) where
@@ -528,8 +812,12 @@ async fn handle_assign_delegated<NS, T, F>(
         // unknown or the route table is full, registration would fail *after*
         // the lease was acquired, stranding it until expiry. The handler is a
         // single-task loop, so nothing fills the table during the await below.
-        nsh.stack()
-            .manage_profile(|p| p.can_delegate_seed(assign_req.hdr.src.network_id))?;
+        nsh.stack().manage_profile(|p| {
+            p.can_delegate_seed(
+                assign_req.hdr.src.network_id,
+                assign_req.t.credentials.as_ref(),
+            )
+        })?;
         let lease = delegated_seed_rpc_timeout(request_seed_lease(nsh, upstream), timeout())
             .await
             .map_err(|_| SeedAssignmentError::UpstreamUnavailable)?
@@ -710,13 +998,32 @@ pub async fn request_seed_lease<NS: NetStackHandle + Clone>(
         port_id: 0, // wildcard — find seed router by key
     };
 
-    let endpoints = Endpoints { inner: nsh.clone() };
-    let result = endpoints
-        .request::<ErgotSeedRouterAssignmentEndpoint>(upstream_addr, &(), None)
+    // Send our credentials, and answer at most one challenge
+    let mut challenge = None;
+    let assignment = loop {
+        let credentials = nsh
+            .stack()
+            .manage_profile(|im| im.admission_credentials(challenge));
+        let result = Endpoints {
+            inner: nsh.clone(),
+            prio: Priority::NORMAL,
+        }
+        .request::<ErgotSeedRouterAssignmentEndpoint>(
+            upstream_addr,
+            &SeedRouterAssignmentRequest { credentials },
+            None,
+        )
         .await
         .map_err(SeedClientError::RequestFailed)?;
-
-    let assignment = result.map_err(SeedClientError::AssignmentDenied)?;
+        match result {
+            Err(SeedAssignmentError::NotAdmitted(AdmissionError::ChallengeRequired(c)))
+                if challenge.is_none() =>
+            {
+                challenge = Some(c);
+            }
+            result => break result.map_err(SeedClientError::AssignmentDenied)?,
+        }
+    };
 
     let seed_net_id = assignment.assignment.net_id;
 
@@ -750,17 +1057,20 @@ pub async fn bridge_seed_refresh<NS: NetStackHandle + Clone>(
     nsh: &NS,
     lease: &SeedLease,
 ) -> Result<SeedLease, SeedClientError> {
-    let result = Endpoints { inner: nsh.clone() }
-        .request::<ErgotSeedRouterRefreshEndpoint>(
-            lease.refresh_addr,
-            &SeedRouterRefreshRequest {
-                refresh_net: lease.net_id,
-                refresh_token: lease.refresh_token,
-            },
-            None,
-        )
-        .await
-        .map_err(SeedClientError::RequestFailed)?;
+    let result = Endpoints {
+        inner: nsh.clone(),
+        prio: Priority::NORMAL,
+    }
+    .request::<ErgotSeedRouterRefreshEndpoint>(
+        lease.refresh_addr,
+        &SeedRouterRefreshRequest {
+            refresh_net: lease.net_id,
+            refresh_token: lease.refresh_token,
+        },
+        None,
+    )
+    .await
+    .map_err(SeedClientError::RequestFailed)?;
 
     let refreshed = result.map_err(SeedClientError::RefreshDenied)?;
 
@@ -780,18 +1090,21 @@ pub async fn release_seed_lease<NS: NetStackHandle + Clone>(
     nsh: &NS,
     lease: &SeedLease,
 ) -> Result<(), SeedClientError> {
-    Endpoints { inner: nsh.clone() }
-        .request::<ErgotSeedRouterReleaseEndpoint>(
-            lease.release_addr,
-            &SeedRouterReleaseRequest {
-                release_net: lease.net_id,
-                refresh_token: lease.refresh_token,
-            },
-            None,
-        )
-        .await
-        .map_err(SeedClientError::RequestFailed)?
-        .map_err(SeedClientError::RefreshDenied)
+    Endpoints {
+        inner: nsh.clone(),
+        prio: Priority::NORMAL,
+    }
+    .request::<ErgotSeedRouterReleaseEndpoint>(
+        lease.release_addr,
+        &SeedRouterReleaseRequest {
+            release_net: lease.net_id,
+            refresh_token: lease.refresh_token,
+        },
+        None,
+    )
+    .await
+    .map_err(SeedClientError::RequestFailed)?
+    .map_err(SeedClientError::RefreshDenied)
 }
 
 // ---------------------------------------------------------------------------
@@ -860,19 +1173,36 @@ pub async fn bus_claim<NS: NetStackHandle + Clone>(
         port_id: 0, // wildcard — find the claim endpoint by key
     };
 
-    let result = Endpoints { inner: nsh.clone() }
+    // Send our credentials, and answer at most one challenge
+    let mut challenge = None;
+    let granted = loop {
+        let credentials = nsh
+            .stack()
+            .manage_profile(|im| im.admission_credentials(challenge));
+        let result = Endpoints {
+            inner: nsh.clone(),
+            prio: Priority::NORMAL,
+        }
         .request::<ErgotAddressClaimEndpoint>(
             link_local,
             &AddressClaimRequest {
                 candidate_node_id,
                 nonce,
+                credentials,
             },
             None,
         )
         .await
         .map_err(ClaimClientError::RequestFailed)?;
-
-    let granted = result.map_err(ClaimClientError::ClaimDenied)?;
+        match result {
+            Err(AddressClaimError::NotAdmitted(AdmissionError::ChallengeRequired(c)))
+                if challenge.is_none() =>
+            {
+                challenge = Some(c);
+            }
+            result => break result.map_err(ClaimClientError::ClaimDenied)?,
+        }
+    };
     let assignment = granted.assignment;
 
     nsh.stack()
@@ -932,8 +1262,6 @@ where
     NS: NetStackHandle + Clone,
     <NS::Profile as crate::interface_manager::Profile>::InterfaceIdent: Clone,
 {
-    use crate::interface_manager::AddressClaimError;
-
     for candidate in candidates {
         match bus_claim(nsh, ident.clone(), candidate, nonce).await {
             Ok(lease) => return Ok(lease),
@@ -953,17 +1281,20 @@ pub async fn bus_claim_refresh<NS: NetStackHandle + Clone>(
     nsh: &NS,
     lease: &NodeClaimLease,
 ) -> Result<NodeClaimLease, ClaimClientError> {
-    let result = Endpoints { inner: nsh.clone() }
-        .request::<ErgotAddressRefreshEndpoint>(
-            lease.refresh_addr,
-            &AddressRefreshRequest {
-                node_id: lease.node_id,
-                refresh_token: lease.refresh_token,
-            },
-            None,
-        )
-        .await
-        .map_err(ClaimClientError::RequestFailed)?;
+    let result = Endpoints {
+        inner: nsh.clone(),
+        prio: Priority::NORMAL,
+    }
+    .request::<ErgotAddressRefreshEndpoint>(
+        lease.refresh_addr,
+        &AddressRefreshRequest {
+            node_id: lease.node_id,
+            refresh_token: lease.refresh_token,
+        },
+        None,
+    )
+    .await
+    .map_err(ClaimClientError::RequestFailed)?;
 
     let refreshed = result.map_err(ClaimClientError::RefreshDenied)?;
 
@@ -979,6 +1310,30 @@ pub async fn bus_claim_refresh<NS: NetStackHandle + Clone>(
 }
 
 /// Helper function for handling socket query requests
+/// Find the `index`-th message type of the sockets with a [`SocketSchema`]
+///
+/// [`SocketSchema`]: crate::socket::SocketSchema
+fn schema_searcher(index: u16, iter: SocketHeaderIter) -> SchemaResponseTx<'static> {
+    iter.filter_map(|hdr| Some((hdr, hdr.schema?)))
+        .flat_map(|(hdr, schema)| {
+            let rx = (hdr.attrs.kind, hdr.key, schema.rx);
+            let tx = schema
+                .tx
+                .map(|(key, ty)| (FrameKind::ENDPOINT_RESP, key, ty));
+            core::iter::once(rx)
+                .chain(tx)
+                .map(move |(kind, key, ty)| SocketSchemaTx {
+                    port: hdr.port,
+                    name: hdr.nash,
+                    kind,
+                    key,
+                    path: schema.path,
+                    ty: Some(ty),
+                })
+        })
+        .nth(usize::from(index))
+}
+
 fn query_searcher(query: SocketQuery, iter: SocketHeaderIter) -> Option<SocketQueryResponse> {
     let SocketQuery {
         key,
@@ -0,0 +1,46 @@
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    net_stack::NetStackHandle,
+    socket::session::{ClientSocket, Listener, ServerSocket},
+    traits::Endpoint,
+};
+
+/// A proxy type usable for creating [session](crate::socket::session) sockets
+#[derive(Clone)]
+pub struct Sessions<NS: NetStackHandle> {
+    pub(super) inner: NS,
+}
+
+impl<NS: NetStackHandle> Sessions<NS> {
+    /// Create a listener accepting sessions for `E`, with a backlog of `N`
+    /// pending sessions
+    pub fn listener<E, const N: usize>(self, name: Option<&str>) -> Listener<E, NS, N>
+    where
+        E: Endpoint,
+        E::Request: Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + 'static,
+    {
+        Listener::new(self.inner.stack(), name)
+    }
+
+    /// Create a socket for opening a session for `E`, with a window of `W` messages
+    pub fn client<E, const W: usize>(self) -> ClientSocket<E, NS, W>
+    where
+        E: Endpoint,
+        E::Request: Serialize + Clone + 'static,
+        E::Response: Clone + DeserializeOwned + 'static,
+    {
+        ClientSocket::new(self.inner.stack())
+    }
+
+    /// Create a socket for accepting a session for `E`, with a window of `W` messages
+    pub fn server<E, const W: usize>(self) -> ServerSocket<E, NS, W>
+    where
+        E: Endpoint,
+        E::Request: Clone + DeserializeOwned + 'static,
+        E::Response: Serialize + Clone + 'static,
+    {
+        ServerSocket::new(self.inner.stack())
+    }
+}
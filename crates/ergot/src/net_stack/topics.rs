@@ -1,19 +1,32 @@
+use mutex::ScopedRawMutex;
 use serde::{Serialize, de::DeserializeOwned};
 
 use crate::{
-    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, Key,
+    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, Key, Priority,
     nash::NameHash,
-    net_stack::{NetStackHandle, NetStackSendError},
+    net_stack::{
+        NetStackHandle, NetStackSendError, ReqRespError,
+        endpoints::Endpoints,
+        history::TopicHistoryQuery,
+        retained::{BroadcastRetainedError, RetainedStorage, RetainedStore},
+    },
     traits::Topic,
+    well_known::{ErgotRetainedEndpoint, RetainedRequest},
 };
 
 /// A proxy type usable for creating helper services
 #[derive(Clone)]
 pub struct Topics<NS: NetStackHandle> {
     pub(super) inner: NS,
+    pub(super) prio: Priority,
 }
 
 impl<NS: NetStackHandle> Topics<NS> {
+    /// Send messages with the given [`Priority`], instead of [`Priority::NORMAL`]
+    pub fn with_priority(self, prio: Priority) -> Self {
+        Self { prio, ..self }
+    }
+
     pub fn single_receiver<T>(
         self,
         name: Option<&str>,
@@ -87,6 +100,67 @@ impl<NS: NetStackHandle> Topics<NS> {
         crate::socket::topic::stack_bor::Receiver::new(self.inner, queue, mtu, name)
     }
 
+    /// Create a [`Publisher`] sending `T` to up to `N` remote subscribers,
+    /// using `rng` to generate refresh tokens
+    ///
+    /// See the [`publisher`](crate::net_stack::publisher) module docs.
+    ///
+    /// [`Publisher`]: crate::net_stack::publisher::Publisher
+    #[cfg(any(feature = "std", feature = "nostd-seed-router"))]
+    pub fn publisher<T, R, const N: usize>(
+        self,
+        rng: R,
+    ) -> crate::net_stack::publisher::Publisher<T, NS, R, N>
+    where
+        T: Topic,
+        T::Message: Serialize + Clone + DeserializeOwned + 'static,
+        NS::Mutex: mutex::ConstInit,
+        R: rand_core::RngCore,
+    {
+        crate::net_stack::publisher::Publisher::new(self.inner, rng).with_priority(self.prio)
+    }
+
+    /// Ask the [`Publisher`] of `T` on the device at `publisher` to send `T` to
+    /// the local `port`, usually that of a unicast receiver
+    ///
+    /// The port of `publisher` is ignored. The subscription expires unless it
+    /// is refreshed, see [`RemoteSubscription::keep_alive()`].
+    ///
+    /// [`Publisher`]: crate::net_stack::publisher::Publisher
+    /// [`RemoteSubscription::keep_alive()`]: crate::net_stack::publisher::RemoteSubscription::keep_alive
+    pub async fn remote_subscribe<T>(
+        self,
+        publisher: Address,
+        port: u8,
+    ) -> Result<
+        crate::net_stack::publisher::RemoteSubscription<T, NS>,
+        crate::net_stack::publisher::RemoteSubscriptionClientError,
+    >
+    where
+        T: Topic,
+        T::Message: Serialize + Clone + DeserializeOwned + 'static,
+    {
+        crate::net_stack::publisher::RemoteSubscription::subscribe(self.inner, publisher, port)
+            .await
+    }
+
+    /// Query the messages of `T` recorded in the [`TopicHistory`] of the
+    /// device at `device`
+    ///
+    /// The port of `device` is ignored, and the device must be running
+    /// [`Services::topic_history_handler()`]. See the
+    /// [`history`](crate::net_stack::history) module docs.
+    ///
+    /// [`TopicHistory`]: crate::net_stack::history::TopicHistory
+    /// [`Services::topic_history_handler()`]: crate::net_stack::services::Services::topic_history_handler
+    pub fn history<T>(self, device: Address) -> TopicHistoryQuery<T, NS>
+    where
+        T: Topic,
+        T::Message: DeserializeOwned,
+    {
+        TopicHistoryQuery::new(self.inner, device)
+    }
+
     /// Send a broadcast message for the topic `T`.
     ///
     /// This message will be sent to all matching local socket listeners, as well
@@ -134,12 +208,66 @@ impl<NS: NetStackHandle> Topics<NS> {
             seq_no: None,
             kind: FrameKind::TOPIC_MSG,
             ttl: DEFAULT_TTL,
+            prio: self.prio,
         };
         let stack = self.inner.stack();
         stack.send_ty(&hdr, msg)?;
         Ok(())
     }
 
+    /// Retain `msg` as the last message of the topic `T` in `store`, then
+    /// broadcast it like [`Self::broadcast`]
+    ///
+    /// See the [`retained`](crate::net_stack::retained) module docs.
+    pub fn broadcast_retained<T>(
+        self,
+        msg: &T::Message,
+        name: Option<&str>,
+        store: &RetainedStore<impl ScopedRawMutex, impl RetainedStorage>,
+    ) -> Result<(), BroadcastRetainedError>
+    where
+        T: Topic,
+        T::Message: Serialize + Clone + DeserializeOwned + 'static,
+    {
+        store
+            .retain::<T>(msg)
+            .map_err(BroadcastRetainedError::Retain)?;
+        self.broadcast::<T>(msg, name)
+            .map_err(BroadcastRetainedError::Send)
+    }
+
+    /// Ask the device at `device` to send the message it retained for the
+    /// topic `T` to the local `port`, usually that of a unicast receiver
+    ///
+    /// The port of `device` is ignored, and the device must be running
+    /// [`Services::retained_handler()`]. Returns whether a retained message
+    /// was sent.
+    ///
+    /// [`Services::retained_handler()`]: crate::net_stack::services::Services::retained_handler
+    pub async fn request_retained<T: Topic>(
+        self,
+        device: Address,
+        port: u8,
+    ) -> Result<bool, ReqRespError> {
+        let device = Address {
+            port_id: 0,
+            ..device
+        };
+        Endpoints {
+            inner: self.inner,
+            prio: self.prio,
+        }
+        .request::<ErgotRetainedEndpoint>(
+            device,
+            &RetainedRequest {
+                key: Key(T::TOPIC_KEY.to_bytes()),
+                port,
+            },
+            None,
+        )
+        .await
+    }
+
     /// Like [`Self::broadcast`], but with a TTL of zero so that messages are
     /// never forwarded off of the local machine.
     pub fn broadcast_local<T>(
@@ -169,6 +297,7 @@ impl<NS: NetStackHandle> Topics<NS> {
             seq_no: None,
             kind: FrameKind::TOPIC_MSG,
             ttl: 0,
+            prio: self.prio,
         };
         let stack = self.inner.stack();
         stack.send_ty_local(&hdr, msg)?;
@@ -197,6 +326,7 @@ impl<NS: NetStackHandle> Topics<NS> {
             seq_no: None,
             kind: FrameKind::TOPIC_MSG,
             ttl: DEFAULT_TTL,
+            prio: self.prio,
         };
         let stack = self.inner.stack();
         stack.send_ty(&hdr, msg)?;
@@ -237,6 +367,7 @@ impl<NS: NetStackHandle> Topics<NS> {
             seq_no: None,
             kind: FrameKind::TOPIC_MSG,
             ttl: DEFAULT_TTL,
+            prio: self.prio,
         };
         let stack = self.inner.stack();
         stack.send_bor(&hdr, msg)?;
@@ -272,9 +403,89 @@ impl<NS: NetStackHandle> Topics<NS> {
             seq_no: None,
             kind: FrameKind::TOPIC_MSG,
             ttl: DEFAULT_TTL,
+            prio: self.prio,
         };
         let stack = self.inner.stack();
         stack.send_bor(&hdr, msg)?;
         Ok(())
     }
+
+    /// Send a broadcast message for the topic `T`, signed with `signer`.
+    ///
+    /// The same as [`Self::broadcast`], but receivers on other devices can
+    /// verify that the message was sent by a trusted signer. `scratch` is used
+    /// to serialize and sign the message, see
+    /// [`NetStack::send_ty_signed()`](crate::NetStack::send_ty_signed).
+    #[cfg(feature = "signing")]
+    pub fn broadcast_signed<T>(
+        self,
+        msg: &T::Message,
+        name: Option<&str>,
+        signer: &crate::signing::Signer,
+        scratch: &mut [u8],
+    ) -> Result<(), NetStackSendError>
+    where
+        T: Topic,
+        T::Message: Serialize + Clone + DeserializeOwned + 'static,
+    {
+        let key = Key(T::TOPIC_KEY.to_bytes());
+        let hdr = Header {
+            src: Address {
+                network_id: 0,
+                node_id: 0,
+                port_id: 0,
+            },
+            dst: Address {
+                network_id: 0,
+                node_id: 0,
+                port_id: 255,
+            },
+            any_all: Some(AnyAllAppendix {
+                key,
+                nash: name.map(NameHash::new),
+            }),
+            seq_no: None,
+            kind: FrameKind::TOPIC_MSG,
+            ttl: DEFAULT_TTL,
+            prio: self.prio,
+        };
+        let stack = self.inner.stack();
+        stack.send_ty_signed(&hdr, &key, msg, signer, scratch)?;
+        Ok(())
+    }
+
+    /// Send a unicast message for the topic `T`, signed with `signer`.
+    ///
+    /// The same as [`Self::unicast`], but the receiver can verify that the
+    /// message was sent by a trusted signer, see [`Self::broadcast_signed`].
+    #[cfg(feature = "signing")]
+    pub fn unicast_signed<T>(
+        self,
+        dest: Address,
+        msg: &T::Message,
+        signer: &crate::signing::Signer,
+        scratch: &mut [u8],
+    ) -> Result<(), NetStackSendError>
+    where
+        T: Topic,
+        T::Message: Serialize + Clone + DeserializeOwned + 'static,
+    {
+        let key = Key(T::TOPIC_KEY.to_bytes());
+        let hdr = Header {
+            src: Address {
+                network_id: 0,
+                node_id: 0,
+                port_id: 0,
+            },
+            dst: dest,
+            any_all: (dest.port_id == 0).then_some(AnyAllAppendix { key, nash: None }),
+            seq_no: None,
+            kind: FrameKind::TOPIC_MSG,
+            ttl: DEFAULT_TTL,
+            prio: self.prio,
+        };
+        let stack = self.inner.stack();
+        stack.send_ty_signed(&hdr, &key, msg, signer, scratch)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,76 @@
+//! Replay protection, shared by link encryption and signed messages
+//!
+//! Senders number their messages with a counter that starts at zero, and
+//! increments with every message. The receiver remembers which of the most
+//! recent counters it received, and rejects the ones it has seen before.
+
+/// The number of most recent counters the receiver remembers
+///
+/// Messages reordered by more than this are rejected as replays.
+pub const REPLAY_WINDOW: u64 = 64;
+
+/// The counters already received from a sender
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct ReplayWindow {
+    /// One more than the highest counter received, zero if none were
+    next: u64,
+    /// Bit `i` is set if counter `next - 1 - i` was received
+    seen: u64,
+}
+
+impl ReplayWindow {
+    /// Has `counter` not been received yet, and is it still in the window?
+    pub(crate) fn check(&self, counter: u64) -> bool {
+        if counter == u64::MAX {
+            // Never sent
+            false
+        } else if counter >= self.next {
+            true
+        } else {
+            let age = self.next - 1 - counter;
+            age < REPLAY_WINDOW && self.seen & (1 << age) == 0
+        }
+    }
+
+    /// Mark `counter` as received, it must have passed [`check`](Self::check)
+    pub(crate) fn accept(&mut self, counter: u64) {
+        if counter >= self.next {
+            let shift = counter - self.next + 1;
+            self.seen = if shift >= REPLAY_WINDOW {
+                0
+            } else {
+                self.seen << shift
+            };
+            self.seen |= 1;
+            self.next = counter + 1;
+        } else {
+            self.seen |= 1 << (self.next - 1 - counter);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{REPLAY_WINDOW, ReplayWindow};
+
+    #[test]
+    fn replay_window() {
+        let mut window = ReplayWindow::default();
+        for ctr in [0, 1, 5, 3] {
+            assert!(window.check(ctr));
+            window.accept(ctr);
+            assert!(!window.check(ctr));
+        }
+        // Not yet received, in the window
+        assert!(window.check(2));
+        assert!(window.check(4));
+
+        // Moving far ahead forgets everything older than the window
+        window.accept(5 + REPLAY_WINDOW);
+        assert!(!window.check(5));
+        assert!(!window.check(4));
+        assert!(window.check(6));
+        assert!(!window.check(5 + REPLAY_WINDOW));
+        assert!(!window.check(u64::MAX));
+    }
+}
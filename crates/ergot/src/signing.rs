@@ -0,0 +1,833 @@
+//! Signed Messages
+//!
+//! Link encryption, in `interface_manager::aead`, only protects a single hop: a
+//! [`Router`](crate::interface_manager::profiles::router::Router) forwarding
+//! a message can read and modify it. For messages that must be trusted end to
+//! end, such as topics broadcast across many hops, or requests to endpoints
+//! that control a device, the sender may instead sign the message, and the
+//! receiving socket verify it before delivery.
+//!
+//! Signing is opt-in, and requires the `signing` feature:
+//!
+//! * Messages are signed by sending them with one of the `_signed` methods,
+//!   such as [`Topics::broadcast_signed()`] or
+//!   [`Endpoints::request_signed()`], using a [`Signer`] that holds a
+//!   [`SigningKey`].
+//! * Sockets are configured with a [`TrustStore`] of the signers they accept,
+//!   using `with_trust_store()`, for example on a topic
+//!   [`Receiver`](crate::socket::topic::stack_vec::Receiver) or an endpoint
+//!   [`Server`](crate::socket::endpoint::stack_vec::Server). Messages received
+//!   from other devices that are unsigned, signed by an unknown signer, or
+//!   modified, are rejected with [`SocketSendError::Unverified`], which is
+//!   reported to the sender as [`ProtocolError::SseUnverified`].
+//!
+//! Messages sent by the local device are never serialized, and are always
+//! delivered to local sockets. Sockets without a trust store accept all
+//! messages, including signed ones.
+//!
+//! Signatures are HMAC-SHA256 tags, so every holder of a [`SigningKey`] can
+//! both sign and verify messages. Responses to signed requests are signed
+//! too, see [Responses](self#responses).
+//!
+//! ## Wire format
+//!
+//! The signature is appended to the serialized body of the message, so it is
+//! ignored by receivers that don't verify it:
+//!
+//! ```text
+//! body,                   postcard:       n bytes
+//! signer: u32,            little endian:  4 bytes
+//! session: u32,           little endian:  4 bytes
+//! counter: u64,           little endian:  8 bytes
+//! tag,                    HMAC-SHA256:   32 bytes
+//! ```
+//!
+//! The tag covers the frame kind, the [`Key`] of the topic or endpoint, the
+//! source node_id and port, the destination port, the session, the counter,
+//! and the body. The network_ids of the addresses are rewritten while the
+//! message is routed, and are not covered, nor is the destination node_id,
+//! which is rewritten for broadcasts. The source node_id is the one the
+//! sender's interfaces fill in, see
+//! [`Profile::own_node_id()`](crate::interface_manager::Profile::own_node_id).
+//! A bridge [`Router`](crate::interface_manager::profiles::router::Router)
+//! has another node_id on its upstream, so its signed messages can only be
+//! verified by devices below it.
+//!
+//! ## Replays
+//!
+//! Each [`Signer`] is created with a session number, which must be larger
+//! than the session of every earlier [`Signer`] of the same key, e.g. a boot
+//! counter kept in non-volatile memory. It numbers the messages it signs
+//! with a counter, starting at zero. Every socket with a trust store keeps
+//! the current session of up to [`TRACKED_SIGNERS`] signers, and a window of
+//! the last [`REPLAY_WINDOW`] counters of that session. It rejects counters
+//! it has seen before, or that are older than the window. A message
+//! delivered to several sockets, such as a broadcast, is accepted once by
+//! each of them.
+//!
+//! The first authentic message of a later session replaces the signer's
+//! current session, and starts a new window. Messages of earlier sessions are
+//! rejected, so recorded messages can't displace the current session. Only
+//! signers that were evicted to make room for others, or that the socket
+//! hasn't heard from since it was created, can have their recorded messages
+//! replayed, and only until the socket receives a message of their current
+//! session.
+//!
+//! ## Responses
+//!
+//! An endpoint server with a trust store signs its response to a signed
+//! request with the key that signed the request, so that the client can tell
+//! it apart from a response forged on the way. Instead of a session and
+//! counter of its own, the response carries those of the request, and its
+//! tag covers them, the [`FrameKind::ENDPOINT_RESP`] kind, the [`Key`] of the
+//! endpoint's requests, the source node_id and port, the destination port,
+//! and the body. A signed response is only accepted by the client of the
+//! request it answers, so it can't be replayed either.
+//!
+//! [`Endpoints::request_signed()`] only accepts signed responses. A server
+//! without a trust store has no key to sign its responses with, and a server
+//! only remembers the last [`PENDING_RESPONSES`] signed requests it didn't
+//! answer yet. The responses of either are rejected by the client, which
+//! waits until the request times out.
+//!
+//! [`Topics::broadcast_signed()`]: crate::net_stack::topics::Topics::broadcast_signed
+//! [`Endpoints::request_signed()`]: crate::net_stack::endpoints::Endpoints::request_signed
+//! [`SocketSendError::Unverified`]: crate::socket::SocketSendError::Unverified
+//! [`ProtocolError::SseUnverified`]: crate::ProtocolError::SseUnverified
+
+use core::sync::atomic::Ordering;
+
+use heapless::Vec;
+use hmac::{Hmac, Mac};
+use portable_atomic::AtomicU64;
+use postcard::ser_flavors::Flavor;
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::{Address, FrameKind, Key, replay::ReplayWindow};
+
+pub use crate::replay::REPLAY_WINDOW;
+
+/// The size of the signer id at the end of a signed body
+pub const SIGNER_SIZE: usize = 4;
+
+/// The size of the session id after the signer id of a signed body
+pub const SESSION_SIZE: usize = 4;
+
+/// The size of the counter after the session id of a signed body
+pub const COUNTER_SIZE: usize = 8;
+
+/// The size of the HMAC-SHA256 tag at the end of a signed body
+pub const TAG_SIZE: usize = 32;
+
+/// The number of bytes added to the body of a message by signing it
+pub const SIGNATURE_SIZE: usize = SIGNER_SIZE + SESSION_SIZE + COUNTER_SIZE + TAG_SIZE;
+
+/// The number of signers whose counters a socket remembers
+///
+/// When a socket receives a message from one more signer, it forgets the
+/// signer it heard from least recently.
+pub const TRACKED_SIGNERS: usize = 4;
+
+/// The number of signed requests an endpoint server remembers, to sign their
+/// responses
+///
+/// When a server receives one more signed request before answering the
+/// others, it forgets the oldest one, and sends its response unsigned.
+pub const PENDING_RESPONSES: usize = 4;
+
+/// A key used to sign and verify messages
+///
+/// Each key has an `id`, which is sent with every signature, so that the
+/// receiver can find the key in its [`TrustStore`].
+#[derive(Clone)]
+pub struct SigningKey {
+    id: u32,
+    key: [u8; 32],
+}
+
+impl SigningKey {
+    /// Create a key with the given id
+    pub const fn new(id: u32, key: [u8; 32]) -> Self {
+        Self { id, key }
+    }
+
+    /// The id of this key
+    pub const fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn mac(&self, fields: &SignedFields, stamp: &Stamp, body: &[u8]) -> Hmac<Sha256> {
+        let mut mac = self.mac_header(fields, stamp);
+        mac.update(body);
+        mac
+    }
+
+    /// The MAC of everything but the body, which is added last
+    fn mac_header(&self, fields: &SignedFields, stamp: &Stamp) -> Hmac<Sha256> {
+        // Infallible: HMAC accepts keys of any length
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.key).unwrap();
+        mac.update(&[fields.kind.0]);
+        mac.update(&fields.key.0);
+        mac.update(&[fields.src_node, fields.src_port, fields.dst_port]);
+        mac.update(&stamp.session.to_le_bytes());
+        mac.update(&stamp.counter.to_le_bytes());
+        mac
+    }
+}
+
+/// A postcard flavor passing the serialized body to a MAC, instead of storing
+/// it
+struct MacFlavor(Hmac<Sha256>);
+
+impl Flavor for MacFlavor {
+    type Output = Hmac<Sha256>;
+
+    fn try_push(&mut self, data: u8) -> postcard::Result<()> {
+        self.0.update(&[data]);
+        Ok(())
+    }
+
+    fn try_extend(&mut self, data: &[u8]) -> postcard::Result<()> {
+        self.0.update(data);
+        Ok(())
+    }
+
+    fn finalize(self) -> postcard::Result<Self::Output> {
+        Ok(self.0)
+    }
+}
+
+/// The signature of a message with `stamp`, and the tag `mac`
+fn encode(stamp: &Stamp, mac: Hmac<Sha256>) -> [u8; SIGNATURE_SIZE] {
+    let mut sig = [0u8; SIGNATURE_SIZE];
+    let (head, rest) = sig.split_at_mut(SIGNER_SIZE);
+    head.copy_from_slice(&stamp.signer.to_le_bytes());
+    let (head, rest) = rest.split_at_mut(SESSION_SIZE);
+    head.copy_from_slice(&stamp.session.to_le_bytes());
+    let (head, rest) = rest.split_at_mut(COUNTER_SIZE);
+    head.copy_from_slice(&stamp.counter.to_le_bytes());
+    rest.copy_from_slice(&mac.finalize().into_bytes());
+    sig
+}
+
+/// Split `data` into the body, the stamp and the tag of its signature
+fn decode(data: &[u8]) -> Result<(&[u8], Stamp, &[u8; TAG_SIZE]), VerifyError> {
+    let (body, tag) = data
+        .split_last_chunk::<TAG_SIZE>()
+        .ok_or(VerifyError::Unsigned)?;
+    let (body, counter) = body
+        .split_last_chunk::<COUNTER_SIZE>()
+        .ok_or(VerifyError::Unsigned)?;
+    let (body, session) = body
+        .split_last_chunk::<SESSION_SIZE>()
+        .ok_or(VerifyError::Unsigned)?;
+    let (body, signer) = body
+        .split_last_chunk::<SIGNER_SIZE>()
+        .ok_or(VerifyError::Unsigned)?;
+    let stamp = Stamp {
+        signer: u32::from_le_bytes(*signer),
+        session: u32::from_le_bytes(*session),
+        counter: u64::from_le_bytes(*counter),
+    };
+    Ok((body, stamp, tag))
+}
+
+impl core::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Signs messages with a [`SigningKey`], see [Replays](self#replays)
+///
+/// A signer is usually created once at startup, e.g. in a `static`, and used
+/// for every signed message the device sends.
+pub struct Signer {
+    key: SigningKey,
+    session: u32,
+    counter: AtomicU64,
+}
+
+impl Signer {
+    /// Start session `session` of `key`
+    ///
+    /// `session` must be larger than the session of every earlier signer of
+    /// `key`, otherwise its messages are rejected as replays, see
+    /// [Replays](self#replays).
+    pub const fn new(key: SigningKey, session: u32) -> Self {
+        Self {
+            key,
+            session,
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    /// The id of the key of this signer
+    pub const fn id(&self) -> u32 {
+        self.key.id
+    }
+
+    /// Calculate the signature of a message, using the next counter
+    pub fn sign(&self, fields: &SignedFields, body: &[u8]) -> [u8; SIGNATURE_SIZE] {
+        self.sign_stamped(fields, body).0
+    }
+
+    fn sign_stamped(&self, fields: &SignedFields, body: &[u8]) -> ([u8; SIGNATURE_SIZE], Stamp) {
+        let stamp = Stamp {
+            signer: self.key.id,
+            session: self.session,
+            counter: self.counter.fetch_add(1, Ordering::Relaxed),
+        };
+        (encode(&stamp, self.key.mac(fields, &stamp, body)), stamp)
+    }
+}
+
+impl core::fmt::Debug for Signer {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Signer")
+            .field("id", &self.key.id)
+            .field("session", &self.session)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The parts of the header of a message that are covered by its signature
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignedFields {
+    pub kind: FrameKind,
+    /// The [`Key`] of the topic or endpoint
+    pub key: Key,
+    /// The node_id of the sender, as seen by the receiver
+    pub src_node: u8,
+    pub src_port: u8,
+    pub dst_port: u8,
+}
+
+/// The signer, session and counter of a verified message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stamp {
+    pub signer: u32,
+    pub session: u32,
+    pub counter: u64,
+}
+
+/// The signers a socket accepts messages from
+pub trait TrustStore: Sync + core::fmt::Debug {
+    /// Find the key with the given id, if it is trusted
+    fn signing_key(&self, id: u32) -> Option<&SigningKey>;
+}
+
+impl TrustStore for [SigningKey] {
+    fn signing_key(&self, id: u32) -> Option<&SigningKey> {
+        self.iter().find(|k| k.id == id)
+    }
+}
+
+impl<const N: usize> TrustStore for [SigningKey; N] {
+    fn signing_key(&self, id: u32) -> Option<&SigningKey> {
+        self.as_slice().signing_key(id)
+    }
+}
+
+/// The reason a signed message was rejected
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The message is too short to carry a signature
+    Unsigned,
+    /// The message was signed with a key that is not in the [`TrustStore`]
+    UnknownSigner(u32),
+    /// The signature does not match the message
+    BadSignature,
+    /// The message was already received, or is too old to tell
+    Replayed,
+}
+
+/// Verify the signature at the end of `data`, and return the body before it
+///
+/// This does not check for replays, see [`ReplayGuard`].
+pub fn verify<'a>(
+    store: &dyn TrustStore,
+    fields: &SignedFields,
+    data: &'a [u8],
+) -> Result<(&'a [u8], Stamp), VerifyError> {
+    let (body, stamp, tag) = decode(data)?;
+    let signing_key = store
+        .signing_key(stamp.signer)
+        .ok_or(VerifyError::UnknownSigner(stamp.signer))?;
+
+    signing_key
+        .mac(fields, &stamp, body)
+        .verify_slice(tag)
+        .map_err(|_| VerifyError::BadSignature)?;
+    Ok((body, stamp))
+}
+
+/// The session and counters received from one signer
+#[derive(Debug)]
+struct Tracked {
+    signer: u32,
+    session: u32,
+    window: ReplayWindow,
+}
+
+impl Tracked {
+    fn accept(&mut self, stamp: &Stamp) -> Result<(), VerifyError> {
+        // Sessions only ever increase, an earlier one is a recording
+        if stamp.session < self.session {
+            return Err(VerifyError::Replayed);
+        }
+        if stamp.session > self.session {
+            self.session = stamp.session;
+            self.window = ReplayWindow::default();
+        }
+        if !self.window.check(stamp.counter) {
+            return Err(VerifyError::Replayed);
+        }
+        self.window.accept(stamp.counter);
+        Ok(())
+    }
+}
+
+/// The signed messages a socket already received, see
+/// [Replays](self#replays)
+#[derive(Debug, Default)]
+pub struct ReplayGuard {
+    /// Ordered from least to most recently heard from
+    tracked: Vec<Tracked, TRACKED_SIGNERS>,
+}
+
+impl ReplayGuard {
+    /// A guard that has not received any messages yet
+    pub const fn new() -> Self {
+        Self {
+            tracked: Vec::new(),
+        }
+    }
+
+    /// Accept a verified message with `stamp`, unless it was already
+    /// received
+    pub fn accept(&mut self, stamp: &Stamp) -> Result<(), VerifyError> {
+        let pos = self.tracked.iter().position(|t| t.signer == stamp.signer);
+        let mut tracked = match pos {
+            Some(pos) => self.tracked.remove(pos),
+            None => {
+                if self.tracked.is_full() {
+                    self.tracked.remove(0);
+                }
+                Tracked {
+                    signer: stamp.signer,
+                    session: stamp.session,
+                    window: ReplayWindow::default(),
+                }
+            }
+        };
+        let res = tracked.accept(stamp);
+        // Infallible: an entry was removed above if there was no room
+        let _ = self.tracked.push(tracked);
+        res
+    }
+}
+
+/// A signed request an endpoint server received
+#[derive(Debug)]
+struct Pending {
+    from: Address,
+    seq_no: u16,
+    stamp: Stamp,
+}
+
+/// A signed request an endpoint client sent
+#[derive(Debug)]
+struct Sent {
+    key: SigningKey,
+    /// The [`Key`] of the endpoint's requests
+    endpoint: Key,
+    stamp: Stamp,
+}
+
+/// The signed requests of an endpoint socket, whose responses are signed,
+/// see [Responses](self#responses)
+#[derive(Debug, Default)]
+pub(crate) struct Exchanges {
+    /// On a server, the signed requests that weren't answered yet, ordered
+    /// from oldest to newest
+    pending: Vec<Pending, PENDING_RESPONSES>,
+    /// On a client, the signed request it waits for the response to
+    sent: Option<Sent>,
+}
+
+impl Exchanges {
+    pub(crate) const fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+            sent: None,
+        }
+    }
+
+    /// Remember a verified request with `stamp` a server received
+    pub(crate) fn received(&mut self, from: Address, seq_no: u16, stamp: Stamp) {
+        if self.pending.is_full() {
+            self.pending.remove(0);
+        }
+        // Infallible: an entry was removed above if there was no room
+        let _ = self.pending.push(Pending {
+            from,
+            seq_no,
+            stamp,
+        });
+    }
+
+    /// Sign a server's response to the request `to` sent with `seq_no`, if it
+    /// was signed by a key in `store`
+    ///
+    /// `fields` are those of the response, with the [`Key`] of the request.
+    pub(crate) fn sign_response<T: Serialize>(
+        &mut self,
+        store: &dyn TrustStore,
+        to: &Address,
+        seq_no: u16,
+        fields: &SignedFields,
+        body: &T,
+    ) -> Option<[u8; SIGNATURE_SIZE]> {
+        let pos = self
+            .pending
+            .iter()
+            .position(|p| p.from == *to && p.seq_no == seq_no)?;
+        let Pending { stamp, .. } = self.pending.remove(pos);
+        let key = store.signing_key(stamp.signer)?;
+        let mac = postcard::serialize_with_flavor(body, MacFlavor(key.mac_header(fields, &stamp)))
+            .ok()?;
+        Some(encode(&stamp, mac))
+    }
+
+    /// Remember the request with `stamp` a client sent to `endpoint`, signed
+    /// by `signer`
+    pub(crate) fn sent(&mut self, signer: &Signer, endpoint: Key, stamp: Stamp) {
+        self.sent = Some(Sent {
+            key: signer.key.clone(),
+            endpoint,
+            stamp,
+        });
+    }
+
+    /// Verify a response received by a client, if it sent a signed request,
+    /// and return the body before the signature
+    pub(crate) fn verify_response<'a>(
+        &self,
+        fields: &SignedFields,
+        data: &'a [u8],
+    ) -> Option<Result<&'a [u8], VerifyError>> {
+        let sent = self.sent.as_ref()?;
+        let res = decode(data).and_then(|(body, stamp, tag)| {
+            // A response to another request
+            if stamp != sent.stamp {
+                return Err(VerifyError::Replayed);
+            }
+            let fields = SignedFields {
+                key: sent.endpoint,
+                ..*fields
+            };
+            sent.key
+                .mac(&fields, &stamp, body)
+                .verify_slice(tag)
+                .map_err(|_| VerifyError::BadSignature)?;
+            Ok(body)
+        });
+        Some(res)
+    }
+}
+
+/// Serialize `body`, followed by its signature, into `scratch`
+///
+/// Returns `None` if `scratch` is too small.
+pub(crate) fn sign_to_slice<'a, T: Serialize>(
+    signer: &Signer,
+    fields: &SignedFields,
+    body: &T,
+    scratch: &'a mut [u8],
+) -> Option<(&'a [u8], Stamp)> {
+    let body_len = postcard::to_slice(body, scratch).ok()?.len();
+    let end = body_len + SIGNATURE_SIZE;
+    // Only use up a counter if the signature fits
+    if scratch.len() < end {
+        return None;
+    }
+    let (sig, stamp) = signer.sign_stamped(fields, &scratch[..body_len]);
+    scratch[body_len..end].copy_from_slice(&sig);
+    Some((&scratch[..end], stamp))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Exchanges, ReplayGuard, SIGNATURE_SIZE, SignedFields, Signer, SigningKey, Stamp,
+        TRACKED_SIGNERS, VerifyError, sign_to_slice, verify,
+    };
+    use crate::{Address, FrameKind, Key};
+
+    const FIELDS: SignedFields = SignedFields {
+        kind: FrameKind::TOPIC_MSG,
+        key: Key(*b"TOPIC123"),
+        src_node: 2,
+        src_port: 0,
+        dst_port: 255,
+    };
+
+    fn signer(id: u32) -> Signer {
+        Signer::new(SigningKey::new(id, [id as u8; 32]), 1)
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let alice = signer(1);
+        let mallory = signer(2);
+        let trusted = [SigningKey::new(1, [1; 32])];
+
+        let mut buf = [0u8; 64];
+        let signed = sign_to_slice(&alice, &FIELDS, &1234u32, &mut buf)
+            .unwrap()
+            .0
+            .to_vec();
+        assert_eq!(signed.len(), 2 + SIGNATURE_SIZE);
+        let (body, stamp) = verify(&trusted, &FIELDS, &signed).unwrap();
+        assert_eq!(body, &[0xD2, 0x09][..]);
+        assert_eq!(stamp.signer, 1);
+        assert_eq!(stamp.counter, 0);
+        // Receivers that don't verify still see the body
+        assert_eq!(postcard::from_bytes::<u32>(&signed), Ok(1234));
+
+        // Any change to the covered fields
+        for fields in [
+            SignedFields {
+                kind: FrameKind::ENDPOINT_REQ,
+                ..FIELDS
+            },
+            SignedFields {
+                key: Key(*b"TOPIC124"),
+                ..FIELDS
+            },
+            SignedFields {
+                src_node: 3,
+                ..FIELDS
+            },
+            SignedFields {
+                src_port: 1,
+                ..FIELDS
+            },
+            SignedFields {
+                dst_port: 1,
+                ..FIELDS
+            },
+        ] {
+            assert_eq!(
+                verify(&trusted, &fields, &signed),
+                Err(VerifyError::BadSignature)
+            );
+        }
+
+        // A modified body, session, or counter
+        for idx in [0, 6, 10] {
+            let mut modified = signed.clone();
+            modified[idx] ^= 1;
+            assert_eq!(
+                verify(&trusted, &FIELDS, &modified),
+                Err(VerifyError::BadSignature)
+            );
+        }
+
+        // An untrusted signer, even if it claims to be a trusted one
+        let mut buf = [0u8; 64];
+        let forged = sign_to_slice(&mallory, &FIELDS, &1234u32, &mut buf)
+            .unwrap()
+            .0
+            .to_vec();
+        assert_eq!(
+            verify(&trusted, &FIELDS, &forged),
+            Err(VerifyError::UnknownSigner(2))
+        );
+        let mut forged = forged;
+        forged[2..6].copy_from_slice(&1u32.to_le_bytes());
+        assert_eq!(
+            verify(&trusted, &FIELDS, &forged),
+            Err(VerifyError::BadSignature)
+        );
+
+        // Unsigned
+        assert_eq!(
+            verify(&trusted, &FIELDS, &[0xD2, 0x09]),
+            Err(VerifyError::Unsigned)
+        );
+    }
+
+    #[test]
+    fn counters_increment() {
+        let alice = signer(1);
+        let trusted = [SigningKey::new(1, [1; 32])];
+        for ctr in 0..3 {
+            let mut buf = [0u8; 64];
+            let signed = sign_to_slice(&alice, &FIELDS, &1234u32, &mut buf)
+                .unwrap()
+                .0;
+            assert_eq!(verify(&trusted, &FIELDS, signed).unwrap().1.counter, ctr);
+        }
+    }
+
+    #[test]
+    fn replays() {
+        let stamp = |signer, session, counter| Stamp {
+            signer,
+            session,
+            counter,
+        };
+        let mut guard = ReplayGuard::new();
+        assert_eq!(guard.accept(&stamp(1, 10, 0)), Ok(()));
+        assert_eq!(guard.accept(&stamp(1, 10, 0)), Err(VerifyError::Replayed));
+        assert_eq!(guard.accept(&stamp(1, 10, 1)), Ok(()));
+
+        // Each signer has its own counters
+        assert_eq!(guard.accept(&stamp(2, 10, 0)), Ok(()));
+        assert_eq!(guard.accept(&stamp(2, 10, 0)), Err(VerifyError::Replayed));
+
+        // A later session starts over, and earlier ones are rejected
+        assert_eq!(guard.accept(&stamp(1, 11, 0)), Ok(()));
+        assert_eq!(guard.accept(&stamp(1, 11, 0)), Err(VerifyError::Replayed));
+        assert_eq!(guard.accept(&stamp(1, 10, 2)), Err(VerifyError::Replayed));
+        assert_eq!(guard.accept(&stamp(1, 9, 0)), Err(VerifyError::Replayed));
+        assert_eq!(guard.accept(&stamp(1, 11, 1)), Ok(()));
+
+        // Signers heard from least recently are forgotten, and can be replayed
+        for signer in 3..(3 + TRACKED_SIGNERS as u32 - 1) {
+            assert_eq!(guard.accept(&stamp(signer, 10, 0)), Ok(()));
+        }
+        assert_eq!(guard.accept(&stamp(2, 10, 0)), Ok(()));
+        assert_eq!(guard.accept(&stamp(1, 11, 1)), Ok(()));
+    }
+
+    #[test]
+    fn earlier_sessions_are_replays() {
+        let trusted = [SigningKey::new(1, [1; 32])];
+        let earlier = Signer::new(SigningKey::new(1, [1; 32]), 7);
+        let later = Signer::new(SigningKey::new(1, [1; 32]), 8);
+        let sign = |signer: &Signer| {
+            let mut buf = [0u8; 64];
+            let signed = sign_to_slice(signer, &FIELDS, &1234u32, &mut buf)
+                .unwrap()
+                .0;
+            verify(&trusted, &FIELDS, signed).unwrap().1
+        };
+        // Recorded before the signer restarted
+        let recorded = [sign(&earlier), sign(&earlier)];
+        let current = [sign(&later), sign(&later)];
+
+        let mut guard = ReplayGuard::new();
+        assert_eq!(guard.accept(&current[0]), Ok(()));
+        // Replaying the earlier session doesn't displace the current one
+        for stamp in &recorded {
+            assert_eq!(guard.accept(stamp), Err(VerifyError::Replayed));
+        }
+        assert_eq!(guard.accept(&current[1]), Ok(()));
+
+        // A socket that only heard of the earlier session moves on to the
+        // later one, and doesn't go back
+        let mut guard = ReplayGuard::new();
+        assert_eq!(guard.accept(&recorded[0]), Ok(()));
+        assert_eq!(guard.accept(&current[0]), Ok(()));
+        assert_eq!(guard.accept(&recorded[1]), Err(VerifyError::Replayed));
+        assert_eq!(guard.accept(&current[1]), Ok(()));
+    }
+
+    #[test]
+    fn signed_responses() {
+        const REQ: SignedFields = SignedFields {
+            kind: FrameKind::ENDPOINT_REQ,
+            key: Key(*b"REQUEST1"),
+            src_node: 1,
+            src_port: 4,
+            dst_port: 0,
+        };
+        const RESP: SignedFields = SignedFields {
+            kind: FrameKind::ENDPOINT_RESP,
+            key: REQ.key,
+            src_node: 2,
+            src_port: 9,
+            dst_port: 4,
+        };
+        let client = Address {
+            network_id: 1,
+            node_id: 1,
+            port_id: 4,
+        };
+        let alice = signer(1);
+        let trusted = [SigningKey::new(1, [1; 32])];
+
+        // The client sends two requests, only waiting for the second
+        let mut sent = Exchanges::new();
+        let mut buf = [0u8; 64];
+        let (_, first) = sign_to_slice(&alice, &REQ, &1u32, &mut buf).unwrap();
+        let (_, second) = sign_to_slice(&alice, &REQ, &2u32, &mut buf).unwrap();
+        sent.sent(&alice, REQ.key, second);
+
+        // The server received both
+        let mut received = Exchanges::new();
+        received.received(client, 10, first);
+        received.received(client, 11, second);
+        let mut respond = |seq_no, fields: &SignedFields| {
+            let sig = received.sign_response(&trusted, &client, seq_no, fields, &1234u32)?;
+            Some([&[0xD2, 0x09][..], &sig].concat())
+        };
+        // Only the requests received are answered, once
+        assert!(respond(12, &RESP).is_none());
+        let to_first = respond(10, &RESP).unwrap();
+        assert!(respond(10, &RESP).is_none());
+        let to_second = respond(11, &RESP).unwrap();
+        assert_eq!(to_second.len(), 2 + SIGNATURE_SIZE);
+
+        assert_eq!(
+            sent.verify_response(&RESP, &to_second),
+            Some(Ok(&[0xD2, 0x09][..]))
+        );
+        // The response to another request, forged, or unsigned
+        assert_eq!(
+            sent.verify_response(&RESP, &to_first),
+            Some(Err(VerifyError::Replayed))
+        );
+        let mut forged = to_second.clone();
+        forged[0] ^= 1;
+        assert_eq!(
+            sent.verify_response(&RESP, &forged),
+            Some(Err(VerifyError::BadSignature))
+        );
+        let from_elsewhere = SignedFields {
+            src_port: 10,
+            ..RESP
+        };
+        assert_eq!(
+            sent.verify_response(&from_elsewhere, &to_second),
+            Some(Err(VerifyError::BadSignature))
+        );
+        assert_eq!(
+            sent.verify_response(&RESP, &[0xD2, 0x09]),
+            Some(Err(VerifyError::Unsigned))
+        );
+        // Without a signed request, the socket's trust store decides
+        assert_eq!(received.verify_response(&RESP, &to_second), None);
+    }
+
+    #[test]
+    fn too_small_scratch() {
+        let alice = signer(1);
+        let mut buf = [0u8; SIGNATURE_SIZE + 1];
+        assert!(sign_to_slice(&alice, &FIELDS, &1234u32, &mut buf).is_none());
+        // No counter was used up
+        let mut buf = [0u8; 64];
+        let signed = sign_to_slice(&alice, &FIELDS, &1234u32, &mut buf)
+            .unwrap()
+            .0;
+        let trusted = [SigningKey::new(1, [1; 32])];
+        assert_eq!(verify(&trusted, &FIELDS, signed).unwrap().1.counter, 0);
+    }
+}
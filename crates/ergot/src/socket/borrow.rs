@@ -38,8 +38,10 @@ use crate::{
     nash::NameHash,
     net_stack::NetStackHandle,
     socket::{
-        Attributes, BorSerFn, HeaderMessage, Response, SocketHeader, SocketSendError, SocketVTable,
+        Attributes, BorSerFn, HeaderMessage, Response, SocketHeader, SocketSchema, SocketSendError,
+        SocketVTable,
     },
+    stats::SocketCounters,
     wire_frames::{self, BorrowedFrame, MAX_HDR_ENCODED_SIZE, de_frame, encode_frame_hdr},
 };
 
@@ -127,6 +129,14 @@ where
                 } else {
                     None
                 },
+                #[cfg(feature = "signing")]
+                trust: None,
+                #[cfg(feature = "signing")]
+                replay: UnsafeCell::new(crate::signing::ReplayGuard::new()),
+                #[cfg(feature = "signing")]
+                exchanges: UnsafeCell::new(crate::signing::Exchanges::new()),
+                schema: None,
+                counters: SocketCounters::new(),
             },
             inner: UnsafeCell::new(QueueBox {
                 q: sto,
@@ -138,6 +148,21 @@ where
         }
     }
 
+    /// Only accept messages from other devices that are signed by a signer in
+    /// `store`, see [`crate::signing`]
+    #[cfg(feature = "signing")]
+    pub fn with_trust_store(mut self, store: &'static dyn crate::signing::TrustStore) -> Self {
+        self.hdr.trust = Some(store);
+        self
+    }
+
+    /// Report `schema` as the message types of this socket, see
+    /// [`SocketSchema`]
+    pub(crate) fn with_schema(mut self, schema: &'static SocketSchema) -> Self {
+        self.hdr.schema = Some(schema);
+        self
+    }
+
     pub fn attach<'a>(self: Pin<&'a mut Self>) -> SocketHdl<'a, Q, T, N> {
         let stack = self.net.clone();
         let ptr_self: NonNull<Self> = NonNull::from(unsafe { self.get_unchecked_mut() });
@@ -475,6 +500,15 @@ impl<Q: BbqHandle, T> ResponseGrant<Q, T> {
             }),
         })
     }
+
+    /// The serialized message, without deserializing it, or `None` if an
+    /// error was received instead
+    pub fn raw_body(&self) -> Option<&[u8]> {
+        match &self.inner {
+            ResponseGrantInner::Ok { grant, offset, .. } => grant.get(*offset..),
+            ResponseGrantInner::Err(_) => None,
+        }
+    }
 }
 
 impl<Q: BbqHandle, T> Drop for ResponseGrant<Q, T> {
@@ -1,7 +1,8 @@
 //! Endpoint Client and Server Sockets
 //!
 //! TODO: Explanation of storage choices and examples using `single`.
-use crate::traits::Endpoint;
+use crate::socket::stream_endpoint::StreamSink;
+use crate::traits::{Endpoint, StreamEndpoint};
 use core::pin::Pin;
 use pin_project::pin_project;
 use serde::{Serialize, de::DeserializeOwned};
@@ -40,6 +41,15 @@ macro_rules! endpoint_server {
             E::Request: Serialize + Clone + DeserializeOwned + 'static,
             NS: crate::net_stack::NetStackHandle,
         {
+            /// Only accept requests from other devices that are signed by a
+            /// signer in `store`, see [`crate::signing`]
+            #[cfg(feature = "signing")]
+            pub fn with_trust_store(self, store: &'static dyn crate::signing::TrustStore) -> Self {
+                Self {
+                    sock: self.sock.with_trust_store(store),
+                }
+            }
+
             /// Attach the Server to a Netstack and receive a Handle
             pub fn attach<'a>(self: Pin<&'a mut Self>) -> ServerHandle<'a, E, NS, $($arr)?> {
                 let this = self.project();
@@ -97,6 +107,18 @@ macro_rules! endpoint_server {
             {
                 self.hdl.serve_blocking(f).await
             }
+
+            /// Wait for an incoming packet, and stream responses using the given async closure
+            ///
+            /// The end of the stream is sent once the closure returns.
+            pub async fn serve_stream<F>(&mut self, f: F) -> Result<(), base::net_stack::NetStackSendError>
+            where
+                E: StreamEndpoint,
+                E::Item: Serialize + Clone + DeserializeOwned + 'static,
+                F: AsyncFnOnce(&E::Request, &mut StreamSink<E, NS>) -> Result<(), base::net_stack::NetStackSendError>,
+            {
+                self.hdl.serve_stream(f).await
+            }
         }
     };
 }
@@ -166,9 +188,10 @@ pub mod raw {
         FrameKind,
         net_stack::NetStackHandle,
         socket::{
-            Attributes,
+            Attributes, SocketSchema,
             raw_owned::{self, Storage},
         },
+        traits::Schema,
     };
 
     #[pin_project]
@@ -233,10 +256,31 @@ pub mod raw {
                     },
                     sto,
                     name,
+                )
+                .with_schema(
+                    const {
+                        &SocketSchema {
+                            path: E::PATH,
+                            rx: <E::Request as Schema>::SCHEMA,
+                            tx: Some((
+                                base::Key(E::RESP_KEY.to_bytes()),
+                                <E::Response as Schema>::SCHEMA,
+                            )),
+                        }
+                    },
                 ),
             }
         }
 
+        /// Only accept requests from other devices that are signed by a signer
+        /// in `store`, see [`crate::signing`]
+        #[cfg(feature = "signing")]
+        pub fn with_trust_store(self, store: &'static dyn crate::signing::TrustStore) -> Self {
+            Self {
+                sock: self.sock.with_trust_store(store),
+            }
+        }
+
         pub fn attach<'a>(self: Pin<&'a mut Self>) -> ServerHandle<'a, S, E, NS> {
             let this = self.project();
             let hdl: raw_owned::SocketHdl<'_, S, E::Request, NS> = this.sock.attach();
@@ -291,6 +335,7 @@ pub mod raw {
                 seq_no: Some(hdr.seq_no),
                 kind: base::FrameKind::ENDPOINT_RESP,
                 ttl: base::DEFAULT_TTL,
+                prio: hdr.prio,
             };
             self.hdl.stack().send_ty::<E::Response>(&hdr, &resp)
         }
@@ -327,6 +372,7 @@ pub mod raw {
                 seq_no: Some(hdr.seq_no),
                 kind: base::FrameKind::ENDPOINT_RESP,
                 ttl: base::DEFAULT_TTL,
+                prio: hdr.prio,
             };
             self.hdl.stack().send_ty::<E::Response>(&hdr, &resp)
         }
@@ -351,16 +397,49 @@ pub mod raw {
 
             // NOTE: We swap src/dst, AND we go from req -> resp (both in kind and key)
             let hdr: base::Header = base::Header {
-                src: hdr.dst,
+                src: {
+                    // modify the port to match our specific port, in case the dst was port 0
+                    // or 255
+                    let mut src = hdr.dst;
+                    src.port_id = self.port();
+                    src
+                },
                 dst: hdr.src,
                 // TODO: we never reply to an any/all, so don't include that info
                 any_all: None,
                 seq_no: Some(hdr.seq_no),
                 kind: base::FrameKind::ENDPOINT_RESP,
                 ttl: base::DEFAULT_TTL,
+                prio: hdr.prio,
             };
             self.hdl.stack().send_ty::<E::Response>(&hdr, &resp)
         }
+
+        pub async fn serve_stream<F>(
+            &mut self,
+            f: F,
+        ) -> Result<(), base::net_stack::NetStackSendError>
+        where
+            E: StreamEndpoint,
+            E::Item: Serialize + Clone + DeserializeOwned + 'static,
+            F: AsyncFnOnce(
+                &E::Request,
+                &mut StreamSink<E, NS>,
+            ) -> Result<(), base::net_stack::NetStackSendError>,
+        {
+            let msg = loop {
+                let res = self.hdl.recv().await;
+                match res {
+                    Ok(req) => break req,
+                    // TODO: Anything with errs? If not, change vtable
+                    Err(_) => continue,
+                }
+            };
+            let base::socket::HeaderMessage { hdr, t } = msg;
+            let mut sink = StreamSink::new(self.hdl.stack(), &hdr, self.port());
+            f(&t, &mut sink).await?;
+            sink.finish().await
+        }
     }
 
     impl<S, E, NS> Client<S, E, NS>
@@ -406,6 +485,10 @@ pub mod raw {
         pub async fn recv(&mut self) -> Response<E::Response> {
             self.hdl.recv().await
         }
+
+        pub fn stack(&self) -> NS::Target {
+            self.hdl.stack()
+        }
     }
 }
 
@@ -58,15 +58,29 @@ use core::{
     ptr::{self, NonNull},
 };
 
-use crate::{FrameKind, HeaderSeq, Key, ProtocolError, nash::NameHash, wire_frames};
+use crate::{
+    FrameKind, HeaderSeq, Key, ProtocolError,
+    nash::NameHash,
+    stats::{SocketCounters, SocketStats},
+    wire_frames,
+};
 use cordyceps::{Linked, list::Links};
 use postcard::ser_flavors;
+use postcard_schema::schema::NamedType;
 use serde::Serialize;
 
 pub mod borrow;
 pub mod endpoint;
+pub mod multiplex;
 pub mod owned;
 pub mod raw_owned;
+#[cfg(any(
+    feature = "tokio-std",
+    feature = "embassy-time",
+    feature = "nostd-seed-router"
+))]
+pub mod session;
+pub mod stream_endpoint;
 pub mod topic;
 
 #[derive(Debug)]
@@ -85,8 +99,45 @@ pub struct SocketHeader {
     pub nash: Option<NameHash>,               // 4 bytes
     pub attrs: Attributes,                    // 2 bytes
     pub port: u8,                             // 1 byte
-                                              // ====================
-                                              // 39 bytes / 63 bytes
+    // ====================
+    // 39 bytes / 63 bytes
+    /// The signers accepted by this socket, see [`crate::signing`]
+    #[cfg(feature = "signing")]
+    pub(crate) trust: Option<&'static dyn crate::signing::TrustStore>,
+    /// The signed messages already received by this socket, only accessed
+    /// by the net stack, with its lock held
+    #[cfg(feature = "signing")]
+    pub(crate) replay: core::cell::UnsafeCell<crate::signing::ReplayGuard>,
+    /// The signed requests of an endpoint socket, whose responses are
+    /// signed, only accessed by the net stack, with its lock held
+    #[cfg(feature = "signing")]
+    pub(crate) exchanges: core::cell::UnsafeCell<crate::signing::Exchanges>,
+    /// The message types of this socket, if it is a topic receiver or an
+    /// endpoint server
+    pub(crate) schema: Option<&'static SocketSchema>,
+    /// The messages delivered to this socket, or dropped, see [`crate::stats`]
+    pub(crate) counters: SocketCounters,
+}
+
+impl SocketHeader {
+    /// The current value of the counters of this socket, see [`crate::stats`]
+    pub fn stats(&self) -> SocketStats {
+        self.counters.snapshot(self)
+    }
+}
+
+/// The path and message types of a topic receiver or endpoint server socket
+///
+/// Captured when the socket is created, and reported by
+/// `Services::schema_handler()`.
+#[derive(Debug)]
+pub struct SocketSchema {
+    /// The path of the topic or endpoint
+    pub path: &'static str,
+    /// The type of the messages received by the socket
+    pub rx: &'static NamedType,
+    /// For endpoint servers, the key and type of the responses sent
+    pub tx: Option<(Key, &'static NamedType)>,
 }
 
 #[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
@@ -97,6 +148,8 @@ pub enum SocketSendError {
     DeserFailed,
     TypeMismatch,
     WhatTheHell,
+    /// The message was not signed by a signer the socket trusts
+    Unverified,
 }
 
 #[derive(Debug, Clone)]
@@ -214,6 +267,7 @@ impl SocketSendError {
             SocketSendError::DeserFailed => ProtocolError::SseDeserFailed,
             SocketSendError::TypeMismatch => ProtocolError::SseTypeMismatch,
             SocketSendError::WhatTheHell => ProtocolError::SseWhatTheHell,
+            SocketSendError::Unverified => ProtocolError::SseUnverified,
         }
     }
 }
@@ -0,0 +1,273 @@
+//! Multiplexing Endpoint Client Sockets
+//!
+//! [`Endpoints::request`] opens a new response socket, and therefore uses a new
+//! port, for every request. A host sending many concurrent requests can run out
+//! of ports this way. A multiplexing [`Client`] instead owns a single response
+//! socket, and matches each response to its request by the header `seq_no`.
+//!
+//! Each request is sent with a `seq_no` taken from the local [`NetStack`],
+//! which servers copy into their response. Any number of requests may be in
+//! flight at once, as long as at most `N` responses are waiting to be picked
+//! up. Responses that match no outstanding request, such as late responses to
+//! a request that timed out, or duplicates, are dropped and counted by
+//! [`ClientHandle::stray_responses`].
+//!
+//! Responses are only received while at least one request is being awaited.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use core::pin::pin;
+//! # use mutex::raw_impls::cs::CriticalSectionRawMutex as CSRMutex;
+//! # use ergot::{Address, NetStack, interface_manager::profiles::null::Null};
+//! ergot::endpoint!(Double, u32, u32, "example/double");
+//!
+//! static STACK: NetStack<CSRMutex, Null> = NetStack::new();
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     # tokio::task::spawn(async {
+//!     #     let srv = STACK.endpoints().bounded_server::<Double, 4>(None);
+//!     #     let srv = pin!(srv);
+//!     #     let mut hdl = srv.attach();
+//!     #     loop {
+//!     #         hdl.serve_blocking(|x| x * 2).await.unwrap();
+//!     #     }
+//!     # });
+//!     # tokio::time::sleep(core::time::Duration::from_millis(50)).await;
+//!     // (not shown: starting a `Double` service...)
+//!     let client = STACK.endpoints().multiplex_client::<Double, 4>();
+//!     let client = pin!(client);
+//!     let hdl = client.attach();
+//!
+//!     // Both requests are in flight at the same time, using one port
+//!     let (a, b) = tokio::join!(
+//!         hdl.request(Address::unknown(), &1, None),
+//!         hdl.request(Address::unknown(), &2, None),
+//!     );
+//!     assert_eq!(a, Ok(2));
+//!     assert_eq!(b, Ok(4));
+//! }
+//! ```
+//!
+//! [`Endpoints::request`]: crate::net_stack::endpoints::Endpoints::request
+//! [`NetStack`]: crate::NetStack
+
+use core::pin::{Pin, pin};
+
+use embassy_futures::select::{Either, select};
+use maitake_sync::{Mutex, WaitMap, wait_map::WakeOutcome};
+use pin_project::pin_project;
+use portable_atomic::{AtomicU32, Ordering};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, Key, Priority,
+    nash::NameHash,
+    net_stack::{NetStackHandle, ReqRespError},
+    socket::{HeaderMessage, Response, endpoint::raw, owned::stack_vec::Bounded},
+    traits::Endpoint,
+};
+
+type RawHandle<'a, E, NS, const N: usize> =
+    raw::ClientHandle<'a, Bounded<Response<<E as Endpoint>::Response>, N>, E, NS>;
+
+/// A multiplexing endpoint Client socket, queueing up to `N` responses
+#[pin_project]
+pub struct Client<E, NS, const N: usize>
+where
+    E: Endpoint,
+    E::Response: Serialize + Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    #[pin]
+    sock: raw::Client<Bounded<Response<E::Response>, N>, E, NS>,
+    prio: Priority,
+}
+
+/// A multiplexing endpoint Client handle
+///
+/// Requests only need a shared reference, so many requests may be awaited
+/// concurrently.
+pub struct ClientHandle<'a, E, NS, const N: usize>
+where
+    E: Endpoint,
+    E::Response: Serialize + Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    // Whoever holds the socket handle dispatches responses to all waiters
+    hdl: Mutex<RawHandle<'a, E, NS, N>>,
+    stack: NS::Target,
+    port: u8,
+    prio: Priority,
+    waiters: WaitMap<u16, Response<E::Response>>,
+    stray: AtomicU32,
+}
+
+impl<E, NS, const N: usize> Client<E, NS, N>
+where
+    E: Endpoint,
+    E::Response: Serialize + Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    pub fn new(net: NS) -> Self {
+        Self {
+            sock: raw::Client::new(net, Bounded::new(), None),
+            prio: Priority::NORMAL,
+        }
+    }
+
+    /// Send requests with the given [`Priority`], instead of [`Priority::NORMAL`]
+    pub fn with_priority(self, prio: Priority) -> Self {
+        Self { prio, ..self }
+    }
+
+    /// Attach the Client socket to the net stack, and receive a Handle
+    pub fn attach<'a>(self: Pin<&'a mut Self>) -> ClientHandle<'a, E, NS, N> {
+        let this = self.project();
+        let hdl = this.sock.attach();
+        ClientHandle {
+            stack: hdl.stack(),
+            port: hdl.port(),
+            prio: *this.prio,
+            hdl: Mutex::new(hdl),
+            waiters: WaitMap::new(),
+            stray: AtomicU32::new(0),
+        }
+    }
+}
+
+impl<E, NS, const N: usize> ClientHandle<'_, E, NS, N>
+where
+    E: Endpoint,
+    E::Request: Serialize + Clone + DeserializeOwned + 'static,
+    E::Response: Serialize + Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    /// The port of this Client socket
+    pub fn port(&self) -> u8 {
+        self.port
+    }
+
+    /// The number of responses that were dropped because they matched no
+    /// outstanding request
+    pub fn stray_responses(&self) -> u32 {
+        self.stray.load(Ordering::Relaxed)
+    }
+
+    /// Perform an [`Endpoint`] Request, and await Response.
+    pub async fn request(
+        &self,
+        dst: Address,
+        req: &E::Request,
+        name: Option<&str>,
+    ) -> Result<E::Response, ReqRespError> {
+        let resp = self
+            .request_full_with_timeout(dst, req, name, core::future::pending())
+            .await?;
+        Ok(resp.t)
+    }
+
+    /// Same as [`Self::request`], but also returns the full message with header,
+    /// and gives up with [`ReqRespError::Timeout`] once the `timeout` future
+    /// completes.
+    pub async fn request_full_with_timeout<F: Future<Output = ()>>(
+        &self,
+        dst: Address,
+        req: &E::Request,
+        name: Option<&str>,
+        timeout: F,
+    ) -> Result<HeaderMessage<E::Response>, ReqRespError> {
+        // If the destination is wildcard, include the any_all appendix to the
+        // header
+        let any_all = match dst.port_id {
+            0 => Some(AnyAllAppendix {
+                key: Key(E::REQ_KEY.to_bytes()),
+                nash: name.map(NameHash::new),
+            }),
+            255 => {
+                return Err(ReqRespError::NoBroadcast);
+            }
+            _ => None,
+        };
+
+        // Register interest BEFORE sending, so the response can't be missed
+        let seq_no = self.stack.next_seq_no();
+        let mut wait = pin!(self.waiters.wait(seq_no));
+        if wait.as_mut().subscribe().await.is_err() {
+            // Only possible with all 65536 sequence numbers in flight
+            return Err(ReqRespError::Timeout);
+        }
+
+        let hdr = Header {
+            src: Address {
+                network_id: 0,
+                node_id: 0,
+                port_id: self.port,
+            },
+            dst,
+            any_all,
+            seq_no: Some(seq_no),
+            kind: FrameKind::ENDPOINT_REQ,
+            ttl: DEFAULT_TTL,
+            prio: self.prio,
+        };
+        self.stack.send_ty(&hdr, req).map_err(ReqRespError::Local)?;
+
+        let recv = async {
+            match select(wait, self.dispatch()).await {
+                Either::First(resp) => resp,
+                Either::Second(never) => match never {},
+            }
+        };
+        match select(recv, timeout).await {
+            Either::First(Ok(Ok(msg))) => Ok(msg),
+            Either::First(Ok(Err(e))) => Err(ReqRespError::Remote(e.t)),
+            // The WaitMap is never closed
+            Either::First(Err(_)) => Err(ReqRespError::Timeout),
+            Either::Second(()) => Err(ReqRespError::Timeout),
+        }
+    }
+
+    /// Perform an [`Endpoint`] Request, giving up with [`ReqRespError::Timeout`] if
+    /// no response is received within `deadline`.
+    #[cfg(any(
+        feature = "tokio-std",
+        feature = "embassy-time",
+        feature = "nostd-seed-router"
+    ))]
+    pub async fn request_with_deadline(
+        &self,
+        dst: Address,
+        req: &E::Request,
+        name: Option<&str>,
+        deadline: core::time::Duration,
+    ) -> Result<E::Response, ReqRespError> {
+        let resp = self
+            .request_full_with_timeout(dst, req, name, crate::net_stack::timer_sleep(deadline))
+            .await?;
+        Ok(resp.t)
+    }
+
+    /// Receive responses, and hand them to the matching waiting request
+    ///
+    /// Only one waiting request receives at a time. When it completes, the
+    /// next one takes over.
+    async fn dispatch(&self) -> core::convert::Infallible {
+        let mut hdl = self.hdl.lock().await;
+        loop {
+            let resp = hdl.recv().await;
+            let seq_no = match &resp {
+                Ok(msg) => msg.hdr.seq_no,
+                Err(err) => err.hdr.seq_no,
+            };
+            match self.waiters.wake(&seq_no, resp) {
+                WakeOutcome::Woke => {}
+                WakeOutcome::NoMatch(_) | WakeOutcome::Closed(_) => {
+                    crate::logging::debug!("Dropping stray response {}", seq_no);
+                    self.stray.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+}
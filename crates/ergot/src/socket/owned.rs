@@ -91,6 +91,10 @@ macro_rules! wrapper {
                 self.hdl.stack()
             }
 
+            pub fn try_recv(&mut self) -> Option<$crate::socket::Response<T>> {
+                self.hdl.try_recv()
+            }
+
             pub fn recv<'b>(&'b mut self) -> Recv<'b, 'a, T, NS, $($arr)?> {
                 Recv {
                     recv: self.hdl.recv(),
@@ -20,9 +20,13 @@ use core::{
 use cordyceps::list::Links;
 use serde::de::DeserializeOwned;
 
-use super::{Attributes, HeaderMessage, Response, SocketHeader, SocketSendError, SocketVTable};
+use super::{
+    Attributes, HeaderMessage, Response, SocketHeader, SocketSchema, SocketSendError, SocketVTable,
+};
 use crate::logging::trace;
-use crate::{HeaderSeq, Key, ProtocolError, nash::NameHash, net_stack::NetStackHandle};
+use crate::{
+    HeaderSeq, Key, ProtocolError, nash::NameHash, net_stack::NetStackHandle, stats::SocketCounters,
+};
 
 #[derive(Debug, PartialEq)]
 pub struct StorageFull;
@@ -184,12 +188,35 @@ where
                 } else {
                     None
                 },
+                #[cfg(feature = "signing")]
+                trust: None,
+                #[cfg(feature = "signing")]
+                replay: UnsafeCell::new(crate::signing::ReplayGuard::new()),
+                #[cfg(feature = "signing")]
+                exchanges: UnsafeCell::new(crate::signing::Exchanges::new()),
+                schema: None,
+                counters: SocketCounters::new(),
             }),
             inner: UnsafeCell::new(StoreBox::new(sto)),
             net,
         }
     }
 
+    /// Only accept messages from other devices that are signed by a signer in
+    /// `store`, see [`crate::signing`]
+    #[cfg(feature = "signing")]
+    pub fn with_trust_store(mut self, store: &'static dyn crate::signing::TrustStore) -> Self {
+        self.hdr.get_mut().trust = Some(store);
+        self
+    }
+
+    /// Report `schema` as the message types of this socket, see
+    /// [`SocketSchema`]
+    pub(crate) fn with_schema(mut self, schema: &'static SocketSchema) -> Self {
+        self.hdr.get_mut().schema = Some(schema);
+        self
+    }
+
     pub fn attach(self: Pin<&mut Self>) -> SocketHdl<'_, S, T, N> {
         let stack = self.net.clone();
         let sp: SocketPtr<'_, S, T, N> = self.into();
@@ -341,9 +368,14 @@ where
             let this_ref: &Socket<S, T, N> = unsafe { self.ptr.as_ptr().as_ref() };
             let box_ref: &mut StoreBox<S, Response<T>> = unsafe { &mut *this_ref.inner.get() };
 
-            box_ref.sto.try_pop()
+            let was_full = box_ref.sto.is_full();
+            box_ref.sto.try_pop().map(|resp| (resp, was_full))
         };
-        unsafe { net.with_lock(f) }
+        let (resp, was_full) = unsafe { net.with_lock(f) }?;
+        if was_full {
+            net.wake_space_waiters();
+        }
+        Some(resp)
     }
 
     pub fn recv<'b>(&'b mut self) -> Recv<'b, 'a, S, T, N> {
@@ -411,8 +443,9 @@ where
             let this_ref: &Socket<S, T, N> = unsafe { self.hdl.ptr.as_ptr().as_ref() };
             let box_ref: &mut StoreBox<S, Response<T>> = unsafe { &mut *this_ref.inner.get() };
 
+            let was_full = box_ref.sto.is_full();
             if let Some(resp) = box_ref.sto.try_pop() {
-                return Some(resp);
+                return Some((resp, was_full));
             }
 
             let new_wake = cx.waker();
@@ -427,7 +460,10 @@ where
             None
         };
         let res = unsafe { net.with_lock(f) };
-        if let Some(t) = res {
+        if let Some((t, was_full)) = res {
+            if was_full {
+                net.wake_space_waiters();
+            }
             Poll::Ready(t)
         } else {
             Poll::Pending
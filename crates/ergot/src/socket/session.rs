@@ -0,0 +1,839 @@
+//! Session Sockets
+//!
+//! Session sockets provide a connection-oriented, reliable, in-order stream of
+//! typed messages between two sockets, built on top of Ergot's at-most-once
+//! delivery.
+//!
+//! A session is described by an [`Endpoint`]: the client sends `E::Request`
+//! messages and receives `E::Response` messages, and the server does the
+//! opposite. Unlike endpoint requests, any number of messages may be sent in
+//! either direction for as long as the session is open.
+//!
+//! ## Protocol
+//!
+//! All segments are sent with [`FrameKind::SESSION`], and are encoded as a
+//! [`Segment`].
+//!
+//! * The client sends a [`Segment::Syn`] to a [`Listener`], carrying its initial
+//!   sequence number in the header `seq_no`. The server answers from a new,
+//!   dedicated socket with a [`Segment::SynAck`], which the client confirms with
+//!   a [`Segment::Ack`].
+//! * Each [`Segment::Data`] carries its sequence number in the header `seq_no`.
+//!   The receiver answers with cumulative [`Segment::Ack`]s, which also advertise
+//!   how much space is left in its receive window.
+//! * Up to `W` messages may be unacknowledged at once. If no acknowledgement
+//!   arrives within the retransmit timeout, all unacknowledged messages are sent
+//!   again.
+//! * When idle, a [`Segment::Keepalive`] is sent to detect a lost peer.
+//! * [`Segment::Fin`] and [`Segment::FinAck`] close the session gracefully, and
+//!   [`Segment::Reset`] aborts it. Dropping a [`Session`] without closing it
+//!   sends a reset.
+//!
+//! A session only makes progress (receiving, acknowledging, retransmitting)
+//! while one of its methods is being awaited, so applications will typically
+//! keep a `recv` call running. As with any windowed protocol, if both sides
+//! keep sending without ever receiving, the session stalls once both receive
+//! windows are full.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use core::pin::pin;
+//! # use mutex::raw_impls::cs::CriticalSectionRawMutex as CSRMutex;
+//! # use ergot::{Address, NetStack, interface_manager::profiles::null::Null};
+//! # use ergot::socket::session::SessionConfig;
+//! ergot::endpoint!(Actuator, u32, u32, "example/actuator");
+//!
+//! static STACK: NetStack<CSRMutex, Null> = NetStack::new();
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let server = async {
+//!         let listener = STACK.sessions().listener::<Actuator, 4>(None);
+//!         let listener = pin!(listener);
+//!         let mut listener = listener.attach();
+//!
+//!         let sock = STACK.sessions().server::<Actuator, 8>();
+//!         let sock = pin!(sock);
+//!         let mut sess = listener.accept(sock, SessionConfig::DEFAULT).await.unwrap();
+//!         let cmd = sess.recv().await.unwrap();
+//!         sess.send(&(cmd + 1)).await.unwrap();
+//!         sess.close().await.unwrap();
+//!     };
+//!
+//!     let client = async {
+//!         let sock = STACK.sessions().client::<Actuator, 8>();
+//!         let sock = pin!(sock);
+//!         let mut sess = sock
+//!             .connect(Address::unknown(), None, SessionConfig::DEFAULT)
+//!             .await
+//!             .unwrap();
+//!         sess.send(&41).await.unwrap();
+//!         assert_eq!(sess.recv().await, Ok(42));
+//!         sess.close().await.unwrap();
+//!     };
+//!
+//!     tokio::join!(server, client);
+//! }
+//! ```
+
+use core::{pin::Pin, time::Duration};
+
+use embassy_futures::select::{Either, select};
+use mutex::ScopedRawMutex;
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, Key, NetStack, Priority,
+    ProtocolError,
+    interface_manager::Profile,
+    nash::NameHash,
+    net_stack::{NetStackHandle, NetStackSendError, timer_sleep},
+    socket::{Attributes, HeaderMessage, Response, owned::stack_vec},
+    traits::Endpoint,
+};
+
+/// A single session protocol message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Segment<T> {
+    /// Open a session. The header `seq_no` is the initiator's initial sequence number.
+    Syn { window: u8 },
+    /// Accept a session. The header `seq_no` is the acceptor's initial sequence
+    /// number, `ack` echoes the initiator's.
+    SynAck { ack: u16, window: u8 },
+    /// A message. The header `seq_no` is the message's sequence number.
+    Data { msg: T },
+    /// All messages before sequence number `ack` have been received, and
+    /// `window` more messages may be sent.
+    Ack { ack: u16, window: u8 },
+    /// Liveness (and window) probe, answered with an [`Segment::Ack`]
+    Keepalive,
+    /// Gracefully close the session
+    Fin,
+    /// Acknowledge a [`Segment::Fin`]
+    FinAck,
+    /// Abort the session
+    Reset,
+}
+
+/// Timing configuration of a [`Session`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionConfig {
+    /// Time to wait for an acknowledgement before retransmitting
+    pub retransmit: Duration,
+    /// Number of consecutive unanswered retransmissions (or keepalives) before
+    /// the peer is considered lost
+    pub max_retries: u8,
+    /// Idle time after which a keepalive is sent
+    pub keepalive: Duration,
+}
+
+impl SessionConfig {
+    pub const DEFAULT: Self = Self {
+        retransmit: Duration::from_millis(250),
+        max_retries: 8,
+        keepalive: Duration::from_secs(10),
+    };
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Debug, PartialEq)]
+pub enum SessionError {
+    /// Sending a segment failed locally
+    Local(NetStackSendError),
+    /// The network reported an error for this session
+    Remote(ProtocolError),
+    /// The peer did not respond within the configured number of retries
+    Timeout,
+    /// The peer reset the session
+    Reset,
+    /// The session has been closed
+    Closed,
+    /// Sessions cannot be opened to broadcast ports
+    NoBroadcast,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Open,
+    PeerClosed,
+    Closing,
+    Closed,
+    Reset,
+    Lost,
+}
+
+/// The client side of a session over the endpoint `E`
+pub type ClientSession<'a, E, NS, const W: usize> =
+    Session<'a, <E as Endpoint>::Request, <E as Endpoint>::Response, NS, W>;
+
+/// The server side of a session over the endpoint `E`
+pub type ServerSession<'a, E, NS, const W: usize> =
+    Session<'a, <E as Endpoint>::Response, <E as Endpoint>::Request, NS, W>;
+
+/// An unattached socket used to open a session, see [`ClientSocket::connect`]
+#[pin_project]
+pub struct ClientSocket<E, NS, const W: usize>
+where
+    E: Endpoint,
+    E::Request: Serialize + Clone + 'static,
+    E::Response: Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    #[pin]
+    socket: stack_vec::Socket<Segment<E::Response>, NS, W>,
+}
+
+/// An unattached socket used to accept a session, see [`ListenerHandle::accept`]
+#[pin_project]
+pub struct ServerSocket<E, NS, const W: usize>
+where
+    E: Endpoint,
+    E::Request: Clone + DeserializeOwned + 'static,
+    E::Response: Serialize + Clone + 'static,
+    NS: NetStackHandle,
+{
+    #[pin]
+    socket: stack_vec::Socket<Segment<E::Request>, NS, W>,
+}
+
+/// A discoverable socket that accepts incoming sessions, with a backlog of `N`
+#[pin_project]
+pub struct Listener<E, NS, const N: usize>
+where
+    E: Endpoint,
+    E::Request: Clone + DeserializeOwned + 'static,
+    E::Response: Serialize + Clone + 'static,
+    NS: NetStackHandle,
+{
+    #[pin]
+    socket: stack_vec::Socket<Segment<E::Request>, NS, N>,
+}
+
+pub struct ListenerHandle<'a, E, NS, const N: usize>
+where
+    E: Endpoint,
+    E::Request: Clone + DeserializeOwned + 'static,
+    E::Response: Serialize + Clone + 'static,
+    NS: NetStackHandle,
+{
+    hdl: stack_vec::SocketHdl<'a, Segment<E::Request>, NS, N>,
+    // The most recently accepted (peer, initial sequence number), used to
+    // ignore retransmitted `Syn`s of a session that was already accepted
+    last: Option<(Address, u16)>,
+}
+
+/// An open session, sending `Tx` and receiving `Rx` messages, with a window of
+/// `W` messages in each direction.
+pub struct Session<'a, Tx, Rx, NS, const W: usize>
+where
+    Tx: Serialize + Clone + 'static,
+    Rx: Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    hdl: stack_vec::SocketHdl<'a, Segment<Rx>, NS, W>,
+    peer: Address,
+    config: SessionConfig,
+    state: State,
+    retries: u8,
+    probing: bool,
+    need_ack: bool,
+
+    // Sent, but not yet acknowledged messages, starting at `tx_una`
+    tx_buf: heapless::Deque<Tx, W>,
+    tx_una: u16,
+    peer_window: u8,
+
+    // Received messages, starting at `rx_read` (stored at `rx_head`). Slots
+    // before `rx_next` are contiguous, slots after may be filled out of order.
+    rx_buf: [Option<Rx>; W],
+    rx_head: usize,
+    rx_read: u16,
+    rx_next: u16,
+}
+
+// ---- impls ----
+
+const fn window_of<const W: usize>() -> u8 {
+    if W > u8::MAX as usize {
+        u8::MAX
+    } else {
+        W as u8
+    }
+}
+
+fn session_hdr(port: u8, dst: Address, seq_no: Option<u16>) -> Header {
+    Header {
+        src: Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: port,
+        },
+        dst,
+        any_all: None,
+        seq_no,
+        kind: FrameKind::SESSION,
+        ttl: DEFAULT_TTL,
+        prio: Priority::NORMAL,
+    }
+}
+
+/// Send a segment. Full queues are treated as loss, and are recovered by
+/// retransmission.
+fn send_segment<T, R, P>(
+    stack: &NetStack<R, P>,
+    hdr: &Header,
+    seg: &Segment<T>,
+) -> Result<(), SessionError>
+where
+    T: Serialize + Clone + 'static,
+    R: ScopedRawMutex,
+    P: Profile,
+{
+    match stack.send_ty(hdr, seg) {
+        Ok(())
+        | Err(NetStackSendError::InterfaceSend(_))
+        | Err(NetStackSendError::SocketSend(_)) => Ok(()),
+        Err(e) => Err(SessionError::Local(e)),
+    }
+}
+
+// impl ClientSocket
+
+impl<E, NS, const W: usize> ClientSocket<E, NS, W>
+where
+    E: Endpoint,
+    E::Request: Serialize + Clone + 'static,
+    E::Response: Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    pub const fn new(net: NS::Target) -> Self {
+        const { assert!(W > 0, "Session window must not be empty") };
+        Self {
+            socket: stack_vec::Socket::new(
+                net,
+                Key(E::RESP_KEY.to_bytes()),
+                Attributes {
+                    kind: FrameKind::SESSION,
+                    discoverable: false,
+                },
+                None,
+            ),
+        }
+    }
+
+    /// Attach the socket, and open a session to `dst`.
+    ///
+    /// If the port of `dst` is `0`, the session is opened with any [`Listener`]
+    /// for `E` at the given node, optionally filtered by `name`.
+    pub async fn connect(
+        self: Pin<&mut Self>,
+        dst: Address,
+        name: Option<&str>,
+        config: SessionConfig,
+    ) -> Result<ClientSession<'_, E, NS, W>, SessionError> {
+        let any_all = match dst.port_id {
+            0 => Some(AnyAllAppendix {
+                key: Key(E::REQ_KEY.to_bytes()),
+                nash: name.map(NameHash::new),
+            }),
+            255 => return Err(SessionError::NoBroadcast),
+            _ => None,
+        };
+
+        let mut hdl = self.project().socket.attach();
+        let stack = hdl.stack();
+        let isn = stack.next_seq_no();
+        let mut hdr = session_hdr(hdl.port(), dst, Some(isn));
+        hdr.any_all = any_all;
+        let syn = Segment::<E::Request>::Syn {
+            window: window_of::<W>(),
+        };
+
+        let mut retries = 0;
+        loop {
+            send_segment(&*stack, &hdr, &syn)?;
+            let accepted = loop {
+                match select(hdl.recv(), timer_sleep(config.retransmit)).await {
+                    Either::First(Ok(HeaderMessage {
+                        hdr,
+                        t: Segment::SynAck { ack, window },
+                    })) if ack == isn => break Some((hdr.src, hdr.seq_no, window)),
+                    // Not for this handshake, keep waiting
+                    Either::First(Ok(_)) => {}
+                    Either::First(Err(e)) => return Err(SessionError::Remote(e.t)),
+                    Either::Second(()) => break None,
+                }
+            };
+
+            if let Some((peer, peer_isn, window)) = accepted {
+                let mut session = Session::new(
+                    hdl,
+                    peer,
+                    config,
+                    isn.wrapping_add(1),
+                    peer_isn.wrapping_add(1),
+                    window,
+                );
+                session.need_ack = true;
+                session.flush_ack()?;
+                return Ok(session);
+            }
+
+            retries += 1;
+            if retries > config.max_retries {
+                return Err(SessionError::Timeout);
+            }
+        }
+    }
+}
+
+// impl ServerSocket
+
+impl<E, NS, const W: usize> ServerSocket<E, NS, W>
+where
+    E: Endpoint,
+    E::Request: Clone + DeserializeOwned + 'static,
+    E::Response: Serialize + Clone + 'static,
+    NS: NetStackHandle,
+{
+    pub const fn new(net: NS::Target) -> Self {
+        const { assert!(W > 0, "Session window must not be empty") };
+        Self {
+            socket: stack_vec::Socket::new(
+                net,
+                Key(E::REQ_KEY.to_bytes()),
+                Attributes {
+                    kind: FrameKind::SESSION,
+                    discoverable: false,
+                },
+                None,
+            ),
+        }
+    }
+}
+
+// impl Listener
+
+impl<E, NS, const N: usize> Listener<E, NS, N>
+where
+    E: Endpoint,
+    E::Request: Clone + DeserializeOwned + 'static,
+    E::Response: Serialize + Clone + 'static,
+    NS: NetStackHandle,
+{
+    pub const fn new(net: NS::Target, name: Option<&str>) -> Self {
+        Self {
+            socket: stack_vec::Socket::new(
+                net,
+                Key(E::REQ_KEY.to_bytes()),
+                Attributes {
+                    kind: FrameKind::SESSION,
+                    discoverable: true,
+                },
+                name,
+            ),
+        }
+    }
+
+    pub fn attach(self: Pin<&mut Self>) -> ListenerHandle<'_, E, NS, N> {
+        ListenerHandle {
+            hdl: self.project().socket.attach(),
+            last: None,
+        }
+    }
+}
+
+impl<E, NS, const N: usize> ListenerHandle<'_, E, NS, N>
+where
+    E: Endpoint,
+    E::Request: Clone + DeserializeOwned + 'static,
+    E::Response: Serialize + Clone + 'static,
+    NS: NetStackHandle,
+{
+    pub fn port(&self) -> u8 {
+        self.hdl.port()
+    }
+
+    /// Wait for an incoming session, and accept it using the given socket.
+    ///
+    /// Returns an error if the client does not confirm the session.
+    pub async fn accept<'b, const W: usize>(
+        &mut self,
+        socket: Pin<&'b mut ServerSocket<E, NS, W>>,
+        config: SessionConfig,
+    ) -> Result<ServerSession<'b, E, NS, W>, SessionError> {
+        let (peer, peer_isn, window) = loop {
+            let Ok(msg) = self.hdl.recv().await else {
+                continue;
+            };
+            let Segment::Syn { window } = msg.t else {
+                continue;
+            };
+            let id = (msg.hdr.src, msg.hdr.seq_no);
+            if self.last == Some(id) {
+                continue;
+            }
+            self.last = Some(id);
+            break (msg.hdr.src, msg.hdr.seq_no, window);
+        };
+
+        let hdl = socket.project().socket.attach();
+        let isn = hdl.stack().next_seq_no();
+        let mut session = Session::new(
+            hdl,
+            peer,
+            config,
+            isn.wrapping_add(1),
+            peer_isn.wrapping_add(1),
+            window,
+        );
+
+        let mut retries = 0;
+        loop {
+            let syn_ack = Segment::SynAck {
+                ack: peer_isn,
+                window: session.rx_window(),
+            };
+            session.send_seg(Some(isn), &syn_ack)?;
+
+            // Any segment from the client confirms the session
+            let confirmed = loop {
+                match select(session.hdl.recv(), timer_sleep(config.retransmit)).await {
+                    Either::First(resp) => {
+                        let from_peer = matches!(&resp, Ok(msg) if msg.hdr.src == peer);
+                        session.handle(resp)?;
+                        if from_peer {
+                            break true;
+                        }
+                    }
+                    Either::Second(()) => break false,
+                }
+            };
+
+            if confirmed {
+                session.flush_ack()?;
+                return Ok(session);
+            }
+
+            retries += 1;
+            if retries > config.max_retries {
+                session.state = State::Lost;
+                return Err(SessionError::Timeout);
+            }
+        }
+    }
+}
+
+// impl Session
+
+impl<'a, Tx, Rx, NS, const W: usize> Session<'a, Tx, Rx, NS, W>
+where
+    Tx: Serialize + Clone + 'static,
+    Rx: Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    fn new(
+        hdl: stack_vec::SocketHdl<'a, Segment<Rx>, NS, W>,
+        peer: Address,
+        config: SessionConfig,
+        tx_una: u16,
+        rx_next: u16,
+        peer_window: u8,
+    ) -> Self {
+        Self {
+            hdl,
+            peer,
+            config,
+            state: State::Open,
+            retries: 0,
+            probing: false,
+            need_ack: false,
+            tx_buf: heapless::Deque::new(),
+            tx_una,
+            peer_window,
+            rx_buf: core::array::from_fn(|_| None),
+            rx_head: 0,
+            rx_read: rx_next,
+            rx_next,
+        }
+    }
+
+    /// The address of the other side of this session
+    pub fn peer(&self) -> Address {
+        self.peer
+    }
+
+    /// The local port of this session
+    pub fn port(&self) -> u8 {
+        self.hdl.port()
+    }
+
+    /// Send a message.
+    ///
+    /// Waits until there is space in the send window, and returns once the
+    /// message has been sent for the first time. Use [`Self::flush`] to wait
+    /// until all messages have been acknowledged.
+    pub async fn send(&mut self, msg: &Tx) -> Result<(), SessionError> {
+        self.drain()?;
+        loop {
+            self.check_open()?;
+            if self.tx_buf.len() < W.min(usize::from(self.peer_window)) {
+                break;
+            }
+            self.poll_once().await?;
+        }
+
+        let seq = self.tx_una.wrapping_add(self.tx_buf.len() as u16);
+        let seg = Segment::Data { msg: msg.clone() };
+        if self.tx_buf.push_back(msg.clone()).is_err() {
+            unreachable!("checked window above");
+        }
+        self.send_seg(Some(seq), &seg)
+    }
+
+    /// Receive the next message, in order.
+    ///
+    /// Returns [`SessionError::Closed`] once the peer has closed the session
+    /// and all of its messages have been received.
+    pub async fn recv(&mut self) -> Result<Rx, SessionError> {
+        loop {
+            if self.rx_next != self.rx_read {
+                let was_full = self.rx_window() == 0;
+                let msg = self.rx_buf[self.rx_head].take();
+                self.rx_head = (self.rx_head + 1) % W;
+                self.rx_read = self.rx_read.wrapping_add(1);
+                if was_full {
+                    // Let the peer know that the window has opened again
+                    self.need_ack = true;
+                    self.flush_ack()?;
+                }
+                if let Some(msg) = msg {
+                    return Ok(msg);
+                }
+            }
+            match self.state {
+                State::Open | State::Closing => {}
+                State::PeerClosed | State::Closed => return Err(SessionError::Closed),
+                State::Reset => return Err(SessionError::Reset),
+                State::Lost => return Err(SessionError::Timeout),
+            }
+            self.poll_once().await?;
+        }
+    }
+
+    /// Wait until all sent messages have been acknowledged by the peer
+    pub async fn flush(&mut self) -> Result<(), SessionError> {
+        self.drain()?;
+        while !self.tx_buf.is_empty() {
+            self.check_open()?;
+            self.poll_once().await?;
+        }
+        Ok(())
+    }
+
+    /// Gracefully close the session, after all sent messages have been
+    /// acknowledged.
+    pub async fn close(mut self) -> Result<(), SessionError> {
+        self.flush().await?;
+        if self.state == State::PeerClosed {
+            // The peer already closed, and acknowledged everything we sent
+            self.state = State::Closed;
+            return Ok(());
+        }
+
+        self.state = State::Closing;
+        self.retries = 0;
+        self.send_seg(None, &Segment::Fin)?;
+        while self.state == State::Closing {
+            self.poll_once().await?;
+        }
+        Ok(())
+    }
+
+    fn check_open(&self) -> Result<(), SessionError> {
+        match self.state {
+            State::Open => Ok(()),
+            State::PeerClosed | State::Closing | State::Closed => Err(SessionError::Closed),
+            State::Reset => Err(SessionError::Reset),
+            State::Lost => Err(SessionError::Timeout),
+        }
+    }
+
+    fn rx_window(&self) -> u8 {
+        let used = usize::from(self.rx_next.wrapping_sub(self.rx_read));
+        (W - used).min(usize::from(u8::MAX)) as u8
+    }
+
+    fn send_seg(&self, seq_no: Option<u16>, seg: &Segment<Tx>) -> Result<(), SessionError> {
+        let hdr = session_hdr(self.hdl.port(), self.peer, seq_no);
+        send_segment(&*self.hdl.stack(), &hdr, seg)
+    }
+
+    fn flush_ack(&mut self) -> Result<(), SessionError> {
+        if !self.need_ack {
+            return Ok(());
+        }
+        self.need_ack = false;
+        self.send_seg(
+            None,
+            &Segment::Ack {
+                ack: self.rx_next,
+                window: self.rx_window(),
+            },
+        )
+    }
+
+    /// Handle all segments that have already been received, without waiting
+    fn drain(&mut self) -> Result<(), SessionError> {
+        while let Some(resp) = self.hdl.try_recv() {
+            self.handle(resp)?;
+        }
+        self.flush_ack()
+    }
+
+    /// Wait for the next segment, or for the current timer to expire
+    async fn poll_once(&mut self) -> Result<(), SessionError> {
+        let waiting = !self.tx_buf.is_empty() || self.probing || self.state == State::Closing;
+        let timeout = if waiting {
+            self.config.retransmit
+        } else {
+            self.config.keepalive
+        };
+
+        match select(self.hdl.recv(), timer_sleep(timeout)).await {
+            Either::First(resp) => {
+                self.handle(resp)?;
+                self.drain()?;
+            }
+            Either::Second(()) => self.on_timeout()?,
+        }
+        self.flush_ack()
+    }
+
+    fn on_timeout(&mut self) -> Result<(), SessionError> {
+        if self.retries >= self.config.max_retries {
+            self.state = State::Lost;
+            return Err(SessionError::Timeout);
+        }
+        self.retries += 1;
+
+        if self.state == State::Closing {
+            return self.send_seg(None, &Segment::Fin);
+        }
+        if self.tx_buf.is_empty() {
+            // Idle: check that the peer is still there
+            self.probing = true;
+            return self.send_seg(None, &Segment::Keepalive);
+        }
+        // Go-back-N: resend everything that has not been acknowledged
+        for (i, msg) in self.tx_buf.iter().enumerate() {
+            let seq = self.tx_una.wrapping_add(i as u16);
+            self.send_seg(Some(seq), &Segment::Data { msg: msg.clone() })?;
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, resp: Response<Segment<Rx>>) -> Result<(), SessionError> {
+        let msg = match resp {
+            Ok(msg) => msg,
+            // Congestion along the way is just loss
+            Err(err) if err.t == ProtocolError::IseInterfaceFull => return Ok(()),
+            Err(err) => {
+                self.state = State::Lost;
+                return Err(SessionError::Remote(err.t));
+            }
+        };
+
+        if msg.hdr.src != self.peer {
+            // A late `SynAck` from a duplicate accept: tell it to go away
+            if let Segment::SynAck { .. } = msg.t {
+                let hdr = session_hdr(self.hdl.port(), msg.hdr.src, None);
+                send_segment(&*self.hdl.stack(), &hdr, &Segment::<Tx>::Reset)?;
+            }
+            return Ok(());
+        }
+
+        self.retries = 0;
+        self.probing = false;
+        match msg.t {
+            Segment::Data { msg: data } => self.handle_data(msg.hdr.seq_no, data),
+            Segment::Ack { ack, window } => self.handle_ack(ack, window),
+            // Our handshake `Ack` was lost, or the peer is probing us
+            Segment::SynAck { .. } | Segment::Keepalive => self.need_ack = true,
+            Segment::Syn { .. } => {}
+            Segment::Fin => {
+                self.send_seg(None, &Segment::FinAck)?;
+                if self.state == State::Open {
+                    self.state = State::PeerClosed;
+                }
+            }
+            Segment::FinAck => {
+                if self.state == State::Closing {
+                    self.state = State::Closed;
+                }
+            }
+            Segment::Reset => {
+                self.state = State::Reset;
+                return Err(SessionError::Reset);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_data(&mut self, seq: u16, data: Rx) {
+        // Always acknowledge, duplicates mean our last `Ack` was lost
+        self.need_ack = true;
+
+        let offset = usize::from(seq.wrapping_sub(self.rx_read));
+        let received = usize::from(self.rx_next.wrapping_sub(self.rx_read));
+        if offset >= W || offset < received {
+            // Outside of the window, or a duplicate
+            return;
+        }
+        let slot = &mut self.rx_buf[(self.rx_head + offset) % W];
+        if slot.is_none() {
+            *slot = Some(data);
+        }
+
+        // Advance over any contiguous messages
+        let mut received = received;
+        while received < W && self.rx_buf[(self.rx_head + received) % W].is_some() {
+            received += 1;
+        }
+        self.rx_next = self.rx_read.wrapping_add(received as u16);
+    }
+
+    fn handle_ack(&mut self, ack: u16, window: u8) {
+        self.peer_window = window;
+        let acked = usize::from(ack.wrapping_sub(self.tx_una));
+        if acked == 0 || acked > self.tx_buf.len() {
+            return;
+        }
+        for _ in 0..acked {
+            self.tx_buf.pop_front();
+        }
+        self.tx_una = ack;
+    }
+}
+
+impl<Tx, Rx, NS, const W: usize> Drop for Session<'_, Tx, Rx, NS, W>
+where
+    Tx: Serialize + Clone + 'static,
+    Rx: Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    fn drop(&mut self) {
+        // Let the peer know immediately, rather than waiting for it to time out
+        if matches!(self.state, State::Open | State::Closing) {
+            _ = self.send_seg(None, &Segment::Reset);
+        }
+    }
+}
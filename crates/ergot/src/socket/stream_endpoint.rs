@@ -0,0 +1,371 @@
+//! Streaming Endpoint Sockets
+//!
+//! A [`StreamEndpoint`] answers one request with any number of response items,
+//! followed by an end marker. This avoids inventing a paging protocol when a
+//! response is a long list, such as a directory listing or a log dump.
+//!
+//! ## Protocol
+//!
+//! Streaming endpoints use the same frames as regular endpoints: the request
+//! is sent as a [`FrameKind::ENDPOINT_REQ`], and each response is sent as a
+//! [`FrameKind::ENDPOINT_RESP`] carrying a [`StreamItem`]. The request key is
+//! derived from [`StreamRequest`](crate::traits::StreamRequest), so a plain
+//! endpoint with the same path and request type is a different endpoint.
+//!
+//! * The client sends the request with an explicit header `seq_no`.
+//! * The server answers with [`StreamItem::Item`]s numbered consecutively,
+//!   starting at the request `seq_no` plus one, followed by a
+//!   [`StreamItem::End`] with the next number.
+//! * The client checks the numbers as items arrive. A lost or reordered item is
+//!   reported as [`ReqRespError::Missed`], after which the stream continues.
+//!
+//! Like other endpoint responses, stream items are not retransmitted. The
+//! client socket queues up to `N` items, so a server that produces items faster
+//! than the client consumes them should pace itself, or the client should use
+//! a deeper queue. Local clients apply backpressure: [`StreamSink::send`] waits
+//! while the client's queue is full.
+//!
+//! ## Example
+//!
+//! ```rust
+//! # use core::pin::pin;
+//! # use mutex::raw_impls::cs::CriticalSectionRawMutex as CSRMutex;
+//! # use ergot::{Address, NetStack, interface_manager::profiles::null::Null};
+//! ergot::stream_endpoint!(Countdown, u32, u32, "example/countdown");
+//!
+//! static STACK: NetStack<CSRMutex, Null> = NetStack::new();
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let srv = STACK.endpoints().bounded_server::<Countdown, 4>(None);
+//!     let srv = pin!(srv);
+//!     let mut srv = srv.attach();
+//!     let server = srv.serve_stream(async |from, sink| {
+//!         for i in (0..*from).rev() {
+//!             sink.send(i).await?;
+//!         }
+//!         Ok(())
+//!     });
+//!
+//!     let client = async {
+//!         let sock = STACK.endpoints().stream_client::<Countdown, 4>();
+//!         let sock = pin!(sock);
+//!         let mut hdl = sock.attach();
+//!         hdl.request(Address::unknown(), &3, None).unwrap();
+//!         let mut items = vec![];
+//!         while let Some(item) = hdl.next().await {
+//!             items.push(item.unwrap());
+//!         }
+//!         assert_eq!(items, [2, 1, 0]);
+//!     };
+//!
+//!     let (res, ()) = tokio::join!(server, client);
+//!     res.unwrap();
+//! }
+//! ```
+
+use core::{
+    marker::PhantomData,
+    pin::{Pin, pin},
+};
+
+use embassy_futures::select::{Either, select};
+use pin_project::pin_project;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, HeaderSeq, Key, Priority,
+    logging::debug,
+    nash::NameHash,
+    net_stack::{NetStackHandle, NetStackSendError, ReqRespError},
+    socket::{Response, SocketSendError, endpoint::raw, owned::stack_vec::Bounded},
+    traits::{StreamEndpoint, StreamItem},
+};
+
+/// The most items a client skips over when items are lost
+///
+/// Responses numbered this far or further ahead of the next expected item,
+/// or numbered before it, are dropped without ending the stream.
+pub const MAX_STREAM_GAP: u16 = 1024;
+
+/// The sending half of a response stream, used by a server
+///
+/// Dropping the sink without calling [`StreamSink::finish`] still sends the
+/// end marker, so the client never waits for items that will not come.
+pub struct StreamSink<E, NS>
+where
+    E: StreamEndpoint,
+    E::Item: Serialize + Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    stack: NS::Target,
+    src: Address,
+    dst: Address,
+    seq_no: u16,
+    prio: Priority,
+    finished: bool,
+    _pd: PhantomData<fn() -> E>,
+}
+
+impl<E, NS> StreamSink<E, NS>
+where
+    E: StreamEndpoint,
+    E::Item: Serialize + Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    /// Create a sink answering the request with the given header, sending from
+    /// `port`
+    pub fn new(stack: NS::Target, req_hdr: &HeaderSeq, port: u8) -> Self {
+        let mut src = req_hdr.dst;
+        src.port_id = port;
+        Self {
+            stack,
+            src,
+            dst: req_hdr.src,
+            seq_no: req_hdr.seq_no.wrapping_add(1),
+            prio: req_hdr.prio,
+            finished: false,
+            _pd: PhantomData,
+        }
+    }
+
+    /// Send one item of the response stream
+    ///
+    /// Items that could not be sent still use up a sequence number, so the
+    /// client will notice that they are missing.
+    pub async fn send(&mut self, item: E::Item) -> Result<(), NetStackSendError> {
+        self.send_msg(&StreamItem::Item(item)).await
+    }
+
+    /// End the response stream
+    pub async fn finish(mut self) -> Result<(), NetStackSendError> {
+        self.finished = true;
+        self.send_msg(&StreamItem::End).await
+    }
+
+    async fn send_msg(&mut self, msg: &StreamItem<E::Item>) -> Result<(), NetStackSendError> {
+        let hdr = self.header();
+        self.seq_no = self.seq_no.wrapping_add(1);
+        loop {
+            // Subscribe before sending, so that room made in between is not
+            // missed
+            let mut space = pin!(self.stack.wait_for_space());
+            let _ = space.as_mut().subscribe();
+            match self.stack.send_ty::<StreamItem<E::Item>>(&hdr, msg) {
+                // A local client is not keeping up, wait until it catches up
+                Err(NetStackSendError::SocketSend(SocketSendError::NoSpace)) => {
+                    let _ = space.await;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    fn header(&self) -> Header {
+        Header {
+            src: self.src,
+            dst: self.dst,
+            any_all: None,
+            seq_no: Some(self.seq_no),
+            kind: FrameKind::ENDPOINT_RESP,
+            ttl: DEFAULT_TTL,
+            prio: self.prio,
+        }
+    }
+}
+
+impl<E, NS> Drop for StreamSink<E, NS>
+where
+    E: StreamEndpoint,
+    E::Item: Serialize + Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    fn drop(&mut self) {
+        if !self.finished {
+            _ = self
+                .stack
+                .send_ty::<StreamItem<E::Item>>(&self.header(), &StreamItem::End);
+        }
+    }
+}
+
+/// A streaming endpoint Client socket, queueing up to `N` response items
+#[pin_project]
+pub struct Client<E, NS, const N: usize>
+where
+    E: StreamEndpoint,
+    E::Item: Serialize + Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    #[pin]
+    sock: raw::Client<Bounded<Response<StreamItem<E::Item>>, N>, E, NS>,
+    prio: Priority,
+}
+
+/// A streaming endpoint Client handle
+pub struct ClientHandle<'a, E, NS, const N: usize>
+where
+    E: StreamEndpoint,
+    E::Item: Serialize + Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    hdl: raw::ClientHandle<'a, Bounded<Response<StreamItem<E::Item>>, N>, E, NS>,
+    stack: NS::Target,
+    prio: Priority,
+    // The sequence number of the next expected response, if a stream is open
+    expected: Option<u16>,
+    // A response that arrived after a gap, returned after reporting the gap
+    pending: Option<Response<StreamItem<E::Item>>>,
+}
+
+impl<E, NS, const N: usize> Client<E, NS, N>
+where
+    E: StreamEndpoint,
+    E::Item: Serialize + Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    pub fn new(net: NS, name: Option<&str>) -> Self {
+        Self {
+            sock: raw::Client::new(net, Bounded::new(), name),
+            prio: Priority::NORMAL,
+        }
+    }
+
+    /// Send requests with the given [`Priority`], instead of [`Priority::NORMAL`]
+    pub fn with_priority(self, prio: Priority) -> Self {
+        Self { prio, ..self }
+    }
+
+    /// Attach the Client socket to the net stack, and receive a Handle
+    pub fn attach<'a>(self: Pin<&'a mut Self>) -> ClientHandle<'a, E, NS, N> {
+        let this = self.project();
+        let hdl = this.sock.attach();
+        let stack = hdl.stack();
+        ClientHandle {
+            hdl,
+            stack,
+            prio: *this.prio,
+            expected: None,
+            pending: None,
+        }
+    }
+}
+
+impl<E, NS, const N: usize> ClientHandle<'_, E, NS, N>
+where
+    E: StreamEndpoint,
+    E::Request: Serialize + Clone + DeserializeOwned + 'static,
+    E::Item: Serialize + Clone + DeserializeOwned + 'static,
+    NS: NetStackHandle,
+{
+    /// The port of this Client socket
+    pub fn port(&self) -> u8 {
+        self.hdl.port()
+    }
+
+    /// Send a request, opening a new response stream
+    ///
+    /// The items are then received with [`Self::next`]. A new request should
+    /// only be sent once the previous stream has ended.
+    pub fn request(
+        &mut self,
+        dst: Address,
+        req: &E::Request,
+        name: Option<&str>,
+    ) -> Result<(), ReqRespError> {
+        // If the destination is wildcard, include the any_all appendix to the
+        // header
+        let any_all = match dst.port_id {
+            0 => Some(AnyAllAppendix {
+                key: Key(E::REQ_KEY.to_bytes()),
+                nash: name.map(NameHash::new),
+            }),
+            255 => {
+                return Err(ReqRespError::NoBroadcast);
+            }
+            _ => None,
+        };
+
+        let seq_no = self.stack.next_seq_no();
+        let hdr = Header {
+            src: Address {
+                network_id: 0,
+                node_id: 0,
+                port_id: self.port(),
+            },
+            dst,
+            any_all,
+            seq_no: Some(seq_no),
+            kind: FrameKind::ENDPOINT_REQ,
+            ttl: DEFAULT_TTL,
+            prio: self.prio,
+        };
+        self.expected = None;
+        self.pending = None;
+        self.stack.send_ty(&hdr, req).map_err(ReqRespError::Local)?;
+        self.expected = Some(seq_no.wrapping_add(1));
+        Ok(())
+    }
+
+    /// Receive the next item of the response stream
+    ///
+    /// Returns `None` once the stream has ended, or if no request was sent.
+    /// An error reported by the remote also ends the stream.
+    pub async fn next(&mut self) -> Option<Result<E::Item, ReqRespError>> {
+        self.next_with_timeout(core::future::pending()).await
+    }
+
+    /// Same as [`Self::next`], but gives up with [`ReqRespError::Timeout`] once
+    /// the `timeout` future completes.
+    ///
+    /// The stream stays open after a timeout, so this may be called again to
+    /// keep waiting.
+    pub async fn next_with_timeout<F: Future<Output = ()>>(
+        &mut self,
+        timeout: F,
+    ) -> Option<Result<E::Item, ReqRespError>> {
+        let expected = self.expected?;
+        let mut timeout = pin!(timeout);
+        let msg = loop {
+            let resp = match self.pending.take() {
+                Some(resp) => resp,
+                None => match select(self.hdl.recv(), timeout.as_mut()).await {
+                    Either::First(resp) => resp,
+                    Either::Second(()) => return Some(Err(ReqRespError::Timeout)),
+                },
+            };
+
+            let msg = match resp {
+                Ok(msg) => msg,
+                Err(e) => {
+                    self.expected = None;
+                    return Some(Err(ReqRespError::Remote(e.t)));
+                }
+            };
+            // Items from an earlier stream, or too far ahead, are not part
+            // of this stream
+            if msg.hdr.seq_no.wrapping_sub(expected) >= MAX_STREAM_GAP {
+                debug!("Dropping stray stream item {}", msg.hdr.seq_no);
+                continue;
+            }
+            break msg;
+        };
+        if msg.hdr.seq_no != expected {
+            // Report the gap now, and the item that revealed it next time
+            self.expected = Some(msg.hdr.seq_no);
+            self.pending = Some(Ok(msg));
+            return Some(Err(ReqRespError::Missed));
+        }
+
+        match msg.t {
+            StreamItem::Item(item) => {
+                self.expected = Some(expected.wrapping_add(1));
+                Some(Ok(item))
+            }
+            StreamItem::End => {
+                self.expected = None;
+                None
+            }
+        }
+    }
+}
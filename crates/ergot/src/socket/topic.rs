@@ -9,7 +9,8 @@ use serde::{Serialize, de::DeserializeOwned};
 use crate as base;
 use crate::{
     FrameKind,
-    socket::{Attributes, Response},
+    socket::{Attributes, Response, SocketSchema},
+    traits::Schema,
 };
 
 macro_rules! topic_receiver {
@@ -49,6 +50,15 @@ macro_rules! topic_receiver {
             T::Message: Serialize + Clone + DeserializeOwned + 'static,
             NS: crate::net_stack::NetStackHandle,
         {
+            /// Only accept messages from other devices that are signed by a
+            /// signer in `store`, see [`crate::signing`]
+            #[cfg(feature = "signing")]
+            pub fn with_trust_store(self, store: &'static dyn crate::signing::TrustStore) -> Self {
+                Self {
+                    sock: self.sock.with_trust_store(store),
+                }
+            }
+
             /// Attach to the [`NetStack`](crate::net_stack::NetStack), and obtain a [`ReceiverHandle`]
             pub fn subscribe<'a>(self: Pin<&'a mut Self>) -> ReceiverHandle<'a, T, NS, $($arr)?> {
                 let this = self.project();
@@ -154,10 +164,28 @@ pub mod raw {
                     },
                     sto,
                     name,
+                )
+                .with_schema(
+                    const {
+                        &SocketSchema {
+                            path: T::PATH,
+                            rx: <T::Message as Schema>::SCHEMA,
+                            tx: None,
+                        }
+                    },
                 ),
             }
         }
 
+        /// Only accept messages from other devices that are signed by a signer
+        /// in `store`, see [`crate::signing`]
+        #[cfg(feature = "signing")]
+        pub fn with_trust_store(self, store: &'static dyn crate::signing::TrustStore) -> Self {
+            Self {
+                sock: self.sock.with_trust_store(store),
+            }
+        }
+
         /// Attach and obtain a ReceiverHandle
         pub fn subscribe<'a>(self: Pin<&'a mut Self>) -> ReceiverHandle<'a, S, T, NS> {
             let this = self.project();
@@ -308,9 +336,10 @@ pub mod stack_bor {
         exports::bbqueue::traits::bbqhdl::BbqHandle,
         net_stack::NetStackHandle,
         socket::{
-            Attributes,
+            Attributes, SocketSchema,
             borrow::{ResponseGrant, Socket, SocketHdl},
         },
+        traits::Schema,
     };
     use serde::Serialize;
 
@@ -355,10 +384,28 @@ pub mod stack_bor {
                     sto,
                     mtu,
                     name,
+                )
+                .with_schema(
+                    const {
+                        &SocketSchema {
+                            path: T::PATH,
+                            rx: <T::Message as Schema>::SCHEMA,
+                            tx: None,
+                        }
+                    },
                 ),
             }
         }
 
+        /// Only accept messages from other devices that are signed by a signer
+        /// in `store`, see [`crate::signing`]
+        #[cfg(feature = "signing")]
+        pub fn with_trust_store(self, store: &'static dyn crate::signing::TrustStore) -> Self {
+            Self {
+                inner: self.inner.with_trust_store(store),
+            }
+        }
+
         /// Attach to the [`NetStack`](crate::net_stack::NetStack), and obtain a [`ReceiverHdl`]
         pub fn subscribe<'a>(self: Pin<&'a mut Self>) -> ReceiverHdl<'a, Q, T, NS> {
             let this = self.project();
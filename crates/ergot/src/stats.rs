@@ -0,0 +1,229 @@
+//! Traffic Counters
+//!
+//! To find out where frames are dropped, each interface and each socket
+//! counts the frames crossing it, and the frames it drops, with
+//! [`InterfaceCounters`] and [`SocketCounters`].
+//!
+//! Interfaces count:
+//!
+//! * the frames sent and received, and their bytes: the length of each
+//!   frame as encoded by the sink of the interface, or as decoded by its
+//!   receive worker, header included, without link framing such as COBS or
+//!   encryption
+//! * the frames dropped because the sink of the interface was full
+//!   ([`InterfaceSendError::InterfaceFull`]) or because they were larger
+//!   than its MTU ([`InterfaceSendError::PacketTooBig`]). These are counted
+//!   by the interface the frame was to be sent out of
+//! * the frames received by a router and dropped because their TTL expired
+//!   ([`InterfaceSendError::TtlExpired`]), or because they would have been
+//!   sent back out of the interface they came from
+//!   ([`InterfaceSendError::RoutingLoop`]). These are counted by the
+//!   interface the frame was received on
+//! * the frames denied by the packet filter
+//!   ([`InterfaceSendError::Filtered`]), counted by the interface they were
+//!   denied on, and the frames received and dropped by the rate limiter of a
+//!   router ([`InterfaceSendError::RateLimited`])
+//!
+//! Sockets count the messages delivered to them, and the messages they
+//! dropped because their storage was full ([`SocketSendError::NoSpace`]),
+//! because the message couldn't be deserialized
+//! ([`SocketSendError::DeserFailed`]), or because it was of an unexpected
+//! type ([`SocketSendError::TypeMismatch`]).
+//!
+//! Counters are atomics, so they cost no locking and work on `no_std`
+//! targets. They wrap around on overflow.
+//!
+//! The counters of a [`NetStack`](crate::NetStack) are read locally with
+//! `NetStack::interface_stats()` and `NetStack::socket_stats()`, or
+//! remotely through the [`ErgotStatsEndpoint`], usually handled by
+//! `Services::stats_handler()`.
+//!
+//! [`ErgotStatsEndpoint`]: crate::well_known::ErgotStatsEndpoint
+
+use portable_atomic::{AtomicU32, Ordering};
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    FrameKind, Key,
+    interface_manager::InterfaceSendError,
+    nash::NameHash,
+    socket::{SocketHeader, SocketSendError},
+};
+
+/// The counters of an interface
+#[derive(Debug, Default)]
+pub struct InterfaceCounters {
+    frames_tx: AtomicU32,
+    bytes_tx: AtomicU32,
+    frames_rx: AtomicU32,
+    bytes_rx: AtomicU32,
+    interface_full: AtomicU32,
+    ttl_expired: AtomicU32,
+    routing_loop: AtomicU32,
+    packet_too_big: AtomicU32,
+    filtered: AtomicU32,
+    rate_limited: AtomicU32,
+}
+
+impl InterfaceCounters {
+    pub const fn new() -> Self {
+        Self {
+            frames_tx: AtomicU32::new(0),
+            bytes_tx: AtomicU32::new(0),
+            frames_rx: AtomicU32::new(0),
+            bytes_rx: AtomicU32::new(0),
+            interface_full: AtomicU32::new(0),
+            ttl_expired: AtomicU32::new(0),
+            routing_loop: AtomicU32::new(0),
+            packet_too_big: AtomicU32::new(0),
+            filtered: AtomicU32::new(0),
+            rate_limited: AtomicU32::new(0),
+        }
+    }
+
+    /// Count a frame of `len` bytes sent
+    pub fn count_sent(&self, len: usize) {
+        self.frames_tx.fetch_add(1, Ordering::Relaxed);
+        self.bytes_tx.fetch_add(len as u32, Ordering::Relaxed);
+    }
+
+    /// Count a frame of `len` bytes received
+    pub fn count_received(&self, len: usize) {
+        self.frames_rx.fetch_add(1, Ordering::Relaxed);
+        self.bytes_rx.fetch_add(len as u32, Ordering::Relaxed);
+    }
+
+    /// Count a frame dropped because of `err`
+    ///
+    /// Only the errors listed in the [module docs](self) are counted, others
+    /// are ignored.
+    pub fn count_drop(&self, err: &InterfaceSendError) {
+        let counter = match err {
+            InterfaceSendError::InterfaceFull => &self.interface_full,
+            InterfaceSendError::TtlExpired => &self.ttl_expired,
+            InterfaceSendError::RoutingLoop => &self.routing_loop,
+            InterfaceSendError::PacketTooBig { .. } => &self.packet_too_big,
+            InterfaceSendError::Filtered => &self.filtered,
+            InterfaceSendError::RateLimited => &self.rate_limited,
+            _ => return,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The current value of the counters, for the interface `interface`
+    pub fn snapshot(&self, interface: u8) -> InterfaceStats {
+        InterfaceStats {
+            interface,
+            frames_tx: self.frames_tx.load(Ordering::Relaxed),
+            bytes_tx: self.bytes_tx.load(Ordering::Relaxed),
+            frames_rx: self.frames_rx.load(Ordering::Relaxed),
+            bytes_rx: self.bytes_rx.load(Ordering::Relaxed),
+            interface_full: self.interface_full.load(Ordering::Relaxed),
+            ttl_expired: self.ttl_expired.load(Ordering::Relaxed),
+            routing_loop: self.routing_loop.load(Ordering::Relaxed),
+            packet_too_big: self.packet_too_big.load(Ordering::Relaxed),
+            filtered: self.filtered.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The counters of a socket
+#[derive(Debug, Default)]
+pub struct SocketCounters {
+    received: AtomicU32,
+    no_space: AtomicU32,
+    deser_failed: AtomicU32,
+    type_mismatch: AtomicU32,
+}
+
+impl SocketCounters {
+    pub const fn new() -> Self {
+        Self {
+            received: AtomicU32::new(0),
+            no_space: AtomicU32::new(0),
+            deser_failed: AtomicU32::new(0),
+            type_mismatch: AtomicU32::new(0),
+        }
+    }
+
+    /// Count the outcome of delivering a message to the socket
+    pub fn count(&self, res: &Result<(), SocketSendError>) {
+        let counter = match res {
+            Ok(()) => &self.received,
+            Err(SocketSendError::NoSpace) => &self.no_space,
+            Err(SocketSendError::DeserFailed) => &self.deser_failed,
+            Err(SocketSendError::TypeMismatch) => &self.type_mismatch,
+            Err(_) => return,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The current value of the counters, for the socket `hdr`
+    pub fn snapshot(&self, hdr: &SocketHeader) -> SocketStats {
+        SocketStats {
+            port: hdr.port,
+            kind: hdr.attrs.kind,
+            key: hdr.key,
+            name: hdr.nash,
+            received: self.received.load(Ordering::Relaxed),
+            no_space: self.no_space.load(Ordering::Relaxed),
+            deser_failed: self.deser_failed.load(Ordering::Relaxed),
+            type_mismatch: self.type_mismatch.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The counters of an interface, see [`InterfaceCounters`]
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct InterfaceStats {
+    /// The ident of the interface, like in [`filter`]
+    ///
+    /// [`filter`]: crate::interface_manager::filter
+    pub interface: u8,
+    /// Frames sent
+    pub frames_tx: u32,
+    /// Bytes of the frames sent
+    pub bytes_tx: u32,
+    /// Frames received
+    pub frames_rx: u32,
+    /// Bytes of the frames received
+    pub bytes_rx: u32,
+    /// Frames dropped because the interface was full
+    pub interface_full: u32,
+    /// Frames received and dropped because their TTL expired
+    pub ttl_expired: u32,
+    /// Frames received and dropped because they would have been sent back
+    /// out of this interface
+    pub routing_loop: u32,
+    /// Frames dropped because they were larger than the MTU of the interface
+    pub packet_too_big: u32,
+    /// Frames denied by the packet filter on this interface
+    pub filtered: u32,
+    /// Frames received and dropped by the rate limiter
+    pub rate_limited: u32,
+}
+
+/// The counters of a socket, see [`SocketCounters`]
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct SocketStats {
+    /// The port of the socket
+    pub port: u8,
+    /// The kind of frames received by the socket
+    pub kind: FrameKind,
+    /// The key of the socket
+    pub key: Key,
+    /// The name of the socket, if any
+    pub name: Option<NameHash>,
+    /// Messages delivered to the socket
+    pub received: u32,
+    /// Messages dropped because the socket was full
+    pub no_space: u32,
+    /// Messages dropped because they couldn't be deserialized
+    pub deser_failed: u32,
+    /// Messages dropped because they were of an unexpected type
+    pub type_mismatch: u32,
+}
@@ -1,4 +1,5 @@
 pub use postcard_schema::{Schema, key::Key};
+use serde::{Deserialize, Serialize};
 
 /// A marker trait denoting a single endpoint
 ///
@@ -16,6 +17,35 @@ pub trait Endpoint {
     const RESP_KEY: Key;
 }
 
+/// A marker trait denoting a server-streaming endpoint
+///
+/// A streaming endpoint answers a single Request with any number of `Item`s,
+/// followed by [StreamItem::End]. On the wire, it is an [Endpoint] whose
+/// Response is a [StreamItem].
+///
+/// Typically used with the [stream_endpoint](crate::stream_endpoint) macro.
+pub trait StreamEndpoint: Endpoint<Response = StreamItem<Self::Item>> {
+    /// The type of each streamed Response item (server to client)
+    type Item: Schema;
+}
+
+/// A single response message of a [StreamEndpoint]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schema)]
+pub enum StreamItem<T> {
+    /// One item of the response stream
+    Item(T),
+    /// The end of the response stream, no more items will follow
+    End,
+}
+
+/// The type a [StreamEndpoint]'s Request [Key] is derived from
+///
+/// Never sent, the Request itself is. Keying streaming requests as this type
+/// keeps them apart from a plain [Endpoint] with the same path and Request
+/// type, so neither kind of server answers the other kind of client.
+#[derive(Debug, Schema)]
+pub struct StreamRequest<T>(pub T);
+
 /// A marker trait denoting a single topic
 ///
 /// Unlike [Endpoint]s, [Topic]s are unidirectional, and can be sent
@@ -131,6 +161,59 @@ macro_rules! endpoint {
     };
 }
 
+/// ## Stream Endpoint macro
+///
+/// Used to define a single streaming Endpoint marker type that implements the
+/// [StreamEndpoint][crate::traits::StreamEndpoint] trait, as well as the
+/// [Endpoint][crate::traits::Endpoint] trait with a
+/// [StreamItem][crate::traits::StreamItem] response.
+///
+/// The request and item types must be owned.
+///
+/// ```rust
+/// # use postcard_schema::Schema;
+/// # use serde::{Serialize, Deserialize};
+/// use ergot::stream_endpoint;
+///
+/// #[derive(Debug, Serialize, Deserialize, Schema)]
+/// pub struct ListReq {
+///     first: u32,
+///     count: u32,
+/// }
+///
+/// stream_endpoint!(ListEndpoint, ListReq, u32, "list/u32");
+/// ```
+///
+/// If the path is omitted, the type name is used instead.
+#[macro_export]
+macro_rules! stream_endpoint {
+    ($tyname:ident, $req:tt, $item:tt $(,)?) => {
+        $crate::stream_endpoint!($tyname, $req, $item, stringify!($tyname));
+    };
+    ($tyname:ident, $req:tt, $item:tt, $path:expr $(,)?) => {
+        /// A streaming Endpoint definition type
+        ///
+        /// Generated by the `stream_endpoint!()` macro
+        pub struct $tyname {
+            _priv: core::marker::PhantomData<()>,
+        }
+
+        impl $crate::traits::Endpoint for $tyname {
+            type Request = $req;
+            type Response = $crate::traits::StreamItem<$item>;
+            const PATH: &'static str = $path;
+            const REQ_KEY: $crate::traits::Key =
+                $crate::traits::Key::for_path::<$crate::traits::StreamRequest<$req>>($path);
+            const RESP_KEY: $crate::traits::Key =
+                $crate::traits::Key::for_path::<$crate::traits::StreamItem<$item>>($path);
+        }
+
+        impl $crate::traits::StreamEndpoint for $tyname {
+            type Item = $item;
+        }
+    };
+}
+
 /// ## Topic macro
 ///
 /// Used to define a single Topic marker type that implements the
@@ -207,7 +290,7 @@ mod test {
     use postcard_schema::Schema;
     use serde::{Deserialize, Serialize};
 
-    use crate::traits::{Endpoint, Topic};
+    use crate::traits::{Endpoint, StreamEndpoint, Topic};
 
     #[derive(Schema, Debug, PartialEq, Serialize, Deserialize)]
     pub struct Stir<'a> {
@@ -277,4 +360,23 @@ mod test {
         assert_eq!(ReqBRespBEp3::PATH, "ReqBRespBEp3");
         assert_eq!(ReqBRespBEp4::PATH, "ReqBRespBEp4");
     }
+
+    #[test]
+    fn test_stream_endpoint_macro() {
+        stream_endpoint!(StreamEp1, u32, u8, "stream1/ep");
+        stream_endpoint!(StreamEp2, u32, u8, "stream2/ep",);
+        stream_endpoint!(StreamEp3, u32, u8);
+        stream_endpoint!(StreamEp4, u32, u8,);
+        assert_eq!(StreamEp1::PATH, "stream1/ep");
+        assert_eq!(StreamEp2::PATH, "stream2/ep");
+        assert_eq!(StreamEp3::PATH, "StreamEp3");
+        assert_eq!(StreamEp4::PATH, "StreamEp4");
+
+        // Streaming and plain endpoints with the same path and types don't
+        // share keys, in either direction
+        endpoint!(PlainEp1, u32, u8, "stream1/ep");
+        assert_ne!(StreamEp1::REQ_KEY, PlainEp1::REQ_KEY);
+        assert_ne!(StreamEp1::RESP_KEY, PlainEp1::RESP_KEY);
+        let _: Option<<StreamEp1 as StreamEndpoint>::Item> = None::<u8>;
+    }
 }
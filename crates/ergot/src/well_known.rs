@@ -1,4 +1,7 @@
 use postcard_schema::Schema;
+use postcard_schema::schema::NamedType;
+#[cfg(feature = "std")]
+use postcard_schema::schema::owned::OwnedNamedType;
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "std")]
@@ -11,11 +14,12 @@ use crate::logging::defmtlog::ErgotDefmtRxOwned;
 use crate::logging::defmtlog::{ErgotDefmtRx, ErgotDefmtTx};
 
 use crate::interface_manager::{
-    AddressClaimError, AddressRefreshError, NodeClaimAssignment, SeedAssignmentError,
-    SeedNetAssignment, SeedRefreshError,
+    AddressClaimError, AddressRefreshError, InterfaceState, NodeClaimAssignment,
+    SeedAssignmentError, SeedNetAssignment, SeedRefreshError, admission::Credentials,
 };
 use crate::nash::NameHash;
-use crate::{Address, FrameKind, endpoint, topic};
+use crate::stats::{InterfaceStats, SocketStats};
+use crate::{Address, FrameKind, Key, endpoint, topic};
 
 endpoint!(ErgotPingEndpoint, u32, u32, "ergot/.well-known/ping");
 
@@ -79,7 +83,7 @@ pub type SeedRouterRefreshResponse = Result<SeedNetAssignment, SeedRefreshError>
 pub type SeedRouterReleaseResponse = Result<(), SeedRefreshError>;
 endpoint!(
     ErgotSeedRouterAssignmentEndpoint,
-    (),
+    SeedRouterAssignmentRequest,
     SeedRouterAssignmentResponse,
     "ergot/.well-known/seed-router/request"
 );
@@ -143,6 +147,14 @@ pub struct SeedRouterAssignment {
     pub release_port: u8,
 }
 
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct SeedRouterAssignmentRequest {
+    /// Checked by the seed router's admission policy, see
+    /// [`admission`](crate::interface_manager::admission)
+    pub credentials: Option<Credentials>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
 #[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
 pub struct SeedRouterRefreshRequest {
@@ -179,6 +191,9 @@ endpoint!(
 pub struct AddressClaimRequest {
     pub candidate_node_id: u8,
     pub nonce: u64,
+    /// Checked by the router's admission policy, see
+    /// [`admission`](crate::interface_manager::admission)
+    pub credentials: Option<Credentials>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
@@ -214,3 +229,367 @@ pub struct PathMtuQuery {
 pub struct PathMtuResult {
     pub path_mtu: u16,
 }
+
+// Subscription advertisements
+topic!(
+    ErgotSubscriptionsTopic,
+    SubscriptionAdvertisement,
+    "ergot/.well-known/subscriptions"
+);
+
+/// The maximum number of topics in a [`SubscriptionAdvertisement`]
+///
+/// Devices subscribing to more topics send an advertisement that is not
+/// `complete`, so that every broadcast is still sent to them.
+pub const MAX_ADVERTISED_KEYS: usize = 16;
+
+/// The topics subscribed to by the sender of the advertisement, and by the
+/// devices behind it, see
+/// [`subscriptions`](crate::interface_manager::subscriptions)
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct SubscriptionAdvertisement {
+    /// How long the receiver may rely on this advertisement, in seconds
+    pub valid_secs: u16,
+    /// `false` if `keys` may be missing some of the subscribed topics, in
+    /// which case the receiver must send every broadcast
+    pub complete: bool,
+    /// The keys of the subscribed topics
+    pub keys: heapless::Vec<Key, MAX_ADVERTISED_KEYS>,
+}
+
+// Remote subscriptions
+pub type RemoteSubscriptionResponse = Result<RemoteSubscriptionLease, RemoteSubscriptionError>;
+endpoint!(
+    ErgotRemoteSubscriptionEndpoint,
+    RemoteSubscriptionRequest,
+    RemoteSubscriptionResponse,
+    "ergot/.well-known/remote-subscription"
+);
+
+/// A request to a [`Publisher`], which serves [`ErgotRemoteSubscriptionEndpoint`]
+/// under the path of its topic as name
+///
+/// The subscriber is the socket at `port`, on the device that sent the request.
+///
+/// [`Publisher`]: crate::net_stack::publisher::Publisher
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub enum RemoteSubscriptionRequest {
+    /// Start sending the topic `key` to `port`, replacing any existing
+    /// subscription of that socket
+    Subscribe { key: Key, port: u8 },
+    /// Extend the subscription of `port`
+    Refresh {
+        key: Key,
+        port: u8,
+        refresh_token: [u8; 8],
+    },
+    /// Stop sending the topic to `port`. The response has an
+    /// `expires_seconds` of zero.
+    Unsubscribe {
+        key: Key,
+        port: u8,
+        refresh_token: [u8; 8],
+    },
+}
+
+/// A successful subscription or refresh
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct RemoteSubscriptionLease {
+    /// How many seconds from NOW does the subscription expire?
+    pub expires_seconds: u16,
+    /// What is the LONGEST time that the publisher will extend a subscription for?
+    pub max_refresh_seconds: u16,
+    /// Don't ask to refresh until we are < this many seconds from the expiration time
+    pub min_refresh_seconds: u16,
+    /// The unique token to be used for the next refresh or unsubscribe request
+    pub refresh_token: [u8; 8],
+}
+
+/// An error occurred when handling a [`RemoteSubscriptionRequest`]
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub enum RemoteSubscriptionError {
+    /// The publisher does not publish the requested topic
+    UnknownTopic,
+    /// The publisher already has as many subscribers as it can hold
+    TableFull,
+    /// The socket has no subscription to refresh or cancel
+    NotSubscribed,
+    /// The subscription has already expired
+    AlreadyExpired,
+    /// The refresh token did not match
+    BadRequest,
+    /// The request to refresh violated the min_refresh_seconds time
+    TooSoon,
+}
+
+// Retained messages
+endpoint!(
+    ErgotRetainedEndpoint,
+    RetainedRequest,
+    bool,
+    "ergot/.well-known/retained"
+);
+
+/// A request for the retained message of a topic, see
+/// [`retained`](crate::net_stack::retained)
+///
+/// The response is `true` if a retained message was sent to the socket at
+/// `port`, on the device that sent the request.
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct RetainedRequest {
+    pub key: Key,
+    pub port: u8,
+}
+
+// Topic history
+pub type TopicHistoryResponse = Result<TopicHistoryEntry, TopicHistoryError>;
+endpoint!(
+    ErgotTopicHistoryEndpoint,
+    TopicHistoryRequest,
+    TopicHistoryResponse,
+    "ergot/.well-known/topic-history"
+);
+
+/// A request for the oldest message of the topic `key` recorded by a
+/// [`TopicHistory`], within both the sequence range and the time range
+///
+/// Both ranges are inclusive. Times are in milliseconds since the history was
+/// created, on the clock of the device holding it.
+///
+/// [`TopicHistory`]: crate::net_stack::history::TopicHistory
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct TopicHistoryRequest {
+    pub key: Key,
+    pub first_seq: u32,
+    pub last_seq: u32,
+    pub from_ms: u64,
+    pub to_ms: u64,
+}
+
+/// The longest message a [`TopicHistoryEntry`] can hold
+pub const MAX_HISTORY_BODY_LEN: usize = 192;
+
+/// A recorded message
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct TopicHistoryEntry {
+    /// The sequence number of the message, counting all messages of the
+    /// topic received since the history was created
+    pub seq: u32,
+    /// When the message was received, in milliseconds since the history was
+    /// created
+    pub rx_ms: u64,
+    /// When this response was sent, on the same clock as `rx_ms`
+    pub now_ms: u64,
+    /// The sender of the message
+    pub src: Address,
+    /// The serialized message, or `None` if it is longer than
+    /// [`MAX_HISTORY_BODY_LEN`]
+    pub body: Option<heapless::Vec<u8, MAX_HISTORY_BODY_LEN>>,
+}
+
+/// An error occurred when handling a [`TopicHistoryRequest`]
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub enum TopicHistoryError {
+    /// The topic is not recorded
+    UnknownTopic,
+    /// No recorded message is within the requested ranges
+    NoMatch,
+}
+
+// Schema introspection
+pub type SchemaResponseTx<'a> = Option<SocketSchemaTx<'a>>;
+endpoint!(
+    ErgotSchemaTxEndpoint,
+    SchemaQuery,
+    SchemaResponseTx<'a>,
+    "ergot/.well-known/schema"
+);
+
+#[cfg(feature = "std")]
+pub type SchemaResponseRxOwned = Option<SocketSchemaRxOwned>;
+#[cfg(feature = "std")]
+endpoint!(
+    ErgotSchemaRxOwnedEndpoint,
+    SchemaQuery,
+    SchemaResponseRxOwned,
+    "ergot/.well-known/schema"
+);
+
+/// A request for the `index`-th message type of the topic receivers and
+/// endpoint servers of a device
+///
+/// Each topic receiver has a single message type, and each endpoint server
+/// has two: its requests, and its responses. The response is `None` once
+/// `index` is past the last message type.
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct SchemaQuery {
+    pub index: u16,
+}
+
+/// A message type of a socket, for sending
+///
+/// Type-punned with `SocketSchemaRxOwned` (with the `std` feature enabled).
+#[derive(Debug, Serialize, Schema, Clone)]
+pub struct SocketSchemaTx<'a> {
+    /// The port of the socket
+    pub port: u8,
+    /// The name of the socket, if any
+    pub name: Option<NameHash>,
+    /// The kind of frames carrying this message type: [`FrameKind::TOPIC_MSG`],
+    /// [`FrameKind::ENDPOINT_REQ`] or [`FrameKind::ENDPOINT_RESP`]
+    pub kind: FrameKind,
+    /// The key of frames carrying this message type
+    pub key: Key,
+    /// The path of the topic or endpoint
+    pub path: &'a str,
+    /// The schema of the message type, or `None` if it is too large to send
+    pub ty: Option<&'a NamedType>,
+}
+
+/// A message type of a socket, for receiving
+///
+/// Type-punned with [`SocketSchemaTx`].
+#[cfg(feature = "std")]
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+pub struct SocketSchemaRxOwned {
+    pub port: u8,
+    pub name: Option<NameHash>,
+    pub kind: FrameKind,
+    pub key: Key,
+    pub path: String,
+    pub ty: Option<OwnedNamedType>,
+}
+
+// Traffic counters
+endpoint!(
+    ErgotStatsEndpoint,
+    StatsQuery,
+    StatsResponse,
+    "ergot/.well-known/stats"
+);
+
+/// A request for the counters of the `index`-th interface or socket of a
+/// device, see [`crate::stats`]
+///
+/// The response is `None` once `index` is past the last interface or socket.
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub enum StatsQuery {
+    Interface { index: u16 },
+    Socket { index: u16 },
+}
+
+/// The response to a [`StatsQuery`]
+pub type StatsResponse = Option<Stats>;
+
+/// The counters of an interface or socket
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub enum Stats {
+    Interface(InterfaceStats),
+    Socket(SocketStats),
+}
+
+// Router introspection
+endpoint!(
+    ErgotRouterEndpoint,
+    RouterQuery,
+    RouterResponse,
+    "ergot/.well-known/router"
+);
+
+/// A request for one entry of the state of a router
+///
+/// The response is `None` once `index` is past the last entry of the table,
+/// for `Upstream` if the router has no upstream interface, and for any query
+/// if the device isn't a router.
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub enum RouterQuery {
+    /// The `index`-th downstream interface
+    Slot { index: u16 },
+    /// The `index`-th net_id assigned by, or delegated to, the router
+    SeedRoute { index: u16 },
+    /// The `index`-th node_id claimed on a bus of the router
+    NodeClaim { index: u16 },
+    /// The upstream interface of a bridge router
+    Upstream,
+}
+
+/// The response to a [`RouterQuery`]
+pub type RouterResponse = Option<RouterEntry>;
+
+/// One entry of the state of a router
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub enum RouterEntry {
+    Slot(SlotEntry),
+    SeedRoute(SeedRouteEntry),
+    NodeClaim(NodeClaimEntry),
+    Upstream(UpstreamEntry),
+}
+
+/// A downstream interface of a router
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct SlotEntry {
+    /// The ident of the interface
+    pub ident: u8,
+    /// The net_id of the interface, `0` while it waits for a seed-assigned
+    /// net_id
+    pub net_id: u16,
+    pub state: InterfaceState,
+}
+
+/// A net_id assigned by a router to a downstream bridge, or delegated to it
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct SeedRouteEntry {
+    /// The assigned net_id
+    pub net_id: u16,
+    /// The net_id the assignment was requested from
+    pub source_net_id: u16,
+    /// The ident of the downstream interface the network is reachable
+    /// through
+    pub via_ident: u8,
+    /// `true` if the net_id is leased from an upstream seed router, rather
+    /// than by this router
+    pub delegated: bool,
+    pub lease: LeaseEntryState,
+}
+
+/// A node_id claimed on a bus
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct NodeClaimEntry {
+    pub node_id: u8,
+    /// The net_id of the bus
+    pub net_id: u16,
+    pub lease: LeaseEntryState,
+}
+
+/// The upstream interface of a bridge router
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub struct UpstreamEntry {
+    pub state: InterfaceState,
+}
+
+/// The state of a seed route or node claim lease
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+pub enum LeaseEntryState {
+    /// The lease expires in `remaining_secs`, unless it is refreshed
+    Active { remaining_secs: u16 },
+    /// The lease expired, and the id stays reserved for `clear_secs`
+    Tombstone { clear_secs: u16 },
+}
@@ -1,8 +1,59 @@
 use crate::logging::warn;
 use postcard::{Serializer, ser_flavors};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, ser::SerializeTuple};
 
-use crate::{Address, AnyAllAppendix, FrameKind, HeaderSeq, Key, ProtocolError, nash::NameHash};
+use crate::{
+    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, HeaderSeq, Key, Priority, ProtocolError,
+    nash::NameHash,
+};
+
+/// How the [`CommonHeader`] of a frame is laid out on the wire
+///
+/// There is no way to tell the encodings apart on the wire, so both ends of
+/// a link must be configured with the same encoding. The encoding only applies
+/// to a single link: routers decode frames with the encoding of the receiving
+/// interface, and encode them again with the encoding of the sending one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeaderEncoding {
+    /// The [`CommonHeader`], serialized with postcard
+    #[default]
+    Standard,
+    /// A bit-packed form of the [`CommonHeader`], for links with a tiny MTU
+    ///
+    /// ```text
+    /// flags: u8                    1 byte
+    ///   bits 0..=2: kind, or 7 if a kind byte follows
+    ///   bit 3:      dst network is the same as the src network
+    ///   bit 4:      a ttl byte follows, otherwise DEFAULT_TTL
+    ///   bits 5..=6: priority
+    ///   bit 7:      a priority byte follows, bits 5..=6 are ignored
+    /// src.network_id: u16, varint  1..=3 bytes
+    /// dst.network_id: u16, varint  0..=3 bytes
+    /// src.node_id: u8              1 byte
+    /// src.port_id: u8              1 byte
+    /// dst.node_id: u8              1 byte
+    /// dst.port_id: u8              1 byte
+    /// seq_no: u16, varint          1..=3 bytes
+    /// kind: u8                     0..=1 byte
+    /// ttl: u8                      0..=1 byte
+    /// prio: u8                     0..=1 byte
+    /// ```
+    ///
+    /// A frame within one network, with the default ttl, and a kind and
+    /// priority that fit in the flags, has a common header of 7 to 11 bytes.
+    /// The standard encoding takes up to 5 bytes for each address alone, as
+    /// the network id is stored in the most significant bits.
+    Compact,
+}
+
+mod compact {
+    pub const KIND_MASK: u8 = 0b0000_0111;
+    pub const SAME_NET: u8 = 1 << 3;
+    pub const HAS_TTL: u8 = 1 << 4;
+    pub const PRIO_SHIFT: u32 = 5;
+    pub const PRIO_MASK: u8 = 0b0110_0000;
+    pub const HAS_PRIO: u8 = 1 << 7;
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CommonHeader {
@@ -12,9 +63,113 @@ pub struct CommonHeader {
     pub seq_no: u16,
     pub kind: FrameKind,
     pub ttl: u8,
+    // Added in 0.13, older versions can't decode this header
+    pub prio: Priority,
     // WARNING: Update MAX_HDR_ENCODED_SIZE if you add/remove anything here!
 }
 
+impl CommonHeader {
+    fn serialize_compact<F>(&self, ser: &mut Serializer<F>) -> Result<(), EncodeFrameError>
+    where
+        F: ser_flavors::Flavor,
+    {
+        let same_net = self.src.network_id == self.dst.network_id;
+        let inline_kind = self.kind.0 < compact::KIND_MASK;
+        let inline_prio = self.prio.0 <= (compact::PRIO_MASK >> compact::PRIO_SHIFT);
+        let has_ttl = self.ttl != DEFAULT_TTL;
+
+        let mut flags = if inline_kind {
+            self.kind.0
+        } else {
+            compact::KIND_MASK
+        };
+        if same_net {
+            flags |= compact::SAME_NET;
+        }
+        if has_ttl {
+            flags |= compact::HAS_TTL;
+        }
+        if inline_prio {
+            flags |= self.prio.0 << compact::PRIO_SHIFT;
+        } else {
+            flags |= compact::HAS_PRIO;
+        }
+
+        ser.output.try_push(flags)?;
+        self.src.network_id.serialize(&mut *ser)?;
+        if !same_net {
+            self.dst.network_id.serialize(&mut *ser)?;
+        }
+        ser.output.try_extend(&[
+            self.src.node_id,
+            self.src.port_id,
+            self.dst.node_id,
+            self.dst.port_id,
+        ])?;
+        self.seq_no.serialize(&mut *ser)?;
+        if !inline_kind {
+            ser.output.try_push(self.kind.0)?;
+        }
+        if has_ttl {
+            ser.output.try_push(self.ttl)?;
+        }
+        if !inline_prio {
+            ser.output.try_push(self.prio.0)?;
+        }
+        Ok(())
+    }
+
+    fn take_compact(data: &[u8]) -> Option<(Self, &[u8])> {
+        fn take_u8(data: &[u8]) -> Option<(u8, &[u8])> {
+            let (first, rest) = data.split_first()?;
+            Some((*first, rest))
+        }
+
+        let (flags, remain) = take_u8(data)?;
+        let (src_net, remain) = postcard::take_from_bytes::<u16>(remain).ok()?;
+        let (dst_net, remain) = if flags & compact::SAME_NET != 0 {
+            (src_net, remain)
+        } else {
+            postcard::take_from_bytes::<u16>(remain).ok()?
+        };
+        let (nodes_ports, remain) = remain.split_first_chunk::<4>()?;
+        let [src_node, src_port, dst_node, dst_port] = *nodes_ports;
+        let (seq_no, remain) = postcard::take_from_bytes::<u16>(remain).ok()?;
+        let (kind, remain) = match flags & compact::KIND_MASK {
+            compact::KIND_MASK => take_u8(remain)?,
+            kind => (kind, remain),
+        };
+        let (ttl, remain) = if flags & compact::HAS_TTL != 0 {
+            take_u8(remain)?
+        } else {
+            (DEFAULT_TTL, remain)
+        };
+        let (prio, remain) = if flags & compact::HAS_PRIO != 0 {
+            take_u8(remain)?
+        } else {
+            ((flags & compact::PRIO_MASK) >> compact::PRIO_SHIFT, remain)
+        };
+
+        let hdr = Self {
+            src: Address {
+                network_id: src_net,
+                node_id: src_node,
+                port_id: src_port,
+            },
+            dst: Address {
+                network_id: dst_net,
+                node_id: dst_node,
+                port_id: dst_port,
+            },
+            seq_no,
+            kind: FrameKind(kind),
+            ttl,
+            prio: Priority(prio),
+        };
+        Some((hdr, remain))
+    }
+}
+
 impl From<&HeaderSeq> for CommonHeader {
     fn from(value: &HeaderSeq) -> Self {
         Self {
@@ -23,6 +178,7 @@ impl From<&HeaderSeq> for CommonHeader {
             seq_no: value.seq_no,
             kind: value.kind,
             ttl: value.ttl,
+            prio: value.prio,
         }
     }
 }
@@ -46,8 +202,14 @@ pub struct PartialDecode<'a> {
     pub hdr_raw: &'a [u8],
 }
 
-pub(crate) fn decode_frame_partial(data: &[u8]) -> Option<PartialDecode<'_>> {
-    let (common, remain) = postcard::take_from_bytes::<CommonHeader>(data).ok()?;
+pub(crate) fn decode_frame_partial(
+    data: &[u8],
+    encoding: HeaderEncoding,
+) -> Option<PartialDecode<'_>> {
+    let (common, remain) = match encoding {
+        HeaderEncoding::Standard => postcard::take_from_bytes::<CommonHeader>(data).ok()?,
+        HeaderEncoding::Compact => CommonHeader::take_compact(data)?,
+    };
     let is_err = common.kind == FrameKind::PROTOCOL_ERROR;
     let any_all = [0, 255].contains(&common.dst.port_id);
 
@@ -113,40 +275,228 @@ impl From<postcard::Error> for EncodeFrameError {
         Self::SerializationError(value)
     }
 }
-/// The largest encoded size of a header, usable for creating a max-sized buffer
+/// The largest encoded size of a header in any [`HeaderEncoding`], usable for
+/// creating a max-sized buffer
 ///
 /// ```text
-/// CommonHeader=================================
+/// Standard CommonHeader========================
 /// src: Address,            u32, varint: 5 bytes
 /// dst: Address,            u32, varint: 5 bytes
 /// seq_no: u16,             u16, varint: 3 bytes
 /// kind: FrameKind,         u8, !varint: 1 byte
 /// ttl: u8,                 u8, !varint: 1 byte
+/// prio: Priority,          u8, !varint: 1 byte
+/// ==================================== 16 bytes
+/// Compact CommonHeader=========================
+/// flags:                   u8, !varint: 1 byte
+/// src.network_id:          u16, varint: 3 bytes
+/// dst.network_id:          u16, varint: 3 bytes
+/// nodes and ports:         [u8; 4]:     4 bytes
+/// seq_no:                  u16, varint: 3 bytes
+/// kind:                    u8, !varint: 1 byte
+/// ttl:                     u8, !varint: 1 byte
+/// prio:                    u8, !varint: 1 byte
+/// ==================================== 17 bytes
 /// AnyAllAppendix===============================
 /// key: Key,                [u8; 8]:     8 bytes
 /// nash: Option<NameHash>,  u32, varint: 5 bytes
-/// ==================================== 28 bytes
+/// ==================================== 13 bytes
+/// Total, largest CommonHeader + AnyAllAppendix
+/// ==================================== 30 bytes
 /// ```
 //
 // TODO: A more automatic way of handling this. This is currently tested with a
 // unit test below.
-pub const MAX_HDR_ENCODED_SIZE: usize = 28;
+pub const MAX_HDR_ENCODED_SIZE: usize = 30;
 
-/// Encode the frame header to the given serializer
+/// The header of a [`FrameKind::FRAGMENT`] frame
+///
+/// A message too large for the MTU of an interface may be sent as a series
+/// of fragments. The reassembled payload is the original [`FrameKind`] as a
+/// single byte, followed by the serialized body. The outer frame header
+/// (addresses, any/all appendix, seq_no) is the same for every fragment of
+/// a message, only the `kind` is replaced with [`FrameKind::FRAGMENT`].
+#[cfg_attr(feature = "defmt-v1", derive(defmt::Format))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FragmentHeader {
+    // WARNING: Update MAX_FRAG_HDR_ENCODED_SIZE if you add/remove anything here!
+    /// Identifies the message, unique per source address
+    pub msg_id: u16,
+    /// The offset of this fragment's data in the reassembled payload
+    pub offset: u16,
+    /// Are there more fragments after this one?
+    pub more: bool,
+    // WARNING: Update MAX_FRAG_HDR_ENCODED_SIZE if you add/remove anything here!
+}
+
+/// The body of a [`FrameKind::FRAGMENT`] frame
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Fragment<'a> {
+    pub hdr: FragmentHeader,
+    pub data: &'a [u8],
+}
+
+/// The largest encoded size of a fragment header, including the length
+/// prefix of the fragment data
+///
+/// ```text
+/// FragmentHeader===============================
+/// msg_id: u16,             u16, varint: 3 bytes
+/// offset: u16,             u16, varint: 3 bytes
+/// more: bool,              bool:        1 byte
+/// Fragment=====================================
+/// data.len(),              u16, varint: 3 bytes
+/// ==================================== 10 bytes
+/// ```
+pub const MAX_FRAG_HDR_ENCODED_SIZE: usize = 10;
+
+/// The largest amount of fragment data that fits in a frame of `mtu` bytes
+///
+/// Returns `None` if the `mtu` is too small to carry any fragment data.
+pub fn fragment_data_len(mtu: u16) -> Option<usize> {
+    let len = (mtu as usize).checked_sub(MAX_HDR_ENCODED_SIZE + MAX_FRAG_HDR_ENCODED_SIZE)?;
+    (len != 0).then_some(len)
+}
+
+/// Already serialized bytes, which are serialized as-is
+pub(crate) struct PreSerialized<'a>(pub(crate) &'a [u8]);
+
+impl Serialize for PreSerialized<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // postcard encodes tuples without a length, and `u8`s as one byte
+        let mut tup = serializer.serialize_tuple(self.0.len())?;
+        for b in self.0 {
+            tup.serialize_element(b)?;
+        }
+        tup.end()
+    }
+}
+
+/// Decode the body of a [`FrameKind::FRAGMENT`] frame
+pub fn decode_fragment(body: &[u8]) -> Option<Fragment<'_>> {
+    postcard::from_bytes(body).ok()
+}
+
+impl HeaderEncoding {
+    /// Encode the frame header to the given serializer
+    pub fn encode_frame_hdr<F>(
+        self,
+        ser: &mut Serializer<F>,
+        hdr: &HeaderSeq,
+    ) -> Result<(), EncodeFrameError>
+    where
+        F: ser_flavors::Flavor,
+    {
+        self.encode_common(ser, &hdr.into())?;
+
+        if let Some(app) = hdr.any_all.as_ref() {
+            ser.output.try_extend(&app.key.0)?;
+            let val: u32 = app.nash.as_ref().map(NameHash::to_u32).unwrap_or(0);
+            val.serialize(ser)?;
+        }
+
+        Ok(())
+    }
+
+    // must not be error
+    // doesn't check if dest is actually any/all
+    pub fn encode_frame_ty<F, T>(
+        self,
+        flav: F,
+        hdr: &HeaderSeq,
+        body: &T,
+    ) -> Result<F::Output, EncodeFrameError>
+    where
+        F: ser_flavors::Flavor,
+        T: Serialize,
+    {
+        let mut serializer = Serializer { output: flav };
+        self.encode_frame_hdr(&mut serializer, hdr)?;
+
+        body.serialize(&mut serializer)?;
+        Ok(serializer.output.finalize()?)
+    }
+
+    pub fn encode_frame_err<F>(
+        self,
+        flav: F,
+        hdr: &HeaderSeq,
+        err: ProtocolError,
+    ) -> Result<F::Output, EncodeFrameError>
+    where
+        F: ser_flavors::Flavor,
+    {
+        let mut serializer = Serializer { output: flav };
+        self.encode_common(&mut serializer, &hdr.into())?;
+        err.serialize(&mut serializer)?;
+        Ok(serializer.output.finalize()?)
+    }
+
+    pub fn de_frame(self, remain: &[u8]) -> Option<BorrowedFrame<'_>> {
+        let res = decode_frame_partial(remain, self)?;
+
+        let app;
+        let body = match res.tail {
+            PartialDecodeTail::Specific(body) => {
+                app = None;
+                Ok(body)
+            }
+            PartialDecodeTail::AnyAll { apdx, body } => {
+                app = Some(apdx);
+                Ok(body)
+            }
+            PartialDecodeTail::Err(protocol_error) => {
+                app = None;
+                Err(protocol_error)
+            }
+        };
+
+        let CommonHeader {
+            src,
+            dst,
+            seq_no,
+            kind,
+            ttl,
+            prio,
+        } = res.hdr;
+
+        Some(BorrowedFrame {
+            hdr: HeaderSeq {
+                src,
+                dst,
+                seq_no,
+                any_all: app,
+                kind,
+                ttl,
+                prio,
+            },
+            body,
+        })
+    }
+
+    fn encode_common<F>(
+        self,
+        ser: &mut Serializer<F>,
+        chdr: &CommonHeader,
+    ) -> Result<(), EncodeFrameError>
+    where
+        F: ser_flavors::Flavor,
+    {
+        match self {
+            HeaderEncoding::Standard => chdr.serialize(&mut *ser)?,
+            HeaderEncoding::Compact => chdr.serialize_compact(ser)?,
+        }
+        Ok(())
+    }
+}
+
+/// Encode the frame header to the given serializer, with the
+/// [`HeaderEncoding::Standard`] encoding
 pub fn encode_frame_hdr<F>(ser: &mut Serializer<F>, hdr: &HeaderSeq) -> Result<(), EncodeFrameError>
 where
     F: ser_flavors::Flavor,
 {
-    let chdr: CommonHeader = hdr.into();
-    chdr.serialize(&mut *ser)?;
-
-    if let Some(app) = hdr.any_all.as_ref() {
-        ser.output.try_extend(&app.key.0)?;
-        let val: u32 = app.nash.as_ref().map(NameHash::to_u32).unwrap_or(0);
-        val.serialize(ser)?;
-    }
-
-    Ok(())
+    HeaderEncoding::Standard.encode_frame_hdr(ser, hdr)
 }
 
 // must not be error
@@ -160,11 +510,7 @@ where
     F: ser_flavors::Flavor,
     T: Serialize,
 {
-    let mut serializer = Serializer { output: flav };
-    encode_frame_hdr(&mut serializer, hdr)?;
-
-    body.serialize(&mut serializer)?;
-    Ok(serializer.output.finalize()?)
+    HeaderEncoding::Standard.encode_frame_ty(flav, hdr, body)
 }
 
 pub fn encode_frame_err<F>(
@@ -175,51 +521,11 @@ pub fn encode_frame_err<F>(
 where
     F: ser_flavors::Flavor,
 {
-    let mut serializer = Serializer { output: flav };
-    let chdr: CommonHeader = hdr.into();
-    chdr.serialize(&mut serializer)?;
-    err.serialize(&mut serializer)?;
-    Ok(serializer.output.finalize()?)
+    HeaderEncoding::Standard.encode_frame_err(flav, hdr, err)
 }
 
 pub fn de_frame(remain: &[u8]) -> Option<BorrowedFrame<'_>> {
-    let res = decode_frame_partial(remain)?;
-
-    let app;
-    let body = match res.tail {
-        PartialDecodeTail::Specific(body) => {
-            app = None;
-            Ok(body)
-        }
-        PartialDecodeTail::AnyAll { apdx, body } => {
-            app = Some(apdx);
-            Ok(body)
-        }
-        PartialDecodeTail::Err(protocol_error) => {
-            app = None;
-            Err(protocol_error)
-        }
-    };
-
-    let CommonHeader {
-        src,
-        dst,
-        seq_no,
-        kind,
-        ttl,
-    } = res.hdr;
-
-    Some(BorrowedFrame {
-        hdr: HeaderSeq {
-            src,
-            dst,
-            seq_no,
-            any_all: app,
-            kind,
-            ttl,
-        },
-        body,
-    })
+    HeaderEncoding::Standard.de_frame(remain)
 }
 
 pub struct BorrowedFrame<'a> {
@@ -232,11 +538,21 @@ mod test {
     use postcard::{Serializer, ser_flavors::Flavor};
 
     use crate::{
-        Address, AnyAllAppendix, FrameKind, HeaderSeq, Key, nash::NameHash,
-        wire_frames::MAX_HDR_ENCODED_SIZE,
+        Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, HeaderSeq, Key, Priority,
+        nash::NameHash, wire_frames::MAX_HDR_ENCODED_SIZE,
     };
 
-    use super::encode_frame_hdr;
+    use super::{
+        Fragment, FragmentHeader, HeaderEncoding, MAX_FRAG_HDR_ENCODED_SIZE, PreSerialized,
+        decode_fragment, fragment_data_len,
+    };
+
+    fn encoded_hdr(encoding: HeaderEncoding, hdr: &HeaderSeq) -> Vec<u8> {
+        let flav = postcard::ser_flavors::StdVec::new();
+        let mut ser = Serializer { output: flav };
+        encoding.encode_frame_hdr(&mut ser, hdr).unwrap();
+        ser.output.finalize().unwrap()
+    }
 
     #[test]
     fn max_hdr_ser_size() {
@@ -247,23 +563,121 @@ mod test {
                 node_id: u8::MAX,
                 port_id: u8::MAX,
             },
+            // Different networks, so the compact encoding includes both
             dst: Address {
-                network_id: u16::MAX,
+                network_id: u16::MAX - 1,
                 node_id: u8::MAX,
                 port_id: u8::MAX,
             },
             seq_no: u16::MAX,
             kind: FrameKind(u8::MAX),
             ttl: u8::MAX,
+            prio: Priority(u8::MAX),
             any_all: Some(AnyAllAppendix {
                 key: Key([0xFFu8; 8]),
                 nash: NameHash::from_u32(u32::MAX),
             }),
         };
-        let flav = postcard::ser_flavors::StdVec::new();
-        let mut ser = Serializer { output: flav };
-        encode_frame_hdr(&mut ser, &hdr).unwrap();
-        let res = ser.output.finalize().unwrap();
-        assert_eq!(res.len(), MAX_HDR_ENCODED_SIZE);
+        let standard = encoded_hdr(HeaderEncoding::Standard, &hdr).len();
+        let compact = encoded_hdr(HeaderEncoding::Compact, &hdr).len();
+        assert_eq!(standard, 29);
+        assert_eq!(compact, 30);
+        assert_eq!(standard.max(compact), MAX_HDR_ENCODED_SIZE);
+    }
+
+    #[test]
+    fn compact_hdr_roundtrip() {
+        let mut hdr = HeaderSeq {
+            src: Address {
+                network_id: 1,
+                node_id: 2,
+                port_id: 3,
+            },
+            dst: Address {
+                network_id: 1,
+                node_id: 1,
+                port_id: 0,
+            },
+            seq_no: 10,
+            kind: FrameKind::TOPIC_MSG,
+            ttl: DEFAULT_TTL,
+            prio: Priority::NORMAL,
+            any_all: Some(AnyAllAppendix {
+                key: Key([1, 2, 3, 4, 5, 6, 7, 8]),
+                nash: NameHash::from_u32(1234),
+            }),
+        };
+
+        let check = |hdr: &HeaderSeq| {
+            let mut frame = encoded_hdr(HeaderEncoding::Compact, hdr);
+            frame.extend_from_slice(&[42, 43]);
+            let res = HeaderEncoding::Compact.de_frame(&frame).unwrap();
+            assert_eq!(Header::from(res.hdr), Header::from(hdr.clone()));
+            assert_eq!(res.body, Ok(&[42u8, 43][..]));
+            frame.len() - 2
+        };
+
+        // flags, src net, 4x node/port, seq_no, and the any/all appendix
+        assert_eq!(check(&hdr), 7 + 8 + 2);
+        assert!(encoded_hdr(HeaderEncoding::Standard, &hdr).len() > 7 + 8 + 2);
+
+        // Everything that does not fit in the flags byte
+        hdr.dst.network_id = 300;
+        hdr.dst.port_id = 5;
+        hdr.any_all = None;
+        hdr.seq_no = u16::MAX;
+        hdr.kind = FrameKind::PROTOCOL_ERROR;
+        hdr.ttl = 3;
+        hdr.prio = Priority(200);
+        let mut frame = encoded_hdr(HeaderEncoding::Compact, &hdr);
+        assert_eq!(frame.len(), 1 + 1 + 2 + 4 + 3 + 3);
+        frame.push(0);
+        let res = HeaderEncoding::Compact.de_frame(&frame).unwrap();
+        assert_eq!(Header::from(res.hdr), Header::from(hdr));
+
+        // Truncated headers are rejected
+        for len in 0..frame.len() - 1 {
+            assert!(HeaderEncoding::Compact.de_frame(&frame[..len]).is_none());
+        }
+    }
+
+    #[test]
+    fn max_frag_hdr_ser_size() {
+        let data = [0u8; 1 << 14];
+        let frag = Fragment {
+            hdr: FragmentHeader {
+                msg_id: u16::MAX,
+                offset: u16::MAX,
+                more: true,
+            },
+            data: &data,
+        };
+        let res = postcard::to_stdvec(&frag).unwrap();
+        assert_eq!(res.len(), MAX_FRAG_HDR_ENCODED_SIZE + data.len());
+    }
+
+    #[test]
+    fn fragment_roundtrip() {
+        let frag = Fragment {
+            hdr: FragmentHeader {
+                msg_id: 1234,
+                offset: 300,
+                more: false,
+            },
+            data: &[1, 2, 3, 4],
+        };
+        let res = postcard::to_stdvec(&frag).unwrap();
+        assert_eq!(decode_fragment(&res), Some(frag));
+
+        assert_eq!(fragment_data_len(40), None);
+        assert_eq!(fragment_data_len(64), Some(24));
+    }
+
+    #[test]
+    fn pre_serialized_is_verbatim() {
+        let data = [0x00, 0xFF, 0x80, 0x7F, 0x01];
+        let mut buf = [0u8; 8];
+        let used = postcard::to_slice(&PreSerialized(&data), &mut buf).unwrap();
+        assert_eq!(used, &data);
     }
 }
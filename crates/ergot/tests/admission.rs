@@ -0,0 +1,169 @@
+//! End-to-end tests for admission control of bus address claims.
+//!
+//! A router with an admission policy only grants node_ids to the edges that
+//! the policy admits. Edges present their credentials with
+//! `DirectEdge::with_identity`.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::time::Duration;
+
+use ergot::{
+    interface_manager::{
+        AddressClaimError, InterfaceState,
+        admission::{AdmissionError, AdmissionPolicy, AllowList, DeviceIdentity},
+        interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::{
+            direct_edge::{DirectEdge, EdgeFrameProcessor},
+            router::Router,
+        },
+        transports::tokio_cobs_stream,
+        utils::{cobs_stream, std::new_std_queue},
+    },
+    net_stack::{
+        ArcNetStack,
+        services::{ClaimClientError, NodeClaimLease, bus_claim},
+    },
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use rand::SeedableRng;
+use tokio::time::timeout;
+
+const ALLOWED: &[u64] = &[0x1234, 0x5678];
+
+/// Start a router with the given admission policy, and an edge with the given
+/// identity connected to it, then claim node_id 47 for the edge.
+async fn claim_with<A>(
+    policy: A,
+    identity: Option<DeviceIdentity>,
+) -> Result<NodeClaimLease, ClaimClientError>
+where
+    A: AdmissionPolicy + Send + 'static,
+{
+    let router_stack = ArcNetStack::<CriticalSectionRawMutex, _>::new_with_profile(
+        Router::<TokioStreamInterface, _, 64, 64, 16>::new(rand::rngs::StdRng::from_seed(
+            [0u8; 32],
+        ))
+        .with_admission_policy(policy),
+    );
+
+    let edge_queue = new_std_queue(4096);
+    let mut edge =
+        DirectEdge::new_target(cobs_stream::Sink::new_from_handle(edge_queue.clone(), 512));
+    if let Some(identity) = identity {
+        edge = edge.with_identity(identity);
+    }
+    let edge_stack: common::EdgeStack = ArcNetStack::new_with_profile(edge);
+
+    let (e_read, r_write) = tokio::io::duplex(8192);
+    let (r_read, e_write) = tokio::io::duplex(8192);
+
+    tokio_cobs_stream::register_router(
+        router_stack.clone(),
+        r_read,
+        r_write,
+        512,
+        4096,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        edge_stack.clone(),
+        e_read,
+        e_write,
+        edge_queue,
+        EdgeFrameProcessor::new(),
+        InterfaceState::Active {
+            net_id: 0,
+            node_id: 47,
+        },
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    tokio::spawn({
+        let stack = router_stack.clone();
+        async move {
+            stack.services().address_claim_handler::<4>().await;
+        }
+    });
+
+    timeout(
+        Duration::from_secs(5),
+        bus_claim(&edge_stack, (), 47, 0xDEAD),
+    )
+    .await
+    .expect("claim timed out")
+}
+
+fn assert_not_admitted(res: Result<NodeClaimLease, ClaimClientError>) {
+    assert!(
+        matches!(
+            res,
+            Err(ClaimClientError::ClaimDenied(
+                AddressClaimError::NotAdmitted(AdmissionError::Denied)
+            ))
+        ),
+        "expected NotAdmitted, got {res:?}"
+    );
+}
+
+/// An edge on the allow list is granted its node_id.
+#[tokio::test]
+async fn allow_list_admits_listed_edge() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let lease = claim_with(AllowList::new(ALLOWED), Some(DeviceIdentity::new(0x5678)))
+        .await
+        .expect("listed edge should be admitted");
+    assert_eq!(lease.node_id, 47);
+    assert_eq!(lease.net_id, 1);
+}
+
+/// Edges that are not on the allow list, or send no credentials at all, are
+/// rejected.
+#[tokio::test]
+async fn allow_list_rejects_other_edges() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let res = claim_with(AllowList::new(ALLOWED), Some(DeviceIdentity::new(0x9999))).await;
+    assert_not_admitted(res);
+
+    let res = claim_with(AllowList::new(ALLOWED), None).await;
+    assert_not_admitted(res);
+}
+
+/// With a shared secret, the edge answers the router's challenge, and is
+/// granted its node_id. An edge with the wrong secret is rejected.
+#[cfg(feature = "signing")]
+#[tokio::test]
+async fn shared_secret_challenge_response() {
+    use ergot::interface_manager::admission::SharedSecret;
+
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let identity = DeviceIdentity::new(0x1234).with_shared_secret([7u8; 32]);
+    let lease = claim_with(SharedSecret::<4>::new([7u8; 32]), Some(identity))
+        .await
+        .expect("edge with the secret should be admitted");
+    assert_eq!(lease.node_id, 47);
+
+    let identity = DeviceIdentity::new(0x1234).with_shared_secret([8u8; 32]);
+    let res = claim_with(SharedSecret::<4>::new([7u8; 32]), Some(identity)).await;
+    assert_not_admitted(res);
+
+    // Without a secret, the challenge can't be answered at all
+    let res = claim_with(
+        SharedSecret::<4>::new([7u8; 32]),
+        Some(DeviceIdentity::new(0x1234)),
+    )
+    .await;
+    assert_not_admitted(res);
+}
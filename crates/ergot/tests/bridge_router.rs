@@ -8,7 +8,7 @@ use ergot::interface_manager::{
     Interface, InterfaceSendError, InterfaceSink, InterfaceState, Profile,
     profiles::router::{Router, UPSTREAM_IDENT},
 };
-use ergot::{Address, AnyAllAppendix, FrameKind, Header, HeaderSeq, Key, ProtocolError};
+use ergot::{Address, AnyAllAppendix, FrameKind, Header, HeaderSeq, Key, Priority, ProtocolError};
 use rand::SeedableRng;
 use serde::Serialize;
 use std::sync::{Arc, Mutex};
@@ -41,8 +41,8 @@ impl RecordingSink {
         }
     }
 
-    fn result(&self) -> Result<(), ()> {
-        if self.fail { Err(()) } else { Ok(()) }
+    fn result(&self) -> Result<usize, ()> {
+        if self.fail { Err(()) } else { Ok(0) }
     }
 }
 
@@ -50,21 +50,21 @@ impl InterfaceSink for RecordingSink {
     fn mtu(&self) -> u16 {
         2048
     }
-    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, _body: &T) -> Result<(), ()> {
+    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, _body: &T) -> Result<usize, ()> {
         self.log
             .lock()
             .unwrap()
             .push(format!("{}:send_ty:{}", self.label, hdr.dst));
         self.result()
     }
-    fn send_raw(&mut self, hdr: &HeaderSeq, _body: &[u8]) -> Result<(), ()> {
+    fn send_raw(&mut self, hdr: &HeaderSeq, _body: &[u8]) -> Result<usize, ()> {
         self.log
             .lock()
             .unwrap()
             .push(format!("{}:send_raw:{}", self.label, hdr.dst));
         self.result()
     }
-    fn send_err(&mut self, hdr: &HeaderSeq, _err: ProtocolError) -> Result<(), ()> {
+    fn send_err(&mut self, hdr: &HeaderSeq, _err: ProtocolError) -> Result<usize, ()> {
         self.log
             .lock()
             .unwrap()
@@ -109,6 +109,7 @@ fn make_hdr(src_net: u16, dst_net: u16, dst_node: u8, dst_port: u8) -> Header {
         seq_no: None,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     }
 }
 
@@ -131,6 +132,7 @@ fn make_broadcast_hdr() -> Header {
         seq_no: None,
         kind: FrameKind::TOPIC_MSG,
         ttl: 16,
+        prio: Priority::NORMAL,
     }
 }
 
@@ -340,6 +342,7 @@ fn bridge_broadcast_from_upstream_skips_upstream() {
         seq_no: 0,
         kind: FrameKind::TOPIC_MSG,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
 
     router.send_raw(&hdr, &[1, 2, 3], UPSTREAM_IDENT).unwrap();
@@ -390,6 +393,7 @@ fn bridge_raw_from_upstream_no_loop() {
         seq_no: 0,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
 
     let result = router.send_raw(&hdr, &[1, 2, 3], UPSTREAM_IDENT);
@@ -437,6 +441,7 @@ fn bridge_downstream_to_downstream_no_upstream() {
         seq_no: 100,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
 
     router.send_raw(&hdr, &[1, 2, 3], id0).unwrap();
@@ -485,6 +490,7 @@ fn bridge_send_err_forwards_upstream() {
         seq_no: None,
         kind: FrameKind::PROTOCOL_ERROR,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
 
     router
@@ -575,6 +581,7 @@ fn broadcast_raw_full_everywhere_reports_genuine_failure() {
         seq_no: 0,
         kind: FrameKind::TOPIC_MSG,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
 
     let res = router.send_raw(&hdr, &[1, 2, 3], UPSTREAM_IDENT);
@@ -12,7 +12,7 @@
 use std::sync::{Arc, Mutex};
 
 use ergot::{
-    Address, FrameKind, HeaderSeq, ProtocolError,
+    Address, FrameKind, HeaderSeq, Priority, ProtocolError,
     interface_manager::{
         FrameProcessor, Interface, InterfaceSink, Profile,
         profiles::router::{Router, RouterFrameProcessor},
@@ -35,17 +35,17 @@ impl InterfaceSink for CaptureSink {
     fn mtu(&self) -> u16 {
         2048
     }
-    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, _body: &T) -> Result<(), ()> {
+    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, _body: &T) -> Result<usize, ()> {
         self.frames.lock().unwrap().push((hdr.src, hdr.dst));
-        Ok(())
+        Ok(0)
     }
-    fn send_raw(&mut self, hdr: &HeaderSeq, _body: &[u8]) -> Result<(), ()> {
+    fn send_raw(&mut self, hdr: &HeaderSeq, _body: &[u8]) -> Result<usize, ()> {
         self.frames.lock().unwrap().push((hdr.src, hdr.dst));
-        Ok(())
+        Ok(0)
     }
-    fn send_err(&mut self, hdr: &HeaderSeq, _err: ProtocolError) -> Result<(), ()> {
+    fn send_err(&mut self, hdr: &HeaderSeq, _err: ProtocolError) -> Result<usize, ()> {
         self.frames.lock().unwrap().push((hdr.src, hdr.dst));
-        Ok(())
+        Ok(0)
     }
 }
 
@@ -74,6 +74,7 @@ fn make_frame(src_net: u16, src_node: u8, dst_net: u16, dst_node: u8, dst_port:
         seq_no: 0,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
     wire_frames::encode_frame_ty(postcard::ser_flavors::StdVec::new(), &hdr, &42u32).unwrap()
 }
@@ -116,7 +117,7 @@ fn unclaimed_node_frame_is_dropped_then_routed_after_claim() {
 
     // Now node_id=50 claims an address on net_id=1.
     let granted = stack
-        .manage_profile(|im| im.request_node_claim(1, 50, 0xABCD))
+        .manage_profile(|im| im.request_node_claim(1, 50, 0xABCD, None))
         .unwrap();
     assert_eq!(granted.node_id, 50);
 
@@ -195,7 +196,7 @@ fn claims_are_purged_when_interface_deregistered() {
     let bus_net = stack.manage_profile(|im| im.net_id_of(bus_ident)).unwrap();
 
     stack
-        .manage_profile(|im| im.request_node_claim(bus_net, 50, 0xAAAA))
+        .manage_profile(|im| im.request_node_claim(bus_net, 50, 0xAAAA, None))
         .unwrap();
     assert!(
         stack.manage_profile(|im| im.is_node_claimed(bus_net, 50)),
@@ -0,0 +1,114 @@
+//! Capture tests: the frames crossing the interfaces of a router and an edge
+//! are recorded by their capture taps, in both directions.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::sync::Mutex;
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address, FrameKind, Key,
+    interface_manager::{
+        InterfaceState,
+        capture::{CaptureTap, CapturedFrame},
+        filter::Direction,
+        interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::EdgeFrameProcessor,
+        profiles::router::Router,
+        transports::tokio_cobs_stream,
+    },
+    net_stack::ArcNetStack,
+    traits::Endpoint,
+    well_known::ErgotPingEndpoint,
+    wire_frames::de_frame,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use rand::SeedableRng;
+
+type RouterStack =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+const EDGE: Address = Address {
+    network_id: 1,
+    node_id: 2,
+    port_id: 0,
+};
+
+/// The interface, direction, kind and key of a frame
+type Record = (u8, Direction, FrameKind, Option<Key>);
+
+/// Keeps a [`Record`] of every frame recorded
+#[derive(Default)]
+struct Recorder(Mutex<Vec<Record>>);
+
+impl CaptureTap for Recorder {
+    fn record(&self, frame: &CapturedFrame<'_>) {
+        let decoded = de_frame(frame.data).expect("frames are recorded in the standard encoding");
+        let key = decoded.hdr.any_all.map(|apdx| apdx.key);
+        self.0
+            .lock()
+            .unwrap()
+            .push((frame.interface, frame.direction, decoded.hdr.kind, key));
+    }
+}
+
+impl Recorder {
+    fn contains(&self, direction: Direction, kind: FrameKind, key: Option<Key>) -> bool {
+        self.0.lock().unwrap().contains(&(0, direction, kind, key))
+    }
+}
+
+#[tokio::test]
+async fn router_and_edge_record_both_directions() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router_tap: &'static Recorder = Box::leak(Box::default());
+    let edge_tap: &'static Recorder = Box::leak(Box::default());
+
+    let router = RouterStack::new_with_profile(
+        Router::new(rand::rngs::StdRng::from_seed([0u8; 32])).with_capture(router_tap),
+    );
+    let (edge, edge_queue) = make_edge_stack();
+    edge.manage_profile(|im| im.set_capture(Some(edge_tap)));
+
+    let (e_read, r_write) = tokio::io::duplex(8192);
+    let (r_read, e_write) = tokio::io::duplex(8192);
+    tokio_cobs_stream::register_router(router.clone(), r_read, r_write, 512, 4096, None, None)
+        .await
+        .unwrap();
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        edge.clone(),
+        e_read,
+        e_write,
+        edge_queue,
+        EdgeFrameProcessor::new(),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    spawn_ping_server(&edge);
+    ping_with_retry(&router, EDGE, 1).await;
+    wait_active(&edge).await;
+
+    let ping = Some(Key(ErgotPingEndpoint::REQ_KEY.to_bytes()));
+    let req = FrameKind::ENDPOINT_REQ;
+    let resp = FrameKind::ENDPOINT_RESP;
+    assert!(router_tap.contains(Direction::Egress, req, ping));
+    assert!(edge_tap.contains(Direction::Ingress, req, ping));
+    // Responses are sent to a specific port, without a key
+    assert!(edge_tap.contains(Direction::Egress, resp, None));
+    assert!(router_tap.contains(Direction::Ingress, resp, None));
+
+    // Nothing is recorded once the taps are removed
+    router.manage_profile(|im| im.set_capture(None));
+    edge.manage_profile(|im| im.set_capture(None));
+    let recorded = router_tap.0.lock().unwrap().len() + edge_tap.0.lock().unwrap().len();
+    ping_with_retry(&router, EDGE, 2).await;
+    let now = router_tap.0.lock().unwrap().len() + edge_tap.0.lock().unwrap().len();
+    assert_eq!(recorded, now);
+}
@@ -0,0 +1,164 @@
+//! Compact header encoding tests: sink encoding and mixed-encoding routing.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use bbqueue::traits::bbqhdl::BbqHandle;
+use common::{EdgeStack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address, DEFAULT_TTL, FrameKind, HeaderSeq, Priority,
+    interface_manager::{
+        InterfaceSink, InterfaceState,
+        interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::{direct_edge::DirectEdge, direct_edge::EdgeFrameProcessor, router::Router},
+        transports::tokio_cobs_stream,
+        utils::{cobs_stream, std::new_std_queue},
+    },
+    net_stack::ArcNetStack,
+    wire_frames::HeaderEncoding,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+
+type RouterStackTy =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+/// Encode one frame with a sink using `encoding`, and return it without
+/// COBS framing
+fn sink_frame(encoding: HeaderEncoding, hdr: &HeaderSeq) -> Vec<u8> {
+    let queue = new_std_queue(1024);
+    let mut sink =
+        cobs_stream::Sink::new_from_handle(queue.clone(), 256).with_header_encoding(encoding);
+    sink.send_ty(hdr, &1234u32).unwrap();
+
+    let rgr = queue.stream_consumer().read().unwrap();
+    let mut frame = rgr.to_vec();
+    let len = cobs::decode_in_place(&mut frame).unwrap();
+    frame.truncate(len);
+    frame
+}
+
+#[test]
+fn sink_encodes_compact_headers() {
+    let hdr = HeaderSeq {
+        src: Address {
+            network_id: 1,
+            node_id: 2,
+            port_id: 10,
+        },
+        dst: Address {
+            network_id: 1,
+            node_id: 1,
+            port_id: 20,
+        },
+        any_all: None,
+        seq_no: 5,
+        kind: FrameKind::ENDPOINT_REQ,
+        ttl: DEFAULT_TTL,
+        prio: Priority::NORMAL,
+    };
+
+    let standard = sink_frame(HeaderEncoding::Standard, &hdr);
+    let compact = sink_frame(HeaderEncoding::Compact, &hdr);
+    assert!(compact.len() < standard.len());
+
+    let frame = HeaderEncoding::Compact.de_frame(&compact).unwrap();
+    assert_eq!(frame.hdr.src, hdr.src);
+    assert_eq!(frame.hdr.dst, hdr.dst);
+    assert_eq!(frame.hdr.seq_no, hdr.seq_no);
+    assert_eq!(frame.hdr.kind, hdr.kind);
+    assert_eq!(frame.body, Ok(&postcard::to_stdvec(&1234u32).unwrap()[..]));
+}
+
+#[tokio::test]
+async fn compact_and_standard_links_through_router() {
+    //  Edge1 <--compact--> Router <--standard--> Edge2
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router_stack: RouterStackTy = RouterStackTy::new();
+
+    let edge1_queue = new_std_queue(4096);
+    let edge1_stack = EdgeStack::new_with_profile(DirectEdge::new_target(
+        cobs_stream::Sink::new_from_handle(edge1_queue.clone(), 512)
+            .with_header_encoding(HeaderEncoding::Compact),
+    ));
+    let (edge2_stack, edge2_queue) = common::make_edge_stack();
+
+    let (e1_read, r1_write) = tokio::io::duplex(8192);
+    let (r1_read, e1_write) = tokio::io::duplex(8192);
+    let (e2_read, r2_write) = tokio::io::duplex(8192);
+    let (r2_read, e2_write) = tokio::io::duplex(8192);
+
+    tokio_cobs_stream::register_router_with_encoding(
+        router_stack.clone(),
+        r1_read,
+        r1_write,
+        512,
+        4096,
+        None,
+        None,
+        HeaderEncoding::Compact,
+    )
+    .await
+    .unwrap();
+    tokio_cobs_stream::register_router(
+        router_stack.clone(),
+        r2_read,
+        r2_write,
+        512,
+        4096,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        edge1_stack.clone(),
+        e1_read,
+        e1_write,
+        edge1_queue,
+        EdgeFrameProcessor::new().with_header_encoding(HeaderEncoding::Compact),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        edge2_stack.clone(),
+        e2_read,
+        e2_write,
+        edge2_queue,
+        EdgeFrameProcessor::new(),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    spawn_ping_server(&edge1_stack);
+    spawn_ping_server(&edge2_stack);
+
+    let edge1_addr = Address {
+        network_id: 1,
+        node_id: 2,
+        port_id: 0,
+    };
+    let edge2_addr = Address {
+        network_id: 2,
+        node_id: 2,
+        port_id: 0,
+    };
+
+    ping_with_retry(&router_stack, edge1_addr, 0).await;
+    ping_with_retry(&router_stack, edge2_addr, 0).await;
+
+    wait_active(&edge1_stack).await;
+    wait_active(&edge2_stack).await;
+
+    assert_eq!(ping_with_retry(&edge1_stack, edge2_addr, 42).await, 42);
+    assert_eq!(ping_with_retry(&edge2_stack, edge1_addr, 43).await, 43);
+}
@@ -6,7 +6,8 @@ use ergot::interface_manager::{
     Interface, InterfaceSendError, InterfaceSink, InterfaceState, Profile, SeedAssignmentError,
     SeedRefreshError, profiles::router::Router,
 };
-use ergot::{Address, AnyAllAppendix, FrameKind, Header, HeaderSeq, Key, ProtocolError};
+use ergot::well_known::SubscriptionAdvertisement;
+use ergot::{Address, AnyAllAppendix, FrameKind, Header, HeaderSeq, Key, Priority, ProtocolError};
 use serde::Serialize;
 use std::sync::{Arc, Mutex};
 
@@ -16,11 +17,17 @@ use std::sync::{Arc, Mutex};
 struct RecordingSink {
     log: Arc<Mutex<Vec<String>>>,
     label: &'static str,
+    /// The serialized bodies of `send_ty`
+    bodies: Arc<Mutex<Vec<Vec<u8>>>>,
 }
 
 impl RecordingSink {
     fn new(label: &'static str, log: Arc<Mutex<Vec<String>>>) -> Self {
-        Self { log, label }
+        Self {
+            log,
+            label,
+            bodies: Arc::default(),
+        }
     }
 }
 
@@ -28,26 +35,30 @@ impl InterfaceSink for RecordingSink {
     fn mtu(&self) -> u16 {
         2048
     }
-    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, _body: &T) -> Result<(), ()> {
+    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, body: &T) -> Result<usize, ()> {
         self.log
             .lock()
             .unwrap()
             .push(format!("{}:send_ty:{}", self.label, hdr.dst));
-        Ok(())
+        self.bodies
+            .lock()
+            .unwrap()
+            .push(postcard::to_stdvec(body).unwrap());
+        Ok(0)
     }
-    fn send_raw(&mut self, hdr: &HeaderSeq, _body: &[u8]) -> Result<(), ()> {
+    fn send_raw(&mut self, hdr: &HeaderSeq, _body: &[u8]) -> Result<usize, ()> {
         self.log
             .lock()
             .unwrap()
             .push(format!("{}:send_raw:{}", self.label, hdr.dst));
-        Ok(())
+        Ok(0)
     }
-    fn send_err(&mut self, hdr: &HeaderSeq, _err: ProtocolError) -> Result<(), ()> {
+    fn send_err(&mut self, hdr: &HeaderSeq, _err: ProtocolError) -> Result<usize, ()> {
         self.log
             .lock()
             .unwrap()
             .push(format!("{}:send_err:{}", self.label, hdr.dst));
-        Ok(())
+        Ok(0)
     }
 }
 
@@ -72,6 +83,7 @@ fn make_hdr(src_net: u16, dst_net: u16, dst_node: u8, dst_port: u8) -> Header {
         seq_no: None,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     }
 }
 
@@ -94,6 +106,7 @@ fn make_broadcast_hdr() -> Header {
         seq_no: None,
         kind: FrameKind::TOPIC_MSG,
         ttl: 16,
+        prio: Priority::NORMAL,
     }
 }
 
@@ -243,6 +256,7 @@ fn send_raw_routing_loop() {
         seq_no: 100,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
 
     let result = router.send_raw(&hdr, &[1, 2, 3], id_usb);
@@ -277,6 +291,7 @@ fn send_raw_forwards_to_other_interface() {
         seq_no: 100,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
 
     router.send_raw(&hdr, &[1, 2, 3], id_usb).unwrap();
@@ -297,7 +312,7 @@ fn seed_assign_success() {
         .register_interface(RecordingSink::new("uart", log.clone()))
         .unwrap();
 
-    let assignment = router.request_seed_net_assign(1).unwrap();
+    let assignment = router.request_seed_net_assign(1, None).unwrap();
     assert_eq!(assignment.net_id, 2);
     assert_eq!(assignment.expires_seconds, 30);
     assert_ne!(assignment.refresh_token, [0; 8]);
@@ -312,7 +327,7 @@ fn seed_assign_unknown_source() {
         .register_interface(RecordingSink::new("uart", log.clone()))
         .unwrap();
 
-    let result = router.request_seed_net_assign(99);
+    let result = router.request_seed_net_assign(99, None);
     assert_eq!(result, Err(SeedAssignmentError::UnknownSource));
 }
 
@@ -325,7 +340,7 @@ fn seed_refresh_success() {
         .register_interface(RecordingSink::new("uart", log.clone()))
         .unwrap();
 
-    let assignment = router.request_seed_net_assign(1).unwrap();
+    let assignment = router.request_seed_net_assign(1, None).unwrap();
 
     // Initial lease is 30s < MIN_SEED_REFRESH (62s) → refresh allowed immediately
     let refreshed = router
@@ -345,7 +360,7 @@ fn seed_refresh_then_too_soon() {
         .register_interface(RecordingSink::new("uart", log.clone()))
         .unwrap();
 
-    let assignment = router.request_seed_net_assign(1).unwrap();
+    let assignment = router.request_seed_net_assign(1, None).unwrap();
     let refreshed = router
         .refresh_seed_net_assignment(1, assignment.net_id, assignment.refresh_token)
         .unwrap();
@@ -364,7 +379,7 @@ fn seed_refresh_bad_token() {
         .register_interface(RecordingSink::new("uart", log.clone()))
         .unwrap();
 
-    let assignment = router.request_seed_net_assign(1).unwrap();
+    let assignment = router.request_seed_net_assign(1, None).unwrap();
 
     let result = router.refresh_seed_net_assignment(1, assignment.net_id, [0xFF; 8]);
     assert_eq!(result, Err(SeedRefreshError::BadRequest));
@@ -380,7 +395,7 @@ fn deregister_cleans_routes() {
         .unwrap();
 
     // Seed route through uart
-    let assignment = router.request_seed_net_assign(1).unwrap();
+    let assignment = router.request_seed_net_assign(1, None).unwrap();
     let seed_net = assignment.net_id;
 
     // Verify routing works
@@ -413,3 +428,143 @@ fn get_nets_returns_active_interfaces() {
     nets.sort();
     assert_eq!(nets, vec![1, 2]);
 }
+
+// --- Subscription pruning tests ---
+
+fn advertisement(keys: &[Key], complete: bool) -> SubscriptionAdvertisement {
+    SubscriptionAdvertisement {
+        valid_secs: 30,
+        complete,
+        keys: keys.iter().copied().collect(),
+    }
+}
+
+fn labels(log: &Mutex<Vec<String>>) -> Vec<String> {
+    let mut labels: Vec<String> = log
+        .lock()
+        .unwrap()
+        .drain(..)
+        .map(|e| e.split(':').next().unwrap().to_string())
+        .collect();
+    labels.sort();
+    labels
+}
+
+#[test]
+fn broadcast_pruned_by_subscriptions() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut router: Router<MockInterface, rand::rngs::StdRng, 64, 64> = Router::new_std();
+
+    let id_usb = router
+        .register_interface(RecordingSink::new("usb", log.clone()))
+        .unwrap();
+    let id_uart = router
+        .register_interface(RecordingSink::new("uart", log.clone()))
+        .unwrap();
+    // The radio never advertises
+    router
+        .register_interface(RecordingSink::new("radio", log.clone()))
+        .unwrap();
+
+    let subscribed = Key(*b"TESTTEST");
+    router.learn_subscriptions(id_usb, &advertisement(&[subscribed], true));
+    router.learn_subscriptions(id_uart, &advertisement(&[], true));
+
+    let mut hdr = make_broadcast_hdr();
+    router.send(&hdr, &42u32).unwrap();
+    assert_eq!(labels(&log), ["radio", "usb"]);
+
+    // Nobody advertised this one, it only goes where the subscriptions are
+    // unknown
+    hdr.any_all.as_mut().unwrap().key = Key(*b"ELSEELSE");
+    router.send(&hdr, &42u32).unwrap();
+    assert_eq!(labels(&log), ["radio"]);
+
+    // Only topic messages are pruned
+    hdr.kind = FrameKind::ENDPOINT_REQ;
+    router.send(&hdr, &42u32).unwrap();
+    assert_eq!(labels(&log), ["radio", "uart", "usb"]);
+
+    // Forwarded broadcasts are pruned too
+    let raw = HeaderSeq {
+        src: Address {
+            network_id: 3,
+            node_id: 2,
+            port_id: 3,
+        },
+        dst: Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: 255,
+        },
+        any_all: Some(AnyAllAppendix {
+            key: subscribed,
+            nash: None,
+        }),
+        seq_no: 100,
+        kind: FrameKind::TOPIC_MSG,
+        ttl: 16,
+        prio: Priority::NORMAL,
+    };
+    router.send_raw(&raw, &[1, 2, 3], 2).unwrap();
+    assert_eq!(labels(&log), ["usb"]);
+
+    // An incomplete advertisement is as good as none
+    router.learn_subscriptions(id_uart, &advertisement(&[], false));
+    router.send_raw(&raw, &[1, 2, 3], 2).unwrap();
+    assert_eq!(labels(&log), ["uart", "usb"]);
+
+    // And subscriptions are forgotten when an interface goes down
+    router
+        .set_interface_state(id_usb, InterfaceState::Down)
+        .unwrap();
+    router
+        .set_interface_state(
+            id_usb,
+            InterfaceState::Active {
+                net_id: 1,
+                node_id: 1,
+            },
+        )
+        .unwrap();
+    hdr.kind = FrameKind::TOPIC_MSG;
+    router.send(&hdr, &42u32).unwrap();
+    assert_eq!(labels(&log), ["radio", "uart", "usb"]);
+}
+
+#[test]
+fn advertisement_split_horizon() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut router: Router<MockInterface, rand::rngs::StdRng, 64, 64> = Router::new_std();
+
+    let usb = RecordingSink::new("usb", log.clone());
+    let uart = RecordingSink::new("uart", log.clone());
+    let id_usb = router.register_interface(usb.clone()).unwrap();
+    let id_uart = router.register_interface(uart.clone()).unwrap();
+
+    let local = Key(*b"LOCALKEY");
+    let behind_usb = Key(*b"USBUSBUS");
+    let behind_uart = Key(*b"UARTUART");
+    router.learn_subscriptions(id_usb, &advertisement(&[behind_usb], true));
+    router.learn_subscriptions(id_uart, &advertisement(&[behind_uart], true));
+
+    let last_advertisement = |sink: &RecordingSink| -> SubscriptionAdvertisement {
+        postcard::from_bytes(sink.bodies.lock().unwrap().last().unwrap()).unwrap()
+    };
+
+    router.advertise_subscriptions(&advertisement(&[local], true));
+    let to_usb = last_advertisement(&usb);
+    assert!(to_usb.complete);
+    assert_eq!(to_usb.keys.as_slice(), [local, behind_uart]);
+    let to_uart = last_advertisement(&uart);
+    assert!(to_uart.complete);
+    assert_eq!(to_uart.keys.as_slice(), [local, behind_usb]);
+
+    // Until the radio advertises, nobody can know what is behind it
+    router
+        .register_interface(RecordingSink::new("radio", log.clone()))
+        .unwrap();
+    router.advertise_subscriptions(&advertisement(&[local], true));
+    assert!(!last_advertisement(&usb).complete);
+    assert!(!last_advertisement(&uart).complete);
+}
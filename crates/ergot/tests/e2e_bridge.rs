@@ -156,7 +156,7 @@ async fn bridge_forwards_ping_upstream() {
     sleep(Duration::from_millis(200)).await;
 
     let bridge_down_assignment = root_stack
-        .manage_profile(|im| im.request_seed_net_assign(1))
+        .manage_profile(|im| im.request_seed_net_assign(1, None))
         .unwrap();
     bridge_stack
         .manage_profile(|im| {
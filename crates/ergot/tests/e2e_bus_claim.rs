@@ -195,24 +195,44 @@ async fn two_point_to_point_edges_each_claim_and_ping() {
     let link_local = Address { network_id: 0, node_id: 1, port_id: 0 };
 
     // Edge1 claims node_id=10
-    let r1 = timeout(Duration::from_secs(5),
-        edge1_stack.endpoints().request::<ErgotAddressClaimEndpoint>(
-            link_local,
-            &AddressClaimRequest { candidate_node_id: 10, nonce: 0xAA },
-            None,
-        ),
-    ).await.unwrap().unwrap();
+    let r1 = timeout(
+        Duration::from_secs(5),
+        edge1_stack
+            .endpoints()
+            .request::<ErgotAddressClaimEndpoint>(
+                link_local,
+                &AddressClaimRequest {
+                    candidate_node_id: 10,
+                    nonce: 0xAA,
+                    credentials: None,
+                },
+                None,
+            ),
+    )
+    .await
+    .unwrap()
+    .unwrap();
     let g1 = r1.expect("edge1 claim should succeed");
     assert_eq!(g1.assignment.node_id, 10);
 
     // Edge2 claims node_id=20
-    let r2 = timeout(Duration::from_secs(5),
-        edge2_stack.endpoints().request::<ErgotAddressClaimEndpoint>(
-            link_local,
-            &AddressClaimRequest { candidate_node_id: 20, nonce: 0xBB },
-            None,
-        ),
-    ).await.unwrap().unwrap();
+    let r2 = timeout(
+        Duration::from_secs(5),
+        edge2_stack
+            .endpoints()
+            .request::<ErgotAddressClaimEndpoint>(
+                link_local,
+                &AddressClaimRequest {
+                    candidate_node_id: 20,
+                    nonce: 0xBB,
+                    credentials: None,
+                },
+                None,
+            ),
+    )
+    .await
+    .unwrap()
+    .unwrap();
     let g2 = r2.expect("edge2 claim should succeed");
     assert_eq!(g2.assignment.node_id, 20);
 
@@ -275,7 +295,11 @@ async fn claim_conflict_different_nonce() {
     let r1 = timeout(Duration::from_secs(5),
         edge_stack.endpoints().request::<ErgotAddressClaimEndpoint>(
             link_local,
-            &AddressClaimRequest { candidate_node_id: 50, nonce: 0x111 },
+            &AddressClaimRequest {
+                candidate_node_id: 50,
+                nonce: 0x111,
+                credentials: None,
+            },
             None,
         ),
     ).await.unwrap().unwrap();
@@ -285,7 +309,11 @@ async fn claim_conflict_different_nonce() {
     let r2 = timeout(Duration::from_secs(5),
         edge_stack.endpoints().request::<ErgotAddressClaimEndpoint>(
             link_local,
-            &AddressClaimRequest { candidate_node_id: 50, nonce: 0x222 },
+            &AddressClaimRequest {
+                candidate_node_id: 50,
+                nonce: 0x222,
+                credentials: None,
+            },
             None,
         ),
     ).await.unwrap().unwrap();
@@ -319,8 +347,16 @@ async fn duplicate_claim_same_nonce() {
 
     spawn_claim_handler(&router_stack);
 
-    let link_local = Address { network_id: 0, node_id: 1, port_id: 0 };
-    let req = AddressClaimRequest { candidate_node_id: 77, nonce: 0xCAFE };
+    let link_local = Address {
+        network_id: 0,
+        node_id: 1,
+        port_id: 0,
+    };
+    let req = AddressClaimRequest {
+        candidate_node_id: 77,
+        nonce: 0xCAFE,
+        credentials: None,
+    };
 
     // First claim
     let r1 = timeout(Duration::from_secs(5),
@@ -420,7 +456,7 @@ async fn retry_skips_taken_candidate() {
 
     // Pre-claim node_id=10 on the bus (net_id=1) as if another device holds it.
     router_stack
-        .manage_profile(|im| im.request_node_claim(1, 10, 0xAAAA))
+        .manage_profile(|im| im.request_node_claim(1, 10, 0xAAAA, None))
         .unwrap();
 
     // The edge offers [10, 11] with a *different* nonce: 10 conflicts, 11 is free.
@@ -449,10 +485,10 @@ async fn retry_exhausts_to_no_free_candidate() {
 
     // Both candidates are already held by another device.
     router_stack
-        .manage_profile(|im| im.request_node_claim(1, 20, 0xAAAA))
+        .manage_profile(|im| im.request_node_claim(1, 20, 0xAAAA, None))
         .unwrap();
     router_stack
-        .manage_profile(|im| im.request_node_claim(1, 21, 0xAAAA))
+        .manage_profile(|im| im.request_node_claim(1, 21, 0xAAAA, None))
         .unwrap();
 
     let err = timeout(
@@ -226,14 +226,17 @@ async fn two_edges_on_shared_bus_claim_and_ping() {
     // ---- Edge A claims node_id=30 ----
     let claim_a = timeout(
         Duration::from_secs(5),
-        edge_a_stack.endpoints().request::<ErgotAddressClaimEndpoint>(
-            link_local_router,
-            &AddressClaimRequest {
-                candidate_node_id: 30,
-                nonce: 0xAAAA,
-            },
-            None,
-        ),
+        edge_a_stack
+            .endpoints()
+            .request::<ErgotAddressClaimEndpoint>(
+                link_local_router,
+                &AddressClaimRequest {
+                    candidate_node_id: 30,
+                    nonce: 0xAAAA,
+                    credentials: None,
+                },
+                None,
+            ),
     )
     .await
     .expect("edge A claim timed out")
@@ -259,14 +262,17 @@ async fn two_edges_on_shared_bus_claim_and_ping() {
     // ---- Edge B claims node_id=50 ----
     let claim_b = timeout(
         Duration::from_secs(5),
-        edge_b_stack.endpoints().request::<ErgotAddressClaimEndpoint>(
-            link_local_router,
-            &AddressClaimRequest {
-                candidate_node_id: 50,
-                nonce: 0xBBBB,
-            },
-            None,
-        ),
+        edge_b_stack
+            .endpoints()
+            .request::<ErgotAddressClaimEndpoint>(
+                link_local_router,
+                &AddressClaimRequest {
+                    candidate_node_id: 50,
+                    nonce: 0xBBBB,
+                    credentials: None,
+                },
+                None,
+            ),
     )
     .await
     .expect("edge B claim timed out")
@@ -352,11 +358,17 @@ async fn bus_claim_conflict_same_segment() {
 
     let r = timeout(
         Duration::from_secs(5),
-        edge_a_stack.endpoints().request::<ErgotAddressClaimEndpoint>(
-            link_local,
-            &AddressClaimRequest { candidate_node_id: 42, nonce: 0x1111 },
-            None,
-        ),
+        edge_a_stack
+            .endpoints()
+            .request::<ErgotAddressClaimEndpoint>(
+                link_local,
+                &AddressClaimRequest {
+                    candidate_node_id: 42,
+                    nonce: 0x1111,
+                    credentials: None,
+                },
+                None,
+            ),
     )
     .await.unwrap().unwrap();
     assert!(r.is_ok(), "edge A should claim successfully");
@@ -374,11 +386,17 @@ async fn bus_claim_conflict_same_segment() {
 
     let r = timeout(
         Duration::from_secs(5),
-        edge_b_stack.endpoints().request::<ErgotAddressClaimEndpoint>(
-            link_local,
-            &AddressClaimRequest { candidate_node_id: 42, nonce: 0x2222 },
-            None,
-        ),
+        edge_b_stack
+            .endpoints()
+            .request::<ErgotAddressClaimEndpoint>(
+                link_local,
+                &AddressClaimRequest {
+                    candidate_node_id: 42,
+                    nonce: 0x2222,
+                    credentials: None,
+                },
+                None,
+            ),
     )
     .await.unwrap().unwrap();
 
@@ -126,7 +126,7 @@ async fn bridge_delegates_downstream_request_to_root() {
     .await
     .unwrap();
     let bridge_link = root
-        .manage_profile(|im| im.request_seed_net_assign(1))
+        .manage_profile(|im| im.request_seed_net_assign(1, None))
         .unwrap();
     bridge
         .manage_profile(|im| im.reassign_interface_net_id(bridge_down, bridge_link.net_id))
@@ -278,7 +278,7 @@ async fn delegated_route_is_routable_end_to_end() {
             .await
             .unwrap();
     let bridge_link = root
-        .manage_profile(|im| im.request_seed_net_assign(1))
+        .manage_profile(|im| im.request_seed_net_assign(1, None))
         .unwrap();
     bridge
         .manage_profile(|im| im.reassign_interface_net_id(bridge_down, bridge_link.net_id))
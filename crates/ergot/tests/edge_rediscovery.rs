@@ -14,7 +14,7 @@
 #![cfg(not(miri))]
 
 use ergot::{
-    Address, FrameKind, HeaderSeq,
+    Address, FrameKind, HeaderSeq, Priority,
     interface_manager::{
         FrameProcessor, Interface, InterfaceSink, InterfaceState, Profile, SeedLease,
         profiles::{
@@ -38,14 +38,14 @@ impl InterfaceSink for NullSink {
     fn mtu(&self) -> u16 {
         2048
     }
-    fn send_ty<T: Serialize>(&mut self, _hdr: &HeaderSeq, _body: &T) -> Result<(), ()> {
-        Ok(())
+    fn send_ty<T: Serialize>(&mut self, _hdr: &HeaderSeq, _body: &T) -> Result<usize, ()> {
+        Ok(0)
     }
-    fn send_raw(&mut self, _hdr: &HeaderSeq, _body: &[u8]) -> Result<(), ()> {
-        Ok(())
+    fn send_raw(&mut self, _hdr: &HeaderSeq, _body: &[u8]) -> Result<usize, ()> {
+        Ok(0)
     }
-    fn send_err(&mut self, _hdr: &HeaderSeq, _err: ergot::ProtocolError) -> Result<(), ()> {
-        Ok(())
+    fn send_err(&mut self, _hdr: &HeaderSeq, _err: ergot::ProtocolError) -> Result<usize, ()> {
+        Ok(0)
     }
 }
 
@@ -78,6 +78,7 @@ fn frame_to(dst_net: u16, dst_node: u8) -> Vec<u8> {
         seq_no: 0,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
     wire_frames::encode_frame_ty(postcard::ser_flavors::StdVec::new(), &hdr, &42u32).unwrap()
 }
@@ -361,6 +362,7 @@ fn controller_reactivates_as_central() {
         seq_no: 0,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
     let frame =
         wire_frames::encode_frame_ty(postcard::ser_flavors::StdVec::new(), &hdr, &42u32).unwrap();
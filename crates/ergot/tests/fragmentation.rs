@@ -0,0 +1,304 @@
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, HeaderSeq, Key, NetStack, Priority,
+    interface_manager::{
+        InterfaceState, interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::EdgeFrameProcessor, profiles::null::Null, profiles::router::Router,
+        transports::tokio_cobs_stream,
+    },
+    net_stack::{ArcNetStack, NetStackSendError, reassembly::REASSEMBLY_SLOTS},
+    traits::Endpoint,
+    wire_frames::{Fragment, FragmentHeader},
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use tokio::time::timeout;
+
+type Blob = Vec<u8>;
+
+ergot::endpoint!(BlobEndpoint, Blob, u32, "test/blob");
+
+type TestNetStack = NetStack<CriticalSectionRawMutex, Null>;
+
+fn blob(len: usize) -> Vec<u8> {
+    (0..len).map(|i| i as u8).collect()
+}
+
+fn blob_hdr(dst: Address) -> Header {
+    Header {
+        src: Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: 1,
+        },
+        dst,
+        any_all: Some(AnyAllAppendix {
+            key: Key(BlobEndpoint::REQ_KEY.to_bytes()),
+            nash: None,
+        }),
+        seq_no: None,
+        kind: FrameKind::ENDPOINT_REQ,
+        ttl: DEFAULT_TTL,
+        prio: Priority::NORMAL,
+    }
+}
+
+fn frag_hdr(port: u8) -> HeaderSeq {
+    HeaderSeq {
+        src: Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: 1,
+        },
+        dst: Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: port,
+        },
+        any_all: None,
+        seq_no: 7,
+        kind: FrameKind::FRAGMENT,
+        ttl: DEFAULT_TTL,
+        prio: Priority::NORMAL,
+    }
+}
+
+/// Send one fragment to a local port
+fn send_fragment(
+    stack: &TestNetStack,
+    port: u8,
+    msg_id: u16,
+    offset: usize,
+    data: &[u8],
+    more: bool,
+) {
+    let frag = Fragment {
+        hdr: FragmentHeader {
+            msg_id,
+            offset: offset as u16,
+            more,
+        },
+        data,
+    };
+    let body = postcard::to_stdvec(&frag).unwrap();
+    stack.send_raw(&frag_hdr(port), &body, ()).unwrap();
+}
+
+/// Send the given payload to a local port as fragments of `chunk` bytes
+fn send_fragments(stack: &TestNetStack, port: u8, msg_id: u16, payload: &[u8], chunk: usize) {
+    let mut offset = 0;
+    for data in payload.chunks(chunk) {
+        let more = offset + data.len() < payload.len();
+        send_fragment(stack, port, msg_id, offset, data, more);
+        offset += data.len();
+    }
+}
+
+/// The reassembled payload for a `BlobEndpoint` request
+fn request_payload(val: &Blob) -> Vec<u8> {
+    let mut payload = vec![FrameKind::ENDPOINT_REQ.0];
+    payload.extend(postcard::to_stdvec(val).unwrap());
+    payload
+}
+
+#[tokio::test]
+async fn local_fragmented_send_is_delivered_whole() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv = STACK.endpoints().bounded_server::<BlobEndpoint, 4>(None);
+    let srv = pin!(srv);
+    let mut hdl = srv.attach();
+
+    let val = blob(1000);
+    let mut scratch = vec![0u8; 2048];
+    STACK
+        .send_ty_fragmented(&blob_hdr(Address::unknown()), &val, 64, &mut scratch)
+        .unwrap();
+    let req = hdl.recv_manual().await.unwrap();
+    assert_eq!(req.t, val);
+
+    // The scratch buffer must fit the whole message
+    let mut scratch = vec![0u8; 100];
+    assert_eq!(
+        STACK.send_ty_fragmented(&blob_hdr(Address::unknown()), &val, 64, &mut scratch),
+        Err(NetStackSendError::MessageTooLarge)
+    );
+}
+
+#[tokio::test]
+async fn fragments_are_reassembled() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv = STACK.endpoints().bounded_server::<BlobEndpoint, 4>(None);
+    let srv = pin!(srv);
+    let mut hdl = srv.attach();
+    let port = hdl.port();
+
+    let val = blob(300);
+    let payload = request_payload(&val);
+    send_fragments(&STACK, port, 1, &payload, 64);
+    let req = hdl.recv_manual().await.unwrap();
+    assert_eq!(req.t, val);
+    assert_eq!(req.hdr.seq_no, 7);
+
+    // Fragments with a gap are discarded
+    send_fragment(&STACK, port, 2, 0, &payload[..64], true);
+    send_fragment(&STACK, port, 2, 128, &payload[128..], false);
+    assert!(
+        timeout(Duration::from_millis(50), hdl.recv_manual())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn oldest_reassembly_is_evicted() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv = STACK.endpoints().bounded_server::<BlobEndpoint, 4>(None);
+    let srv = pin!(srv);
+    let mut hdl = srv.attach();
+    let port = hdl.port();
+
+    let val = blob(100);
+    let payload = request_payload(&val);
+
+    // Start one more message than there are slots
+    for msg_id in 0..=(REASSEMBLY_SLOTS as u16) {
+        send_fragment(&STACK, port, msg_id, 0, &payload[..64], true);
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    // The first message was evicted, the second one can be completed
+    send_fragment(&STACK, port, 0, 64, &payload[64..], false);
+    assert!(
+        timeout(Duration::from_millis(50), hdl.recv_manual())
+            .await
+            .is_err()
+    );
+    send_fragment(&STACK, port, 1, 64, &payload[64..], false);
+    assert_eq!(hdl.recv_manual().await.unwrap().t, val);
+}
+
+#[tokio::test]
+async fn stale_reassembly_times_out() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv = STACK.endpoints().bounded_server::<BlobEndpoint, 4>(None);
+    let srv = pin!(srv);
+    let mut hdl = srv.attach();
+    let port = hdl.port();
+
+    let payload = request_payload(&blob(100));
+    send_fragment(&STACK, port, 1, 0, &payload[..64], true);
+    tokio::time::sleep(Duration::from_millis(2_100)).await;
+
+    // Only the first fragment starts a message, so the tail alone is dropped
+    send_fragment(&STACK, port, 1, 64, &payload[64..], false);
+    assert!(
+        timeout(Duration::from_millis(50), hdl.recv_manual())
+            .await
+            .is_err()
+    );
+}
+
+type RouterStackTy =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+#[tokio::test]
+async fn fragmented_request_through_router() {
+    //  Edge1 <--duplex1--> Router <--duplex2--> Edge2
+    //
+    // The router's interfaces have an MTU of 128 bytes
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router_stack: RouterStackTy = RouterStackTy::new();
+    let (edge1_stack, edge1_queue) = make_edge_stack();
+    let (edge2_stack, edge2_queue) = make_edge_stack();
+
+    let (e1_read, r1_write) = tokio::io::duplex(8192);
+    let (r1_read, e1_write) = tokio::io::duplex(8192);
+    let (e2_read, r2_write) = tokio::io::duplex(8192);
+    let (r2_read, e2_write) = tokio::io::duplex(8192);
+
+    for (read, write) in [(r1_read, r1_write), (r2_read, r2_write)] {
+        tokio_cobs_stream::register_router(
+            router_stack.clone(),
+            read,
+            write,
+            128,
+            4096,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+    for (stack, read, write, queue) in [
+        (&edge1_stack, e1_read, e1_write, edge1_queue),
+        (&edge2_stack, e2_read, e2_write, edge2_queue),
+    ] {
+        tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+            stack.clone(),
+            read,
+            write,
+            queue,
+            EdgeFrameProcessor::new(),
+            InterfaceState::Inactive,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    spawn_ping_server(&edge1_stack);
+    spawn_ping_server(&edge2_stack);
+
+    let edge1_addr = Address {
+        network_id: 1,
+        node_id: 2,
+        port_id: 0,
+    };
+    let edge2_addr = Address {
+        network_id: 2,
+        node_id: 2,
+        port_id: 0,
+    };
+    ping_with_retry(&router_stack, edge1_addr, 0).await;
+    ping_with_retry(&router_stack, edge2_addr, 0).await;
+    wait_active(&edge1_stack).await;
+    wait_active(&edge2_stack).await;
+
+    let srv = edge2_stack
+        .endpoints()
+        .bounded_server::<BlobEndpoint, 4>(None);
+    let srv = pin!(srv);
+    let mut hdl = srv.attach();
+
+    // Fits the edge's MTU, but is too large to be forwarded by the router
+    let val = blob(400);
+    assert!(edge1_stack.send_ty(&blob_hdr(edge2_addr), &val).is_ok());
+    assert!(
+        timeout(Duration::from_millis(100), hdl.recv_manual())
+            .await
+            .is_err()
+    );
+
+    let mut scratch = vec![0u8; 2048];
+    edge1_stack
+        .send_ty_fragmented(&blob_hdr(edge2_addr), &val, 128, &mut scratch)
+        .unwrap();
+    let req = timeout(Duration::from_secs(2), hdl.recv_manual())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(req.t, val);
+    assert_eq!(req.hdr.src.network_id, 1);
+}
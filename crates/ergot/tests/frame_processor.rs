@@ -4,7 +4,7 @@
 #![cfg(not(miri))]
 
 use ergot::{
-    Address, FrameKind, HeaderSeq,
+    Address, FrameKind, HeaderSeq, Priority,
     interface_manager::{
         FrameProcessor, InterfaceState, Profile,
         interface_impls::tokio_stream::TokioStreamInterface,
@@ -38,6 +38,7 @@ fn make_frame(src_net: u16, src_node: u8, dst_net: u16, dst_node: u8, dst_port:
         seq_no: 0,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
     wire_frames::encode_frame_ty(postcard::ser_flavors::StdVec::new(), &hdr, &42u32).unwrap()
 }
@@ -0,0 +1,153 @@
+//! Link encryption tests: sealed links between two edge devices.
+
+#![cfg(all(feature = "tokio-std", feature = "link-aead"))]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use common::ping_with_retry;
+use ergot::{
+    Address,
+    interface_manager::{
+        InterfaceState, Profile,
+        aead::{AeadInterface, AeadProcessor, LinkKey, LinkRole, Sink},
+        hello::HelloProcessor,
+        interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::{CENTRAL_NODE_ID, DirectEdge, EdgeFrameProcessor},
+        transports::tokio_cobs_stream,
+        utils::{cobs_stream, std::new_std_queue},
+    },
+    net_stack::ArcNetStack,
+    well_known::ErgotPingEndpoint,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use tokio::{
+    io::{DuplexStream, duplex},
+    time::{sleep, timeout},
+};
+
+type SealedInterface = AeadInterface<TokioStreamInterface, 1024>;
+type SealedStack = ArcNetStack<CriticalSectionRawMutex, DirectEdge<SealedInterface>>;
+
+const PSK: LinkKey = LinkKey::new([0x42; 32]);
+
+/// Register a sealed edge link, returning the stack
+async fn sealed_edge(
+    key: &LinkKey,
+    role: LinkRole,
+    read: DuplexStream,
+    write: DuplexStream,
+) -> SealedStack {
+    let queue = new_std_queue(4096);
+    let sink = Sink::new(
+        cobs_stream::Sink::new_from_handle(queue.clone(), 1024),
+        key,
+        role,
+        &mut rand::rng(),
+    );
+    let (stack, processor, state) = match role {
+        LinkRole::Controller => (
+            SealedStack::new_with_profile(DirectEdge::new_controller(sink, InterfaceState::Down)),
+            EdgeFrameProcessor::new_controller(1),
+            InterfaceState::Active {
+                net_id: 1,
+                node_id: CENTRAL_NODE_ID,
+            },
+        ),
+        LinkRole::Target => (
+            SealedStack::new_with_profile(DirectEdge::new_target(sink)),
+            EdgeFrameProcessor::new(),
+            InterfaceState::Inactive,
+        ),
+    };
+
+    tokio_cobs_stream::register_edge::<_, SealedInterface, _, _>(
+        stack.clone(),
+        read,
+        write,
+        queue,
+        AeadProcessor::<_, 1024>::new(HelloProcessor::new(processor), key, role, &mut rand::rng()),
+        state,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    stack
+}
+
+fn spawn_ping_server(stack: &SealedStack) {
+    tokio::spawn({
+        let stack = stack.clone();
+        async move {
+            let server = stack
+                .endpoints()
+                .bounded_server::<ErgotPingEndpoint, 4>(Some("ping"));
+            let server = pin!(server);
+            let mut hdl = server.attach();
+            loop {
+                let _ = hdl
+                    .serve(|val: &u32| {
+                        let v = *val;
+                        async move { v }
+                    })
+                    .await;
+            }
+        }
+    });
+}
+
+const TGT_ADDR: Address = Address {
+    network_id: 1,
+    node_id: 2,
+    port_id: 0,
+};
+
+#[tokio::test]
+async fn sealed_ping() {
+    let (ctrl_read, tgt_write) = duplex(8192);
+    let (tgt_read, ctrl_write) = duplex(8192);
+
+    let key = PSK.derive(&[1; 16]);
+    let ctrl = sealed_edge(&key, LinkRole::Controller, ctrl_read, ctrl_write).await;
+    let tgt = sealed_edge(&key, LinkRole::Target, tgt_read, tgt_write).await;
+    spawn_ping_server(&tgt);
+
+    assert_eq!(ping_with_retry(&ctrl, TGT_ADDR, 42).await, 42);
+    assert_eq!(ping_with_retry(&ctrl, TGT_ADDR, 43).await, 43);
+}
+
+#[tokio::test]
+async fn wrong_key_is_rejected() {
+    let (ctrl_read, tgt_write) = duplex(8192);
+    let (tgt_read, ctrl_write) = duplex(8192);
+
+    // Same pre-shared key, but derived for different links
+    let ctrl = sealed_edge(
+        &PSK.derive(&[1; 16]),
+        LinkRole::Controller,
+        ctrl_read,
+        ctrl_write,
+    )
+    .await;
+    let tgt = sealed_edge(&PSK.derive(&[2; 16]), LinkRole::Target, tgt_read, tgt_write).await;
+    spawn_ping_server(&tgt);
+
+    let res = timeout(
+        Duration::from_millis(500),
+        ctrl.endpoints()
+            .request::<ErgotPingEndpoint>(TGT_ADDR, &42, Some("ping")),
+    )
+    .await;
+    assert!(!matches!(res, Ok(Ok(_))));
+
+    // No frame from the controller was accepted, so the target never
+    // learned its address
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(
+        tgt.manage_profile(|im| im.interface_state(())),
+        Some(InterfaceState::Inactive)
+    );
+}
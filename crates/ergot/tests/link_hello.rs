@@ -0,0 +1,202 @@
+//! Link hello tests: version negotiation when a link comes up.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{EdgeStack, make_edge_stack, ping_with_retry, spawn_ping_server};
+use ergot::{
+    Address,
+    interface_manager::{
+        InterfaceState, Profile,
+        hello::{
+            Capabilities, HELLO_RETRY_MS, HelloProcessor, LinkHello, MAX_LINK_HELLO_SIZE,
+            PROTOCOL_VERSION,
+        },
+        interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::{CENTRAL_NODE_ID, DirectEdge, EdgeFrameProcessor},
+        transports::tokio_cobs_stream,
+        utils::{cobs_stream, std::new_std_queue},
+    },
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt, DuplexStream},
+    time::{sleep, timeout},
+};
+
+async fn read_frame(rx: &mut DuplexStream) -> Vec<u8> {
+    let mut buf = vec![];
+    loop {
+        let byte = timeout(Duration::from_secs(1), rx.read_u8())
+            .await
+            .expect("no frame received")
+            .unwrap();
+        if byte == 0 {
+            return cobs::decode_vec(&buf).unwrap();
+        }
+        buf.push(byte);
+    }
+}
+
+async fn write_frame(tx: &mut DuplexStream, data: &[u8]) {
+    let mut frame = cobs::encode_vec(data);
+    frame.push(0);
+    tx.write_all(&frame).await.unwrap();
+}
+
+async fn write_hello(tx: &mut DuplexStream, hello: LinkHello) {
+    let mut buf = [0u8; MAX_LINK_HELLO_SIZE];
+    write_frame(tx, hello.encode(&mut buf).unwrap()).await;
+}
+
+async fn wait_state(stack: &EdgeStack, state: InterfaceState) {
+    for _ in 0..50 {
+        if stack.manage_profile(|im| im.interface_state(())) == Some(state) {
+            return;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    panic!("interface never reached {state:?}");
+}
+
+#[tokio::test]
+async fn hello_then_ping() {
+    let (ctrl_read, tgt_write) = tokio::io::duplex(8192);
+    let (tgt_read, ctrl_write) = tokio::io::duplex(8192);
+
+    let ctrl_queue = new_std_queue(4096);
+    let ctrl_stack = EdgeStack::new_with_profile(DirectEdge::new_controller(
+        cobs_stream::Sink::new_from_handle(ctrl_queue.clone(), 512),
+        InterfaceState::Down,
+    ));
+    let (tgt_stack, tgt_queue) = make_edge_stack();
+
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        ctrl_stack.clone(),
+        ctrl_read,
+        ctrl_write,
+        ctrl_queue,
+        HelloProcessor::new(EdgeFrameProcessor::new_controller(1)),
+        InterfaceState::Active {
+            net_id: 1,
+            node_id: CENTRAL_NODE_ID,
+        },
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        tgt_stack.clone(),
+        tgt_read,
+        tgt_write,
+        tgt_queue,
+        HelloProcessor::new(EdgeFrameProcessor::new()),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    spawn_ping_server(&tgt_stack);
+
+    let tgt_addr = Address {
+        network_id: 1,
+        node_id: 2,
+        port_id: 0,
+    };
+    assert_eq!(ping_with_retry(&ctrl_stack, tgt_addr, 42).await, 42);
+}
+
+#[tokio::test]
+async fn incompatible_peer_is_refused() {
+    let (mut peer_read, tgt_write) = tokio::io::duplex(8192);
+    let (tgt_read, mut peer_write) = tokio::io::duplex(8192);
+
+    let (tgt_stack, tgt_queue) = make_edge_stack();
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        tgt_stack.clone(),
+        tgt_read,
+        tgt_write,
+        tgt_queue,
+        HelloProcessor::new(EdgeFrameProcessor::new()),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    // The hello is sent as soon as the link comes up
+    let hello = LinkHello::decode(&read_frame(&mut peer_read).await).unwrap();
+    assert_eq!(hello.version, PROTOCOL_VERSION);
+    assert!(!hello.reply);
+
+    // A peer that only speaks a newer protocol is answered, then refused
+    let newer = LinkHello {
+        version: PROTOCOL_VERSION + 10,
+        min_version: PROTOCOL_VERSION + 1,
+        capabilities: Capabilities::NONE,
+        reply: false,
+    };
+    write_hello(&mut peer_write, newer).await;
+    let reply = LinkHello::decode(&read_frame(&mut peer_read).await).unwrap();
+    assert!(reply.reply);
+    wait_state(
+        &tgt_stack,
+        InterfaceState::Incompatible {
+            peer_version: PROTOCOL_VERSION + 10,
+        },
+    )
+    .await;
+
+    // Once the peer offers a compatible version, the interface may come up
+    let compatible = LinkHello {
+        reply: true,
+        ..LinkHello::new(Capabilities::NONE)
+    };
+    write_hello(&mut peer_write, compatible).await;
+    wait_state(&tgt_stack, InterfaceState::Inactive).await;
+}
+
+#[tokio::test]
+async fn silent_peer_is_refused() {
+    let (mut peer_read, tgt_write) = tokio::io::duplex(8192);
+    let (tgt_read, mut peer_write) = tokio::io::duplex(8192);
+
+    let (tgt_stack, tgt_queue) = make_edge_stack();
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        tgt_stack.clone(),
+        tgt_read,
+        tgt_write,
+        tgt_queue,
+        HelloProcessor::new(EdgeFrameProcessor::new()),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    // A lost hello is sent again
+    let hello = LinkHello::decode(&read_frame(&mut peer_read).await).unwrap();
+    assert!(!hello.reply);
+    let start = tokio::time::Instant::now();
+    let again = LinkHello::decode(&read_frame(&mut peer_read).await).unwrap();
+    assert_eq!(again, hello);
+    assert!(start.elapsed() >= Duration::from_millis(HELLO_RETRY_MS / 2));
+
+    // A peer without link hellos sends frames, but never answers
+    for _ in 0..16 {
+        write_frame(&mut peer_write, &[1, 2, 3]).await;
+    }
+    wait_state(&tgt_stack, InterfaceState::Incompatible { peer_version: 0 }).await;
+
+    // An updated peer is accepted
+    write_hello(&mut peer_write, LinkHello::new(Capabilities::NONE)).await;
+    wait_state(&tgt_stack, InterfaceState::Inactive).await;
+}
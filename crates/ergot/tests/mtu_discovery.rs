@@ -6,7 +6,7 @@ use ergot::interface_manager::{
     Interface, InterfaceSendError, InterfaceSink, Profile, profiles::router::Router,
 };
 use ergot::wire_frames::{de_frame, encode_frame_err};
-use ergot::{Address, FrameKind, HeaderSeq, ProtocolError};
+use ergot::{Address, FrameKind, HeaderSeq, Priority, ProtocolError};
 use rand_core::RngCore;
 use serde::Serialize;
 use std::sync::{Arc, Mutex};
@@ -70,27 +70,27 @@ impl InterfaceSink for MtuSink {
     fn mtu(&self) -> u16 {
         self.mtu
     }
-    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, _body: &T) -> Result<(), ()> {
+    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, _body: &T) -> Result<usize, ()> {
         self.log.lock().unwrap().push(SinkEvent::SendTy {
             label: self.label,
             dst: hdr.dst,
         });
-        Ok(())
+        Ok(0)
     }
-    fn send_raw(&mut self, hdr: &HeaderSeq, _body: &[u8]) -> Result<(), ()> {
+    fn send_raw(&mut self, hdr: &HeaderSeq, _body: &[u8]) -> Result<usize, ()> {
         self.log.lock().unwrap().push(SinkEvent::SendRaw {
             label: self.label,
             dst: hdr.dst,
         });
-        Ok(())
+        Ok(0)
     }
-    fn send_err(&mut self, hdr: &HeaderSeq, err: ProtocolError) -> Result<(), ()> {
+    fn send_err(&mut self, hdr: &HeaderSeq, err: ProtocolError) -> Result<usize, ()> {
         self.log.lock().unwrap().push(SinkEvent::SendErr {
             label: self.label,
             dst: hdr.dst,
             err,
         });
-        Ok(())
+        Ok(0)
     }
 }
 
@@ -119,6 +119,7 @@ fn error_frame_round_trip_simple() {
         seq_no: 42,
         kind: FrameKind::PROTOCOL_ERROR,
         ttl: 8,
+        prio: Priority::NORMAL,
     };
 
     let flav = postcard::ser_flavors::StdVec::new();
@@ -150,6 +151,7 @@ fn error_frame_round_trip_packet_too_big() {
         seq_no: 99,
         kind: FrameKind::PROTOCOL_ERROR,
         ttl: 8,
+        prio: Priority::NORMAL,
     };
 
     let err = ProtocolError::IsePacketTooBig { mtu: 512 };
@@ -179,6 +181,7 @@ fn error_frame_round_trip_max_mtu() {
         seq_no: 0,
         kind: FrameKind::PROTOCOL_ERROR,
         ttl: 1,
+        prio: Priority::NORMAL,
     };
 
     let err = ProtocolError::IsePacketTooBig { mtu: u16::MAX };
@@ -229,6 +232,7 @@ fn router_send_raw_packet_too_big() {
         seq_no: 1,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
 
     // 100 bytes of payload — well over the 64 byte MTU
@@ -266,6 +270,7 @@ fn router_send_raw_within_mtu_succeeds() {
         seq_no: 1,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
 
     let small_payload = [0xABu8; 10];
@@ -16,17 +16,17 @@ impl InterfaceSink for MockSinkA {
     fn mtu(&self) -> u16 {
         2048
     }
-    fn send_ty<T: Serialize>(&mut self, _hdr: &HeaderSeq, _body: &T) -> Result<(), ()> {
+    fn send_ty<T: Serialize>(&mut self, _hdr: &HeaderSeq, _body: &T) -> Result<usize, ()> {
         LAST_SINK.store(1, Ordering::SeqCst);
-        Ok(())
+        Ok(0)
     }
-    fn send_raw(&mut self, _hdr: &HeaderSeq, _body: &[u8]) -> Result<(), ()> {
+    fn send_raw(&mut self, _hdr: &HeaderSeq, _body: &[u8]) -> Result<usize, ()> {
         LAST_SINK.store(1, Ordering::SeqCst);
-        Ok(())
+        Ok(0)
     }
-    fn send_err(&mut self, _hdr: &HeaderSeq, _err: ProtocolError) -> Result<(), ()> {
+    fn send_err(&mut self, _hdr: &HeaderSeq, _err: ProtocolError) -> Result<usize, ()> {
         LAST_SINK.store(1, Ordering::SeqCst);
-        Ok(())
+        Ok(0)
     }
 }
 
@@ -36,17 +36,17 @@ impl InterfaceSink for MockSinkB {
     fn mtu(&self) -> u16 {
         2048
     }
-    fn send_ty<T: Serialize>(&mut self, _hdr: &HeaderSeq, _body: &T) -> Result<(), ()> {
+    fn send_ty<T: Serialize>(&mut self, _hdr: &HeaderSeq, _body: &T) -> Result<usize, ()> {
         LAST_SINK.store(2, Ordering::SeqCst);
-        Ok(())
+        Ok(0)
     }
-    fn send_raw(&mut self, _hdr: &HeaderSeq, _body: &[u8]) -> Result<(), ()> {
+    fn send_raw(&mut self, _hdr: &HeaderSeq, _body: &[u8]) -> Result<usize, ()> {
         LAST_SINK.store(2, Ordering::SeqCst);
-        Ok(())
+        Ok(0)
     }
-    fn send_err(&mut self, _hdr: &HeaderSeq, _err: ProtocolError) -> Result<(), ()> {
+    fn send_err(&mut self, _hdr: &HeaderSeq, _err: ProtocolError) -> Result<usize, ()> {
         LAST_SINK.store(2, Ordering::SeqCst);
-        Ok(())
+        Ok(0)
     }
 }
 
@@ -56,17 +56,17 @@ impl InterfaceSink for MockSinkC {
     fn mtu(&self) -> u16 {
         2048
     }
-    fn send_ty<T: Serialize>(&mut self, _hdr: &HeaderSeq, _body: &T) -> Result<(), ()> {
+    fn send_ty<T: Serialize>(&mut self, _hdr: &HeaderSeq, _body: &T) -> Result<usize, ()> {
         LAST_SINK.store(3, Ordering::SeqCst);
-        Ok(())
+        Ok(0)
     }
-    fn send_raw(&mut self, _hdr: &HeaderSeq, _body: &[u8]) -> Result<(), ()> {
+    fn send_raw(&mut self, _hdr: &HeaderSeq, _body: &[u8]) -> Result<usize, ()> {
         LAST_SINK.store(3, Ordering::SeqCst);
-        Ok(())
+        Ok(0)
     }
-    fn send_err(&mut self, _hdr: &HeaderSeq, _err: ProtocolError) -> Result<(), ()> {
+    fn send_err(&mut self, _hdr: &HeaderSeq, _err: ProtocolError) -> Result<usize, ()> {
         LAST_SINK.store(3, Ordering::SeqCst);
-        Ok(())
+        Ok(0)
     }
 }
 
@@ -111,6 +111,7 @@ fn make_dummy_hdr() -> HeaderSeq {
         seq_no: 0,
         kind: ergot::FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: ergot::Priority::NORMAL,
     }
 }
 
@@ -0,0 +1,151 @@
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+use std::{pin::pin, time::Duration};
+
+use ergot::{
+    Address, DEFAULT_TTL, FrameKind, Header, HeaderSeq, NetStack, Priority,
+    interface_manager::profiles::null::Null, net_stack::ReqRespError,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+
+ergot::endpoint!(Double, u32, u32, "test/double");
+
+type TestNetStack = NetStack<CriticalSectionRawMutex, Null>;
+
+/// Manually respond to a request, using the given seq_no
+fn respond(stack: &TestNetStack, req: &HeaderSeq, srv_port: u8, seq_no: u16, resp: u32) {
+    let hdr = Header {
+        src: Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: srv_port,
+        },
+        dst: req.src,
+        any_all: None,
+        seq_no: Some(seq_no),
+        kind: FrameKind::ENDPOINT_RESP,
+        ttl: DEFAULT_TTL,
+        prio: Priority::NORMAL,
+    };
+    stack.send_ty(&hdr, &resp).unwrap();
+}
+
+#[tokio::test]
+async fn concurrent_requests_share_one_port() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv = STACK.endpoints().bounded_server::<Double, 8>(None);
+    let srv = pin!(srv);
+    let mut srv = srv.attach();
+
+    let client = STACK.endpoints().multiplex_client::<Double, 8>();
+    let client = pin!(client);
+    let hdl = client.attach();
+
+    // Collect all requests first, then answer them in reverse order
+    let server = async {
+        let mut reqs = vec![];
+        for _ in 0..4 {
+            reqs.push(srv.recv_manual().await.unwrap());
+        }
+        let ports: Vec<u8> = reqs.iter().map(|r| r.hdr.src.port_id).collect();
+        assert!(ports.iter().all(|p| *p == hdl.port()));
+        for req in reqs.iter().rev() {
+            respond(&STACK, &req.hdr, srv.port(), req.hdr.seq_no, req.t * 2);
+        }
+    };
+    let (a, b, c, d, ()) = tokio::join!(
+        hdl.request(Address::unknown(), &1, None),
+        hdl.request(Address::unknown(), &2, None),
+        hdl.request(Address::unknown(), &3, None),
+        hdl.request(Address::unknown(), &4, None),
+        server,
+    );
+    assert_eq!([a, b, c, d], [Ok(2), Ok(4), Ok(6), Ok(8)]);
+    assert_eq!(hdl.stray_responses(), 0);
+
+    let broadcast = Address {
+        network_id: 0,
+        node_id: 0,
+        port_id: 255,
+    };
+    assert_eq!(
+        hdl.request(broadcast, &1, None).await,
+        Err(ReqRespError::NoBroadcast)
+    );
+}
+
+#[tokio::test]
+async fn stray_and_duplicate_responses_are_dropped() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv = STACK.endpoints().bounded_server::<Double, 8>(None);
+    let srv = pin!(srv);
+    let mut srv = srv.attach();
+
+    let client = STACK.endpoints().multiplex_client::<Double, 8>();
+    let client = pin!(client);
+    let hdl = client.attach();
+
+    // The first request times out before its response is sent
+    let res = hdl
+        .request_full_with_timeout(
+            Address::unknown(),
+            &1,
+            None,
+            tokio::time::sleep(Duration::from_millis(20)),
+        )
+        .await;
+    assert_eq!(res.unwrap_err(), ReqRespError::Timeout);
+    let late = srv.recv_manual().await.unwrap();
+
+    let server = async {
+        let req = srv.recv_manual().await.unwrap();
+        // The late response, and a duplicate of the real one
+        respond(&STACK, &late.hdr, srv.port(), late.hdr.seq_no, 100);
+        respond(&STACK, &req.hdr, srv.port(), req.hdr.seq_no, req.t * 2);
+        respond(&STACK, &req.hdr, srv.port(), req.hdr.seq_no, 200);
+    };
+    let (res, ()) = tokio::join!(hdl.request(Address::unknown(), &2, None), server);
+    assert_eq!(res, Ok(4));
+
+    // The duplicate is only drained by the next request
+    let server = async {
+        let req = srv.recv_manual().await.unwrap();
+        respond(&STACK, &req.hdr, srv.port(), req.hdr.seq_no, req.t * 2);
+    };
+    let (res, ()) = tokio::join!(hdl.request(Address::unknown(), &3, None), server);
+    assert_eq!(res, Ok(6));
+    assert_eq!(hdl.stray_responses(), 2);
+}
+
+#[tokio::test]
+async fn endpoints_request_ignores_other_seq_nos() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv = STACK.endpoints().bounded_server::<Double, 8>(None);
+    let srv = pin!(srv);
+    let mut srv = srv.attach();
+
+    let server = async {
+        let req = srv.recv_manual().await.unwrap();
+        respond(
+            &STACK,
+            &req.hdr,
+            srv.port(),
+            req.hdr.seq_no.wrapping_add(1),
+            0,
+        );
+        // The response socket only holds one message, let the client drop it
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        respond(&STACK, &req.hdr, srv.port(), req.hdr.seq_no, req.t * 2);
+    };
+    let (res, ()) = tokio::join!(
+        STACK
+            .endpoints()
+            .request::<Double>(Address::unknown(), &5, None),
+        server,
+    );
+    assert_eq!(res, Ok(10));
+}
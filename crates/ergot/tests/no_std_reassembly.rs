@@ -0,0 +1,88 @@
+//! Tests for reassembly into statically sized buffers (no_std compatible)
+
+use std::{pin::pin, time::Duration};
+
+use ergot::{
+    Address, DEFAULT_TTL, FrameKind, HeaderSeq, NetStack, Priority,
+    interface_manager::profiles::null::Null,
+    net_stack::reassembly::ReassemblyBuffers,
+    wire_frames::{Fragment, FragmentHeader},
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use tokio::time::timeout;
+
+type Blob = Vec<u8>;
+
+ergot::endpoint!(BlobEndpoint, Blob, u32, "test/blob");
+
+type TestNetStack = NetStack<CriticalSectionRawMutex, Null>;
+
+/// The reassembled payload for a `BlobEndpoint` request of `len` bytes
+fn request_payload(len: usize) -> (Blob, Vec<u8>) {
+    let val: Blob = (0..len).map(|i| i as u8).collect();
+    let mut payload = vec![FrameKind::ENDPOINT_REQ.0];
+    payload.extend(postcard::to_stdvec(&val).unwrap());
+    (val, payload)
+}
+
+/// Send the given payload to a local port as fragments of `chunk` bytes
+fn send_fragments(stack: &TestNetStack, port: u8, msg_id: u16, payload: &[u8], chunk: usize) {
+    let hdr = HeaderSeq {
+        src: Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: 1,
+        },
+        dst: Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: port,
+        },
+        any_all: None,
+        seq_no: 7,
+        kind: FrameKind::FRAGMENT,
+        ttl: DEFAULT_TTL,
+        prio: Priority::NORMAL,
+    };
+    let mut offset = 0;
+    for data in payload.chunks(chunk) {
+        let frag = Fragment {
+            hdr: FragmentHeader {
+                msg_id,
+                offset: offset as u16,
+                more: offset + data.len() < payload.len(),
+            },
+            data,
+        };
+        let body = postcard::to_stdvec(&frag).unwrap();
+        stack.send_raw(&hdr, &body, ()).unwrap();
+        offset += data.len();
+    }
+}
+
+#[tokio::test]
+async fn reassembles_into_static_buffers() {
+    static STACK: TestNetStack = NetStack::new();
+    let buffers = Box::leak(Box::new(ReassemblyBuffers::<2, 2100>::new()));
+    STACK.set_reassembly_buffers(buffers);
+
+    let srv = STACK.endpoints().bounded_server::<BlobEndpoint, 4>(None);
+    let srv = pin!(srv);
+    let mut hdl = srv.attach();
+    let port = hdl.port();
+
+    // A 2 KiB blob, through 64 byte hops
+    let (val, payload) = request_payload(2048);
+    send_fragments(&STACK, port, 1, &payload, 56);
+    let req = hdl.recv_manual().await.unwrap();
+    assert_eq!(req.t, val);
+
+    // Larger than the buffers, discarded
+    let (_, payload) = request_payload(2200);
+    send_fragments(&STACK, port, 2, &payload, 56);
+    assert!(
+        timeout(Duration::from_millis(50), hdl.recv_manual())
+            .await
+            .is_err()
+    );
+}
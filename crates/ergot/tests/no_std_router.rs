@@ -3,9 +3,10 @@
 use ergot::interface_manager::{
     AddressClaimError, AddressRefreshError, Interface, InterfaceSendError, InterfaceSink,
     InterfaceState, Profile, SeedAssignmentError, SeedRefreshError,
+    admission::{AdmissionError, AllowList, Credentials},
     profiles::router::{DeregisterError, RegisterError, Router},
 };
-use ergot::{Address, AnyAllAppendix, FrameKind, Header, HeaderSeq, Key, ProtocolError};
+use ergot::{Address, AnyAllAppendix, FrameKind, Header, HeaderSeq, Key, Priority, ProtocolError};
 use rand_core::RngCore;
 use serde::Serialize;
 use std::sync::{Arc, Mutex};
@@ -47,26 +48,26 @@ impl InterfaceSink for RecordingSink {
     fn mtu(&self) -> u16 {
         2048
     }
-    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, _body: &T) -> Result<(), ()> {
+    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, _body: &T) -> Result<usize, ()> {
         self.log
             .lock()
             .unwrap()
             .push(format!("{}:send_ty:{}", self.label, hdr.dst));
-        Ok(())
+        Ok(0)
     }
-    fn send_raw(&mut self, hdr: &HeaderSeq, _body: &[u8]) -> Result<(), ()> {
+    fn send_raw(&mut self, hdr: &HeaderSeq, _body: &[u8]) -> Result<usize, ()> {
         self.log
             .lock()
             .unwrap()
             .push(format!("{}:send_raw:{}", self.label, hdr.dst));
-        Ok(())
+        Ok(0)
     }
-    fn send_err(&mut self, hdr: &HeaderSeq, _err: ProtocolError) -> Result<(), ()> {
+    fn send_err(&mut self, hdr: &HeaderSeq, _err: ProtocolError) -> Result<usize, ()> {
         self.log
             .lock()
             .unwrap()
             .push(format!("{}:send_err:{}", self.label, hdr.dst));
-        Ok(())
+        Ok(0)
     }
 }
 
@@ -91,6 +92,7 @@ fn make_hdr(src_net: u16, dst_net: u16, dst_node: u8, dst_port: u8) -> Header {
         seq_no: None,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     }
 }
 
@@ -113,6 +115,7 @@ fn make_broadcast_hdr() -> Header {
         seq_no: None,
         kind: FrameKind::TOPIC_MSG,
         ttl: 16,
+        prio: Priority::NORMAL,
     }
 }
 
@@ -361,6 +364,7 @@ fn send_raw_forwarding_skips_source() {
         seq_no: 100,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
 
     router.send_raw(&hdr, &[1, 2, 3], id_usb).unwrap();
@@ -395,6 +399,7 @@ fn send_raw_routing_loop() {
         seq_no: 100,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
 
     let result = router.send_raw(&hdr, &[1, 2, 3], id_usb);
@@ -414,7 +419,7 @@ fn seed_assign_success() {
         .unwrap();
 
     // ESP (on net_id=1) requests a seed net_id for its BLE downstream
-    let assignment = router.request_seed_net_assign(1).unwrap();
+    let assignment = router.request_seed_net_assign(1, None).unwrap();
 
     // Should get net_id=2 (next after the direct link's net_id=1)
     assert_eq!(assignment.net_id, 2);
@@ -432,10 +437,38 @@ fn seed_assign_unknown_source() {
         .unwrap();
 
     // Request from net_id=99 which doesn't exist
-    let result = router.request_seed_net_assign(99);
+    let result = router.request_seed_net_assign(99, None);
     assert_eq!(result, Err(SeedAssignmentError::UnknownSource));
 }
 
+#[test]
+fn seed_assign_not_admitted() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut router = Router::<MockInterface, MockRng, 4, 8>::new(MockRng(0))
+        .with_admission_policy(AllowList::new(&[0x1234]));
+
+    router
+        .register_interface(RecordingSink::new("uart", log.clone()))
+        .unwrap();
+
+    let denied = SeedAssignmentError::NotAdmitted(AdmissionError::Denied);
+    assert_eq!(router.request_seed_net_assign(1, None), Err(denied.clone()));
+    assert_eq!(router.can_delegate_seed(1, None), Err(denied.clone()));
+    let other = Credentials {
+        unique_id: 0x9999,
+        proof: None,
+    };
+    assert_eq!(router.request_seed_net_assign(1, Some(&other)), Err(denied));
+
+    // Rejected requests don't use up a seed route
+    let allowed = Credentials {
+        unique_id: 0x1234,
+        proof: None,
+    };
+    let assignment = router.request_seed_net_assign(1, Some(&allowed)).unwrap();
+    assert_eq!(assignment.net_id, 2);
+}
+
 #[test]
 fn seed_routes_full() {
     let log = Arc::new(Mutex::new(Vec::new()));
@@ -447,11 +480,11 @@ fn seed_routes_full() {
         .unwrap();
 
     // Fill both seed route slots
-    router.request_seed_net_assign(1).unwrap();
-    router.request_seed_net_assign(1).unwrap();
+    router.request_seed_net_assign(1, None).unwrap();
+    router.request_seed_net_assign(1, None).unwrap();
 
     // Third should fail
-    let result = router.request_seed_net_assign(1);
+    let result = router.request_seed_net_assign(1, None);
     assert_eq!(result, Err(SeedAssignmentError::NetIdsExhausted));
 }
 
@@ -465,7 +498,7 @@ fn tombstone_reserves_seed_net_id_then_reuses_freed() {
         .register_interface(RecordingSink::new("uart", log.clone()))
         .unwrap();
     // Seed route reachable via net_id=1 → gets net_id=2
-    let seed = router.request_seed_net_assign(1).unwrap();
+    let seed = router.request_seed_net_assign(1, None).unwrap();
     assert_eq!(seed.net_id, 2);
 
     // A second direct link gets net_id=3 (1 and 2 are in use).
@@ -479,11 +512,11 @@ fn tombstone_reserves_seed_net_id_then_reuses_freed() {
 
     // A fresh seed request must NOT reuse the tombstoned net_id=2 (reserved
     // for the grace period). It reuses the freed net_id=1 instead.
-    let reused = router.request_seed_net_assign(3).unwrap();
+    let reused = router.request_seed_net_assign(3, None).unwrap();
     assert_eq!(reused.net_id, 1);
 
     // The next request skips 1 (seed), 2 (tombstone), 3 (slot) → 4.
-    let next = router.request_seed_net_assign(3).unwrap();
+    let next = router.request_seed_net_assign(3, None).unwrap();
     assert_eq!(next.net_id, 4);
 }
 
@@ -503,7 +536,7 @@ fn claim_is_net_id_scoped() {
         .unwrap();
 
     // Claim node_id 30 on net_id 1.
-    let granted = router.request_node_claim(1, 30, 0xAA).unwrap();
+    let granted = router.request_node_claim(1, 30, 0xAA, None).unwrap();
     assert_eq!(granted.node_id, 30);
     assert_eq!(granted.net_id, 1);
 
@@ -531,13 +564,13 @@ fn claim_rejects_reserved_node_ids() {
 
     for reserved in [0u8, 1, 2, 255] {
         assert_eq!(
-            router.request_node_claim(1, reserved, 0x55),
+            router.request_node_claim(1, reserved, 0x55, None),
             Err(AddressClaimError::InvalidNodeId),
             "reserved node_id {reserved} must be rejected",
         );
     }
     // A normal node_id still works.
-    assert!(router.request_node_claim(1, 42, 0x55).is_ok());
+    assert!(router.request_node_claim(1, 42, 0x55, None).is_ok());
 }
 
 #[test]
@@ -550,7 +583,7 @@ fn claim_unknown_source_net() {
 
     // net_id 99 is not a registered interface.
     assert_eq!(
-        router.request_node_claim(99, 42, 0xAB),
+        router.request_node_claim(99, 42, 0xAB, None),
         Err(AddressClaimError::UnknownSource),
     );
 }
@@ -564,11 +597,11 @@ fn claim_exhausted_when_table_full() {
         .register_interface(RecordingSink::new("bus", log.clone()))
         .unwrap();
 
-    assert!(router.request_node_claim(1, 10, 0x1).is_ok());
-    assert!(router.request_node_claim(1, 11, 0x2).is_ok());
+    assert!(router.request_node_claim(1, 10, 0x1, None).is_ok());
+    assert!(router.request_node_claim(1, 11, 0x2, None).is_ok());
     // Third distinct claim → table full.
     assert_eq!(
-        router.request_node_claim(1, 12, 0x3),
+        router.request_node_claim(1, 12, 0x3, None),
         Err(AddressClaimError::Exhausted),
     );
 }
@@ -581,15 +614,15 @@ fn claim_duplicate_same_nonce_returns_existing() {
         .register_interface(RecordingSink::new("bus", log.clone()))
         .unwrap();
 
-    let g1 = router.request_node_claim(1, 42, 0xABCD).unwrap();
+    let g1 = router.request_node_claim(1, 42, 0xABCD, None).unwrap();
     // Same node_id + same nonce → idempotent (retransmit), returns existing.
-    let g2 = router.request_node_claim(1, 42, 0xABCD).unwrap();
+    let g2 = router.request_node_claim(1, 42, 0xABCD, None).unwrap();
     assert_eq!(g1.node_id, g2.node_id);
     assert_eq!(g1.net_id, g2.net_id);
     assert_eq!(g1.refresh_token, g2.refresh_token);
     // Same node_id, different nonce → conflict.
     assert_eq!(
-        router.request_node_claim(1, 42, 0x9999),
+        router.request_node_claim(1, 42, 0x9999, None),
         Err(AddressClaimError::Conflict),
     );
 }
@@ -602,7 +635,7 @@ fn claim_refresh_extends_lease_and_rotates_token() {
         .register_interface(RecordingSink::new("bus", log.clone()))
         .unwrap();
 
-    let granted = router.request_node_claim(1, 42, 0xAB).unwrap();
+    let granted = router.request_node_claim(1, 42, 0xAB, None).unwrap();
     assert_eq!(granted.expires_seconds, 30);
 
     // The initial 30s lease is below MIN_SEED_REFRESH (62s), so refresh is allowed.
@@ -626,7 +659,7 @@ fn claim_refresh_too_soon_after_extension() {
         .register_interface(RecordingSink::new("bus", log.clone()))
         .unwrap();
 
-    let g = router.request_node_claim(1, 42, 0xAB).unwrap();
+    let g = router.request_node_claim(1, 42, 0xAB, None).unwrap();
     // First refresh extends the lease to 120s.
     let r = router.refresh_node_claim(1, 42, g.refresh_token).unwrap();
     // ~120s remain, which is > MIN_SEED_REFRESH (62s) → too soon to refresh again.
@@ -644,7 +677,7 @@ fn claim_refresh_bad_token_and_unknown_node() {
         .register_interface(RecordingSink::new("bus", log.clone()))
         .unwrap();
 
-    let g = router.request_node_claim(1, 42, 0xAB).unwrap();
+    let g = router.request_node_claim(1, 42, 0xAB, None).unwrap();
 
     // Wrong token → BadRequest.
     assert_eq!(
@@ -669,7 +702,7 @@ fn seed_route_unicast_routing() {
         .unwrap();
 
     // ESP requests seed net_id for its BLE downstream
-    let assignment = router.request_seed_net_assign(1).unwrap();
+    let assignment = router.request_seed_net_assign(1, None).unwrap();
     let seed_net = assignment.net_id; // should be 2
 
     // PC also connected directly: net_id=3
@@ -701,7 +734,7 @@ fn seed_route_raw_forwarding() {
         .unwrap();
 
     // ESP's BLE downstream gets seed net_id=3
-    let assignment = router.request_seed_net_assign(1).unwrap();
+    let assignment = router.request_seed_net_assign(1, None).unwrap();
     let seed_net = assignment.net_id;
 
     // Raw packet from PC (via usb, net_id=2) destined to seed_net (phone via ESP)
@@ -720,6 +753,7 @@ fn seed_route_raw_forwarding() {
         seq_no: 100,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
 
     // Source is usb (ident 1), should forward through uart (ident 0)
@@ -739,7 +773,7 @@ fn seed_refresh_success() {
         .register_interface(RecordingSink::new("uart", log.clone()))
         .unwrap();
 
-    let assignment = router.request_seed_net_assign(1).unwrap();
+    let assignment = router.request_seed_net_assign(1, None).unwrap();
 
     // Initial lease is 30s, MIN_SEED_REFRESH is 62s.
     // remaining (30s) < MIN_SEED_REFRESH (62s) → refresh allowed immediately
@@ -760,7 +794,7 @@ fn seed_refresh_then_too_soon() {
         .register_interface(RecordingSink::new("uart", log.clone()))
         .unwrap();
 
-    let assignment = router.request_seed_net_assign(1).unwrap();
+    let assignment = router.request_seed_net_assign(1, None).unwrap();
 
     // First refresh: allowed (initial 30s < MIN_SEED_REFRESH 62s)
     let refreshed = router
@@ -781,7 +815,7 @@ fn seed_refresh_bad_token() {
         .register_interface(RecordingSink::new("uart", log.clone()))
         .unwrap();
 
-    let assignment = router.request_seed_net_assign(1).unwrap();
+    let assignment = router.request_seed_net_assign(1, None).unwrap();
 
     let result = router.refresh_seed_net_assignment(
         1,
@@ -800,7 +834,7 @@ fn seed_refresh_wrong_source() {
         .register_interface(RecordingSink::new("uart", log.clone()))
         .unwrap();
 
-    let assignment = router.request_seed_net_assign(1).unwrap();
+    let assignment = router.request_seed_net_assign(1, None).unwrap();
 
     // Correct token but wrong source_net: a lease is keyed by (net_id,
     // source_net), so a request from the wrong source doesn't match any
@@ -822,7 +856,7 @@ fn seed_refresh_unknown_net_id() {
         .register_interface(RecordingSink::new("uart", log.clone()))
         .unwrap();
 
-    router.request_seed_net_assign(1).unwrap();
+    router.request_seed_net_assign(1, None).unwrap();
 
     let result = router.refresh_seed_net_assignment(1, 999, [0; 8]);
     assert_eq!(result, Err(SeedRefreshError::UnknownNetId));
@@ -839,7 +873,7 @@ fn seed_deregister_cleans_routes() {
         .unwrap();
 
     // Seed route through ESP
-    let assignment = router.request_seed_net_assign(1).unwrap();
+    let assignment = router.request_seed_net_assign(1, None).unwrap();
     let seed_net = assignment.net_id;
 
     // Verify routing works before deregister
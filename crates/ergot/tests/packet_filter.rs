@@ -0,0 +1,310 @@
+//! Packet filter tests: routers and edges with a `RuleTable`, denying
+//! frames on ingress and egress.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{
+    pin::pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address, FrameKind, HeaderSeq, Key, Priority, ProtocolError,
+    interface_manager::{
+        FrameProcessor, Interface, InterfaceSink, InterfaceState,
+        filter::{Rule, RuleTable, Verdict},
+        interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::{
+            direct_edge::{DirectEdge, EdgeFrameProcessor},
+            router::Router,
+        },
+        transports::tokio_cobs_stream,
+    },
+    net_stack::{ArcNetStack, ReqRespError},
+    traits::Endpoint,
+    well_known::ErgotPingEndpoint,
+    wire_frames,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use rand::SeedableRng;
+use serde::Serialize;
+use tokio::time::timeout;
+
+ergot::endpoint!(SecretEndpoint, u32, u32, "test/secret");
+
+type FilteredRouter = Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64, 0>;
+type RouterStack = ArcNetStack<
+    CriticalSectionRawMutex,
+    Router<
+        TokioStreamInterface,
+        rand::rngs::StdRng,
+        64,
+        64,
+        0,
+        ergot::interface_manager::admission::OpenAdmission,
+        RuleTable<4>,
+    >,
+>;
+
+const EDGE1: Address = Address {
+    network_id: 1,
+    node_id: 2,
+    port_id: 0,
+};
+const EDGE2: Address = Address {
+    network_id: 2,
+    node_id: 2,
+    port_id: 0,
+};
+const ROUTER_ON_1: Address = Address {
+    network_id: 1,
+    node_id: 1,
+    port_id: 0,
+};
+
+/// Start a router with the given rules, and two edges connected to it, on
+/// net_ids 1 and 2. Both edges serve ping.
+async fn router_with_edges(
+    table: RuleTable<4>,
+) -> (RouterStack, common::EdgeStack, common::EdgeStack) {
+    let router_stack = RouterStack::new_with_profile(
+        FilteredRouter::new(rand::rngs::StdRng::from_seed([0u8; 32])).with_packet_filter(table),
+    );
+    let (edge1, edge1_queue) = make_edge_stack();
+    let (edge2, edge2_queue) = make_edge_stack();
+
+    for (edge, queue) in [(&edge1, edge1_queue), (&edge2, edge2_queue)] {
+        let (e_read, r_write) = tokio::io::duplex(8192);
+        let (r_read, e_write) = tokio::io::duplex(8192);
+        tokio_cobs_stream::register_router(
+            router_stack.clone(),
+            r_read,
+            r_write,
+            512,
+            4096,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+            edge.clone(),
+            e_read,
+            e_write,
+            queue,
+            EdgeFrameProcessor::new(),
+            InterfaceState::Inactive,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        spawn_ping_server(edge);
+    }
+
+    // Pings from the router itself are never forwarded, and teach the edges
+    // their net_ids
+    ping_with_retry(&router_stack, EDGE1, 1).await;
+    ping_with_retry(&router_stack, EDGE2, 2).await;
+    wait_active(&edge1).await;
+    wait_active(&edge2).await;
+
+    (router_stack, edge1, edge2)
+}
+
+fn spawn_router_servers(stack: &RouterStack) {
+    tokio::spawn({
+        let stack = stack.clone();
+        async move {
+            let server = stack
+                .endpoints()
+                .bounded_server::<ErgotPingEndpoint, 4>(Some("ping"));
+            let server = pin!(server);
+            let mut hdl = server.attach();
+            loop {
+                let _ = hdl
+                    .serve(|val: &u32| {
+                        let v = *val;
+                        async move { v }
+                    })
+                    .await;
+            }
+        }
+    });
+    tokio::spawn({
+        let stack = stack.clone();
+        async move {
+            let server = stack.endpoints().bounded_server::<SecretEndpoint, 4>(None);
+            let server = pin!(server);
+            let mut hdl = server.attach();
+            loop {
+                let _ = hdl
+                    .serve(|val: &u32| {
+                        let v = *val + 1;
+                        async move { v }
+                    })
+                    .await;
+            }
+        }
+    });
+}
+
+/// Devices may reach the router, but not each other. The denied request is
+/// answered with `IseFiltered`, instead of timing out.
+#[tokio::test]
+async fn devices_may_not_reach_each_other() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let mut table = RuleTable::new(Verdict::Allow);
+    table.push(Rule::deny().egress().forwarded()).unwrap();
+    let (router, edge1, _edge2) = router_with_edges(table).await;
+    spawn_router_servers(&router);
+
+    let res = timeout(
+        Duration::from_secs(2),
+        edge1
+            .endpoints()
+            .request::<ErgotPingEndpoint>(EDGE2, &42, Some("ping")),
+    )
+    .await
+    .expect("denied request should be answered");
+    assert_eq!(res, Err(ReqRespError::Remote(ProtocolError::IseFiltered)));
+
+    assert_eq!(ping_with_retry(&edge1, ROUTER_ON_1, 7).await, 7);
+}
+
+/// Only the ping endpoint may be called from the first interface.
+#[tokio::test]
+async fn ingress_endpoint_allow_list() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let mut table = RuleTable::new(Verdict::Allow);
+    table
+        .push(
+            Rule::allow()
+                .ingress()
+                .on_interface(0)
+                .kind(FrameKind::ENDPOINT_REQ)
+                .key(Key(ErgotPingEndpoint::REQ_KEY.to_bytes())),
+        )
+        .unwrap();
+    table
+        .push(
+            Rule::deny()
+                .ingress()
+                .on_interface(0)
+                .kind(FrameKind::ENDPOINT_REQ),
+        )
+        .unwrap();
+    let (router, edge1, edge2) = router_with_edges(table).await;
+    spawn_router_servers(&router);
+
+    let secret = |edge: common::EdgeStack| async move {
+        timeout(
+            Duration::from_secs(2),
+            edge.endpoints()
+                .request::<SecretEndpoint>(ROUTER_ON_1, &1, None),
+        )
+        .await
+        .expect("request should be answered")
+    };
+
+    assert_eq!(
+        secret(edge1.clone()).await,
+        Err(ReqRespError::Remote(ProtocolError::IseFiltered))
+    );
+    assert_eq!(ping_with_retry(&edge1, ROUTER_ON_1, 3).await, 3);
+    // The second interface is not restricted
+    assert_eq!(secret(edge2).await, Ok(2));
+}
+
+// --- DirectEdge, with a sink that records errors ---
+
+#[derive(Clone, Default)]
+struct ErrSink {
+    errs: Arc<Mutex<Vec<(Address, ProtocolError)>>>,
+}
+
+impl InterfaceSink for ErrSink {
+    fn mtu(&self) -> u16 {
+        512
+    }
+    fn send_ty<T: Serialize>(&mut self, _hdr: &HeaderSeq, _body: &T) -> Result<usize, ()> {
+        Ok(0)
+    }
+    fn send_raw(&mut self, _hdr: &HeaderSeq, _body: &[u8]) -> Result<usize, ()> {
+        Ok(0)
+    }
+    fn send_err(&mut self, hdr: &HeaderSeq, err: ProtocolError) -> Result<usize, ()> {
+        self.errs.lock().unwrap().push((hdr.dst, err));
+        Ok(0)
+    }
+}
+
+struct ErrInterface;
+impl Interface for ErrInterface {
+    type Sink = ErrSink;
+}
+
+/// A DirectEdge denies requests on ingress, answering them with
+/// `IseFiltered`, and refuses to send topic messages.
+#[tokio::test]
+async fn direct_edge_filter() {
+    let sink = ErrSink::default();
+    let mut table = RuleTable::<2>::new(Verdict::Allow);
+    table
+        .push(Rule::deny().ingress().kind(FrameKind::ENDPOINT_REQ))
+        .unwrap();
+    table
+        .push(Rule::deny().egress().kind(FrameKind::TOPIC_MSG))
+        .unwrap();
+    let stack = ArcNetStack::<CriticalSectionRawMutex, _>::new_with_profile(
+        DirectEdge::<ErrInterface>::new_controller(
+            sink.clone(),
+            InterfaceState::Active {
+                net_id: 1,
+                node_id: 1,
+            },
+        )
+        .with_packet_filter(table),
+    );
+
+    let hdr = HeaderSeq {
+        src: Address {
+            network_id: 1,
+            node_id: 2,
+            port_id: 9,
+        },
+        dst: Address {
+            network_id: 1,
+            node_id: 1,
+            port_id: 4,
+        },
+        any_all: None,
+        seq_no: 17,
+        kind: FrameKind::ENDPOINT_REQ,
+        ttl: 16,
+        prio: Priority::NORMAL,
+    };
+    let frame =
+        wire_frames::encode_frame_ty(postcard::ser_flavors::StdVec::new(), &hdr, &42u32).unwrap();
+    let mut proc = EdgeFrameProcessor::new_controller(1);
+    proc.process_frame(&frame, &stack, ());
+
+    let errs = sink.errs.lock().unwrap().clone();
+    assert_eq!(errs, vec![(hdr.src, ProtocolError::IseFiltered)]);
+
+    ergot::topic!(DeniedTopic, u32, "test/denied");
+    assert!(stack.topics().broadcast::<DeniedTopic>(&1, None).is_err());
+
+    // Both denied frames are counted by the interface
+    let stats = stack.interface_stats(0).unwrap();
+    assert_eq!(stats.frames_rx, 1);
+    assert_eq!(stats.bytes_rx, frame.len() as u32);
+    assert_eq!(stats.filtered, 2);
+}
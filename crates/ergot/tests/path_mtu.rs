@@ -0,0 +1,136 @@
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address, NetStack,
+    interface_manager::{
+        InterfaceState, interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::EdgeFrameProcessor, profiles::null::Null, profiles::router::Router,
+        transports::tokio_cobs_stream,
+    },
+    net_stack::ArcNetStack,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use tokio::time::timeout;
+
+type RouterStackTy =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+#[tokio::test]
+async fn local_path_mtu_is_unbounded() {
+    static STACK: NetStack<CriticalSectionRawMutex, Null> = NetStack::new();
+
+    tokio::spawn(STACK.services().path_mtu_handler::<4>());
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let res = STACK
+        .discovery()
+        .discover_path_mtu(Address::unknown())
+        .await;
+    assert_eq!(res, Ok(u16::MAX));
+    assert_eq!(STACK.path_mtu(Address::unknown()), Some(u16::MAX));
+}
+
+#[tokio::test]
+async fn path_mtu_through_router() {
+    //  Edge1 <--duplex1 (256)--> Router <--duplex2 (128)--> Edge2
+    //
+    // Both edges have an MTU of 512 bytes towards the router
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router_stack: RouterStackTy = RouterStackTy::new();
+    let (edge1_stack, edge1_queue) = make_edge_stack();
+    let (edge2_stack, edge2_queue) = make_edge_stack();
+
+    let (e1_read, r1_write) = tokio::io::duplex(8192);
+    let (r1_read, e1_write) = tokio::io::duplex(8192);
+    let (e2_read, r2_write) = tokio::io::duplex(8192);
+    let (r2_read, e2_write) = tokio::io::duplex(8192);
+
+    for (read, write, mtu) in [(r1_read, r1_write, 256), (r2_read, r2_write, 128)] {
+        tokio_cobs_stream::register_router(
+            router_stack.clone(),
+            read,
+            write,
+            mtu,
+            4096,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+    for (stack, read, write, queue) in [
+        (&edge1_stack, e1_read, e1_write, edge1_queue),
+        (&edge2_stack, e2_read, e2_write, edge2_queue),
+    ] {
+        tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+            stack.clone(),
+            read,
+            write,
+            queue,
+            EdgeFrameProcessor::new(),
+            InterfaceState::Inactive,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    spawn_ping_server(&edge1_stack);
+    spawn_ping_server(&edge2_stack);
+    tokio::spawn(edge1_stack.services().path_mtu_handler::<4>());
+    tokio::spawn(edge2_stack.services().path_mtu_handler::<4>());
+    tokio::spawn(router_stack.services().path_mtu_handler::<4>());
+
+    let edge1_addr = Address {
+        network_id: 1,
+        node_id: 2,
+        port_id: 0,
+    };
+    let edge2_addr = Address {
+        network_id: 2,
+        node_id: 2,
+        port_id: 0,
+    };
+    ping_with_retry(&router_stack, edge1_addr, 0).await;
+    ping_with_retry(&router_stack, edge2_addr, 0).await;
+    wait_active(&edge1_stack).await;
+    wait_active(&edge2_stack).await;
+
+    let discover = |stack: &common::EdgeStack, addr: Address| {
+        let stack = stack.clone();
+        async move {
+            timeout(
+                Duration::from_secs(2),
+                stack.discovery().discover_path_mtu(addr),
+            )
+            .await
+            .unwrap()
+            .unwrap()
+        }
+    };
+
+    assert_eq!(edge1_stack.path_mtu(edge2_addr), None);
+    assert_eq!(discover(&edge1_stack, edge2_addr).await, 128);
+    assert_eq!(discover(&edge2_stack, edge1_addr).await, 256);
+    assert_eq!(edge1_stack.path_mtu(edge2_addr), Some(128));
+    assert_eq!(edge2_stack.path_mtu(edge1_addr), Some(256));
+
+    // Querying the router itself only crosses the edge's own interface
+    let router_addr = Address {
+        network_id: 2,
+        node_id: 1,
+        port_id: 0,
+    };
+    assert_eq!(discover(&edge2_stack, router_addr).await, 512);
+
+    edge1_stack.forget_path_mtu(edge2_addr);
+    assert_eq!(edge1_stack.path_mtu(edge2_addr), None);
+}
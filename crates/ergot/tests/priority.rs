@@ -0,0 +1,182 @@
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use bbqueue::traits::bbqhdl::BbqHandle;
+use common::{make_edge_stack, spawn_ping_server, wait_active};
+use ergot::{
+    Address, FrameKind, HeaderSeq, Priority,
+    interface_manager::{
+        InterfaceSink, InterfaceState,
+        interface_impls::tokio_stream::{TokioStreamInterface, TokioStreamPrioInterface},
+        profiles::{direct_edge::EdgeFrameProcessor, router::Router},
+        transports::tokio_cobs_stream,
+        utils::{
+            cobs_stream,
+            priority::{self, read_cobs_frame},
+            std::new_std_queue,
+        },
+    },
+    net_stack::ArcNetStack,
+    wire_frames::de_frame,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use tokio::time::timeout;
+
+ergot::endpoint!(Stop, u32, u32, "test/stop");
+ergot::topic!(LogTopic, u32, "test/log");
+
+fn hdr(prio: Priority) -> HeaderSeq {
+    HeaderSeq {
+        src: Address {
+            network_id: 1,
+            node_id: 1,
+            port_id: 1,
+        },
+        dst: Address {
+            network_id: 2,
+            node_id: 2,
+            port_id: 2,
+        },
+        any_all: None,
+        seq_no: 0,
+        kind: FrameKind::TOPIC_MSG,
+        ttl: 16,
+        prio,
+    }
+}
+
+#[tokio::test]
+async fn sink_drains_by_priority() {
+    let qs: [_; 3] = core::array::from_fn(|_| new_std_queue(1024));
+    let mut sink = priority::Sink::new(
+        qs.each_ref()
+            .map(|q| cobs_stream::Sink::new_from_handle(q.clone(), 256)),
+    );
+    let rxs = qs.each_ref().map(BbqHandle::stream_consumer);
+    assert_eq!(sink.mtu(), 256);
+
+    // A burst of low priority frames queued first
+    for i in 0..3u32 {
+        sink.send_ty(&hdr(Priority::LOW), &i).unwrap();
+    }
+    sink.send_ty(&hdr(Priority::NORMAL), &10u32).unwrap();
+    // Priorities without a queue of their own share the highest queue
+    sink.send_ty(&hdr(Priority::CRITICAL), &20u32).unwrap();
+    sink.send_ty(&hdr(Priority::HIGH), &30u32).unwrap();
+
+    let mut sent = vec![];
+    for _ in 0..6 {
+        let (grant, len) = read_cobs_frame(&rxs).await;
+        let frame = cobs::decode_vec(&grant[..len - 1]).unwrap();
+        grant.release(len);
+        let frame = de_frame(&frame).unwrap();
+        let val: u32 = postcard::from_bytes(frame.body.unwrap()).unwrap();
+        sent.push((frame.hdr.prio, val));
+    }
+    assert_eq!(
+        sent,
+        [
+            (Priority::CRITICAL, 20),
+            (Priority::HIGH, 30),
+            (Priority::NORMAL, 10),
+            (Priority::LOW, 0),
+            (Priority::LOW, 1),
+            (Priority::LOW, 2),
+        ]
+    );
+}
+
+type RouterStackTy = ArcNetStack<
+    CriticalSectionRawMutex,
+    Router<TokioStreamPrioInterface<4>, rand::rngs::StdRng, 64, 64>,
+>;
+
+#[tokio::test]
+async fn priority_through_router() {
+    //  Edge <--duplex--> Router (with priority queues)
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router_stack: RouterStackTy = RouterStackTy::new();
+    let (edge_stack, edge_queue) = make_edge_stack();
+
+    let (e_read, r_write) = tokio::io::duplex(8192);
+    let (r_read, e_write) = tokio::io::duplex(8192);
+
+    tokio_cobs_stream::register_router_prio(
+        router_stack.clone(),
+        r_read,
+        r_write,
+        512,
+        4096,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        edge_stack.clone(),
+        e_read,
+        e_write,
+        edge_queue,
+        EdgeFrameProcessor::new(),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    spawn_ping_server(&edge_stack);
+    let edge_addr = Address {
+        network_id: 1,
+        node_id: 2,
+        port_id: 0,
+    };
+    common::ping_with_retry(&router_stack, edge_addr, 0).await;
+    wait_active(&edge_stack).await;
+
+    // The request and its response keep the priority of the request
+    let srv = edge_stack.endpoints().bounded_server::<Stop, 4>(None);
+    let srv = pin!(srv);
+    let mut srv = srv.attach();
+    let server = async {
+        let req = srv.recv_manual().await.unwrap();
+        assert_eq!(req.hdr.prio, Priority::CRITICAL);
+        edge_stack
+            .endpoints()
+            .respond_owned::<Stop>(&req.hdr, &(req.t + 1))
+            .unwrap();
+    };
+    let (resp, ()) = tokio::join!(
+        timeout(
+            Duration::from_secs(2),
+            router_stack
+                .endpoints()
+                .with_priority(Priority::CRITICAL)
+                .request_full::<Stop>(edge_addr, &1, None),
+        ),
+        server,
+    );
+    let resp = resp.unwrap().unwrap();
+    assert_eq!(resp.t, 2);
+    assert_eq!(resp.hdr.prio, Priority::CRITICAL);
+
+    // Topic messages too
+    let sub = router_stack
+        .topics()
+        .heap_bounded_receiver::<LogTopic>(8, None);
+    let sub = pin!(sub);
+    let mut sub = sub.subscribe();
+    edge_stack
+        .topics()
+        .with_priority(Priority::LOW)
+        .broadcast::<LogTopic>(&5, None)
+        .unwrap();
+    let msg = timeout(Duration::from_secs(2), sub.recv()).await.unwrap();
+    assert_eq!(msg.t, 5);
+    assert_eq!(msg.hdr.prio, Priority::LOW);
+}
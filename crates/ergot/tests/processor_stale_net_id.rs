@@ -12,7 +12,7 @@
 use std::sync::{Arc, Mutex};
 
 use ergot::{
-    Address, FrameKind, HeaderSeq, ProtocolError,
+    Address, FrameKind, HeaderSeq, Priority, ProtocolError,
     interface_manager::{
         FrameProcessor, Interface, InterfaceSink, InterfaceState, Profile,
         profiles::router::{Router, RouterFrameProcessor},
@@ -41,17 +41,17 @@ impl InterfaceSink for CaptureSink {
     fn mtu(&self) -> u16 {
         2048
     }
-    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, _body: &T) -> Result<(), ()> {
+    fn send_ty<T: Serialize>(&mut self, hdr: &HeaderSeq, _body: &T) -> Result<usize, ()> {
         self.frames.lock().unwrap().push((hdr.src, hdr.dst));
-        Ok(())
+        Ok(0)
     }
-    fn send_raw(&mut self, hdr: &HeaderSeq, _body: &[u8]) -> Result<(), ()> {
+    fn send_raw(&mut self, hdr: &HeaderSeq, _body: &[u8]) -> Result<usize, ()> {
         self.frames.lock().unwrap().push((hdr.src, hdr.dst));
-        Ok(())
+        Ok(0)
     }
-    fn send_err(&mut self, hdr: &HeaderSeq, _err: ProtocolError) -> Result<(), ()> {
+    fn send_err(&mut self, hdr: &HeaderSeq, _err: ProtocolError) -> Result<usize, ()> {
         self.frames.lock().unwrap().push((hdr.src, hdr.dst));
-        Ok(())
+        Ok(0)
     }
 }
 
@@ -79,6 +79,7 @@ fn make_frame(src_net: u16, src_node: u8, dst_net: u16, dst_node: u8, dst_port:
         seq_no: 0,
         kind: FrameKind::ENDPOINT_REQ,
         ttl: 16,
+        prio: Priority::NORMAL,
     };
     wire_frames::encode_frame_ty(postcard::ser_flavors::StdVec::new(), &hdr, &42u32).unwrap()
 }
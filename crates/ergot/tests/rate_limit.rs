@@ -0,0 +1,155 @@
+//! Rate limiting tests: a router with `TokenBuckets`, and an edge flooding
+//! it with topic broadcasts.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address, FrameKind,
+    interface_manager::{
+        InterfaceState,
+        interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::{direct_edge::EdgeFrameProcessor, router::Router},
+        rate_limit::{DroppedFrames, Limits, Rate, TokenBuckets},
+        transports::tokio_cobs_stream,
+    },
+    net_stack::ArcNetStack,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use rand::SeedableRng;
+use tokio::time::{sleep, timeout};
+
+ergot::topic!(NoisyTopic, u32, "test/noisy");
+
+type RouterStack = ArcNetStack<
+    CriticalSectionRawMutex,
+    Router<
+        TokioStreamInterface,
+        rand::rngs::StdRng,
+        64,
+        64,
+        0,
+        ergot::interface_manager::admission::OpenAdmission,
+        ergot::interface_manager::filter::AllowAll,
+        TokenBuckets<8, 8>,
+    >,
+>;
+
+const EDGE1: Address = Address {
+    network_id: 1,
+    node_id: 2,
+    port_id: 0,
+};
+const EDGE2: Address = Address {
+    network_id: 2,
+    node_id: 2,
+    port_id: 0,
+};
+
+/// Each device may send 5 topic messages at once, then one per second
+fn limiter() -> TokenBuckets<8, 8> {
+    TokenBuckets::new(
+        Limits::unlimited(),
+        Limits::unlimited().with_kind(FrameKind::TOPIC_MSG, Some(Rate::new(1, 5))),
+    )
+}
+
+/// Receive messages until none arrive for a while
+macro_rules! drain {
+    ($sub:expr) => {{
+        let mut n = 0;
+        while timeout(Duration::from_millis(300), $sub.recv())
+            .await
+            .is_ok()
+        {
+            n += 1;
+        }
+        n
+    }};
+}
+
+#[tokio::test]
+async fn noisy_device_is_limited() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router = RouterStack::new_with_profile(
+        Router::new(rand::rngs::StdRng::from_seed([0u8; 32])).with_rate_limiter(limiter()),
+    );
+    let (edge1, edge1_queue) = make_edge_stack();
+    let (edge2, edge2_queue) = make_edge_stack();
+
+    for (edge, queue) in [(&edge1, edge1_queue), (&edge2, edge2_queue)] {
+        let (e_read, r_write) = tokio::io::duplex(8192);
+        let (r_read, e_write) = tokio::io::duplex(8192);
+        tokio_cobs_stream::register_router(router.clone(), r_read, r_write, 512, 4096, None, None)
+            .await
+            .unwrap();
+        tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+            edge.clone(),
+            e_read,
+            e_write,
+            queue,
+            EdgeFrameProcessor::new(),
+            InterfaceState::Inactive,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        spawn_ping_server(edge);
+    }
+    ping_with_retry(&router, EDGE1, 1).await;
+    ping_with_retry(&router, EDGE2, 2).await;
+    wait_active(&edge1).await;
+    wait_active(&edge2).await;
+
+    let router_sub = router
+        .topics()
+        .heap_bounded_receiver::<NoisyTopic>(32, None);
+    let router_sub = pin!(router_sub);
+    let mut router_sub = router_sub.subscribe();
+    let edge2_sub = edge2.topics().heap_bounded_receiver::<NoisyTopic>(32, None);
+    let edge2_sub = pin!(edge2_sub);
+    let mut edge2_sub = edge2_sub.subscribe();
+    // Let the subscriptions settle
+    sleep(Duration::from_millis(50)).await;
+
+    for i in 0..20 {
+        edge1.topics().broadcast::<NoisyTopic>(&i, None).unwrap();
+    }
+
+    // Only the burst gets through, both to the router and to the other edge
+    let got = drain!(router_sub);
+    assert!((5..=6).contains(&got), "router got {got}");
+    let got = drain!(edge2_sub);
+    assert!((5..=6).contains(&got), "edge2 got {got}");
+    let dropped = router.manage_profile(|im| im.rate_limiter_mut().dropped());
+    assert!(
+        matches!(
+            dropped,
+            DroppedFrames {
+                interface: 0,
+                source: 14..=15
+            }
+        ),
+        "{dropped:?}"
+    );
+    // Also counted by the interface the frames came from
+    let stats = router.interface_stats(0).unwrap();
+    assert_eq!(stats.interface, 0);
+    assert!((14..=15).contains(&stats.rate_limited), "{stats:?}");
+
+    // Other devices are not affected
+    edge2.topics().broadcast::<NoisyTopic>(&100, None).unwrap();
+    let msg = timeout(Duration::from_secs(2), router_sub.recv())
+        .await
+        .unwrap();
+    assert_eq!(msg.t, 100);
+    // Nor are other kinds of frames from the noisy device
+    assert_eq!(ping_with_retry(&edge1, EDGE2, 3).await, 3);
+}
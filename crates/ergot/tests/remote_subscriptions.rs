@@ -0,0 +1,161 @@
+//! Remote subscription tests: an edge publishes a topic to a subscriber on
+//! another edge, behind a router, without broadcasting it.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address,
+    interface_manager::{
+        InterfaceState, interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::EdgeFrameProcessor, profiles::router::Router,
+        transports::tokio_cobs_stream,
+    },
+    net_stack::{ArcNetStack, publisher::RemoteSubscriptionClientError},
+    well_known::RemoteSubscriptionError,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use rand::SeedableRng;
+use tokio::time::timeout;
+
+ergot::topic!(TelemetryTopic, u32, "test/telemetry");
+ergot::topic!(OtherTopic, u32, "test/other");
+
+type RouterStack =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+const EDGE1: Address = Address {
+    network_id: 1,
+    node_id: 2,
+    port_id: 0,
+};
+const EDGE2: Address = Address {
+    network_id: 2,
+    node_id: 2,
+    port_id: 0,
+};
+
+#[tokio::test]
+async fn publish_to_remote_subscriber() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router =
+        RouterStack::new_with_profile(Router::new(rand::rngs::StdRng::from_seed([0u8; 32])));
+    let (edge1, edge1_queue) = make_edge_stack();
+    let (edge2, edge2_queue) = make_edge_stack();
+
+    for (edge, queue) in [(&edge1, edge1_queue), (&edge2, edge2_queue)] {
+        let (e_read, r_write) = tokio::io::duplex(8192);
+        let (r_read, e_write) = tokio::io::duplex(8192);
+        tokio_cobs_stream::register_router(router.clone(), r_read, r_write, 512, 4096, None, None)
+            .await
+            .unwrap();
+        tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+            edge.clone(),
+            e_read,
+            e_write,
+            queue,
+            EdgeFrameProcessor::new(),
+            InterfaceState::Inactive,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        spawn_ping_server(edge);
+    }
+    ping_with_retry(&router, EDGE1, 1).await;
+    ping_with_retry(&router, EDGE2, 2).await;
+    wait_active(&edge1).await;
+    wait_active(&edge2).await;
+
+    let publisher = edge1
+        .topics()
+        .publisher::<TelemetryTopic, _, 2>(rand::rngs::StdRng::seed_from_u64(1));
+    let server = pin!(publisher.serve::<4>());
+
+    let test = async {
+        // A broadcast receiver on the router must not see published messages
+        let snoop = router
+            .topics()
+            .heap_bounded_receiver::<TelemetryTopic>(8, None);
+        let snoop = pin!(snoop);
+        let mut snoop = snoop.subscribe();
+
+        let rx = edge2
+            .topics()
+            .heap_bounded_receiver::<TelemetryTopic>(8, None);
+        let rx = pin!(rx);
+        let mut rx = rx.subscribe_unicast();
+
+        let mut sub = edge2
+            .topics()
+            .remote_subscribe::<TelemetryTopic>(EDGE1, rx.port())
+            .await
+            .unwrap();
+        assert_eq!(sub.publisher(), EDGE1);
+        assert_eq!(publisher.subscribers(), 1);
+
+        assert_eq!(publisher.publish(&7), 1);
+        let msg = timeout(Duration::from_secs(2), rx.recv()).await.unwrap();
+        assert_eq!(msg.t, 7);
+        assert_eq!(msg.hdr.src.network_id, EDGE1.network_id);
+        assert!(
+            timeout(Duration::from_millis(200), snoop.recv())
+                .await
+                .is_err()
+        );
+
+        // A new subscription may be refreshed immediately, after which the
+        // publisher rejects refreshes until the lease runs low
+        sub.refresh().await.unwrap();
+        assert!(sub.lease().expires_seconds > sub.lease().min_refresh_seconds);
+        assert_eq!(
+            sub.refresh().await.unwrap_err(),
+            RemoteSubscriptionClientError::Denied(RemoteSubscriptionError::TooSoon)
+        );
+
+        // Only the published topic may be subscribed to: the request isn't
+        // delivered to the publisher, so it is never answered
+        let other = edge2
+            .topics()
+            .remote_subscribe::<OtherTopic>(EDGE1, rx.port());
+        assert!(timeout(Duration::from_millis(200), other).await.is_err());
+
+        // The subscriber table is bounded
+        let _sub2 = router
+            .topics()
+            .remote_subscribe::<TelemetryTopic>(EDGE1, 100)
+            .await
+            .unwrap();
+        let err = router
+            .topics()
+            .remote_subscribe::<TelemetryTopic>(EDGE1, 101)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            RemoteSubscriptionClientError::Denied(RemoteSubscriptionError::TableFull)
+        );
+
+        sub.unsubscribe().await.unwrap();
+        assert_eq!(publisher.subscribers(), 1);
+        assert_eq!(publisher.publish(&8), 1);
+        assert!(
+            timeout(Duration::from_millis(200), rx.recv())
+                .await
+                .is_err()
+        );
+    };
+
+    tokio::select! {
+        _ = server => unreachable!(),
+        _ = test => {}
+    }
+}
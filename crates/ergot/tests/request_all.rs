@@ -0,0 +1,230 @@
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use common::{EdgeStack, make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address, NetStack,
+    interface_manager::{
+        InterfaceState, interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::EdgeFrameProcessor, profiles::null::Null, profiles::router::Router,
+        transports::tokio_cobs_stream,
+    },
+    net_stack::ArcNetStack,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+
+ergot::endpoint!(FirmwareVersion, (), u32, "test/firmware");
+
+type TestNetStack = NetStack<CriticalSectionRawMutex, Null>;
+
+const WAIT: Duration = Duration::from_millis(100);
+
+#[tokio::test]
+async fn local_request_all_collects_every_server() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv1 = STACK
+        .endpoints()
+        .bounded_server::<FirmwareVersion, 4>(Some("a"));
+    let srv1 = pin!(srv1);
+    let mut srv1 = srv1.attach();
+    let srv2 = STACK
+        .endpoints()
+        .bounded_server::<FirmwareVersion, 4>(Some("b"));
+    let srv2 = pin!(srv2);
+    let mut srv2 = srv2.attach();
+
+    let servers = async {
+        srv1.serve_blocking(|_| 1).await.unwrap();
+        srv2.serve_blocking(|_| 2).await.unwrap();
+    };
+    let (resps, ()) = tokio::join!(
+        STACK
+            .endpoints()
+            .request_all::<FirmwareVersion, 8>(Address::unknown(), &(), None, WAIT),
+        servers,
+    );
+    let mut resps: Vec<u32> = resps.unwrap().into_iter().map(|r| r.t).collect();
+    resps.sort();
+    assert_eq!(resps, [1, 2]);
+
+    // Only servers with the given name respond
+    let (resps, res) = tokio::join!(
+        STACK.endpoints().request_all::<FirmwareVersion, 8>(
+            Address::unknown(),
+            &(),
+            Some("b"),
+            WAIT
+        ),
+        srv2.serve_blocking(|_| 2),
+    );
+    res.unwrap();
+    let resps = resps.unwrap();
+    assert_eq!(resps.len(), 1);
+    assert_eq!(resps[0].t, 2);
+    assert_eq!(resps[0].hdr.src.port_id, srv2.port());
+}
+
+#[tokio::test]
+async fn request_all_stops_at_max() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv1 = STACK.endpoints().bounded_server::<FirmwareVersion, 4>(None);
+    let srv1 = pin!(srv1);
+    let mut srv1 = srv1.attach();
+    let srv2 = STACK.endpoints().bounded_server::<FirmwareVersion, 4>(None);
+    let srv2 = pin!(srv2);
+    let mut srv2 = srv2.attach();
+
+    let servers = async {
+        srv1.serve_blocking(|_| 1).await.unwrap();
+        // The response socket only had room for one response
+        srv2.serve_blocking(|_| 2).await.unwrap_err();
+    };
+    // A long timeout, which is not reached once `max` responses arrived
+    let (resps, ()) = tokio::join!(
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            STACK.endpoints().request_all::<FirmwareVersion, 1>(
+                Address::unknown(),
+                &(),
+                None,
+                Duration::from_secs(10),
+            )
+        ),
+        servers,
+    );
+    assert_eq!(resps.unwrap().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn request_all_without_servers_is_empty() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let resps = STACK
+        .endpoints()
+        .request_all::<FirmwareVersion, 8>(Address::unknown(), &(), None, WAIT)
+        .await
+        .unwrap();
+    assert!(resps.is_empty());
+}
+
+type RouterStackTy =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+fn spawn_firmware_server(stack: &EdgeStack, version: u32) {
+    tokio::spawn({
+        let stack = stack.clone();
+        async move {
+            let srv = stack.endpoints().bounded_server::<FirmwareVersion, 4>(None);
+            let srv = pin!(srv);
+            let mut srv = srv.attach();
+            loop {
+                let _ = srv.serve_blocking(|_| version).await;
+            }
+        }
+    });
+}
+
+#[tokio::test]
+async fn request_all_through_router() {
+    //  Edge1 <--duplex1--> Router <--duplex2--> Edge2
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router_stack: RouterStackTy = RouterStackTy::new();
+    let (edge1_stack, edge1_queue) = make_edge_stack();
+    let (edge2_stack, edge2_queue) = make_edge_stack();
+
+    let (e1_read, r1_write) = tokio::io::duplex(8192);
+    let (r1_read, e1_write) = tokio::io::duplex(8192);
+    let (e2_read, r2_write) = tokio::io::duplex(8192);
+    let (r2_read, e2_write) = tokio::io::duplex(8192);
+
+    for (read, write) in [(r1_read, r1_write), (r2_read, r2_write)] {
+        tokio_cobs_stream::register_router(
+            router_stack.clone(),
+            read,
+            write,
+            512,
+            4096,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+    for (stack, read, write, queue) in [
+        (&edge1_stack, e1_read, e1_write, edge1_queue),
+        (&edge2_stack, e2_read, e2_write, edge2_queue),
+    ] {
+        tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+            stack.clone(),
+            read,
+            write,
+            queue,
+            EdgeFrameProcessor::new(),
+            InterfaceState::Inactive,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    spawn_ping_server(&edge1_stack);
+    spawn_ping_server(&edge2_stack);
+    let edge1_addr = Address {
+        network_id: 1,
+        node_id: 2,
+        port_id: 0,
+    };
+    let edge2_addr = Address {
+        network_id: 2,
+        node_id: 2,
+        port_id: 0,
+    };
+    ping_with_retry(&router_stack, edge1_addr, 0).await;
+    ping_with_retry(&router_stack, edge2_addr, 0).await;
+    wait_active(&edge1_stack).await;
+    wait_active(&edge2_stack).await;
+
+    spawn_firmware_server(&edge1_stack, 10);
+    spawn_firmware_server(&edge2_stack, 20);
+    // Let the servers attach first
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // From the router, both edges respond
+    let resps = router_stack
+        .endpoints()
+        .heap_request_all::<FirmwareVersion>(
+            Address::unknown(),
+            &(),
+            None,
+            Duration::from_millis(500),
+            8,
+        )
+        .await
+        .unwrap();
+    let mut found: Vec<(u16, u32)> = resps.iter().map(|r| (r.hdr.src.network_id, r.t)).collect();
+    found.sort();
+    assert_eq!(found, [(1, 10), (2, 20)]);
+
+    // From an edge, the broadcast is flooded through the router to the other edge
+    let resps = edge1_stack
+        .endpoints()
+        .request_all::<FirmwareVersion, 8>(
+            Address::unknown(),
+            &(),
+            None,
+            Duration::from_millis(500),
+        )
+        .await
+        .unwrap();
+    let mut found: Vec<u32> = resps.iter().map(|r| r.t).collect();
+    found.sort();
+    assert_eq!(found, [10, 20]);
+}
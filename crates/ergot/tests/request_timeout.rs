@@ -0,0 +1,100 @@
+use std::{
+    pin::pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use ergot::{
+    Address, NetStack,
+    interface_manager::profiles::null::Null,
+    net_stack::{ReqRespError, endpoints::RetryPolicy},
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use tokio::{spawn, time::sleep};
+
+ergot::endpoint!(FlakyEndpoint, u32, u32, "test/flaky");
+
+type TestNetStack = NetStack<CriticalSectionRawMutex, Null>;
+
+#[tokio::test]
+async fn request_times_out_without_response() {
+    static STACK: TestNetStack = NetStack::new();
+
+    // A server that accepts requests but never responds
+    let srv = STACK.endpoints().bounded_server::<FlakyEndpoint, 4>(None);
+    let srv = pin!(srv);
+    let _hdl = srv.attach();
+
+    let res = STACK
+        .endpoints()
+        .request_with_deadline::<FlakyEndpoint>(
+            Address::unknown(),
+            &1,
+            None,
+            Duration::from_millis(50),
+        )
+        .await;
+    assert_eq!(res, Err(ReqRespError::Timeout));
+}
+
+#[tokio::test]
+async fn request_retries_until_response() {
+    static STACK: TestNetStack = NetStack::new();
+    static SEEN: AtomicUsize = AtomicUsize::new(0);
+
+    let tsk = spawn(async {
+        let srv = STACK.endpoints().bounded_server::<FlakyEndpoint, 4>(None);
+        let srv = pin!(srv);
+        let mut hdl = srv.attach();
+        loop {
+            // Drop the first two requests on the floor
+            let req = hdl.recv_manual().await.unwrap();
+            if SEEN.fetch_add(1, Ordering::Relaxed) < 2 {
+                continue;
+            }
+            STACK
+                .endpoints()
+                .respond_owned::<FlakyEndpoint>(&req.hdr, &(req.t * 2))
+                .unwrap();
+            break;
+        }
+    });
+    sleep(Duration::from_millis(50)).await;
+
+    let client = STACK
+        .endpoints()
+        .client::<FlakyEndpoint>(Address::unknown(), None)
+        .with_deadline(Duration::from_millis(50))
+        .with_retry(
+            RetryPolicy::attempts(3)
+                .with_backoff(Duration::from_millis(5), Duration::from_millis(20)),
+        );
+    let res = client.request(&21).await;
+    assert_eq!(res, Ok(42));
+    assert_eq!(SEEN.load(Ordering::Relaxed), 3);
+    tsk.await.unwrap();
+}
+
+#[tokio::test]
+async fn request_gives_up_after_attempts() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let client = STACK
+        .endpoints()
+        .client::<FlakyEndpoint>(Address::unknown(), None)
+        .with_deadline(Duration::from_millis(20))
+        .with_retry(RetryPolicy::attempts(2));
+
+    {
+        let srv = STACK.endpoints().bounded_server::<FlakyEndpoint, 4>(None);
+        let srv = pin!(srv);
+        let _hdl = srv.attach();
+        assert_eq!(client.request(&1).await, Err(ReqRespError::Timeout));
+    }
+
+    // Routing errors are not retried
+    assert!(matches!(
+        client.request(&1).await,
+        Err(ReqRespError::Local(_))
+    ));
+}
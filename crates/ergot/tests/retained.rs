@@ -0,0 +1,148 @@
+//! Retained message tests: an edge keeps the last message of a topic, and
+//! sends it to another edge, behind a router, that connects late.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address,
+    interface_manager::{
+        InterfaceState, interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::EdgeFrameProcessor, profiles::router::Router,
+        transports::tokio_cobs_stream,
+    },
+    net_stack::{
+        ArcNetStack,
+        retained::{HeapRetained, RetainedStore},
+    },
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use rand::SeedableRng;
+use tokio::time::timeout;
+
+ergot::topic!(ConfigTopic, u32, "test/config");
+ergot::topic!(StatusTopic, u32, "test/status");
+
+type RouterStack =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+const EDGE1: Address = Address {
+    network_id: 1,
+    node_id: 2,
+    port_id: 0,
+};
+const EDGE2: Address = Address {
+    network_id: 2,
+    node_id: 2,
+    port_id: 0,
+};
+
+#[tokio::test]
+async fn late_subscribers_get_retained_messages() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router =
+        RouterStack::new_with_profile(Router::new(rand::rngs::StdRng::from_seed([0u8; 32])));
+    let (edge1, edge1_queue) = make_edge_stack();
+    let (edge2, edge2_queue) = make_edge_stack();
+
+    for (edge, queue) in [(&edge1, edge1_queue), (&edge2, edge2_queue)] {
+        let (e_read, r_write) = tokio::io::duplex(8192);
+        let (r_read, e_write) = tokio::io::duplex(8192);
+        tokio_cobs_stream::register_router(router.clone(), r_read, r_write, 512, 4096, None, None)
+            .await
+            .unwrap();
+        tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+            edge.clone(),
+            e_read,
+            e_write,
+            queue,
+            EdgeFrameProcessor::new(),
+            InterfaceState::Inactive,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        spawn_ping_server(edge);
+    }
+    ping_with_retry(&router, EDGE1, 1).await;
+    ping_with_retry(&router, EDGE2, 2).await;
+    wait_active(&edge1).await;
+    wait_active(&edge2).await;
+
+    let store = RetainedStore::<CriticalSectionRawMutex, _>::new(HeapRetained::new());
+    edge1
+        .topics()
+        .broadcast_retained::<ConfigTopic>(&1, None, &store)
+        .unwrap();
+    edge1
+        .topics()
+        .broadcast_retained::<ConfigTopic>(&2, None, &store)
+        .unwrap();
+    assert_eq!(store.get::<ConfigTopic>(), Some(2));
+
+    let handler = pin!(edge1.services().retained_handler::<4, 64>(&store));
+    let publisher = edge1
+        .topics()
+        .publisher::<ConfigTopic, _, 2>(rand::rngs::StdRng::seed_from_u64(1));
+    let server = pin!(publisher.serve_with_retained::<4>(&store));
+
+    let test = async {
+        let rx = edge2.topics().heap_bounded_receiver::<ConfigTopic>(8, None);
+        let rx = pin!(rx);
+        let mut rx = rx.subscribe_unicast();
+
+        // On request, only the last message is sent
+        let sent = edge2
+            .topics()
+            .request_retained::<ConfigTopic>(EDGE1, rx.port())
+            .await
+            .unwrap();
+        assert!(sent);
+        let msg = timeout(Duration::from_secs(2), rx.recv()).await.unwrap();
+        assert_eq!(msg.t, 2);
+        assert_eq!(msg.hdr.src.network_id, EDGE1.network_id);
+
+        // Nothing is sent for topics without a retained message
+        let sent = edge2
+            .topics()
+            .request_retained::<StatusTopic>(EDGE1, rx.port())
+            .await
+            .unwrap();
+        assert!(!sent);
+
+        // A new remote subscriber gets the retained message right away
+        let _sub = edge2
+            .topics()
+            .remote_subscribe::<ConfigTopic>(EDGE1, rx.port())
+            .await
+            .unwrap();
+        let msg = timeout(Duration::from_secs(2), rx.recv()).await.unwrap();
+        assert_eq!(msg.t, 2);
+
+        store.forget::<ConfigTopic>();
+        let sent = edge2
+            .topics()
+            .request_retained::<ConfigTopic>(EDGE1, rx.port())
+            .await
+            .unwrap();
+        assert!(!sent);
+        assert!(
+            timeout(Duration::from_millis(200), rx.recv())
+                .await
+                .is_err()
+        );
+    };
+
+    tokio::select! {
+        _ = handler => unreachable!(),
+        _ = server => unreachable!(),
+        _ = test => {}
+    }
+}
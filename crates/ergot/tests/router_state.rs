@@ -0,0 +1,102 @@
+//! Router introspection tests: an edge reads the interfaces of the router it
+//! is connected to, through the router endpoint.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::pin::pin;
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address,
+    interface_manager::{
+        InterfaceState, interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::EdgeFrameProcessor, profiles::router::Router,
+        transports::tokio_cobs_stream,
+    },
+    net_stack::ArcNetStack,
+    well_known::SlotEntry,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use rand::SeedableRng;
+
+type RouterStack =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+const ROUTER: Address = Address {
+    network_id: 1,
+    node_id: 1,
+    port_id: 0,
+};
+
+const EDGE: Address = Address {
+    network_id: 1,
+    node_id: 2,
+    port_id: 0,
+};
+
+#[tokio::test]
+async fn edge_reads_router_state() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router =
+        RouterStack::new_with_profile(Router::new(rand::rngs::StdRng::from_seed([0u8; 32])));
+    let (edge, edge_queue) = make_edge_stack();
+
+    let (e_read, r_write) = tokio::io::duplex(8192);
+    let (r_read, e_write) = tokio::io::duplex(8192);
+    let ident =
+        tokio_cobs_stream::register_router(router.clone(), r_read, r_write, 512, 4096, None, None)
+            .await
+            .unwrap();
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        edge.clone(),
+        e_read,
+        e_write,
+        edge_queue,
+        EdgeFrameProcessor::new(),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    spawn_ping_server(&edge);
+    ping_with_retry(&router, EDGE, 1).await;
+    wait_active(&edge).await;
+
+    let router_handler = pin!(router.services().router_handler::<4>());
+    let edge_handler = pin!(edge.services().router_handler::<4>());
+    let test = async {
+        let state = edge.discovery().discover_router(ROUTER).await.unwrap();
+        assert_eq!(
+            state.slots,
+            [SlotEntry {
+                ident,
+                net_id: 1,
+                state: InterfaceState::Active {
+                    net_id: 1,
+                    node_id: 1,
+                },
+            }]
+        );
+        assert!(state.seed_routes.is_empty());
+        assert!(state.node_claims.is_empty());
+        assert!(state.upstream.is_none());
+
+        // An edge isn't a router, and has nothing to report
+        let state = router.discovery().discover_router(EDGE).await.unwrap();
+        assert!(state.slots.is_empty());
+        assert!(state.upstream.is_none());
+    };
+
+    // Poll the handlers first, so that they are listening before any request
+    tokio::select! {
+        biased;
+        _ = router_handler => unreachable!(),
+        _ = edge_handler => unreachable!(),
+        _ = test => {}
+    }
+}
@@ -0,0 +1,186 @@
+//! Schema introspection tests: a router lists the topics and endpoints of an
+//! edge, with the schemas of their message types.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address, FrameKind, Key,
+    interface_manager::{
+        InterfaceState, interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::EdgeFrameProcessor, profiles::router::Router,
+        transports::tokio_cobs_stream,
+    },
+    net_stack::ArcNetStack,
+    traits::{Endpoint, Topic},
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use postcard_schema::{Schema, schema::owned::OwnedNamedType};
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Schema, Clone, PartialEq)]
+pub struct Sample {
+    pub channel: u8,
+    pub value: i32,
+}
+
+ergot::topic!(SampleTopic, Sample, "test/sample");
+ergot::endpoint!(ScaleEndpoint, Sample, i64, "test/scale");
+
+type RouterStack =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+const EDGE: Address = Address {
+    network_id: 1,
+    node_id: 2,
+    port_id: 0,
+};
+
+#[tokio::test]
+async fn list_edge_schemas() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router =
+        RouterStack::new_with_profile(Router::new(rand::rngs::StdRng::from_seed([0u8; 32])));
+    let (edge, edge_queue) = make_edge_stack();
+
+    let (e_read, r_write) = tokio::io::duplex(8192);
+    let (r_read, e_write) = tokio::io::duplex(8192);
+    tokio_cobs_stream::register_router(router.clone(), r_read, r_write, 512, 4096, None, None)
+        .await
+        .unwrap();
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        edge.clone(),
+        e_read,
+        e_write,
+        edge_queue,
+        EdgeFrameProcessor::new(),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    spawn_ping_server(&edge);
+    ping_with_retry(&router, EDGE, 1).await;
+    wait_active(&edge).await;
+
+    let rx = edge
+        .topics()
+        .heap_bounded_receiver::<SampleTopic>(4, Some("samples"));
+    let rx = pin!(rx);
+    let rx = rx.subscribe();
+    let server = edge.endpoints().bounded_server::<ScaleEndpoint, 2>(None);
+    let server = pin!(server);
+    let server = server.attach();
+    let mut scratch = [0u8; 512];
+    let handler = pin!(edge.services().schema_handler::<4>(&mut scratch));
+
+    let test = async {
+        let schemas = router
+            .discovery()
+            .discover_schemas(EDGE, Duration::from_secs(1))
+            .await
+            .unwrap();
+
+        let topic: Vec<_> = schemas.iter().filter(|s| s.path == "test/sample").collect();
+        assert_eq!(topic.len(), 1);
+        assert_eq!(topic[0].port, rx.port());
+        assert_eq!(topic[0].kind, FrameKind::TOPIC_MSG);
+        assert_eq!(topic[0].key, Key(SampleTopic::TOPIC_KEY.to_bytes()));
+        assert!(topic[0].name.is_some());
+        assert_eq!(
+            topic[0].ty,
+            Some(OwnedNamedType::from(<Sample as Schema>::SCHEMA))
+        );
+
+        let endpoint: Vec<_> = schemas.iter().filter(|s| s.path == "test/scale").collect();
+        assert_eq!(endpoint.len(), 2);
+        assert!(endpoint.iter().all(|s| s.port == server.port()));
+        assert_eq!(endpoint[0].kind, FrameKind::ENDPOINT_REQ);
+        assert_eq!(endpoint[0].key, Key(ScaleEndpoint::REQ_KEY.to_bytes()));
+        assert_eq!(
+            endpoint[0].ty,
+            Some(OwnedNamedType::from(<Sample as Schema>::SCHEMA))
+        );
+        assert_eq!(endpoint[1].kind, FrameKind::ENDPOINT_RESP);
+        assert_eq!(endpoint[1].key, Key(ScaleEndpoint::RESP_KEY.to_bytes()));
+        assert_eq!(
+            endpoint[1].ty,
+            Some(OwnedNamedType::from(<i64 as Schema>::SCHEMA))
+        );
+
+        // The ping server and the schema handler are listed as well
+        assert!(schemas.iter().any(|s| s.path == "ergot/.well-known/ping"));
+        assert!(schemas.iter().any(|s| s.path == "ergot/.well-known/schema"));
+    };
+
+    // Poll the server first, so that it is listening before any request
+    tokio::select! {
+        biased;
+        _ = handler => unreachable!(),
+        _ = test => {}
+    }
+}
+
+#[tokio::test]
+async fn oversized_schemas_are_omitted() {
+    let (edge, _queue) = make_edge_stack();
+    let rx = edge.topics().heap_bounded_receiver::<SampleTopic>(4, None);
+    let rx = pin!(rx);
+    let _rx = rx.subscribe();
+
+    // Large enough for the socket information, but not for its schema
+    let mut scratch = [0u8; 48];
+    let handler = edge.services().schema_handler::<4>(&mut scratch);
+    let test = async {
+        let schemas = edge
+            .discovery()
+            .discover_schemas(Address::unknown(), Duration::from_secs(1))
+            .await
+            .unwrap();
+        let topic = schemas.iter().find(|s| s.path == "test/sample").unwrap();
+        assert_eq!(topic.ty, None);
+    };
+
+    // Poll the server first, so that it is listening before any request
+    tokio::select! {
+        biased;
+        _ = handler => unreachable!(),
+        _ = test => {}
+    }
+}
+
+#[tokio::test]
+async fn unsendable_sockets_are_skipped() {
+    let (edge, _queue) = make_edge_stack();
+    let rx = edge.topics().heap_bounded_receiver::<SampleTopic>(4, None);
+    let rx = pin!(rx);
+    let _rx = rx.subscribe();
+
+    // Too small for any socket information: each request is answered with
+    // an error instead of being left to time out
+    let mut scratch = [0u8; 8];
+    let handler = edge.services().schema_handler::<4>(&mut scratch);
+    let test = async {
+        let schemas = edge
+            .discovery()
+            .discover_schemas(Address::unknown(), Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert!(schemas.is_empty());
+    };
+
+    // Poll the server first, so that it is listening before any request
+    tokio::select! {
+        biased;
+        _ = handler => unreachable!(),
+        _ = test => {}
+    }
+}
@@ -34,14 +34,14 @@ impl InterfaceSink for NullSink {
     fn mtu(&self) -> u16 {
         2048
     }
-    fn send_ty<T: Serialize>(&mut self, _: &HeaderSeq, _: &T) -> Result<(), ()> {
-        Ok(())
+    fn send_ty<T: Serialize>(&mut self, _: &HeaderSeq, _: &T) -> Result<usize, ()> {
+        Ok(0)
     }
-    fn send_raw(&mut self, _: &HeaderSeq, _: &[u8]) -> Result<(), ()> {
-        Ok(())
+    fn send_raw(&mut self, _: &HeaderSeq, _: &[u8]) -> Result<usize, ()> {
+        Ok(0)
     }
-    fn send_err(&mut self, _: &HeaderSeq, _: ProtocolError) -> Result<(), ()> {
-        Ok(())
+    fn send_err(&mut self, _: &HeaderSeq, _: ProtocolError) -> Result<usize, ()> {
+        Ok(0)
     }
 }
 
@@ -295,12 +295,12 @@ fn can_delegate_seed_gates_unknown_source_and_full_table() {
 
     // Unknown source is rejected before any upstream lease is requested.
     assert_eq!(
-        bridge.manage_profile(|im| im.can_delegate_seed(source_net + 999)),
+        bridge.manage_profile(|im| im.can_delegate_seed(source_net + 999, None)),
         Err(SeedAssignmentError::UnknownSource)
     );
     // A known source with room succeeds.
     assert_eq!(
-        bridge.manage_profile(|im| im.can_delegate_seed(source_net)),
+        bridge.manage_profile(|im| im.can_delegate_seed(source_net, None)),
         Ok(())
     );
 
@@ -312,7 +312,7 @@ fn can_delegate_seed_gates_unknown_source_and_full_table() {
             .expect("registration should succeed until the table is full");
     }
     assert_eq!(
-        bridge.manage_profile(|im| im.can_delegate_seed(source_net)),
+        bridge.manage_profile(|im| im.can_delegate_seed(source_net, None)),
         Err(SeedAssignmentError::NetIdsExhausted),
         "a full route table must be rejected up front"
     );
@@ -352,7 +352,7 @@ fn delegated_parent_state_scales_with_route_capacity() {
     }
 
     assert_eq!(
-        bridge.manage_profile(|im| im.can_delegate_seed(source_net)),
+        bridge.manage_profile(|im| im.can_delegate_seed(source_net, None)),
         Err(SeedAssignmentError::NetIdsExhausted)
     );
 }
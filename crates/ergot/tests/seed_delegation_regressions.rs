@@ -35,16 +35,16 @@ impl InterfaceSink for NullSink {
         2048
     }
 
-    fn send_ty<T: Serialize>(&mut self, _: &HeaderSeq, _: &T) -> Result<(), ()> {
-        Ok(())
+    fn send_ty<T: Serialize>(&mut self, _: &HeaderSeq, _: &T) -> Result<usize, ()> {
+        Ok(0)
     }
 
-    fn send_raw(&mut self, _: &HeaderSeq, _: &[u8]) -> Result<(), ()> {
-        Ok(())
+    fn send_raw(&mut self, _: &HeaderSeq, _: &[u8]) -> Result<usize, ()> {
+        Ok(0)
     }
 
-    fn send_err(&mut self, _: &HeaderSeq, _: ProtocolError) -> Result<(), ()> {
-        Ok(())
+    fn send_err(&mut self, _: &HeaderSeq, _: ProtocolError) -> Result<usize, ()> {
+        Ok(0)
     }
 }
 
@@ -74,7 +74,7 @@ fn upstream_refresh_retry_after_lost_response_is_idempotent() {
         .manage_profile(|im| im.register_interface_pending(NullSink))
         .unwrap();
     let bridge_link_assignment = root
-        .manage_profile(|im| im.request_seed_net_assign(root_source_net))
+        .manage_profile(|im| im.request_seed_net_assign(root_source_net, None))
         .unwrap();
     bridge
         .manage_profile(|im| {
@@ -86,7 +86,7 @@ fn upstream_refresh_retry_after_lost_response_is_idempotent() {
         .unwrap();
 
     let parent_assignment = root
-        .manage_profile(|im| im.request_seed_net_assign(root_source_net))
+        .manage_profile(|im| im.request_seed_net_assign(root_source_net, None))
         .unwrap();
     let parent = SeedLease {
         net_id: parent_assignment.net_id,
@@ -166,7 +166,7 @@ fn replay_reports_actual_remaining_lease_time() {
         .unwrap();
     let source_net = root.manage_profile(|im| im.net_id_of(down)).unwrap();
     let initial = root
-        .manage_profile(|im| im.request_seed_net_assign(source_net))
+        .manage_profile(|im| im.request_seed_net_assign(source_net, None))
         .unwrap();
     let refreshed = root
         .manage_profile(|im| {
@@ -248,11 +248,11 @@ fn bridge_rejects_zero_source_and_local_seed_allocation() {
         .unwrap();
 
     assert_eq!(
-        bridge.manage_profile(|im| im.can_delegate_seed(0)),
+        bridge.manage_profile(|im| im.can_delegate_seed(0, None)),
         Err(SeedAssignmentError::UnknownSource)
     );
     assert_eq!(
-        bridge.manage_profile(|im| im.request_seed_net_assign(0)),
+        bridge.manage_profile(|im| im.request_seed_net_assign(0, None)),
         Err(SeedAssignmentError::ProfileCantSeed)
     );
 
@@ -374,7 +374,7 @@ async fn setup_delegation_chain() -> (RouterStack, RouterStack, RouterStack) {
     .await
     .unwrap();
     let bridge_link_assignment = root
-        .manage_profile(|im| im.request_seed_net_assign(1))
+        .manage_profile(|im| im.request_seed_net_assign(1, None))
         .unwrap();
     bridge
         .manage_profile(|im| {
@@ -524,7 +524,9 @@ async fn disappearing_downlink_releases_the_upstream_assignment() {
                 {
                     embassy_futures::select::Either::First(Ok(req)) => {
                         let result = stack
-                            .manage_profile(|p| p.request_seed_net_assign(req.hdr.src.network_id))
+                            .manage_profile(|p| {
+                                p.request_seed_net_assign(req.hdr.src.network_id, None)
+                            })
                             .map(|assignment| SeedRouterAssignment {
                                 assignment,
                                 refresh_port: 0,
@@ -581,7 +583,7 @@ async fn disappearing_downlink_releases_the_upstream_assignment() {
     request.abort();
 
     let reused = root
-        .manage_profile(|p| p.request_seed_net_assign(1))
+        .manage_profile(|p| p.request_seed_net_assign(1, None))
         .unwrap();
     assert_eq!(reused.net_id, 3, "released root net_id should be reusable");
 
@@ -0,0 +1,306 @@
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address, DEFAULT_TTL, FrameKind, Header, Key, NetStack, Priority,
+    interface_manager::{
+        InterfaceState, interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::EdgeFrameProcessor, profiles::null::Null, profiles::router::Router,
+        transports::tokio_cobs_stream,
+    },
+    net_stack::ArcNetStack,
+    socket::{
+        Attributes,
+        owned::stack_vec::Socket,
+        session::{Segment, SessionConfig, SessionError},
+    },
+    traits::Endpoint,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use tokio::time::timeout;
+
+ergot::endpoint!(CommandSession, u32, u32, "test/session");
+
+type TestNetStack = NetStack<CriticalSectionRawMutex, Null>;
+
+const FAST: SessionConfig = SessionConfig {
+    retransmit: Duration::from_millis(20),
+    max_retries: 4,
+    keepalive: Duration::from_millis(200),
+};
+
+#[tokio::test]
+async fn local_session_in_order() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let server = async {
+        let listener = STACK.sessions().listener::<CommandSession, 4>(None);
+        let listener = pin!(listener);
+        let mut listener = listener.attach();
+        let sock = STACK.sessions().server::<CommandSession, 4>();
+        let sock = pin!(sock);
+        let mut sess = listener.accept(sock, FAST).await.unwrap();
+        // Check ordering, then report back how many messages were received
+        for i in 0..32 {
+            assert_eq!(sess.recv().await, Ok(i));
+        }
+        sess.send(&32).await.unwrap();
+        sess.recv().await.unwrap_err()
+    };
+
+    let client = async {
+        let sock = STACK.sessions().client::<CommandSession, 4>();
+        let sock = pin!(sock);
+        let mut sess = sock.connect(Address::unknown(), None, FAST).await.unwrap();
+
+        // Send more messages than fit in the window
+        for i in 0..32 {
+            sess.send(&i).await.unwrap();
+        }
+        assert_eq!(sess.recv().await, Ok(32));
+        sess.close().await.unwrap();
+    };
+
+    let (server_res, ()) = tokio::join!(server, client);
+    assert_eq!(server_res, SessionError::Closed);
+}
+
+#[tokio::test]
+async fn unacked_data_is_retransmitted() {
+    static STACK: TestNetStack = NetStack::new();
+
+    // A hand-rolled server, to control exactly which segments get acknowledged
+    let fake = Socket::<Segment<u32>, &TestNetStack, 8>::new(
+        &STACK,
+        Key(CommandSession::REQ_KEY.to_bytes()),
+        Attributes {
+            kind: FrameKind::SESSION,
+            discoverable: true,
+        },
+        None,
+    );
+    let fake = pin!(fake);
+    let mut fake = fake.attach();
+    let port = fake.port();
+
+    let reply = move |dst: Address, seq_no: Option<u16>, seg: Segment<u32>| {
+        let hdr = Header {
+            src: Address {
+                network_id: 0,
+                node_id: 0,
+                port_id: port,
+            },
+            dst,
+            any_all: None,
+            seq_no,
+            kind: FrameKind::SESSION,
+            ttl: DEFAULT_TTL,
+            prio: Priority::NORMAL,
+        };
+        STACK.send_ty(&hdr, &seg).unwrap();
+    };
+
+    let client = tokio::spawn(async {
+        let sock = STACK.sessions().client::<CommandSession, 4>();
+        let sock = pin!(sock);
+        let mut sess = sock.connect(Address::unknown(), None, FAST).await.unwrap();
+        sess.send(&7).await.unwrap();
+        sess.flush().await.unwrap();
+        sess.close().await.unwrap();
+    });
+
+    let syn = fake.recv().await.unwrap();
+    assert!(matches!(syn.t, Segment::Syn { .. }));
+    let client_addr = syn.hdr.src;
+    reply(
+        client_addr,
+        Some(1000),
+        Segment::SynAck {
+            ack: syn.hdr.seq_no,
+            window: 4,
+        },
+    );
+    let ack = fake.recv().await.unwrap();
+    assert_eq!(
+        ack.t,
+        Segment::Ack {
+            ack: 1001,
+            window: 4
+        }
+    );
+
+    // Don't acknowledge the first copy of the data
+    let first = fake.recv().await.unwrap();
+    assert_eq!(first.t, Segment::Data { msg: 7 });
+    let again = fake.recv().await.unwrap();
+    assert_eq!(again.t, Segment::Data { msg: 7 });
+    assert_eq!(again.hdr.seq_no, first.hdr.seq_no);
+
+    reply(
+        client_addr,
+        None,
+        Segment::Ack {
+            ack: first.hdr.seq_no.wrapping_add(1),
+            window: 4,
+        },
+    );
+    let fin = loop {
+        let msg = fake.recv().await.unwrap();
+        if msg.t != (Segment::Data { msg: 7 }) {
+            break msg;
+        }
+    };
+    assert_eq!(fin.t, Segment::Fin);
+    reply(client_addr, None, Segment::FinAck);
+
+    client.await.unwrap();
+}
+
+#[tokio::test]
+async fn dropped_session_resets_peer() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let server = async {
+        let listener = STACK.sessions().listener::<CommandSession, 4>(None);
+        let listener = pin!(listener);
+        let mut listener = listener.attach();
+        let sock = STACK.sessions().server::<CommandSession, 4>();
+        let sock = pin!(sock);
+        let mut sess = listener.accept(sock, FAST).await.unwrap();
+        assert_eq!(sess.recv().await, Ok(1));
+        // Dropped without closing
+    };
+
+    let client = async {
+        let sock = STACK.sessions().client::<CommandSession, 4>();
+        let sock = pin!(sock);
+        let mut sess = sock.connect(Address::unknown(), None, FAST).await.unwrap();
+        sess.send(&1).await.unwrap();
+        assert_eq!(sess.recv().await, Err(SessionError::Reset));
+    };
+
+    tokio::join!(server, client);
+}
+
+#[tokio::test]
+async fn connect_times_out_without_accept() {
+    static STACK: TestNetStack = NetStack::new();
+
+    // Syns are queued, but never accepted
+    let listener = STACK.sessions().listener::<CommandSession, 8>(None);
+    let listener = pin!(listener);
+    let _listener = listener.attach();
+
+    let sock = STACK.sessions().client::<CommandSession, 4>();
+    let sock = pin!(sock);
+    let res = sock.connect(Address::unknown(), None, FAST).await;
+    assert!(matches!(res, Err(SessionError::Timeout)));
+}
+
+type RouterStackTy =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+#[tokio::test]
+async fn session_through_router() {
+    //  Edge1 <--duplex1--> Router <--duplex2--> Edge2
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router_stack: RouterStackTy = RouterStackTy::new();
+    let (edge1_stack, edge1_queue) = make_edge_stack();
+    let (edge2_stack, edge2_queue) = make_edge_stack();
+
+    let (e1_read, r1_write) = tokio::io::duplex(8192);
+    let (r1_read, e1_write) = tokio::io::duplex(8192);
+    let (e2_read, r2_write) = tokio::io::duplex(8192);
+    let (r2_read, e2_write) = tokio::io::duplex(8192);
+
+    for (read, write) in [(r1_read, r1_write), (r2_read, r2_write)] {
+        tokio_cobs_stream::register_router(
+            router_stack.clone(),
+            read,
+            write,
+            512,
+            4096,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+    for (stack, read, write, queue) in [
+        (&edge1_stack, e1_read, e1_write, edge1_queue),
+        (&edge2_stack, e2_read, e2_write, edge2_queue),
+    ] {
+        tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+            stack.clone(),
+            read,
+            write,
+            queue,
+            EdgeFrameProcessor::new(),
+            InterfaceState::Inactive,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    spawn_ping_server(&edge1_stack);
+    spawn_ping_server(&edge2_stack);
+
+    let edge1_addr = Address {
+        network_id: 1,
+        node_id: 2,
+        port_id: 0,
+    };
+    let edge2_addr = Address {
+        network_id: 2,
+        node_id: 2,
+        port_id: 0,
+    };
+    ping_with_retry(&router_stack, edge1_addr, 0).await;
+    ping_with_retry(&router_stack, edge2_addr, 0).await;
+    wait_active(&edge1_stack).await;
+    wait_active(&edge2_stack).await;
+
+    let server = tokio::spawn({
+        let stack = edge2_stack.clone();
+        async move {
+            let listener = stack.sessions().listener::<CommandSession, 4>(Some("cmd"));
+            let listener = pin!(listener);
+            let mut listener = listener.attach();
+            let sock = stack.sessions().server::<CommandSession, 4>();
+            let sock = pin!(sock);
+            let mut sess = listener.accept(sock, SessionConfig::DEFAULT).await.unwrap();
+            let mut total = 0;
+            while let Ok(val) = sess.recv().await {
+                total += val;
+            }
+            total
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let sock = edge1_stack.sessions().client::<CommandSession, 4>();
+    let sock = pin!(sock);
+    let mut sess = sock
+        .connect(edge2_addr, Some("cmd"), SessionConfig::DEFAULT)
+        .await
+        .unwrap();
+    assert_eq!(sess.peer().network_id, 2);
+    for i in 1..=20 {
+        sess.send(&i).await.unwrap();
+    }
+    sess.close().await.unwrap();
+
+    let total = timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(total, (1..=20).sum());
+}
@@ -0,0 +1,232 @@
+//! Signed message tests: a controller sending signed and unsigned messages to
+//! a target whose sockets only trust one signer.
+
+#![cfg(all(feature = "tokio-std", feature = "signing"))]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use common::{EdgeStack, make_edge_stack};
+use ergot::{
+    Address, ProtocolError,
+    interface_manager::{
+        InterfaceState,
+        interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::{CENTRAL_NODE_ID, DirectEdge, EdgeFrameProcessor},
+        transports::tokio_cobs_stream,
+        utils::{cobs_stream, std::new_std_queue},
+    },
+    net_stack::ReqRespError,
+    signing::{Signer, SigningKey},
+    well_known::ErgotPingEndpoint,
+};
+use tokio::{
+    io::duplex,
+    time::{sleep, timeout},
+};
+
+ergot::topic!(CommandTopic, u32, "test/command");
+
+const ALICE: SigningKey = SigningKey::new(1, [0xA1; 32]);
+const MALLORY: SigningKey = SigningKey::new(2, [0x66; 32]);
+static TRUSTED: [SigningKey; 1] = [ALICE];
+
+const TGT_ADDR: Address = Address {
+    network_id: 1,
+    node_id: 2,
+    port_id: 0,
+};
+
+/// Connect a controller to a target, returning both stacks
+async fn linked_pair() -> (EdgeStack, EdgeStack) {
+    let (ctrl_read, tgt_write) = duplex(8192);
+    let (tgt_read, ctrl_write) = duplex(8192);
+
+    let ctrl_queue = new_std_queue(4096);
+    let ctrl = EdgeStack::new_with_profile(DirectEdge::new_controller(
+        cobs_stream::Sink::new_from_handle(ctrl_queue.clone(), 512),
+        InterfaceState::Down,
+    ));
+    let (tgt, tgt_queue) = make_edge_stack();
+
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        ctrl.clone(),
+        ctrl_read,
+        ctrl_write,
+        ctrl_queue,
+        EdgeFrameProcessor::new_controller(1),
+        InterfaceState::Active {
+            net_id: 1,
+            node_id: CENTRAL_NODE_ID,
+        },
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        tgt.clone(),
+        tgt_read,
+        tgt_write,
+        tgt_queue,
+        EdgeFrameProcessor::new(),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    (ctrl, tgt)
+}
+
+fn spawn_trusting_ping_server(stack: &EdgeStack) {
+    tokio::spawn({
+        let stack = stack.clone();
+        async move {
+            let server = stack
+                .endpoints()
+                .bounded_server::<ErgotPingEndpoint, 4>(Some("ping"))
+                .with_trust_store(&TRUSTED);
+            let server = pin!(server);
+            let mut hdl = server.attach();
+            loop {
+                let _ = hdl
+                    .serve(|val: &u32| {
+                        let v = *val;
+                        async move { v }
+                    })
+                    .await;
+            }
+        }
+    });
+}
+
+async fn signed_ping_with_retry(stack: &EdgeStack, signer: &Signer, val: u32) -> u32 {
+    let mut scratch = [0u8; 128];
+    for _ in 0..30 {
+        let result = timeout(
+            Duration::from_millis(500),
+            stack.endpoints().request_signed::<ErgotPingEndpoint>(
+                TGT_ADDR,
+                &val,
+                Some("ping"),
+                signer,
+                &mut scratch,
+            ),
+        )
+        .await;
+        match result {
+            Ok(Ok(v)) => return v,
+            _ => sleep(Duration::from_millis(100)).await,
+        }
+    }
+    panic!("signed ping failed after retries");
+}
+
+#[tokio::test]
+async fn only_trusted_requests_are_served() {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let (ctrl, tgt) = linked_pair().await;
+    spawn_trusting_ping_server(&tgt);
+    let alice = Signer::new(ALICE, 1);
+    let mallory = Signer::new(MALLORY, 1);
+
+    assert_eq!(signed_ping_with_retry(&ctrl, &alice, 42).await, 42);
+    assert_eq!(signed_ping_with_retry(&ctrl, &alice, 46).await, 46);
+
+    let mut scratch = [0u8; 128];
+    let res = timeout(
+        Duration::from_secs(2),
+        ctrl.endpoints().request_signed::<ErgotPingEndpoint>(
+            TGT_ADDR,
+            &43,
+            Some("ping"),
+            &mallory,
+            &mut scratch,
+        ),
+    )
+    .await
+    .unwrap();
+    assert_eq!(res, Err(ReqRespError::Remote(ProtocolError::SseUnverified)));
+
+    let res = timeout(
+        Duration::from_secs(2),
+        ctrl.endpoints()
+            .request::<ErgotPingEndpoint>(TGT_ADDR, &44, Some("ping")),
+    )
+    .await
+    .unwrap();
+    assert_eq!(res, Err(ReqRespError::Remote(ProtocolError::SseUnverified)));
+
+    // Local requests are always trusted
+    let res = timeout(
+        Duration::from_secs(2),
+        tgt.endpoints()
+            .request::<ErgotPingEndpoint>(Address::unknown(), &45, Some("ping")),
+    )
+    .await
+    .unwrap();
+    assert_eq!(res, Ok(45));
+}
+
+#[tokio::test]
+async fn only_trusted_topic_messages_are_delivered() {
+    let (ctrl, tgt) = linked_pair().await;
+    // Wait for the link to come up
+    common::spawn_ping_server(&tgt);
+    common::ping_with_retry(&ctrl, TGT_ADDR, 1).await;
+
+    let sub = tgt
+        .topics()
+        .heap_bounded_receiver::<CommandTopic>(8, None)
+        .with_trust_store(&TRUSTED);
+    let sub = pin!(sub);
+    let mut sub = sub.subscribe();
+    let alice = Signer::new(ALICE, 1);
+    let mallory = Signer::new(MALLORY, 1);
+
+    let mut scratch = [0u8; 128];
+    ctrl.topics().broadcast::<CommandTopic>(&1, None).unwrap();
+    ctrl.topics()
+        .broadcast_signed::<CommandTopic>(&2, None, &mallory, &mut scratch)
+        .unwrap();
+    ctrl.topics()
+        .broadcast_signed::<CommandTopic>(&3, None, &alice, &mut scratch)
+        .unwrap();
+    ctrl.topics()
+        .broadcast_signed::<CommandTopic>(&4, None, &alice, &mut scratch)
+        .unwrap();
+
+    let msg = timeout(Duration::from_secs(2), sub.recv()).await.unwrap();
+    assert_eq!(msg.t, 3);
+    let msg = timeout(Duration::from_secs(2), sub.recv()).await.unwrap();
+    assert_eq!(msg.t, 4);
+    let res = timeout(Duration::from_millis(200), sub.recv()).await;
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn unsigned_responses_are_rejected() {
+    let (ctrl, tgt) = linked_pair().await;
+    // A server without a trust store can't sign its responses
+    common::spawn_ping_server(&tgt);
+    assert_eq!(common::ping_with_retry(&ctrl, TGT_ADDR, 1).await, 1);
+    let alice = Signer::new(ALICE, 1);
+
+    let mut scratch = [0u8; 128];
+    let res = timeout(
+        Duration::from_secs(1),
+        ctrl.endpoints().request_signed::<ErgotPingEndpoint>(
+            TGT_ADDR,
+            &2,
+            Some("ping"),
+            &alice,
+            &mut scratch,
+        ),
+    )
+    .await;
+    // The unsigned response is rejected, so the request times out
+    assert!(res.is_err());
+}
@@ -5,7 +5,7 @@ use bbqueue::{
     traits::{coordination::cas::AtomicCoord, notifier::maitake::MaiNotSpsc, storage::Inline},
 };
 use ergot::{
-    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, HeaderSeq, Key, NetStack,
+    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, HeaderSeq, Key, NetStack, Priority,
     ProtocolError,
     interface_manager::profiles::null::Null,
     socket::{Attributes, owned::single::Socket},
@@ -71,6 +71,7 @@ async fn hello() {
                         seq_no: None,
                         kind: FrameKind::ENDPOINT_REQ,
                         ttl: DEFAULT_TTL,
+                        prio: Priority::NORMAL,
                     },
                     &Other { a: 345, b: -123 },
                 )
@@ -88,6 +89,7 @@ async fn hello() {
                         seq_no: None,
                         kind: FrameKind::ENDPOINT_REQ,
                         ttl: DEFAULT_TTL,
+                        prio: Priority::NORMAL,
                     },
                     &Example { a: 42, b: 789 },
                 )
@@ -109,6 +111,7 @@ async fn hello() {
                         seq_no: 123,
                         kind: FrameKind::ENDPOINT_REQ,
                         ttl: DEFAULT_TTL,
+                        prio: Priority::NORMAL,
                     },
                     &body,
                     (),
@@ -171,6 +174,7 @@ async fn hello() {
                 seq_no: None,
                 kind: FrameKind::ENDPOINT_REQ,
                 ttl: DEFAULT_TTL,
+                prio: Priority::NORMAL,
             },
             &Other { a: 345, b: -123 },
         )
@@ -187,6 +191,7 @@ async fn hello() {
                 seq_no: None,
                 kind: FrameKind::ENDPOINT_REQ,
                 ttl: DEFAULT_TTL,
+                prio: Priority::NORMAL,
             },
             &Example { a: 42, b: 789 },
         )
@@ -232,6 +237,7 @@ async fn hello_err() {
                     seq_no: None,
                     kind: FrameKind::PROTOCOL_ERROR,
                     ttl: 1,
+                    prio: Priority::NORMAL,
                 },
                 ProtocolError::NsseNoRoute,
                 None,
@@ -307,6 +313,7 @@ async fn hello_borrowed() {
                     seq_no: None,
                     kind: FrameKind::ENDPOINT_REQ,
                     ttl: 1,
+                    prio: Priority::NORMAL,
                 },
                 &s,
             )
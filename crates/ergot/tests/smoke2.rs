@@ -3,8 +3,8 @@
 use std::{pin::pin, time::Duration};
 
 use ergot::{
-    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, HeaderSeq, Key, NetStack, endpoint,
-    interface_manager::profiles::null::Null, traits::Endpoint,
+    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, HeaderSeq, Key, NetStack, Priority,
+    endpoint, interface_manager::profiles::null::Null, traits::Endpoint,
 };
 use mutex::raw_impls::cs::CriticalSectionRawMutex;
 
@@ -67,6 +67,7 @@ async fn hello() {
                         seq_no: None,
                         kind: FrameKind::ENDPOINT_REQ,
                         ttl: DEFAULT_TTL,
+                        prio: Priority::NORMAL,
                     },
                     &Other { a: 345, b: -123 },
                 )
@@ -84,6 +85,7 @@ async fn hello() {
                         seq_no: None,
                         kind: FrameKind::ENDPOINT_REQ,
                         ttl: DEFAULT_TTL,
+                        prio: Priority::NORMAL,
                     },
                     &Example { a: 42, b: 789 },
                 )
@@ -105,6 +107,7 @@ async fn hello() {
                         seq_no: 123,
                         kind: FrameKind::ENDPOINT_REQ,
                         ttl: DEFAULT_TTL,
+                        prio: Priority::NORMAL,
                     },
                     &body,
                     (),
@@ -167,6 +170,7 @@ async fn hello() {
                 seq_no: None,
                 kind: FrameKind::ENDPOINT_REQ,
                 ttl: DEFAULT_TTL,
+                prio: Priority::NORMAL,
             },
             &Other { a: 345, b: -123 },
         )
@@ -183,6 +187,7 @@ async fn hello() {
                 seq_no: None,
                 kind: FrameKind::ENDPOINT_REQ,
                 ttl: DEFAULT_TTL,
+                prio: Priority::NORMAL,
             },
             &Example { a: 42, b: 789 },
         )
@@ -0,0 +1,222 @@
+//! Traffic counter tests: interfaces and sockets count the frames they
+//! handle and drop, locally and through the stats endpoint.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address, AnyAllAppendix, DEFAULT_TTL, FrameKind, Header, Key, NetStack, Priority,
+    interface_manager::{
+        InterfaceState, interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::EdgeFrameProcessor, profiles::null::Null, profiles::router::Router,
+        transports::tokio_cobs_stream,
+    },
+    net_stack::ArcNetStack,
+    traits::{Endpoint, Topic},
+    well_known::ErgotPingEndpoint,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use rand::SeedableRng;
+use tokio::time::{sleep, timeout};
+
+type RouterStack =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+const EDGE: Address = Address {
+    network_id: 1,
+    node_id: 2,
+    port_id: 0,
+};
+
+ergot::topic!(CountTopic, u32, "test/count");
+
+#[tokio::test]
+async fn router_reads_edge_counters() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router =
+        RouterStack::new_with_profile(Router::new(rand::rngs::StdRng::from_seed([0u8; 32])));
+    let (edge, edge_queue) = make_edge_stack();
+
+    let (e_read, r_write) = tokio::io::duplex(8192);
+    let (r_read, e_write) = tokio::io::duplex(8192);
+    let ident =
+        tokio_cobs_stream::register_router(router.clone(), r_read, r_write, 512, 4096, None, None)
+            .await
+            .unwrap();
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        edge.clone(),
+        e_read,
+        e_write,
+        edge_queue,
+        EdgeFrameProcessor::new(),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    spawn_ping_server(&edge);
+    ping_with_retry(&router, EDGE, 1).await;
+    wait_active(&edge).await;
+
+    // Each ping is a request one way and a response the other way
+    let local = router.interface_stats(0).unwrap();
+    assert_eq!(local.interface, ident);
+    assert!(local.frames_tx >= 1);
+    assert!(local.frames_rx >= 1);
+    assert!(local.bytes_tx >= 1);
+    assert_eq!(local.interface_full, 0);
+    assert!(router.interface_stats(1).is_none());
+
+    let handler = pin!(edge.services().stats_handler::<4>());
+    let test = async {
+        let stats = router.discovery().discover_stats(EDGE).await.unwrap();
+        assert_eq!(stats.interfaces.len(), 1);
+        assert_eq!(stats.interfaces[0].interface, 0);
+        assert!(stats.interfaces[0].frames_rx >= 1);
+        assert!(stats.interfaces[0].frames_tx >= 1);
+
+        let ping = Key(ErgotPingEndpoint::REQ_KEY.to_bytes());
+        let ping = stats.sockets.iter().find(|s| s.key == ping).unwrap();
+        assert_eq!(ping.kind, FrameKind::ENDPOINT_REQ);
+        assert!(ping.received >= 1);
+        assert_eq!(ping.no_space, 0);
+    };
+
+    // Poll the handler first, so that it is listening before any request
+    tokio::select! {
+        biased;
+        _ = handler => unreachable!(),
+        _ = test => {}
+    }
+}
+
+#[tokio::test]
+async fn router_counts_dropped_frames() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router =
+        RouterStack::new_with_profile(Router::new(rand::rngs::StdRng::from_seed([0u8; 32])));
+    let (edge, edge_queue) = make_edge_stack();
+
+    let (e_read, r_write) = tokio::io::duplex(8192);
+    let (r_read, e_write) = tokio::io::duplex(8192);
+    tokio_cobs_stream::register_router(router.clone(), r_read, r_write, 512, 4096, None, None)
+        .await
+        .unwrap();
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        edge.clone(),
+        e_read,
+        e_write,
+        edge_queue,
+        EdgeFrameProcessor::new(),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    spawn_ping_server(&edge);
+    ping_with_retry(&router, EDGE, 1).await;
+    wait_active(&edge).await;
+
+    let frame = |dst: Address, ttl: u8| Header {
+        src: Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: 1,
+        },
+        dst,
+        any_all: None,
+        seq_no: None,
+        kind: FrameKind::TOPIC_MSG,
+        ttl,
+        prio: Priority::NORMAL,
+    };
+    let elsewhere = Address {
+        network_id: 9,
+        node_id: 2,
+        port_id: 1,
+    };
+    let same_net = Address {
+        network_id: 1,
+        node_id: 3,
+        port_id: 1,
+    };
+    // The edge uses up the last hop
+    edge.send_serialized(&frame(elsewhere, 1), &[1]).unwrap();
+    // The router would send this back to the edge
+    edge.send_serialized(&frame(same_net, DEFAULT_TTL), &[2])
+        .unwrap();
+
+    let counted = timeout(Duration::from_secs(2), async {
+        loop {
+            let stats = router.interface_stats(0).unwrap();
+            if stats.ttl_expired + stats.routing_loop == 2 {
+                return stats;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(counted.ttl_expired, 1);
+    assert_eq!(counted.routing_loop, 1);
+}
+
+#[tokio::test]
+async fn sockets_count_dropped_messages() {
+    static STACK: NetStack<CriticalSectionRawMutex, Null> = NetStack::new();
+
+    let rx = STACK.topics().bounded_receiver::<CountTopic, 1>(None);
+    let rx = pin!(rx);
+    let mut rx = rx.subscribe();
+    let port = rx.port();
+    let stats = || {
+        (0..)
+            .map_while(|i| STACK.socket_stats(i))
+            .find(|s| s.port == port)
+            .unwrap()
+    };
+
+    // The second message doesn't fit
+    STACK.topics().broadcast::<CountTopic>(&1, None).unwrap();
+    _ = STACK.topics().broadcast::<CountTopic>(&2, None);
+    let counted = stats();
+    assert_eq!(counted.kind, FrameKind::TOPIC_MSG);
+    assert_eq!(counted.key, Key(CountTopic::TOPIC_KEY.to_bytes()));
+    assert_eq!(counted.received, 1);
+    assert_eq!(counted.no_space, 1);
+    assert_eq!(rx.recv().await.t, 1);
+
+    // A `u32` can't be longer than five bytes
+    let hdr = Header {
+        src: Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: 1,
+        },
+        dst: Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: 255,
+        },
+        any_all: Some(AnyAllAppendix {
+            key: Key(CountTopic::TOPIC_KEY.to_bytes()),
+            nash: None,
+        }),
+        seq_no: None,
+        kind: FrameKind::TOPIC_MSG,
+        ttl: DEFAULT_TTL,
+        prio: Priority::NORMAL,
+    };
+    _ = STACK.send_serialized(&hdr, &[0xFF; 6]);
+    assert_eq!(stats().deser_failed, 1);
+    assert_eq!(stats().received, 1);
+}
@@ -0,0 +1,270 @@
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address, NetStack,
+    interface_manager::{
+        InterfaceState, interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::EdgeFrameProcessor, profiles::null::Null, profiles::router::Router,
+        transports::tokio_cobs_stream,
+    },
+    net_stack::{ArcNetStack, ReqRespError},
+    socket::stream_endpoint::MAX_STREAM_GAP,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use tokio::time::timeout;
+
+ergot::stream_endpoint!(Counter, u32, u32, "test/counter");
+
+type TestNetStack = NetStack<CriticalSectionRawMutex, Null>;
+
+#[tokio::test]
+async fn local_stream_in_order() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv = STACK.endpoints().bounded_server::<Counter, 4>(None);
+    let srv = pin!(srv);
+    let mut srv = srv.attach();
+    // Many more items than the client can queue at once
+    let server = srv.serve_stream(async |count, sink| {
+        for i in 0..*count {
+            sink.send(i).await?;
+        }
+        Ok(())
+    });
+
+    let client = async {
+        let sock = STACK.endpoints().stream_client::<Counter, 4>();
+        let sock = pin!(sock);
+        let mut hdl = sock.attach();
+        let broadcast = Address {
+            network_id: 0,
+            node_id: 0,
+            port_id: 255,
+        };
+        assert_eq!(
+            hdl.request(broadcast, &1, None),
+            Err(ReqRespError::NoBroadcast)
+        );
+        hdl.request(Address::unknown(), &100, None).unwrap();
+        let mut items = vec![];
+        while let Some(item) = hdl.next().await {
+            items.push(item.unwrap());
+        }
+        assert_eq!(items, (0..100).collect::<Vec<_>>());
+
+        // The stream stays ended
+        assert_eq!(hdl.next().await, None);
+    };
+
+    let (res, ()) = tokio::join!(server, client);
+    res.unwrap();
+}
+
+#[tokio::test]
+async fn dropped_sink_ends_stream() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv = STACK.endpoints().bounded_server::<Counter, 4>(None);
+    let srv = pin!(srv);
+    let mut srv = srv.attach();
+
+    let sock = STACK.endpoints().stream_client::<Counter, 8>();
+    let sock = pin!(sock);
+    let mut hdl = sock.attach();
+    hdl.request(Address::unknown(), &3, None).unwrap();
+
+    let req = srv.recv_manual().await.unwrap();
+    let mut sink = STACK
+        .endpoints()
+        .stream_sink::<Counter>(&req.hdr, srv.port());
+    sink.send(10).await.unwrap();
+    sink.send(11).await.unwrap();
+    drop(sink);
+
+    assert_eq!(hdl.next().await, Some(Ok(10)));
+    assert_eq!(hdl.next().await, Some(Ok(11)));
+    assert_eq!(hdl.next().await, None);
+}
+
+#[tokio::test]
+async fn missing_item_is_reported() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv = STACK.endpoints().bounded_server::<Counter, 4>(None);
+    let srv = pin!(srv);
+    let mut srv = srv.attach();
+
+    let sock = STACK.endpoints().stream_client::<Counter, 8>();
+    let sock = pin!(sock);
+    let mut hdl = sock.attach();
+    hdl.request(Address::unknown(), &3, None).unwrap();
+
+    // Pretend the first item got lost on the way
+    let mut req = srv.recv_manual().await.unwrap();
+    req.hdr.seq_no = req.hdr.seq_no.wrapping_add(1);
+    let mut sink = STACK
+        .endpoints()
+        .stream_sink::<Counter>(&req.hdr, srv.port());
+    sink.send(1).await.unwrap();
+    sink.send(2).await.unwrap();
+    sink.finish().await.unwrap();
+
+    assert_eq!(hdl.next().await, Some(Err(ReqRespError::Missed)));
+    assert_eq!(hdl.next().await, Some(Ok(1)));
+    assert_eq!(hdl.next().await, Some(Ok(2)));
+    assert_eq!(hdl.next().await, None);
+}
+
+#[tokio::test]
+async fn stray_items_are_ignored() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv = STACK.endpoints().bounded_server::<Counter, 4>(None);
+    let srv = pin!(srv);
+    let mut srv = srv.attach();
+
+    let sock = STACK.endpoints().stream_client::<Counter, 8>();
+    let sock = pin!(sock);
+    let mut hdl = sock.attach();
+    hdl.request(Address::unknown(), &3, None).unwrap();
+    let req = srv.recv_manual().await.unwrap();
+
+    // Items of an earlier stream, and items much too far ahead
+    for offset in [0u16.wrapping_sub(5), MAX_STREAM_GAP] {
+        let mut hdr = req.hdr.clone();
+        hdr.seq_no = hdr.seq_no.wrapping_add(offset);
+        let mut stray = STACK.endpoints().stream_sink::<Counter>(&hdr, srv.port());
+        stray.send(99).await.unwrap();
+        stray.finish().await.unwrap();
+    }
+
+    let mut sink = STACK
+        .endpoints()
+        .stream_sink::<Counter>(&req.hdr, srv.port());
+    sink.send(1).await.unwrap();
+    sink.finish().await.unwrap();
+    assert_eq!(hdl.next().await, Some(Ok(1)));
+    assert_eq!(hdl.next().await, None);
+}
+
+#[tokio::test]
+async fn next_times_out() {
+    static STACK: TestNetStack = NetStack::new();
+
+    let srv = STACK.endpoints().bounded_server::<Counter, 4>(None);
+    let srv = pin!(srv);
+    let mut srv = srv.attach();
+
+    let sock = STACK.endpoints().stream_client::<Counter, 8>();
+    let sock = pin!(sock);
+    let mut hdl = sock.attach();
+
+    // No request, no stream
+    assert_eq!(hdl.next().await, None);
+
+    hdl.request(Address::unknown(), &1, None).unwrap();
+    let req = srv.recv_manual().await.unwrap();
+    assert_eq!(
+        hdl.next_with_timeout(tokio::time::sleep(Duration::from_millis(20)))
+            .await,
+        Some(Err(ReqRespError::Timeout))
+    );
+
+    // The stream is still open after a timeout
+    let mut sink = STACK
+        .endpoints()
+        .stream_sink::<Counter>(&req.hdr, srv.port());
+    sink.send(7).await.unwrap();
+    sink.finish().await.unwrap();
+    assert_eq!(hdl.next().await, Some(Ok(7)));
+    assert_eq!(hdl.next().await, None);
+}
+
+type RouterStackTy =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+#[tokio::test]
+async fn stream_through_router() {
+    //  Edge <--duplex--> Router
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router_stack: RouterStackTy = RouterStackTy::new();
+    let (edge_stack, edge_queue) = make_edge_stack();
+
+    let (e_read, r_write) = tokio::io::duplex(8192);
+    let (r_read, e_write) = tokio::io::duplex(8192);
+
+    tokio_cobs_stream::register_router(
+        router_stack.clone(),
+        r_read,
+        r_write,
+        512,
+        4096,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        edge_stack.clone(),
+        e_read,
+        e_write,
+        edge_queue,
+        EdgeFrameProcessor::new(),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    spawn_ping_server(&edge_stack);
+    let edge_addr = Address {
+        network_id: 1,
+        node_id: 2,
+        port_id: 0,
+    };
+    ping_with_retry(&router_stack, edge_addr, 0).await;
+    wait_active(&edge_stack).await;
+
+    tokio::spawn({
+        let stack = edge_stack.clone();
+        async move {
+            let srv = stack.endpoints().bounded_server::<Counter, 4>(None);
+            let srv = pin!(srv);
+            let mut srv = srv.attach();
+            loop {
+                let _ = srv
+                    .serve_stream(async |count, sink| {
+                        for i in 0..*count {
+                            sink.send(i * 2).await?;
+                        }
+                        Ok(())
+                    })
+                    .await;
+            }
+        }
+    });
+    // Let the server attach first
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let sock = router_stack.endpoints().stream_client::<Counter, 32>();
+    let sock = pin!(sock);
+    let mut hdl = sock.attach();
+    hdl.request(edge_addr, &20, None).unwrap();
+    let mut items = vec![];
+    loop {
+        let next = timeout(Duration::from_secs(2), hdl.next()).await.unwrap();
+        match next {
+            Some(item) => items.push(item.unwrap()),
+            None => break,
+        }
+    }
+    assert_eq!(items, (0..20).map(|i| i * 2).collect::<Vec<_>>());
+}
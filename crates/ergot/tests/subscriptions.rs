@@ -0,0 +1,112 @@
+//! Subscription advertisement tests: a router only forwards topic broadcasts
+//! to the edges that advertised the topic.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address,
+    interface_manager::{
+        InterfaceState, interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::EdgeFrameProcessor, profiles::router::Router,
+        transports::tokio_cobs_stream,
+    },
+    net_stack::ArcNetStack,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use rand::SeedableRng;
+use tokio::time::{sleep, timeout};
+
+ergot::topic!(SensorTopic, u32, "test/sensor");
+
+type RouterStack =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+const EDGE1: Address = Address {
+    network_id: 1,
+    node_id: 2,
+    port_id: 0,
+};
+const EDGE2: Address = Address {
+    network_id: 2,
+    node_id: 2,
+    port_id: 0,
+};
+
+#[tokio::test]
+async fn broadcasts_follow_advertisements() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router =
+        RouterStack::new_with_profile(Router::new(rand::rngs::StdRng::from_seed([0u8; 32])));
+    let (edge1, edge1_queue) = make_edge_stack();
+    let (edge2, edge2_queue) = make_edge_stack();
+
+    for (edge, queue) in [(&edge1, edge1_queue), (&edge2, edge2_queue)] {
+        let (e_read, r_write) = tokio::io::duplex(8192);
+        let (r_read, e_write) = tokio::io::duplex(8192);
+        tokio_cobs_stream::register_router(router.clone(), r_read, r_write, 512, 4096, None, None)
+            .await
+            .unwrap();
+        tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+            edge.clone(),
+            e_read,
+            e_write,
+            queue,
+            EdgeFrameProcessor::new(),
+            InterfaceState::Inactive,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        spawn_ping_server(edge);
+    }
+    ping_with_retry(&router, EDGE1, 1).await;
+    ping_with_retry(&router, EDGE2, 2).await;
+    wait_active(&edge1).await;
+    wait_active(&edge2).await;
+
+    let sub1 = edge1.topics().heap_bounded_receiver::<SensorTopic>(8, None);
+    let sub1 = pin!(sub1);
+    let mut sub1 = sub1.subscribe();
+
+    // Only the first edge subscribes, when both advertise
+    assert!(edge1.advertise_subscriptions(30));
+    assert!(edge2.advertise_subscriptions(30));
+    sleep(Duration::from_millis(100)).await;
+
+    let sub2 = edge2.topics().heap_bounded_receiver::<SensorTopic>(8, None);
+    let sub2 = pin!(sub2);
+    let mut sub2 = sub2.subscribe();
+
+    router.topics().broadcast::<SensorTopic>(&1, None).unwrap();
+    let msg = timeout(Duration::from_secs(2), sub1.recv()).await.unwrap();
+    assert_eq!(msg.t, 1);
+    assert!(
+        timeout(Duration::from_millis(300), sub2.recv())
+            .await
+            .is_err(),
+        "the router should not know about the second subscription yet"
+    );
+
+    // Once the second edge advertises its new subscription, it gets the
+    // broadcasts too
+    assert!(edge2.advertise_subscriptions(30));
+    sleep(Duration::from_millis(100)).await;
+    router.topics().broadcast::<SensorTopic>(&2, None).unwrap();
+    let msg = timeout(Duration::from_secs(2), sub1.recv()).await.unwrap();
+    assert_eq!(msg.t, 2);
+    let msg = timeout(Duration::from_secs(2), sub2.recv()).await.unwrap();
+    assert_eq!(msg.t, 2);
+
+    // Broadcasts from an edge are pruned the same way
+    edge2.topics().broadcast::<SensorTopic>(&3, None).unwrap();
+    let msg = timeout(Duration::from_secs(2), sub1.recv()).await.unwrap();
+    assert_eq!(msg.t, 3);
+}
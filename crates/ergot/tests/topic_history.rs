@@ -0,0 +1,150 @@
+//! Topic history tests: a router records the messages of a topic broadcast by
+//! one edge, and another edge queries them.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::{pin::pin, time::Duration};
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address, Key,
+    interface_manager::{
+        InterfaceState, interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::direct_edge::EdgeFrameProcessor, profiles::router::Router,
+        transports::tokio_cobs_stream,
+    },
+    net_stack::{
+        ArcNetStack,
+        history::{TopicHistory, TopicHistoryClientError},
+    },
+    traits::Topic,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use rand::SeedableRng;
+use tokio::time::sleep;
+
+ergot::topic!(EventTopic, u32, "test/event");
+ergot::topic!(OtherTopic, u32, "test/other");
+
+type RouterStack =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+const ROUTER: Address = Address {
+    network_id: 1,
+    node_id: 1,
+    port_id: 0,
+};
+const EDGE1: Address = Address {
+    network_id: 1,
+    node_id: 2,
+    port_id: 0,
+};
+const EDGE2: Address = Address {
+    network_id: 2,
+    node_id: 2,
+    port_id: 0,
+};
+
+#[tokio::test]
+async fn query_router_history() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let router =
+        RouterStack::new_with_profile(Router::new(rand::rngs::StdRng::from_seed([0u8; 32])));
+    let (edge1, edge1_queue) = make_edge_stack();
+    let (edge2, edge2_queue) = make_edge_stack();
+
+    for (edge, queue) in [(&edge1, edge1_queue), (&edge2, edge2_queue)] {
+        let (e_read, r_write) = tokio::io::duplex(8192);
+        let (r_read, e_write) = tokio::io::duplex(8192);
+        tokio_cobs_stream::register_router(router.clone(), r_read, r_write, 512, 4096, None, None)
+            .await
+            .unwrap();
+        tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+            edge.clone(),
+            e_read,
+            e_write,
+            queue,
+            EdgeFrameProcessor::new(),
+            InterfaceState::Inactive,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        spawn_ping_server(edge);
+    }
+    ping_with_retry(&router, EDGE1, 1).await;
+    ping_with_retry(&router, EDGE2, 2).await;
+    wait_active(&edge1).await;
+    wait_active(&edge2).await;
+
+    let history = TopicHistory::<CriticalSectionRawMutex>::new();
+    let recorder = pin!(
+        router
+            .services()
+            .topic_history_recorder::<EventTopic>(&history, 4, 256)
+    );
+    let handler = pin!(router.services().topic_history_handler::<4>(&history));
+
+    let test = async {
+        // Let the recorder subscribe
+        sleep(Duration::from_millis(50)).await;
+        for event in 0..6 {
+            edge1
+                .topics()
+                .broadcast::<EventTopic>(&event, None)
+                .unwrap();
+        }
+        sleep(Duration::from_millis(300)).await;
+        for event in 6..8 {
+            edge1
+                .topics()
+                .broadcast::<EventTopic>(&event, None)
+                .unwrap();
+        }
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(history.len(&Key(EventTopic::TOPIC_KEY.to_bytes())), 4);
+
+        // The last four messages are kept, oldest first
+        let mut query = edge2.topics().history::<EventTopic>(ROUTER);
+        let mut records = vec![];
+        while let Some(record) = query.next_record().await.unwrap() {
+            records.push(record);
+        }
+        let events: Vec<_> = records.iter().map(|r| r.msg.unwrap()).collect();
+        assert_eq!(events, [4, 5, 6, 7]);
+        let seqs: Vec<_> = records.iter().map(|r| r.seq).collect();
+        assert_eq!(seqs, [4, 5, 6, 7]);
+        assert!(records.iter().all(|r| r.src.network_id == EDGE1.network_id));
+        assert!(records[1].age_ms >= records[2].age_ms + 250);
+
+        // By time range: only the messages received after the pause
+        let from_ms = records[1].rx_ms + 1;
+        let mut query = edge2
+            .topics()
+            .history::<EventTopic>(ROUTER)
+            .time_ms(from_ms..=u64::MAX);
+        assert_eq!(query.next_record().await.unwrap().unwrap().msg, Some(6));
+
+        // By sequence range
+        let mut query = edge2.topics().history::<EventTopic>(ROUTER).seq(0..=4);
+        assert_eq!(query.next_record().await.unwrap().unwrap().seq, 4);
+        assert_eq!(query.next_record().await.unwrap(), None);
+
+        let mut query = edge2.topics().history::<OtherTopic>(ROUTER);
+        assert_eq!(
+            query.next_record().await,
+            Err(TopicHistoryClientError::UnknownTopic)
+        );
+    };
+
+    tokio::select! {
+        _ = recorder => unreachable!(),
+        _ = handler => unreachable!(),
+        _ = test => {}
+    }
+}
@@ -0,0 +1,284 @@
+//! Traceroute tests: probes with an increasing TTL expire at each router on
+//! the way, and each router answers from its own address.
+//!
+//! Topology:
+//! ```text
+//! Edge1 ←→ Bridge ←→ RootRouter ←→ Edge2
+//! ```
+//!
+//! Edge1 is reached through the seed net_id assigned to the bridge downstream,
+//! like in `e2e_bridge_seed`.
+
+#![cfg(feature = "tokio-std")]
+#![cfg(not(miri))]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{make_edge_stack, ping_with_retry, spawn_ping_server, wait_active};
+use ergot::{
+    Address,
+    interface_manager::{
+        InterfaceState, Profile,
+        interface_impls::tokio_stream::TokioStreamInterface,
+        profiles::{
+            direct_edge::EdgeFrameProcessor,
+            router::{Router, UPSTREAM_IDENT},
+        },
+        transports::tokio_cobs_stream,
+        utils::{cobs_stream, std::new_std_queue},
+    },
+    net_stack::{ArcNetStack, discovery::TracerouteHop, services::bridge_seed_assign},
+    well_known::ErgotPingEndpoint,
+};
+use mutex::raw_impls::cs::CriticalSectionRawMutex;
+use tokio::time::{sleep, timeout};
+
+type RootStack =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+type BridgeStack =
+    ArcNetStack<CriticalSectionRawMutex, Router<TokioStreamInterface, rand::rngs::StdRng, 64, 64>>;
+
+/// Wait for an interface to be Active, return its net_id.
+async fn wait_interface_active(stack: &BridgeStack, ident: u8) -> u16 {
+    for _ in 0..50 {
+        let state = stack.manage_profile(|im| im.interface_state(ident));
+        if let Some(InterfaceState::Active { net_id, .. }) = state {
+            return net_id;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    panic!("interface {} never reached Active", ident);
+}
+
+#[tokio::test]
+async fn traceroute_through_bridge() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    // Create stacks
+    let bridge_up_queue = new_std_queue(4096);
+    let bridge_stack: BridgeStack = BridgeStack::new_with_profile(Router::new_bridge_std(
+        cobs_stream::Sink::new_from_handle(bridge_up_queue.clone(), 512),
+    ));
+    let root_stack: RootStack = RootStack::new();
+    let (edge1_stack, edge1_queue) = make_edge_stack();
+    let (edge2_stack, edge2_queue) = make_edge_stack();
+
+    // Duplex channels
+    let (bridge_up_read, root_d0_write) = tokio::io::duplex(8192);
+    let (root_d0_read, bridge_up_write) = tokio::io::duplex(8192);
+    let (e1_read, bridge_d0_write) = tokio::io::duplex(8192);
+    let (bridge_d0_read, e1_write) = tokio::io::duplex(8192);
+    let (e2_read, root_d1_write) = tokio::io::duplex(8192);
+    let (root_d1_read, e2_write) = tokio::io::duplex(8192);
+
+    // Start seed router handler on root
+    tokio::spawn({
+        let root = root_stack.clone();
+        async move {
+            root.services().seed_router_request_handler::<4>().await;
+        }
+    });
+
+    // Register root downstream[0] → bridge upstream (net_id=1)
+    tokio_cobs_stream::register_router(
+        root_stack.clone(),
+        root_d0_read,
+        root_d0_write,
+        512,
+        4096,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    // Register root downstream[1] → edge2 (net_id=2)
+    tokio_cobs_stream::register_router(
+        root_stack.clone(),
+        root_d1_read,
+        root_d1_write,
+        512,
+        4096,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    // Register bridge upstream
+    tokio_cobs_stream::register_bridge_upstream(
+        bridge_stack.clone(),
+        bridge_up_read,
+        bridge_up_write,
+        bridge_up_queue,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    // Register bridge downstream[0] → edge1 as pending (no auto-assigned
+    // net_id) to avoid collision with upstream's net_id; the seed assignment
+    // below gives it a real one.
+    let bridge_d0_ident = tokio_cobs_stream::register_bridge_downstream(
+        bridge_stack.clone(),
+        bridge_d0_read,
+        bridge_d0_write,
+        512,
+        4096,
+        None,
+        None,
+    )
+    .await
+    .expect("bridge downstream registration");
+
+    // Register edge1 as target of bridge
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        edge1_stack.clone(),
+        e1_read,
+        e1_write,
+        edge1_queue,
+        EdgeFrameProcessor::new(),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    // Register edge2 as target of root
+    tokio_cobs_stream::register_edge::<_, TokioStreamInterface, _, _>(
+        edge2_stack.clone(),
+        e2_read,
+        e2_write,
+        edge2_queue,
+        EdgeFrameProcessor::new(),
+        InterfaceState::Inactive,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    // Start ping servers
+    spawn_ping_server(&edge1_stack);
+    spawn_ping_server(&edge2_stack);
+
+    // Bootstrap: root pings edge2 to activate it
+    let edge2_addr = Address {
+        network_id: 2,
+        node_id: 2,
+        port_id: 0,
+    };
+    ping_with_retry(&root_stack, edge2_addr, 0).await;
+    wait_active(&edge2_stack).await;
+
+    // Bootstrap bridge upstream: root pings the bridge address (net_id=1, node=2)
+    let bridge_addr_from_root = Address {
+        network_id: 1,
+        node_id: 2,
+        port_id: 0,
+    };
+    let _ = timeout(
+        Duration::from_millis(500),
+        root_stack.endpoints().request::<ErgotPingEndpoint>(
+            bridge_addr_from_root,
+            &0u32,
+            Some("ping"),
+        ),
+    )
+    .await;
+
+    // Wait for bridge upstream to become Active
+    let _bridge_upstream_net = wait_interface_active(&bridge_stack, UPSTREAM_IDENT).await;
+
+    // === KEY PART: Bridge requests seed net_id from root ===
+    let lease = bridge_seed_assign(&bridge_stack, UPSTREAM_IDENT, bridge_d0_ident)
+        .await
+        .expect("seed assignment should succeed");
+
+    assert_eq!(lease.net_id, 3, "seed net_id should be 3");
+
+    // Verify bridge's downstream was reassigned
+    let new_net = bridge_stack.manage_profile(|im| im.interface_state(bridge_d0_ident));
+    assert!(
+        matches!(new_net, Some(InterfaceState::Active { net_id: 3, .. })),
+        "bridge downstream should be net_id=3, got {:?}",
+        new_net
+    );
+
+    // Bootstrap edge1: now that bridge downstream has net_id=3, ping edge1 to activate it
+    let edge1_global_addr = Address {
+        network_id: 3,
+        node_id: 2,
+        port_id: 0,
+    };
+    ping_with_retry(&root_stack, edge1_global_addr, 0).await;
+    wait_active(&edge1_stack).await;
+
+    // Each router answers from its address on the interface the probe came
+    // from: the root on edge2's net, the bridge on its upstream net
+    let hops = edge2_stack
+        .discovery()
+        .traceroute(edge1_global_addr, Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert_eq!(hops.len(), 3, "hops: {hops:?}");
+    assert_eq!(
+        hops[0],
+        TracerouteHop::Router(Address {
+            network_id: 2,
+            node_id: 1,
+            port_id: 0,
+        })
+    );
+    assert_eq!(
+        hops[1],
+        TracerouteHop::Router(Address {
+            network_id: 1,
+            node_id: 2,
+            port_id: 0,
+        })
+    );
+    let TracerouteHop::Destination(dst) = hops[2] else {
+        panic!("expected the destination, got {:?}", hops[2]);
+    };
+    assert_eq!((dst.network_id, dst.node_id), (3, 2));
+
+    // The root is one hop closer
+    let hops = root_stack
+        .discovery()
+        .traceroute(edge1_global_addr, Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert_eq!(hops.len(), 2, "hops: {hops:?}");
+    assert_eq!(
+        hops[0],
+        TracerouteHop::Router(Address {
+            network_id: 1,
+            node_id: 2,
+            port_id: 0,
+        })
+    );
+    assert!(matches!(hops[1], TracerouteHop::Destination(_)));
+
+    // A device with no ping server doesn't answer, and every hop after the
+    // last router times out
+    let hops = edge2_stack
+        .discovery()
+        .traceroute(
+            Address {
+                network_id: 9,
+                node_id: 2,
+                port_id: 0,
+            },
+            Duration::from_millis(100),
+        )
+        .await
+        .unwrap();
+    assert_eq!(hops.len(), usize::from(ergot::DEFAULT_TTL));
+    assert!(matches!(hops[0], TracerouteHop::Router(_)));
+    assert!(hops[1..].iter().all(|hop| *hop == TracerouteHop::Timeout));
+}
@@ -3,7 +3,10 @@ use ergot::{
     toolkits::tokio_tcp::{EdgeStack, new_std_queue, new_target_stack, register_edge_interface},
     topic,
     traits::Endpoint,
-    well_known::{DeviceInfo, ErgotSeedRouterAssignmentEndpoint, NameRequirement, SocketQuery},
+    well_known::{
+        DeviceInfo, ErgotSeedRouterAssignmentEndpoint, NameRequirement,
+        SeedRouterAssignmentRequest, SocketQuery,
+    },
 };
 use log::info;
 use tokio::{net::TcpStream, select, time::sleep};
@@ -51,7 +54,11 @@ async fn main() -> io::Result<()> {
         log::warn!("DISCO SAID: {:?}", res);
         let resp = stack
             .endpoints()
-            .request::<ErgotSeedRouterAssignmentEndpoint>(res[0].address, &(), None)
+            .request::<ErgotSeedRouterAssignmentEndpoint>(
+                res[0].address,
+                &SeedRouterAssignmentRequest::default(),
+                None,
+            )
             .await;
         log::warn!("GOT: {:?}", resp);
         break;
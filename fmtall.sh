@@ -5,6 +5,7 @@ set -euxo pipefail
 # Format crates
 cargo fmt --manifest-path=./crates/ergot/Cargo.toml
 cargo fmt --manifest-path=./crates/cobs-acc/Cargo.toml
+cargo fmt --manifest-path=./crates/ergot-cli/Cargo.toml
 
 # Format all the demo workspaces
 cargo fmt --manifest-path=./demos/shared-icd/Cargo.toml